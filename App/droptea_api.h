@@ -5,14 +5,87 @@
 typedef void* DropTeaHandle;
 
 // Callback type ต้องตรงกับ Rust: (type, task_id, data1, data2, val1, val2)
-typedef void (*RustCallback)(int, const char*, const char*, const char*, uint64_t, uint64_t);
+// ทุกฟังก์ชันที่ล้มได้คืน DropTeaErrorCode (0 = สำเร็จ) ข้อความดูจาก droptea_last_error_message
+// ฟังก์ชันที่คืน Pointer (droptea_init / droptea_get_peers) คืน NULL แทน
+// ค่าเดิมห้ามเปลี่ยน เพิ่มต่อท้ายได้อย่างเดียว (1..9 ตรงกับ DropTeaError::code() ฝั่ง Rust)
+typedef enum DropTeaErrorCode {
+    DROPTEA_OK                   = 0,
+    DROPTEA_ERR_NETWORK          = 1,
+    DROPTEA_ERR_TLS              = 2,
+    DROPTEA_ERR_TIMEOUT          = 3,
+    DROPTEA_ERR_REJECTED         = 4,
+    DROPTEA_ERR_STORAGE          = 5,
+    DROPTEA_ERR_PROTOCOL         = 6,
+    DROPTEA_ERR_CANCELLED        = 7,
+    DROPTEA_ERR_CONFIG           = 8,
+    DROPTEA_ERR_INTERNAL         = 9,
+    DROPTEA_ERR_INVALID_ARGUMENT = 10, // Pointer เป็น NULL / ข้อความว่าง
+    DROPTEA_ERR_BIND             = 11, // droptea_init เปิด Port ไม่ได้
+    DROPTEA_ERR_NOT_FOUND        = 12, // ไม่มี Transfer / คำขอตาม task_id
+} DropTeaErrorCode;
+
+// type ตัวแรกของ RustCallback: (task_id, data1, data2, val1, val2)
+#define DROPTEA_EVT_LOG             0   // -, msg, level
+#define DROPTEA_EVT_PEER_FOUND      1   // peer_id, name (ชื่อเล่นถ้าตั้งไว้), "ip|transport", port
+#define DROPTEA_EVT_PEER_LOST       2   // peer_id
+#define DROPTEA_EVT_PROGRESS        3   // task_id, -, -, current, total
+#define DROPTEA_EVT_COMPLETED       4   // task_id, info, metadata (JSON, "" = ไม่มี)
+#define DROPTEA_EVT_ERROR           5   // task_id, error
+#define DROPTEA_EVT_INCOMING        6   // task_id, "[[REQUEST]]|filename|size|sender|device", metadata (JSON, "" = ไม่มี)
+#define DROPTEA_EVT_REJECTED        7   // task_id, reason
+#define DROPTEA_EVT_STARTED         8   // task_id, msg
+#define DROPTEA_EVT_RETRYING        9   // task_id, error, -, attempt, delay_ms
+#define DROPTEA_EVT_SERVER_STARTED  10  // -, port (ข้อความ), -, port
+#define DROPTEA_EVT_STALLED         11  // task_id, -, -, stalled_ms
+#define DROPTEA_EVT_BATCH_PROGRESS  12  // batch_id, current, "files_done|files_total", bytes_done, bytes_total
+#define DROPTEA_EVT_PEER_UPDATED    13  // peer_id, ip, old_ip, port
+#define DROPTEA_EVT_DUPLICATE       14  // task_id, filename, existing (ว่าง = ฝั่งส่ง)
+#define DROPTEA_EVT_HOOK_FAILED     15  // task_id, path, error (หลัง COMPLETED ไฟล์ยังอยู่)
+#define DROPTEA_EVT_SHARE_BROWSED   16  // peer (Fingerprint / IP), share (ว่าง = List), path, allowed (0 = ถูกปฏิเสธ)
+
+// user_data = ค่าที่ให้ไว้ตอน droptea_init (ส่งคืนทุกครั้ง ไม่ต้องใช้ตัวแปร Global)
+typedef void (*RustCallback)(int, const char*, const char*, const char*, uint64_t, uint64_t, void* user_data);
+// Signature เดิมสำหรับ droptea_init_legacy
+typedef void (*RustCallbackLegacy)(int, const char*, const char*, const char*, uint64_t, uint64_t);
+// Trace เป็น JSON ต่อ Event (ถูกเรียกจากหลาย Thread ได้)
+typedef void (*TraceCallback)(const char*);
 
 extern "C" {
-    // เพิ่ม parameter port (uint16_t)
-    DropTeaHandle droptea_init(const char* storage_path, uint16_t port, int mode, RustCallback callback);
+    // mode: 0 = TCP, 1 = QUIC (Port กำหนดตอน droptea_start_service)
+    DropTeaHandle droptea_init(const char* storage_path, int mode, RustCallback callback, void* user_data);
+    DropTeaHandle droptea_init_legacy(const char* storage_path, int mode, RustCallbackLegacy callback);
+    // ใช้ config.toml เดียวกับ Python (node_name, save_path, mode, port, dev_mode, [peers.*] ...) NULL = ใช้ DROPTEA_* อย่างเดียว
+    // แก้ไฟล์ระหว่างรันได้ (CONFIG_RELOADED) / Port และ dev_mode ในไฟล์ชนะ Argument ของ droptea_start_service
+    DropTeaHandle droptea_init_with_config(const char* config_path, RustCallback callback, void* user_data);
+    // ctx = NULL: Error ของ droptea_init / ctx ที่เป็น NULL / Trace Callback, คืน NULL = ไม่มี Error (ต้องคืนด้วย droptea_free_string)
+    char* droptea_last_error_message(DropTeaHandle ctx);
     
-    void droptea_start_service(DropTeaHandle ctx, uint16_t port, const char* device_id, bool dev_mode);
-    void droptea_resolve_request(DropTeaHandle ctx, const char* task_id, bool accept);
+    // device_id ไม่ถูกใช้แล้ว (ตั้งชื่อเครื่องด้วย node_name ของ droptea_init_with_config)
+    DropTeaErrorCode droptea_start_service(DropTeaHandle ctx, uint16_t port, const char* device_id, bool dev_mode);
+    DropTeaErrorCode droptea_resolve_request(DropTeaHandle ctx, const char* task_id, bool accept);
+    // ip = IP หรือ peer_id จาก DROPTEA_EVT_PEER_FOUND, target_os = nullptr (ใช้ OS ที่ Peer ประกาศ) หรือ Override เช่น "ios"
+    // DROPTEA_OK = เข้าคิวแล้ว (ผลมาทาง Callback ด้วย task_id นี้)
+    DropTeaErrorCode droptea_send_file(DropTeaHandle ctx, const char* ip, uint16_t port, const char* path, const char* task_id, const char* target_os);
+    // metadata_json = {"key": "value", ...} ของแอป (nullptr = ไม่มี) ผู้รับได้ใน data2 ของ INCOMING / COMPLETED
+    DropTeaErrorCode droptea_send_file_with_metadata(DropTeaHandle ctx, const char* ip, uint16_t port, const char* path, const char* task_id, const char* target_os, const char* metadata_json);
+    // DROPTEA_ERR_NOT_FOUND = ไม่มี Transfer นี้ (จบไปแล้ว)
+    DropTeaErrorCode droptea_cancel_transfer(DropTeaHandle ctx, const char* task_id);
+    DropTeaErrorCode droptea_cancel(DropTeaHandle ctx, const char* task_id);
+    // paused = false คือทำต่อ
+    DropTeaErrorCode droptea_pause_transfer(DropTeaHandle ctx, const char* task_id, bool paused);
+    DropTeaErrorCode droptea_stop_service(DropTeaHandle ctx);
+    // JSON Array: [{key, id, online, name, ip, nickname, notes, first_seen, last_seen, profile}] ต้องคืนด้วย droptea_free_string
+    char* droptea_get_peers(DropTeaHandle ctx);
+    // JSON Array: [{path, filename, received, filesize, modified_ms, resumable}] ของ .part ที่ค้างอยู่ ต้องคืนด้วย droptea_free_string
+    char* droptea_list_incomplete(DropTeaHandle ctx);
+    void droptea_free_string(char* s);
+    // ประกาศตัวทันทีและ Ping Peer บน LAN (ปุ่ม Refresh)
+    DropTeaErrorCode droptea_refresh_discovery(DropTeaHandle ctx);
+    // true = UI เปิดอยู่ (ประกาศตัวถี่), false = Idle (ถอยห่างการประกาศ)
+    DropTeaErrorCode droptea_set_activity_state(DropTeaHandle ctx, bool active);
+    DropTeaErrorCode droptea_export_diagnostics(DropTeaHandle ctx, const char* path, const char* log_dir);
+    // level: 0 = error ... 4 = trace (ตั้งได้ครั้งเดียวต่อโปรเซส)
+    DropTeaErrorCode droptea_set_trace_callback(TraceCallback callback, int level);
     void droptea_free(DropTeaHandle ctx);
     
     // ฟังก์ชันจาก bridge.cpp สำหรับสร้าง Shortcut
@@ -82,7 +82,7 @@ public:
 };
 
 // ================= Rust Callback =================
-void on_rust_event(int type, const char* task_id, const char* d1, const char* d2, uint64_t v1, uint64_t v2) {
+void on_rust_event(int type, const char* task_id, const char* d1, const char* d2, uint64_t v1, uint64_t v2, void* user_data) {
     std::string id = task_id ? task_id : "";
     std::string data1 = d1 ? d1 : "";
     std::string data2 = d2 ? d2 : "";
@@ -201,8 +201,8 @@ int main(int argc, char* argv[]) {
     std::cout << " Mode        : " << modeStr << std::endl;
     std::cout << "---------------------------------------" << std::endl;
 
-    // Init Rust (ส่ง Port, Mode และ Path เข้าไป)
-    global_core = droptea_init(download_path.c_str(), (uint16_t)port, mode, on_rust_event);
+    // Init Rust (ส่ง Mode และ Path เข้าไป, Port ส่งตอน Start Service)
+    global_core = droptea_init(download_path.c_str(), mode, on_rust_event, nullptr);
 
     if (global_core) {
         // Start Service
@@ -217,7 +217,9 @@ int main(int argc, char* argv[]) {
         }
         droptea_free(global_core);
     } else {
-        std::cerr << "Failed to init Rust core" << std::endl;
+        char* err = droptea_last_error_message(nullptr);
+        std::cerr << "Failed to init Rust core: " << (err ? err : "unknown error") << std::endl;
+        droptea_free_string(err);
         return 1;
     }
 
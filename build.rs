@@ -0,0 +1,5 @@
+fn main() {
+    // napi-rs: macOS ต้องให้ Symbol ของ Node หาตอนโหลด Module (.node)
+    #[cfg(feature = "node")]
+    napi_build::setup();
+}
@@ -0,0 +1,7 @@
+// Integration Harness (ดู core/harness.rs) ต้อง Build ด้วย --features harness
+fn main() {
+    if let Err(e) = droptea_core::core::harness::harness_main() {
+        eprintln!("droptea-harness: {:#}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,7 @@
+// Helper Process ของโหมด sandbox_helper (ดู core/sandbox.rs) Engine เป็นคนเรียก ไม่ได้ใช้ตรงๆ
+fn main() {
+    if let Err(e) = droptea_core::core::sandbox::helper_main() {
+        eprintln!("droptea-sandbox: {:#}", e);
+        std::process::exit(1);
+    }
+}
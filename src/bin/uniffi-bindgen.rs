@@ -0,0 +1,4 @@
+// สร้างไฟล์ Swift / Kotlin จาก Library ที่ Build ด้วย --features mobile (ดู Cargo.toml)
+fn main() {
+    uniffi::uniffi_bindgen_main()
+}
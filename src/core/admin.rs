@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::Semaphore;
+use tokio::time::timeout;
+use anyhow::{bail, Context};
+
+use crate::core::error::DropTeaError;
+use crate::core::transfer::{DataStream, DynTransport, IO_TIMEOUT, MAX_HEADER_SIZE};
+use crate::core::utils;
+use crate::core::reload::Live;
+
+// ==========================================
+// Remote Administration (Headless Receiver)
+// ใช้ Framing เดียวกับ FileHeader: [u32 len][JSON] แต่ JSON มี key "control"
+// อนุญาตเฉพาะ Peer ที่ Fingerprint อยู่ใน [admin] fingerprints
+// ==========================================
+
+// .part ที่ไม่ถูกแตะนานเกินนี้ถือว่าค้าง (Transfer ที่ยังวิ่งอยู่จะเขียนตลอด)
+const STALE_PART_AGE: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum AdminCommand {
+    Status,
+    SetLimits { max_incoming: Option<usize>, max_outgoing: Option<usize> },
+    Cleanup,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ControlRequest {
+    pub control: AdminCommand,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AdminResponse {
+    pub ok: bool,
+    #[serde(default)]
+    pub data: serde_json::Value,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl AdminResponse {
+    fn ok(data: serde_json::Value) -> Self { Self { ok: true, data, error: None } }
+    fn err(msg: &str) -> Self { Self { ok: false, data: serde_json::Value::Null, error: Some(msg.to_string()) } }
+}
+
+// Semaphore + ขนาดปัจจุบัน (tokio Semaphore ไม่บอกขนาดรวม)
+#[derive(Debug)]
+pub struct Limit {
+    pub semaphore: Arc<Semaphore>,
+    size: AtomicUsize,
+}
+
+impl Limit {
+    pub fn new(semaphore: Arc<Semaphore>, size: usize) -> Self {
+        Self { semaphore, size: AtomicUsize::new(size) }
+    }
+
+    pub fn size(&self) -> usize { self.size.load(Ordering::SeqCst) }
+
+    pub fn in_use(&self) -> usize { self.size().saturating_sub(self.semaphore.available_permits()) }
+
+    // ลดขนาดได้เฉพาะ Permit ที่ว่างอยู่ ส่วนที่กำลังใช้จะถูกคืนตามปกติ
+    pub(crate) fn resize(&self, new_size: usize) -> usize {
+        let old = self.size.load(Ordering::SeqCst);
+        let applied = if new_size > old {
+            self.semaphore.add_permits(new_size - old);
+            new_size
+        } else {
+            old - self.semaphore.forget_permits(old - new_size)
+        };
+        self.size.store(applied, Ordering::SeqCst);
+        applied
+    }
+}
+
+#[derive(Debug)]
+pub struct AdminContext {
+    pub fingerprints: HashSet<String>,
+    pub node_name: String,
+    // storage.download_dir (Reload ได้)
+    pub save_path: Arc<Live<String>>,
+    // โฟลเดอร์ .part (storage.temp_path) None = อยู่ข้างไฟล์ปลายทาง
+    pub temp_dir: Option<String>,
+    pub incoming: Arc<Limit>,
+    pub outgoing: Arc<Limit>,
+}
+
+impl AdminContext {
+    fn execute(&self, cmd: AdminCommand) -> AdminResponse {
+        match cmd {
+            AdminCommand::Status => AdminResponse::ok(serde_json::json!({
+                "node_name": self.node_name,
+                "version": env!("CARGO_PKG_VERSION"),
+                "incoming": { "active": self.incoming.in_use(), "max": self.incoming.size() },
+                "outgoing": { "active": self.outgoing.in_use(), "max": self.outgoing.size() },
+                "free_space": fs2::available_space(self.save_path.get()).ok(),
+            })),
+            AdminCommand::SetLimits { max_incoming, max_outgoing } => {
+                let incoming = max_incoming.map(|n| self.incoming.resize(n)).unwrap_or_else(|| self.incoming.size());
+                let outgoing = max_outgoing.map(|n| self.outgoing.resize(n)).unwrap_or_else(|| self.outgoing.size());
+                info!("🛠️ Admin set limits: incoming={} outgoing={}", incoming, outgoing);
+                AdminResponse::ok(serde_json::json!({ "max_incoming": incoming, "max_outgoing": outgoing }))
+            }
+            AdminCommand::Cleanup => match std::iter::once(self.save_path.get()).chain(self.temp_dir.clone())
+                .map(|dir| utils::cleanup_stale_parts(&dir, STALE_PART_AGE)).sum::<crate::core::error::Result<usize>>() {
+                Ok(removed) => {
+                    info!("🛠️ Admin cleanup removed {} stale .part files", removed);
+                    AdminResponse::ok(serde_json::json!({ "removed": removed }))
+                }
+                Err(e) => AdminResponse::err(&e.to_string()),
+            },
+        }
+    }
+}
+
+pub(crate) async fn write_frame<S: DataStream>(stream: &mut S, json: &[u8]) -> anyhow::Result<()> {
+    stream.write_all(&(json.len() as u32).to_le_bytes()).await?;
+    stream.write_all(json).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+pub(crate) async fn read_frame<S: DataStream>(stream: &mut S) -> anyhow::Result<Vec<u8>> {
+    let mut len_buf = [0u8; 4];
+    timeout(IO_TIMEOUT, stream.read_exact(&mut len_buf)).await.context("Response timeout")??;
+    let len = u32::from_le_bytes(len_buf) as usize;
+    if len > MAX_HEADER_SIZE { bail!(DropTeaError::Protocol("Response too large".into())); }
+    let mut buf = vec![0u8; len];
+    timeout(IO_TIMEOUT, stream.read_exact(&mut buf)).await.context("Response timeout")??;
+    Ok(buf)
+}
+
+/// ฝั่งรับ: ถูกเรียกจาก handle_incoming เมื่อ Header เป็น ControlRequest
+pub async fn handle_control<S: DataStream>(
+    mut stream: S,
+    request: ControlRequest,
+    peer_fingerprint: Option<&str>,
+    admin: Option<&AdminContext>,
+) -> anyhow::Result<()> {
+    let response = match (admin, peer_fingerprint) {
+        (Some(ctx), Some(fp)) if ctx.fingerprints.contains(fp) => ctx.execute(request.control),
+        _ => {
+            warn!("Rejected admin command from unauthorized peer (fingerprint: {:?})", peer_fingerprint);
+            AdminResponse::err("Unauthorized")
+        }
+    };
+    let json = serde_json::to_vec(&response)?;
+    timeout(IO_TIMEOUT, write_frame(&mut stream, &json)).await.context("Admin response timeout")??;
+    let _ = stream.shutdown().await;
+    Ok(())
+}
+
+/// ฝั่ง Admin: ส่งคำสั่งไปยังเครื่องปลายทางผ่าน Transport ที่เข้ารหัสอยู่แล้ว
+pub async fn send_command(transport: &DynTransport, ip: &str, port: u16, cmd: AdminCommand) -> anyhow::Result<AdminResponse> {
+    let mut stream = transport.connect(ip, port).await?;
+    let json = serde_json::to_vec(&ControlRequest { control: cmd })?;
+    write_frame(&mut stream, &json).await?;
+    let buf = read_frame(&mut stream).await.context("Admin command failed")?;
+    serde_json::from_slice(&buf).context("Invalid admin response")
+}
@@ -0,0 +1,129 @@
+use std::collections::HashSet;
+use std::fs::{self as std_fs, File as StdFile, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::Context;
+use log::info;
+use zip::write::FileOptions;
+
+// ==========================================
+// Archive-on-receive (สำหรับเครื่องรับแบบ Drop-box / Collection Station)
+// ไฟล์ที่รับจากผู้ส่งคนเดียวกันในวันเดียวกัน = 1 Session = 1 Archive
+// ==========================================
+
+const ZSTD_LEVEL: i32 = 3;
+
+// Append ทีละ Archive (หลาย Transfer จบพร้อมกันได้)
+static ARCHIVE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveMode {
+    Off,
+    Zip,
+    TarZst,
+}
+
+impl ArchiveMode {
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "off" | "none" | "" => Some(ArchiveMode::Off),
+            "zip" => Some(ArchiveMode::Zip),
+            "tar.zst" | "tarzst" | "tar_zst" => Some(ArchiveMode::TarZst),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ArchiveMode::Off => "",
+            ArchiveMode::Zip => "zip",
+            ArchiveMode::TarZst => "tar.zst",
+        }
+    }
+}
+
+/// ย้ายไฟล์ที่รับเสร็จแล้วเข้า Session Archive แล้วคืน Path ของ Archive
+pub async fn archive_received(save_path: &str, sender_name: &str, file: &Path, mode: ArchiveMode) -> anyhow::Result<PathBuf> {
+    let archive = session_archive_path(save_path, sender_name, mode);
+    let file = file.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let _lock = ARCHIVE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let entry_name = file.file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_else(|| "unknown_file".to_string());
+        match mode {
+            ArchiveMode::Zip => append_zip(&archive, &file, &entry_name)?,
+            ArchiveMode::TarZst => append_tar_zst(&archive, &file, &entry_name)?,
+            ArchiveMode::Off => return Ok(file),
+        }
+        std_fs::remove_file(&file).context("Failed to remove archived file")?;
+        info!("🗜️ Archived '{}' into {:?}", entry_name, archive);
+        Ok(archive)
+    }).await?
+}
+
+fn session_archive_path(save_path: &str, sender_name: &str, mode: ArchiveMode) -> PathBuf {
+    let safe_sender: String = sender_name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    Path::new(save_path).join(format!("{}_{}.{}", safe_sender, today_stamp(), mode.extension()))
+}
+
+// YYYYMMDD (UTC) โดยไม่ต้องพึ่ง chrono
+fn today_stamp() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64 / 86_400;
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}{:02}{:02}", year, month, day)
+}
+
+fn append_zip(archive: &Path, file: &Path, entry_name: &str) -> anyhow::Result<()> {
+    let (mut z, existing) = if archive.exists() {
+        let f = OpenOptions::new().read(true).write(true).open(archive)?;
+        let names: HashSet<String> = zip::ZipArchive::new(f.try_clone()?)?.file_names().map(|n| n.to_string()).collect();
+        (zip::ZipWriter::new_append(f)?, names)
+    } else {
+        (zip::ZipWriter::new(StdFile::create(archive)?), HashSet::new())
+    };
+
+    // ชื่อซ้ำใน Session เดียวกัน -> เติม _1, _2, ...
+    let mut name = entry_name.to_string();
+    let mut n = 1;
+    while existing.contains(&name) {
+        let p = Path::new(entry_name);
+        let stem = p.file_stem().and_then(|s| s.to_str()).unwrap_or(entry_name);
+        let ext = p.extension().and_then(|s| s.to_str()).map(|e| format!(".{}", e)).unwrap_or_default();
+        name = format!("{}_{}{}", stem, n, ext);
+        n += 1;
+    }
+
+    let options = FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .large_file(std_fs::metadata(file)?.len() >= u32::MAX as u64);
+    z.start_file(name, options)?;
+    io::copy(&mut StdFile::open(file)?, &mut z)?;
+    z.finish()?;
+    Ok(())
+}
+
+// tar.zst แบบ Append ได้: แต่ละไฟล์เป็น zstd frame แยกที่มี tar entry ไม่มี end-of-archive block
+// (zstd decoder อ่าน frame ต่อกันเป็น stream เดียว) ชื่อซ้ำจะถูกเก็บไว้ทั้งหมดตามลำดับ
+fn append_tar_zst(archive: &Path, file: &Path, entry_name: &str) -> anyhow::Result<()> {
+    let out = OpenOptions::new().create(true).append(true).open(archive)?;
+    let mut enc = zstd::Encoder::new(out, ZSTD_LEVEL)?;
+    {
+        let mut builder = tar::Builder::new(&mut enc);
+        builder.append_file(entry_name, &mut StdFile::open(file)?)?;
+        // Builder เขียน end-of-archive ตอน finish/drop -> ข้ามไป เพื่อให้ต่อ frame ถัดไปได้
+        std::mem::forget(builder);
+    }
+    enc.finish()?.sync_all()?;
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use serde::{Serialize, Deserialize};
+
+use crate::core::discovery::PeerInfo;
+use crate::core::stats::TransferStats;
+use crate::core::thumbnail::Thumbnail;
+use crate::core::transfer::{CertificateAction, Metadata, Throughput, TransferCallback, NOTIFY_INTERVAL_MS};
+
+// ==========================================
+// Batch Progress (โฟลเดอร์ / หลายไฟล์ที่ส่งเป็นชุดเดียว)
+// ผู้ส่งแนบ BatchInfo (id, จำนวนไฟล์, ขนาดรวม) ใน FileHeader ทุกไฟล์ของชุด
+// ทั้งสองฝั่งรวม Progress รายไฟล์เป็น BatchProgress เดียว -> UI แสดงแถบเดียวแทน 5,000 แถบ
+// Progress รายไฟล์ยังส่งตามปกติ (BatchCallback แค่ดักไว้นับเพิ่ม)
+// ==========================================
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BatchInfo {
+    pub id: String,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchProgress {
+    pub batch_id: String,
+    pub files_done: u64,
+    pub files_total: u64,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    // ไฟล์ล่าสุดที่มีความคืบหน้า
+    pub current: String,
+}
+
+#[derive(Debug)]
+struct ActiveFile {
+    filename: String,
+    current: u64,
+    total: u64,
+}
+
+#[derive(Debug)]
+struct BatchState {
+    info: BatchInfo,
+    files_done: u64,
+    // สำเร็จ + ล้ม/ถูกปฏิเสธ (ครบ files แล้วลบชุดทิ้ง)
+    finished: u64,
+    bytes_done: u64,
+    active: HashMap<String, ActiveFile>,
+    current: String,
+    last_emit: Option<Instant>,
+}
+
+impl BatchState {
+    fn snapshot(&self) -> BatchProgress {
+        let in_flight: u64 = self.active.values().map(|f| f.current).sum();
+        BatchProgress {
+            batch_id: self.info.id.clone(),
+            files_done: self.files_done,
+            files_total: self.info.files,
+            bytes_done: (self.bytes_done + in_flight).min(self.info.bytes.max(self.bytes_done)),
+            bytes_total: self.info.bytes,
+            current: self.current.clone(),
+        }
+    }
+}
+
+/// ชุดที่กำลังส่ง/รับอยู่ (key ฝั่งรับรวม Peer ไว้ด้วย id ของคนละผู้ส่งชนกันได้)
+#[derive(Debug, Default)]
+pub struct BatchTracker {
+    batches: StdMutex<HashMap<String, BatchState>>,
+}
+
+enum Update { Start(String), Progress(u64, u64), Complete, Leave }
+
+impl BatchTracker {
+    pub fn new() -> Arc<Self> { Arc::new(Self::default()) }
+
+    // None = ไม่ต้องแจ้ง UI (ถี่เกิน / ไม่มีชุดนี้แล้ว)
+    fn update(&self, key: &str, task_id: &str, update: Update) -> Option<BatchProgress> {
+        let mut batches = self.batches.lock().unwrap();
+        let state = batches.get_mut(key)?;
+        match update {
+            Update::Start(filename) => {
+                state.current = filename.clone();
+                state.active.entry(task_id.to_string()).or_insert(ActiveFile { filename, current: 0, total: 0 });
+            }
+            Update::Progress(current, total) => {
+                let file = state.active.entry(task_id.to_string()).or_insert(ActiveFile { filename: String::new(), current: 0, total: 0 });
+                file.current = current;
+                file.total = total;
+                if !file.filename.is_empty() { state.current = file.filename.clone(); }
+                let throttled = state.last_emit.is_some_and(|t| t.elapsed() < Duration::from_millis(NOTIFY_INTERVAL_MS as u64));
+                if throttled { return None; }
+            }
+            Update::Complete => {
+                let file = state.active.remove(task_id)?;
+                state.files_done += 1;
+                state.finished += 1;
+                state.bytes_done += file.total.max(file.current);
+            }
+            Update::Leave => {
+                // จบโดยไม่ Complete (Reject / Error / ยกเลิก) นับว่าจบแต่ไม่นับ Byte
+                state.active.remove(task_id)?;
+                state.finished += 1;
+            }
+        }
+        state.last_emit = Some(Instant::now());
+        let progress = state.snapshot();
+        if state.finished >= state.info.files { batches.remove(key); }
+        Some(progress)
+    }
+
+    fn register(&self, key: &str, info: &BatchInfo, task_id: &str) {
+        let mut batches = self.batches.lock().unwrap();
+        let state = batches.entry(key.to_string()).or_insert_with(|| BatchState {
+            info: info.clone(),
+            files_done: 0,
+            finished: 0,
+            bytes_done: 0,
+            active: HashMap::new(),
+            current: String::new(),
+            last_emit: None,
+        });
+        state.active.entry(task_id.to_string()).or_insert(ActiveFile { filename: String::new(), current: 0, total: 0 });
+    }
+}
+
+// ไฟล์หนึ่งของชุด Drop (Callback ตัวสุดท้ายหายไป) แล้วนับว่าจบ ถ้ายังไม่ได้ Complete
+struct Member<CB: TransferCallback> {
+    tracker: Arc<BatchTracker>,
+    key: String,
+    task_id: String,
+    inner: CB,
+}
+
+impl<CB: TransferCallback> Drop for Member<CB> {
+    fn drop(&mut self) {
+        if let Some(progress) = self.tracker.update(&self.key, &self.task_id, Update::Leave) {
+            self.inner.on_batch_progress(&progress);
+        }
+    }
+}
+
+/// ห่อ Callback ของไฟล์ในชุด: ส่งต่อทุก Event แล้วแจ้ง BatchProgress เพิ่ม (batch = None คือส่งต่ออย่างเดียว)
+#[derive(Clone)]
+pub struct BatchCallback<CB: TransferCallback + Clone> {
+    inner: CB,
+    member: Option<Arc<Member<CB>>>,
+}
+
+impl<CB: TransferCallback + Clone> BatchCallback<CB> {
+    pub fn new(inner: CB, tracker: &Arc<BatchTracker>, key: String, batch: Option<&BatchInfo>, task_id: &str) -> Self {
+        let member = batch.filter(|b| b.files > 0).map(|info| {
+            tracker.register(&key, info, task_id);
+            Arc::new(Member { tracker: tracker.clone(), key, task_id: task_id.to_string(), inner: inner.clone() })
+        });
+        Self { inner, member }
+    }
+
+    fn track(&self, update: Update) {
+        let Some(m) = &self.member else { return; };
+        if let Some(progress) = m.tracker.update(&m.key, &m.task_id, update) {
+            self.inner.on_batch_progress(&progress);
+        }
+    }
+}
+
+impl<CB: TransferCallback + Clone> TransferCallback for BatchCallback<CB> {
+    fn on_start(&self, task_id: &str, filename: &str) {
+        self.inner.on_start(task_id, filename);
+        self.track(Update::Start(filename.to_string()));
+    }
+    fn on_progress(&self, task_id: &str, current: u64, total: u64) {
+        self.inner.on_progress(task_id, current, total);
+        self.track(Update::Progress(current, total));
+    }
+    fn on_progress_ex(&self, task_id: &str, current: u64, total: u64, rate: &Throughput) {
+        self.inner.on_progress_ex(task_id, current, total, rate);
+        self.track(Update::Progress(current, total));
+    }
+    fn on_complete(&self, task_id: &str, info: &str) {
+        self.inner.on_complete(task_id, info);
+        self.track(Update::Complete);
+    }
+    fn on_complete_with_stats(&self, task_id: &str, info: &str, stats: &TransferStats, metadata: &Metadata) {
+        self.inner.on_complete_with_stats(task_id, info, stats, metadata);
+        self.track(Update::Complete);
+    }
+    fn on_error(&self, task_id: &str, error: &str) { self.inner.on_error(task_id, error); }
+    fn on_reject(&self, task_id: &str, reason: &str) { self.inner.on_reject(task_id, reason); }
+    fn on_peer_found(&self, id: &str, name: &str, ip: &str, port: u16, ssid: Option<&str>, transport: &str) {
+        self.inner.on_peer_found(id, name, ip, port, ssid, transport);
+    }
+    fn on_peer_found_ex(&self, peer: &PeerInfo) { self.inner.on_peer_found_ex(peer); }
+    fn on_peer_lost(&self, id: &str) { self.inner.on_peer_lost(id); }
+    fn on_peer_updated(&self, id: &str, old_ip: &str, ip: &str, port: u16) { self.inner.on_peer_updated(id, old_ip, ip, port); }
+    fn ask_accept_file(&self, task_id: &str, filename: &str, filesize: u64, sender_name: &str, sender_device: &str, session_id: Option<&str>, verdict: Option<&str>, thumbnail: Option<&Thumbnail>, metadata: &Metadata) -> anyhow::Result<bool> {
+        self.inner.ask_accept_file(task_id, filename, filesize, sender_name, sender_device, session_id, verdict, thumbnail, metadata)
+    }
+    fn ask_verify_certificate(&self, peer_id: &str, fingerprint: &str, filename: Option<&str>) -> anyhow::Result<CertificateAction> {
+        self.inner.ask_verify_certificate(peer_id, fingerprint, filename)
+    }
+    fn on_clock_skew(&self, task_id: &str, peer: &str, skew_ms: i64) { self.inner.on_clock_skew(task_id, peer, skew_ms); }
+    fn on_batch_progress(&self, progress: &BatchProgress) { self.inner.on_batch_progress(progress); }
+    fn on_stalled(&self, task_id: &str, stalled_ms: u64) { self.inner.on_stalled(task_id, stalled_ms); }
+    // มีอยู่แล้ว = ไฟล์นี้ของชุดเสร็จ (ไม่มี Byte วิ่ง)
+    fn on_duplicate(&self, task_id: &str, filename: &str, existing: Option<&str>) {
+        self.inner.on_duplicate(task_id, filename, existing);
+        self.track(Update::Complete);
+    }
+    fn on_hook_failed(&self, task_id: &str, path: &str, error: &str) { self.inner.on_hook_failed(task_id, path, error); }
+    fn on_share_browsed(&self, peer: &str, share: &str, path: &str, allowed: bool) { self.inner.on_share_browsed(peer, share, path, allowed); }
+}
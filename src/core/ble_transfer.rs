@@ -0,0 +1,143 @@
+use std::path::Path;
+use std::pin::Pin;
+use std::time::Duration;
+use anyhow::Context;
+use btleplug::api::{Peripheral, ValueNotification, WriteType};
+use futures::stream::{Stream, StreamExt};
+use log::{info, warn};
+use uuid::Uuid;
+
+use crate::core::cancel::{CancellationToken, REJECT_CANCELLED};
+use crate::core::handshake;
+use crate::core::transfer::{
+    incompatible_reason, FileHeader, TransferCallback, ACK_EXPIRED, ACK_INCOMPATIBLE, CAP_SHA256, MIN_PROTOCOL_VERSION,
+    PROTOCOL_VERSION, REJECT_EXPIRED,
+};
+use crate::core::utils;
+
+// ==========================================
+// BLE GATT Transfer (ไฟล์เล็ก เมื่อ Peer ไม่มีทาง IP)
+// Central (เรา) เขียน Frame ลง d7ed แล้วรอ ACK จาก Notify d7ee:
+//   [0x01][FileHeader JSON]          -> ACK ตัดสินรับ/ไม่รับ (seq = 0)
+//   [0x02][seq u32 LE][data ≤ 180B]  -> ACK ทุก BLE_ACK_WINDOW Chunk และ Chunk สุดท้าย (seq = Chunk นั้น)
+//   [0x03][blake3 32B]               -> ACK เมื่อตรวจ Hash ผ่าน
+// ACK = [status u8][seq u32 LE] status เหมือน ACK บน LAN (0 = Reject, 1 = OK, 2 = Expired, 3 = Incompatible: seq = Version ผู้รับ)
+// ตอนนี้ส่งออกได้อย่างเดียว (btleplug เป็น Central เท่านั้น ฝั่งรับคือแอปมือถือ)
+// ==========================================
+
+const FILE_CHAR_UUID: &str = "0000d7ed-0000-1000-8000-00805f9b34fb";
+const FILE_ACK_CHAR_UUID: &str = "0000d7ee-0000-1000-8000-00805f9b34fb";
+const FRAME_HEADER: u8 = 0x01;
+const FRAME_DATA: u8 = 0x02;
+const FRAME_END: u8 = 0x03;
+// MTU ที่ iOS เจรจาได้บ่อยสุดคือ 185 -> เหลือที่ให้ Data หลังหัก Frame Header
+const BLE_CHUNK_SIZE: usize = 180;
+const BLE_ACK_WINDOW: u32 = 16;
+const BLE_ACK_TIMEOUT: Duration = Duration::from_secs(15);
+pub const DEFAULT_BLE_MAX_FILE_SIZE: u64 = 64 * 1024;
+
+type Notifications = Pin<Box<dyn Stream<Item = ValueNotification> + Send>>;
+
+async fn next_ack(notifications: &mut Notifications, uuid: Uuid, limit: Duration) -> anyhow::Result<(u8, u32)> {
+    tokio::time::timeout(limit, async {
+        while let Some(n) = notifications.next().await {
+            if n.uuid != uuid || n.value.len() < 5 { continue; }
+            return Ok((n.value[0], u32::from_le_bytes([n.value[1], n.value[2], n.value[3], n.value[4]])));
+        }
+        anyhow::bail!("BLE link closed")
+    }).await.context("BLE ACK timeout")?
+}
+
+/// ส่งไฟล์เล็กผ่าน GATT ถึง `mac` (Caller เช็คขนาดกับ ble_max_file_size มาแล้ว)
+pub async fn send_file(mac: &str, path: &str, task_id: &str, sender_name: &str, callback: &impl TransferCallback, decision_timeout: Duration, cancel: &CancellationToken) -> anyhow::Result<()> {
+    let data = tokio::fs::read(path).await.context("Failed to read source file")?;
+    let metadata = tokio::fs::metadata(path).await?;
+    let header = FileHeader {
+        filename: Path::new(path).file_name().context("Invalid file path")?.to_string_lossy().to_string(),
+        filesize: data.len() as u64,
+        sender_name: sender_name.to_string(),
+        sender_device: std::env::consts::OS.to_string(),
+        compression: None,
+        sent_at: Some(utils::timestamp_millis()),
+        modified_at: metadata.modified().ok().and_then(utils::system_time_millis),
+        mode: utils::permission_bits(&metadata),
+        dedup: None,
+        expires_in_ms: None,
+        sha256: Some(utils::sha256_bytes(&data)),
+        quick_hash: None,
+        transfer_id: None,
+        control_port: None,
+        protocol_version: PROTOCOL_VERSION,
+        min_protocol_version: MIN_PROTOCOL_VERSION,
+        // ไม่บีบ / ไม่ Dedup / ไม่มี Control Channel บน GATT
+        capabilities: CAP_SHA256,
+        batch: None,
+        thumbnail: None,
+        guest: false,
+        parallel: None,
+        resuming: false,
+        metadata: Default::default(),
+    };
+
+    let device = handshake::find_and_connect(mac).await?;
+    let result = async {
+        device.discover_services().await?;
+        let chars = device.characteristics();
+        let file_uuid = Uuid::parse_str(FILE_CHAR_UUID)?;
+        let ack_uuid = Uuid::parse_str(FILE_ACK_CHAR_UUID)?;
+        let file_char = chars.iter().find(|c| c.uuid == file_uuid).context("Peer does not support BLE file transfer")?;
+        let ack_char = chars.iter().find(|c| c.uuid == ack_uuid).context("Peer does not support BLE file transfer")?;
+        device.subscribe(ack_char).await?;
+        let mut notifications = device.notifications().await?;
+
+        info!("🔵 Sending '{}' ({} bytes) over BLE to {}", header.filename, data.len(), mac);
+        let mut frame = vec![FRAME_HEADER];
+        frame.extend_from_slice(&serde_json::to_vec(&header)?);
+        device.write(file_char, &frame, WriteType::WithResponse).await?;
+        let decision = tokio::select! {
+            ack = next_ack(&mut notifications, ack_uuid, decision_timeout) => ack,
+            _ = cancel.cancelled() => { callback.on_reject(task_id, REJECT_CANCELLED); return Ok(()); }
+        };
+        match decision {
+            Ok((1, _)) => {}
+            Ok((ACK_EXPIRED, _)) => { callback.on_reject(task_id, REJECT_EXPIRED); return Ok(()); }
+            Ok((ACK_INCOMPATIBLE, version)) => { callback.on_reject(task_id, &incompatible_reason(version as u64, &header)); return Ok(()); }
+            Ok(_) => { callback.on_reject(task_id, "Receiver Rejected"); return Ok(()); }
+            Err(_) => { callback.on_reject(task_id, "Timeout"); return Ok(()); }
+        }
+        callback.on_start(task_id, &header.filename);
+
+        let total = data.len() as u64;
+        let last = data.len().div_ceil(BLE_CHUNK_SIZE).saturating_sub(1) as u32;
+        for (seq, chunk) in data.chunks(BLE_CHUNK_SIZE).enumerate() {
+            if cancel.is_cancelled() {
+                callback.on_reject(task_id, REJECT_CANCELLED);
+                return Ok(());
+            }
+            let seq = seq as u32;
+            let mut frame = Vec::with_capacity(5 + chunk.len());
+            frame.push(FRAME_DATA);
+            frame.extend_from_slice(&seq.to_le_bytes());
+            frame.extend_from_slice(chunk);
+            device.write(file_char, &frame, WriteType::WithResponse).await?;
+            if (seq + 1).is_multiple_of(BLE_ACK_WINDOW) || seq == last {
+                let (status, acked) = next_ack(&mut notifications, ack_uuid, BLE_ACK_TIMEOUT).await?;
+                anyhow::ensure!(status == 1 && acked == seq, "BLE transfer out of sync (ack {} for chunk {})", acked, seq);
+                callback.on_progress(task_id, ((seq as u64 + 1) * BLE_CHUNK_SIZE as u64).min(total), total);
+            }
+        }
+
+        let mut frame = vec![FRAME_END];
+        frame.extend_from_slice(blake3::hash(&data).as_bytes());
+        device.write(file_char, &frame, WriteType::WithResponse).await?;
+        let (status, _) = next_ack(&mut notifications, ack_uuid, BLE_ACK_TIMEOUT).await?;
+        anyhow::ensure!(status == 1, "Receiver reported checksum mismatch");
+        callback.on_complete(task_id, "Success (BLE)");
+        Ok(())
+    }.await;
+
+    if let Err(e) = device.disconnect().await {
+        warn!("BLE disconnect failed: {}", e);
+    }
+    result
+}
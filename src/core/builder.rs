@@ -0,0 +1,298 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+use crate::core::compression::CompressionAlgo;
+use crate::core::discovery::{registry as discovery_registry, DynDiscoveryBackend, ManualDiscovery};
+use crate::core::engine::{DropTeaConfig, DropTeaCore, EngineParts, RetryPolicy, TransportMode};
+use crate::core::error::{DropTeaError, Result};
+use crate::core::events::{NoopHandler, TransferEventHandler};
+use crate::core::hooks::DynPostReceiveHook;
+use crate::core::middleware::DynTransferMiddleware;
+use crate::core::io_priority::IoPriority;
+use crate::core::peer_prefs::PeerOverride;
+use crate::core::shares;
+use crate::core::transfer::{DynTransport, Timeouts};
+use crate::core::transports::custom;
+use crate::core::transports::memory::MemoryNetwork;
+
+// ==========================================
+// Engine Builder (ใช้แทนการเขียน DropTeaConfig ครบทุก Field เอง)
+// ไม่ตั้งอะไร = ค่าเดียวกับ DropTeaConfig::default() / ไม่ให้ Handler = NoopHandler (ฟังด้วย subscribe แทน)
+// Field ที่ไม่มี Setter ตั้งผ่าน configure() ได้ ทุกค่าถูกตรวจรวดเดียวตอน build()
+// ==========================================
+
+pub struct DropTeaBuilder {
+    config: DropTeaConfig,
+    handler: Option<Box<dyn TransferEventHandler>>,
+    runtime: Option<Arc<Runtime>>,
+    parts: EngineParts,
+}
+
+impl Default for DropTeaBuilder {
+    fn default() -> Self {
+        Self::from_config(DropTeaConfig::default())
+    }
+}
+
+impl DropTeaBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// เริ่มจาก Config ที่มีอยู่แล้ว (เช่น AppConfig::to_engine_config) แล้วแก้บางค่า
+    pub fn from_config(config: DropTeaConfig) -> Self {
+        Self { config, handler: None, runtime: None, parts: EngineParts::default() }
+    }
+
+    pub fn transport(mut self, mode: TransportMode) -> Self {
+        self.config.mode = mode;
+        self
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.config.port = port;
+        self
+    }
+
+    pub fn storage(mut self, path: impl Into<String>) -> Self {
+        self.config.storage_path = path.into();
+        self
+    }
+
+    /// โฟลเดอร์ที่ไฟล์ที่รับลง (ไม่ตั้ง = ./downloads)
+    pub fn download_dir(mut self, path: impl Into<String>) -> Self {
+        self.config.download_dir = path.into();
+        self
+    }
+
+    /// โฟลเดอร์ของ .part ระหว่างรับ (ไม่ตั้ง = ข้างไฟล์ปลายทาง)
+    pub fn temp_dir(mut self, path: impl Into<String>) -> Self {
+        self.config.temp_dir = Some(path.into());
+        self
+    }
+
+    pub fn node_name(mut self, name: impl Into<String>) -> Self {
+        self.config.node_name = name.into();
+        self
+    }
+
+    /// จำนวน Transfer ที่รับ / ส่งพร้อมกันได้
+    pub fn limits(mut self, max_incoming: usize, max_outgoing: usize) -> Self {
+        self.config.max_incoming = max_incoming;
+        self.config.max_outgoing = max_outgoing;
+        self
+    }
+
+    /// จำกัดความเร็วต่อ Transfer (byte/s) ของ Peer ที่ไม่ได้ตั้ง max_bytes_per_sec เอง
+    pub fn max_bytes_per_sec(mut self, rate: u64) -> Self {
+        self.config.max_bytes_per_sec = Some(rate);
+        self
+    }
+
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.config.timeouts = timeouts;
+        self
+    }
+
+    pub fn io_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeouts.io = timeout;
+        self
+    }
+
+    pub fn decision_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeouts.user_decision = timeout;
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.config.retry = retry;
+        self
+    }
+
+    /// Algo ที่อยากใช้ก่อน (ใช้เมื่อปลายทางรองรับ)
+    pub fn compression(mut self, algo: CompressionAlgo) -> Self {
+        self.config.preferred_compression = Some(algo);
+        self
+    }
+
+    pub fn compression_level(mut self, level: i32) -> Self {
+        self.config.compression_level = Some(level);
+        self
+    }
+
+    pub fn io_priority(mut self, priority: IoPriority) -> Self {
+        self.config.io_priority = priority;
+        self
+    }
+
+    pub fn dev_mode(mut self, enabled: bool) -> Self {
+        self.config.dev_mode = enabled;
+        self
+    }
+
+    pub fn guest(mut self, enabled: bool) -> Self {
+        self.config.guest = enabled;
+        self
+    }
+
+    /// ค่าเฉพาะ Peer (id = Fingerprint / Peer ID / ชื่อเครื่อง / IP)
+    pub fn peer(mut self, id: impl Into<String>, prefs: PeerOverride) -> Self {
+        self.config.peers.insert(id.into(), prefs);
+        self
+    }
+
+    /// แก้ Field อื่นของ DropTeaConfig ตรงๆ
+    pub fn configure(mut self, f: impl FnOnce(&mut DropTeaConfig)) -> Self {
+        f(&mut self.config);
+        self
+    }
+
+    pub fn handler(mut self, handler: impl TransferEventHandler + 'static) -> Self {
+        self.handler = Some(Box::new(handler));
+        self
+    }
+
+    /// ใช้ Runtime ของ Embedder (ไม่ระบุ = สร้าง Multi-thread Runtime ให้)
+    pub fn runtime(mut self, rt: Arc<Runtime>) -> Self {
+        self.runtime = Some(rt);
+        self
+    }
+
+    /// ไม่เปิด Socket ของ Transport: Engine ที่ใช้ network เดียวกันส่งหากันได้ที่ 127.0.0.1:port (ไว้ทดสอบ)
+    pub fn memory_network(mut self, network: Arc<MemoryNetwork>) -> Self {
+        self.parts.memory_network = Some(network);
+        self
+    }
+
+    /// ใช้ Transport นี้แทน mode (name = ชื่อใน Stats / mDNS) ดู transports/custom.rs
+    pub fn custom_transport(mut self, name: &'static str, transport: Arc<DynTransport>) -> Self {
+        self.parts.transport = Some((name, transport));
+        self
+    }
+
+    /// ใส่ตัวแรก = ไม่ใช้ mDNS / BLE อีก (เช่น MockDiscovery) เพิ่มหลัง build ได้ด้วย register_discovery_backend
+    pub fn discovery_backend(mut self, backend: DynDiscoveryBackend) -> Self {
+        self.parts.discovery.get_or_insert_with(Vec::new).push(backend);
+        self
+    }
+
+    /// รันหลังไฟล์ที่รับลงที่แล้ว (ใส่ได้หลายตัว รันตามลำดับ) Error = Event HookFailed
+    pub fn post_receive_hook(mut self, hook: DynPostReceiveHook) -> Self {
+        self.parts.post_receive.push(hook);
+        self
+    }
+
+    /// ชั้นเสริมรอบการส่ง / รับทุกไฟล์ (ใส่ได้หลายตัว เรียงตามลำดับ) ดู middleware.rs
+    pub fn middleware(mut self, middleware: DynTransferMiddleware) -> Self {
+        self.parts.middleware.push(middleware);
+        self
+    }
+
+    /// ตรวจแล้วคืน Config (ไม่สร้าง Engine)
+    pub fn config(self) -> Result<DropTeaConfig> {
+        validate(&self.config)?;
+        Ok(self.config)
+    }
+
+    pub fn build(self) -> Result<DropTeaCore> {
+        validate(&self.config)?;
+        if self.config.guest && self.parts.transport.is_some() {
+            return Err(DropTeaError::Config("guest mode needs a TLS transport (tcp / quic)".into()));
+        }
+        let rt = match self.runtime {
+            Some(rt) => rt,
+            None => Arc::new(Runtime::new().map_err(|e| DropTeaError::Internal(format!("Failed to start runtime: {}", e)))?),
+        };
+        let handler = self.handler.unwrap_or_else(|| Box::new(NoopHandler));
+        DropTeaCore::new_with_parts(rt, self.config, handler, self.parts)
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> Result<()> {
+    Err(DropTeaError::Config(msg.into()))
+}
+
+fn validate(config: &DropTeaConfig) -> Result<()> {
+    if config.storage_path.trim().is_empty() {
+        return invalid("storage path is empty");
+    }
+    if config.download_dir.trim().is_empty() {
+        return invalid("download dir is empty");
+    }
+    // node_name เป็นส่วนหนึ่งของชื่อไฟล์ Cert/Key
+    if config.node_name.trim().is_empty() {
+        return invalid("node name is empty");
+    }
+    if config.node_name.contains(['/', '\\']) || config.node_name.contains("..") {
+        return invalid(format!("node name '{}' must not contain path separators", config.node_name));
+    }
+    if config.max_incoming == 0 || config.max_outgoing == 0 {
+        return invalid("max_incoming / max_outgoing must be at least 1");
+    }
+    if config.max_bytes_per_sec == Some(0) {
+        return invalid("max_bytes_per_sec must be at least 1");
+    }
+    if config.timeouts.io.is_zero() || config.timeouts.user_decision.is_zero() {
+        return invalid("io / user_decision timeouts must be non-zero");
+    }
+    if config.retry.attempts == 0 {
+        return invalid("retry attempts must be at least 1");
+    }
+    if !(0.0..=1.0).contains(&config.retry.jitter) {
+        return invalid("retry jitter must be between 0.0 and 1.0");
+    }
+    // Level ใช้กับ Algo ที่ตกลงกันได้ตอนส่ง (เกินช่วงของ Algo นั้นถูก Clamp) แต่นอกช่วงของทุก Algo = พิมพ์ผิดแน่
+    if let Some(level) = config.compression_level.filter(|l| !(0..=22).contains(l)) {
+        return invalid(format!("compression level {} out of range (0..=22)", level));
+    }
+    if let Some(name) = config.custom_transport.as_deref().filter(|n| !custom::is_registered(n)) {
+        return invalid(format!("transport '{}' is not registered (available: {})", name, custom::registered().join(", ")));
+    }
+    if config.guest && (config.custom_transport.is_some() || !matches!(config.mode, TransportMode::Tcp | TransportMode::Quic)) {
+        return invalid("guest mode needs a TLS transport (tcp / quic)");
+    }
+    if config.rendezvous_server.is_some() && config.mode != TransportMode::Quic {
+        return invalid("rendezvous_server is only used with quic");
+    }
+    #[cfg(feature = "webrtc")]
+    if config.ice_servers.is_some() && config.mode != TransportMode::WebRtc {
+        return invalid("ice_servers is only used with webrtc");
+    }
+    #[cfg(not(feature = "webrtc"))]
+    if config.ice_servers.is_some() {
+        return invalid("ice_servers needs a build with the webrtc feature");
+    }
+    for (i, name) in config.discovery_backends.iter().enumerate() {
+        if !discovery_registry::is_known(name) {
+            return invalid(format!("unknown discovery backend '{}' (available: {})", name, discovery_registry::available().join(", ")));
+        }
+        if config.discovery_backends[..i].contains(name) {
+            return invalid(format!("discovery backend '{}' listed twice", name));
+        }
+    }
+    if let Some(peer) = config.manual_peers.iter().find(|p| !ManualDiscovery::is_valid(p)) {
+        return invalid(format!("manual peer '{}' must be host:port", peer));
+    }
+    if let Some(localsend) = &config.localsend {
+        // แอป LocalSend หาเฉพาะ Port ของมัน / Multicast ใช้ Port เดียวกับ HTTP
+        if localsend.port == 0 || localsend.port == config.port {
+            return invalid(format!("localsend port {} must be fixed and differ from the server port", localsend.port));
+        }
+    }
+    if let Some(links) = &config.links {
+        // Port อยู่ใน Link ที่ส่งต่อไปแล้ว -> สุ่มใหม่ทุกครั้งไม่ได้
+        if links.port == 0 || links.port == config.port || config.localsend.as_ref().is_some_and(|l| l.port == links.port) {
+            return invalid(format!("links port {} must be fixed and differ from the server / localsend ports", links.port));
+        }
+    }
+    if let Some(share) = config.shares.iter().find(|s| !shares::is_valid_name(&s.name)) {
+        return invalid(format!("share name '{}' must be non-empty without '/'", share.name));
+    }
+    for (id, prefs) in &config.peers {
+        if let Err(DropTeaError::Config(msg)) = prefs.validate(config.mode) {
+            return invalid(format!("peer '{}': {}", id, msg));
+        }
+    }
+    Ok(())
+}
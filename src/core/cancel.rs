@@ -0,0 +1,144 @@
+use std::future::Future;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use dashmap::DashMap;
+use tokio::sync::watch;
+pub use tokio_util::sync::CancellationToken;
+
+// ==========================================
+// Cancellation (ลำดับชั้นเดียวทั้งระบบ)
+//   engine (ปิดโปรแกรม) -> service (stop_service) -> transfer (ยกเลิกราย Task)
+// ยกเลิกชั้นบน = ลูกทุกตัวถูกยกเลิกตาม Loop/Handler แค่ select! กับ cancelled()
+// Transfer มีสถานะ Pause ด้วย (สั่งจากเครื่องเราหรือ Peer ผ่าน control.rs)
+// ==========================================
+
+pub const REJECT_CANCELLED: &str = "Cancelled";
+
+/// อีกฝั่งของ Transfer: ใช้แจ้ง CANCEL/PAUSE ไปหา Peer และตรวจว่าคำสั่งที่เข้ามามาจาก Peer ตัวจริง
+#[derive(Debug, Clone)]
+pub struct RemoteTransfer {
+    pub ip: IpAddr,
+    // Control Port ของ Peer (None = Peer รุ่นเก่า แจ้งกลับไม่ได้)
+    pub control_port: Option<u16>,
+    // Task ID ฝั่งผู้ส่ง (ใช้อ้างอิง Transfer เดียวกันทั้งสองเครื่อง)
+    pub transfer_id: String,
+}
+
+#[derive(Debug)]
+struct Entry {
+    token: CancellationToken,
+    paused: watch::Sender<bool>,
+    remote: Option<RemoteTransfer>,
+}
+
+/// Token ของ Transfer ที่กำลังทำงาน เรียกยกเลิกด้วย Task ID ได้ทั้งฝั่งรับและฝั่งส่ง
+#[derive(Debug, Default)]
+pub struct TransferTokens {
+    tokens: DashMap<String, Entry>,
+}
+
+/// สิ่งที่ Loop ของ Transfer ต้องคอยดู: ยกเลิก + หยุดชั่วคราว
+#[derive(Debug, Clone)]
+pub struct TransferSignal {
+    cancel: CancellationToken,
+    paused: watch::Receiver<bool>,
+}
+
+/// ถือไว้ตลอด Transfer Drop แล้วเอาออกจาก Registry
+pub struct TransferGuard {
+    registry: Arc<TransferTokens>,
+    task_id: String,
+    signal: TransferSignal,
+}
+
+impl TransferTokens {
+    pub fn new() -> Arc<Self> { Arc::new(Self::default()) }
+
+    pub fn register(self: &Arc<Self>, parent: &CancellationToken, task_id: &str) -> TransferGuard {
+        let token = parent.child_token();
+        let (paused, paused_rx) = watch::channel(false);
+        self.tokens.insert(task_id.to_string(), Entry { token: token.clone(), paused, remote: None });
+        TransferGuard { registry: self.clone(), task_id: task_id.to_string(), signal: TransferSignal { cancel: token, paused: paused_rx } }
+    }
+
+    /// false = ไม่มี Transfer นี้ (จบไปแล้วหรือไม่เคยมี)
+    pub fn cancel(&self, task_id: &str) -> bool {
+        match self.tokens.get(task_id) {
+            Some(entry) => { entry.token.cancel(); true }
+            None => false,
+        }
+    }
+
+    pub fn set_paused(&self, task_id: &str, paused: bool) -> bool {
+        match self.tokens.get(task_id) {
+            Some(entry) => { entry.paused.send_replace(paused); true }
+            None => false,
+        }
+    }
+
+    pub fn contains(&self, task_id: &str) -> bool {
+        self.tokens.contains_key(task_id)
+    }
+
+    pub fn remote(&self, task_id: &str) -> Option<RemoteTransfer> {
+        self.tokens.get(task_id).and_then(|entry| entry.remote.clone())
+    }
+
+    /// Task ID ในเครื่องเรา ของ Transfer ที่ Peer `ip` อ้างถึงด้วย `transfer_id`
+    pub fn find_remote(&self, ip: IpAddr, transfer_id: &str) -> Option<String> {
+        self.tokens.iter()
+            .find(|e| e.remote.as_ref().is_some_and(|r| r.ip == ip && r.transfer_id == transfer_id))
+            .map(|e| e.key().clone())
+    }
+}
+
+impl TransferSignal {
+    /// ไม่ได้ลงทะเบียนใน Registry (Pause ไม่ได้ ยกเลิกได้อย่างเดียว)
+    pub fn detached(cancel: CancellationToken) -> Self {
+        Self { cancel, paused: watch::channel(false).1 }
+    }
+
+    pub fn token(&self) -> &CancellationToken { &self.cancel }
+    pub fn is_cancelled(&self) -> bool { self.cancel.is_cancelled() }
+    pub async fn cancelled(&self) { self.cancel.cancelled().await }
+    pub fn is_paused(&self) -> bool { *self.paused.borrow() }
+
+    /// คืนทันทีถ้าไม่ได้ Pause (หรือ Transfer จบไปแล้ว)
+    pub async fn resumed(&self) {
+        let mut paused = self.paused.clone();
+        let _ = paused.wait_for(|p| !*p).await;
+    }
+
+    /// timeout ที่ไม่นับช่วง Pause (อีกฝั่งหยุดส่งโดยตั้งใจ ไม่ใช่ Connection ตาย)
+    pub async fn timeout<F: Future>(&self, limit: Duration, fut: F) -> Option<F::Output> {
+        tokio::pin!(fut);
+        let mut paused = self.paused.clone();
+        let mut alive = true;
+        loop {
+            let running = !*paused.borrow_and_update();
+            tokio::select! {
+                out = &mut fut => return Some(out),
+                _ = tokio::time::sleep(limit), if running => return None,
+                changed = paused.changed(), if alive => alive = changed.is_ok(),
+            }
+        }
+    }
+}
+
+impl TransferGuard {
+    pub fn token(&self) -> &CancellationToken { &self.signal.cancel }
+    pub fn signal(&self) -> &TransferSignal { &self.signal }
+
+    pub fn set_remote(&self, remote: RemoteTransfer) {
+        if let Some(mut entry) = self.registry.tokens.get_mut(&self.task_id) {
+            entry.remote = Some(remote);
+        }
+    }
+}
+
+impl Drop for TransferGuard {
+    fn drop(&mut self) {
+        self.registry.tokens.remove(&self.task_id);
+    }
+}
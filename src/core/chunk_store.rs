@@ -0,0 +1,119 @@
+use std::fs::{self as std_fs, File as StdFile};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use dashmap::DashMap;
+
+use crate::core::dedup::ChunkHash;
+
+// ==========================================
+// Content-addressable Chunk Store (storage.chunk_store_mb, ใช้คู่กับ dedup)
+// เก็บเนื้อ Chunk ของไฟล์ที่รับไว้แยกจากไฟล์จริง: <dir>/<2 hex แรก>/<blake3 hex>
+// ไฟล์ที่รับถูกลบ / เขียนทับ (เช่น Build Artifact ชื่อเดิมทุกรอบ) Chunk เก่ายังใช้ตอบ Dedup ได้
+// เกินขนาดที่ตั้งไว้ -> ลบ Chunk ที่ไม่ได้ใช้นานที่สุดก่อน (ลงไปเหลือ 90%)
+// ==========================================
+
+pub const STORE_DIR: &str = "chunk_store";
+const TEMP_SUFFIX: &str = ".tmp";
+
+#[derive(Debug, Clone, Copy)]
+struct Entry { len: u64, last_used_ms: u64 }
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+#[derive(Debug)]
+pub struct ChunkStore {
+    dir: PathBuf,
+    max_bytes: u64,
+    chunks: DashMap<ChunkHash, Entry>,
+    used: Mutex<u64>,
+}
+
+impl ChunkStore {
+    /// โหลดรายการ Chunk ที่มีอยู่ (ใช้ mtime เป็นเวลาที่ใช้ล่าสุด ทุกครั้งที่ถูกใช้จะ touch ไฟล์)
+    pub fn open(dir: PathBuf, max_bytes: u64) -> Self {
+        let chunks = DashMap::new();
+        let mut used = 0u64;
+        for fan in std_fs::read_dir(&dir).into_iter().flatten().flatten() {
+            for file in std_fs::read_dir(fan.path()).into_iter().flatten().flatten() {
+                let name = file.file_name().to_string_lossy().to_string();
+                // เขียนค้างตอนปิดแอป
+                if name.ends_with(TEMP_SUFFIX) { let _ = std_fs::remove_file(file.path()); continue; }
+                let Ok(hash) = hex::decode(&name).map_err(|_| ()).and_then(|b| ChunkHash::try_from(b.as_slice()).map_err(|_| ())) else { continue };
+                let Ok(meta) = file.metadata() else { continue };
+                let last_used_ms = meta.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map_or(0, |d| d.as_millis() as u64);
+                used += meta.len();
+                chunks.insert(hash, Entry { len: meta.len(), last_used_ms });
+            }
+        }
+        log::info!("Chunk store loaded: {} chunks, {} of {} bytes", chunks.len(), used, max_bytes);
+        let store = Self { dir, max_bytes, chunks, used: Mutex::new(used) };
+        store.evict();
+        store
+    }
+
+    fn path_of(&self, hash: &ChunkHash) -> PathBuf {
+        let name = hex::encode(hash);
+        self.dir.join(&name[..2]).join(name)
+    }
+
+    pub fn contains(&self, hash: &ChunkHash, len: u64) -> bool {
+        self.chunks.get(hash).is_some_and(|e| e.len == len)
+    }
+
+    /// อ่าน Chunk (Hash ไม่ตรง = ไฟล์เสีย ลบทิ้งแล้วคืน Error)
+    pub fn read(&self, hash: &ChunkHash) -> std::io::Result<Vec<u8>> {
+        let path = self.path_of(hash);
+        let mut data = Vec::new();
+        StdFile::open(&path)?.read_to_end(&mut data)?;
+        if blake3::hash(&data).as_bytes() != hash {
+            self.remove(hash);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Corrupt chunk in store"));
+        }
+        let now = now_ms();
+        if let Some(mut entry) = self.chunks.get_mut(hash) { entry.last_used_ms = now; }
+        let _ = StdFile::options().write(true).open(&path).and_then(|f| f.set_modified(SystemTime::now()));
+        Ok(data)
+    }
+
+    /// เก็บ Chunk (มีแล้วแค่ต่ออายุ) เขียนลงไฟล์ชั่วคราวแล้ว Rename -> ไม่มี Chunk ครึ่งๆ
+    pub fn put(&self, hash: &ChunkHash, data: &[u8]) -> std::io::Result<()> {
+        if let Some(mut entry) = self.chunks.get_mut(hash) {
+            entry.last_used_ms = now_ms();
+            return Ok(());
+        }
+        if data.len() as u64 > self.max_bytes { return Ok(()); }
+        let path = self.path_of(hash);
+        if let Some(parent) = path.parent() { std_fs::create_dir_all(parent)?; }
+        let temp = path.with_extension(TEMP_SUFFIX.trim_start_matches('.'));
+        StdFile::create(&temp)?.write_all(data)?;
+        std_fs::rename(&temp, &path)?;
+        self.chunks.insert(*hash, Entry { len: data.len() as u64, last_used_ms: now_ms() });
+        *self.used.lock().unwrap() += data.len() as u64;
+        self.evict();
+        Ok(())
+    }
+
+    fn remove(&self, hash: &ChunkHash) {
+        if let Some((_, entry)) = self.chunks.remove(hash) {
+            let _ = std_fs::remove_file(self.path_of(hash));
+            let mut used = self.used.lock().unwrap();
+            *used = used.saturating_sub(entry.len);
+        }
+    }
+
+    fn evict(&self) {
+        if *self.used.lock().unwrap() <= self.max_bytes { return; }
+        let mut by_age: Vec<(ChunkHash, Entry)> = self.chunks.iter().map(|e| (*e.key(), *e.value())).collect();
+        by_age.sort_by_key(|(_, e)| e.last_used_ms);
+        let target = self.max_bytes / 10 * 9;
+        for (hash, _) in by_age {
+            if *self.used.lock().unwrap() <= target { break; }
+            self.remove(&hash);
+        }
+        log::debug!("Chunk store evicted down to {} bytes", self.used.lock().unwrap());
+    }
+}
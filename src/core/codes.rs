@@ -0,0 +1,374 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::watch;
+use tokio::time::timeout;
+use anyhow::{bail, Context};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+
+use crate::core::admin::{read_frame, write_frame};
+use crate::core::compression;
+use crate::core::error::DropTeaError;
+use crate::core::transfer::{DataStream, DynStream, DynTransport, IO_TIMEOUT};
+
+// ==========================================
+// One-time Transfer Codes (แบบ Magic Wormhole)
+// ผู้ส่ง offer_code(path) ได้ Code "<nameplate>-<คำ>-<คำ>" เช่น "42-tiger-lemon" ไปบอกผู้รับ (พูด / แชท)
+// Nameplate (ตัวเลขหน้า) เป็นของสาธารณะ: ประกาศเป็น feat "code=<n>" (mDNS / Broadcast) + id "code-<n>" บน Rendezvous
+// คำที่เหลือเป็นความลับ ไม่ออกไปกับ Wire: ตกลง Key กันด้วย SPAKE2 (Framing เดียวกับ Admin: [u32 len][JSON]):
+//   ผู้รับ -> {"code": {"nameplate", "pake": x*G + w*M}}   ผู้ส่ง -> {"pake": y*G + w*N}
+//   ผู้รับ -> {"proof"}   ผู้ส่ง -> {"ok", "filename", "proof"} แล้วส่งไฟล์ทาง Connection เดิมด้วย Pipeline ปกติ
+// w = Scalar จาก Code / key = blake3(Nameplate, ข้อความทั้งสอง, จุดที่คำนวณร่วมกัน, w) / proof = blake3 keyed(key, บทบาท)
+// ผู้รับพิสูจน์ก่อน ผู้ส่งพิสูจน์กลับก่อนผู้รับยอมรับไฟล์
+// คนที่ปลอมเป็นอีกฝั่ง (ประกาศ Nameplate เดียวกัน / ต่อเข้ามาเอง) ได้ทดสอบ Code แค่ 1 คำต่อ Connection ไม่มีอะไรให้เดาต่อแบบ Offline
+// (Code มีแค่ 16 bit จึงต้องใช้ PAKE) / Code ใช้ได้ครั้งเดียว: Claim แรกผิดก็ทิ้งเลย ไม่ให้เดาซ้ำ
+// ผู้รับไม่ถาม User และไม่เพิ่ม Whitelist (เชื่อเฉพาะ Transfer นี้)
+// ==========================================
+
+pub const FEATURE_PREFIX: &str = "code=";
+pub const RENDEZVOUS_PREFIX: &str = "code-";
+pub const DEFAULT_TTL: Duration = Duration::from_secs(10 * 60);
+// Event ของ Code (ส่ง / หมดอายุ / ถูกเดาผิด) ใช้ Task ID นี้
+pub const TASK_PREFIX: &str = "code:";
+pub const REJECT_WRONG_CODE: &str = "Wrong code";
+const UNKNOWN_CODE: &str = "Unknown code";
+const MAX_NAMEPLATE: u16 = 999;
+const KEY_CONTEXT: &str = "DropTea 2024 one-time transfer code v2";
+// M / N ของ SPAKE2 (Hash-to-curve จากข้อความคงที่ -> ไม่มีใครรู้ Discrete Log)
+const BLIND_CONTEXT: &str = "DropTea 2024 one-time transfer code SPAKE2 blinding";
+
+const WORDS: [&str; 256] = [
+    "acid", "actor", "alarm", "album", "alpha", "amber", "angle", "apple", "arrow", "atlas", "audio", "award",
+    "bacon", "badge", "baker", "bamboo", "banana", "basil", "beach", "berry", "bison", "blade", "blanket", "bloom",
+    "board", "bonus", "brave", "bread", "brick", "bridge", "brush", "bubble", "cabin", "cable", "cactus", "camel",
+    "candle", "candy", "canoe", "canvas", "carbon", "carpet", "castle", "cedar", "chalk", "cherry", "chess", "chili",
+    "cider", "circle", "citrus", "clock", "cloud", "clover", "cobalt", "cocoa", "comet", "coral", "cotton", "crane",
+    "crayon", "cricket", "crown", "crystal", "cupcake", "daisy", "delta", "denim", "desert", "diamond", "dolphin", "donut",
+    "dragon", "dream", "drum", "eagle", "echo", "elbow", "ember", "emerald", "engine", "falcon", "fern", "fiddle",
+    "field", "flame", "flute", "forest", "fossil", "fox", "frost", "galaxy", "garden", "garlic", "gecko", "ginger",
+    "glacier", "globe", "grape", "gravel", "guitar", "hammer", "harbor", "hazel", "helmet", "honey", "horizon", "husky",
+    "iceberg", "igloo", "indigo", "island", "ivory", "jacket", "jaguar", "jasmine", "jelly", "jungle", "kayak", "kettle",
+    "kiwi", "koala", "ladder", "lagoon", "lantern", "lemon", "lilac", "lily", "lime", "llama", "lobster", "lotus",
+    "magnet", "mango", "maple", "marble", "meadow", "melon", "meteor", "mint", "mirror", "mocha", "monkey", "mosaic",
+    "muffin", "nebula", "nectar", "needle", "noodle", "nutmeg", "oasis", "ocean", "olive", "onion", "orbit", "orchid",
+    "otter", "owl", "oyster", "paddle", "panda", "paper", "parrot", "peach", "pebble", "pencil", "pepper", "piano",
+    "pickle", "pillow", "pilot", "pine", "planet", "plum", "pocket", "polar", "pony", "poppy", "potato", "prism",
+    "puffin", "pumpkin", "puzzle", "quartz", "quill", "rabbit", "radar", "radish", "rain", "raven", "reef", "ribbon",
+    "river", "robin", "rocket", "rose", "ruby", "saddle", "saffron", "salmon", "sand", "sapphire", "scarf", "shadow",
+    "shell", "silver", "sketch", "sled", "snow", "socket", "sparrow", "spice", "spider", "spoon", "spruce", "squid",
+    "star", "stone", "storm", "sugar", "summit", "sunset", "swan", "tango", "tea", "thunder", "tiger", "timber",
+    "toast", "topaz", "torch", "tulip", "tundra", "turtle", "umbrella", "unicorn", "valley", "velvet", "violet", "violin",
+    "volcano", "waffle", "walnut", "walrus", "wave", "whale", "willow", "window", "winter", "wizard", "yarn", "zebra",
+    "zephyr", "zinc", "zigzag", "zoom",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CodeClaim {
+    pub nameplate: u16,
+    // ข้อความ SPAKE2 ของผู้รับ (Ristretto แบบ Hex)
+    pub pake: String,
+    // Algo ที่ผู้รับแตกได้ (แบบ TXT "comp")
+    #[serde(default)]
+    pub compression: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CodeRequest {
+    pub code: CodeClaim,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Challenge {
+    #[serde(default)]
+    pake: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Proof {
+    proof: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Answer {
+    ok: bool,
+    #[serde(default)]
+    filename: Option<String>,
+    #[serde(default)]
+    proof: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// ตัวพิมพ์เล็ก ช่องว่างเป็น "-" (ผู้รับพิมพ์ "42 Tiger lemon" ก็ได้)
+pub fn normalize(code: &str) -> String {
+    code.split(|c: char| c.is_whitespace() || c == '-').filter(|p| !p.is_empty()).collect::<Vec<_>>().join("-").to_lowercase()
+}
+
+/// Nameplate ของ Code ที่หน้าตาถูกต้อง (None = พิมพ์ผิดรูปแบบ)
+pub fn nameplate(code: &str) -> Option<u16> {
+    let code = normalize(code);
+    let mut parts = code.split('-');
+    let plate = parts.next()?.parse::<u16>().ok().filter(|n| (1..=MAX_NAMEPLATE).contains(n))?;
+    let words: Vec<&str> = parts.collect();
+    (words.len() == 2 && words.iter().all(|w| WORDS.contains(w))).then_some(plate)
+}
+
+fn wide(context: &str, input: &[u8]) -> [u8; 64] {
+    let mut bytes = [0u8; 64];
+    blake3::Hasher::new_derive_key(context).update(input).finalize_xof().fill(&mut bytes);
+    bytes
+}
+
+fn blind(label: &str) -> RistrettoPoint {
+    RistrettoPoint::from_uniform_bytes(&wide(BLIND_CONTEXT, label.as_bytes()))
+}
+
+// SPAKE2 ฝั่งเดียว (ผู้รับใช้ M ผู้ส่งใช้ N)
+struct Spake {
+    receiver: bool,
+    w: Scalar,
+    secret: Scalar,
+    message: RistrettoPoint,
+}
+
+impl Spake {
+    fn start(code: &str, receiver: bool) -> Self {
+        let w = Scalar::from_bytes_mod_order_wide(&wide(KEY_CONTEXT, normalize(code).as_bytes()));
+        let mut random = [0u8; 64];
+        rand::thread_rng().fill(&mut random[..]);
+        let secret = Scalar::from_bytes_mod_order_wide(&random);
+        let message = RISTRETTO_BASEPOINT_POINT * secret + blind(if receiver { "M" } else { "N" }) * w;
+        Self { receiver, w, secret, message }
+    }
+
+    fn message(&self) -> String {
+        hex::encode(self.message.compress().as_bytes())
+    }
+
+    /// Key จากข้อความของอีกฝั่ง (None = ไม่ใช่จุดบน Curve) Code ไม่ตรงกันได้ Key คนละตัว
+    fn finish(&self, plate: u16, theirs: &str) -> Option<[u8; 32]> {
+        let bytes: [u8; 32] = hex::decode(theirs).ok()?.try_into().ok()?;
+        let theirs = CompressedRistretto(bytes).decompress()?;
+        let shared = (theirs - blind(if self.receiver { "N" } else { "M" }) * self.w) * self.secret;
+        let (receiver, sender) = if self.receiver { (self.message, theirs) } else { (theirs, self.message) };
+        let mut hasher = blake3::Hasher::new_derive_key(KEY_CONTEXT);
+        hasher.update(&plate.to_be_bytes());
+        for point in [receiver, sender, shared] {
+            hasher.update(point.compress().as_bytes());
+        }
+        hasher.update(self.w.as_bytes());
+        Some(*hasher.finalize().as_bytes())
+    }
+}
+
+fn proof(key: &[u8; 32], role: &str) -> String {
+    blake3::keyed_hash(key, role.as_bytes()).to_hex().to_string()
+}
+
+// --- Sender Side ---
+
+struct CodeOffer {
+    code: String,
+    path: PathBuf,
+    expires_at: Instant,
+}
+
+/// Code ที่เปิดรอผู้รับอยู่ (เปลี่ยนเมื่อไหร่ subscribe ได้ยิน -> Engine ประกาศ Nameplate ใหม่)
+pub struct CodeOffers {
+    offers: StdMutex<HashMap<u16, CodeOffer>>,
+    changed: watch::Sender<()>,
+}
+
+impl std::fmt::Debug for CodeOffers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CodeOffers").field("nameplates", &self.nameplates()).finish()
+    }
+}
+
+impl CodeOffers {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self { offers: StdMutex::new(HashMap::new()), changed: watch::channel(()).0 })
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<()> { self.changed.subscribe() }
+
+    /// สุ่ม Code ใหม่ให้ไฟล์นี้ (taken = Nameplate ที่ Peer อื่นบน LAN ใช้อยู่)
+    pub fn offer(&self, path: PathBuf, ttl: Duration, taken: &HashSet<u16>) -> Result<String, DropTeaError> {
+        let mut offers = self.offers.lock().unwrap();
+        offers.retain(|_, o| o.expires_at > Instant::now());
+        let mut rng = rand::thread_rng();
+        let free: Vec<u16> = (1..=MAX_NAMEPLATE).filter(|n| !offers.contains_key(n) && !taken.contains(n)).collect();
+        if free.is_empty() { return Err(DropTeaError::Config("Too many open transfer codes".into())); }
+        let plate = free[rng.gen_range(0..free.len())];
+        let code = format!("{}-{}-{}", plate, WORDS[rng.gen_range(0..WORDS.len())], WORDS[rng.gen_range(0..WORDS.len())]);
+        offers.insert(plate, CodeOffer { code: code.clone(), path, expires_at: Instant::now() + ttl });
+        drop(offers);
+        self.changed.send_replace(());
+        Ok(code)
+    }
+
+    /// ปิด Code (false = ไม่มี / ใช้ไปแล้ว / หมดอายุแล้ว)
+    pub fn cancel(&self, code: &str) -> bool {
+        let code = normalize(code);
+        let removed = {
+            let mut offers = self.offers.lock().unwrap();
+            let plate = offers.iter().find(|(_, o)| o.code == code).map(|(n, _)| *n);
+            plate.and_then(|n| offers.remove(&n)).is_some()
+        };
+        if removed { self.changed.send_replace(()); }
+        removed
+    }
+
+    /// หยิบ Offer ออก (ใช้ได้ครั้งเดียว ไม่ว่า proof จะถูกหรือไม่)
+    fn take(&self, plate: u16) -> Option<CodeOffer> {
+        let offer = self.offers.lock().unwrap().remove(&plate);
+        if offer.is_some() { self.changed.send_replace(()); }
+        offer.filter(|o| o.expires_at > Instant::now())
+    }
+
+    pub fn nameplates(&self) -> Vec<u16> {
+        let now = Instant::now();
+        let mut plates: Vec<u16> = self.offers.lock().unwrap().iter().filter(|(_, o)| o.expires_at > now).map(|(n, _)| *n).collect();
+        plates.sort_unstable();
+        plates
+    }
+
+    /// feat ที่ต้องประกาศตอนนี้
+    pub fn features(&self) -> Vec<String> {
+        self.nameplates().into_iter().map(|n| format!("{}{}", FEATURE_PREFIX, n)).collect()
+    }
+}
+
+/// Claim ที่ตรวจ proof ของผู้รับผ่านแล้ว ยังไม่ได้ตอบ (Middleware ยังปฏิเสธได้)
+pub struct Verified {
+    pub code: String,
+    pub path: PathBuf,
+    proof: String,
+}
+
+pub enum Claimed {
+    // ไม่มี Code นี้ / หมดอายุ
+    Unknown,
+    // proof ผิด -> Code ถูกทิ้งแล้ว
+    Wrong(String),
+    Verified(Verified),
+}
+
+/// ฝั่งผู้ส่ง: ตอบข้อความ SPAKE2 แล้วตรวจ proof ของผู้รับ
+pub async fn verify<S: DataStream>(stream: &mut S, claim: &CodeClaim, offers: &CodeOffers, io_timeout: Duration) -> anyhow::Result<Claimed> {
+    let Some(offer) = offers.take(claim.nameplate) else {
+        let reply = Challenge { error: Some(UNKNOWN_CODE.to_string()), ..Default::default() };
+        timeout(io_timeout, write_frame(stream, &serde_json::to_vec(&reply)?)).await.context("Code challenge timeout")??;
+        let _ = stream.shutdown().await;
+        return Ok(Claimed::Unknown);
+    };
+    let spake = Spake::start(&offer.code, false);
+    let Some(key) = spake.finish(claim.nameplate, &claim.pake) else {
+        refuse(stream, REJECT_WRONG_CODE, io_timeout).await?;
+        return Ok(Claimed::Wrong(offer.code));
+    };
+    let reply = Challenge { pake: Some(spake.message()), ..Default::default() };
+    timeout(io_timeout, write_frame(stream, &serde_json::to_vec(&reply)?)).await.context("Code challenge timeout")??;
+    let received: Proof = serde_json::from_slice(&read_frame(stream).await?).context("Invalid code proof")?;
+    if received.proof != proof(&key, "receiver") {
+        refuse(stream, REJECT_WRONG_CODE, io_timeout).await?;
+        return Ok(Claimed::Wrong(offer.code));
+    }
+    Ok(Claimed::Verified(Verified { proof: proof(&key, "sender"), code: offer.code, path: offer.path }))
+}
+
+impl Verified {
+    /// บอกชื่อไฟล์ + พิสูจน์ตัวกลับ -> ผู้เรียกส่งไฟล์ต่อทาง Stream เดิม
+    pub async fn accept<S: DataStream>(&self, stream: &mut S, filename: &str, io_timeout: Duration) -> anyhow::Result<()> {
+        let answer = Answer { ok: true, filename: Some(filename.to_string()), proof: Some(self.proof.clone()), ..Default::default() };
+        timeout(io_timeout, write_frame(stream, &serde_json::to_vec(&answer)?)).await.context("Code answer timeout")??;
+        Ok(())
+    }
+}
+
+pub async fn refuse<S: DataStream>(stream: &mut S, reason: &str, io_timeout: Duration) -> anyhow::Result<()> {
+    let answer = Answer { error: Some(reason.to_string()), ..Default::default() };
+    timeout(io_timeout, write_frame(stream, &serde_json::to_vec(&answer)?)).await.context("Code answer timeout")??;
+    let _ = stream.shutdown().await;
+    Ok(())
+}
+
+// --- Receiver Side ---
+
+/// ฝั่งผู้รับ: พิสูจน์ว่ารู้ Code แล้วตรวจว่าผู้ส่งรู้ด้วย คืน (Stream ที่ไฟล์จะตามมา, ชื่อไฟล์)
+pub async fn claim(transport: &DynTransport, host: &str, port: u16, code: &str) -> anyhow::Result<(DynStream, String)> {
+    let plate = nameplate(code).ok_or_else(|| DropTeaError::Config(format!("'{}' is not a transfer code", code)))?;
+    let spake = Spake::start(code, true);
+    let mut stream = transport.connect(host, port).await?;
+    let request = CodeRequest { code: CodeClaim { nameplate: plate, pake: spake.message(), compression: compression::advertised_algos() } };
+    timeout(IO_TIMEOUT, write_frame(&mut stream, &serde_json::to_vec(&request)?)).await.context("Code request timeout")??;
+    let challenge: Challenge = serde_json::from_slice(&read_frame(&mut stream).await?).context("Invalid code challenge")?;
+    let Some(sender_pake) = challenge.pake else {
+        bail!(DropTeaError::Rejected(challenge.error.unwrap_or_else(|| UNKNOWN_CODE.to_string())));
+    };
+    let key = spake.finish(plate, &sender_pake).ok_or_else(|| DropTeaError::Protocol("Invalid code exchange".into()))?;
+    let mine = Proof { proof: proof(&key, "receiver") };
+    timeout(IO_TIMEOUT, write_frame(&mut stream, &serde_json::to_vec(&mine)?)).await.context("Code proof timeout")??;
+    let answer: Answer = serde_json::from_slice(&read_frame(&mut stream).await?).context("Invalid code answer")?;
+    if !answer.ok {
+        bail!(DropTeaError::Rejected(answer.error.unwrap_or_else(|| REJECT_WRONG_CODE.to_string())));
+    }
+    // ผู้ส่งต้องรู้ Code ด้วย ไม่ใช่แค่ประกาศ Nameplate เดียวกัน
+    if answer.proof.as_deref() != Some(proof(&key, "sender").as_str()) {
+        bail!(DropTeaError::Protocol("Sender could not prove the code".into()));
+    }
+    let filename = answer.filename.ok_or_else(|| DropTeaError::Protocol("Missing filename in code answer".into()))?;
+    Ok((stream, filename))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_accepts_spaces_and_case() {
+        assert_eq!(normalize("42 Tiger  lemon"), "42-tiger-lemon");
+        assert_eq!(normalize(" 42--tiger-LEMON "), "42-tiger-lemon");
+    }
+
+    #[test]
+    fn nameplate_checks_shape_and_words() {
+        assert_eq!(nameplate("42-tiger-lemon"), Some(42));
+        assert_eq!(nameplate("999 Tiger Lemon"), Some(999));
+        assert_eq!(nameplate("0-tiger-lemon"), None);
+        assert_eq!(nameplate("1000-tiger-lemon"), None);
+        assert_eq!(nameplate("42-tiger"), None);
+        assert_eq!(nameplate("42-tiger-lemon-acid"), None);
+        assert_eq!(nameplate("42-tiger-droptea"), None);
+    }
+
+    #[test]
+    fn proof_matches_only_with_the_same_code() {
+        let (receiver, sender) = (Spake::start("42-tiger-lemon", true), Spake::start("42 Tiger Lemon", false));
+        let receiver_key = receiver.finish(42, &sender.message()).unwrap();
+        let sender_key = sender.finish(42, &receiver.message()).unwrap();
+        assert_eq!(proof(&receiver_key, "receiver"), proof(&sender_key, "receiver"));
+        assert_ne!(proof(&sender_key, "receiver"), proof(&sender_key, "sender"));
+
+        let guess = Spake::start("42-tiger-mango", false);
+        let guess_key = guess.finish(42, &receiver.message()).unwrap();
+        assert_ne!(receiver.finish(42, &guess.message()).unwrap(), guess_key);
+        // Nameplate อยู่ใน Key ด้วย
+        assert_ne!(receiver.finish(43, &sender.message()).unwrap(), receiver_key);
+    }
+
+    #[test]
+    fn finish_rejects_malformed_messages() {
+        let spake = Spake::start("42-tiger-lemon", true);
+        assert!(spake.finish(42, "not hex").is_none());
+        assert!(spake.finish(42, &"ff".repeat(32)).is_none());
+    }
+}
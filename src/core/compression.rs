@@ -1,14 +1,16 @@
 use std::io;
+use std::path::Path;
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use tokio::io::{AsyncWrite, AsyncRead, BufReader, ReadBuf};
-use async_compression::tokio::write::{GzipEncoder, ZstdEncoder, ZlibEncoder};
-use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder, ZlibDecoder};
+use tokio::io::{AsyncWrite, AsyncRead, AsyncReadExt, BufReader, ReadBuf};
+use async_compression::tokio::write::{GzipEncoder, ZstdEncoder, ZlibEncoder, Lz4Encoder};
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder, ZlibDecoder, Lz4Decoder};
 use async_compression::Level;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CompressionAlgo {
     Zstd,
+    Lz4, // เบา CPU สำหรับเครื่องสเปคต่ำ
     Gzip,
     Zlib,
     None, // 🔥 โหมดส่งสด
@@ -18,15 +20,17 @@ impl CompressionAlgo {
     pub fn as_str(&self) -> &'static str {
         match self {
             CompressionAlgo::Zstd => "zstd",
+            CompressionAlgo::Lz4 => "lz4",
             CompressionAlgo::Gzip => "gzip",
             CompressionAlgo::Zlib => "zlib",
             CompressionAlgo::None => "none",
         }
     }
 
-    pub fn from_str(s: &str) -> Option<Self> {
+    pub fn from_name(s: &str) -> Option<Self> {
         match s {
             "zstd" => Some(CompressionAlgo::Zstd),
+            "lz4" => Some(CompressionAlgo::Lz4),
             "gzip" => Some(CompressionAlgo::Gzip),
             "zlib" => Some(CompressionAlgo::Zlib),
             "none" => Some(CompressionAlgo::None),
@@ -35,9 +39,85 @@ impl CompressionAlgo {
     }
 }
 
+// 🔥 Capability Negotiation: ลำดับความชอบของเรา (None รองรับเสมอ)
+pub const SUPPORTED_ALGOS: &[CompressionAlgo] = &[
+    CompressionAlgo::Zstd,
+    CompressionAlgo::Lz4,
+    CompressionAlgo::Gzip,
+    CompressionAlgo::Zlib,
+    CompressionAlgo::None,
+];
+
+/// ค่าสำหรับ mDNS TXT "comp" เช่น "zstd,gzip,zlib,none"
+pub fn advertised_algos() -> String {
+    SUPPORTED_ALGOS.iter().map(|a| a.as_str()).collect::<Vec<_>>().join(",")
+}
+
+pub fn parse_algo_list(s: &str) -> Vec<CompressionAlgo> {
+    s.split(',').filter_map(|p| CompressionAlgo::from_name(p.trim())).collect()
+}
+
+/// เลือก Algo ที่ปลายทางรองรับจริง
+/// - รู้ Capability: `preferred` ถ้าปลายทางมี ไม่งั้นตัวแรกตามลำดับของเราที่ปลายทางมี ไม่งั้น None
+/// - ไม่รู้ (Peer รุ่นเก่า/ไม่ผ่าน mDNS): พฤติกรรมเดิม iOS = None, อื่นๆ = Zstd
+pub fn negotiate(peer_algos: Option<&[CompressionAlgo]>, target_os: Option<&str>, preferred: Option<CompressionAlgo>) -> CompressionAlgo {
+    match peer_algos {
+        Some(theirs) => preferred
+            .filter(|p| theirs.contains(p))
+            .or_else(|| SUPPORTED_ALGOS.iter().copied().find(|a| theirs.contains(a)))
+            .unwrap_or(CompressionAlgo::None),
+        None => match (target_os, preferred) {
+            (_, Some(CompressionAlgo::None)) | (Some("ios"), _) => CompressionAlgo::None,
+            _ => CompressionAlgo::Zstd,
+        },
+    }
+}
+
+// 🔥 Content-aware: ไฟล์ที่บีบอัดมาแล้ว zstd ซ้ำเปลือง CPU และทำให้ Wi-Fi ช้าลง
+const INCOMPRESSIBLE_EXTENSIONS: &[&str] = &[
+    // Video / Audio
+    "mp4", "m4v", "mkv", "mov", "avi", "webm", "mp3", "m4a", "aac", "ogg", "opus", "flac",
+    // Image
+    "jpg", "jpeg", "png", "gif", "webp", "heic", "heif", "avif",
+    // Archive / Package (รวม Office ที่เป็น zip ข้างใน)
+    "zip", "gz", "tgz", "bz2", "xz", "zst", "lz4", "7z", "rar", "br",
+    "apk", "ipa", "jar", "docx", "xlsx", "pptx",
+];
+const ENTROPY_SAMPLE_SIZE: usize = 1024 * 1024;
+const ENTROPY_THRESHOLD: f64 = 7.5; // bits/byte (สูงสุด 8.0 = สุ่มล้วน)
+
+fn has_incompressible_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| INCOMPRESSIBLE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+fn shannon_entropy(sample: &[u8]) -> f64 {
+    if sample.is_empty() { return 0.0; }
+    let mut counts = [0u64; 256];
+    for &b in sample { counts[b as usize] += 1; }
+    let len = sample.len() as f64;
+    counts.iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| { let p = c as f64 / len; -p * p.log2() })
+        .sum()
+}
+
+/// เช็คนามสกุลก่อน ถ้าไม่รู้จักค่อยสุ่มอ่าน 1MB แรกมาวัด Entropy
+pub async fn looks_incompressible(path: &Path) -> bool {
+    if has_incompressible_extension(path) { return true; }
+
+    let mut file = match tokio::fs::File::open(path).await { Ok(f) => f, Err(_) => return false };
+    let mut sample = Vec::with_capacity(ENTROPY_SAMPLE_SIZE);
+    if (&mut file).take(ENTROPY_SAMPLE_SIZE as u64).read_to_end(&mut sample).await.is_err() { return false; }
+    shannon_entropy(&sample) >= ENTROPY_THRESHOLD
+}
+
 // Wrapper Writer
 pub enum Compressor<W: AsyncWrite + Unpin> {
     Zstd(ZstdEncoder<W>),
+    Lz4(Lz4Encoder<W>),
     Gzip(GzipEncoder<W>),
     Zlib(ZlibEncoder<W>),
     None(W), // Passthrough
@@ -45,19 +125,37 @@ pub enum Compressor<W: AsyncWrite + Unpin> {
 
 impl<W: AsyncWrite + Unpin> Compressor<W> {
     pub fn new(writer: W, algo: CompressionAlgo) -> Self {
+        Self::with_level(writer, algo, None)
+    }
+
+    /// `level` = ค่าของแต่ละ Algo (zstd 1-22, lz4 0-12, gzip/zlib 0-9) เกินช่วงจะถูก Clamp
+    /// ไม่ระบุ = zstd Fastest, อื่นๆ ใช้ค่า Default ของ Algo
+    pub fn with_level(writer: W, algo: CompressionAlgo, level: Option<i32>) -> Self {
         match algo {
-            CompressionAlgo::Zstd => Compressor::Zstd(ZstdEncoder::with_quality(writer, Level::Fastest)),
-            CompressionAlgo::Gzip => Compressor::Gzip(GzipEncoder::new(writer)),
-            CompressionAlgo::Zlib => Compressor::Zlib(ZlibEncoder::new(writer)),
+            CompressionAlgo::Zstd => Compressor::Zstd(ZstdEncoder::with_quality(writer, level.map(Level::Precise).unwrap_or(Level::Fastest))),
+            CompressionAlgo::Lz4 => Compressor::Lz4(Lz4Encoder::with_quality(writer, level.map(Level::Precise).unwrap_or(Level::Default))),
+            CompressionAlgo::Gzip => Compressor::Gzip(GzipEncoder::with_quality(writer, level.map(Level::Precise).unwrap_or(Level::Default))),
+            CompressionAlgo::Zlib => Compressor::Zlib(ZlibEncoder::with_quality(writer, level.map(Level::Precise).unwrap_or(Level::Default))),
             CompressionAlgo::None => Compressor::None(writer),
         }
     }
+
+    pub fn into_inner(self) -> W {
+        match self {
+            Compressor::Zstd(e) => e.into_inner(),
+            Compressor::Lz4(e) => e.into_inner(),
+            Compressor::Gzip(e) => e.into_inner(),
+            Compressor::Zlib(e) => e.into_inner(),
+            Compressor::None(w) => w,
+        }
+    }
 }
 
 impl<W: AsyncWrite + Unpin> AsyncWrite for Compressor<W> {
     fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
         match &mut *self {
             Compressor::Zstd(inner) => Pin::new(inner).poll_write(cx, buf),
+            Compressor::Lz4(inner) => Pin::new(inner).poll_write(cx, buf),
             Compressor::Gzip(inner) => Pin::new(inner).poll_write(cx, buf),
             Compressor::Zlib(inner) => Pin::new(inner).poll_write(cx, buf),
             Compressor::None(inner) => Pin::new(inner).poll_write(cx, buf),
@@ -67,6 +165,7 @@ impl<W: AsyncWrite + Unpin> AsyncWrite for Compressor<W> {
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
         match &mut *self {
             Compressor::Zstd(inner) => Pin::new(inner).poll_flush(cx),
+            Compressor::Lz4(inner) => Pin::new(inner).poll_flush(cx),
             Compressor::Gzip(inner) => Pin::new(inner).poll_flush(cx),
             Compressor::Zlib(inner) => Pin::new(inner).poll_flush(cx),
             Compressor::None(inner) => Pin::new(inner).poll_flush(cx),
@@ -76,6 +175,7 @@ impl<W: AsyncWrite + Unpin> AsyncWrite for Compressor<W> {
     fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
         match &mut *self {
             Compressor::Zstd(inner) => Pin::new(inner).poll_shutdown(cx),
+            Compressor::Lz4(inner) => Pin::new(inner).poll_shutdown(cx),
             Compressor::Gzip(inner) => Pin::new(inner).poll_shutdown(cx),
             Compressor::Zlib(inner) => Pin::new(inner).poll_shutdown(cx),
             Compressor::None(inner) => Pin::new(inner).poll_shutdown(cx),
@@ -86,6 +186,7 @@ impl<W: AsyncWrite + Unpin> AsyncWrite for Compressor<W> {
 // Wrapper Reader
 pub enum Decompressor<R: AsyncRead + Unpin> {
     Zstd(ZstdDecoder<BufReader<R>>),
+    Lz4(Lz4Decoder<BufReader<R>>),
     Gzip(GzipDecoder<BufReader<R>>),
     Zlib(ZlibDecoder<BufReader<R>>),
     None(BufReader<R>),
@@ -96,6 +197,7 @@ impl<R: AsyncRead + Unpin> Decompressor<R> {
         let buf_reader = BufReader::new(reader);
         match algo {
             CompressionAlgo::Zstd => Decompressor::Zstd(ZstdDecoder::new(buf_reader)),
+            CompressionAlgo::Lz4 => Decompressor::Lz4(Lz4Decoder::new(buf_reader)),
             CompressionAlgo::Gzip => Decompressor::Gzip(GzipDecoder::new(buf_reader)),
             CompressionAlgo::Zlib => Decompressor::Zlib(ZlibDecoder::new(buf_reader)),
             CompressionAlgo::None => Decompressor::None(buf_reader),
@@ -107,6 +209,7 @@ impl<R: AsyncRead + Unpin> AsyncRead for Decompressor<R> {
     fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
         match &mut *self {
             Decompressor::Zstd(inner) => Pin::new(inner).poll_read(cx, buf),
+            Decompressor::Lz4(inner) => Pin::new(inner).poll_read(cx, buf),
             Decompressor::Gzip(inner) => Pin::new(inner).poll_read(cx, buf),
             Decompressor::Zlib(inner) => Pin::new(inner).poll_read(cx, buf),
             Decompressor::None(inner) => Pin::new(inner).poll_read(cx, buf),
@@ -1,6 +1,28 @@
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
-use crate::core::engine::TransportMode;
+use anyhow::Context;
+use crate::core::engine::{RetryPolicy, TransportMode, DEFAULT_MAX_INCOMING, DEFAULT_MAX_OUTGOING, DOWNLOAD_DIR};
+use crate::core::archive::ArchiveMode;
+use crate::core::io_priority::IoPriority;
+use crate::core::webhook::ApprovalWebhook;
+use crate::core::hooks::{CommandHook, DEFAULT_HOOK_TIMEOUT};
+use crate::core::compression::CompressionAlgo;
+use crate::core::ble_transfer::DEFAULT_BLE_MAX_FILE_SIZE;
+use crate::core::shares::{ShareOptions, SharedFolder, MAX_SHARE_ENTRIES};
+use crate::core::peer_prefs::PeerOverride;
+use crate::core::save_rules::{ConflictPolicy, SaveRule};
+use crate::core::reputation::ReputationConfig;
+use crate::core::transfer::Timeouts;
+use crate::core::discovery::{DeviceType, HealthCheck};
+use crate::core::discovery::privacy::DEFAULT_ROTATE_INTERVAL;
+use crate::core::discovery::broadcast::DEFAULT_BROADCAST_PORT;
+use crate::core::links::{self, LinkConfig};
+use crate::core::localsend::{self, LocalSendConfig};
+use crate::core::parallel::{ParallelPolicy, MAX_STREAMS};
+use crate::core::transports::quic::{CongestionControl, QuicConfig};
+use crate::core::transports::custom;
+use std::time::Duration;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct AppConfig {
@@ -8,6 +30,39 @@ pub struct AppConfig {
     pub storage: StorageConfig,
     #[serde(default)] 
     pub dev: Option<DevConfig>,
+    #[serde(default)]
+    pub approval: Option<ApprovalConfig>,
+    #[serde(default)]
+    pub hooks: Option<HooksConfig>,
+    #[serde(default)]
+    pub admin: Option<AdminConfig>,
+    #[serde(default)]
+    pub rendezvous: Option<RendezvousConfig>,
+    #[serde(default)]
+    pub shares: Vec<ShareConfig>,
+    #[serde(default)]
+    pub sharing: Option<SharesConfig>,
+    #[serde(default)]
+    pub save_rules: Vec<SaveRuleConfig>,
+    #[serde(default)]
+    pub reputation: Option<ReputationFileConfig>,
+    #[serde(default)]
+    pub timeouts: Option<TimeoutsConfig>,
+    #[serde(default)]
+    pub retry: Option<RetryConfig>,
+    #[serde(default)]
+    pub discovery: Option<DiscoveryConfig>,
+    #[serde(default)]
+    pub localsend: Option<LocalSendFileConfig>,
+    #[serde(default)]
+    pub links: Option<LinksFileConfig>,
+    #[serde(default)]
+    pub quic: Option<QuicFileConfig>,
+    #[serde(default)]
+    pub tcp: Option<ParallelFileConfig>,
+    // Key = Fingerprint / Peer ID / ชื่อเครื่อง / IP
+    #[serde(default)]
+    pub peers: HashMap<String, PeerConfig>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -19,6 +74,45 @@ pub struct ServerConfig {
     
     // 🟢 UPDATED: รับค่า node_name จาก Config (Optional)
     pub node_name: Option<String>,
+
+    // Algo ที่อยากใช้ก่อน (ถ้าปลายทางรองรับ) เช่น "lz4" สำหรับเครื่อง CPU ต่ำ
+    pub compression: Option<String>,
+    pub compression_level: Option<i32>,
+
+    // ใช้กับ mode = "webrtc" เช่น ["stun:stun.l.google.com:19302"]
+    #[serde(default)]
+    pub ice_servers: Option<Vec<String>>,
+
+    // ขอ Port Forward จาก Router เอง (NAT-PMP / UPnP) ปิดไว้เป็น Default
+    #[serde(default)]
+    pub port_mapping: bool,
+
+    // Address ของเครื่องเราบน Hotspot ที่แชร์อยู่ (ไม่ระบุ = หาเองจาก Interface)
+    pub hotspot_gateway: Option<String>,
+
+    // วัด Disk/Compression ตอนเริ่มแล้วปรับ Limit ให้เหมาะกับเครื่อง
+    #[serde(default)]
+    pub auto_tune: bool,
+
+    // Path ของ droptea-sandbox -> แตก Compression ของไฟล์ที่รับในโปรเซสแยกสิทธิ์ต่ำ
+    pub sandbox_helper: Option<String>,
+
+    // Peer ที่เห็นแค่ทาง BLE: ส่งไฟล์ไม่เกินขนาดนี้ (byte) ผ่าน GATT ได้ 0 = ปิด
+    pub ble_max_file_size: Option<u64>,
+
+    // ใช้เครื่องคนอื่นชั่วคราว: Cert สุ่มใน Memory ไม่แตะ known_hosts / Whitelist ของเจ้าของเครื่อง
+    #[serde(default)]
+    pub guest: bool,
+
+    // ชนิดเครื่องที่ประกาศใน mDNS: "phone", "laptop", "desktop", "server" (ไม่ระบุ = เดาเอง)
+    pub device_type: Option<String>,
+
+    // จำนวน Transfer ที่รับ / ส่งพร้อมกันได้ (ไม่ระบุ = 5 / 50)
+    pub max_incoming: Option<usize>,
+    pub max_outgoing: Option<usize>,
+
+    // จำกัดความเร็ว (byte/s) ต่อ Transfer ที่ [peers.*] ไม่ได้ตั้งไว้ แก้ระหว่างรันได้
+    pub max_bytes_per_sec: Option<u64>,
 }
 
 fn default_mode() -> String { "tcp".to_string() }
@@ -27,6 +121,33 @@ fn default_mode() -> String { "tcp".to_string() }
 pub struct StorageConfig {
     pub save_path: String,
     pub temp_path: String,
+    // โฟลเดอร์ที่ไฟล์ที่รับลง (ไม่ใส่ = ./downloads) แก้ระหว่างรันได้ ส่วน save_path (Cert / known_hosts) ต้อง Restart
+    #[serde(default)]
+    pub download_dir: Option<String>,
+    // รวมไฟล์ที่รับเข้า Archive เดียวต่อ Session: "off" (default), "zip", "tar.zst"
+    #[serde(default)]
+    pub archive: Option<String>,
+    // "normal" (default) หรือ "background" = Disk I/O ระดับต่ำ (ionice idle / Background Mode)
+    #[serde(default)]
+    pub io_priority: Option<String>,
+    // ไฟล์ที่ส่งขนาดนี้ขึ้นไป (MB) อ่านผ่าน mmap แทน read() (ไม่ใส่ / 0 = ปิด)
+    #[serde(default)]
+    pub mmap_threshold_mb: Option<u64>,
+    // เก็บ Chunk Index ของไฟล์ที่รับไว้ ให้ไฟล์ที่คล้ายของเดิมส่งเฉพาะส่วนที่เปลี่ยน
+    #[serde(default)]
+    pub dedup: bool,
+    // เก็บเนื้อ Chunk ไว้แยกได้ไม่เกินกี่ MB (ไม่ใส่ / 0 = ปิด) ไฟล์เดิมถูกลบ / เขียนทับแล้วก็ยัง Dedup ได้
+    #[serde(default)]
+    pub chunk_store_mb: Option<u64>,
+    // ไฟล์ที่มีอยู่แล้วในโฟลเดอร์ปลายทาง (ขนาด + quick_hash ตรง) ข้ามไปเลยไม่ต้องถาม
+    #[serde(default)]
+    pub skip_duplicates: bool,
+    // หลายไฟล์พร้อมกันจากผู้ส่งเดียวกัน = Session เดียว รับทีละไฟล์ตามลำดับ
+    #[serde(default)]
+    pub sender_queue: bool,
+    // เก็บคิวส่งที่ยังไม่จบไว้ใน outbox.json -> เปิดแอปใหม่แล้วส่งต่อให้เอง (Default เปิด)
+    #[serde(default)]
+    pub persist_outbox: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -34,28 +155,472 @@ pub struct DevConfig {
     pub enabled: bool,
 }
 
+// Webhook อนุมัติการรับไฟล์ (สำหรับเครื่องที่ไม่มีคนเฝ้า)
+#[derive(Debug, Deserialize, Clone)]
+pub struct ApprovalConfig {
+    pub webhook_url: String,
+    #[serde(default = "default_webhook_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_webhook_timeout() -> u64 { 10 }
+
+// คำสั่งที่รันหลังรับไฟล์ครบ (argv ไม่ผ่าน Shell, Placeholder ดู hooks.rs)
+#[derive(Debug, Deserialize, Clone)]
+pub struct HooksConfig {
+    #[serde(default)]
+    pub post_receive: Vec<String>,
+    pub timeout_secs: Option<u64>,
+}
+
+// Reputation ของไฟล์ที่ส่งมา (db = ไฟล์ Hash ในเครื่อง, url = Service ที่มี {sha256} ใน Path)
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReputationFileConfig {
+    pub db: Option<String>,
+    pub url: Option<String>,
+    pub timeout_secs: Option<u64>,
+}
+
+// ไม่ระบุ = ค่า Default ใน transfer.rs / discovery (Network ช้าหรือผู้ใช้ตอบช้าค่อยเพิ่ม)
+#[derive(Debug, Deserialize, Clone)]
+pub struct TimeoutsConfig {
+    pub io_secs: Option<u64>,
+    pub user_decision_secs: Option<u64>,
+    pub stall_secs: Option<u64>,
+    pub health_check_interval_secs: Option<u64>,
+    pub peer_stale_secs: Option<u64>,
+}
+
+impl TimeoutsConfig {
+    fn timeouts(&self) -> Timeouts {
+        let default = Timeouts::default();
+        Timeouts {
+            io: self.io_secs.map_or(default.io, Duration::from_secs),
+            user_decision: self.user_decision_secs.map_or(default.user_decision, Duration::from_secs),
+            stall: self.stall_secs.map_or(default.stall, Duration::from_secs),
+        }
+    }
+
+    fn health_check(&self) -> HealthCheck {
+        let default = HealthCheck::default();
+        HealthCheck {
+            // 0 = Loop ไม่หยุด -> อย่างน้อย 1 วินาที
+            interval: self.health_check_interval_secs.map_or(default.interval, |s| Duration::from_secs(s.max(1))),
+            stale_after: self.peer_stale_secs.map_or(default.stale_after, Duration::from_secs),
+        }
+    }
+}
+
+// ไม่ระบุ = RetryPolicy::default()
+#[derive(Debug, Deserialize, Clone)]
+pub struct RetryConfig {
+    pub attempts: Option<u32>,
+    pub backoff_ms: Option<u64>,
+    pub max_backoff_ms: Option<u64>,
+    pub jitter: Option<f64>,
+}
+
+impl RetryConfig {
+    fn policy(&self) -> RetryPolicy {
+        let default = RetryPolicy::default();
+        RetryPolicy {
+            // 0 = ไม่ส่งเลย -> อย่างน้อย 1 ครั้ง
+            attempts: self.attempts.map_or(default.attempts, |a| a.max(1)),
+            backoff: self.backoff_ms.map_or(default.backoff, Duration::from_millis),
+            max_backoff: self.max_backoff_ms.map_or(default.max_backoff, Duration::from_millis),
+            jitter: self.jitter.map_or(default.jitter, |j| j.clamp(0.0, 1.0)),
+        }
+    }
+}
+
+// ไฟล์ใหญ่แบ่งส่งหลาย Stream ([quic] ไม่ระบุ = 4 Stream) / หลาย Connection ([tcp] ไม่ระบุ = ปิด) parallel_streams <= 1 = ปิด
+#[derive(Debug, Deserialize, Clone)]
+pub struct ParallelFileConfig {
+    #[serde(alias = "parallel_connections")]
+    pub parallel_streams: Option<u32>,
+    pub parallel_threshold_mb: Option<u64>,
+}
+
+impl ParallelFileConfig {
+    fn policy(&self, default: ParallelPolicy) -> ParallelPolicy {
+        ParallelPolicy {
+            streams: self.parallel_streams.map_or(default.streams, |s| s.min(MAX_STREAMS)),
+            threshold: self.parallel_threshold_mb.map_or(default.threshold, |mb| mb.saturating_mul(1024 * 1024)),
+        }
+    }
+}
+
+// Transport ของ mode = "quic" (ไม่ระบุ = ค่าเดิมของ QuicConfig) congestion = "cubic" / "newreno" / "bbr"
+#[derive(Debug, Deserialize, Clone)]
+pub struct QuicFileConfig {
+    #[serde(flatten)]
+    pub parallel: ParallelFileConfig,
+    pub congestion: Option<String>,
+    pub initial_window_kb: Option<u64>,
+    pub initial_rtt_ms: Option<u64>,
+    pub segmentation_offload: Option<bool>,
+    pub stream_window_mb: Option<u64>,
+    pub connection_window_mb: Option<u64>,
+    pub keep_alive_secs: Option<u64>,
+    pub idle_timeout_secs: Option<u64>,
+}
+
+impl QuicFileConfig {
+    // ชื่อที่ไม่รู้จัก = Cubic (แบบเดียวกับ [server] compression)
+    fn quic(&self) -> QuicConfig {
+        let default = QuicConfig::default();
+        let mb = |v: u64| v.saturating_mul(1024 * 1024);
+        QuicConfig {
+            stream_window_size: self.stream_window_mb.map_or(default.stream_window_size, mb),
+            connection_window_size: self.connection_window_mb.map_or(default.connection_window_size, mb),
+            keep_alive_interval: self.keep_alive_secs.map_or(default.keep_alive_interval, |s| Duration::from_secs(s.max(1))),
+            max_idle_timeout: self.idle_timeout_secs.map_or(default.max_idle_timeout, |s| Duration::from_secs(s.max(1))),
+            congestion: self.congestion.as_deref().and_then(|c| CongestionControl::from_name(&c.to_lowercase())).unwrap_or_default(),
+            initial_window: self.initial_window_kb.map(|kb| kb.saturating_mul(1024)),
+            initial_rtt: self.initial_rtt_ms.map(|ms| Duration::from_millis(ms.max(1))),
+            segmentation_offload: self.segmentation_offload.unwrap_or(default.segmentation_offload),
+            ..default
+        }
+    }
+}
+
+// ประกาศตัวบน LAN (privacy = ซ่อนชื่อเครื่องใน mDNS ด้วย Token ที่เปลี่ยนทุก rotate_secs)
+// backends = ["mdns", "ble", "manual", "broadcast", <ชื่อที่ลงไว้>] (ไม่ระบุ = mdns + ble + manual ถ้ามี)
+#[derive(Debug, Deserialize, Clone)]
+pub struct DiscoveryConfig {
+    #[serde(default)]
+    pub privacy: bool,
+    pub rotate_secs: Option<u64>,
+    #[serde(default)]
+    pub backends: Vec<String>,
+    // "host:port" ของ Peer ที่ค้นหาไม่เจอ (ต่าง Subnet / VPN)
+    #[serde(default)]
+    pub manual: Vec<String>,
+    pub broadcast_port: Option<u16>,
+}
+
+impl DiscoveryConfig {
+    fn privacy(&self) -> Option<Duration> {
+        // 0 = เปลี่ยนทุกรอบประกาศ -> อย่างน้อย 1 นาที
+        self.privacy.then(|| self.rotate_secs.map_or(DEFAULT_ROTATE_INTERVAL, |s| Duration::from_secs(s.max(60))))
+    }
+}
+
+// คุยกับแอป LocalSend (port = ทั้ง Multicast และ HTTP API, https = false สำหรับเครื่องที่ปิด Encryption ไว้)
+#[derive(Debug, Deserialize, Clone)]
+pub struct LocalSendFileConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    pub port: Option<u16>,
+    pub https: Option<bool>,
+}
+
+// Link ให้ Browser โหลดไฟล์ (host = IP / ชื่อที่ใส่ใน Link เมื่อเดาจาก Default Route ผิด)
+#[derive(Debug, Deserialize, Clone)]
+pub struct LinksFileConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    pub port: Option<u16>,
+    pub host: Option<String>,
+}
+
+// NAT Traversal ของ QUIC (server = ใช้ของคนอื่น, listen = เป็น Server เอง)
+#[derive(Debug, Deserialize, Clone)]
+pub struct RendezvousConfig {
+    pub server: Option<String>,
+    pub listen: Option<String>,
+}
+
+// โฟลเดอร์ที่ให้ Peer Browse/Pull ได้ (Read-only) สิทธิ์ราย Fingerprint จัดการผ่าน grant_share / revoke_share
+#[derive(Debug, Deserialize, Clone)]
+pub struct ShareConfig {
+    pub name: String,
+    pub path: String,
+}
+
+// ค่ารวมของทุก Share: hashes = แนบ quick_hash ในผล Browse (Default เปิด), max_entries = รายการต่อหนึ่ง Browse
+#[derive(Debug, Deserialize, Clone)]
+pub struct SharesConfig {
+    pub hashes: Option<bool>,
+    pub max_entries: Option<usize>,
+}
+
+impl SharesConfig {
+    fn options(&self) -> ShareOptions {
+        let default = ShareOptions::default();
+        ShareOptions {
+            hashes: self.hashes.unwrap_or(default.hashes),
+            // Browse ต้องตอบได้ใน Frame เดียว
+            max_entries: self.max_entries.unwrap_or(default.max_entries).clamp(1, MAX_SHARE_ENTRIES),
+        }
+    }
+}
+
+// โฟลเดอร์ปลายทางตามชนิดไฟล์ match = ชื่อกลุ่ม / ".นามสกุล" / MIME, on_conflict = "unique" (default) / "overwrite"
+#[derive(Debug, Deserialize, Clone)]
+pub struct SaveRuleConfig {
+    #[serde(rename = "match")]
+    pub matchers: Vec<String>,
+    pub dest: String,
+    pub on_conflict: Option<String>,
+}
+
+// ค่าเฉพาะ Peer: transport = "plaintcp" / mode ของเรา, compression = ชื่อ Algo หรือ "none" = ไม่บีบ
+#[derive(Debug, Deserialize, Clone)]
+pub struct PeerConfig {
+    pub transport: Option<String>,
+    pub compression: Option<String>,
+    pub max_bytes_per_sec: Option<u64>,
+    pub auto_accept: Option<bool>,
+}
+
+impl PeerConfig {
+    // ชื่อที่ไม่รู้จัก = ไม่ Override (แบบเดียวกับ [server] compression)
+    fn to_override(&self) -> PeerOverride {
+        PeerOverride {
+            transport: self.transport.as_deref().and_then(|t| TransportMode::from_name(&t.to_lowercase())),
+            compression: self.compression.as_deref().and_then(|c| CompressionAlgo::from_name(&c.to_lowercase())),
+            max_bytes_per_sec: self.max_bytes_per_sec,
+            auto_accept: self.auto_accept,
+        }
+    }
+}
+
+// Peer ที่สั่งงานเครื่องนี้ได้ (อ้างอิงด้วย Certificate Fingerprint)
+#[derive(Debug, Deserialize, Clone)]
+pub struct AdminConfig {
+    #[serde(default)]
+    pub fingerprints: Vec<String>,
+}
+
+// ==========================================
+// Override จาก Environment / Command Line (Container / Headless ไม่ต้องมีไฟล์ Config)
+// ลำดับ: ไฟล์ (หรือ ENV_BASE ถ้าไม่มีไฟล์) -> DROPTEA_* -> --flag (ตัวหลังชนะ)
+// ชื่อ Flag มาจากชื่อ Env: DROPTEA_SAVE_PATH -> --save-path (ใช้ --save-path=x หรือ --save-path x ก็ได้)
+// ==========================================
+
+#[derive(Debug, Clone, Copy)]
+enum Kind { Str, Int, Bool, List }
+
+// (Env, Key ใน TOML, ชนิด) List = คั่นด้วย ","
+const OVERRIDES: &[(&str, &str, Kind)] = &[
+    ("DROPTEA_PORT", "server.port", Kind::Int),
+    ("DROPTEA_MODE", "server.mode", Kind::Str),
+    ("DROPTEA_NODE_NAME", "server.node_name", Kind::Str),
+    ("DROPTEA_COMPRESSION", "server.compression", Kind::Str),
+    ("DROPTEA_COMPRESSION_LEVEL", "server.compression_level", Kind::Int),
+    ("DROPTEA_ICE_SERVERS", "server.ice_servers", Kind::List),
+    ("DROPTEA_PORT_MAPPING", "server.port_mapping", Kind::Bool),
+    ("DROPTEA_HOTSPOT_GATEWAY", "server.hotspot_gateway", Kind::Str),
+    ("DROPTEA_AUTO_TUNE", "server.auto_tune", Kind::Bool),
+    ("DROPTEA_SANDBOX_HELPER", "server.sandbox_helper", Kind::Str),
+    ("DROPTEA_GUEST", "server.guest", Kind::Bool),
+    ("DROPTEA_DEVICE_TYPE", "server.device_type", Kind::Str),
+    ("DROPTEA_MAX_INCOMING", "server.max_incoming", Kind::Int),
+    ("DROPTEA_MAX_OUTGOING", "server.max_outgoing", Kind::Int),
+    ("DROPTEA_MAX_BYTES_PER_SEC", "server.max_bytes_per_sec", Kind::Int),
+    ("DROPTEA_SAVE_PATH", "storage.save_path", Kind::Str),
+    ("DROPTEA_TEMP_PATH", "storage.temp_path", Kind::Str),
+    ("DROPTEA_DOWNLOAD_DIR", "storage.download_dir", Kind::Str),
+    ("DROPTEA_ARCHIVE", "storage.archive", Kind::Str),
+    ("DROPTEA_IO_PRIORITY", "storage.io_priority", Kind::Str),
+    ("DROPTEA_MMAP_THRESHOLD_MB", "storage.mmap_threshold_mb", Kind::Int),
+    ("DROPTEA_DEDUP", "storage.dedup", Kind::Bool),
+    ("DROPTEA_CHUNK_STORE_MB", "storage.chunk_store_mb", Kind::Int),
+    ("DROPTEA_SKIP_DUPLICATES", "storage.skip_duplicates", Kind::Bool),
+    ("DROPTEA_SENDER_QUEUE", "storage.sender_queue", Kind::Bool),
+    ("DROPTEA_PERSIST_OUTBOX", "storage.persist_outbox", Kind::Bool),
+    ("DROPTEA_DEV", "dev.enabled", Kind::Bool),
+    ("DROPTEA_APPROVAL_WEBHOOK", "approval.webhook_url", Kind::Str),
+    ("DROPTEA_POST_RECEIVE", "hooks.post_receive", Kind::List),
+    ("DROPTEA_ADMIN_FINGERPRINTS", "admin.fingerprints", Kind::List),
+    ("DROPTEA_RENDEZVOUS_SERVER", "rendezvous.server", Kind::Str),
+    ("DROPTEA_RENDEZVOUS_LISTEN", "rendezvous.listen", Kind::Str),
+    ("DROPTEA_DISCOVERY_PRIVACY", "discovery.privacy", Kind::Bool),
+    ("DROPTEA_DISCOVERY_BACKENDS", "discovery.backends", Kind::List),
+    ("DROPTEA_MANUAL_PEERS", "discovery.manual", Kind::List),
+    ("DROPTEA_LOCALSEND", "localsend.enabled", Kind::Bool),
+    ("DROPTEA_LOCALSEND_PORT", "localsend.port", Kind::Int),
+    ("DROPTEA_LINKS", "links.enabled", Kind::Bool),
+    ("DROPTEA_LINKS_PORT", "links.port", Kind::Int),
+];
+
+// Field ที่ไฟล์ต้องมี -> ค่าตั้งต้นเมื่อไม่มีไฟล์ (Port เดียวกับ config.toml ตัวอย่าง)
+const ENV_BASE: &str = r#"
+[server]
+port = 8080
+buffer_size = 65536
+
+[storage]
+save_path = "."
+temp_path = "."
+"#;
+
+fn flag_name(env: &str) -> String {
+    format!("--{}", env.trim_start_matches("DROPTEA_").to_lowercase().replace('_', "-"))
+}
+
+fn parse_override(source: &str, raw: &str, kind: Kind) -> anyhow::Result<toml::Value> {
+    let raw = raw.trim();
+    Ok(match kind {
+        Kind::Str => toml::Value::String(raw.to_string()),
+        Kind::Int => toml::Value::Integer(raw.parse().map_err(|_| anyhow::anyhow!("{}: expected a number, got '{}'", source, raw))?),
+        Kind::Bool => toml::Value::Boolean(match raw.to_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => true,
+            "0" | "false" | "no" | "off" => false,
+            _ => anyhow::bail!("{}: expected true/false, got '{}'", source, raw),
+        }),
+        Kind::List => toml::Value::Array(raw.split(',').map(str::trim).filter(|s| !s.is_empty()).map(|s| toml::Value::String(s.to_string())).collect()),
+    })
+}
+
+// สร้าง Table ที่ยังไม่มีให้ระหว่างทาง (เช่น [dev] ไม่อยู่ในไฟล์)
+fn set_key(root: &mut toml::Value, key: &str, value: toml::Value) -> anyhow::Result<()> {
+    let (section, field) = key.split_once('.').context("Override key needs a section")?;
+    let table = root.as_table_mut().context("Config root is not a table")?
+        .entry(section).or_insert_with(|| toml::Value::Table(Default::default()))
+        .as_table_mut().with_context(|| format!("[{}] is not a table", section))?;
+    table.insert(field.to_string(), value);
+    Ok(())
+}
+
+fn apply_env(root: &mut toml::Value) -> anyhow::Result<()> {
+    for (env, key, kind) in OVERRIDES {
+        // ค่าว่าง = ไม่ตั้ง (Compose / Kubernetes ชอบส่งตัวแปรว่างมา)
+        let Some(raw) = std::env::var(env).ok().filter(|v| !v.trim().is_empty()) else { continue };
+        set_key(root, key, parse_override(env, &raw, *kind)?)?;
+    }
+    Ok(())
+}
+
+fn apply_args(root: &mut toml::Value, args: &[String]) -> anyhow::Result<()> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        let (env, key, kind) = OVERRIDES.iter().find(|(env, _, _)| flag_name(env) == flag)
+            .with_context(|| format!("Unknown option '{}'", flag))?;
+        let raw = match (inline, kind) {
+            (Some(v), _) => v,
+            // --guest เฉยๆ = เปิด
+            (None, Kind::Bool) if iter.as_slice().first().is_none_or(|next| next.starts_with("--")) => "true".to_string(),
+            (None, _) => iter.next().cloned().with_context(|| format!("{} needs a value", flag))?,
+        };
+        set_key(root, key, parse_override(&flag_name(env), &raw, *kind)?)?;
+    }
+    Ok(())
+}
+
 impl AppConfig {
+    /// ไฟล์ + DROPTEA_* (ตั้งใน Environment ชนะค่าในไฟล์)
     pub fn load_from_file(path: &str) -> anyhow::Result<Self> {
-        let content = fs::read_to_string(path)?;
-        let config: AppConfig = toml::from_str(&content)?;
-        Ok(config)
+        Self::load(Some(path), &[])
+    }
+
+    /// ไม่มีไฟล์: ค่าตั้งต้น (ENV_BASE) + DROPTEA_*
+    pub fn from_env() -> anyhow::Result<Self> {
+        Self::load(None, &[])
+    }
+
+    /// ไฟล์ (None = ENV_BASE) -> DROPTEA_* -> args (เช่น ["--port", "9000", "--mode=quic"])
+    pub fn load(path: Option<&str>, args: &[String]) -> anyhow::Result<Self> {
+        let content = match path {
+            Some(path) => fs::read_to_string(path)?,
+            None => ENV_BASE.to_string(),
+        };
+        let mut root: toml::Value = toml::from_str(&content)?;
+        apply_env(&mut root)?;
+        apply_args(&mut root, args)?;
+        Ok(root.try_into()?)
+    }
+
+    /// Flag ทั้งหมดที่ load รับ (ไว้พิมพ์ --help) คู่กับชื่อ Env
+    pub fn override_flags() -> Vec<(String, &'static str)> {
+        OVERRIDES.iter().map(|(env, _, _)| (flag_name(env), *env)).collect()
     }
 
     // แปลง File Config เป็น Engine Config
     pub fn to_engine_config(&self) -> crate::core::engine::DropTeaConfig {
-        let mode = match self.server.mode.to_lowercase().as_str() {
-            "quic" => TransportMode::Quic,
-            "plaintcp" | "plain_tcp" => TransportMode::PlainTcp,
-            _ => TransportMode::Tcp,
-        };
-        
+        let name = self.server.mode.to_lowercase();
+        let mode = TransportMode::from_name(&name).unwrap_or(TransportMode::Tcp);
+        // ไม่ใช่ Transport ในตัวแต่ลงไว้ด้วย register_transport -> ใช้ตัวนั้น
+        let custom_transport = custom::is_registered(&name).then_some(name);
+
         crate::core::engine::DropTeaConfig {
             mode,
             port: self.server.port,
             storage_path: self.storage.save_path.clone(),
+            download_dir: self.storage.download_dir.clone().unwrap_or_else(|| DOWNLOAD_DIR.to_string()),
+            temp_dir: Some(self.storage.temp_path.clone()).filter(|p| !p.trim().is_empty()),
             // 🟢 UPDATED: ใช้ค่าจาก Config ถ้ามี ถ้าไม่มีให้ใช้ Device Name ของเครื่อง
-            node_name: self.server.node_name.clone().unwrap_or_else(|| whoami::devicename()),
+            node_name: self.server.node_name.clone().unwrap_or_else(whoami::devicename),
             dev_mode: self.dev.as_ref().map(|d| d.enabled).unwrap_or(false),
+            archive_mode: self.storage.archive.as_deref().and_then(ArchiveMode::from_name).unwrap_or(ArchiveMode::Off),
+            custom_transport,
+            approval_webhook: self.approval.as_ref().map(|a| ApprovalWebhook {
+                url: a.webhook_url.clone(),
+                timeout: Duration::from_secs(a.timeout_secs),
+            }),
+            post_receive: self.hooks.as_ref().filter(|h| !h.post_receive.is_empty()).map(|h| CommandHook {
+                argv: h.post_receive.clone(),
+                timeout: h.timeout_secs.map_or(DEFAULT_HOOK_TIMEOUT, Duration::from_secs),
+            }),
+            compression_level: self.server.compression_level,
+            preferred_compression: self.server.compression.as_deref().and_then(|c| CompressionAlgo::from_name(&c.to_lowercase())),
+            ice_servers: self.server.ice_servers.clone(),
+            io_priority: self.storage.io_priority.as_deref().and_then(IoPriority::from_name).unwrap_or_default(),
+            mmap_threshold: self.storage.mmap_threshold_mb.filter(|mb| *mb > 0).map(|mb| mb * 1024 * 1024),
+            rendezvous_server: self.rendezvous.as_ref().and_then(|r| r.server.clone()),
+            rendezvous_listen: self.rendezvous.as_ref().and_then(|r| r.listen.clone()),
+            dedup: self.storage.dedup,
+            chunk_store: self.storage.chunk_store_mb.filter(|mb| *mb > 0).map(|mb| mb * 1024 * 1024),
+            skip_duplicates: self.storage.skip_duplicates,
+            sender_queue: self.storage.sender_queue,
+            persist_outbox: self.storage.persist_outbox.unwrap_or(true),
+            port_mapping: self.server.port_mapping,
+            hotspot_gateway: self.server.hotspot_gateway.as_deref().and_then(|ip| ip.parse().ok()),
+            auto_tune: self.server.auto_tune,
+            sandbox_helper: self.server.sandbox_helper.clone(),
+            reputation: self.reputation.as_ref().map(|r| ReputationConfig {
+                db: r.db.as_ref().map(Into::into),
+                url: r.url.clone(),
+                timeout: r.timeout_secs.map(Duration::from_secs),
+            }),
+            ble_max_file_size: self.server.ble_max_file_size.unwrap_or(DEFAULT_BLE_MAX_FILE_SIZE),
+            admin_fingerprints: self.admin.as_ref().map(|a| a.fingerprints.clone()).unwrap_or_default(),
+            timeouts: self.timeouts.as_ref().map(TimeoutsConfig::timeouts).unwrap_or_default(),
+            health_check: self.timeouts.as_ref().map(TimeoutsConfig::health_check).unwrap_or_default(),
+            retry: self.retry.as_ref().map(RetryConfig::policy).unwrap_or_default(),
+            parallel: self.quic.as_ref().map_or_else(ParallelPolicy::default, |q| q.parallel.policy(ParallelPolicy::default())),
+            tcp_parallel: self.tcp.as_ref().map_or_else(ParallelPolicy::off, |t| t.policy(ParallelPolicy::off())),
+            quic: self.quic.as_ref().map(QuicFileConfig::quic).unwrap_or_default(),
+            discovery_privacy: self.discovery.as_ref().and_then(DiscoveryConfig::privacy),
+            discovery_backends: self.discovery.as_ref().map(|d| d.backends.iter().map(|b| b.to_lowercase()).collect()).unwrap_or_default(),
+            manual_peers: self.discovery.as_ref().map(|d| d.manual.clone()).unwrap_or_default(),
+            broadcast_port: self.discovery.as_ref().and_then(|d| d.broadcast_port).unwrap_or(DEFAULT_BROADCAST_PORT),
+            localsend: self.localsend.as_ref().filter(|l| l.enabled).map(|l| LocalSendConfig {
+                port: l.port.unwrap_or(localsend::DEFAULT_PORT),
+                https: l.https.unwrap_or(true),
+            }),
+            links: self.links.as_ref().filter(|l| l.enabled).map(|l| LinkConfig {
+                port: l.port.unwrap_or(links::DEFAULT_PORT),
+                host: l.host.clone(),
+            }),
+            device_type: self.server.device_type.as_deref().and_then(|d| DeviceType::from_name(&d.to_lowercase())),
+            guest: self.server.guest,
+            max_incoming: self.server.max_incoming.unwrap_or(DEFAULT_MAX_INCOMING),
+            max_outgoing: self.server.max_outgoing.unwrap_or(DEFAULT_MAX_OUTGOING),
+            max_bytes_per_sec: self.server.max_bytes_per_sec,
+            shares: self.shares.iter().map(|s| SharedFolder { name: s.name.clone(), path: s.path.clone().into() }).collect(),
+            share_options: self.sharing.as_ref().map(SharesConfig::options).unwrap_or_default(),
+            save_rules: self.save_rules.iter().map(|r| SaveRule {
+                matchers: r.matchers.clone(),
+                dest: r.dest.clone().into(),
+                conflict: r.on_conflict.as_deref().and_then(ConflictPolicy::from_name).unwrap_or_default(),
+            }).collect(),
+            peers: self.peers.iter().map(|(id, p)| (id.clone(), p.to_override())).collect(),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,305 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{bail, Context};
+use log::{debug, info, warn};
+use serde::{Serialize, Deserialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+use tokio::time::{timeout, Instant};
+
+use crate::core::cancel::{CancellationToken, TransferTokens};
+use crate::core::compression;
+use crate::core::utils;
+
+// ==========================================
+// Control Channel (Port แยกจาก Data)
+// Frame: [u32 len LE][JSON {"type": "PING", ...}] ต่อได้หลาย Request ใน Connection เดียว
+// Port ประกาศผ่าน mDNS TXT "ctl" และแนบใน FileHeader (control_port) ให้ผู้รับตอบกลับได้
+// ไม่มี TLS: คำสั่งอ้างถึง Transfer ที่มีอยู่แล้วเท่านั้น และต้องมาจาก IP ของ Peer ของ Transfer นั้น
+// ==========================================
+
+const MAX_CONTROL_FRAME: usize = 16 * 1024;
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(2);
+// Connection ที่ไม่มี Request เข้ามานานเกินนี้ปิดทิ้ง
+const CONTROL_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// --- Speed Test ---
+// ประกาศใน features ของ mDNS (Peer รุ่นเก่าไม่รู้จัก SPEED_TEST -> ไม่ต้องลอง)
+pub const SPEED_TEST_FEATURE: &str = "speedtest";
+// ฝั่งถูกวัดยอมส่ง/รับแต่ละทิศไม่เกินนี้ และทีละ Test (Control Channel ไม่มี TLS ใครบน LAN ก็ขอได้)
+pub const MAX_SPEED_TEST: Duration = Duration::from_secs(10);
+pub const DEFAULT_SPEED_TEST: Duration = Duration::from_secs(3);
+const SPEED_TEST_CHUNK: usize = 64 * 1024;
+const SPEED_TEST_RTT_SAMPLES: usize = 5;
+// เผื่อ Chunk สุดท้ายที่ยังค้างใน Buffer ตอนหมดเวลา
+const SPEED_TEST_GRACE: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ControlMessage {
+    Ping { nonce: u64 },
+    Pong { nonce: u64 },
+    // transfer_id = Task ID ฝั่งผู้ส่ง (FileHeader.transfer_id)
+    Cancel { transfer_id: String },
+    Pause { transfer_id: String, paused: bool },
+    Caps,
+    Capabilities { version: String, compression: String, features: Vec<String> },
+    Ack { ok: bool },
+    // ACK แล้ว: ผู้ขอส่ง Data Chunk นาน duration_ms -> SPEED_RESULT (ที่ฝั่งนี้นับได้) -> ฝั่งนี้ส่งกลับนานเท่ากัน
+    // Chunk = [u32 len LE][Random Bytes] ปิดท้ายด้วย len = 0
+    SpeedTest { duration_ms: u64 },
+    SpeedResult { bytes: u64, elapsed_ms: u64 },
+}
+
+pub async fn write_frame<W: AsyncWrite + Unpin>(stream: &mut W, msg: &ControlMessage) -> anyhow::Result<()> {
+    let json = serde_json::to_vec(msg)?;
+    stream.write_all(&(json.len() as u32).to_le_bytes()).await?;
+    stream.write_all(&json).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+pub async fn read_frame<R: AsyncRead + Unpin>(stream: &mut R) -> anyhow::Result<ControlMessage> {
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await?;
+    let len = u32::from_le_bytes(len_buf) as usize;
+    if len > MAX_CONTROL_FRAME { bail!("Control frame too large"); }
+    let mut buf = vec![0u8; len];
+    stream.read_exact(&mut buf).await?;
+    serde_json::from_slice(&buf).context("Invalid control message")
+}
+
+// --- ฝั่งรับคำสั่ง ---
+
+pub struct ControlContext {
+    pub transfers: Arc<TransferTokens>,
+    pub features: Vec<String>,
+    // Speed Test ที่กำลังรันอยู่ (Permit เดียว)
+    pub speed_tests: Semaphore,
+}
+
+impl ControlContext {
+    pub fn new(transfers: Arc<TransferTokens>, features: Vec<String>) -> Self {
+        Self { transfers, features, speed_tests: Semaphore::new(1) }
+    }
+}
+
+/// Port ว่างใดก็ได้ (Dual-Stack เหมือน Data Port) Bind ก่อนเข้า Runtime เพื่อรู้ Port ไปประกาศ
+pub fn bind() -> anyhow::Result<std::net::TcpListener> {
+    Ok(utils::bind_dual_stack(0)?)
+}
+
+pub async fn serve(listener: std::net::TcpListener, ctx: Arc<ControlContext>, cancel: CancellationToken) {
+    let listener = match TcpListener::from_std(listener) {
+        Ok(l) => l,
+        Err(e) => { warn!("Control channel unavailable: {}", e); return; }
+    };
+    loop {
+        let accepted = tokio::select! {
+            _ = cancel.cancelled() => break,
+            accepted = listener.accept() => accepted,
+        };
+        match accepted {
+            Ok((stream, addr)) => {
+                let ctx = ctx.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, addr.ip().to_canonical(), &ctx).await {
+                        debug!("Control connection from {} closed: {}", addr, e);
+                    }
+                });
+            }
+            Err(e) => {
+                warn!("Control accept failed: {}", e);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, peer: IpAddr, ctx: &ControlContext) -> anyhow::Result<()> {
+    loop {
+        let msg = match timeout(CONTROL_IDLE_TIMEOUT, read_frame(&mut stream)).await {
+            Ok(Ok(msg)) => msg,
+            Ok(Err(e)) if e.downcast_ref::<std::io::Error>().is_some_and(|io| io.kind() == std::io::ErrorKind::UnexpectedEof) => return Ok(()),
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Ok(()),
+        };
+        if let ControlMessage::SpeedTest { duration_ms } = msg {
+            serve_speed_test(&mut stream, peer, ctx, duration_ms).await?;
+            continue;
+        }
+        let reply = dispatch(msg, peer, ctx);
+        timeout(CONTROL_TIMEOUT, write_frame(&mut stream, &reply)).await.context("Control reply timeout")??;
+    }
+}
+
+async fn serve_speed_test(stream: &mut TcpStream, peer: IpAddr, ctx: &ControlContext, duration_ms: u64) -> anyhow::Result<()> {
+    let duration = Duration::from_millis(duration_ms);
+    let permit = match duration <= MAX_SPEED_TEST {
+        true => ctx.speed_tests.try_acquire().ok(),
+        false => None,
+    };
+    timeout(CONTROL_TIMEOUT, write_frame(stream, &ControlMessage::Ack { ok: permit.is_some() })).await.context("Control reply timeout")??;
+    if permit.is_none() { return Ok(()); }
+    info!("📶 Speed test with {} ({:?} each way)", peer, duration);
+    let (bytes, elapsed) = drain_chunks(stream, duration + SPEED_TEST_GRACE).await?;
+    let result = ControlMessage::SpeedResult { bytes, elapsed_ms: elapsed.as_millis() as u64 };
+    timeout(CONTROL_TIMEOUT, write_frame(stream, &result)).await.context("Control reply timeout")??;
+    stream_chunks(stream, duration).await?;
+    Ok(())
+}
+
+// ส่ง Random Chunk จนครบ duration แล้วปิดด้วย len = 0
+async fn stream_chunks<W: AsyncWrite + Unpin>(stream: &mut W, duration: Duration) -> anyhow::Result<u64> {
+    let mut chunk = vec![0u8; SPEED_TEST_CHUNK];
+    rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut chunk);
+    let deadline = Instant::now() + duration;
+    let mut sent = 0u64;
+    while Instant::now() < deadline {
+        timeout(CONTROL_TIMEOUT, async {
+            stream.write_all(&(chunk.len() as u32).to_le_bytes()).await?;
+            stream.write_all(&chunk).await
+        }).await.context("Speed test write timeout")??;
+        sent += chunk.len() as u64;
+    }
+    stream.write_all(&0u32.to_le_bytes()).await?;
+    stream.flush().await?;
+    Ok(sent)
+}
+
+// นับ Byte จนเจอ len = 0 (เริ่มจับเวลาที่ Chunk แรก) เกิน limit = ฝั่งโน้นส่งนานกว่าที่ตกลง
+async fn drain_chunks<R: AsyncRead + Unpin>(stream: &mut R, limit: Duration) -> anyhow::Result<(u64, Duration)> {
+    let mut buf = vec![0u8; SPEED_TEST_CHUNK];
+    let mut received = 0u64;
+    let mut started: Option<Instant> = None;
+    timeout(limit, async {
+        loop {
+            let mut len_buf = [0u8; 4];
+            timeout(CONTROL_TIMEOUT, stream.read_exact(&mut len_buf)).await.context("Speed test read timeout")??;
+            let len = u32::from_le_bytes(len_buf) as usize;
+            if len == 0 { break; }
+            if len > SPEED_TEST_CHUNK { bail!("Speed test chunk too large"); }
+            started.get_or_insert_with(Instant::now);
+            timeout(CONTROL_TIMEOUT, stream.read_exact(&mut buf[..len])).await.context("Speed test read timeout")??;
+            received += len as u64;
+        }
+        anyhow::Ok(())
+    }).await.context("Speed test ran past its duration")??;
+    Ok((received, started.map_or(Duration::ZERO, |s| s.elapsed())))
+}
+
+fn dispatch(msg: ControlMessage, peer: IpAddr, ctx: &ControlContext) -> ControlMessage {
+    match msg {
+        ControlMessage::Ping { nonce } => ControlMessage::Pong { nonce },
+        ControlMessage::Caps => ControlMessage::Capabilities {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            compression: compression::advertised_algos(),
+            features: ctx.features.clone(),
+        },
+        ControlMessage::Cancel { transfer_id } => {
+            let ok = ctx.transfers.find_remote(peer, &transfer_id).is_some_and(|task_id| ctx.transfers.cancel(&task_id));
+            if ok { info!("🛑 Transfer {} cancelled by {}", transfer_id, peer); }
+            ControlMessage::Ack { ok }
+        }
+        ControlMessage::Pause { transfer_id, paused } => {
+            let ok = ctx.transfers.find_remote(peer, &transfer_id).is_some_and(|task_id| ctx.transfers.set_paused(&task_id, paused));
+            if ok { info!("{} Transfer {} by {}", if paused { "⏸️ Paused" } else { "▶️ Resumed" }, transfer_id, peer); }
+            ControlMessage::Ack { ok }
+        }
+        // handle_connection รับไปทำเองแล้ว (ต้องใช้ Stream ต่อ)
+        ControlMessage::SpeedTest { .. } => ControlMessage::Ack { ok: false },
+        // Reply ที่ถูกส่งมาเป็น Request
+        ControlMessage::Pong { .. } | ControlMessage::Capabilities { .. } | ControlMessage::Ack { .. } | ControlMessage::SpeedResult { .. } => ControlMessage::Ack { ok: false },
+    }
+}
+
+// --- ฝั่งส่งคำสั่ง ---
+
+pub async fn request(addr: SocketAddr, msg: &ControlMessage) -> anyhow::Result<ControlMessage> {
+    timeout(CONTROL_TIMEOUT, async {
+        let mut stream = TcpStream::connect(addr).await?;
+        write_frame(&mut stream, msg).await?;
+        read_frame(&mut stream).await
+    }).await.context("Control request timeout")?
+}
+
+/// Health Check: true = Peer ตอบ PONG ด้วย Nonce เดียวกัน
+pub async fn ping(addr: SocketAddr) -> bool {
+    let nonce = rand::random();
+    matches!(request(addr, &ControlMessage::Ping { nonce }).await, Ok(ControlMessage::Pong { nonce: n }) if n == nonce)
+}
+
+/// RTT ที่ดีที่สุดจาก PING `samples` ครั้งใน Connection เดียว (ไม่นับเวลา Connect) None = วัดไม่ได้
+pub async fn rtt(addr: SocketAddr, samples: usize) -> Option<Duration> {
+    timeout(CONTROL_TIMEOUT, async {
+        let mut stream = TcpStream::connect(addr).await.ok()?;
+        best_rtt(&mut stream, samples).await
+    }).await.ok().flatten()
+}
+
+async fn best_rtt(stream: &mut TcpStream, samples: usize) -> Option<Duration> {
+    let mut best: Option<Duration> = None;
+    for _ in 0..samples {
+        let nonce = rand::random();
+        let started = Instant::now();
+        write_frame(stream, &ControlMessage::Ping { nonce }).await.ok()?;
+        match read_frame(stream).await.ok()? {
+            ControlMessage::Pong { nonce: n } if n == nonce => {}
+            _ => return None,
+        }
+        let elapsed = started.elapsed();
+        best = Some(best.map_or(elapsed, |b| b.min(elapsed)));
+    }
+    best
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SpeedTestResult {
+    pub rtt_ms: f64,
+    // upload = เราส่งไป (วัดที่ Peer), download = Peer ส่งมา (วัดที่เรา)
+    pub upload_bytes_per_sec: u64,
+    pub download_bytes_per_sec: u64,
+}
+
+fn bytes_per_sec(bytes: u64, elapsed: Duration) -> u64 {
+    match elapsed.as_secs_f64() {
+        secs if secs > 0.0 => (bytes as f64 / secs) as u64,
+        _ => 0,
+    }
+}
+
+/// วัด RTT แล้วส่ง/รับ Random Data ทิศละ duration ผ่าน Control Channel (ใช้เวลาราว 2 x duration)
+pub async fn speed_test(addr: SocketAddr, duration: Duration) -> anyhow::Result<SpeedTestResult> {
+    if duration.is_zero() || duration > MAX_SPEED_TEST { bail!("Speed test duration must be between 1ms and {:?}", MAX_SPEED_TEST); }
+    let mut stream = timeout(CONTROL_TIMEOUT, TcpStream::connect(addr)).await.context("Control connect timeout")??;
+    let rtt = timeout(CONTROL_TIMEOUT, best_rtt(&mut stream, SPEED_TEST_RTT_SAMPLES)).await.ok().flatten()
+        .context("Peer did not answer PING")?;
+    write_frame(&mut stream, &ControlMessage::SpeedTest { duration_ms: duration.as_millis() as u64 }).await?;
+    match timeout(CONTROL_TIMEOUT, read_frame(&mut stream)).await.context("Control request timeout")?? {
+        ControlMessage::Ack { ok: true } => {}
+        ControlMessage::Ack { ok: false } => bail!("Peer is busy with another speed test"),
+        reply => bail!("Unexpected reply to SPEED_TEST: {:?}", reply),
+    }
+    stream_chunks(&mut stream, duration).await?;
+    let upload = match timeout(duration + SPEED_TEST_GRACE, read_frame(&mut stream)).await.context("Speed test result timeout")?? {
+        ControlMessage::SpeedResult { bytes, elapsed_ms } => bytes_per_sec(bytes, Duration::from_millis(elapsed_ms)),
+        reply => bail!("Unexpected reply to speed test upload: {:?}", reply),
+    };
+    let (bytes, elapsed) = drain_chunks(&mut stream, duration + SPEED_TEST_GRACE).await?;
+    Ok(SpeedTestResult {
+        rtt_ms: rtt.as_secs_f64() * 1000.0,
+        upload_bytes_per_sec: upload,
+        download_bytes_per_sec: bytes_per_sec(bytes, elapsed),
+    })
+}
+
+/// แจ้ง Peer (Caller spawn เอง) Peer ปฏิเสธหรือติดต่อไม่ได้ก็แค่ Log
+pub async fn notify(addr: SocketAddr, msg: ControlMessage) {
+    match request(addr, &msg).await {
+        Ok(ControlMessage::Ack { ok: true }) => {}
+        Ok(reply) => warn!("Peer {} refused {:?}: {:?}", addr, msg, reply),
+        Err(e) => warn!("Failed to send {:?} to {}: {}", msg, addr, e),
+    }
+}
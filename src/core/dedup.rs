@@ -0,0 +1,343 @@
+use std::fs::{self as std_fs, File as StdFile, OpenOptions as StdOpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+use dashmap::DashMap;
+use serde::{Serialize, Deserialize};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, DuplexStream};
+use tokio::time::{timeout, Duration};
+use anyhow::{bail, Context};
+
+use crate::core::cancel::{TransferSignal, REJECT_CANCELLED};
+use crate::core::chunk_store::ChunkStore;
+use crate::core::transfer::{DataStream, Throughput, ThroughputMeter, NOTIFY_INTERVAL_MS};
+
+// ==========================================
+// Chunk-level Deduplication
+// ฝั่งรับเก็บ Index: blake3(chunk) -> ไฟล์/Offset ที่เคยรับไว้
+// Protocol (หลัง ACK=1, ก่อน Compressor เริ่ม):
+//   ผู้ส่ง -> [hash 32 bytes] x chunk_count
+//   ผู้รับ -> Bitmap ceil(chunk_count/8) bytes (bit = 1 คือต้องส่ง)
+//   ผู้ส่ง -> เฉพาะ Chunk ที่ต้องส่ง เรียงตามลำดับ (ผ่าน Compressor ตามปกติ)
+// ใช้เฉพาะเมื่อปลายทางประกาศ feat=dedup ผ่าน mDNS
+// ไฟล์ที่รับครบ (และผ่าน Hash ของ Session แล้ว) ถูกบันทึก (path, size, mtime, blake3) ลง File Index ด้วย
+// ตอนมี Offer ใหม่: size/mtime ยังตรง -> เชื่อ Chunk Hash ได้เลยไม่ต้องอ่านไฟล์ใหม่, ไม่ตรง -> ทิ้ง Chunk ของไฟล์นั้น
+// เปิด Chunk Store ไว้ (chunk_store.rs) -> Chunk ที่ไฟล์ต้นทางหายไปแล้วยังหยิบจาก Store ได้
+// ==========================================
+
+pub const FEATURE: &str = "dedup";
+pub const CHUNK_SIZE: u64 = 1024 * 1024;
+const INDEX_FILE: &str = "chunk_index.jsonl";
+const FILE_INDEX_FILE: &str = "file_index.jsonl";
+const DUPLEX_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+pub type ChunkHash = [u8; 32];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct DedupInfo {
+    pub chunk_size: u64,
+    pub chunk_count: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChunkLocation {
+    pub file: PathBuf,
+    pub offset: u64,
+    pub len: u64,
+}
+
+/// ที่มาของ Chunk ที่ผู้รับมีอยู่แล้ว
+#[derive(Debug, Clone)]
+pub enum ChunkSource {
+    File(ChunkLocation),
+    Store { hash: ChunkHash, len: u64 },
+}
+
+impl ChunkSource {
+    fn len(&self) -> u64 {
+        match self { Self::File(loc) => loc.len, Self::Store { len, .. } => *len }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexLine { h: String, f: PathBuf, o: u64, l: u64 }
+
+/// สภาพไฟล์ตอนที่ Index ไว้ (blake3 ทั้งไฟล์ เป็น hex)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FileRecord {
+    pub size: u64,
+    pub mtime_ms: u64,
+    pub blake3: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FileLine { f: PathBuf, s: u64, m: u64, b: String }
+
+fn stat(path: &Path) -> Option<(u64, u64)> {
+    let meta = std_fs::metadata(path).ok()?;
+    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64;
+    Some((meta.len(), mtime))
+}
+
+fn chunk_len(index: u64, chunk_size: u64, filesize: u64) -> u64 {
+    chunk_size.min(filesize.saturating_sub(index * chunk_size))
+}
+
+pub fn chunk_count(filesize: u64, chunk_size: u64) -> u64 {
+    filesize.div_ceil(chunk_size)
+}
+
+// whole = Some -> Hash ทั้งไฟล์ไปพร้อมกันในรอบเดียว (ฝั่งรับใช้ทำ FileRecord)
+// store = Some -> เก็บเนื้อ Chunk ลง Store ไปด้วย (ฝั่งรับตอน Index)
+fn hash_file_blocking(path: &Path, chunk_size: u64, mut whole: Option<&mut blake3::Hasher>, store: Option<&ChunkStore>) -> std::io::Result<Vec<ChunkHash>> {
+    let mut f = StdFile::open(path)?;
+    let mut buf = vec![0u8; chunk_size as usize];
+    let mut hashes = Vec::new();
+    loop {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match f.read(&mut buf[filled..])? { 0 => break, n => filled += n }
+        }
+        if filled == 0 { break; }
+        if let Some(h) = whole.as_deref_mut() { h.update(&buf[..filled]); }
+        let hash = *blake3::hash(&buf[..filled]).as_bytes();
+        if let Some(Err(e)) = store.map(|s| s.put(&hash, &buf[..filled])) {
+            log::debug!("Chunk store: cannot keep chunk of '{}': {}", path.display(), e);
+        }
+        hashes.push(hash);
+        if filled < buf.len() { break; }
+    }
+    Ok(hashes)
+}
+
+pub async fn hash_chunks(path: &str, chunk_size: u64) -> anyhow::Result<Vec<ChunkHash>> {
+    let path = PathBuf::from(path);
+    Ok(tokio::task::spawn_blocking(move || hash_file_blocking(&path, chunk_size, None, None)).await??)
+}
+
+// --- Index ---
+
+#[derive(Debug)]
+pub struct ChunkIndex {
+    index_path: PathBuf,
+    file_index_path: PathBuf,
+    chunks: DashMap<ChunkHash, ChunkLocation>,
+    files: DashMap<PathBuf, FileRecord>,
+    store: Option<ChunkStore>,
+    append_lock: Mutex<()>,
+}
+
+impl ChunkIndex {
+    pub fn open(save_path: &str, store: Option<ChunkStore>) -> Arc<Self> {
+        let index_path = Path::new(save_path).join(INDEX_FILE);
+        let chunks = DashMap::new();
+        if let Ok(f) = StdFile::open(&index_path) {
+            for line in BufReader::new(f).lines().map_while(Result::ok) {
+                let Ok(entry) = serde_json::from_str::<IndexLine>(&line) else { continue };
+                let Ok(bytes) = hex::decode(&entry.h) else { continue };
+                let Ok(hash) = ChunkHash::try_from(bytes.as_slice()) else { continue };
+                // ไฟล์ถูกลบ/ย้ายไปแล้วก็ไม่ต้องโหลด
+                if entry.f.exists() {
+                    chunks.insert(hash, ChunkLocation { file: entry.f, offset: entry.o, len: entry.l });
+                }
+            }
+        }
+        // บรรทัดหลังทับบรรทัดก่อน (รับไฟล์ชื่อเดิมซ้ำ) ยังไม่เช็ค size/mtime ตรงนี้ ไปเช็คตอนถูกใช้จริง
+        let file_index_path = Path::new(save_path).join(FILE_INDEX_FILE);
+        let files = DashMap::new();
+        if let Ok(f) = StdFile::open(&file_index_path) {
+            for line in BufReader::new(f).lines().map_while(Result::ok) {
+                let Ok(entry) = serde_json::from_str::<FileLine>(&line) else { continue };
+                files.insert(entry.f, FileRecord { size: entry.s, mtime_ms: entry.m, blake3: entry.b });
+            }
+        }
+        files.retain(|path: &PathBuf, _| path.exists());
+        log::info!("Chunk index loaded: {} chunks, {} files", chunks.len(), files.len());
+        Arc::new(Self { index_path, file_index_path, chunks, files, store, append_lock: Mutex::new(()) })
+    }
+
+    /// FileRecord ของไฟล์ที่ยังไม่ถูกแก้ตั้งแต่ Index (size/mtime เปลี่ยน -> ทิ้ง Record และ Chunk ของไฟล์นั้น คืน None)
+    pub fn verified_file(&self, path: &Path) -> Option<FileRecord> {
+        let record = self.files.get(path)?.clone();
+        match stat(path) {
+            Some((size, mtime_ms)) if size == record.size && mtime_ms == record.mtime_ms => Some(record),
+            _ => {
+                log::debug!("Dedup: '{}' changed since indexed, dropping its chunks", path.display());
+                self.files.remove(path);
+                self.chunks.retain(|_, loc| loc.file != path);
+                None
+            }
+        }
+    }
+
+    fn read(&self, source: &ChunkSource) -> std::io::Result<Vec<u8>> {
+        match source {
+            ChunkSource::File(loc) => Self::read_chunk(loc),
+            ChunkSource::Store { hash, .. } => match &self.store {
+                Some(store) => store.read(hash),
+                None => Err(std::io::Error::new(std::io::ErrorKind::NotFound, "Chunk store disabled")),
+            },
+        }
+    }
+
+    fn read_chunk(loc: &ChunkLocation) -> std::io::Result<Vec<u8>> {
+        let mut f = StdFile::open(&loc.file)?;
+        f.seek(SeekFrom::Start(loc.offset))?;
+        let mut buf = vec![0u8; loc.len as usize];
+        f.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    // ไฟล์ที่รับไว้ก่อน แล้วค่อย Store (ไฟล์ถูกแก้ / ลบไปแล้ว)
+    fn lookup(&self, hash: &ChunkHash, len: u64) -> Option<ChunkSource> {
+        if let Some(loc) = self.lookup_verified(hash, len) { return Some(ChunkSource::File(loc)); }
+        self.store.as_ref().filter(|s| s.contains(hash, len)).map(|_| ChunkSource::Store { hash: *hash, len })
+    }
+
+    // ไฟล์ต้นทางอาจถูกแก้ไปแล้ว -> มี FileRecord ก็ดูแค่ size/mtime, Entry เก่าที่ไม่มี Record อ่านมา Hash ซ้ำ
+    fn lookup_verified(&self, hash: &ChunkHash, len: u64) -> Option<ChunkLocation> {
+        let loc = self.chunks.get(hash)?.clone();
+        if loc.len != len { return None; }
+        if self.files.contains_key(&loc.file) {
+            return self.verified_file(&loc.file).map(|_| loc);
+        }
+        match Self::read_chunk(&loc) {
+            Ok(data) if blake3::hash(&data).as_bytes() == hash => Some(loc),
+            _ => { self.chunks.remove(hash); None }
+        }
+    }
+
+    /// เพิ่มไฟล์ที่รับเสร็จแล้วเข้า Index (ไฟล์เดิมที่ยังไม่ถูกแก้ ข้ามได้เลยไม่ต้อง Hash ใหม่)
+    pub async fn index_file(self: &Arc<Self>, path: &Path) -> anyhow::Result<()> {
+        let me = self.clone();
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+            if me.verified_file(&path).is_some() { return Ok(()); }
+            let (filesize, mtime_ms) = stat(&path).context("Cannot stat received file")?;
+            let mut whole = blake3::Hasher::new();
+            let hashes = hash_file_blocking(&path, CHUNK_SIZE, Some(&mut whole), me.store.as_ref())?;
+            let record = FileRecord { size: filesize, mtime_ms, blake3: whole.finalize().to_hex().to_string() };
+            let _guard = me.append_lock.lock().unwrap();
+            // ชื่อเดิมถูกเขียนทับด้วยไฟล์ใหม่ -> Chunk เก่าของ Path นี้ใช้ไม่ได้แล้ว
+            me.chunks.retain(|_, loc| loc.file != path);
+            let mut out = StdOpenOptions::new().create(true).append(true).open(&me.index_path)?;
+            for (i, hash) in hashes.iter().enumerate() {
+                let loc = ChunkLocation { file: path.clone(), offset: i as u64 * CHUNK_SIZE, len: chunk_len(i as u64, CHUNK_SIZE, filesize) };
+                if me.chunks.contains_key(hash) { continue; }
+                let line = IndexLine { h: hex::encode(hash), f: loc.file.clone(), o: loc.offset, l: loc.len };
+                writeln!(out, "{}", serde_json::to_string(&line)?)?;
+                me.chunks.insert(*hash, loc);
+            }
+            let mut files = StdOpenOptions::new().create(true).append(true).open(&me.file_index_path)?;
+            let line = FileLine { f: path.clone(), s: record.size, m: record.mtime_ms, b: record.blake3.clone() };
+            writeln!(files, "{}", serde_json::to_string(&line)?)?;
+            me.files.insert(path, record);
+            Ok(())
+        }).await?
+    }
+}
+
+// --- Sender Side ---
+
+/// ส่ง Hash แล้วรอ Bitmap กลับมา คืน needed[i]
+pub async fn negotiate_send<S: DataStream>(stream: &mut S, hashes: &[ChunkHash], io_timeout: Duration) -> anyhow::Result<Vec<bool>> {
+    stream.write_all(&hashes.concat()).await?;
+    stream.flush().await?;
+    let mut bitmap = vec![0u8; hashes.len().div_ceil(8)];
+    timeout(io_timeout, stream.read_exact(&mut bitmap)).await.context("Dedup bitmap timeout")??;
+    Ok((0..hashes.len()).map(|i| bitmap[i / 8] & (1 << (i % 8)) != 0).collect())
+}
+
+/// Reader ที่ให้เฉพาะ Chunk ที่ต้องส่ง คืน (Reader, จำนวน Byte ทั้งหมด)
+pub fn needed_chunks_reader(mut file: tokio::fs::File, needed: Vec<bool>, chunk_size: u64, filesize: u64) -> (DuplexStream, u64) {
+    let total = needed.iter().enumerate().filter(|(_, n)| **n).map(|(i, _)| chunk_len(i as u64, chunk_size, filesize)).sum();
+    let (mut tx, rx) = tokio::io::duplex(DUPLEX_BUFFER_SIZE);
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; chunk_size as usize];
+        for (i, _) in needed.iter().enumerate().filter(|(_, n)| **n) {
+            let len = chunk_len(i as u64, chunk_size, filesize) as usize;
+            if file.seek(SeekFrom::Start(i as u64 * chunk_size)).await.is_err() { break; }
+            if file.read_exact(&mut buf[..len]).await.is_err() { break; }
+            if tx.write_all(&buf[..len]).await.is_err() { break; }
+        }
+        let _ = tx.shutdown().await;
+    });
+    (rx, total)
+}
+
+// --- Receiver Side ---
+
+pub struct ReceivePlan {
+    chunk_size: u64,
+    filesize: u64,
+    have: Vec<Option<ChunkSource>>,
+    index: Option<Arc<ChunkIndex>>,
+}
+
+impl ReceivePlan {
+    pub fn reused_bytes(&self) -> u64 { self.have.iter().flatten().map(ChunkSource::len).sum() }
+}
+
+/// อ่าน Hash จากผู้ส่ง เทียบกับ Index แล้วตอบ Bitmap (ไม่มี Index = ขอทุก Chunk)
+pub async fn negotiate_receive<S: DataStream>(stream: &mut S, info: &DedupInfo, filesize: u64, index: Option<&Arc<ChunkIndex>>, io_timeout: Duration) -> anyhow::Result<ReceivePlan> {
+    if info.chunk_size == 0 || info.chunk_count != chunk_count(filesize, info.chunk_size) {
+        bail!("Invalid dedup header");
+    }
+    let mut raw = vec![0u8; info.chunk_count as usize * 32];
+    timeout(io_timeout, stream.read_exact(&mut raw)).await.context("Dedup hashes timeout")??;
+    let hashes: Vec<ChunkHash> = raw.chunks_exact(32).map(|c| ChunkHash::try_from(c).unwrap()).collect();
+
+    let chunk_size = info.chunk_size;
+    let have = match index {
+        Some(index) if chunk_size == CHUNK_SIZE => {
+            let index = index.clone();
+            tokio::task::spawn_blocking(move || {
+                hashes.iter().enumerate()
+                    .map(|(i, h)| index.lookup(h, chunk_len(i as u64, chunk_size, filesize)))
+                    .collect::<Vec<_>>()
+            }).await?
+        }
+        _ => vec![None; hashes.len()],
+    };
+
+    let mut bitmap = vec![0u8; have.len().div_ceil(8)];
+    for (i, loc) in have.iter().enumerate() {
+        if loc.is_none() { bitmap[i / 8] |= 1 << (i % 8); }
+    }
+    stream.write_all(&bitmap).await?;
+    stream.flush().await?;
+    Ok(ReceivePlan { chunk_size, filesize, have, index: index.cloned() })
+}
+
+/// ประกอบไฟล์: Chunk ที่มีอยู่แล้วอ่านจาก Disk, ที่เหลืออ่านจาก Stream
+pub async fn assemble<R, W, F>(mut reader: R, writer: &mut W, plan: &ReceivePlan, mut on_progress: F, io_timeout: Duration, signal: &TransferSignal) -> anyhow::Result<()>
+where R: AsyncReadExt + Unpin, W: AsyncWrite + Unpin, F: FnMut(u64, u64, Throughput)
+{
+    let mut buf = vec![0u8; plan.chunk_size as usize];
+    let mut done = 0u64;
+    let mut last_time = tokio::time::Instant::now();
+    let mut meter = ThroughputMeter::start();
+    for (i, loc) in plan.have.iter().enumerate() {
+        if signal.is_cancelled() { bail!(REJECT_CANCELLED); }
+        let len = chunk_len(i as u64, plan.chunk_size, plan.filesize) as usize;
+        match loc {
+            Some(source) => {
+                let (source, index) = (source.clone(), plan.index.clone().context("Dedup plan without an index")?);
+                let data = tokio::task::spawn_blocking(move || index.read(&source)).await??;
+                writer.write_all(&data).await?;
+            }
+            None => {
+                signal.timeout(io_timeout, reader.read_exact(&mut buf[..len])).await.context("Read timeout")??;
+                writer.write_all(&buf[..len]).await?;
+            }
+        }
+        done += len as u64;
+        let now = tokio::time::Instant::now();
+        if now.duration_since(last_time).as_millis() > NOTIFY_INTERVAL_MS || done == plan.filesize {
+            on_progress(done, plan.filesize, meter.sample(done, plan.filesize));
+            last_time = now;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::fs::File as StdFile;
+use std::io::{Read, SeekFrom};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, DuplexStream};
+use tokio::time::{timeout, Duration};
+use anyhow::{bail, Context};
+
+use crate::core::cancel::{TransferSignal, REJECT_CANCELLED};
+use crate::core::transfer::{DataStream, FileHeader, CAP_DELTA, Throughput, ThroughputMeter, NOTIFY_INTERVAL_MS};
+
+// ==========================================
+// Block-level Delta (ไฟล์ใหญ่ที่ผู้รับมีเวอร์ชันเก่าอยู่แล้ว เช่น VM Image / โปรเจกต์วิดีโอ / ไฟล์จาก mirror_folder)
+// แบบ rsync: Basis = ไฟล์ชื่อเดียวกันในโฟลเดอร์ปลายทาง ผู้รับส่ง Signature ของทุก Block ให้ผู้ส่ง
+// ผู้ส่งเลื่อน Window ทีละ Byte ด้วย Rolling Checksum -> เจอ Block เดิมแม้ข้อมูลถูกแทรก/ลบจนตำแหน่งเลื่อน
+// Protocol (แทน ACK=1 เมื่อผู้ส่งมี CAP_DELTA และ Basis ใหญ่พอ, ไม่ใช้ร่วมกับ dedup / parallel / resume):
+//   ผู้รับ -> ACK_DELTA (offset ของ ACK = block_size) + [u64 block_count] + ([u32 weak][blake3 32 bytes] x block_count)
+//   ผู้ส่ง -> Op Stream ผ่าน Compressor / Middleware ตามปกติ:
+//     COPY [u64 block][u32 count] / DATA [u32 len][bytes] / END
+// ผู้รับประกอบไฟล์ใหม่ลง .part จาก Basis + DATA แล้วย้ายเข้าที่ตามปกติ (Basis ไม่ถูกแตะจนกว่าจะครบ)
+// ==========================================
+
+// ไฟล์เล็กกว่านี้ส่งทั้งไฟล์เร็วกว่าไปกลับแลก Signature
+pub const DELTA_MIN_SIZE: u64 = 16 * 1024 * 1024;
+const MIN_BLOCK_SIZE: u64 = 64 * 1024;
+const MAX_BLOCK_SIZE: u64 = 8 * 1024 * 1024;
+// Signature ไม่เกิน ~9 MB (Basis ใหญ่กว่านี้ใช้ Block ใหญ่ขึ้น)
+const MAX_BLOCKS: u64 = 1 << 18;
+const SIG_ENTRY_SIZE: usize = 4 + 32;
+const DATA_RECORD_SIZE: u64 = 1024 * 1024;
+const READ_AHEAD: usize = 4 * 1024 * 1024;
+const DUPLEX_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+const OP_END: u8 = 0;
+const OP_COPY: u8 = 1;
+const OP_DATA: u8 = 2;
+
+/// Checksum แบบ rsync (a = ผลรวม Byte, b = ผลรวมถ่วงตำแหน่ง) เลื่อนออก 1 / เข้า 1 Byte ได้ใน O(1)
+struct Rolling { a: u32, b: u32, len: u32 }
+
+impl Rolling {
+    fn new(data: &[u8]) -> Self {
+        let len = data.len() as u32;
+        let (mut a, mut b) = (0u32, 0u32);
+        for (i, &x) in data.iter().enumerate() {
+            a = a.wrapping_add(x as u32);
+            b = b.wrapping_add((len - i as u32).wrapping_mul(x as u32));
+        }
+        Self { a, b, len }
+    }
+
+    fn roll(&mut self, out: u8, inp: u8) {
+        self.a = self.a.wrapping_sub(out as u32).wrapping_add(inp as u32);
+        self.b = self.b.wrapping_sub(self.len.wrapping_mul(out as u32)).wrapping_add(self.a);
+    }
+
+    fn digest(&self) -> u32 {
+        ((self.b & 0xffff) << 16) | (self.a & 0xffff)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BlockSig { weak: u32, strong: [u8; 32] }
+
+/// Signature ของ Basis (เฉพาะ Block เต็ม ส่วนท้ายที่ไม่เต็ม Block ผู้ส่งส่งเป็น DATA)
+#[derive(Debug)]
+pub struct Signature {
+    pub block_size: u64,
+    blocks: Vec<BlockSig>,
+}
+
+impl Signature {
+    pub fn block_count(&self) -> u64 { self.blocks.len() as u64 }
+
+    fn block_size_for(basis_len: u64) -> u64 {
+        basis_len.div_ceil(MAX_BLOCKS).next_power_of_two().clamp(MIN_BLOCK_SIZE, MAX_BLOCK_SIZE)
+    }
+
+    /// ฝั่งรับ: อ่าน Basis ทั้งไฟล์ครั้งเดียว
+    pub async fn of(basis: &Path) -> anyhow::Result<Self> {
+        let basis = basis.to_path_buf();
+        tokio::task::spawn_blocking(move || -> anyhow::Result<Self> {
+            let mut file = StdFile::open(&basis)?;
+            let block_size = Self::block_size_for(file.metadata()?.len());
+            let mut buf = vec![0u8; block_size as usize];
+            let mut blocks = Vec::new();
+            loop {
+                let mut filled = 0;
+                while filled < buf.len() {
+                    match file.read(&mut buf[filled..])? { 0 => break, n => filled += n }
+                }
+                if filled < buf.len() { break; }
+                blocks.push(BlockSig { weak: Rolling::new(&buf).digest(), strong: *blake3::hash(&buf).as_bytes() });
+            }
+            Ok(Self { block_size, blocks })
+        }).await?
+    }
+
+    pub async fn send<S: DataStream>(&self, stream: &mut S) -> anyhow::Result<()> {
+        let mut out = Vec::with_capacity(8 + self.blocks.len() * SIG_ENTRY_SIZE);
+        out.extend_from_slice(&self.block_count().to_le_bytes());
+        for block in &self.blocks {
+            out.extend_from_slice(&block.weak.to_le_bytes());
+            out.extend_from_slice(&block.strong);
+        }
+        stream.write_all(&out).await?;
+        stream.flush().await?;
+        Ok(())
+    }
+
+    /// ฝั่งส่ง: block_size มากับ ACK_DELTA
+    pub async fn receive<S: DataStream>(stream: &mut S, block_size: u64, io_timeout: Duration) -> anyhow::Result<Self> {
+        if !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&block_size) { bail!("Invalid delta block size {}", block_size); }
+        let mut count = [0u8; 8];
+        timeout(io_timeout, stream.read_exact(&mut count)).await.context("Delta signature timeout")??;
+        let count = u64::from_le_bytes(count);
+        if count > MAX_BLOCKS { bail!("Delta signature too large ({} blocks)", count); }
+        let mut raw = vec![0u8; count as usize * SIG_ENTRY_SIZE];
+        timeout(io_timeout, stream.read_exact(&mut raw)).await.context("Delta signature timeout")??;
+        let blocks = raw.chunks_exact(SIG_ENTRY_SIZE).map(|c| BlockSig {
+            weak: u32::from_le_bytes(c[..4].try_into().unwrap()),
+            strong: c[4..].try_into().unwrap(),
+        }).collect();
+        Ok(Self { block_size, blocks })
+    }
+}
+
+/// Basis ที่ใช้ทำ Delta ได้ (None = ส่งทั้งไฟล์ตามปกติ) ผู้เรียกตรวจเรื่อง resume เอง
+pub fn basis_for(header: &FileHeader, dir: &str) -> Option<PathBuf> {
+    if !header.has_capability(CAP_DELTA) || header.dedup.is_some() || header.parallel.is_some() || header.filesize < DELTA_MIN_SIZE {
+        return None;
+    }
+    let name = Path::new(&header.filename).file_name()?;
+    let basis = Path::new(dir).join(name);
+    std::fs::metadata(&basis).is_ok_and(|m| m.is_file() && m.len() >= DELTA_MIN_SIZE).then_some(basis)
+}
+
+// --- Sender Side ---
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Copy { block: u64, count: u32 },
+    // ช่วงของไฟล์ใหม่ที่ต้องส่งจริง (อ่านตอนส่ง ไม่เก็บไว้ใน Memory)
+    Data { offset: u64, len: u64 },
+}
+
+fn push_copy(ops: &mut Vec<Op>, block: u64) {
+    if let Some(Op::Copy { block: first, count }) = ops.last_mut() {
+        if *first + *count as u64 == block && *count < u32::MAX { *count += 1; return; }
+    }
+    ops.push(Op::Copy { block, count: 1 });
+}
+
+fn plan_blocking(path: &Path, sig: &Signature) -> std::io::Result<Vec<Op>> {
+    let bs = sig.block_size as usize;
+    let mut table: HashMap<u32, Vec<u64>> = HashMap::new();
+    for (i, block) in sig.blocks.iter().enumerate() { table.entry(block.weak).or_default().push(i as u64); }
+
+    let mut file = StdFile::open(path)?;
+    let file_len = file.metadata()?.len();
+    let mut ops = Vec::new();
+    let mut buf: Vec<u8> = Vec::with_capacity(READ_AHEAD + bs);
+    // buf[0] อยู่ที่ pos ของไฟล์, Window = buf[start..start + bs]
+    let (mut pos, mut start, mut literal_from) = (0u64, 0usize, 0u64);
+    let mut eof = false;
+    let mut rolling: Option<Rolling> = None;
+    loop {
+        // Window ต้องมี Byte ถัดไปให้เลื่อนด้วย -> เติมก่อนถึงท้าย buf
+        if buf.len() - start <= bs && !eof {
+            buf.drain(..start);
+            pos += start as u64;
+            start = 0;
+            let want = buf.len() + READ_AHEAD;
+            let filled = buf.len();
+            buf.resize(want, 0);
+            let mut end = filled;
+            while end < want {
+                match file.read(&mut buf[end..])? { 0 => { eof = true; break; } n => end += n }
+            }
+            buf.truncate(end);
+            continue;
+        }
+        if buf.len() - start < bs { break; }
+        let window = &buf[start..start + bs];
+        let weak = rolling.get_or_insert_with(|| Rolling::new(window)).digest();
+        let found = table.get(&weak).and_then(|candidates| {
+            let strong = blake3::hash(window);
+            candidates.iter().copied().find(|&i| sig.blocks[i as usize].strong == *strong.as_bytes())
+        });
+        if let Some(block) = found {
+            let at = pos + start as u64;
+            if at > literal_from { ops.push(Op::Data { offset: literal_from, len: at - literal_from }); }
+            push_copy(&mut ops, block);
+            start += bs;
+            literal_from = at + bs as u64;
+            rolling = None;
+        } else if start + bs < buf.len() {
+            rolling.as_mut().unwrap().roll(buf[start], buf[start + bs]);
+            start += 1;
+        } else {
+            break;
+        }
+    }
+    if file_len > literal_from { ops.push(Op::Data { offset: literal_from, len: file_len - literal_from }); }
+    Ok(ops)
+}
+
+/// หา Block ของ Basis ในไฟล์ที่จะส่ง (อ่านทั้งไฟล์หนึ่งรอบ)
+pub async fn plan(path: &str, sig: Signature) -> anyhow::Result<Vec<Op>> {
+    let path = PathBuf::from(path);
+    Ok(tokio::task::spawn_blocking(move || plan_blocking(&path, &sig)).await??)
+}
+
+fn wire_size(ops: &[Op]) -> u64 {
+    let records: u64 = ops.iter().map(|op| match op {
+        Op::Copy { .. } => 1 + 8 + 4,
+        Op::Data { len, .. } => len.div_ceil(DATA_RECORD_SIZE) * (1 + 4) + len,
+    }).sum();
+    records + 1
+}
+
+/// จำนวน Byte ของไฟล์ใหม่ที่ต้องส่งจริง (ไม่นับ Op)
+pub fn literal_bytes(ops: &[Op]) -> u64 {
+    ops.iter().map(|op| match op { Op::Data { len, .. } => *len, Op::Copy { .. } => 0 }).sum()
+}
+
+/// Reader ของ Op Stream คืน (Reader, จำนวน Byte ทั้งหมด)
+pub fn ops_reader(mut file: tokio::fs::File, ops: Vec<Op>) -> (DuplexStream, u64) {
+    let total = wire_size(&ops);
+    let (mut tx, rx) = tokio::io::duplex(DUPLEX_BUFFER_SIZE);
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; DATA_RECORD_SIZE as usize];
+        for op in ops {
+            let written = match op {
+                Op::Copy { block, count } => {
+                    let mut record = vec![OP_COPY];
+                    record.extend_from_slice(&block.to_le_bytes());
+                    record.extend_from_slice(&count.to_le_bytes());
+                    tx.write_all(&record).await
+                }
+                Op::Data { offset, len } => async {
+                    file.seek(SeekFrom::Start(offset)).await?;
+                    let mut left = len;
+                    while left > 0 {
+                        let n = left.min(DATA_RECORD_SIZE) as usize;
+                        file.read_exact(&mut buf[..n]).await?;
+                        tx.write_all(&[OP_DATA]).await?;
+                        tx.write_all(&(n as u32).to_le_bytes()).await?;
+                        tx.write_all(&buf[..n]).await?;
+                        left -= n as u64;
+                    }
+                    Ok::<(), std::io::Error>(())
+                }.await,
+            };
+            if written.is_err() { return; }
+        }
+        let _ = tx.write_all(&[OP_END]).await;
+        let _ = tx.shutdown().await;
+    });
+    (rx, total)
+}
+
+// --- Receiver Side ---
+
+/// ประกอบไฟล์ใหม่จาก Op Stream: COPY อ่านจาก Basis, DATA อ่านจาก Stream
+#[allow(clippy::too_many_arguments)]
+pub async fn apply<R, W, F>(mut reader: R, writer: &mut W, basis: &Path, sig: &Signature, filesize: u64, mut on_progress: F, io_timeout: Duration, signal: &TransferSignal) -> anyhow::Result<()>
+where R: AsyncReadExt + Unpin, W: AsyncWrite + Unpin, F: FnMut(u64, u64, Throughput)
+{
+    let mut source = tokio::fs::File::open(basis).await.context("Cannot open delta basis")?;
+    let mut buf = vec![0u8; sig.block_size.max(DATA_RECORD_SIZE) as usize];
+    let mut done = 0u64;
+    let mut last_time = tokio::time::Instant::now();
+    let mut meter = ThroughputMeter::start();
+    loop {
+        if signal.is_cancelled() { bail!(REJECT_CANCELLED); }
+        let mut tag = [0u8; 1];
+        signal.timeout(io_timeout, reader.read_exact(&mut tag)).await.context("Read timeout")??;
+        match tag[0] {
+            OP_END => break,
+            OP_COPY => {
+                let mut record = [0u8; 12];
+                signal.timeout(io_timeout, reader.read_exact(&mut record)).await.context("Read timeout")??;
+                let block = u64::from_le_bytes(record[..8].try_into().unwrap());
+                let count = u32::from_le_bytes(record[8..].try_into().unwrap()) as u64;
+                if block.checked_add(count).is_none_or(|end| end > sig.block_count()) { bail!("Delta copies past the basis"); }
+                if done + count * sig.block_size > filesize { bail!("Delta longer than the file"); }
+                source.seek(SeekFrom::Start(block * sig.block_size)).await?;
+                let block_buf = &mut buf[..sig.block_size as usize];
+                for _ in 0..count {
+                    source.read_exact(block_buf).await.context("Delta basis changed during transfer")?;
+                    writer.write_all(block_buf).await?;
+                }
+                done += count * sig.block_size;
+            }
+            OP_DATA => {
+                let mut len = [0u8; 4];
+                signal.timeout(io_timeout, reader.read_exact(&mut len)).await.context("Read timeout")??;
+                let len = u32::from_le_bytes(len) as u64;
+                if len > DATA_RECORD_SIZE || done + len > filesize { bail!("Invalid delta data record"); }
+                signal.timeout(io_timeout, reader.read_exact(&mut buf[..len as usize])).await.context("Read timeout")??;
+                writer.write_all(&buf[..len as usize]).await?;
+                done += len;
+            }
+            other => bail!("Unknown delta op {}", other),
+        }
+        let now = tokio::time::Instant::now();
+        if now.duration_since(last_time).as_millis() > NOTIFY_INTERVAL_MS || done == filesize {
+            on_progress(done, filesize, meter.sample(done, filesize));
+            last_time = now;
+        }
+    }
+    if done != filesize { bail!("Delta ended at {} of {} bytes", done, filesize); }
+    Ok(())
+}
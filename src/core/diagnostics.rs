@@ -0,0 +1,243 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use serde_json::json;
+use anyhow::Context;
+
+use crate::core::engine::DropTeaConfig;
+use crate::core::events::{TransferEvent, TransferEventHandler};
+use crate::core::utils;
+
+// ==========================================
+// Diagnostics Bundle (แนบตอนแจ้ง Bug)
+// รวม Event ล่าสุด, History, Config (ตัดความลับ), Peer Table, Log แล้วบีบเป็น Zip เดียว
+// ==========================================
+
+const MAX_RECORDED_EVENTS: usize = 500;
+// เอาเฉพาะท้ายไฟล์ Log (ของใหม่สุด)
+const MAX_LOG_TAIL: u64 = 2 * 1024 * 1024;
+const REDACTED: &str = "<redacted>";
+
+// --- Event Recorder (Ring Buffer) ---
+
+#[derive(Default)]
+pub struct EventRecorder {
+    events: Mutex<VecDeque<(u64, TransferEvent)>>,
+}
+
+impl EventRecorder {
+    pub fn record(&self, event: &TransferEvent) {
+        // Progress ถี่เกินไป ไม่มีประโยชน์ตอน Debug
+        if matches!(event, TransferEvent::Progress { .. } | TransferEvent::BatchProgress { .. }) { return; }
+        if let Ok(mut q) = self.events.lock() {
+            if q.len() >= MAX_RECORDED_EVENTS { q.pop_front(); }
+            q.push_back((utils::timestamp_millis(), event.clone()));
+        }
+    }
+
+    pub fn to_jsonl(&self) -> String {
+        let Ok(q) = self.events.lock() else { return String::new() };
+        q.iter()
+            .filter_map(|(ts, ev)| serde_json::to_string(&json!({ "ts": ts, "event": ev })).ok())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// ห่อ Handler เดิมแล้วเก็บ Event ไว้ด้วย
+pub struct RecordingHandler {
+    pub inner: Box<dyn TransferEventHandler>,
+    pub recorder: Arc<EventRecorder>,
+}
+
+impl TransferEventHandler for RecordingHandler {
+    fn on_event(&self, event: TransferEvent) {
+        self.recorder.record(&event);
+        self.inner.on_event(event);
+    }
+}
+
+// --- Redaction ---
+
+// เหลือแค่ scheme://host (Path/Query มักมี Token)
+fn redact_url(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+            let host = host.rsplit('@').next().unwrap_or(host);
+            format!("{}://{}/{}", scheme, host, REDACTED)
+        }
+        None => REDACTED.to_string(),
+    }
+}
+
+// turn:user:pass@host -> turn:host
+fn redact_ice_server(url: &str) -> String {
+    match (url.split_once(':'), url.rsplit_once('@')) {
+        (Some((scheme, _)), Some((_, host))) => format!("{}:{}", scheme, host),
+        _ => url.to_string(),
+    }
+}
+
+pub fn redacted_config(config: &DropTeaConfig) -> serde_json::Value {
+    json!({
+        "mode": format!("{:?}", config.mode),
+        "custom_transport": config.custom_transport,
+        "port": config.port,
+        "storage_path": config.storage_path,
+        "download_dir": config.download_dir,
+        "temp_dir": config.temp_dir,
+        "node_name": config.node_name,
+        "dev_mode": config.dev_mode,
+        "archive_mode": format!("{:?}", config.archive_mode),
+        "approval_webhook": config.approval_webhook.as_ref().map(|w| json!({
+            "url": redact_url(&w.url),
+            "timeout_secs": w.timeout.as_secs(),
+        })),
+        "compression_level": config.compression_level,
+        "preferred_compression": config.preferred_compression.map(|c| c.as_str()),
+        "admin_fingerprints": config.admin_fingerprints.len(),
+        "ice_servers": config.ice_servers.as_ref().map(|s| s.iter().map(|u| redact_ice_server(u)).collect::<Vec<_>>()),
+        "io_priority": format!("{:?}", config.io_priority),
+        "mmap_threshold_bytes": config.mmap_threshold,
+        "rendezvous_server": config.rendezvous_server,
+        "rendezvous_listen": config.rendezvous_listen,
+        "dedup": config.dedup,
+        "chunk_store_bytes": config.chunk_store,
+        "skip_duplicates": config.skip_duplicates,
+        "sender_queue": config.sender_queue,
+        "persist_outbox": config.persist_outbox,
+        "port_mapping": config.port_mapping,
+        "hotspot_gateway": config.hotspot_gateway,
+        "auto_tune": config.auto_tune,
+        "sandbox_helper": config.sandbox_helper,
+        "ble_max_file_size": config.ble_max_file_size,
+        // Argument อาจมี Token / Path ส่วนตัว -> ชื่อโปรแกรมอย่างเดียว
+        "post_receive": config.post_receive.as_ref().map(|h| json!({
+            "program": h.argv.first(),
+            "timeout_secs": h.timeout.as_secs(),
+        })),
+        "reputation": config.reputation.as_ref().map(|r| json!({
+            "db": r.db,
+            "url": r.url.as_deref().map(redact_url),
+        })),
+        "timeouts": json!({
+            "io_secs": config.timeouts.io.as_secs(),
+            "user_decision_secs": config.timeouts.user_decision.as_secs(),
+            "stall_secs": config.timeouts.stall.as_secs(),
+            "health_check_interval_secs": config.health_check.interval.as_secs(),
+            "peer_stale_secs": config.health_check.stale_after.as_secs(),
+        }),
+        "retry": json!({
+            "attempts": config.retry.attempts,
+            "backoff_ms": config.retry.backoff.as_millis() as u64,
+            "max_backoff_ms": config.retry.max_backoff.as_millis() as u64,
+            "jitter": config.retry.jitter,
+        }),
+        "quic_parallel": json!({
+            "streams": config.parallel.streams,
+            "threshold_bytes": config.parallel.threshold,
+        }),
+        "quic": json!({
+            "congestion": config.quic.congestion.as_str(),
+            "initial_window": config.quic.initial_window,
+            "initial_rtt_ms": config.quic.initial_rtt.map(|d| d.as_millis() as u64),
+            "segmentation_offload": config.quic.segmentation_offload,
+            "stream_window_size": config.quic.stream_window_size,
+            "connection_window_size": config.quic.connection_window_size,
+            "keep_alive_secs": config.quic.keep_alive_interval.as_secs(),
+            "idle_timeout_secs": config.quic.max_idle_timeout.as_secs(),
+        }),
+        "tcp_parallel": json!({
+            "connections": config.tcp_parallel.streams,
+            "threshold_bytes": config.tcp_parallel.threshold,
+        }),
+        "discovery_privacy_rotate_secs": config.discovery_privacy.map(|d| d.as_secs()),
+        "discovery_backends": config.discovery_backends,
+        "manual_peers": config.manual_peers.len(),
+        "broadcast_port": config.broadcast_port,
+        "localsend_port": config.localsend.as_ref().map(|l| l.port),
+        "links_port": config.links.as_ref().map(|l| l.port),
+        "device_type": config.device_type.map(|d| d.as_str()),
+        "guest": config.guest,
+        "max_incoming": config.max_incoming,
+        "max_outgoing": config.max_outgoing,
+        "max_bytes_per_sec": config.max_bytes_per_sec,
+        "shares": config.shares.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(),
+        "share_hashes": config.share_options.hashes,
+        "share_max_entries": config.share_options.max_entries,
+        // Key เป็น Fingerprint / IP ของ Peer -> นับอย่างเดียว
+        "peers": config.peers.len(),
+        // ไม่ใส่ dest (Path ในเครื่อง User)
+        "save_rules": config.save_rules.iter().map(|r| json!({
+            "match": r.matchers,
+            "on_conflict": r.conflict.as_str(),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+// --- Bundle ---
+
+pub struct DiagnosticsInput<'a> {
+    pub config: &'a serde_json::Value,
+    pub peers: serde_json::Value,
+    pub recorder: &'a EventRecorder,
+    pub save_path: &'a str,
+    pub log_dirs: &'a [PathBuf],
+}
+
+fn read_tail(path: &Path, max: u64) -> std::io::Result<Vec<u8>> {
+    let mut f = fs::File::open(path)?;
+    let len = f.metadata()?.len();
+    if len > max { f.seek(SeekFrom::Start(len - max))?; }
+    let mut buf = Vec::new();
+    f.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+fn is_log_file(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    name.ends_with(".jsonl") || name.ends_with(".log") || name.contains(".jsonl.") || name.contains(".log.")
+}
+
+pub fn export(out_path: &str, input: DiagnosticsInput) -> anyhow::Result<()> {
+    let staging = std::env::temp_dir().join(format!("droptea-diag-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&staging).context("Failed to create staging directory")?;
+    let result = (|| -> anyhow::Result<()> {
+        let version = json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+            "host": utils::get_system_name(),
+            "exported_at": utils::timestamp_millis(),
+        });
+        fs::write(staging.join("version.json"), serde_json::to_vec_pretty(&version)?)?;
+        fs::write(staging.join("config.json"), serde_json::to_vec_pretty(input.config)?)?;
+        fs::write(staging.join("peers.json"), serde_json::to_vec_pretty(&input.peers)?)?;
+        fs::write(staging.join("events.jsonl"), input.recorder.to_jsonl())?;
+
+        let history = Path::new(input.save_path).join("history.jsonl");
+        if let Ok(data) = read_tail(&history, MAX_LOG_TAIL) {
+            fs::write(staging.join("history.jsonl"), data)?;
+        }
+
+        let logs_out = staging.join("logs");
+        fs::create_dir_all(&logs_out)?;
+        for dir in input.log_dirs {
+            let Ok(entries) = fs::read_dir(dir) else { continue };
+            for path in entries.flatten().map(|e| e.path()).filter(|p| p.is_file() && is_log_file(p)) {
+                if let (Some(name), Ok(data)) = (path.file_name(), read_tail(&path, MAX_LOG_TAIL)) {
+                    fs::write(logs_out.join(name), data)?;
+                }
+            }
+        }
+
+        // ใช้ Zip Util เดียวกับ compress_folder
+        utils::compress_folder(staging.to_string_lossy().into_owned(), out_path.to_string())?;
+        Ok(())
+    })();
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Mutex as StdMutex;
+use std::time::{Instant, Duration};
+use async_trait::async_trait;
+use tracing::{info, error, warn};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use futures::stream::StreamExt;
+use btleplug::api::{Central, CentralEvent, Manager as _, Peripheral, ScanFilter};
+use btleplug::platform::Manager;
+
+use crate::core::discovery::{DiscoveryBackend, DiscoveryInternalEvent, LocalNode, MdnsBackend};
+use crate::core::discovery::ble_advertise::{self, Advertiser};
+
+const DROPTEA_UUID_PART: &str = "d7ea";
+const DROPTEA_NAME_PREFIX: &str = "DT-";
+const BLE_CACHE_TTL_MS: u128 = 1000;      
+
+// ==========================================
+// BLE Scanner Backend (+ Advertise เมื่อ Build ด้วย feature "ble-advertise")
+// ==========================================
+
+#[derive(Default)]
+pub struct BleBackend {
+    task: StdMutex<Option<JoinHandle<()>>>,
+    node: StdMutex<Option<LocalNode>>,
+    advertiser: tokio::sync::Mutex<Option<Advertiser>>,
+}
+
+impl BleBackend {
+    pub fn new() -> Self { Self::default() }
+
+    // Address ที่ใส่ใน Advertisement: Hotspot ก่อน (มือถือที่เกาะอยู่ต่อได้แน่) แล้วค่อย IPv4 ตัวแรก
+    pub(crate) fn advertised_endpoint(node: &LocalNode) -> Option<(Ipv4Addr, u16)> {
+        let v4 = |ip: IpAddr| match ip { IpAddr::V4(v4) => Some(v4), IpAddr::V6(_) => None };
+        let ip = node.hotspot_addr.and_then(v4)
+            .or_else(|| MdnsBackend::get_local_ips().into_iter().find_map(v4))?;
+        (node.port != 0).then_some((ip, node.port))
+    }
+
+    async fn advertise(&self, node: &LocalNode) {
+        if !cfg!(feature = "ble-advertise") { return; }
+        let mut advertiser = self.advertiser.lock().await;
+        if let Some(old) = advertiser.take() {
+            old.stop().await;
+        }
+        let name: String = format!("{}{}", DROPTEA_NAME_PREFIX, node.name)
+            .chars().take(ble_advertise::MAX_LOCAL_NAME_LEN).collect();
+        match Advertiser::start(&name, Self::advertised_endpoint(node)).await {
+            Ok(a) => *advertiser = Some(a),
+            // Scan ยังทำงานต่อได้ แค่มือถือจะไม่เห็นเราทาง BLE
+            Err(e) => warn!("BLE advertising unavailable: {:#}", e),
+        }
+    }
+}
+
+#[async_trait]
+impl DiscoveryBackend for BleBackend {
+    fn name(&self) -> &str { "ble" }
+
+    async fn start(&self, node: &LocalNode, tx: mpsc::Sender<DiscoveryInternalEvent>) -> anyhow::Result<()> {
+        // 🟢 Branch 1: Dev Mode (Mock Data)
+        if node.dev_mode {
+            let my_id = node.id.clone();
+            let handle = tokio::spawn(async move {
+                let mut counter = 0;
+                loop {
+                    if counter >= 5 { break; }
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                    counter += 1;
+                    let mock_id = format!("{}_mock_{}", my_id, counter);
+                    info!("🔧 [DevMode] Injecting Mock BLE Peer");
+                    let _ = tx.send(DiscoveryInternalEvent::BleFound {
+                        id: mock_id,
+                        name: format!("Mock Device #{}", counter),
+                        ssid: Some("Dev_WiFi_5G".to_string()),
+                        mac: "00:11:22:AA:BB:CC".to_string(),
+                        addr: None,
+                    }).await;
+                }
+            });
+            *self.task.lock().unwrap() = Some(handle);
+            return Ok(());
+        }
+
+        // 🟠 Branch 2: Production Mode (Real BLE)
+        *self.node.lock().unwrap() = Some(node.clone());
+        self.advertise(node).await;
+
+        let handle = tokio::spawn(async move {
+            let manager = match Manager::new().await { Ok(m) => m, Err(e) => { error!("BLE Init Error: {}", e); return; } };
+            let adapters = match manager.adapters().await { Ok(a) => a, Err(e) => { error!("BLE Adapter Error: {}", e); return; } };
+            let central = match adapters.into_iter().next() { Some(c) => c, None => { error!("BLE: No Adapter Found"); return; } };
+
+            let mut events = match central.events().await {
+                Ok(e) => e,
+                Err(e) => { error!("Failed to subscribe to BLE events: {}", e); return; }
+            };
+
+            if let Err(e) = central.start_scan(ScanFilter::default()).await {
+                error!("BLE Start Scan Error: {}", e);
+                return;
+            }
+
+            info!("🔵 BLE Scanner Running (Filtering for '{}' or '{}')", DROPTEA_NAME_PREFIX, DROPTEA_UUID_PART);
+
+            let mut processed_cache: HashMap<String, Instant> = HashMap::new();
+
+            while let Some(event) = events.next().await {
+                match event {
+                    CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id) => {
+                         let id_str = id.to_string();
+                         if let Some(last_time) = processed_cache.get(&id_str) {
+                             if last_time.elapsed().as_millis() < BLE_CACHE_TTL_MS {
+                                 continue; 
+                             }
+                         }
+                         processed_cache.insert(id_str.clone(), Instant::now());
+
+                        if let Ok(p) = central.peripheral(&id).await {
+                            if let Ok(Some(props)) = p.properties().await {
+                                let name = props.local_name.clone().unwrap_or("Unknown".to_string());
+                                let mac = p.address().to_string();
+                                let services = props.services.clone();
+                                // Desktop ที่ Advertise ด้วย DropTea ใส่ ip:port มาใน Service Data
+                                let addr = props.service_data.iter()
+                                    .find(|(uuid, _)| uuid.to_string().to_lowercase().contains(DROPTEA_UUID_PART))
+                                    .and_then(|(_, data)| ble_advertise::decode_endpoint(data))
+                                    .map(|(ip, port)| (IpAddr::V4(ip), port));
+
+                                let mut is_target = false;
+
+                                if name.starts_with(DROPTEA_NAME_PREFIX) {
+                                    is_target = true;
+                                }
+
+                                if !is_target {
+                                    for uuid in &services {
+                                        if uuid.to_string().to_lowercase().contains(DROPTEA_UUID_PART) {
+                                            is_target = true;
+                                            break;
+                                        }
+                                    }
+                                }
+
+                                if is_target {
+                                    let display_name = if name == "Unknown" {
+                                        "iPad/iPhone (DropTea)".to_string()
+                                    } else {
+                                        name.clone()
+                                    };
+
+                                    let unique_id = if name == "Unknown" || name == display_name {
+                                        format!("ble-{}", mac.replace(":", ""))
+                                    } else {
+                                        name.clone()
+                                    };
+
+                                    let _ = tx.send(DiscoveryInternalEvent::BleFound {
+                                        id: unique_id,
+                                        name: display_name,
+                                        ssid: None,
+                                        mac,
+                                        addr,
+                                    }).await;
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+        *self.task.lock().unwrap() = Some(handle);
+
+        Ok(())
+    }
+
+    async fn stop(&self) -> anyhow::Result<()> {
+        if let Some(handle) = self.task.lock().unwrap().take() {
+            handle.abort();
+        }
+        self.node.lock().unwrap().take();
+        if let Some(advertiser) = self.advertiser.lock().await.take() {
+            advertiser.stop().await;
+        }
+        Ok(())
+    }
+
+    // BlueZ ประกาศซ้ำให้เองตลอดเวลาที่ Register อยู่
+    async fn announce(&self) -> anyhow::Result<()> { Ok(()) }
+
+    // IP ใน Service Data เปลี่ยนตาม Network
+    async fn refresh_addresses(&self) -> anyhow::Result<()> {
+        let node = self.node.lock().unwrap().clone();
+        if let Some(node) = node {
+            self.advertise(&node).await;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use std::net::Ipv4Addr;
+
+// ==========================================
+// BLE Advertiser (Peripheral)
+// ประกาศชื่อ DT-… + Service d7ea ให้ฝั่ง iOS Scan เจอเครื่อง Desktop ได้ด้วย
+// Service Data = ip(4) + port(2, big-endian) -> Legacy Advertising มีแค่ 31 byte จึงใส่ได้เฉพาะ IPv4
+// ตอนนี้รองรับ Linux (BlueZ ผ่าน D-Bus) เปิดด้วย feature "ble-advertise"
+// ==========================================
+
+pub const SERVICE_UUID: &str = "0000d7ea-0000-1000-8000-00805f9b34fb";
+// Flags + UUID 16-bit + Service Data ใช้ไปแล้ว เหลือที่ให้ชื่อประมาณนี้
+pub const MAX_LOCAL_NAME_LEN: usize = 12;
+
+pub fn encode_endpoint(ip: Ipv4Addr, port: u16) -> Vec<u8> {
+    let mut data = ip.octets().to_vec();
+    data.extend_from_slice(&port.to_be_bytes());
+    data
+}
+
+pub fn decode_endpoint(data: &[u8]) -> Option<(Ipv4Addr, u16)> {
+    let data: &[u8; 6] = data.get(..6)?.try_into().ok()?;
+    let ip = Ipv4Addr::new(data[0], data[1], data[2], data[3]);
+    let port = u16::from_be_bytes([data[4], data[5]]);
+    (!ip.is_unspecified() && port != 0).then_some((ip, port))
+}
+
+#[cfg(all(feature = "ble-advertise", target_os = "linux"))]
+pub use self::bluez::Advertiser;
+
+#[cfg(all(feature = "ble-advertise", target_os = "linux"))]
+mod bluez {
+    use std::collections::HashMap;
+    use std::ffi::CString;
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use anyhow::Context;
+    use dbus::arg::{PropMap, RefArg, Variant};
+    use dbus::channel::{MatchingReceiver, Sender, Token};
+    use dbus::message::MatchRule;
+    use dbus::nonblock::{Proxy, SyncConnection};
+    use dbus::nonblock::stdintf::org_freedesktop_dbus::ObjectManager;
+    use dbus::strings::ErrorName;
+    use dbus::Path;
+    use tokio::task::JoinHandle;
+
+    use super::{encode_endpoint, SERVICE_UUID};
+
+    const BLUEZ: &str = "org.bluez";
+    const ADVERTISING_MANAGER: &str = "org.bluez.LEAdvertisingManager1";
+    const ADVERT_PATH: &str = "/org/droptea/advertisement0";
+    const CALL_TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub struct Advertiser {
+        conn: Arc<SyncConnection>,
+        adapter: Path<'static>,
+        token: Token,
+        io: JoinHandle<()>,
+    }
+
+    // org.bluez.LEAdvertisement1 (BlueZ อ่านผ่าน Properties.GetAll ตอน Register)
+    fn properties(local_name: &str, endpoint: Option<(Ipv4Addr, u16)>) -> PropMap {
+        let mut props = PropMap::new();
+        props.insert("Type".into(), Variant(Box::new("peripheral".to_string())));
+        props.insert("ServiceUUIDs".into(), Variant(Box::new(vec![SERVICE_UUID.to_string()])));
+        props.insert("LocalName".into(), Variant(Box::new(local_name.to_string())));
+        if let Some((ip, port)) = endpoint {
+            let mut data: HashMap<String, Variant<Box<dyn RefArg>>> = HashMap::new();
+            data.insert(SERVICE_UUID.into(), Variant(Box::new(encode_endpoint(ip, port))));
+            props.insert("ServiceData".into(), Variant(Box::new(data)));
+        }
+        props
+    }
+
+    impl Advertiser {
+        pub async fn start(local_name: &str, endpoint: Option<(Ipv4Addr, u16)>) -> anyhow::Result<Self> {
+            let (resource, conn) = dbus_tokio::connection::new_system_sync().context("Failed to connect to system D-Bus")?;
+            let io = tokio::spawn(async move {
+                let err = resource.await;
+                tracing::warn!("BLE advertiser lost D-Bus connection: {}", err);
+            });
+
+            let root = Proxy::new(BLUEZ, "/", CALL_TIMEOUT, conn.clone());
+            let objects = root.get_managed_objects().await.context("BlueZ not available")?;
+            let Some(adapter) = objects.into_iter()
+                .find(|(_, ifaces)| ifaces.contains_key(ADVERTISING_MANAGER))
+                .map(|(path, _)| path)
+            else {
+                io.abort();
+                anyhow::bail!("No adapter supports LE advertising");
+            };
+
+            let name = local_name.to_string();
+            let token = conn.start_receive(MatchRule::new_method_call().with_path(ADVERT_PATH), Box::new(move |msg, conn| {
+                let reply = match (msg.interface().as_deref(), msg.member().as_deref()) {
+                    (Some("org.freedesktop.DBus.Properties"), Some("GetAll")) => msg.method_return().append1(properties(&name, endpoint)),
+                    (Some("org.bluez.LEAdvertisement1"), Some("Release")) => msg.method_return(),
+                    _ => msg.error(&ErrorName::from("org.freedesktop.DBus.Error.UnknownMethod"), &CString::new("Unknown method").unwrap()),
+                };
+                let _ = conn.send(reply);
+                true
+            }));
+
+            let manager = Proxy::new(BLUEZ, adapter.clone(), CALL_TIMEOUT, conn.clone());
+            let registered: Result<(), _> = manager
+                .method_call(ADVERTISING_MANAGER, "RegisterAdvertisement", (Path::from(ADVERT_PATH), PropMap::new()))
+                .await;
+            if let Err(e) = registered {
+                conn.stop_receive(token);
+                io.abort();
+                return Err(e).context("RegisterAdvertisement failed");
+            }
+            tracing::info!("🔵 BLE advertising as '{}' on {} ({:?})", local_name, adapter, endpoint);
+            Ok(Self { conn, adapter, token, io })
+        }
+
+        pub async fn stop(self) {
+            let manager = Proxy::new(BLUEZ, self.adapter.clone(), CALL_TIMEOUT, self.conn.clone());
+            let unregistered: Result<(), _> = manager
+                .method_call(ADVERTISING_MANAGER, "UnregisterAdvertisement", (Path::from(ADVERT_PATH),))
+                .await;
+            if let Err(e) = unregistered {
+                tracing::warn!("UnregisterAdvertisement failed: {}", e);
+            }
+            self.conn.stop_receive(self.token);
+            self.io.abort();
+        }
+    }
+}
+
+// Build ที่ไม่มี Advertiser: Scan ได้อย่างเดียวเหมือนเดิม
+#[cfg(not(all(feature = "ble-advertise", target_os = "linux")))]
+pub struct Advertiser;
+
+#[cfg(not(all(feature = "ble-advertise", target_os = "linux")))]
+impl Advertiser {
+    pub async fn start(_local_name: &str, _endpoint: Option<(Ipv4Addr, u16)>) -> anyhow::Result<Self> {
+        anyhow::bail!("BLE advertising is not supported in this build (feature 'ble-advertise', Linux only)")
+    }
+
+    pub async fn stop(self) {}
+}
@@ -0,0 +1,166 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex as StdMutex};
+use async_trait::async_trait;
+use anyhow::Context;
+use serde::{Serialize, Deserialize};
+use tracing::{debug, info};
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::core::compression;
+use crate::core::discovery::{mdns, DiscoveryBackend, DiscoveryInternalEvent, LocalNode, PeerProfile};
+use crate::core::transfer::PROTOCOL_VERSION;
+
+// ==========================================
+// UDP Broadcast Backend (แทน mDNS บน Network ที่บล็อก Multicast เช่น Wi-Fi บริษัท / Hotspot บางรุ่น)
+// ประกาศ Beacon (JSON) ไปที่ 255.255.255.255:port ทุกรอบ announce แล้วฟัง Port เดียวกัน
+// ID ของ Peer ตรงกับ Instance ของ mDNS -> เครื่องที่เห็นทั้งสองทางเป็น Peer เดียว
+// Privacy Mode ไม่ประกาศ (Beacon มีชื่อจริง) แต่ยังฟังของคนอื่น
+// ==========================================
+
+pub const DEFAULT_BROADCAST_PORT: u16 = 28190;
+const BEACON_VERSION: u32 = 1;
+// ใหญ่พอสำหรับ Beacon ที่มี Feature / Fingerprint ครบ
+const MAX_BEACON: usize = 2048;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Beacon {
+    v: u32,
+    id: String,
+    name: String,
+    port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ctl: Option<u16>,
+    #[serde(default)]
+    feat: Vec<String>,
+    #[serde(default)]
+    comp: String,
+    // blake3 hex ของ Cert (เหมือน TXT "fp")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fp: Option<String>,
+    #[serde(default)]
+    guest: bool,
+    #[serde(default)]
+    profile: PeerProfile,
+}
+
+impl Beacon {
+    fn of(node: &LocalNode) -> Self {
+        Self {
+            v: BEACON_VERSION,
+            id: node.id.clone(),
+            name: node.name.clone(),
+            port: node.port,
+            ctl: node.control_port,
+            feat: node.features.clone(),
+            comp: compression::advertised_algos(),
+            fp: node.profile.fingerprint.clone(),
+            guest: node.guest,
+            profile: PeerProfile {
+                os: Some(std::env::consts::OS.to_string()),
+                device_type: Some(node.profile.device_type),
+                protocol_version: Some(PROTOCOL_VERSION),
+                transports: node.profile.transports.clone(),
+                availability: Some(node.availability),
+            },
+        }
+    }
+
+    fn into_event(self, from: SocketAddr) -> DiscoveryInternalEvent {
+        let ip = from.ip().to_canonical().to_string();
+        // blake3 hex เท่านั้น (ค่าอื่นไม่เอามาเป็น Key)
+        let fingerprint = self.fp.filter(|fp| fp.len() == 64 && fp.bytes().all(|b| b.is_ascii_hexdigit())).map(|fp| fp.to_lowercase());
+        DiscoveryInternalEvent::MdnsFound {
+            id: mdns::fullname(&self.id),
+            name: self.name,
+            ip: ip.clone(),
+            addrs: vec![ip],
+            port: self.port,
+            compression: Some(compression::parse_algo_list(&self.comp)),
+            features: self.feat.into_iter().filter(|f| !f.is_empty()).collect(),
+            external_addr: None,
+            control_port: self.ctl,
+            private: false,
+            guest: self.guest,
+            fingerprint,
+            // ค่าจากอีกเครื่อง -> กรองแบบเดียวกับ TXT ของ mDNS
+            profile: PeerProfile {
+                os: self.profile.os.as_deref().and_then(mdns::txt_token),
+                transports: self.profile.transports.iter().filter_map(|t| mdns::txt_token(t)).collect(),
+                ..self.profile
+            },
+        }
+    }
+}
+
+pub struct BroadcastDiscovery {
+    port: u16,
+    socket: StdMutex<Option<Arc<UdpSocket>>>,
+    node: StdMutex<Option<LocalNode>>,
+    task: StdMutex<Option<JoinHandle<()>>>,
+}
+
+impl BroadcastDiscovery {
+    pub fn new(port: u16) -> Self {
+        Self { port, socket: StdMutex::new(None), node: StdMutex::new(None), task: StdMutex::new(None) }
+    }
+
+    // เครื่องเดียวกันรันหลาย Engine ได้ (Dev Mode) -> Reuse Address
+    fn bind(port: u16) -> anyhow::Result<UdpSocket> {
+        use socket2::{Domain, Socket, Type};
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, None)?;
+        socket.set_reuse_address(true)?;
+        socket.set_broadcast(true)?;
+        socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)).into())?;
+        socket.set_nonblocking(true)?;
+        Ok(UdpSocket::from_std(socket.into())?)
+    }
+}
+
+#[async_trait]
+impl DiscoveryBackend for BroadcastDiscovery {
+    fn name(&self) -> &str { "broadcast" }
+
+    async fn start(&self, node: &LocalNode, tx: mpsc::Sender<DiscoveryInternalEvent>) -> anyhow::Result<()> {
+        let socket = Arc::new(Self::bind(self.port).with_context(|| format!("Failed to bind broadcast port {}", self.port))?);
+        *self.socket.lock().unwrap() = Some(socket.clone());
+        *self.node.lock().unwrap() = Some(node.clone());
+        let (my_id, dev_mode) = (node.id.clone(), node.dev_mode);
+        let task = tokio::spawn(async move {
+            let mut buf = vec![0u8; MAX_BEACON];
+            while let Ok((n, from)) = socket.recv_from(&mut buf).await {
+                let Ok(beacon) = serde_json::from_slice::<Beacon>(&buf[..n]) else { continue };
+                // รุ่นใหม่กว่าที่อ่านไม่ออก / Beacon ของตัวเอง
+                if beacon.v != BEACON_VERSION || (!dev_mode && beacon.id == my_id) { continue; }
+                debug!("📣 Beacon from {} ({})", beacon.name, from);
+                if tx.send(beacon.into_event(from)).await.is_err() { break; }
+            }
+        });
+        if let Some(old) = self.task.lock().unwrap().replace(task) { old.abort(); }
+        info!("📣 Broadcast discovery on UDP {}", self.port);
+        self.announce().await
+    }
+
+    async fn stop(&self) -> anyhow::Result<()> {
+        if let Some(task) = self.task.lock().unwrap().take() { task.abort(); }
+        self.socket.lock().unwrap().take();
+        self.node.lock().unwrap().take();
+        Ok(())
+    }
+
+    async fn announce(&self) -> anyhow::Result<()> {
+        let socket = self.socket.lock().unwrap().clone();
+        let node = self.node.lock().unwrap().clone();
+        let (Some(socket), Some(node)) = (socket, node) else { return Ok(()) };
+        if node.privacy.is_some() { return Ok(()); }
+        let beacon = serde_json::to_vec(&Beacon::of(&node))?;
+        socket.send_to(&beacon, (Ipv4Addr::BROADCAST, self.port)).await.context("Broadcast failed")?;
+        Ok(())
+    }
+
+    async fn update_node(&self, node: &LocalNode) -> anyhow::Result<()> {
+        *self.node.lock().unwrap() = Some(node.clone());
+        self.announce().await
+    }
+}
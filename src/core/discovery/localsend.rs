@@ -0,0 +1,101 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex as StdMutex};
+use async_trait::async_trait;
+use anyhow::Context;
+use tracing::{debug, info};
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::core::discovery::{DiscoveryBackend, DiscoveryInternalEvent, LocalNode};
+use crate::core::localsend::{DeviceInfo, LocalSend, MULTICAST_ADDR};
+
+// ==========================================
+// LocalSend Discovery Backend (เปิดเองเมื่อมี [localsend])
+// ประกาศ DeviceInfo (announce = true) ไปที่ 224.0.0.167:<port> แล้วฟังกลุ่มเดียวกัน
+// เครื่อง LocalSend ตอบกลับด้วย POST /register (เข้า LocalSend::found ทาง Server) หรือ Multicast announce = false
+// เราตอบ Announce ของคนอื่นด้วย Multicast อย่างเดียว (ไม่ต้องมี HTTP Client ใน Backend)
+// ==========================================
+
+const MAX_ANNOUNCEMENT: usize = 4096;
+
+pub struct LocalSendDiscovery {
+    localsend: Arc<LocalSend>,
+    socket: StdMutex<Option<Arc<UdpSocket>>>,
+    node: StdMutex<Option<LocalNode>>,
+    task: StdMutex<Option<JoinHandle<()>>>,
+}
+
+impl LocalSendDiscovery {
+    pub fn new(localsend: Arc<LocalSend>) -> Self {
+        Self { localsend, socket: StdMutex::new(None), node: StdMutex::new(None), task: StdMutex::new(None) }
+    }
+
+    // แอป LocalSend บนเครื่องเดียวกันก็ฟัง Port นี้ -> Reuse Address
+    fn bind(port: u16) -> anyhow::Result<UdpSocket> {
+        use socket2::{Domain, Socket, Type};
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, None)?;
+        socket.set_reuse_address(true)?;
+        socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)).into())?;
+        socket.join_multicast_v4(&MULTICAST_ADDR, &Ipv4Addr::UNSPECIFIED)?;
+        socket.set_nonblocking(true)?;
+        Ok(UdpSocket::from_std(socket.into())?)
+    }
+
+    async fn send(socket: &UdpSocket, info: &DeviceInfo, port: u16) -> anyhow::Result<()> {
+        socket.send_to(&serde_json::to_vec(info)?, (MULTICAST_ADDR, port)).await.context("LocalSend multicast failed")?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DiscoveryBackend for LocalSendDiscovery {
+    fn name(&self) -> &str { "localsend" }
+
+    async fn start(&self, node: &LocalNode, tx: mpsc::Sender<DiscoveryInternalEvent>) -> anyhow::Result<()> {
+        let port = self.localsend.port();
+        let socket = Arc::new(Self::bind(port).with_context(|| format!("Failed to join LocalSend multicast on port {}", port))?);
+        *self.socket.lock().unwrap() = Some(socket.clone());
+        *self.node.lock().unwrap() = Some(node.clone());
+        self.localsend.attach(tx);
+        let (localsend, hidden) = (self.localsend.clone(), node.privacy.is_some());
+        let task = tokio::spawn(async move {
+            let mut buf = vec![0u8; MAX_ANNOUNCEMENT];
+            while let Ok((n, from)) = socket.recv_from(&mut buf).await {
+                let Ok(info) = serde_json::from_slice::<DeviceInfo>(&buf[..n]) else { continue };
+                if info.fingerprint == localsend.fingerprint() { continue; }
+                // v1 ใช้ "announcement" -> ไม่มี announce ถือว่าเป็นการตอบ
+                if info.announce == Some(true) && !hidden {
+                    debug!("📱 LocalSend announce from {} ({})", info.alias, from);
+                    let _ = Self::send(&socket, &localsend.info(Some(false)), localsend.port()).await;
+                }
+                localsend.found(info, from.ip()).await;
+            }
+        });
+        if let Some(old) = self.task.lock().unwrap().replace(task) { old.abort(); }
+        info!("📱 LocalSend discovery on {}:{}", MULTICAST_ADDR, port);
+        self.announce().await
+    }
+
+    async fn stop(&self) -> anyhow::Result<()> {
+        if let Some(task) = self.task.lock().unwrap().take() { task.abort(); }
+        self.socket.lock().unwrap().take();
+        self.node.lock().unwrap().take();
+        self.localsend.detach();
+        Ok(())
+    }
+
+    async fn announce(&self) -> anyhow::Result<()> {
+        let socket = self.socket.lock().unwrap().clone();
+        let node = self.node.lock().unwrap().clone();
+        let (Some(socket), Some(node)) = (socket, node) else { return Ok(()) };
+        // Privacy Mode: Announcement มีชื่อเครื่องจริง -> ฟังอย่างเดียว
+        if node.privacy.is_some() { return Ok(()); }
+        Self::send(&socket, &self.localsend.info(Some(true)), self.localsend.port()).await
+    }
+
+    async fn update_node(&self, node: &LocalNode) -> anyhow::Result<()> {
+        *self.node.lock().unwrap() = Some(node.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::Mutex as StdMutex;
+use async_trait::async_trait;
+use tracing::warn;
+use tokio::sync::mpsc;
+
+use crate::core::discovery::{DiscoveryBackend, DiscoveryInternalEvent, LocalNode, PeerProfile};
+
+// ==========================================
+// Manual Discovery Backend ([discovery] manual = ["host:port", ...])
+// Peer ที่ mDNS / Broadcast มองไม่เห็น (ต่าง Subnet / VPN / Multicast ถูกบล็อก)
+// ประกาศทุกตัวตอน start / announce (Resolve ชื่อใหม่ทุกรอบ) Health Check ตัดตัวที่ต่อไม่ติดเหมือน Peer อื่น
+// ==========================================
+
+pub struct ManualDiscovery {
+    peers: Vec<String>,
+    tx: StdMutex<Option<mpsc::Sender<DiscoveryInternalEvent>>>,
+}
+
+impl ManualDiscovery {
+    pub fn new(peers: Vec<String>) -> Self {
+        Self { peers, tx: StdMutex::new(None) }
+    }
+
+    // "host:port" -> ชื่อที่แสดง + Port (Host เป็น [v6] ได้)
+    fn split(entry: &str) -> Option<(&str, u16)> {
+        let (host, port) = entry.rsplit_once(':')?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        Some((host, port.parse().ok()?)).filter(|(h, _)| !h.is_empty())
+    }
+
+    /// ตรวจรูปแบบตอน Build (ยังไม่ Resolve)
+    pub fn is_valid(entry: &str) -> bool {
+        Self::split(entry).is_some()
+    }
+
+    async fn found(entry: &str) -> anyhow::Result<DiscoveryInternalEvent> {
+        let (host, port) = Self::split(entry).ok_or_else(|| anyhow::anyhow!("expected host:port"))?;
+        let resolved = tokio::net::lookup_host(entry).await?.next().ok_or_else(|| anyhow::anyhow!("no address"))?;
+        let ip = match resolved.ip() {
+            std::net::IpAddr::V6(v6) => format!("[{}]", v6),
+            v4 => v4.to_string(),
+        };
+        Ok(DiscoveryInternalEvent::MdnsFound {
+            id: format!("manual:{}", entry),
+            name: host.to_string(),
+            ip: ip.clone(),
+            addrs: vec![ip],
+            port,
+            compression: None,
+            features: vec![],
+            external_addr: None,
+            control_port: None,
+            private: false,
+            guest: false,
+            fingerprint: None,
+            profile: PeerProfile::default(),
+        })
+    }
+}
+
+#[async_trait]
+impl DiscoveryBackend for ManualDiscovery {
+    fn name(&self) -> &str { "manual" }
+
+    async fn start(&self, _node: &LocalNode, tx: mpsc::Sender<DiscoveryInternalEvent>) -> anyhow::Result<()> {
+        *self.tx.lock().unwrap() = Some(tx);
+        self.announce().await
+    }
+
+    async fn stop(&self) -> anyhow::Result<()> {
+        *self.tx.lock().unwrap() = None;
+        Ok(())
+    }
+
+    async fn announce(&self) -> anyhow::Result<()> {
+        let Some(tx) = self.tx.lock().unwrap().clone() else { return Ok(()) };
+        for entry in &self.peers {
+            match Self::found(entry).await {
+                Ok(event) => { let _ = tx.send(event).await; }
+                Err(e) => warn!("Manual peer '{}' unavailable: {}", entry, e),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::net::{IpAddr, UdpSocket};
+use std::time::Duration;
+use async_trait::async_trait;
+use tracing::warn;
+use mdns_sd::{ScopedIp, ServiceDaemon, ServiceInfo, ServiceEvent};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use anyhow::Context;
+
+use crate::core::discovery::{Availability, DeviceType, DiscoveryBackend, DiscoveryInternalEvent, LocalNode, PeerProfile};
+use crate::core::discovery::privacy::{RotatingToken, TXT_PRIVATE};
+use crate::core::compression;
+use crate::core::transfer::PROTOCOL_VERSION;
+use crate::core::utils;
+
+pub const SERVICE_TYPE: &str = "_droptea._tcp.local.";
+// Peer ประกาศหลาย Address (Ethernet + Wi-Fi + VPN) -> ลองต่อทุกตัวพร้อมกันแล้วเลือกตัวที่ต่อติด
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+// Guest Mode: Cert ชั่วคราว (Peer ไม่ควรจำ / ไม่ควรเชื่อชื่อนี้ข้าม Session)
+const TXT_GUEST: &str = "guest";
+const TXT_FINGERPRINT: &str = "fp";
+// Profile ของเครื่อง (ดู PeerProfile) ผู้ส่งไม่ต้องเดา OS ของปลายทางเอง
+const TXT_OS: &str = "os";
+const TXT_DEVICE: &str = "dev";
+const TXT_PROTOCOL: &str = "pv";
+const TXT_TRANSPORTS: &str = "tr";
+const TXT_AVAILABILITY: &str = "avail";
+// os / transport เป็นชื่อสั้นๆ ยาวกว่านี้หรือมีอักษรแปลก = ไม่เอา (ไปแสดงใน UI ต่อ)
+const MAX_TXT_TOKEN: usize = 32;
+
+pub(super) fn txt_token(s: &str) -> Option<String> {
+    let s = s.trim();
+    let valid = !s.is_empty() && s.len() <= MAX_TXT_TOKEN && s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+    valid.then(|| s.to_ascii_lowercase())
+}
+
+/// ID ของ Peer ที่ประกาศด้วย id นี้ (Full Name ของ Instance) Backend อื่นใช้ให้เป็น Peer เดียวกัน
+pub(super) fn fullname(id: &str) -> String {
+    format!("DropTea-{}.{}", id, SERVICE_TYPE)
+}
+
+// ==========================================
+// mDNS / DNS-SD Backend (LAN)
+// ==========================================
+
+pub struct MdnsBackend {
+    daemon: ServiceDaemon,
+    registered: StdMutex<Option<ServiceInfo>>,
+    node: StdMutex<Option<LocalNode>>,
+    // Address ที่ประกาศอยู่ (ใช้กรองตัวเองตอน Resolve) เปลี่ยนได้เมื่อสลับ Network
+    local_ips: Arc<StdMutex<Vec<IpAddr>>>,
+    // Instance Name ที่ประกาศอยู่ (กรองตัวเองตอน Resolve) Privacy Mode เปลี่ยนไปตาม Token
+    instance: Arc<StdMutex<String>>,
+    privacy: StdMutex<Option<RotatingToken>>,
+}
+
+impl MdnsBackend {
+    pub fn new() -> anyhow::Result<Self> {
+        let daemon = ServiceDaemon::new()
+            .map_err(|e| anyhow::anyhow!("Failed to create mDNS daemon: {}", e))?;
+        Ok(Self {
+            daemon,
+            registered: StdMutex::new(None),
+            node: StdMutex::new(None),
+            local_ips: Arc::new(StdMutex::new(vec![])),
+            instance: Arc::new(StdMutex::new(String::new())),
+            privacy: StdMutex::new(None),
+        })
+    }
+
+    pub(crate) fn get_local_ip() -> String {
+        match UdpSocket::bind("0.0.0.0:0") {
+            Ok(s) => match s.connect("8.8.8.8:80") {
+                Ok(_) => s.local_addr().map(|a| a.ip().to_string()).unwrap_or_else(|_| "127.0.0.1".to_string()),
+                Err(_) => "127.0.0.1".to_string(),
+            },
+            Err(_) => "127.0.0.1".to_string(),
+        }
+    }
+
+    // ทุก Interface ที่ใช้งานได้ (v4 + v6) แทนการเดา IP เดียวจาก Default Route
+    pub(crate) fn get_local_ips() -> Vec<IpAddr> {
+        let mut ips: Vec<IpAddr> = match if_addrs::get_if_addrs() {
+            Ok(ifaces) => ifaces.into_iter()
+                .filter(|i| matches!(i.oper_status, if_addrs::IfOperStatus::Up | if_addrs::IfOperStatus::Unknown))
+                .filter(|i| !i.is_loopback())
+                // 169.254.x.x = ไม่ได้ DHCP ประกาศไปก็ไม่มีใครต่อได้
+                .filter(|i| !(i.ip().is_ipv4() && i.is_link_local()))
+                .map(|i| i.ip())
+                .collect(),
+            Err(e) => {
+                warn!("Failed to enumerate interfaces: {}", e);
+                vec![]
+            }
+        };
+        ips.sort();
+        ips.dedup();
+        if ips.is_empty() {
+            ips.extend(Self::get_local_ip().parse::<IpAddr>());
+        }
+        ips
+    }
+
+    // v6 ก่อน (Global > Link-local ที่มี Zone) แล้วค่อย v4 / Dev Mode ยังเอา v4 ก่อนเพื่อ Simulator
+    fn address_rank(ip: &ScopedIp, dev_mode: bool) -> u8 {
+        let addr = ip.to_ip_addr();
+        let v6_rank = if !utils::is_link_local_v6(&addr) {
+            0
+        } else if ip.to_string().contains('%') {
+            1
+        } else {
+            // Link-local ที่ไม่รู้ Interface ต่อไม่ได้จริง
+            3
+        };
+        match (ip.is_ipv4(), dev_mode) {
+            (true, true) => 0,
+            (true, false) => 2,
+            (false, true) => v6_rank + 1,
+            (false, false) => v6_rank,
+        }
+    }
+
+    // ประกาศทุก Address ทั้ง A และ AAAA (mdns-sd ตอบเฉพาะ Address ที่อยู่บน Interface ที่ Query เข้ามา)
+    // ชื่อ Service เดิม -> register ซ้ำคือแทนที่ของเก่า Peer ไม่เห็นเราหายไป
+    fn register(&self, node: &LocalNode) -> anyhow::Result<()> {
+        let mut my_ips = Self::get_local_ips();
+        // Adapter ของ Hotspot บางตัว (Windows) รายงานสถานะไม่ตรง -> ใส่ให้แน่ใจว่าประกาศ
+        if let Some(addr) = node.hotspot_addr.filter(|a| !my_ips.contains(a)) {
+            my_ips.push(addr);
+        }
+
+        // 🕶️ Privacy Mode: ทุกอย่างที่บอกตัวตนได้ใช้ Token แทน
+        let token = self.privacy.lock().unwrap().as_ref().map(|t| t.current().to_string());
+        let (id, name) = match &token {
+            Some(token) => (token.clone(), token.clone()),
+            None => (node.id.clone(), node.name.clone()),
+        };
+        let instance_name = format!("DropTea-{}", id);
+        let host_name = format!("{}.local.", id);
+
+        let mut properties = HashMap::new();
+        properties.insert("id".to_string(), id);
+        properties.insert("ver".to_string(), "1.0".to_string());
+        properties.insert("name".to_string(), name);
+        if token.is_some() {
+            properties.insert(TXT_PRIVATE.to_string(), "1".to_string());
+        }
+        if node.guest {
+            properties.insert(TXT_GUEST.to_string(), "1".to_string());
+        }
+        // Privacy Mode ไม่บอก (Fingerprint ผูกตัวตนข้ามการหมุน Token ได้)
+        if let Some(fp) = node.profile.fingerprint.as_ref().filter(|_| token.is_none()) {
+            properties.insert(TXT_FINGERPRINT.to_string(), fp.clone());
+        }
+        properties.insert(TXT_OS.to_string(), std::env::consts::OS.to_string());
+        properties.insert(TXT_DEVICE.to_string(), node.profile.device_type.as_str().to_string());
+        properties.insert(TXT_PROTOCOL.to_string(), PROTOCOL_VERSION.to_string());
+        if !node.profile.transports.is_empty() {
+            properties.insert(TXT_TRANSPORTS.to_string(), node.profile.transports.join(","));
+        }
+        properties.insert(TXT_AVAILABILITY.to_string(), node.availability.as_str().to_string());
+        properties.insert("comp".to_string(), compression::advertised_algos());
+        if !node.features.is_empty() {
+            properties.insert("feat".to_string(), node.features.join(","));
+        }
+        if let Some(ext) = &node.external_addr {
+            properties.insert("ext".to_string(), ext.clone());
+        }
+        if let Some(ctl) = node.control_port {
+            properties.insert("ctl".to_string(), ctl.to_string());
+        }
+
+        let my_info = ServiceInfo::new(
+            SERVICE_TYPE, &instance_name, &host_name, &my_ips[..], node.port, properties
+        ).context("Failed to create ServiceInfo")?;
+
+        self.daemon.register(my_info.clone()).context("Failed to register mDNS")?;
+        *self.registered.lock().unwrap() = Some(my_info);
+        *self.local_ips.lock().unwrap() = my_ips;
+        *self.instance.lock().unwrap() = instance_name;
+        Ok(())
+    }
+
+    // candidates เรียงตามความชอบแล้ว: คืนทุกตัวที่ต่อ TCP ติด (ตัวแรก = ตัวที่เลือก) ถ้าไม่มีตัวไหนติดเลย
+    // (เช่นโหมด QUIC ที่ไม่มี TCP Listener หรือ Firewall) ก็ใช้ตัวที่ชอบที่สุดไปก่อน
+    async fn pick_reachable(mut candidates: Vec<String>, port: u16) -> Vec<String> {
+        if candidates.len() > 1 {
+            let probes = candidates.iter().map(|host| async move {
+                let Some((ip, scope_id)) = utils::parse_scoped_ip(host) else { return false };
+                let addr = utils::scoped_socket_addr(ip, port, scope_id);
+                matches!(tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect(addr)).await, Ok(Ok(_)))
+            });
+            let reachable = futures::future::join_all(probes).await;
+            let alive: Vec<String> = candidates.iter().zip(reachable).filter(|(_, ok)| *ok).map(|(c, _)| c.clone()).collect();
+            if !alive.is_empty() {
+                return alive;
+            }
+        }
+        candidates.truncate(1);
+        candidates
+    }
+}
+
+#[async_trait]
+impl DiscoveryBackend for MdnsBackend {
+    fn name(&self) -> &str { "mdns" }
+
+    async fn start(&self, node: &LocalNode, tx: mpsc::Sender<DiscoveryInternalEvent>) -> anyhow::Result<()> {
+        let daemon = self.daemon.clone();
+        *self.privacy.lock().unwrap() = node.privacy.map(RotatingToken::new);
+        self.register(node)?;
+        *self.node.lock().unwrap() = Some(node.clone());
+        let my_ips = self.local_ips.clone();
+        let rt = tokio::runtime::Handle::current();
+        let my_instance = self.instance.clone();
+        let dev_mode = node.dev_mode;
+
+        let receiver = daemon.browse(SERVICE_TYPE).context("Failed to browse mDNS")?;
+
+        std::thread::spawn(move || {
+            while let Ok(event) = receiver.recv() {
+                match event {
+                    ServiceEvent::ServiceResolved(info) => {
+                        if !dev_mode && info.get_fullname().contains(my_instance.lock().unwrap().as_str()) { continue; }
+
+                        // 🟢 UPDATED: อนุญาตให้ connect ตัวเองได้ถ้าเป็น Dev Mode (สำหรับ Simulator)
+                        // ตัด Address ที่ซ้ำกับของเราออก (เช่น Bridge ของ Docker) ไม่งั้น Probe จะต่อติดตัวเอง
+                        let mut addrs: Vec<&ScopedIp> = info.get_addresses().iter()
+                            .filter(|ip| dev_mode || !my_ips.lock().unwrap().contains(&ip.to_ip_addr()))
+                            .collect();
+                        addrs.sort_by_key(|ip| Self::address_rank(ip, dev_mode));
+
+                        if !addrs.is_empty() {
+                            let id = info.get_fullname().to_string();
+                            
+                            // 🟢 UPDATED: จัด Format IP ให้เป็น String ที่ถูกต้อง (เติม [] ถ้าเป็น IPv6, Link-local มี %zone ติดมาด้วย)
+                            let candidates: Vec<String> = addrs.iter().map(|ip| if ip.is_ipv6() {
+                                format!("[{}]", ip)
+                            } else {
+                                ip.to_string()
+                            }).collect();
+                            
+                            let port = info.get_port();
+                            let props = info.get_properties();
+                            let raw_name = props.get("name").map(|v| v.to_string()).unwrap_or_else(|| "Unknown".to_string());
+                            let clean_name = raw_name.split('=').next_back().unwrap_or(&raw_name).trim().to_string();
+                            let algos = info.get_property_val_str("comp").map(compression::parse_algo_list);
+                            let features = info.get_property_val_str("feat")
+                                .map(|f| f.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+                                .unwrap_or_default();
+                            let external_addr = info.get_property_val_str("ext").map(|s| s.to_string());
+                            let control_port = info.get_property_val_str("ctl").and_then(|s| s.parse().ok());
+                            let private = info.get_property_val_str(TXT_PRIVATE) == Some("1");
+                            let guest = info.get_property_val_str(TXT_GUEST) == Some("1");
+                            // blake3 hex เท่านั้น (ค่าอื่นไม่เอามาเป็น Key)
+                            let fingerprint = info.get_property_val_str(TXT_FINGERPRINT)
+                                .filter(|fp| fp.len() == 64 && fp.bytes().all(|b| b.is_ascii_hexdigit()))
+                                .map(str::to_lowercase);
+                            let profile = PeerProfile {
+                                os: info.get_property_val_str(TXT_OS).and_then(txt_token),
+                                device_type: info.get_property_val_str(TXT_DEVICE).and_then(DeviceType::from_name),
+                                protocol_version: info.get_property_val_str(TXT_PROTOCOL).and_then(|v| v.parse().ok()),
+                                transports: info.get_property_val_str(TXT_TRANSPORTS)
+                                    .map(|t| t.split(',').filter_map(txt_token).collect())
+                                    .unwrap_or_default(),
+                                availability: info.get_property_val_str(TXT_AVAILABILITY).and_then(Availability::from_name),
+                            };
+
+                            let tx = tx.clone();
+                            rt.spawn(async move {
+                                let addrs = Self::pick_reachable(candidates, port).await;
+                                let ip = addrs[0].clone();
+                                let _ = tx.send(DiscoveryInternalEvent::MdnsFound { id, name: clean_name, ip, addrs, port, compression: algos, features, external_addr, control_port, private, guest, fingerprint, profile }).await;
+                            });
+                        }
+                    },
+                    ServiceEvent::ServiceRemoved(_type, fullname) => {
+                         let _ = tx.blocking_send(DiscoveryInternalEvent::MdnsLost { id: fullname });
+                    },
+                    _ => {}
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn stop(&self) -> anyhow::Result<()> {
+        self.node.lock().unwrap().take();
+        if let Some(info) = self.registered.lock().unwrap().take() {
+            if let Err(e) = self.daemon.unregister(info.get_fullname()) {
+                warn!("mDNS unregister failed: {}", e);
+            }
+        }
+        // ปิด Browse -> Thread ตัวรับ Event จะจบเองเมื่อ Channel ถูกปิด
+        let _ = self.daemon.stop_browse(SERVICE_TYPE);
+        Ok(())
+    }
+
+    async fn announce(&self) -> anyhow::Result<()> {
+        // 🕶️ ครบรอบเปลี่ยน Token -> ถอนชื่อเก่าก่อน Peer จะได้ไม่เห็นเครื่องเดียวกันสองตัว
+        let rotated = self.privacy.lock().unwrap().as_mut().is_some_and(RotatingToken::rotate_if_due);
+        let node = self.node.lock().unwrap().clone();
+        if let (true, Some(node)) = (rotated, node) {
+            if let Some(old) = self.registered.lock().unwrap().take() {
+                if let Err(e) = self.daemon.unregister(old.get_fullname()) {
+                    warn!("mDNS unregister failed: {}", e);
+                }
+            }
+            return self.register(&node);
+        }
+        let info = self.registered.lock().unwrap().clone();
+        if let Some(info) = info {
+            self.daemon.register(info).context("mDNS re-announce failed")?;
+        }
+        Ok(())
+    }
+
+    async fn refresh_addresses(&self) -> anyhow::Result<()> {
+        let node = self.node.lock().unwrap().clone();
+        if let Some(node) = node {
+            self.register(&node)?;
+        }
+        Ok(())
+    }
+
+    // TXT เปลี่ยน -> ลงทะเบียนชื่อเดิมซ้ำ (Peer ได้ ServiceResolved ใหม่)
+    async fn update_node(&self, node: &LocalNode) -> anyhow::Result<()> {
+        let mut current = self.node.lock().unwrap();
+        let Some(current) = current.as_mut() else { return Ok(()) };
+        *current = node.clone();
+        self.register(node)
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::core::discovery::{DiscoveryBackend, DiscoveryInternalEvent, LocalNode, PeerProfile};
+
+// ==========================================
+// Mock Discovery Backend (คู่กับ MemoryTransport)
+// ไม่ประกาศ / Scan อะไรจริง ผู้ทดสอบยิง DiscoveryInternalEvent เข้า Engine เองด้วย inject()
+// ใส่ผ่าน DropTeaBuilder::discovery_backend -> แทน mDNS / BLE ทั้งหมด
+// ==========================================
+
+#[derive(Default)]
+pub struct MockDiscovery {
+    tx: StdMutex<Option<mpsc::Sender<DiscoveryInternalEvent>>>,
+    // สิ่งที่ Engine ประกาศล่าสุด (ชื่อ / Port / Availability ...)
+    node: StdMutex<Option<LocalNode>>,
+    announces: AtomicUsize,
+}
+
+impl MockDiscovery {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// false = Engine ยังไม่ start_service หรือหยุดไปแล้ว
+    pub async fn inject(&self, event: DiscoveryInternalEvent) -> bool {
+        let tx = self.tx.lock().unwrap().clone();
+        match tx {
+            Some(tx) => tx.send(event).await.is_ok(),
+            None => false,
+        }
+    }
+
+    /// Peer บน LAN แบบที่ mDNS เห็น (ไม่มี Profile / Compression / Control Port)
+    pub fn peer(id: &str, name: &str, ip: &str, port: u16) -> DiscoveryInternalEvent {
+        DiscoveryInternalEvent::MdnsFound {
+            id: id.to_string(),
+            name: name.to_string(),
+            ip: ip.to_string(),
+            addrs: vec![ip.to_string()],
+            port,
+            compression: None,
+            features: vec![],
+            external_addr: None,
+            control_port: None,
+            private: false,
+            guest: false,
+            fingerprint: None,
+            profile: PeerProfile::default(),
+        }
+    }
+
+    /// สิ่งที่ Engine อีกตัวประกาศ (local_node ของ Mock ฝั่งนั้น) ในรูปที่ mDNS เห็นที่ ip -> Port / Control Port / Features ตรงของจริง
+    pub fn announced(node: &LocalNode, ip: &str) -> DiscoveryInternalEvent {
+        DiscoveryInternalEvent::MdnsFound {
+            id: format!("{}._droptea._tcp.local.", node.name),
+            name: node.name.clone(),
+            ip: ip.to_string(),
+            addrs: vec![ip.to_string()],
+            port: node.port,
+            compression: Some(crate::core::compression::SUPPORTED_ALGOS.to_vec()),
+            features: node.features.clone(),
+            external_addr: None,
+            control_port: node.control_port,
+            private: false,
+            guest: node.guest,
+            fingerprint: node.profile.fingerprint.clone(),
+            profile: PeerProfile::default(),
+        }
+    }
+
+    pub fn local_node(&self) -> Option<LocalNode> {
+        self.node.lock().unwrap().clone()
+    }
+
+    pub fn announces(&self) -> usize {
+        self.announces.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl DiscoveryBackend for MockDiscovery {
+    fn name(&self) -> &str { "mock" }
+
+    async fn start(&self, node: &LocalNode, tx: mpsc::Sender<DiscoveryInternalEvent>) -> anyhow::Result<()> {
+        *self.node.lock().unwrap() = Some(node.clone());
+        *self.tx.lock().unwrap() = Some(tx);
+        Ok(())
+    }
+
+    async fn stop(&self) -> anyhow::Result<()> {
+        *self.tx.lock().unwrap() = None;
+        Ok(())
+    }
+
+    async fn announce(&self) -> anyhow::Result<()> {
+        self.announces.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn update_node(&self, node: &LocalNode) -> anyhow::Result<()> {
+        *self.node.lock().unwrap() = Some(node.clone());
+        Ok(())
+    }
+}
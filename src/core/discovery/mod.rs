@@ -0,0 +1,866 @@
+pub mod mdns;
+pub mod ble;
+pub mod ble_advertise;
+pub mod privacy;
+pub mod mock;
+pub mod manual;
+pub mod broadcast;
+pub mod localsend;
+pub mod registry;
+
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Instant, Duration};
+use std::net::IpAddr; // 🟢 UPDATED: เพิ่ม IpAddr
+use tracing::{info, debug, warn, Instrument};
+use tokio::time::timeout;
+use tokio::sync::{mpsc, oneshot, watch};
+use async_trait::async_trait;
+use serde::{Serialize, Deserialize};
+
+// 📦 Dependencies
+use dashmap::DashMap; 
+use rand::Rng;       
+
+use crate::core::transfer::{DynTransport, TransferCallback, REJECT_BUSY, REJECT_RECEIVING_DISABLED};
+use crate::core::cancel::CancellationToken;
+use crate::core::compression::CompressionAlgo;
+use crate::core::control;
+use crate::core::handshake::ConnectionInfo;
+use crate::core::utils;
+use crate::core::peer_registry::PeerRegistry;
+
+pub use self::ble::BleBackend;
+pub use self::mdns::MdnsBackend;
+pub use self::mock::MockDiscovery;
+pub use self::manual::ManualDiscovery;
+pub use self::broadcast::BroadcastDiscovery;
+pub use self::localsend::LocalSendDiscovery;
+pub use self::registry::{register_discovery_factory, DiscoveryFactory, DynDiscoveryFactory};
+
+// ==========================================
+// 🎯 CONFIGURATION
+// ==========================================
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+pub const PEER_STALE_THRESHOLD: Duration = Duration::from_secs(15);
+// วัด RTT ก่อนส่งไฟล์ใหญ่ (ดู fastest_addr)
+const PATH_PROBE_SAMPLES: usize = 3;
+const PATH_PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+// Presence backoff: ประกาศถี่ตอน UI เปิดอยู่ แล้วค่อยๆ ห่างขึ้นเมื่อ Idle
+const ANNOUNCE_INTERVAL_ACTIVE_SEC: u64 = 5;
+const ANNOUNCE_INTERVAL_IDLE_MAX_SEC: u64 = 300;
+
+// ==========================================
+// 1. Data Structures
+// ==========================================
+
+/// Health Check ของ Peer บน LAN (ตั้งได้จาก Config)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthCheck {
+    // รอบการตรวจ Peer ที่เงียบไป
+    pub interval: Duration,
+    // ไม่ได้ยินจาก Peer นานเกินนี้ -> เริ่ม Ping (พลาด 3 ครั้งถือว่าหาย)
+    pub stale_after: Duration,
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self { interval: HEALTH_CHECK_INTERVAL, stale_after: PEER_STALE_THRESHOLD }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransportType {
+    Lan,
+    BleOnly,
+    Hybrid,
+}
+
+impl std::fmt::Display for TransportType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransportType::Lan => write!(f, "LAN"),
+            TransportType::BleOnly => write!(f, "BLE"),
+            TransportType::Hybrid => write!(f, "HYBRID"),
+        }
+    }
+}
+
+/// ชนิดเครื่องที่ประกาศใน mDNS (TXT "dev") ให้ UI เลือก Icon ได้
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceType {
+    Phone,
+    Laptop,
+    Desktop,
+    Server,
+}
+
+impl DeviceType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Phone => "phone",
+            Self::Laptop => "laptop",
+            Self::Desktop => "desktop",
+            Self::Server => "server",
+        }
+    }
+
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "phone" => Some(Self::Phone),
+            "laptop" => Some(Self::Laptop),
+            "desktop" => Some(Self::Desktop),
+            "server" => Some(Self::Server),
+            _ => None,
+        }
+    }
+
+    /// เดาจาก OS / แบตเตอรี่ (Server เดาไม่ได้ ต้องตั้ง [server] device_type เอง)
+    pub fn detect() -> Self {
+        if cfg!(any(target_os = "ios", target_os = "android")) {
+            return Self::Phone;
+        }
+        // Linux: มีแบตเตอรี่ใน sysfs = Laptop / OS อื่นไม่รู้ -> Desktop
+        let battery = std::fs::read_dir("/sys/class/power_supply")
+            .map(|dir| dir.flatten().any(|e| e.file_name().to_string_lossy().starts_with("BAT")))
+            .unwrap_or(false);
+        if battery { Self::Laptop } else { Self::Desktop }
+    }
+}
+
+/// สถานะรับไฟล์ที่ประกาศใน mDNS (TXT "avail") ผู้ส่งเห็นก่อน Connect
+/// Busy = รับเต็มทุกช่อง (ประกาศให้เอง) หรือ User ตั้งเอง, ReceivingDisabled = ปิดรับ
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Availability {
+    #[default]
+    Available,
+    Busy,
+    ReceivingDisabled,
+}
+
+impl Availability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Available => "available",
+            Self::Busy => "busy",
+            Self::ReceivingDisabled => "receiving_disabled",
+        }
+    }
+
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "available" => Some(Self::Available),
+            "busy" => Some(Self::Busy),
+            "receiving_disabled" | "disabled" => Some(Self::ReceivingDisabled),
+            _ => None,
+        }
+    }
+
+    // offset ของ ACK_UNAVAILABLE
+    pub fn code(&self) -> u64 {
+        match self {
+            Self::Available => 0,
+            Self::Busy => 1,
+            Self::ReceivingDisabled => 2,
+        }
+    }
+
+    pub fn from_code(code: u64) -> Self {
+        match code {
+            0 => Self::Available,
+            2 => Self::ReceivingDisabled,
+            _ => Self::Busy,
+        }
+    }
+
+    /// เหตุผลที่ผู้รับปฏิเสธทันที (None = รับได้ตามปกติ)
+    pub fn reject_reason(&self) -> Option<&'static str> {
+        match self {
+            Self::Available => None,
+            Self::Busy => Some(REJECT_BUSY),
+            Self::ReceivingDisabled => Some(REJECT_RECEIVING_DISABLED),
+        }
+    }
+}
+
+/// สิ่งที่ Peer ประกาศเกี่ยวกับตัวเองใน mDNS (Peer รุ่นเก่า / BLE = ว่างทั้งหมด)
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PeerProfile {
+    // std::env::consts::OS ของ Peer เช่น "ios", "android", "linux" (TXT "os")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_type: Option<DeviceType>,
+    // PROTOCOL_VERSION ของ Peer (TXT "pv")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol_version: Option<u32>,
+    // Transport ที่ Peer รับได้ เช่น ["tcp"] (TXT "tr" ชื่อเดียวกับ [server] mode)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transports: Vec<String>,
+    // None = Peer รุ่นเก่าที่ไม่ได้ประกาศ (ถือว่ารับได้)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub availability: Option<Availability>,
+}
+
+impl PeerProfile {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PeerInfo {
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+    pub ip: Option<IpAddr>, // 🟢 UPDATED: เปลี่ยนจาก String เป็น IpAddr (Strong Type)
+    pub scope_id: u32, // Interface ของ IPv6 Link-local (0 = ไม่มี)
+    pub addrs: Vec<(IpAddr, u32)>, // ทุก Address ที่ต่อติดจาก mDNS รอบล่าสุด (LAN + Hotspot ฯลฯ) รวม ip
+    pub port: u16,
+    pub ssid: Option<String>,
+    pub ble_mac: Option<String>,
+    pub transport: TransportType,
+    pub last_seen: Instant,
+    pub missed_pings: u32,
+    pub compression: Option<Vec<CompressionAlgo>>, // None = ไม่ได้ประกาศ (Peer รุ่นเก่า / BLE)
+    pub features: Vec<String>, // ความสามารถเสริมจาก TXT "feat" เช่น "dedup"
+    pub external_addr: Option<String>, // ip:port ที่ Router Map ไว้ให้ (TXT "ext")
+    pub control_port: Option<u16>, // Control Channel (TXT "ctl") None = Peer รุ่นเก่า / BLE
+    pub guest: bool, // ใช้ตัวตนชั่วคราว (TXT "guest") Fingerprint เปลี่ยนทุกครั้งที่เปิดแอป
+    pub profile: PeerProfile, // OS / ชนิดเครื่อง / Protocol / Transport ที่ Peer ประกาศ
+}
+
+impl PeerInfo {
+    /// IP พร้อม Zone (เช่น fe80::1%3) ส่งต่อให้ connect ได้ทันที
+    pub fn host(&self) -> Option<String> {
+        self.ip.map(|ip| utils::format_scoped_ip(ip, self.scope_id))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ActivityState {
+    Active,
+    Idle,
+}
+
+pub enum DiscoveryInternalEvent {
+    // addrs = ทุก Address ที่ต่อติด เรียงตามความชอบ (ip = ตัวแรก)
+    // private = ประกาศด้วย Token (ชื่อจริงต้องขอผ่าน privacy::identify), guest = ตัวตนชั่วคราว
+    // fingerprint = Cert ที่ Peer ประกาศเอง (ยังไม่ได้ยืนยันด้วย TLS) ใช้เป็น Key ของ Peer Registry
+    MdnsFound { id: String, name: String, ip: String, addrs: Vec<String>, port: u16, compression: Option<Vec<CompressionAlgo>>, features: Vec<String>, external_addr: Option<String>, control_port: Option<u16>, private: bool, guest: bool, fingerprint: Option<String>, profile: PeerProfile },
+    MdnsLost { id: String },
+    // addr = ip:port จาก Service Data (เฉพาะ Peer ที่ Advertise ด้วย DropTea รุ่นที่รองรับ)
+    BleFound { id: String, name: String, ssid: Option<String>, mac: String, addr: Option<(IpAddr, u16)> },
+}
+
+/// ข้อมูลของเครื่องเรา ที่ Backend ใช้ประกาศตัว
+#[derive(Clone, Debug)]
+pub struct LocalNode {
+    pub id: String,
+    pub name: String,
+    pub port: u16,
+    pub dev_mode: bool,
+    pub features: Vec<String>,
+    pub external_addr: Option<String>,
+    pub hotspot_addr: Option<IpAddr>,
+    pub control_port: Option<u16>,
+    // Some = ประกาศด้วย Token สุ่มที่เปลี่ยนทุกช่วงนี้แทนชื่อจริง (ดู privacy.rs)
+    pub privacy: Option<Duration>,
+    // Guest Mode: Cert ชั่วคราว ประกาศให้ Peer รู้ว่าไม่ต้องจำเครื่องนี้
+    pub guest: bool,
+    pub profile: LocalProfile,
+    pub availability: Availability,
+}
+
+/// สิ่งที่เครื่องเราประกาศเพิ่มใน mDNS (Peer เห็นเป็น PeerProfile)
+#[derive(Clone, Debug)]
+pub struct LocalProfile {
+    // Fingerprint ของ Cert เรา (Peer ใช้จำเราข้ามการเปลี่ยนชื่อเครื่อง) None = โหมดที่ไม่มี TLS
+    pub fingerprint: Option<String>,
+    pub device_type: DeviceType,
+    // Transport ที่ Listener ของเรารับได้
+    pub transports: Vec<String>,
+}
+
+// ==========================================
+// 2. Discovery Backend (mDNS, BLE, Manual, Broadcast หรือ Custom จาก Embedder ดู registry.rs)
+// ==========================================
+
+/// แหล่งค้นหา Peer หนึ่งแหล่ง ส่งผลลัพธ์กลับผ่าน `DiscoveryInternalEvent`
+/// (แนวเดียวกับ `Transport` ใน transfer.rs)
+#[async_trait]
+pub trait DiscoveryBackend: Send + Sync + 'static {
+    fn name(&self) -> &str;
+    async fn start(&self, node: &LocalNode, tx: mpsc::Sender<DiscoveryInternalEvent>) -> anyhow::Result<()>;
+    async fn stop(&self) -> anyhow::Result<()>;
+    /// ถูกเรียกตามรอบ Presence Backoff (ดู `ActivityState`)
+    async fn announce(&self) -> anyhow::Result<()>;
+    /// Address ของเครื่องเปลี่ยน (สลับ Wi-Fi ฯลฯ) -> ประกาศใหม่ด้วย Address ชุดใหม่
+    async fn refresh_addresses(&self) -> anyhow::Result<()> {
+        self.announce().await
+    }
+    /// ข้อมูลที่ประกาศเปลี่ยนระหว่างทำงาน (เช่น Availability) Backend ที่ไม่ได้ประกาศอะไรไม่ต้องทำ
+    async fn update_node(&self, _node: &LocalNode) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+pub type DynDiscoveryBackend = Arc<dyn DiscoveryBackend>;
+type LanWaiter = oneshot::Sender<(String, u16)>;
+
+// ==========================================
+// 3. Discovery Engine
+// ==========================================
+
+#[derive(Clone)]
+pub struct DiscoveryEngine<CB: TransferCallback> {
+    pub callback: CB,
+    pub known_peers: Arc<DashMap<String, PeerInfo>>,
+    backends: Arc<StdMutex<Vec<DynDiscoveryBackend>>>,
+    local_node: Arc<StdMutex<Option<LocalNode>>>,
+    event_tx: mpsc::Sender<DiscoveryInternalEvent>,
+    activity_tx: Arc<watch::Sender<ActivityState>>,
+    // Transfer ที่รอ Peer BLE-only โผล่บน LAN (ดู wait_for_lan)
+    lan_waiters: Arc<DashMap<String, Vec<LanWaiter>>>,
+    health: HealthCheck,
+    privacy: Option<Duration>,
+    guest: bool,
+    // ขอชื่อจริงของ Peer ที่เปิด Privacy Mode (None = Transport ไม่มี Cert ให้ยืนยันตัวตน)
+    identify: Option<Arc<DynTransport>>,
+    profile: LocalProfile,
+    // สถานะที่ประกาศอยู่ (ตั้งได้ก่อน start ด้วย)
+    availability: Arc<StdMutex<Availability>>,
+    // ชื่อ / IP / เวลาที่เห็นล่าสุด ของ Peer ที่เจอ (ชื่อเล่นเติมตอนส่ง Event ใน RegistryHandler)
+    registry: Arc<PeerRegistry>,
+}
+
+impl<CB: TransferCallback + Clone + Send + Sync + 'static> DiscoveryEngine<CB> {
+
+    /// backends = None ใช้ mDNS + BLE, Some = ใช้เฉพาะชุดนี้ (เช่น MockDiscovery ตอนทดสอบ)
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(callback: CB, health: HealthCheck, privacy: Option<Duration>, guest: bool, identify: Option<Arc<DynTransport>>, profile: LocalProfile, registry: Arc<PeerRegistry>, backends: Option<Vec<DynDiscoveryBackend>>) -> anyhow::Result<(Self, mpsc::Receiver<DiscoveryInternalEvent>)> {
+        let backends = match backends {
+            Some(backends) => backends,
+            None => vec![Arc::new(MdnsBackend::new()?) as DynDiscoveryBackend, Arc::new(BleBackend::new())],
+        };
+
+        let (tx, rx) = mpsc::channel(100);
+        let (activity_tx, _) = watch::channel(ActivityState::Active);
+
+        Ok((Self {
+            callback,
+            known_peers: Arc::new(DashMap::new()), 
+            backends: Arc::new(StdMutex::new(backends)),
+            local_node: Arc::new(StdMutex::new(None)),
+            event_tx: tx,
+            activity_tx: Arc::new(activity_tx),
+            lan_waiters: Arc::new(DashMap::new()),
+            health,
+            privacy,
+            guest,
+            identify,
+            profile,
+            availability: Arc::new(StdMutex::new(Availability::Available)),
+            registry,
+        }, rx))
+    }
+
+    /// เพิ่ม Backend ของ Embedder (เช่น Cloud Directory) ถ้า Engine เริ่มไปแล้วจะ Start ให้ทันที
+    pub async fn register_backend(&self, backend: DynDiscoveryBackend) -> anyhow::Result<()> {
+        let node = self.local_node.lock().unwrap().clone();
+        if let Some(node) = node {
+            backend.start(&node, self.event_tx.clone()).await?;
+        }
+        info!("🧩 Discovery backend registered: {}", backend.name());
+        self.backends.lock().unwrap().push(backend);
+        Ok(())
+    }
+
+    fn backends_snapshot(&self) -> Vec<DynDiscoveryBackend> {
+        self.backends.lock().unwrap().clone()
+    }
+
+    /// Peer ที่ ip เป็น Address ไหนก็ได้ของมัน (ผู้เรียกอาจส่งด้วย Address ที่ไม่ใช่ตัวที่เลือกไว้ เช่น Hotspot)
+    pub fn find_peer_by_addr(&self, ip: IpAddr, port: u16) -> Option<PeerInfo> {
+        self.known_peers.iter()
+            .find(|r| r.value().port == port && (r.value().ip == Some(ip) || r.value().addrs.iter().any(|(a, _)| *a == ip)))
+            .map(|r| r.value().clone())
+    }
+
+    pub fn local_node(&self) -> Option<LocalNode> {
+        self.local_node.lock().unwrap().clone()
+    }
+
+    /// Address ล่าสุดของ Peer (ใช้ตอน Transfer ที่รอคิวอยู่ได้เริ่มจริง)
+    pub fn current_addr(&self, id: &str) -> Option<(String, u16)> {
+        let peer = self.known_peers.get(id)?;
+        Some((peer.host()?, peer.port))
+    }
+
+    /// รอจน Peer มี Address บน LAN (เช่นเห็นแค่ทาง BLE แล้วต่อมาเจอผ่าน mDNS) คืน None เมื่อหมดเวลา
+    pub async fn wait_for_lan(&self, id: &str, limit: Duration) -> Option<(String, u16)> {
+        if let Some(addr) = self.current_addr(id) { return Some(addr); }
+        let (tx, rx) = oneshot::channel();
+        self.lan_waiters.entry(id.to_string()).or_default().push(tx);
+        // mDNS อาจเจอระหว่างลงทะเบียน -> เช็คซ้ำอีกรอบ
+        if let Some(addr) = self.current_addr(id) { return Some(addr); }
+        timeout(limit, rx).await.ok()?.ok()
+    }
+
+    /// Peer ที่มีหลาย Address (เช่น LAN + Hotspot): วัด RTT ทุกเส้นแล้วใช้เส้นที่เร็วสุดต่อจากนี้
+    /// None = มีเส้นเดียว / วัดไม่ได้เลย (ใช้ current_addr ตามเดิม)
+    pub async fn fastest_addr(&self, id: &str) -> Option<(String, u16)> {
+        let peer = self.known_peers.get(id)?.clone();
+        if peer.addrs.len() < 2 { return None; }
+        let probes = peer.addrs.iter().map(|&(ip, scope_id)| async move {
+            // Control Channel วัดได้แม่นกว่า (PING ไปกลับจริง) Peer รุ่นเก่าใช้เวลา TCP Connect แทน
+            let rtt = match peer.control_port {
+                Some(ctl) => control::rtt(utils::scoped_socket_addr(ip, ctl, scope_id), PATH_PROBE_SAMPLES).await,
+                None => {
+                    let started = tokio::time::Instant::now();
+                    let addr = utils::scoped_socket_addr(ip, peer.port, scope_id);
+                    matches!(timeout(PATH_PROBE_TIMEOUT, tokio::net::TcpStream::connect(addr)).await, Ok(Ok(_))).then(|| started.elapsed())
+                }
+            };
+            rtt.map(|rtt| (rtt, ip, scope_id))
+        });
+        let results: Vec<_> = futures::future::join_all(probes).await.into_iter().flatten().collect();
+        let &(rtt, ip, scope_id) = results.iter().min_by_key(|(rtt, ..)| *rtt)?;
+        debug!("🛣️ Path probe for {}: {:?}", peer.display_name, results);
+        if let Some(mut p) = self.known_peers.get_mut(id) {
+            if p.ip != Some(ip) || p.scope_id != scope_id {
+                info!("🛣️ Switching {} to {} ({:?})", peer.display_name, utils::format_scoped_ip(ip, scope_id), rtt);
+                p.ip = Some(ip);
+                p.scope_id = scope_id;
+            }
+        }
+        Some((utils::format_scoped_ip(ip, scope_id), peer.port))
+    }
+
+    /// หลังสลับ Network: ประกาศตัวใหม่ แล้วให้ Health Check Ping ทุก Peer บน LAN รอบถัดไปทันที
+    pub async fn on_network_changed(&self) {
+        if self.local_node.lock().unwrap().is_none() { return; }
+        for backend in self.backends_snapshot() {
+            if let Err(e) = backend.refresh_addresses().await {
+                warn!("Discovery backend '{}' refresh failed: {}", backend.name(), e);
+            }
+        }
+        self.expire_lan_peers();
+    }
+
+    /// User สั่ง Refresh: ประกาศตัวทันทีไม่รอรอบ Presence แล้ว Ping ทุก Peer บน LAN (ตัวที่หายไปแล้วได้ PeerLost)
+    pub async fn refresh(&self) {
+        if self.local_node.lock().unwrap().is_none() { return; }
+        for backend in self.backends_snapshot() {
+            if let Err(e) = backend.announce().await {
+                warn!("Discovery backend '{}' announce failed: {}", backend.name(), e);
+            }
+        }
+        self.expire_lan_peers();
+    }
+
+    // ดัน last_seen ให้เลย stale_after -> Health Check รอบถัดไป Ping ทันที
+    fn expire_lan_peers(&self) {
+        let stale = Instant::now().checked_sub(self.health.stale_after + Duration::from_secs(1));
+        if let Some(stale) = stale {
+            for mut peer in self.known_peers.iter_mut() {
+                if peer.transport != TransportType::BleOnly {
+                    peer.last_seen = stale;
+                }
+            }
+        }
+    }
+
+    /// คำตอบจาก BLE Handshake -> เข้าเส้นทางเดียวกับ Advertisement (Upgrade เป็น Hybrid + ปลุก wait_for_lan)
+    pub async fn report_handshake(&self, mac: &str, info: &ConnectionInfo) {
+        let id = self.known_peers.iter()
+            .find(|r| r.value().ble_mac.as_deref() == Some(mac))
+            .map(|r| r.key().clone())
+            .unwrap_or_else(|| format!("ble-{}", mac.replace(':', "")));
+        // IPv6 Link-local ไม่มี Scope ของฝั่งเรา -> ใช้เฉพาะ IP ที่ไม่มี Scope
+        let addr = info.ip.as_deref()
+            .and_then(utils::parse_scoped_ip)
+            .filter(|(_, scope_id)| *scope_id == 0)
+            .map(|(ip, _)| ip)
+            .filter(|_| info.port != 0)
+            .map(|ip| (ip, info.port));
+        let _ = self.event_tx.send(DiscoveryInternalEvent::BleFound {
+            id,
+            name: info.name.clone(),
+            ssid: info.ssid.clone(),
+            mac: mac.to_string(),
+            addr,
+        }).await;
+    }
+
+    /// เปลี่ยนสถานะที่ประกาศ (ไม่เปลี่ยน = ไม่ต้องประกาศซ้ำ)
+    pub async fn set_availability(&self, availability: Availability) {
+        if std::mem::replace(&mut *self.availability.lock().unwrap(), availability) == availability { return; }
+        let node = self.local_node.lock().unwrap().as_mut().map(|node| {
+            node.availability = availability;
+            node.clone()
+        });
+        let Some(node) = node else { return };
+        info!("🚦 Availability: {}", availability.as_str());
+        for backend in self.backends_snapshot() {
+            if let Err(e) = backend.update_node(&node).await {
+                warn!("Discovery backend '{}' update failed: {}", backend.name(), e);
+            }
+        }
+    }
+
+    /// แทน feat ที่ขึ้นต้นด้วย prefix ด้วยชุดใหม่แล้วประกาศซ้ำ (เช่น Nameplate ของ Code ที่เปิดอยู่ ดู codes.rs)
+    pub async fn replace_features(&self, prefix: &str, features: Vec<String>) {
+        let node = self.local_node.lock().unwrap().as_mut().and_then(|node| {
+            let mut updated: Vec<String> = node.features.iter().filter(|f| !f.starts_with(prefix)).cloned().collect();
+            updated.extend(features);
+            (updated != node.features).then(|| {
+                node.features = updated;
+                node.clone()
+            })
+        });
+        let Some(node) = node else { return };
+        for backend in self.backends_snapshot() {
+            if let Err(e) = backend.update_node(&node).await {
+                warn!("Discovery backend '{}' update failed: {}", backend.name(), e);
+            }
+        }
+    }
+
+    pub async fn stop(&self) {
+        self.local_node.lock().unwrap().take();
+        for backend in self.backends_snapshot() {
+            if let Err(e) = backend.stop().await {
+                warn!("Discovery backend '{}' stop failed: {}", backend.name(), e);
+            }
+        }
+    }
+
+    /// Active = UI เปิดอยู่ (ประกาศตัวถี่), Idle = ถอยห่างแบบ Exponential จนถึง ANNOUNCE_INTERVAL_IDLE_MAX_SEC
+    pub fn set_activity_state(&self, state: ActivityState) {
+        self.activity_tx.send_if_modified(|current| {
+            if *current == state { return false; }
+            info!("📡 Presence state: {:?} -> {:?}", current, state);
+            *current = state;
+            true
+        });
+    }
+
+    fn next_announce_interval(state: ActivityState, prev: Duration) -> Duration {
+        match state {
+            ActivityState::Active => Duration::from_secs(ANNOUNCE_INTERVAL_ACTIVE_SEC),
+            ActivityState::Idle => (prev * 2).min(Duration::from_secs(ANNOUNCE_INTERVAL_IDLE_MAX_SEC)),
+        }
+    }
+
+    fn spawn_presence_announcer(&self, cancel: CancellationToken) {
+        let engine = self.clone();
+        let mut activity_rx = self.activity_tx.subscribe();
+
+        tokio::spawn(async move {
+            let mut interval = Duration::from_secs(ANNOUNCE_INTERVAL_ACTIVE_SEC);
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = tokio::time::sleep(interval) => {},
+                    changed = activity_rx.changed() => {
+                        if changed.is_err() { break; }
+                        // กลับมา Active -> ประกาศทันทีและรีเซ็ต Backoff
+                        if *activity_rx.borrow_and_update() == ActivityState::Active {
+                            interval = Duration::from_secs(ANNOUNCE_INTERVAL_ACTIVE_SEC);
+                        } else {
+                            continue;
+                        }
+                    }
+                }
+
+                if engine.local_node.lock().unwrap().is_none() { break; }
+                for backend in engine.backends_snapshot() {
+                    if let Err(e) = backend.announce().await {
+                        warn!("Discovery backend '{}' announce failed: {}", backend.name(), e);
+                    }
+                }
+                let state = *activity_rx.borrow();
+                interval = Self::next_announce_interval(state, interval);
+                debug!("📡 Next presence announcement in {:?} ({:?})", interval, state);
+            }
+        });
+    }
+
+    pub async fn run_health_check(&self, cancel: CancellationToken) {
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = tokio::time::sleep(self.health.interval) => {},
+            }
+
+            // Ping ผ่าน Control Channel เท่านั้น (Peer ที่ไม่ประกาศ ctl ปล่อยให้ mDNS ตัดสินเอง)
+            let suspects: Vec<(String, IpAddr, u32, u16, String)> = self.known_peers
+                .iter()
+                .filter(|r| {
+                    let p = r.value();
+                    p.transport != TransportType::BleOnly &&
+                    p.ip.is_some() && p.control_port.is_some() &&
+                    p.last_seen.elapsed() > self.health.stale_after
+                })
+                .map(|r| {
+                    let p = r.value();
+                    // 🟢 UPDATED: p.ip เป็น IpAddr แล้ว unwrap ออกมาได้เลย
+                    (p.id.clone(), p.ip.unwrap(), p.scope_id, p.control_port.unwrap(), p.display_name.clone())
+                })
+                .collect();
+
+            if suspects.is_empty() { continue; }
+
+            for (id, ip, scope_id, port, name) in suspects {
+                let peers_ref = self.known_peers.clone();
+                let cb_ref = self.callback.clone();
+                let span = tracing::debug_span!("health_check", peer_id = %id);
+
+                tokio::spawn(async move {
+                    // 🟢 UPDATED: รองรับทั้ง IPv4 และ IPv6 (Link-local ต้องระบุ Scope ID)
+                    let addr = utils::scoped_socket_addr(ip, port, scope_id);
+
+                    let is_alive = control::ping(addr).await;
+
+                    if let Some(mut peer) = peers_ref.get_mut(&id) {
+                        if is_alive {
+                            peer.last_seen = Instant::now();
+                            peer.missed_pings = 0;
+                            debug!("✅ Peer Verified: {}", name);
+                        } else {
+                            peer.missed_pings += 1;
+                            warn!("⚠️ Missed Ping {}/3 for {}", peer.missed_pings, name);
+
+                            if peer.missed_pings >= 3 {
+                                if peer.transport == TransportType::Hybrid {
+                                    info!("🔻 Link Degraded: {} (Fallback to BLE)", name);
+                                    peer.transport = TransportType::BleOnly;
+                                    peer.ip = None;
+                                    peer.addrs.clear();
+                                } else if peer.transport == TransportType::Lan {
+                                    info!("💀 Peer Lost: {}", name);
+                                    cb_ref.on_peer_lost(&id);
+                                    drop(peer); 
+                                    peers_ref.remove(&id);
+                                }
+                            }
+                        }
+                    }
+                }.instrument(span));
+
+                let jitter = rand::thread_rng().gen_range(50..150);
+                tokio::time::sleep(Duration::from_millis(jitter)).await;
+            }
+        }
+    }
+
+    // 🕶️ Peer ประกาศด้วย Token -> ขอชื่อจริงผ่าน TLS ถ้าบอกก็แจ้ง UI ซ้ำด้วยชื่อจริง (ไม่บอกก็แสดง Token ต่อไป)
+    fn spawn_identify(transport: Arc<DynTransport>, peers: Arc<DashMap<String, PeerInfo>>, cb: CB, id: String, ip: String, port: u16) {
+        let span = tracing::debug_span!("identify", peer_id = %id, ip = %ip);
+        tokio::spawn(async move {
+            let host = if ip.contains(':') { format!("[{}]", ip) } else { ip.clone() };
+            let name = match privacy::identify(&transport, &host, port).await {
+                Ok(Some(name)) => name,
+                Ok(None) => { debug!("🕶️ {} keeps its name private", id); return; }
+                Err(e) => { debug!("🕶️ Identify {} failed: {}", id, e); return; }
+            };
+            let Some(mut peer) = peers.get_mut(&id) else { return; };
+            info!("🕶️ Identified {} as {}", id, name);
+            peer.name = name.clone();
+            peer.display_name = name;
+            cb.on_peer_found_ex(&peer);
+        }.instrument(span));
+    }
+
+    // 📶 Transfer ที่รอ Peer นี้อยู่ -> ไปทาง LAN ได้แล้ว
+    fn wake_lan_waiters(lan_waiters: &DashMap<String, Vec<LanWaiter>>, id: &str, ip: &str, port: u16) {
+        if let Some((_, waiters)) = lan_waiters.remove(id) {
+            info!("📶 LAN path ready for {} queued transfer(s) to {}", waiters.len(), id);
+            for waiter in waiters {
+                let _ = waiter.send((ip.to_string(), port));
+            }
+        }
+    }
+
+    /// `rx` มีแค่ครั้งแรก (Start ซ้ำหลัง Service ถูกยกเลิก ใช้ Event Loop เดิม)
+    /// `cancel` ถูกยกเลิก -> หยุด Backend และ Loop ประกาศตัว
+    #[allow(clippy::too_many_arguments)]
+    pub async fn start(&self, device_id: String, port: u16, dev_mode: bool, features: Vec<String>, external_addr: Option<String>, hotspot_addr: Option<IpAddr>, control_port: Option<u16>, rx: Option<mpsc::Receiver<DiscoveryInternalEvent>>, cancel: CancellationToken) -> anyhow::Result<()> {
+
+        let my_system_name = utils::get_system_name();
+        info!("🚀 Discovery Engine Starting: {} (DevMode: {})", my_system_name, dev_mode);
+
+        let node = LocalNode { id: device_id, name: my_system_name, port, dev_mode, features, external_addr, hotspot_addr, control_port, privacy: self.privacy, guest: self.guest, profile: self.profile.clone(), availability: *self.availability.lock().unwrap() };
+        *self.local_node.lock().unwrap() = Some(node.clone());
+        for backend in self.backends_snapshot() {
+            backend.start(&node, self.event_tx.clone()).await
+                .map_err(|e| anyhow::anyhow!("Discovery backend '{}' failed to start: {}", backend.name(), e))?;
+        }
+        self.spawn_presence_announcer(cancel.clone());
+        let checker = self.clone();
+        let check_cancel = cancel.clone();
+        tokio::spawn(async move { checker.run_health_check(check_cancel).await });
+        let engine = self.clone();
+        tokio::spawn(async move {
+            cancel.cancelled().await;
+            engine.stop().await;
+        });
+
+        let Some(mut rx) = rx else { return Ok(()); };
+        let peers = self.known_peers.clone();
+        let cb = self.callback.clone();
+        let lan_waiters = self.lan_waiters.clone();
+        let identify = self.identify.clone();
+        let registry = self.registry.clone();
+
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                match event {
+                    DiscoveryInternalEvent::MdnsFound { id, name, ip, addrs, port, compression, features, external_addr, control_port, private, guest, fingerprint, profile } => {
+                        // 🟢 UPDATED: แปลง String กลับเป็น IpAddr เพื่อความปลอดภัย ([v6] และ %zone ด้วย)
+                        if let Some((mut parsed_ip, mut scope_id)) = utils::parse_scoped_ip(&ip) {
+                            let addrs: Vec<(IpAddr, u32)> = addrs.iter().filter_map(|a| utils::parse_scoped_ip(a)).collect();
+                            // 🔀 Address เดิมยังอยู่ในชุดใหม่ -> ไม่สลับตาม Event ที่มาทีหลัง (เลือกเส้นทางด้วย RTT ใน fastest_addr)
+                            if let Some(current) = peers.get(&id).and_then(|p| p.ip.map(|ip| (ip, p.scope_id))).filter(|c| addrs.contains(c)) {
+                                (parsed_ip, scope_id) = current;
+                            }
+                            let ip = utils::format_scoped_ip(parsed_ip, scope_id);
+                            // ก่อน Callback: RegistryHandler หา Record ของ ID นี้ได้ตั้งแต่ PeerFound แรก
+                            registry.observe(&id, fingerprint.as_deref(), &name, Some(&ip), !private && !guest);
+                            let is_new = !peers.contains_key(&id);
+                            peers.entry(id.clone())
+                                .and_modify(|peer| {
+                                    // 🔄 DHCP เปลี่ยน IP กลาง Session -> แก้ Entry เดิม ไม่ต้องรอ Health Check ฆ่าทิ้ง
+                                    if let Some(old_ip) = peer.host().filter(|old| *old != ip || peer.port != port) {
+                                        info!("🔄 Address Changed: {} {} -> {}", name, old_ip, ip);
+                                        cb.on_peer_updated(&id, &old_ip, &ip, port);
+                                    }
+                                    peer.ip = Some(parsed_ip); // Store as IpAddr
+                                    peer.scope_id = scope_id;
+                                    peer.addrs = addrs.clone();
+                                    peer.port = port;
+                                    peer.last_seen = Instant::now();
+                                    peer.missed_pings = 0;
+                                    peer.compression = compression.clone();
+                                    peer.features = features.clone();
+                                    peer.external_addr = external_addr.clone();
+                                    peer.control_port = control_port;
+                                    peer.guest = guest;
+                                    peer.profile = profile.clone();
+
+                                    if peer.transport == TransportType::BleOnly {
+                                        info!("🆙 Link Upgraded: {} (BLE -> Hybrid)", name);
+                                        peer.transport = TransportType::Hybrid;
+                                    } else {
+                                        peer.transport = TransportType::Lan;
+                                    }
+                                    cb.on_peer_found_ex(peer);
+                                })
+                                .or_insert_with(|| {
+                                    info!("✨ LAN Found: {} @ {}", name, ip);
+                                    let peer = PeerInfo {
+                                        id: id.clone(),
+                                        name: name.clone(),
+                                        display_name: name,
+                                        ip: Some(parsed_ip), // Store as IpAddr
+                                        scope_id,
+                                        addrs,
+                                        port,
+                                        ssid: None,
+                                        ble_mac: None,
+                                        transport: TransportType::Lan,
+                                        last_seen: Instant::now(),
+                                        missed_pings: 0,
+                                        compression,
+                                        features,
+                                        external_addr,
+                                        control_port,
+                                        guest,
+                                        profile,
+                                    };
+                                    cb.on_peer_found_ex(&peer);
+                                    peer
+                                });
+                            Self::wake_lan_waiters(&lan_waiters, &id, &ip, port);
+                            if let (true, true, Some(transport)) = (private, is_new, identify.clone()) {
+                                Self::spawn_identify(transport, peers.clone(), cb.clone(), id, ip, port);
+                            }
+                        }
+                    },
+
+                    DiscoveryInternalEvent::BleFound { id, name, ssid, mac, addr } => {
+                        let addr_str = addr.map(|(ip, _)| ip.to_string());
+                        registry.observe(&id, None, &name, addr_str.as_deref(), true);
+                        if let Some(mut peer) = peers.get_mut(&id) {
+                            peer.ssid = ssid.clone();
+                            peer.ble_mac = Some(mac.clone());
+                            peer.last_seen = Instant::now();
+                            if peer.transport == TransportType::Lan {
+                                peer.transport = TransportType::Hybrid;
+                                info!("🔗 Link Merged: {} (Hybrid)", name);
+                            }
+                            // 📡 mDNS ไม่เห็น (เช่น Multicast ถูกบล็อก) แต่ Advertisement บอก Address มา -> ใช้ได้เลย Health Check จะยืนยันเอง
+                            if let (Some((ip, port)), None) = (addr, peer.ip) {
+                                info!("🆙 Link Upgraded via BLE advert: {} @ {}:{}", name, ip, port);
+                                peer.ip = Some(ip);
+                                peer.scope_id = 0;
+                                peer.addrs = vec![(ip, 0)];
+                                peer.port = port;
+                                peer.transport = TransportType::Hybrid;
+                                cb.on_peer_found_ex(&peer);
+                                drop(peer);
+                                Self::wake_lan_waiters(&lan_waiters, &id, &ip.to_string(), port);
+                            }
+                        } else {
+                            info!("👻 BLE Found: {} (Mac: {})", name, mac);
+                            let transport = if addr.is_some() { TransportType::Hybrid } else { TransportType::BleOnly };
+                            let peer = PeerInfo {
+                                id,
+                                name: name.clone(),
+                                display_name: name,
+                                ip: addr.map(|(ip, _)| ip),
+                                scope_id: 0,
+                                addrs: addr.map(|(ip, _)| vec![(ip, 0)]).unwrap_or_default(),
+                                port: addr.map_or(0, |(_, port)| port),
+                                ssid,
+                                ble_mac: Some(mac),
+                                transport,
+                                last_seen: Instant::now(),
+                                missed_pings: 0,
+                                compression: None,
+                                features: vec![],
+                                external_addr: None,
+                                control_port: None,
+                                guest: false,
+                                profile: PeerProfile::default(),
+                            };
+                            cb.on_peer_found_ex(&peer);
+                            peers.insert(peer.id.clone(), peer);
+                        }
+                    },
+
+                    DiscoveryInternalEvent::MdnsLost { id } => {
+                        let mut remove = false;
+                        if let Some(mut peer) = peers.get_mut(&id) {
+                            if peer.transport == TransportType::Hybrid {
+                                info!("⚠️ LAN Lost, downgrading to BLE: {}", peer.display_name);
+                                peer.transport = TransportType::BleOnly;
+                                peer.ip = None;
+                                peer.addrs.clear();
+                            } else {
+                                remove = true;
+                            }
+                        }
+                        if remove && peers.remove(&id).is_some() {
+                            cb.on_peer_lost(&id);
+                        }
+                    },
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
\ No newline at end of file
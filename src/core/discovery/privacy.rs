@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use anyhow::Context;
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use tokio::io::AsyncWriteExt;
+use tokio::time::timeout;
+
+use crate::core::admin::{read_frame, write_frame};
+use crate::core::transfer::{DataStream, DynTransport};
+
+// ==========================================
+// Privacy Mode ของ mDNS / DNS-SD
+// Instance Name / Host / TXT "id" / "name" เป็น Token สุ่มที่เปลี่ยนตามรอบ -> คนใน Network สาธารณะไล่ดูชื่อเครื่องไม่ได้
+// TXT "priv" = 1 บอก Peer ว่าชื่อจริงต้องขอผ่าน TLS: [u32 len][JSON] ที่มี key "identify"
+// ตอบชื่อจริงเฉพาะ Cert ที่เราเคยรับไฟล์มาแล้ว (PeerCaps::accepted_at) ที่เหลือได้ name = null
+// SAN ใน Certificate ยังเป็น node_name อยู่ (ใครต่อ TLS ก็เห็น) ต้องการปิดหมดให้ตั้ง node_name กลางๆ
+// ==========================================
+
+pub const TXT_PRIVATE: &str = "priv";
+pub const DEFAULT_ROTATE_INTERVAL: Duration = Duration::from_secs(15 * 60);
+const TOKEN_BYTES: usize = 6;
+
+// เปลี่ยนได้ตอน announce() รอบถัดไปหลังครบ rotate_every (ไม่ได้เปลี่ยนตรงเวลาเป๊ะ)
+#[derive(Debug)]
+pub struct RotatingToken {
+    rotate_every: Duration,
+    token: String,
+    issued: Instant,
+}
+
+impl RotatingToken {
+    pub fn new(rotate_every: Duration) -> Self {
+        Self { rotate_every, token: new_token(), issued: Instant::now() }
+    }
+
+    pub fn current(&self) -> &str { &self.token }
+
+    /// ครบรอบแล้ว -> สุ่ม Token ใหม่ คืน true
+    pub fn rotate_if_due(&mut self) -> bool {
+        if self.issued.elapsed() < self.rotate_every { return false; }
+        self.token = new_token();
+        self.issued = Instant::now();
+        true
+    }
+}
+
+fn new_token() -> String {
+    let bytes: [u8; TOKEN_BYTES] = rand::thread_rng().gen();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct IdentifyQuery {}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct IdentifyRequest {
+    pub identify: IdentifyQuery,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IdentifyResponse {
+    pub name: Option<String>,
+}
+
+/// ขอชื่อจริงของ Peer ที่ประกาศ priv (None = Peer ไม่ยอมบอก)
+pub async fn identify(transport: &Arc<DynTransport>, ip: &str, port: u16) -> anyhow::Result<Option<String>> {
+    let mut stream = transport.connect(ip, port).await?;
+    write_frame(&mut stream, &serde_json::to_vec(&IdentifyRequest::default())?).await?;
+    let buf = read_frame(&mut stream).await.context("Identify failed")?;
+    let response: IdentifyResponse = serde_json::from_slice(&buf).context("Invalid identify response")?;
+    Ok(response.name)
+}
+
+/// ฝั่งรับตอบ (เรียกจาก handlers หลังตัดสินแล้วว่าจะบอกชื่อหรือไม่)
+pub async fn respond<S: DataStream>(mut stream: S, name: Option<String>, io_timeout: Duration) -> anyhow::Result<()> {
+    let json = serde_json::to_vec(&IdentifyResponse { name })?;
+    timeout(io_timeout, write_frame(&mut stream, &json)).await.context("Identify response timeout")??;
+    let _ = stream.shutdown().await;
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::core::discovery::{BleBackend, BroadcastDiscovery, DynDiscoveryBackend, ManualDiscovery, MdnsBackend};
+use crate::core::engine::DropTeaConfig;
+use crate::core::error::{DropTeaError, Result};
+
+// ==========================================
+// Discovery Backend ตามชื่อ ([discovery] backends = ["mdns", "broadcast", ...])
+// ในตัว: mdns / ble / manual / broadcast
+// Crate อื่นลงชื่อเพิ่มด้วย register_discovery_factory (เช่น Cloud Directory) แล้วใส่ชื่อใน Config ได้เลย
+// ทุก Backend ส่ง DiscoveryInternalEvent เข้าตาราง Peer / Event ชุดเดียวกัน
+// ==========================================
+
+pub const BUILTIN: &[&str] = &["mdns", "ble", "manual", "broadcast"];
+// ไม่ระบุ backends (+ manual ถ้ามี [discovery] manual)
+const DEFAULT_BACKENDS: &[&str] = &["mdns", "ble"];
+
+/// สร้าง Backend ตอน Engine เริ่ม (อ่านค่าของตัวเองจาก Config ได้)
+pub trait DiscoveryFactory: Send + Sync + 'static {
+    fn create(&self, config: &DropTeaConfig) -> anyhow::Result<DynDiscoveryBackend>;
+}
+
+pub type DynDiscoveryFactory = Arc<dyn DiscoveryFactory>;
+
+fn registry() -> &'static RwLock<HashMap<&'static str, DynDiscoveryFactory>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<&'static str, DynDiscoveryFactory>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// ลงชื่อเดิมซ้ำ = แทนตัวเก่า / ชื่อของ Backend ในตัว = Error
+pub fn register_discovery_factory(name: &'static str, factory: DynDiscoveryFactory) -> Result<()> {
+    if name.is_empty() || name != name.to_lowercase() {
+        return Err(DropTeaError::Config(format!("discovery backend name '{}' must be non-empty lowercase", name)));
+    }
+    if BUILTIN.contains(&name) {
+        return Err(DropTeaError::Config(format!("discovery backend '{}' is built in", name)));
+    }
+    registry().write().unwrap().insert(name, factory);
+    Ok(())
+}
+
+pub fn is_known(name: &str) -> bool {
+    BUILTIN.contains(&name) || registry().read().unwrap().contains_key(name)
+}
+
+/// ทุกชื่อที่ใช้ได้ (ในตัวก่อน แล้วตามด้วยที่ลงไว้)
+pub fn available() -> Vec<&'static str> {
+    let mut extra: Vec<_> = registry().read().unwrap().keys().copied().collect();
+    extra.sort_unstable();
+    BUILTIN.iter().copied().chain(extra).collect()
+}
+
+fn create(name: &str, config: &DropTeaConfig) -> anyhow::Result<DynDiscoveryBackend> {
+    Ok(match name {
+        "mdns" => Arc::new(MdnsBackend::new()?),
+        "ble" => Arc::new(BleBackend::new()),
+        "manual" => Arc::new(ManualDiscovery::new(config.manual_peers.clone())),
+        "broadcast" => Arc::new(BroadcastDiscovery::new(config.broadcast_port)),
+        other => {
+            let factory = registry().read().unwrap().get(other).cloned();
+            factory.ok_or_else(|| anyhow::anyhow!("discovery backend '{}' is not registered", other))?.create(config)?
+        }
+    })
+}
+
+/// Backend ตาม discovery_backends ของ Config (ว่าง = mDNS + BLE)
+pub fn from_config(config: &DropTeaConfig) -> anyhow::Result<Vec<DynDiscoveryBackend>> {
+    let mut names: Vec<&str> = match config.discovery_backends.is_empty() {
+        true => DEFAULT_BACKENDS.to_vec(),
+        false => config.discovery_backends.iter().map(String::as_str).collect(),
+    };
+    if config.discovery_backends.is_empty() && !config.manual_peers.is_empty() {
+        names.push("manual");
+    }
+    names.into_iter().map(|name| create(name, config)).collect()
+}
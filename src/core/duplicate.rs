@@ -0,0 +1,42 @@
+use std::fs::File as StdFile;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+// ==========================================
+// Duplicate Detection (storage.skip_duplicates)
+// ผู้ส่งแนบ quick_hash มากับ FileHeader: blake3(ขนาด + 64 KiB แรก / กลาง / ท้าย) อ่านไม่กี่ร้อย KB แม้ไฟล์ใหญ่
+// ผู้รับหาไฟล์ขนาดเท่ากันในโฟลเดอร์ปลายทาง (ไม่ลงโฟลเดอร์ย่อย) แล้วเทียบ Hash เดียวกัน -> ตรง = "มีแล้ว" ข้ามไม่ต้องถาม
+// ไม่ใช่ Hash ทั้งไฟล์: ไฟล์ที่ต่างกันแค่ช่วงที่ไม่ได้อ่านจะถูกนับว่าซ้ำ -> เป็น Opt-in
+// ==========================================
+
+const SAMPLE_SIZE: u64 = 64 * 1024;
+
+/// blake3 ของขนาดไฟล์ + ช่วงต้น/กลาง/ท้าย (hex) ไฟล์ไม่เกิน 3 ช่วง = อ่านทั้งไฟล์
+pub fn quick_hash(path: &Path) -> std::io::Result<String> {
+    let mut file = StdFile::open(path)?;
+    let size = file.metadata()?.len();
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&size.to_le_bytes());
+    if size <= SAMPLE_SIZE * 3 {
+        std::io::copy(&mut file, &mut hasher)?;
+    } else {
+        let mut buf = vec![0u8; SAMPLE_SIZE as usize];
+        for offset in [0, size / 2 - SAMPLE_SIZE / 2, size - SAMPLE_SIZE] {
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut buf)?;
+            hasher.update(&buf);
+        }
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// ไฟล์ใน dir ที่ขนาดและ quick_hash ตรงกับที่ผู้ส่งประกาศ (ตัวแรกที่เจอ)
+/// .part ที่จองพื้นที่ไว้เต็มขนาด (ยังรับไม่ครบ) ไม่นับ
+pub fn find_existing(dir: &Path, size: u64, hash: &str) -> Option<PathBuf> {
+    std::fs::read_dir(dir).ok()?
+        .filter_map(Result::ok)
+        .filter(|e| e.metadata().is_ok_and(|m| m.is_file() && m.len() == size))
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_none_or(|ext| ext != "part"))
+        .find(|p| quick_hash(p).is_ok_and(|h| h == hash))
+}
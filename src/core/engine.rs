@@ -1,44 +1,353 @@
 use std::sync::{Arc, Mutex as StdMutex}; 
+use std::sync::atomic::Ordering;
 use std::collections::HashMap;
 use std::time::Duration;
 use tokio::runtime::Runtime;
-use tokio::sync::{Semaphore, Mutex as TokioMutex, mpsc};
+use tokio::sync::{broadcast, Semaphore, Mutex as TokioMutex, mpsc, watch};
 use tokio::time::Instant;
-use log::{info, error};
+use tracing::Instrument;
+use anyhow::Context;
+use rand::Rng;
 
-use crate::core::events::{TransferEvent, TransferEventHandler};
-use crate::core::transfer::{DynTransport, TransferCallback};
-use crate::core::handlers::{handle_incoming, handle_sending};
-use crate::core::discovery::{DiscoveryEngine, DiscoveryInternalEvent};
+use crate::core::admin::{self, AdminCommand, AdminContext, AdminResponse, Limit};
+use crate::core::archive::ArchiveMode;
+use crate::core::ble_transfer;
+use crate::core::cancel::{CancellationToken, RemoteTransfer, TransferTokens, REJECT_CANCELLED};
+use crate::core::error::{self, DropTeaError};
+use crate::core::control::{self, ControlContext, ControlMessage};
+use crate::core::dedup::{self, ChunkIndex};
+use crate::core::chunk_store::{ChunkStore, STORE_DIR};
+use crate::core::diagnostics::{self, DiagnosticsInput, EventRecorder, RecordingHandler};
+use crate::core::hotspot;
+use crate::core::security;
+use crate::core::io_priority::IoPriority;
+use crate::core::netwatch;
+use crate::core::outbox::{Outbox, QueuedSend};
+use crate::core::parallel::{self, LaneRegistry, ParallelPolicy, ParallelSend};
+use crate::core::resume::{self, IncompleteFile, ResumeRegistry};
+use crate::core::peer_caps::{PeerCaps, PeerCapsCache};
+use crate::core::thumbnail::Thumbnail;
+use crate::core::stats::{StatsCollector, StatsStore, TransferStats};
+use crate::core::builder::DropTeaBuilder;
+use crate::core::batch::{BatchCallback, BatchInfo, BatchProgress, BatchTracker};
+use crate::core::port_mapping::{self, MappingProtocol};
+use crate::core::sandbox::SandboxHelper;
+use crate::core::reputation::{self, ReputationConfig};
+use crate::core::sender_queue::SenderQueues;
+use crate::core::session::{self, SessionApprovals, SessionDecision, SessionOffer};
+use crate::core::shares::{self, ShareCommand, ShareContext, ShareOptions, ShareResponse, SharedFolder};
+use crate::core::save_rules::SaveRule;
+use crate::core::reload::{ConfigReloader, Live};
+use crate::core::peer_prefs::{PeerOverride, Throttled};
+use crate::core::peer_registry::{PeerEntry, PeerRecord, PeerRegistry, RegistryHandler};
+use crate::core::loopback_bench;
+use crate::core::tuning::{self, TuningPreset, Tunables};
+use crate::core::events::{EventStream, IncomingRequest, StreamingHandler, TransferEvent, TransferEventHandler};
+use crate::core::transfer::{DynTransport, Metadata, Throughput, Timeouts, TransferCallback, CAP_COMPRESSION, CAP_DEDUP, CAP_PARALLEL_STREAMS, CAP_RESUME};
+use crate::core::handlers::{handle_incoming, handle_sending, ReceiveOptions, SendParams, OFFER_HASH_MAX_SIZE};
+use crate::core::webhook::ApprovalWebhook;
+use crate::core::hooks::{CommandHook, DynPostReceiveHook, PostReceiveHook};
+use crate::core::middleware::{Direction, DynTransferMiddleware, MiddlewareChain, TransferContext};
+use crate::core::discovery::{ActivityState, Availability, BleBackend, DeviceType, DiscoveryEngine, DiscoveryInternalEvent, DynDiscoveryBackend, HealthCheck, LocalProfile, LocalSendDiscovery, MdnsBackend, PeerInfo};
+use crate::core::discovery::broadcast::DEFAULT_BROADCAST_PORT;
+use crate::core::discovery::registry as discovery_registry;
+use crate::core::handshake::ConnectionInfo;
+use crate::core::compression::{self, CompressionAlgo};
 use crate::core::transports::tcp::TcpTransport;
-use crate::core::transports::quic::QuicTransport;
+use crate::core::transports::quic::{QuicConfig, QuicTransport};
+use crate::core::rendezvous::RendezvousClient;
+use crate::core::codes::{self, CodeOffers};
+use crate::core::localsend::{self, LocalSend, LocalSendConfig, ReceiveContext};
+use crate::core::links::{self, DownloadLinks, LinkConfig};
 use crate::core::transports::plain_tcp::PlainTcpTransport;
+#[cfg(feature = "webrtc")]
+use crate::core::transports::webrtc::WebRtcTransport;
+use crate::core::transports::memory::{MemoryNetwork, MemoryTransport};
+use crate::core::transports::custom;
 
-const MAX_CONCURRENT_CONNECTIONS: usize = 100;
+pub const DEFAULT_MAX_OUTGOING: usize = 50;
+pub const DEFAULT_MAX_INCOMING: usize = 5;
+// ที่ลงของไฟล์ที่รับ (ไม่ตั้ง download_dir) และที่เก็บ Whitelist / History / Share Grant / Dedup Index เสมอ
+pub const DOWNLOAD_DIR: &str = "./downloads";
+// ส่งหา Peer ที่เห็นแค่ทาง BLE: รอให้เจอบน LAN ได้นานเท่านี้
+const LAN_PATH_WAIT: Duration = Duration::from_secs(300);
+// ไฟล์ใหญ่กว่านี้ถึงคุ้มเสียเวลาวัด RTT ทุก Address ของ Peer ก่อนส่ง
+const PATH_PROBE_MIN_SIZE: u64 = 16 * 1024 * 1024;
+// รอบเช็คว่ารับเต็มทุกช่องหรือยัง (ประกาศ busy ใน mDNS)
+const AVAILABILITY_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// รอบเช็คว่า Task ปล่อย Transport หมดหรือยังตอน close
+const CLOSE_POLL_INTERVAL: Duration = Duration::from_millis(20);
+// receive_code: รอเห็นผู้ส่งบน LAN ได้นานเท่านี้ก่อนไปถาม Rendezvous
+const CODE_LAN_WAIT: Duration = Duration::from_secs(3);
+
+// ผลการเสนอ Session ของ send_batch (None = ยังรอผู้รับตัดสินใจ)
+type SessionGate = watch::Receiver<Option<SessionDecision>>;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub enum TransportMode { Tcp, Quic, PlainTcp }
+pub enum TransportMode {
+    Tcp,
+    Quic,
+    PlainTcp,
+    #[cfg(feature = "webrtc")]
+    WebRtc,
+}
+
+impl TransportMode {
+    // ชื่อเดียวกับ [server] mode ใน config.toml
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tcp => "tcp",
+            Self::Quic => "quic",
+            Self::PlainTcp => "plaintcp",
+            #[cfg(feature = "webrtc")]
+            Self::WebRtc => "webrtc",
+        }
+    }
+
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "tcp" => Some(Self::Tcp),
+            "quic" => Some(Self::Quic),
+            "plaintcp" | "plain_tcp" => Some(Self::PlainTcp),
+            #[cfg(feature = "webrtc")]
+            "webrtc" => Some(Self::WebRtc),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct DropTeaConfig {
     pub mode: TransportMode,
     pub port: u16,
     pub storage_path: String,
+    // โฟลเดอร์ที่ไฟล์ที่รับลง (Hot-Reload ได้ Transfer ที่เริ่มไปแล้วลงที่เดิม)
+    pub download_dir: String,
+    // .part ระหว่างรับอยู่ที่นี่แล้วค่อยย้ายไปปลายทาง (None = ข้างไฟล์ปลายทาง)
+    pub temp_dir: Option<String>,
     pub node_name: String,
     pub dev_mode: bool,
+    pub archive_mode: ArchiveMode,
+    // ชื่อที่ลงไว้ด้วย custom::register_transport (Some = ใช้แทน mode)
+    pub custom_transport: Option<String>,
+    pub approval_webhook: Option<ApprovalWebhook>,
+    // คำสั่งหลังรับไฟล์ครบ ([hooks] post_receive) รันก่อน Hook ที่ใส่ผ่าน DropTeaBuilder
+    pub post_receive: Option<CommandHook>,
+    pub compression_level: Option<i32>,
+    pub preferred_compression: Option<CompressionAlgo>,
+    // Fingerprint ของ Peer ที่สั่งงานเครื่องนี้จากระยะไกลได้ (ว่าง = ปิด)
+    pub admin_fingerprints: Vec<String>,
+    // STUN/TURN สำหรับ TransportMode::WebRtc (None = ใช้ Default)
+    pub ice_servers: Option<Vec<String>>,
+    // Default ของทุก Transfer (ส่งแต่ละครั้ง Override ได้ด้วย SendOptions)
+    pub io_priority: IoPriority,
+    // ไฟล์ที่ส่งขนาดนี้ขึ้นไปอ่านผ่าน mmap (None = ปิด, ดู mmap.rs)
+    pub mmap_threshold: Option<u64>,
+    // host:port ของ Rendezvous Server (QUIC Hole Punching: send_file ด้วย Peer ID แทน IP)
+    pub rendezvous_server: Option<String>,
+    // รัน Rendezvous Server ในตัว (เครื่องที่มี Public IP)
+    pub rendezvous_listen: Option<String>,
+    // เก็บ Chunk Index ของไฟล์ที่รับ -> ผู้ส่งส่งเฉพาะส่วนที่ยังไม่มี (ประกาศ feat=dedup)
+    pub dedup: bool,
+    // ขนาดสูงสุดของ Chunk Store (None = ปิด, ใช้เมื่อเปิด dedup เท่านั้น ดู chunk_store.rs)
+    pub chunk_store: Option<u64>,
+    // ข้ามไฟล์ที่ผู้รับมีอยู่แล้ว (ขนาด + quick_hash ตรงกับไฟล์ในโฟลเดอร์ปลายทาง)
+    pub skip_duplicates: bool,
+    // ขอ Port Forward จาก Router (NAT-PMP / UPnP) แล้วประกาศ External Address ผ่าน mDNS
+    pub port_mapping: bool,
+    // ไฟล์ที่ส่งมาพร้อมกันจากผู้ส่งเดียวกัน: ถามครั้งเดียว และเขียนลง Disk ทีละไฟล์ตามลำดับ
+    pub sender_queue: bool,
+    // เก็บคิวส่งลง storage_path/outbox.json แล้วส่งต่อหลังเปิดแอปใหม่
+    pub persist_outbox: bool,
+    // Override Address ของ Hotspot/ICS ที่เครื่องนี้เปิดอยู่ (None = Auto Detect)
+    pub hotspot_gateway: Option<std::net::IpAddr>,
+    // โฟลเดอร์ที่เปิดให้ Browse/Pull ตอนเริ่ม (ว่าง = ปิด จนกว่าจะ publish_share)
+    pub shares: Vec<SharedFolder>,
+    // [sharing]: Hash / จำนวนรายการต่อ Browse
+    pub share_options: ShareOptions,
+    // ไฟล์ที่รับ: แยกโฟลเดอร์ตามชนิดไฟล์ กฎแรกที่ตรงชนะ (ว่าง = ลง download_dir ทั้งหมด)
+    pub save_rules: Vec<SaveRule>,
+    // วัด Disk/Compression ตอนเริ่ม Service แล้ว Apply Preset ให้เอง
+    pub auto_tune: bool,
+    // Peer ที่เห็นแค่ทาง BLE: ไฟล์ไม่เกินขนาดนี้ส่งผ่าน GATT แทนการรอ LAN (0 = ปิด)
+    pub ble_max_file_size: u64,
+    // ถามความน่าเชื่อถือของไฟล์จาก SHA-256 ก่อนขึ้น Prompt (None = ปิด)
+    pub reputation: Option<ReputationConfig>,
+    // Helper สำหรับแตก Compression ฝั่งรับนอกโปรเซสหลัก (None = แตกในโปรเซสเหมือนเดิม)
+    pub sandbox_helper: Option<String>,
+    // IO / รอผู้ใช้ตัดสินใจ (ทั้งฝั่งรับและฝั่งส่ง)
+    pub timeouts: Timeouts,
+    // รอบ Ping Peer บน LAN ที่เงียบไป
+    pub health_check: HealthCheck,
+    // Connect ไม่ติดแล้วลองใหม่กี่ครั้ง / รอนานเท่าไร
+    pub retry: RetryPolicy,
+    // QUIC: ไฟล์ใหญ่แบ่งส่งหลาย Stream พร้อมกัน (ดู parallel.rs)
+    pub parallel: ParallelPolicy,
+    // TLS-TCP: ช่วงละ Connection (ปิดเป็น Default)
+    pub tcp_parallel: ParallelPolicy,
+    // Window / Congestion Control ของ QUIC (rendezvous_server ในนี้ไม่ใช้ -> ใช้ของ DropTeaConfig)
+    pub quic: QuicConfig,
+    // Some = mDNS ประกาศด้วย Token สุ่มแทนชื่อเครื่อง เปลี่ยนทุกช่วงนี้ (ชื่อจริงบอกเฉพาะ Peer ที่เคยรับไฟล์มาแล้ว)
+    pub discovery_privacy: Option<Duration>,
+    // ชื่อ Backend ตาม discovery/registry.rs (ว่าง = mDNS + BLE)
+    pub discovery_backends: Vec<String>,
+    // host:port ของ Peer ที่ไม่ต้องค้นหา (Backend "manual")
+    pub manual_peers: Vec<String>,
+    // UDP Port ของ Backend "broadcast"
+    pub broadcast_port: u16,
+    // Some = คุยกับแอป LocalSend ได้ (Multicast + HTTP API บน Port ของ LocalSend ดู localsend.rs)
+    pub localsend: Option<LocalSendConfig>,
+    // Some = create_link ได้ (HTTP Server เล็กๆ ให้ Browser โหลดไฟล์ ดู links.rs)
+    pub links: Option<LinkConfig>,
+    // ชนิดเครื่องที่ประกาศใน mDNS (None = เดาจาก OS / แบตเตอรี่)
+    pub device_type: Option<DeviceType>,
+    // Cert ชั่วคราวใน Memory (ไม่เขียน Identity / known_hosts / Whitelist / Peer Cache / Outbox ลง Disk)
+    pub guest: bool,
+    // Transfer ที่รับ / ส่งพร้อมกันได้ (auto_tune ปรับฝั่งรับต่อเองได้)
+    pub max_incoming: usize,
+    pub max_outgoing: usize,
+    // จำกัดความเร็วต่อ Transfer ทั้งส่งและรับ เมื่อ [peers.*] ไม่ได้ระบุ max_bytes_per_sec (None = ไม่จำกัด)
+    pub max_bytes_per_sec: Option<u64>,
+    // ค่าเฉพาะ Peer (Key = Fingerprint / Peer ID / ชื่อเครื่อง / IP) ดู peer_prefs.rs
+    pub peers: HashMap<String, PeerOverride>,
+}
+
+impl Default for DropTeaConfig {
+    fn default() -> Self {
+        Self {
+            mode: TransportMode::Tcp,
+            port: 0,
+            storage_path: ".".to_string(),
+            download_dir: DOWNLOAD_DIR.to_string(),
+            temp_dir: None,
+            node_name: whoami::devicename(),
+            dev_mode: false,
+            archive_mode: ArchiveMode::Off,
+            custom_transport: None,
+            approval_webhook: None,
+            post_receive: None,
+            compression_level: None,
+            preferred_compression: None,
+            admin_fingerprints: vec![],
+            ice_servers: None,
+            io_priority: IoPriority::Normal,
+            mmap_threshold: None,
+            rendezvous_server: None,
+            rendezvous_listen: None,
+            dedup: false,
+            chunk_store: None,
+            skip_duplicates: false,
+            port_mapping: false,
+            sender_queue: false,
+            persist_outbox: true,
+            hotspot_gateway: None,
+            shares: vec![],
+            share_options: ShareOptions::default(),
+            save_rules: vec![],
+            auto_tune: false,
+            ble_max_file_size: ble_transfer::DEFAULT_BLE_MAX_FILE_SIZE,
+            reputation: None,
+            sandbox_helper: None,
+            timeouts: Timeouts::default(),
+            health_check: HealthCheck::default(),
+            retry: RetryPolicy::default(),
+            parallel: ParallelPolicy::default(),
+            tcp_parallel: ParallelPolicy::off(),
+            quic: QuicConfig::default(),
+            discovery_privacy: None,
+            discovery_backends: vec![],
+            manual_peers: vec![],
+            broadcast_port: DEFAULT_BROADCAST_PORT,
+            localsend: None,
+            links: None,
+            device_type: None,
+            guest: false,
+            max_incoming: DEFAULT_MAX_INCOMING,
+            max_outgoing: DEFAULT_MAX_OUTGOING,
+            max_bytes_per_sec: None,
+            peers: HashMap::new(),
+        }
+    }
+}
+
+// Connect ล้มด้วยเหตุชั่วคราว (Network / Timeout) แล้วลองใหม่ด้วย Backoff ทวีคูณ
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    // รวมครั้งแรก (1 = ไม่ลองซ้ำ)
+    pub attempts: u32,
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    // สุ่มบวก/ลบสัดส่วนนี้ของ Delay (0.0 - 1.0) กันหลายเครื่องลองพร้อมกันเป๊ะ
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { attempts: 3, backoff: Duration::from_secs(1), max_backoff: Duration::from_secs(30), jitter: 0.2 }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay ก่อนเริ่มครั้งที่ `attempt` (ครั้งที่ 2 = backoff, 3 = backoff x2, ...)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(2).min(16);
+        let base = self.backoff.saturating_mul(1 << exp).min(self.max_backoff);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 { return base; }
+        base.mul_f64(1.0 + rand::thread_rng().gen_range(-jitter..=jitter))
+    }
+}
+
+// Override ต่อการส่งแต่ละครั้ง (ไม่ระบุ = ใช้ค่าจาก DropTeaConfig)
+#[derive(Debug, Clone, Default)]
+pub struct SendOptions {
+    pub compression_level: Option<i32>,
+    pub io_priority: Option<IoPriority>,
+    // ผู้รับต้องตัดสินใจภายในเวลานี้ ไม่งั้นข้อเสนอถูกถอน (None = ใช้ DropTeaConfig::timeouts)
+    pub expires_in: Option<Duration>,
+    // ไฟล์นี้เป็นส่วนหนึ่งของชุด (ตั้งให้เองโดย send_batch)
+    pub batch: Option<BatchInfo>,
+    // ภาพตัวอย่าง (ใช้เฉพาะไฟล์รูป/วิดีโอ, send_batch ไม่ส่งต่อให้ทุกไฟล์)
+    pub thumbnail: Option<Thumbnail>,
+    // key-value ของแอป ไปถึง Incoming / Completed ของผู้รับ (send_batch แนบให้ทุกไฟล์ในชุด)
+    pub metadata: Metadata,
+    // ชื่อที่ผู้รับเห็นแทนชื่อไฟล์ (mirror_folder ใส่ Path ในโฟลเดอร์ให้)
+    pub name: Option<String>,
 }
 
 pub struct ClientStat { pub count: u32, pub first_seen: Instant, pub banned_until: Option<Instant> }
 pub struct ConnectionGuard { pub clients: TokioMutex<HashMap<std::net::IpAddr, ClientStat>> }
 impl ConnectionGuard {
     pub fn new() -> Self { Self { clients: TokioMutex::new(HashMap::new()) } }
-    pub async fn check_access(&self, ip: std::net::IpAddr) -> bool { true }
+    pub async fn check_access(&self, _ip: std::net::IpAddr) -> bool { true }
+}
+impl Default for ConnectionGuard {
+    fn default() -> Self { Self::new() }
+}
+
+/// ส่วนที่แทนของจริงได้ตอนสร้าง Engine (ไม่อยู่ใน DropTeaConfig เพราะไม่ใช่ค่าจาก config.toml) ตั้งผ่าน DropTeaBuilder
+#[derive(Default)]
+pub struct EngineParts {
+    // Some = Transport ในโปรเซสที่ 127.0.0.1:port แทน Socket (TLS ถ้า mode เป็น tcp / quic)
+    pub memory_network: Option<Arc<MemoryNetwork>>,
+    // Some = ใช้แทน mode / custom_transport ของ Config (ชื่อไว้ใน Stats / mDNS)
+    pub transport: Option<(&'static str, Arc<DynTransport>)>,
+    // Some = ใช้แทน mDNS / BLE ทั้งหมด
+    pub discovery: Option<Vec<DynDiscoveryBackend>>,
+    // รันหลังรับไฟล์ครบ (ต่อจาก [hooks] post_receive ของ Config)
+    pub post_receive: Vec<DynPostReceiveHook>,
+    pub middleware: Vec<DynTransferMiddleware>,
 }
 
 pub struct DropTeaCore {
     pub rt: Arc<Runtime>,
     pub handler: Arc<Box<dyn TransferEventHandler>>,
     pub transport: Arc<DynTransport>,
+    // Connect อย่างเดียว: ส่งหา Peer ที่ [peers.*] บังคับ plaintcp ไว้ (Engine โหมดอื่น)
+    plain_client: Arc<DynTransport>,
+    // ตัวเดียวกับ transport แต่เก็บ Type จริงไว้เรียก Signaling (เฉพาะโหมด WebRtc)
+    #[cfg(feature = "webrtc")]
+    pub webrtc: Option<Arc<WebRtcTransport>>,
     pub discovery: DiscoveryEngine<EventHandlerAdapter>,
     pub discovery_rx: StdMutex<Option<mpsc::Receiver<DiscoveryInternalEvent>>>,
     pub guard: Arc<ConnectionGuard>,
@@ -46,71 +355,669 @@ pub struct DropTeaCore {
     pub incoming_limiter: Arc<Semaphore>, 
     pub pending_transfers: Arc<StdMutex<HashMap<String, mpsc::UnboundedSender<crate::core::notification::UserResponse>>>>,
     pub node_name: String,
-    pub dev_mode: bool,
+    pub receive_options: ReceiveOptions,
+    pub compression_level: Option<i32>,
+    pub preferred_compression: Option<CompressionAlgo>,
+    pub io_priority: IoPriority,
+    pub mmap_threshold: Option<u64>,
+    pub recorder: Arc<EventRecorder>,
+    events: Arc<EventStream>,
+    // Config ปัจจุบัน + ค่าที่ Hot-Reload ได้ (dev_mode / approval / save_rules / limits)
+    reloader: Arc<ConfigReloader>,
+    pub rendezvous_listen: Option<std::net::SocketAddr>,
+    pub port_mapping: Option<MappingProtocol>,
+    pub hotspot_gateway: Option<std::net::IpAddr>,
+    // ขนาดปัจจุบันของ incoming_limiter (ตัวเดียวกับที่ Admin ใช้)
+    pub incoming_limit: Arc<Limit>,
+    pub tunables: Arc<Tunables>,
+    pub auto_tune: bool,
+    // Fingerprint ของ Cert เราเอง (ส่งให้ Peer ตอน BLE Handshake) None = โหมดที่ไม่มี TLS
+    pub local_fingerprint: Option<String>,
+    pub ble_max_file_size: u64,
+    pub timeouts: Timeouts,
+    pub retry: RetryPolicy,
+    pub parallel: ParallelPolicy,
+    pub tcp_parallel: ParallelPolicy,
+    pub batches: Arc<BatchTracker>,
+    // สรุปของ Transfer ที่จบแล้ว (get_task_stats)
+    pub stats: Arc<StatsStore>,
+    mode: TransportMode,
+    // ชื่อของ Transport ที่ใช้จริง (mode.as_str() หรือชื่อของ Custom Transport)
+    transport_name: &'static str,
+    // engine -> service -> transfer (ดู cancel.rs)
+    pub shutdown: CancellationToken,
+    service: StdMutex<CancellationToken>,
+    pub transfers: Arc<TransferTokens>,
+    // Port ของ Control Channel ของ Service ปัจจุบัน (None = ยังไม่ Start / Bind ไม่ได้)
+    control_port: Arc<StdMutex<Option<u16>>>,
+    // None = ปิด persist_outbox
+    outbox: Option<Arc<Outbox>>,
+    // Capability ของ Peer ที่เคยรู้ (ผูกกับ Fingerprint) + ที่อยู่ known_hosts ของ TLS
+    peer_caps: Arc<PeerCapsCache>,
+    // ชื่อเล่น / โน้ต / เวลาที่เห็นล่าสุด (get_peers)
+    peer_registry: Arc<PeerRegistry>,
+    storage_path: String,
+    guest: bool,
+    // ประกาศ / หา Nameplate ของ Code ข้าม NAT (None = ไม่ได้ตั้ง rendezvous_server หรือไม่ใช่โหมด QUIC)
+    rendezvous: Option<Arc<RendezvousClient>>,
+    // [localsend] (Server เริ่มใน start_service, Peer ของ LocalSend ส่งผ่าน API นี้ใน spawn_send)
+    localsend: Option<Arc<LocalSend>>,
+    // [links] (Server เริ่มใน start_service)
+    links: Option<Arc<DownloadLinks>>,
+}
+
+// Event ทุกตัวผ่าน Recorder (Diagnostics) และ EventStream (subscribe) ก่อนถึง Handler ของ Caller
+fn wrap_handler(inner: Box<dyn TransferEventHandler>, recorder: &Arc<EventRecorder>, events: &Arc<EventStream>) -> Arc<Box<dyn TransferEventHandler>> {
+    let streaming = StreamingHandler { inner, stream: events.clone() };
+    Arc::new(Box::new(RecordingHandler { inner: Box::new(streaming), recorder: recorder.clone() }))
+}
+
+// IPv6 ต้องครอบ [] ก่อนต่อ Port
+fn bracket_host(ip: &str) -> String {
+    if ip.contains(':') && !ip.starts_with('[') { format!("[{}]", ip) } else { ip.to_string() }
+}
+
+// Key ของ known_hosts (TLS เห็นแค่ IP ไม่มี Zone)
+fn host_key(host: &str) -> String {
+    crate::core::utils::parse_scoped_ip(host).map_or_else(|| host.to_string(), |(ip, _)| ip.to_string())
+}
+
+// สถานะที่ประกาศ: User ตั้งเองมาก่อน, Available แต่รับเต็มทุกช่อง = Busy
+fn announced_availability(manual: Availability, incoming: &Limit) -> Availability {
+    match manual {
+        Availability::Available if incoming.in_use() >= incoming.size() => Availability::Busy,
+        other => other,
+    }
+}
+
+fn resolve_addr(addr: Option<&str>) -> anyhow::Result<Option<std::net::SocketAddr>> {
+    use std::net::ToSocketAddrs;
+    match addr {
+        Some(a) => Ok(Some(a.to_socket_addrs()?.next().with_context(|| format!("Cannot resolve '{}'", a))?)),
+        None => Ok(None),
+    }
+}
+
+// Code เปิด / ปิด -> ประกาศ Nameplate ชุดใหม่ทาง Discovery + Rendezvous
+async fn announce_codes(offers: Arc<CodeOffers>, discovery: DiscoveryEngine<EventHandlerAdapter>, rendezvous: Option<Arc<RendezvousClient>>, shutdown: CancellationToken) {
+    let mut changed = offers.subscribe();
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            result = changed.changed() => if result.is_err() { return },
+        }
+        discovery.replace_features(codes::FEATURE_PREFIX, offers.features()).await;
+        if let Some(rendezvous) = &rendezvous {
+            rendezvous.set_aliases(offers.nameplates().into_iter().map(|n| format!("{}{}", codes::RENDEZVOUS_PREFIX, n)).collect());
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct EventHandlerAdapter(pub Arc<Box<dyn TransferEventHandler>>);
 
 impl TransferCallback for EventHandlerAdapter {
-    fn ask_accept_file(&self, task_id: &str, filename: &str, size: u64, sender: &str, device: &str) -> anyhow::Result<bool> {
-        let data = format!("[[REQUEST]]|{}|{}|{}|{}", filename, size, sender, device);
-        self.0.on_event(TransferEvent::Incoming { task_id: task_id.to_string(), filename: data });
+    fn ask_accept_file(&self, task_id: &str, filename: &str, size: u64, sender: &str, device: &str, session_id: Option<&str>, verdict: Option<&str>, thumbnail: Option<&Thumbnail>, metadata: &Metadata) -> anyhow::Result<bool> {
+        let mut data = format!("[[REQUEST]]|{}|{}|{}|{}", filename, size, sender, device);
+        // Field ที่ 5 (ต่อท้าย) -> UI เดิมที่อ่านแค่ 4 ช่องยังใช้ได้
+        if session_id.is_some() || verdict.is_some() { data.push('|'); data.push_str(session_id.unwrap_or_default()); }
+        // Field ที่ 6: ผล Reputation (Session ว่างได้)
+        if let Some(verdict) = verdict { data.push('|'); data.push_str(verdict); }
+        let request = IncomingRequest {
+            filename: filename.to_string(), size, sender: sender.to_string(), device: device.to_string(),
+            session_id: session_id.map(str::to_string), verdict: verdict.map(str::to_string), metadata: metadata.clone(),
+        };
+        self.0.on_event(TransferEvent::Incoming { task_id: task_id.to_string(), filename: data, thumbnail: thumbnail.cloned(), request: Some(request) });
         Ok(false)
     }
     fn on_start(&self, task_id: &str, filename: &str) { self.0.on_event(TransferEvent::Started { task_id: task_id.to_string(), msg: filename.to_string() }); }
-    fn on_progress(&self, task_id: &str, current: u64, total: u64) { self.0.on_event(TransferEvent::Progress { task_id: task_id.to_string(), current, total }); }
-    fn on_complete(&self, task_id: &str, info: &str) { self.0.on_event(TransferEvent::Completed { task_id: task_id.to_string(), info: info.to_string() }); }
+    fn on_progress(&self, task_id: &str, current: u64, total: u64) { self.on_progress_ex(task_id, current, total, &Throughput::default()); }
+    fn on_progress_ex(&self, task_id: &str, current: u64, total: u64, rate: &Throughput) {
+        self.0.on_event(TransferEvent::Progress {
+            task_id: task_id.to_string(), current, total,
+            bytes_per_sec: rate.bytes_per_sec, avg_bytes_per_sec: rate.avg_bytes_per_sec, eta_secs: rate.eta_secs,
+        });
+    }
+    fn on_complete(&self, task_id: &str, info: &str) {
+        self.0.on_event(TransferEvent::Completed { task_id: task_id.to_string(), info: info.to_string(), stats: None, metadata: Metadata::new() });
+    }
+    fn on_complete_with_stats(&self, task_id: &str, info: &str, stats: &TransferStats, metadata: &Metadata) {
+        self.0.on_event(TransferEvent::Completed { task_id: task_id.to_string(), info: info.to_string(), stats: Some(stats.clone()), metadata: metadata.clone() });
+    }
     fn on_error(&self, task_id: &str, error: &str) { self.0.on_event(TransferEvent::Error { task_id: task_id.to_string(), error: error.to_string() }); }
     fn on_reject(&self, task_id: &str, reason: &str) { self.0.on_event(TransferEvent::Rejected { task_id: task_id.to_string(), reason: reason.to_string() }); }
     fn on_peer_found(&self, id: &str, name: &str, ip: &str, port: u16, ssid: Option<&str>, transport: &str) {
-        self.0.on_event(TransferEvent::PeerFound { id: id.to_string(), name: name.to_string(), ip: ip.to_string(), port, ssid: ssid.map(|s| s.to_string()), transport: transport.to_string() });
+        self.0.on_event(TransferEvent::PeerFound {
+            id: id.to_string(), name: name.to_string(), ip: ip.to_string(), port, ssid: ssid.map(|s| s.to_string()), transport: transport.to_string(),
+            nickname: None, notes: None, profile: Default::default(), compression: vec![],
+        });
+    }
+    fn on_peer_found_ex(&self, peer: &PeerInfo) {
+        self.0.on_event(TransferEvent::PeerFound {
+            id: peer.id.clone(), name: peer.display_name.clone(), ip: peer.host().unwrap_or_default(), port: peer.port,
+            ssid: peer.ssid.clone(), transport: peer.transport.to_string(), nickname: None, notes: None,
+            profile: peer.profile.clone(),
+            compression: peer.compression.iter().flatten().map(|a| a.as_str().to_string()).collect(),
+        });
+    }
+    fn on_clock_skew(&self, task_id: &str, peer: &str, skew_ms: i64) {
+        self.0.on_event(TransferEvent::ClockSkew { task_id: task_id.to_string(), peer: peer.to_string(), skew_ms });
+    }
+    fn on_batch_progress(&self, p: &BatchProgress) {
+        self.0.on_event(TransferEvent::BatchProgress {
+            batch_id: p.batch_id.clone(), files_done: p.files_done, files_total: p.files_total,
+            bytes_done: p.bytes_done, bytes_total: p.bytes_total, current: p.current.clone(),
+        });
+    }
+    fn on_stalled(&self, task_id: &str, stalled_ms: u64) {
+        self.0.on_event(TransferEvent::Stalled { task_id: task_id.to_string(), stalled_ms });
+    }
+    fn on_duplicate(&self, task_id: &str, filename: &str, existing: Option<&str>) {
+        self.0.on_event(TransferEvent::Duplicate { task_id: task_id.to_string(), filename: filename.to_string(), existing: existing.map(str::to_string) });
+    }
+    fn on_hook_failed(&self, task_id: &str, path: &str, error: &str) {
+        self.0.on_event(TransferEvent::HookFailed { task_id: task_id.to_string(), path: path.to_string(), error: error.to_string() });
+    }
+    fn on_share_browsed(&self, peer: &str, share: &str, path: &str, allowed: bool) {
+        self.0.on_event(TransferEvent::ShareBrowsed { peer: peer.to_string(), share: share.to_string(), path: path.to_string(), allowed });
     }
     fn on_peer_lost(&self, id: &str) { self.0.on_event(TransferEvent::PeerLost { id: id.to_string() }); }
+    fn on_peer_updated(&self, id: &str, old_ip: &str, ip: &str, port: u16) {
+        self.0.on_event(TransferEvent::PeerUpdated { id: id.to_string(), old_ip: old_ip.to_string(), ip: ip.to_string(), port });
+    }
     fn ask_verify_certificate(&self, _: &str, _: &str, _: Option<&str>) -> anyhow::Result<crate::core::transfer::CertificateAction> { Ok(crate::core::transfer::CertificateAction::Accept) }
 }
 
 impl DropTeaCore {
-    pub fn new_with_config(rt: Arc<Runtime>, config: DropTeaConfig, handler: Box<dyn TransferEventHandler>) -> anyhow::Result<Self> {
-        let transport: Arc<DynTransport> = match config.mode {
-            TransportMode::Tcp => Arc::new(rt.block_on(async { TcpTransport::new(config.port, &config.storage_path, &config.node_name, None).await })?),
-            TransportMode::Quic => Arc::new(rt.block_on(async { QuicTransport::new(config.port, &config.storage_path, &config.node_name, None).await })?),
-            TransportMode::PlainTcp => Arc::new(rt.block_on(async { PlainTcpTransport::new(config.port).await })?),
+    pub fn new_with_config(rt: Arc<Runtime>, config: DropTeaConfig, handler: Box<dyn TransferEventHandler>) -> error::Result<Self> {
+        Self::new_with_parts(rt, config, handler, EngineParts::default())
+    }
+
+    pub fn new_with_parts(rt: Arc<Runtime>, config: DropTeaConfig, handler: Box<dyn TransferEventHandler>, parts: EngineParts) -> error::Result<Self> {
+        let custom = match (parts.transport, config.custom_transport.as_deref()) {
+            (Some(transport), _) => Some(transport),
+            (None, Some(name)) => Some(rt.block_on(custom::create(name, &config))?),
+            (None, None) => None,
         };
+        #[cfg(feature = "webrtc")]
+        let webrtc = match config.mode {
+            TransportMode::WebRtc if custom.is_none() => Some(Arc::new(WebRtcTransport::new(config.ice_servers.clone())?)),
+            _ => None,
+        };
+        // 👤 Guest = ตัวตนใหม่ทุกครั้งที่เปิด Engine ไม่เหลือร่องรอยบนเครื่องที่ยืมมา
+        let identity = (custom.is_none() && matches!(config.mode, TransportMode::Tcp | TransportMode::Quic))
+            .then(|| security::Identity::load(&config.storage_path, &config.node_name, config.guest)).transpose()?;
+        let transport_name = custom.as_ref().map_or(config.mode.as_str(), |(name, _)| *name);
+        let builtin = custom.is_none();
+        let mut rendezvous = None;
+        let transport: Arc<DynTransport> = match (custom, parts.memory_network, config.mode) {
+            (Some((_, transport)), _, _) => transport,
+            (None, Some(network), _) => {
+                let addr = std::net::SocketAddr::from((std::net::Ipv4Addr::LOCALHOST, config.port));
+                match &identity {
+                    Some(identity) => Arc::new(MemoryTransport::with_identity(network, addr, &config.storage_path, identity)?),
+                    None => Arc::new(MemoryTransport::new(network, addr)?),
+                }
+            }
+            (None, None, TransportMode::Tcp) => {
+                let identity = identity.as_ref().context("TLS identity missing")?;
+                Arc::new(rt.block_on(async { TcpTransport::new(config.port, &config.storage_path, identity, None).await })?)
+            }
+            (None, None, TransportMode::Quic) => {
+                let identity = identity.as_ref().context("TLS identity missing")?;
+                let quic_config = QuicConfig { rendezvous_server: resolve_addr(config.rendezvous_server.as_deref())?, ..config.quic.clone() };
+                let quic = Arc::new(rt.block_on(async { QuicTransport::new(config.port, &config.storage_path, &config.node_name, identity, Some(quic_config)).await })?);
+                rendezvous = quic.rendezvous();
+                quic
+            }
+            (None, None, TransportMode::PlainTcp) => Arc::new(rt.block_on(async { PlainTcpTransport::new(config.port).await })?),
+            #[cfg(feature = "webrtc")]
+            (None, None, TransportMode::WebRtc) => webrtc.clone().context("WebRTC transport missing")?,
+        };
+
+        let local_fingerprint = identity.as_ref().and_then(security::Identity::fingerprint);
 
-        let h_arc = Arc::new(handler);
-        let (discovery, rx) = DiscoveryEngine::new(EventHandlerAdapter(h_arc.clone()))?;
+        let recorder = Arc::new(EventRecorder::default());
+        let events = EventStream::new();
+        let peer_registry = Arc::new(match config.guest {
+            true => PeerRegistry::in_memory(),
+            false => PeerRegistry::open(&config.storage_path),
+        });
+        // ชั้นนอกสุด -> Recorder / subscribe เห็นชื่อเล่นใน PeerFound ด้วย
+        let h_arc: Arc<Box<dyn TransferEventHandler>> = Arc::new(Box::new(RegistryHandler { inner: wrap_handler(handler, &recorder, &events), registry: peer_registry.clone() }));
+        // ขอชื่อจริงของ Peer ที่ซ่อนชื่อได้เฉพาะ Transport ที่มี Cert ยืนยันตัวตน
+        let identify = identity.is_some().then(|| transport.clone());
+        let device_type = config.device_type.unwrap_or_else(DeviceType::detect);
+        let profile = LocalProfile {
+            fingerprint: local_fingerprint.clone(),
+            device_type,
+            transports: vec![transport_name.to_string()],
+        };
+        // 📱 ใช้ Cert ของ Engine ถ้ามี (Fingerprint ที่ LocalSend เห็นคงที่ข้ามการเปิดแอป)
+        let localsend = config.localsend.clone()
+            .map(|ls| LocalSend::new(ls, &config.node_name, device_type, identity.as_ref().map(|i| (i.certs.clone(), i.key.clone()))))
+            .transpose()?;
+        let backends = match parts.discovery {
+            Some(backends) => backends,
+            None => {
+                let mut backends = discovery_registry::from_config(&config)?;
+                if let Some(ls) = &localsend { backends.push(Arc::new(LocalSendDiscovery::new(ls.clone())) as DynDiscoveryBackend); }
+                backends
+            }
+        };
+        let (discovery, rx) = DiscoveryEngine::new(EventHandlerAdapter(h_arc.clone()), config.health_check, config.discovery_privacy, config.guest, identify, profile, peer_registry.clone(), Some(backends))?;
+        let outgoing_limiter = Arc::new(Semaphore::new(config.max_outgoing));
+        let incoming_limiter = Arc::new(Semaphore::new(config.max_incoming));
+        let incoming_limit = Arc::new(Limit::new(incoming_limiter.clone(), config.max_incoming));
+        let outgoing_limit = Arc::new(Limit::new(outgoing_limiter.clone(), config.max_outgoing));
+        let reloader = ConfigReloader::new(&config, incoming_limit.clone(), outgoing_limit.clone(), h_arc.clone());
+        let tunables = Arc::new(Tunables::default());
+        let shutdown = CancellationToken::new();
+        let transfers = TransferTokens::new();
+        let batches = BatchTracker::new();
+        let stats = StatsStore::new();
+        let peer_caps = Arc::new(match config.guest {
+            true => PeerCapsCache::in_memory(),
+            false => PeerCapsCache::open(&config.storage_path),
+        });
+        // ก่อน Struct Literal (node_name ถูก Move เข้า Self)
+        let shares = Arc::new(ShareContext::new(&config.shares, DOWNLOAD_DIR, &config.node_name, config.share_options));
+        let codes = CodeOffers::new();
+        rt.spawn(announce_codes(codes.clone(), discovery.clone(), rendezvous.clone(), shutdown.clone()));
+        let admin = (!config.admin_fingerprints.is_empty()).then(|| Arc::new(AdminContext {
+            fingerprints: config.admin_fingerprints.iter().map(|f| f.to_lowercase()).collect(),
+            node_name: config.node_name.clone(),
+            save_path: reloader.download_dir.clone(),
+            temp_dir: config.temp_dir.clone(),
+            incoming: incoming_limit.clone(),
+            outgoing: outgoing_limit,
+        }));
         Ok(Self {
-            rt, handler: h_arc, transport, discovery, discovery_rx: StdMutex::new(Some(rx)),
+            rt, handler: h_arc, transport,
+            #[cfg(feature = "webrtc")]
+            webrtc,
+            discovery, discovery_rx: StdMutex::new(Some(rx)),
+            plain_client: Arc::new(PlainTcpTransport::client()),
             guard: Arc::new(ConnectionGuard::new()),
-            outgoing_limiter: Arc::new(Semaphore::new(50)),
-            incoming_limiter: Arc::new(Semaphore::new(5)), 
+            outgoing_limiter,
+            incoming_limiter,
             pending_transfers: Arc::new(StdMutex::new(HashMap::new())),
             node_name: config.node_name,
-            dev_mode: config.dev_mode,
+            receive_options: ReceiveOptions {
+                archive_mode: config.archive_mode,
+                approval_webhook: reloader.approval_webhook.clone(),
+                admin,
+                io_priority: config.io_priority,
+                dedup: config.dedup.then(|| ChunkIndex::open(DOWNLOAD_DIR, config.chunk_store.map(|max| ChunkStore::open(std::path::Path::new(DOWNLOAD_DIR).join(STORE_DIR), max)))),
+                sender_queue: config.sender_queue.then(SenderQueues::new),
+                shares,
+                download_dir: reloader.download_dir.clone(),
+                save_rules: reloader.save_rules.clone(),
+                tunables: tunables.clone(),
+                transfers: transfers.clone(),
+                reputation: config.reputation.as_ref().and_then(ReputationConfig::build),
+                sandbox: config.sandbox_helper.as_deref().map(SandboxHelper::new).transpose()?.map(Arc::new),
+                timeouts: config.timeouts,
+                batches: batches.clone(),
+                sessions: SessionApprovals::new(),
+                peer_caps: peer_caps.clone(),
+                stats: stats.clone(),
+                transport: transport_name,
+                guest: config.guest,
+                peer_overrides: reloader.peers.clone(),
+                max_bytes_per_sec: reloader.max_bytes_per_sec.clone(),
+                availability: Live::new(Availability::Available),
+                parallel: LaneRegistry::new(),
+                resume: ResumeRegistry::new(),
+                temp_dir: config.temp_dir.as_ref().map(std::path::PathBuf::from),
+                skip_duplicates: config.skip_duplicates,
+                post_receive: Arc::new(config.post_receive.iter().map(|h| Arc::new(h.clone()) as Arc<dyn PostReceiveHook>).chain(parts.post_receive).collect()),
+                middleware: Arc::new(MiddlewareChain::new(parts.middleware)),
+                requested: None,
+                codes,
+            },
+            batches,
+            stats,
+            mode: config.mode,
+            transport_name,
+            timeouts: config.timeouts,
+            retry: config.retry,
+            parallel: config.parallel,
+            tcp_parallel: config.tcp_parallel,
+            compression_level: config.compression_level,
+            preferred_compression: config.preferred_compression,
+            io_priority: config.io_priority,
+            mmap_threshold: config.mmap_threshold,
+            recorder,
+            events,
+            reloader,
+            rendezvous_listen: resolve_addr(config.rendezvous_listen.as_deref())?,
+            // WebRTC เจาะ NAT เองผ่าน ICE ไม่ต้อง Map / Custom Transport ไม่ได้ฟังบน config.port เสมอไป
+            port_mapping: match (config.port_mapping && builtin, config.mode) {
+                (false, _) => None,
+                #[cfg(feature = "webrtc")]
+                (_, TransportMode::WebRtc) => None,
+                (true, TransportMode::Quic) => Some(MappingProtocol::Udp),
+                (true, _) => Some(MappingProtocol::Tcp),
+            },
+            hotspot_gateway: config.hotspot_gateway,
+            incoming_limit,
+            tunables,
+            auto_tune: config.auto_tune,
+            local_fingerprint,
+            ble_max_file_size: config.ble_max_file_size,
+            service: StdMutex::new(shutdown.child_token()),
+            shutdown,
+            transfers,
+            control_port: Arc::new(StdMutex::new(None)),
+            outbox: (config.persist_outbox && !config.guest).then(|| Arc::new(Outbox::open(&config.storage_path))),
+            peer_caps,
+            peer_registry,
+            storage_path: config.storage_path.clone(),
+            guest: config.guest,
+            rendezvous,
+            localsend,
+            links: config.links.clone().map(DownloadLinks::new),
         })
     }
 
+    /// หยุด Accept / Discovery / Transfer ทั้งหมดของ Service ปัจจุบัน (start_service ใหม่ได้)
+    pub fn stop_service(&self) {
+        let mut service = self.service.lock().unwrap();
+        service.cancel();
+        *service = self.shutdown.child_token();
+    }
+
+    /// ปิด Engine แล้วรอจน Task ทุกตัวปล่อย Transport (Listener คืน Port) ใช้ก่อนสร้าง Engine ใหม่บน Port เดิม
+    /// false = ยังมีที่อื่นถือ Core / Transport อยู่เกิน limit (Port อาจยังไม่ว่าง)
+    pub async fn close(core: Arc<Self>, limit: Duration) -> bool {
+        let transport = Arc::downgrade(&core.transport);
+        core.shutdown.cancel();
+        drop(core);
+        let deadline = tokio::time::Instant::now() + limit;
+        while transport.strong_count() > 0 {
+            if tokio::time::Instant::now() >= deadline { return false; }
+            tokio::time::sleep(CLOSE_POLL_INTERVAL).await;
+        }
+        true
+    }
+
+    /// false = ไม่มี Transfer นี้ (จบไปแล้ว)
+    pub fn cancel_transfer(&self, task_id: &str) -> bool {
+        self.notify_peer(task_id, |transfer_id| ControlMessage::Cancel { transfer_id });
+        self.transfers.cancel(task_id)
+    }
+
+    /// สร้าง Engine แบบ Fluent (ตรวจค่าตอน build())
+    pub fn builder() -> DropTeaBuilder {
+        DropTeaBuilder::new()
+    }
+
+    /// Builder ที่ใช้ Transport ของ Crate อื่นแทน mode (เช่น Serial Link / Unix Socket) ดู transports/custom.rs
+    pub fn with_transport(name: &'static str, transport: Arc<DynTransport>) -> DropTeaBuilder {
+        DropTeaBuilder::new().custom_transport(name, transport)
+    }
+
+    /// Config ที่ Apply อยู่ตอนนี้ (รวมค่าที่ Reload มาแล้ว)
+    pub fn config(&self) -> DropTeaConfig {
+        self.reloader.current()
+    }
+
+    /// Apply ค่าที่ Hot-Reload ได้จาก Config ใหม่ (ค่าอื่นเปลี่ยน = Error ไม่มีอะไรถูก Apply)
+    pub fn reload_config(&self, config: DropTeaConfig) -> error::Result<Vec<String>> {
+        self.reloader.apply(config)
+    }
+
+    /// ดูไฟล์ config.toml แล้ว Reload เองเมื่อถูกแก้ (ไฟล์ที่ Reload ไม่ได้ -> Error Event task_id = "config")
+    pub fn watch_config(&self, path: &str) {
+        let watch = self.reloader.watch(std::path::PathBuf::from(path), self.shutdown.child_token());
+        self.rt.spawn(watch);
+    }
+
+    /// ค่าเฉพาะ Peer ตอน Runtime (id = Fingerprint / Peer ID / ชื่อเครื่อง / IP, ทุก Field ว่าง = ลบ)
+    /// มีผลกับ Transfer ที่เริ่มหลังจากนี้ (Reload ที่แก้ [peers.*] ในไฟล์จะแทนที่ชุดนี้ทั้งหมด)
+    pub fn set_peer_override(&self, peer: &str, prefs: PeerOverride) -> error::Result<()> {
+        prefs.validate(self.mode)?;
+        self.reloader.peers.set(peer, prefs);
+        Ok(())
+    }
+
+    pub fn clear_peer_override(&self, peer: &str) -> bool {
+        self.reloader.peers.remove(peer)
+    }
+
+    pub fn peer_overrides(&self) -> HashMap<String, PeerOverride> {
+        self.reloader.peers.all()
+    }
+
+    // --- Peer Registry ---
+
+    /// Peer ที่ออนไลน์อยู่ (ก่อน) และที่เคยเห็น เรียงตามเวลาที่เห็นล่าสุด
+    pub fn get_peers(&self) -> Vec<PeerEntry> {
+        let mut entries: HashMap<String, PeerEntry> = self.peer_registry.all().into_iter()
+            .map(|(key, record)| (key.clone(), PeerEntry { key, id: None, online: false, profile: Default::default(), record }))
+            .collect();
+        for peer in self.discovery.known_peers.iter() {
+            let key = self.peer_registry.key(&peer.id);
+            let entry = entries.entry(key.clone()).or_insert_with(|| PeerEntry { key, id: None, online: false, profile: Default::default(), record: PeerRecord::default() });
+            entry.id = Some(peer.id.clone());
+            entry.online = true;
+            entry.profile = peer.profile.clone();
+            entry.record.name = peer.display_name.clone();
+            if let Some(host) = peer.host() { entry.record.ip = Some(host); }
+        }
+        let mut entries: Vec<PeerEntry> = entries.into_values().collect();
+        entries.sort_by(|a, b| b.online.cmp(&a.online).then(b.record.last_seen.cmp(&a.record.last_seen)));
+        entries
+    }
+
+    /// peer = Discovery ID หรือ Key จาก get_peers (None / "" = ลบชื่อเล่น)
+    pub fn set_peer_nickname(&self, peer: &str, nickname: Option<&str>) -> error::Result<()> {
+        self.peer_registry.set_nickname(peer, nickname)?;
+        self.announce_peer(peer);
+        Ok(())
+    }
+
+    pub fn set_peer_notes(&self, peer: &str, notes: Option<&str>) -> error::Result<()> {
+        self.peer_registry.set_notes(peer, notes)?;
+        self.announce_peer(peer);
+        Ok(())
+    }
+
+    pub fn forget_peer(&self, peer: &str) -> bool {
+        let key = self.peer_registry.key(peer);
+        let forgotten = self.peer_registry.forget(&key);
+        if forgotten { self.announce_peer(&key); }
+        forgotten
+    }
+
+    // ส่ง PeerFound ซ้ำให้ UI เห็นชื่อเล่นใหม่ทันที (เฉพาะ Peer ที่ออนไลน์อยู่)
+    fn announce_peer(&self, peer: &str) {
+        let key = self.peer_registry.key(peer);
+        let online: Vec<PeerInfo> = self.discovery.known_peers.iter()
+            .filter(|p| self.peer_registry.key(&p.id) == key)
+            .map(|p| p.value().clone())
+            .collect();
+        let adapter = EventHandlerAdapter(self.handler.clone());
+        for peer in &online {
+            adapter.on_peer_found_ex(peer);
+        }
+    }
+
+    // Override ตอนส่ง: Fingerprint ที่เคยเห็นที่ Address นี้ -> Peer ID -> ชื่อใน Discovery -> IP (ไม่ระบุความเร็ว = server.max_bytes_per_sec)
+    fn send_override(&self, peer_id: Option<&str>, host: &str) -> PeerOverride {
+        let host = host_key(host);
+        let fingerprint = security::known_fingerprint(&self.storage_path, &host);
+        let name = peer_id.and_then(|id| self.discovery.known_peers.get(id).map(|p| p.display_name.clone()));
+        let mut prefs = self.reloader.peers.lookup(fingerprint.as_deref().into_iter().chain(peer_id).chain(name.as_deref()).chain([host.as_str()]));
+        prefs.max_bytes_per_sec = prefs.max_bytes_per_sec.or(self.reloader.max_bytes_per_sec.get());
+        prefs
+    }
+
+    // บังคับ plaintcp = Connect ด้วย TCP เปล่า (Peer นั้นต้องรันโหมด plaintcp) นอกนั้นใช้ Transport ของ Engine
+    fn send_transport(&self, prefs: &PeerOverride) -> (Arc<DynTransport>, &'static str) {
+        match prefs.transport {
+            Some(TransportMode::PlainTcp) if self.transport_name != TransportMode::PlainTcp.as_str() => (self.plain_client.clone(), TransportMode::PlainTcp.as_str()),
+            _ => (self.transport.clone(), self.transport_name),
+        }
+    }
+
+    /// สรุปของ Transfer ที่จบแล้ว (None = ยังไม่จบ / ไม่สำเร็จ / เก่าเกินที่เก็บไว้)
+    pub fn task_stats(&self, task_id: &str) -> Option<TransferStats> {
+        self.stats.get(task_id)
+    }
+
+    /// Event ทุกตัวแบบ async (Subscribe ได้หลายตัว เห็นเฉพาะ Event หลังจาก Subscribe)
+    /// ใช้แทน TransferEventHandler ได้โดยส่ง NoopHandler ให้ new_with_config / send_file
+    pub fn subscribe(&self) -> broadcast::Receiver<TransferEvent> {
+        self.events.subscribe()
+    }
+
+    /// หยุด/ทำต่อ Transfer ทั้งสองฝั่ง (Peer ไม่ถือว่า Connection ตายระหว่าง Pause)
+    pub fn pause_transfer(&self, task_id: &str, paused: bool) -> bool {
+        self.notify_peer(task_id, |transfer_id| ControlMessage::Pause { transfer_id, paused });
+        self.transfers.set_paused(task_id, paused)
+    }
+
+    // แจ้งอีกฝั่งของ Transfer ผ่าน Control Channel (Peer รุ่นเก่าไม่มี Port ก็ข้าม)
+    fn notify_peer(&self, task_id: &str, msg: impl FnOnce(String) -> ControlMessage) {
+        let Some(remote) = self.transfers.remote(task_id) else { return; };
+        let Some(port) = remote.control_port else { return; };
+        self.rt.spawn(control::notify(std::net::SocketAddr::new(remote.ip, port), msg(remote.transfer_id)));
+    }
+
+    // โฟลเดอร์ที่อาจมี .part (temp_dir ก่อน แล้วที่รับไฟล์)
+    fn part_dirs(&self) -> Vec<std::path::PathBuf> {
+        self.receive_options.temp_dir.iter().cloned().chain(std::iter::once(std::path::PathBuf::from(self.receive_options.download_dir.get()))).collect()
+    }
+
+    /// .part ที่ค้างอยู่ (resumable = ผู้ส่งเดิมกลับมาต่อได้ภายใน RESUME_WINDOW)
+    pub fn list_incomplete(&self) -> Vec<IncompleteFile> {
+        self.receive_options.resume.list_incomplete(&self.part_dirs())
+    }
+
     pub fn start_service(&self, port: u16) {
         let rt = self.rt.clone(); let transport = self.transport.clone(); let h = self.handler.clone(); 
-        let guard = self.guard.clone(); let inc_lim = self.incoming_limiter.clone(); let p_map = self.pending_transfers.clone(); 
-        let save_path = "./downloads".to_string(); 
-        let is_dev = self.dev_mode;
+        let inc_lim = self.incoming_limiter.clone(); let p_map = self.pending_transfers.clone(); 
+        let state_dir = DOWNLOAD_DIR.to_string(); 
+        let is_dev = self.reloader.dev_mode.clone();
+        let receive_options = self.receive_options.clone();
+        let service = self.service.lock().unwrap().clone();
+        let mut features = match self.receive_options.dedup {
+            Some(_) => vec![dedup::FEATURE.to_string()],
+            None => vec![],
+        };
+        features.push(control::SPEED_TEST_FEATURE.to_string());
+        features.push(parallel::FEATURE.to_string());
+        features.push(resume::FEATURE.to_string());
+        if self.receive_options.reputation.is_some() { features.push(reputation::FEATURE.to_string()); }
+        features.extend(self.receive_options.codes.features());
+        // 🎛️ Control Channel: PING / CANCEL / PAUSE / CAPS แยกจาก Data Port
+        let control_port = match control::bind() {
+            Ok(listener) => {
+                let port = listener.local_addr().ok().map(|a| a.port());
+                let ctx = Arc::new(ControlContext::new(self.transfers.clone(), features.clone()));
+                rt.spawn(control::serve(listener, ctx, service.clone()));
+                port
+            }
+            Err(e) => { tracing::warn!("Control channel unavailable: {}", e); None }
+        };
+        *self.control_port.lock().unwrap() = control_port;
+        if self.auto_tune {
+            let (incoming_limit, tunables, h_tune) = (self.incoming_limit.clone(), self.tunables.clone(), h.clone());
+            let dir = self.receive_options.download_dir.get();
+            rt.spawn(async move {
+                match tuning::benchmark(std::path::Path::new(&dir)).await {
+                    Ok(preset) => {
+                        let msg = Self::apply_preset(&incoming_limit, &tunables, &preset);
+                        h_tune.on_event(TransferEvent::Log { level: "INFO".into(), msg });
+                    }
+                    Err(e) => tracing::warn!("Auto-tune skipped: {}", e),
+                }
+            });
+        }
+        if let Some(bind) = self.rendezvous_listen {
+            let service = service.clone();
+            rt.spawn(async move {
+                tokio::select! {
+                    _ = service.cancelled() => {},
+                    result = crate::core::rendezvous::serve(bind) => if let Err(e) = result {
+                        tracing::error!("Rendezvous server stopped: {}", e);
+                    },
+                }
+            });
+        }
+        if let Some(ls) = &self.localsend {
+            match localsend::bind(ls.port()) {
+                Ok(listener) => {
+                    let ctx = Arc::new(ReceiveContext { callback: EventHandlerAdapter(h.clone()), pending_map: p_map.clone(), options: receive_options.clone() });
+                    rt.spawn(ls.clone().serve(listener, ctx, service.clone()));
+                }
+                Err(e) => tracing::warn!("LocalSend API unavailable on port {}: {}", ls.port(), e),
+            }
+        }
+        if let Some(links) = &self.links {
+            match crate::core::utils::bind_dual_stack(links.port()) {
+                Ok(listener) => { rt.spawn(links.clone().serve(listener, EventHandlerAdapter(h.clone()), receive_options.timeouts.io, service.clone())); }
+                Err(e) => tracing::warn!("Download links unavailable on port {}: {}", links.port(), e),
+            }
+        }
+        // 🧹 Janitor: ตอนเริ่มแล้วทุก JANITOR_INTERVAL ลบ .part ที่ตายแล้ว / Park .part ที่ค้างจากรอบก่อน Restart กลับให้ต่อได้
+        {
+            let (resume, dirs, service) = (self.receive_options.resume.clone(), self.part_dirs(), service.clone());
+            rt.spawn(async move {
+                loop {
+                    let report = resume.sweep(&dirs, resume::PART_TTL);
+                    if report.removed > 0 || report.recovered > 0 {
+                        tracing::info!("🧹 Removed {} stale .part files, recovered {} resumable", report.removed, report.recovered);
+                    }
+                    tokio::select! {
+                        _ = service.cancelled() => break,
+                        _ = tokio::time::sleep(resume::JANITOR_INTERVAL) => {},
+                    }
+                }
+            });
+        }
+        // 🚦 รับเต็มทุกช่อง -> ประกาศ busy ให้ผู้ส่งเห็นก่อน Connect (ว่างแล้วกลับเป็นค่าที่ User ตั้ง)
+        {
+            let (discovery, availability, incoming_limit, service) = (self.discovery.clone(), self.receive_options.availability.clone(), self.incoming_limit.clone(), service.clone());
+            rt.spawn(async move {
+                loop {
+                    tokio::select! {
+                        _ = service.cancelled() => break,
+                        _ = tokio::time::sleep(AVAILABILITY_CHECK_INTERVAL) => {},
+                    }
+                    discovery.set_availability(announced_availability(availability.get(), &incoming_limit)).await;
+                }
+            });
+        }
+        let accept_service = service.clone();
         rt.spawn(async move {
+            let service = accept_service;
             h.on_event(TransferEvent::ServerStarted { port });
             loop {
-                match transport.accept().await {
-                    Ok((stream, addr)) => {
-                        let h_c = h.clone(); let path = save_path.clone(); let lim = inc_lim.clone(); let map = p_map.clone();
+                let accepted = tokio::select! {
+                    _ = service.cancelled() => break,
+                    accepted = transport.accept() => accepted,
+                };
+                match accepted {
+                    Ok((stream, addr, fingerprint)) => {
+                        let h_c = h.clone(); let path = state_dir.clone(); let lim = inc_lim.clone(); let map = p_map.clone();
+                        let opts = receive_options.clone();
+                        let cancel = service.clone();
+                        let is_dev = is_dev.clone();
+                        // task_id ถูกเติมใน handle_incoming ตอนได้ FileHeader
+                        let span = tracing::info_span!("receive", peer = %addr, task_id = tracing::field::Empty);
+                        let trace_span = span.clone();
                         tokio::spawn(async move {
-                            if let Err(e) = handle_incoming(stream, path, EventHandlerAdapter(h_c.clone()), lim, map).await {
-                                if is_dev {
+                            if let Err(e) = handle_incoming(stream, path, EventHandlerAdapter(h_c.clone()), lim, map, opts, addr, fingerprint, cancel, trace_span).await {
+                                if is_dev.load(Ordering::Relaxed) {
                                     h_c.on_event(TransferEvent::Error { task_id: "incoming".into(), error: e.to_string() });
                                 } else {
-                                    log::error!("Incoming connection failed: {}", e);
+                                    tracing::error!("Incoming connection failed: {}", e);
                             }
                             }
-                        });
+                        }.instrument(span));
                     }
                     Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
                 }
@@ -118,46 +1025,802 @@ impl DropTeaCore {
         });
         
         let rx_opt = self.discovery_rx.lock().unwrap().take();
-        if let Some(rx) = rx_opt {
+        {
             let discovery = self.discovery.clone();
             let device_id = self.node_name.clone(); 
-            let is_dev = self.dev_mode;
+            // Discovery อ่านครั้งเดียวตอนเริ่ม (dev_mode ที่ Reload มามีผลกับ mDNS ตอน start_service รอบถัดไป)
+            let is_dev = self.reloader.dev_mode.load(Ordering::Relaxed);
             let h_discovery = self.handler.clone();
+            let mapping_protocol = self.port_mapping.filter(|_| port != 0);
+            let hotspot_gateway = self.hotspot_gateway;
+            let transport = self.transport.clone();
             rt.spawn(async move {
-                if let Err(e) = discovery.start(device_id, port, is_dev, rx).await {
+                let external_addr = match mapping_protocol {
+                    Some(protocol) => port_mapping::start(protocol, port).await.map(|m| {
+                        h_discovery.on_event(TransferEvent::Log { level: "INFO".into(), msg: format!("External address: {}", m.external) });
+                        m.external.to_string()
+                    }),
+                    None => None,
+                };
+                // 📶 เปิด Hotspot อยู่ -> ประกาศ Address ฝั่ง Hotspot ด้วย ให้มือถือที่เกาะอยู่เห็น
+                let hotspot = hotspot::detect(hotspot_gateway);
+                if let Some(hs) = &hotspot {
+                    h_discovery.on_event(TransferEvent::Log { level: "INFO".into(), msg: format!("Hotspot detected: {} on '{}'", hs.addr, hs.interface) });
+                }
+                if let Err(e) = discovery.start(device_id, port, is_dev, features, external_addr, hotspot.map(|hs| hs.addr), control_port, rx_opt, service.clone()).await {
                     h_discovery.on_event(TransferEvent::Error { task_id: "discovery".into(), error: e.to_string() });
+                    return;
+                }
+
+                // 📶 สลับ Network -> Registration และ Connection Pool เดิมใช้ไม่ได้แล้ว
+                let mut addrs = MdnsBackend::get_local_ips();
+                loop {
+                    addrs = tokio::select! {
+                        _ = service.cancelled() => break,
+                        addrs = netwatch::wait_for_change(&addrs) => addrs,
+                    };
+                    tracing::info!("📶 Network changed: {:?}", addrs);
+                    transport.on_network_changed().await;
+                    discovery.on_network_changed().await;
+                    h_discovery.on_event(TransferEvent::NetworkChanged { addrs: addrs.iter().map(|a| a.to_string()).collect() });
                 }
             });
         }
+        self.restore_outbox();
+    }
+
+    /// target_os = Override OS ของปลายทาง (None = ใช้ที่ Peer ประกาศใน mDNS / ที่จำไว้ใน Peer Cache)
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_file(&self, ip: String, port: u16, path: String, task_id: String, my_name: String, event_handler: Box<dyn TransferEventHandler>, target_os: Option<String>, options: SendOptions) {
+        let h = wrap_handler(event_handler, &self.recorder, &self.events);
+        self.enqueue_send(ip, port, path, task_id, my_name, h, target_os, options, None);
+    }
+
+    /// ส่งหลายไฟล์เป็นชุดเดียว (files = [(task_id, path)]) Handler ได้ Event รายไฟล์ตามปกติ + BATCH_PROGRESS รวม
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_batch(&self, ip: String, port: u16, files: Vec<(String, String)>, batch_id: String, my_name: String, event_handler: Box<dyn TransferEventHandler>, target_os: Option<String>, options: SendOptions) {
+        if files.is_empty() { return; }
+        let h = wrap_handler(event_handler, &self.recorder, &self.events);
+        // ไฟล์ที่อ่านขนาดไม่ได้นับเป็น 0 (จะไปล้มตอนส่งเอง)
+        let bytes = files.iter().map(|(_, path)| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)).sum();
+        let batch = BatchInfo { id: batch_id, files: files.len() as u64, bytes };
+
+        // 🎫 เสนอ Manifest ทั้งชุดก่อน: ผู้รับกดรับครั้งเดียว ไฟล์ในชุดรอผลก่อนค่อย Connect
+        let (decided, gate) = watch::channel(None);
+        let target = match crate::core::utils::parse_scoped_ip(&ip) {
+            Some(_) => Some((ip.clone(), port)),
+            None => self.discovery.current_addr(&ip),
+        };
+        let paths: Vec<String> = files.iter().map(|(_, path)| path.clone()).collect();
+        let base = SessionOffer { batch_id: batch.id.clone(), sender_name: my_name.clone(), sender_device: std::env::consts::OS.to_string(), files: Vec::new(), mirror: None };
+        let by_id = crate::core::utils::parse_scoped_ip(&ip).is_none().then_some(ip.as_str());
+        let prefs = self.send_override(by_id, target.as_ref().map_or(ip.as_str(), |(host, _)| host.as_str()));
+        let (transport, _) = self.send_transport(&prefs);
+        let wait = self.timeouts.user_decision;
+        let batch_id = batch.id.clone();
+        self.rt.spawn(async move {
+            let decision = match target {
+                Some((host, port)) => async {
+                    let files = session::build_manifest(&paths, OFFER_HASH_MAX_SIZE).await?;
+                    session::offer(&*transport, &bracket_host(&host), port, SessionOffer { files, ..base }, wait).await.map(|r| r.decision)
+                }.await,
+                None => Err(anyhow::anyhow!("No address for session offer")),
+            };
+            let decision = decision.unwrap_or_else(|e| {
+                tracing::info!("Batch {} falls back to per-file approval: {}", batch_id, e);
+                SessionDecision::PerFile
+            });
+            let _ = decided.send(Some(decision));
+        });
+
+        for (task_id, path) in files {
+            let options = SendOptions { batch: Some(batch.clone()), thumbnail: None, ..options.clone() };
+            self.enqueue_send(ip.clone(), port, path, task_id, my_name.clone(), h.clone(), target_os.clone(), options, Some(gate.clone()));
+        }
+    }
+
+    /// Mirror local_dir ไปที่ <save_path>/<remote_label> ของปลายทาง: แลก Manifest (Path / ขนาด / quick_hash) ก่อน
+    /// แล้วส่งเฉพาะไฟล์ที่ปลายทางยังไม่มี / เปลี่ยนไปเป็นชุดเดียว (ไม่ลบไฟล์ที่ต้นทางไม่มีแล้ว)
+    /// รอผู้รับตัดสินใจ (Peer ที่ Whitelist ไว้ผ่านเลย) คืน [(task_id, path ในโฟลเดอร์)] ที่เข้าคิวส่ง ว่าง = ตรงกันอยู่แล้ว
+    #[allow(clippy::too_many_arguments)]
+    pub fn mirror_folder(&self, ip: String, port: u16, local_dir: &str, remote_label: &str, my_name: String, event_handler: Box<dyn TransferEventHandler>, options: SendOptions) -> error::Result<Vec<(String, String)>> {
+        if !session::is_valid_mirror_label(remote_label) {
+            return Err(DropTeaError::Config(format!("mirror label '{}' must be a single folder name", remote_label)));
+        }
+        let (host, port) = match crate::core::utils::parse_scoped_ip(&ip) {
+            Some(_) => (ip.clone(), port),
+            None => self.discovery.current_addr(&ip).ok_or_else(|| DropTeaError::Network(format!("No address for '{}'", ip)))?,
+        };
+        let root = std::path::PathBuf::from(local_dir);
+        let scanned = self.rt.block_on(tokio::task::spawn_blocking(move || session::scan_mirror(&root)))
+            .map_err(|e| DropTeaError::Internal(e.to_string()))??;
+
+        let by_id = crate::core::utils::parse_scoped_ip(&ip).is_none().then_some(ip.as_str());
+        let prefs = self.send_override(by_id, &host);
+        let (transport, _) = self.send_transport(&prefs);
+        let batch_id = uuid::Uuid::new_v4().to_string();
+        let offer = SessionOffer {
+            batch_id: batch_id.clone(),
+            sender_name: my_name.clone(),
+            sender_device: std::env::consts::OS.to_string(),
+            files: scanned.iter().map(|(_, entry)| entry.clone()).collect(),
+            mirror: Some(remote_label.to_string()),
+        };
+        let response = self.rt.block_on(session::offer(&*transport, &bracket_host(&host), port, offer, self.timeouts.user_decision))?;
+        let needed = match (response.decision, response.needed) {
+            (SessionDecision::Approved, Some(needed)) => needed,
+            (SessionDecision::Declined, _) => return Err(DropTeaError::Rejected("Receiver Rejected".into())),
+            // PerFile วางไฟล์ลงโฟลเดอร์ย่อยไม่ได้ / Peer รุ่นเก่าไม่รู้จัก Mirror
+            _ => return Err(DropTeaError::Protocol("Receiver does not support folder mirror".into())),
+        };
+        let files: Vec<_> = scanned.into_iter().filter(|(_, entry)| needed.contains(&entry.name)).collect();
+        tracing::info!("🪞 Mirror '{}' -> '{}': {} file(s) to send", local_dir, remote_label, files.len());
+        if files.is_empty() { return Ok(Vec::new()); }
+
+        let h = wrap_handler(event_handler, &self.recorder, &self.events);
+        let batch = BatchInfo { id: batch_id, files: files.len() as u64, bytes: files.iter().map(|(_, e)| e.size).sum() };
+        let mut queued = Vec::with_capacity(files.len());
+        for (path, entry) in files {
+            let task_id = uuid::Uuid::new_v4().to_string();
+            let options = SendOptions { batch: Some(batch.clone()), thumbnail: None, name: Some(entry.name.clone()), ..options.clone() };
+            self.enqueue_send(ip.clone(), port, path.to_string_lossy().to_string(), task_id.clone(), my_name.clone(), h.clone(), None, options, None);
+            queued.push((task_id, entry.name));
+        }
+        Ok(queued)
     }
 
-    pub fn send_file(&self, ip: String, port: u16, path: String, task_id: String, my_name: String, event_handler: Box<dyn TransferEventHandler>, target_os: Option<String>) {
-        let rt = self.rt.clone(); let transport = self.transport.clone();
-        let h: Arc<Box<dyn TransferEventHandler>> = Arc::new(event_handler);
+    // gate = ผลการเสนอ Session ของชุด (None = ไม่ต้องรอ)
+    #[allow(clippy::too_many_arguments)]
+    fn enqueue_send(&self, ip: String, port: u16, path: String, task_id: String, my_name: String, h: Arc<Box<dyn TransferEventHandler>>, target_os: Option<String>, options: SendOptions, gate: Option<SessionGate>) {
+        let by_addr = crate::core::utils::parse_scoped_ip(&ip);
+        let peer = match by_addr {
+            Some((addr, _)) => self.discovery.find_peer_by_addr(addr, port),
+            // ไม่ใช่ IP -> อาจเป็น Peer ID (เช่น Peer ที่เห็นแค่ทาง BLE ยังไม่มี IP)
+            None => self.discovery.known_peers.get(&ip).map(|p| p.value().clone()),
+        };
+        let job = QueuedSend {
+            task_id, ip, port, path,
+            sender_name: my_name,
+            target_os,
+            peer_id: peer.as_ref().map(|p| p.id.clone()),
+            compression_level: options.compression_level,
+            io_priority: options.io_priority.map(|p| p.as_str().to_string()),
+            expires_in_ms: options.expires_in.map(|d| d.as_millis() as u64),
+            batch: options.batch,
+            thumbnail: options.thumbnail,
+            metadata: options.metadata,
+            name: options.name,
+            queued_at: crate::core::utils::timestamp_millis(),
+        };
+        if let Some(outbox) = &self.outbox { outbox.push(job.clone()); }
+        self.spawn_send(job, h, false, gate);
+    }
+
+    // ส่งต่อคิวที่ค้างจากรอบก่อน (เรียกตอน start_service) Event ไปที่ Handler หลัก
+    fn restore_outbox(&self) {
+        let Some(outbox) = &self.outbox else { return; };
+        let jobs: Vec<QueuedSend> = outbox.snapshot().into_iter().filter(|j| !self.transfers.contains(&j.task_id)).collect();
+        if jobs.is_empty() { return; }
+        self.handler.on_event(TransferEvent::Log { level: "INFO".into(), msg: format!("Restoring {} queued transfer(s)", jobs.len()) });
+        for job in jobs {
+            // Session ของรอบก่อนไม่อยู่แล้ว -> ผู้รับถามทีละไฟล์
+            self.spawn_send(job, self.handler.clone(), true, None);
+        }
+    }
+
+    // restored = มาจาก Outbox: รอ Peer กลับมาใน Discovery ได้ไม่จำกัดเวลา (จนกว่า Service หยุด)
+    fn spawn_send(&self, job: QueuedSend, h: Arc<Box<dyn TransferEventHandler>>, restored: bool, gate: Option<SessionGate>) {
+        let rt = self.rt.clone();
         let limiter = self.outgoing_limiter.clone();
+        let QueuedSend { task_id, ip, port, path, sender_name: my_name, target_os, peer_id, .. } = job.clone();
+        // 📌 [peers.*] มาก่อนค่าของ Engine
+        let prefs = self.send_override(peer_id.as_deref(), &ip);
+        let (transport, transport_name) = self.send_transport(&prefs);
+        let by_addr = crate::core::utils::parse_scoped_ip(&ip);
+        let compression_level = job.compression_level.or(self.compression_level);
+        let io_priority = job.io_priority.as_deref().and_then(IoPriority::from_name).unwrap_or(self.io_priority);
+        let mmap_threshold = self.mmap_threshold;
+        let expires_in = job.expires_in_ms.map(Duration::from_millis);
+        // ค่าใน Config มาก่อน Preset จาก Tuning
+        let preferred = self.preferred_compression.or_else(|| self.tunables.compression());
+        let ble_max_file_size = self.ble_max_file_size;
+        let timeouts = self.timeouts;
+        let retry = self.retry;
+        let parallel_policy = match transport_name {
+            name if name == TransportMode::Quic.as_str() => Some(self.parallel),
+            name if name == TransportMode::Tcp.as_str() => Some(self.tcp_parallel),
+            _ => None,
+        };
+        let discovery = self.discovery.clone();
+        let service = self.service.lock().unwrap().clone();
+        let transfers = self.transfers.clone();
+        let control_port = *self.control_port.lock().unwrap();
+        let outbox = self.outbox.clone();
+        let batches = self.batches.clone();
+        let stats = self.stats.clone();
+        let peer_caps = self.peer_caps.clone();
+        let storage_path = self.storage_path.clone();
+        let guest = self.guest;
+        let middleware = self.receive_options.middleware.clone();
+        let localsend = self.localsend.clone().zip(peer_id.as_deref().and_then(|id| self.discovery.known_peers.get(id).map(|p| p.value().clone())))
+            .and_then(|(ls, peer)| Some((ls, peer.host()?, peer.port, localsend::peer_protocol(&peer.features)?)));
+        let span = tracing::info_span!("send", task_id = %task_id, peer = peer_id.as_deref().unwrap_or(&ip));
         
         rt.spawn(async move {
-            let _p = match limiter.acquire().await { Ok(p) => p, Err(_) => return };
-            let target_host = if ip.contains(':') && !ip.starts_with('[') { format!("[{}]", ip) } else { ip.clone() };
-
-            match transport.connect(&target_host, port).await {
-                Ok(stream) => {
-                    let adapter = EventHandlerAdapter(h.clone());
-                    if let Err(e) = handle_sending(stream, path, task_id.clone(), adapter, my_name, target_os).await {
+            let transfer = transfers.register(&service, &task_id);
+            let batch_key = format!("send|{}", job.batch.as_ref().map_or("", |b| b.id.as_str()));
+            let adapter = BatchCallback::new(EventHandlerAdapter(h.clone()), &batches, batch_key, job.batch.as_ref(), &task_id);
+            let job_id = task_id.clone();
+            async {
+                // 🎫 ชุดที่เสนอ Session ไว้: รอผู้รับตัดสินใจทั้งชุดก่อน (ปฏิเสธ = ไม่ต้อง Connect ทุกไฟล์)
+                if let Some(mut gate) = gate {
+                    let decision = tokio::select! {
+                        d = gate.wait_for(Option::is_some) => d.ok().and_then(|d| *d),
+                        _ = transfer.token().cancelled() => {
+                            h.on_event(TransferEvent::Rejected { task_id, reason: REJECT_CANCELLED.into() });
+                            return;
+                        }
+                    };
+                    if decision == Some(SessionDecision::Declined) {
+                        h.on_event(TransferEvent::Rejected { task_id, reason: "Receiver Rejected".into() });
+                        return;
+                    }
+                }
+                // 🧱 Middleware ตัดสินก่อนเปิด Connection (ครั้งเดียว ไม่ซ้ำตอน Retry / Resume)
+                let mut context = TransferContext {
+                    task_id: task_id.clone(),
+                    direction: Direction::Send,
+                    filename: job.name.clone().unwrap_or_else(|| std::path::Path::new(&path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()),
+                    filesize: tokio::fs::metadata(&path).await.map_or(0, |m| m.len()),
+                    peer: peer_id.clone().unwrap_or_else(|| ip.clone()),
+                    metadata: job.metadata.clone(),
+                };
+                if let Err(reason) = middleware.before_send(&mut context).await {
+                    h.on_event(TransferEvent::Rejected { task_id, reason });
+                    return;
+                }
+                // 📱 เครื่อง LocalSend: ส่งผ่าน HTTP API ของมัน (ไม่มี Compression / Dedup / Resume)
+                if let Some((localsend, host, port, https)) = localsend {
+                    if let Err(e) = localsend.send(&host, port, https, &path, &context.filename, &task_id, &adapter, transfer.signal(), expires_in.unwrap_or(timeouts.user_decision), timeouts.io).await {
                         h.on_event(TransferEvent::Error { task_id, error: e.to_string() });
                     }
+                    return;
                 }
-                Err(e) => h.on_event(TransferEvent::Error { task_id, error: e.to_string() }),
+                // 📶 BLE อย่างเดียวส่งไฟล์ไม่ได้ -> รอจน Peer โผล่บน LAN แล้วส่งทางนั้นแทนการให้ User กดใหม่
+                if let Some(id) = peer_id.as_deref().filter(|id| discovery.current_addr(id).is_none()) {
+                    // 🔵 ไฟล์เล็กส่งทาง GATT ได้เลยไม่ต้องรอ LAN
+                    let small = context.filesize <= ble_max_file_size && !middleware.wraps_stream();
+                    let ble_mac = discovery.known_peers.get(id).and_then(|p| p.ble_mac.clone());
+                    if let Some(mac) = ble_mac.as_deref().filter(|_| small) {
+                        if let Err(e) = ble_transfer::send_file(mac, &path, &task_id, &my_name, &adapter, expires_in.unwrap_or(timeouts.user_decision), transfer.token()).await {
+                            h.on_event(TransferEvent::Error { task_id, error: e.to_string() });
+                        }
+                        return;
+                    }
+                    h.on_event(TransferEvent::Log { level: "INFO".into(), msg: format!("Waiting for LAN path to {}", id) });
+                    let limit = if restored { Duration::MAX } else { LAN_PATH_WAIT };
+                    let found = tokio::select! {
+                        found = discovery.wait_for_lan(id, limit) => found,
+                        _ = transfer.token().cancelled() => None,
+                    };
+                    if found.is_none() && !transfer.token().is_cancelled() {
+                        h.on_event(TransferEvent::Error { task_id, error: "Peer never appeared on LAN".into() });
+                        return;
+                    }
+                }
+                let _p = match limiter.acquire().await { Ok(p) => p, Err(_) => return };
+                // ยกเลิกระหว่างรอ LAN / รอคิว -> ไม่ต้อง Connect
+                if transfer.token().is_cancelled() {
+                    h.on_event(TransferEvent::Rejected { task_id, reason: REJECT_CANCELLED.into() });
+                    return;
+                }
+
+                // 🔥 เลือก Compression จาก Capability ที่ Peer ประกาศผ่าน mDNS (ดูตอนจะส่งจริง ข้อมูลล่าสุด)
+                let peer = peer_id.as_deref().and_then(|id| discovery.known_peers.get(id).map(|p| p.value().clone()));
+                let announced = peer.as_ref().filter(|p| p.compression.is_some());
+                // 🗃️ ไม่ได้ประกาศผ่าน mDNS (ส่งด้วย IP / Rendezvous / BLE) -> ใช้ที่เคยรู้จาก Fingerprint เดียวกัน
+                let cached = peer_id.as_deref().and_then(|id| peer_caps.lookup(id))
+                    .or_else(|| peer_caps.lookup(&host_key(&ip)))
+                    .or_else(|| security::known_fingerprint(&storage_path, &host_key(&ip)).and_then(|fp| peer_caps.get(&fp).map(|caps| (fp, caps))))
+                    .map(|(_, caps)| caps);
+                let peer_algos = match announced {
+                    Some(p) => p.compression.clone(),
+                    None => cached.as_ref().and_then(PeerCaps::compression_algos),
+                };
+                let peer_features = match announced {
+                    Some(p) => p.features.clone(),
+                    None => cached.as_ref().map(|c| c.features.clone()).unwrap_or_default(),
+                };
+                let lacks = |cap| cached.as_ref().is_some_and(|c| c.lacks(cap));
+                let use_dedup = peer_features.iter().any(|f| f == dedup::FEATURE) && !lacks(CAP_DEDUP);
+                // SHA-256 ใน Header ใช้แค่ Reputation ของผู้รับ -> อ่านทั้งไฟล์เฉพาะเมื่อ Peer ขอ
+                let offer_sha256 = peer_features.iter().any(|f| f == reputation::FEATURE);
+                // 🛤️ QUIC / TLS-TCP + ไฟล์ใหญ่ -> แบ่งหลาย Stream (Dedup ส่งเฉพาะ Chunk ที่ขาด ใช้ร่วมกันไม่ได้)
+                let parallel_plan = match parallel_policy.filter(|_| !use_dedup && !middleware.wraps_stream() && peer_features.iter().any(|f| f == parallel::FEATURE) && !lacks(CAP_PARALLEL_STREAMS)) {
+                    Some(policy) => tokio::fs::metadata(&path).await.ok().and_then(|m| policy.plan(m.len())),
+                    None => None,
+                };
+                // OS ของปลายทาง (iOS ที่ไม่ได้ประกาศ Compression = ส่งดิบ): target_os ที่ผู้เรียกระบุเป็น Override
+                // ไม่ระบุ -> ที่ Peer ประกาศใน mDNS -> ที่จำไว้จากรอบก่อน
+                let peer_os = target_os
+                    .or_else(|| peer.as_ref().and_then(|p| p.profile.os.clone()))
+                    .or_else(|| cached.as_ref().and_then(|c| c.os.clone()));
+                let compression_algo = match (prefs.compression, lacks(CAP_COMPRESSION)) {
+                    (Some(CompressionAlgo::None), _) | (_, true) => CompressionAlgo::None,
+                    (forced, false) => compression::negotiate(peer_algos.as_deref(), peer_os.as_deref(), forced.or(preferred)),
+                };
+
+                // 🛣️ Peer มีหลายเส้น (LAN + Hotspot) และไฟล์ใหญ่ -> เลือกเส้นที่ RTT ต่ำสุด (resolve ด้านล่างจะได้เส้นนั้น)
+                if let Some(id) = peer_id.as_deref() {
+                    let large = tokio::fs::metadata(&path).await.is_ok_and(|m| m.len() >= PATH_PROBE_MIN_SIZE);
+                    if large { discovery.fastest_addr(id).await; }
+                }
+
+                // 🔄 รอคิวอยู่นานแล้ว Peer อาจเปลี่ยน IP -> ใช้ Address ล่าสุดจาก Discovery
+                let resolve = || match peer_id.as_deref().and_then(|id| discovery.current_addr(id)) {
+                    Some(addr) => addr,
+                    None => (ip.clone(), port),
+                };
+                let (mut target_ip, mut target_port) = resolve();
+                if by_addr.is_some() && (target_ip.trim_matches(|c| c == '[' || c == ']') != ip.trim_matches(|c| c == '[' || c == ']') || target_port != port) {
+                    tracing::info!("Peer moved, sending to {}:{} instead of {}:{}", target_ip, target_port, ip, port);
+                }
+
+                // 🔁 Wi-Fi สะดุดชั่วคราว -> ลองใหม่ตาม RetryPolicy (แต่ละรอบใช้ Address ล่าสุด)
+                let mut attempt = 1;
+                let mut moved = false;
+                // 🔁 Connection หลุดกลางไฟล์ (เปลี่ยน AP) -> ต่อใหม่แล้วส่งต่อจาก offset ที่ผู้รับมี (ดู resume.rs) นับรวมใน RetryPolicy
+                let resumable = parallel_plan.is_none() && !use_dedup && peer_features.iter().any(|f| f == resume::FEATURE) && !lacks(CAP_RESUME);
+                let mut resuming = false;
+                loop {
+                    let connected = loop {
+                        let e = match transport.connect(&bracket_host(&target_ip), target_port).await {
+                            Ok(stream) => break Ok(stream),
+                            Err(e) => e,
+                        };
+                        // IP เปลี่ยนระหว่าง Connect -> ลองที่ Address ใหม่ทันที (ไม่นับเป็นรอบ Retry)
+                        let (new_ip, new_port) = resolve();
+                        if !moved && (new_ip != target_ip || new_port != target_port) {
+                            tracing::info!("Peer moved during connect, retrying {}:{}", new_ip, new_port);
+                            (target_ip, target_port) = (new_ip, new_port);
+                            moved = true;
+                            continue;
+                        }
+                        if attempt >= retry.attempts || !e.is_transient() { break Err(e); }
+                        attempt += 1;
+                        let delay = retry.delay(attempt);
+                        tracing::info!(attempt, error = %e, "Connect to {}:{} failed, retry {}/{} in {:?}", target_ip, target_port, attempt, retry.attempts, delay);
+                        h.on_event(TransferEvent::Retrying { task_id: task_id.clone(), attempt, delay_ms: delay.as_millis() as u64, error: e.to_string() });
+                        tokio::select! {
+                            _ = tokio::time::sleep(delay) => {}
+                            _ = transfer.token().cancelled() => break Err(DropTeaError::Cancelled),
+                        }
+                        (target_ip, target_port) = resolve();
+                        moved = false;
+                    };
+
+                    match connected {
+                        Ok(stream) => {
+                            // TLS ผ่านแล้ว known_hosts มี Fingerprint ของปลายทาง -> จำสิ่งที่ Peer ประกาศไว้ใช้รอบหน้า
+                            let target_key = host_key(&target_ip);
+                            if let Some(fp) = security::known_fingerprint(&storage_path, &target_key) {
+                                peer_caps.record(&fp, Some(peer_id.as_deref().unwrap_or(&target_key)), |caps| {
+                                    if let Some(p) = announced {
+                                        caps.compression = p.compression.as_ref().map(|algos| algos.iter().map(|a| a.as_str().to_string()).collect());
+                                        caps.features = p.features.clone();
+                                    }
+                                    if let Some(p) = &peer {
+                                        caps.transport = Some(p.transport.to_string());
+                                        if let Some(os) = &p.profile.os { caps.os = Some(os.clone()); }
+                                    }
+                                });
+                            }
+                            // ผู้รับอ้างถึง Transfer นี้ด้วย task_id ของเรา (FileHeader.transfer_id)
+                            if let Some((addr, _)) = crate::core::utils::parse_scoped_ip(&target_ip) {
+                                transfer.set_remote(RemoteTransfer { ip: addr, control_port: peer.as_ref().and_then(|p| p.control_port), transfer_id: task_id.clone() });
+                            }
+                            let collector = StatsCollector::new(stats.clone(), transport_name, attempt - 1);
+                            let stream = Throttled::new(stream, prefs.max_bytes_per_sec);
+                            let lanes = parallel_plan.map(|plan| ParallelSend {
+                                transport: transport.clone(),
+                                host: bracket_host(&target_ip),
+                                port: target_port,
+                                plan,
+                                max_bytes_per_sec: prefs.max_bytes_per_sec,
+                            });
+                            let params = SendParams {
+                                compression_level, io_priority, mmap_threshold, use_dedup, parallel: lanes, resuming, expires_in, control_port,
+                                batch: job.batch.clone(), thumbnail: job.thumbnail.clone(), offer_sha256, middleware: middleware.clone(), guest, timeouts,
+                            };
+                            let sent = handle_sending(stream, path.clone(), task_id.clone(), adapter.clone(), my_name.clone(), compression_algo, context.clone(), params, transfer.signal().clone(), collector).await;
+                            match sent {
+                                Err(e) if resumable && attempt < retry.attempts && !transfer.token().is_cancelled() && resume::connection_lost(&e) => {
+                                    attempt += 1;
+                                    let delay = retry.delay(attempt);
+                                    tracing::info!(attempt, error = %e, "Connection lost during transfer, resuming {}/{} in {:?}", attempt, retry.attempts, delay);
+                                    h.on_event(TransferEvent::Retrying { task_id: task_id.clone(), attempt, delay_ms: delay.as_millis() as u64, error: e.to_string() });
+                                    tokio::select! {
+                                        _ = tokio::time::sleep(delay) => {}
+                                        _ = transfer.token().cancelled() => {
+                                            h.on_event(TransferEvent::Rejected { task_id, reason: REJECT_CANCELLED.into() });
+                                            break;
+                                        }
+                                    }
+                                    (target_ip, target_port) = resolve();
+                                    moved = false;
+                                    resuming = true;
+                                    continue;
+                                }
+                                Err(e) => h.on_event(TransferEvent::Error { task_id, error: e.to_string() }),
+                                Ok(()) => {}
+                            }
+                        }
+                        Err(DropTeaError::Cancelled) => h.on_event(TransferEvent::Rejected { task_id, reason: REJECT_CANCELLED.into() }),
+                        Err(e) => h.on_event(TransferEvent::Error { task_id, error: e.to_string() }),
+                    }
+                    break;
+                }
+            }.await;
+            // หยุด Service / ปิดแอป -> เก็บไว้ส่งรอบหน้า, จบด้วยเหตุอื่น (รวมถึง User ยกเลิกเอง) -> ออกจากคิว
+            if let Some(outbox) = outbox.filter(|_| !service.is_cancelled()) {
+                outbox.remove(&job_id);
+            }
+        }.instrument(span));
+    }
+
+    // Backend เสริมจาก Embedder (เรียกก่อนหรือหลัง start_service ก็ได้)
+    pub fn register_discovery_backend(&self, backend: DynDiscoveryBackend) -> error::Result<()> {
+        Ok(self.rt.block_on(self.discovery.register_backend(backend))?)
+    }
+
+    pub fn set_activity_state(&self, state: ActivityState) {
+        self.discovery.set_activity_state(state);
+    }
+
+    /// ประกาศตัวทันทีและ Ping Peer ทุกตัวบน LAN (ปุ่ม Refresh ของ UI) ไม่ได้ Start Service อยู่ = ไม่ทำอะไร
+    pub fn refresh_discovery(&self) {
+        let discovery = self.discovery.clone();
+        self.rt.spawn(async move { discovery.refresh().await });
+    }
+
+    /// Busy / ReceivingDisabled = ประกาศใน mDNS และปฏิเสธไฟล์ที่เข้ามาทันทีด้วยเหตุผลของสถานะนั้น
+    pub fn set_availability(&self, availability: Availability) {
+        self.receive_options.availability.set(availability);
+        let (discovery, incoming_limit) = (self.discovery.clone(), self.incoming_limit.clone());
+        self.rt.spawn(async move { discovery.set_availability(announced_availability(availability, &incoming_limit)).await });
+    }
+
+    /// ค่าที่ User ตั้ง (Busy อัตโนมัติตอนรับเต็มไม่นับ)
+    pub fn availability(&self) -> Availability {
+        self.receive_options.availability.get()
+    }
+
+    /// วัด RTT + ความเร็วทั้งสองทิศกับ Peer ผ่าน Control Channel (ทิศละ duration) ผลมาเป็น Event SpeedTest
+    /// วัดไม่สำเร็จ = Error ที่ task_id "speedtest:<peer_id>"
+    pub fn speed_test(&self, peer_id: &str, duration: Duration) -> error::Result<()> {
+        if duration.is_zero() || duration > control::MAX_SPEED_TEST {
+            return Err(DropTeaError::Config(format!("Speed test duration must be at most {}s", control::MAX_SPEED_TEST.as_secs())));
+        }
+        let peer = self.discovery.known_peers.get(peer_id).map(|p| p.clone())
+            .ok_or_else(|| DropTeaError::Config(format!("Unknown peer '{}'", peer_id)))?;
+        let (Some(ip), Some(ctl)) = (peer.ip, peer.control_port) else {
+            return Err(DropTeaError::Config(format!("Peer '{}' has no control channel", peer.display_name)));
+        };
+        if !peer.features.iter().any(|f| f == control::SPEED_TEST_FEATURE) {
+            return Err(DropTeaError::Config(format!("Peer '{}' does not support speed tests", peer.display_name)));
+        }
+        let addr = crate::core::utils::scoped_socket_addr(ip, ctl, peer.scope_id);
+        let (handler, peer_id) = (self.handler.clone(), peer_id.to_string());
+        self.rt.spawn(async move {
+            match control::speed_test(addr, duration).await {
+                Ok(result) => handler.on_event(TransferEvent::SpeedTest {
+                    peer: peer_id,
+                    rtt_ms: result.rtt_ms,
+                    upload_bytes_per_sec: result.upload_bytes_per_sec,
+                    download_bytes_per_sec: result.download_bytes_per_sec,
+                }),
+                Err(e) => handler.on_event(TransferEvent::Error { task_id: format!("speedtest:{}", peer_id), error: format!("{:#}", e) }),
+            }
+        });
+        Ok(())
+    }
+
+    // ส่งคำสั่ง Admin ไปยังเครื่อง Headless (ปลายทางต้องมี Fingerprint เราใน [admin])
+    pub fn send_admin_command(&self, ip: &str, port: u16, cmd: AdminCommand) -> error::Result<AdminResponse> {
+        let target_host = bracket_host(ip);
+        Ok(self.rt.block_on(admin::send_command(&*self.transport, &target_host, port, cmd))?)
+    }
+
+    // --- Shared Folders ---
+
+    // List / Browse Share ของเครื่องปลายทาง (เห็นเฉพาะ Share ที่ Fingerprint เราได้สิทธิ์)
+    pub fn send_share_command(&self, ip: &str, port: u16, cmd: ShareCommand) -> error::Result<ShareResponse> {
+        Ok(self.rt.block_on(shares::send_command(&*self.transport, &bracket_host(ip), port, cmd))?)
+    }
+
+    pub fn pull_shared_file(&self, ip: &str, port: u16, share: &str, path: &str, dest: &str) -> error::Result<u64> {
+        Ok(self.rt.block_on(shares::pull_file(&*self.transport, &bracket_host(ip), port, share, path, std::path::Path::new(dest)))?)
+    }
+
+    /// ขอให้เครื่องปลายทางส่งไฟล์จาก Share มาให้ (id จาก browse) ไฟล์มาทาง Connection เดียวกัน
+    /// ได้ Event เหมือนมีคนส่งมาแต่ไม่ถาม (Task ID ฝั่งเราเริ่มที่ STARTED) Err = ปลายทางไม่ยอมส่ง / ต่อไม่ได้
+    pub fn request_shared_file(&self, ip: &str, port: u16, id: &str) -> error::Result<()> {
+        let addr = crate::core::utils::parse_scoped_ip(ip)
+            .map(|(addr, scope_id)| crate::core::utils::scoped_socket_addr(addr, port, scope_id))
+            .ok_or_else(|| DropTeaError::Config(format!("'{}' is not an IP address", ip)))?;
+        let (stream, filename) = self.rt.block_on(shares::request_file(&*self.transport, &bracket_host(ip), port, id))?;
+        let fingerprint = security::known_fingerprint(&self.storage_path, &host_key(ip));
+        let options = ReceiveOptions { requested: Some(filename), ..self.receive_options.clone() };
+        let (h, limiter, pending) = (self.handler.clone(), self.incoming_limiter.clone(), self.pending_transfers.clone());
+        let cancel = self.service.lock().unwrap().clone();
+        let span = tracing::info_span!("receive", peer = %addr, task_id = tracing::field::Empty);
+        let trace_span = span.clone();
+        self.rt.spawn(async move {
+            if let Err(e) = handle_incoming(stream, DOWNLOAD_DIR.to_string(), EventHandlerAdapter(h), limiter, pending, options, addr, fingerprint, cancel, trace_span).await {
+                tracing::error!("Requested file failed: {}", e);
+            }
+        }.instrument(span));
+        Ok(())
+    }
+
+    /// เปิด One-time Code ให้ไฟล์นี้ (ttl = None ใช้ codes::DEFAULT_TTL) คืน Code ไปบอกผู้รับ
+    /// Event ของการส่ง / Code หมดอายุ (Error) / ถูกเดาผิด (Rejected) ใช้ Task ID "code:<code>"
+    pub fn offer_code(&self, path: &str, ttl: Option<Duration>) -> error::Result<String> {
+        if !std::path::Path::new(path).is_file() {
+            return Err(DropTeaError::Config(format!("'{}' is not a file", path)));
+        }
+        // Nameplate ที่เครื่องอื่นบน LAN ประกาศอยู่ -> ไม่ใช้ซ้ำ (ผู้รับจะไปเจอเครื่องนั้นก่อน)
+        let taken = self.discovery.known_peers.iter()
+            .flat_map(|p| p.features.iter().filter_map(|f| f.strip_prefix(codes::FEATURE_PREFIX)?.parse().ok()).collect::<Vec<u16>>())
+            .collect();
+        let ttl = ttl.unwrap_or(codes::DEFAULT_TTL);
+        let code = self.receive_options.codes.offer(path.into(), ttl, &taken)?;
+        let (offers, handler, expiring) = (self.receive_options.codes.clone(), self.handler.clone(), code.clone());
+        self.rt.spawn(async move {
+            tokio::time::sleep(ttl).await;
+            if offers.cancel(&expiring) {
+                handler.on_event(TransferEvent::Error { task_id: format!("{}{}", codes::TASK_PREFIX, expiring), error: "Code expired".to_string() });
             }
         });
+        Ok(code)
+    }
+
+    /// false = ไม่มี Code นี้ (ใช้ไปแล้ว / หมดอายุ)
+    pub fn cancel_code(&self, code: &str) -> bool {
+        self.receive_options.codes.cancel(code)
+    }
+
+    /// Link ให้ Browser ของเครื่องที่ไม่มี DropTea โหลดไฟล์นี้ได้ครั้งเดียว (ttl = None ใช้ links::DEFAULT_TTL) ต้องเปิด [links]
+    /// Event ของการโหลด / Link หมดอายุ (Error) ใช้ Task ID "link:<token>"
+    pub fn create_link(&self, path: &str, ttl: Option<Duration>) -> error::Result<String> {
+        let downloads = self.links.clone().ok_or_else(|| DropTeaError::Config("Download links are disabled (see [links])".into()))?;
+        if !std::path::Path::new(path).is_file() {
+            return Err(DropTeaError::Config(format!("'{}' is not a file", path)));
+        }
+        let ttl = ttl.unwrap_or(links::DEFAULT_TTL);
+        let token = downloads.create(path.into(), ttl);
+        let (handler, expiring) = (self.handler.clone(), token.clone());
+        let url = downloads.url(&token);
+        self.rt.spawn(async move {
+            tokio::time::sleep(ttl).await;
+            if downloads.expire(&expiring) {
+                handler.on_event(TransferEvent::Error { task_id: format!("{}{}", links::TASK_PREFIX, expiring), error: "Link expired".to_string() });
+            }
+        });
+        Ok(url)
+    }
+
+    /// ปิด Link (รับทั้ง URL เต็มหรือ Token) false = ไม่มี / โหลดไปแล้ว / หมดอายุ
+    pub fn revoke_link(&self, link: &str) -> bool {
+        self.links.as_ref().is_some_and(|links| links.revoke(link))
     }
 
-    pub fn resolve_request(&self, task_id: String, accept: bool) {
+    /// พิมพ์ Code ที่ผู้ส่งบอกมา: หาเครื่องที่ประกาศ Nameplate นี้บน LAN ก่อน ไม่เจอค่อยถาม Rendezvous
+    /// ไฟล์มาเป็น Event ปกติโดยไม่ถาม (เหมือน request_shared_file) Err = Code ผิด / หาผู้ส่งไม่เจอ
+    pub fn receive_code(&self, code: &str) -> error::Result<()> {
+        let plate = codes::nameplate(code).ok_or_else(|| DropTeaError::Config(format!("'{}' is not a transfer code", code)))?;
+        let feature = format!("{}{}", codes::FEATURE_PREFIX, plate);
+        let (stream, filename, addr, fingerprint) = self.rt.block_on(async {
+            let deadline = tokio::time::Instant::now() + CODE_LAN_WAIT;
+            let lan = loop {
+                let found = self.discovery.known_peers.iter().find(|p| p.features.contains(&feature)).and_then(|p| Some((p.host()?, p.port)));
+                if found.is_some() || tokio::time::Instant::now() >= deadline { break found; }
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            };
+            let (host, port) = match lan {
+                Some((host, port)) => (host, port),
+                None if self.rendezvous.is_some() => (format!("{}{}", codes::RENDEZVOUS_PREFIX, plate), 0),
+                None => return Err(anyhow::Error::from(DropTeaError::Config(format!("No sender with code {} nearby", plate)))),
+            };
+            let (stream, filename) = codes::claim(&*self.transport, &bracket_host(&host), port, code).await?;
+            let addr = crate::core::utils::parse_scoped_ip(&host)
+                .map(|(ip, scope_id)| crate::core::utils::scoped_socket_addr(ip, port, scope_id))
+                .unwrap_or_else(|| std::net::SocketAddr::from((std::net::Ipv4Addr::UNSPECIFIED, port)));
+            Ok((stream, filename, addr, security::known_fingerprint(&self.storage_path, &host_key(&host))))
+        })?;
+        let options = ReceiveOptions { requested: Some(filename), ..self.receive_options.clone() };
+        let (h, limiter, pending) = (self.handler.clone(), self.incoming_limiter.clone(), self.pending_transfers.clone());
+        let cancel = self.service.lock().unwrap().clone();
+        let span = tracing::info_span!("receive", peer = %addr, task_id = tracing::field::Empty);
+        let trace_span = span.clone();
+        self.rt.spawn(async move {
+            if let Err(e) = handle_incoming(stream, DOWNLOAD_DIR.to_string(), EventHandlerAdapter(h), limiter, pending, options, addr, fingerprint, cancel, trace_span).await {
+                tracing::error!("Code transfer failed: {}", e);
+            }
+        }.instrument(span));
+        Ok(())
+    }
+
+    /// เปิดโฟลเดอร์เป็น Share ตอนรัน (ไม่เขียนลง config.toml) Peer เห็นเมื่อได้ grant_share แล้ว
+    pub fn publish_share(&self, name: &str, path: &str) -> error::Result<()> {
+        self.receive_options.shares.publish(SharedFolder { name: name.to_string(), path: path.into() })
+    }
+
+    /// false = ไม่มี Share ชื่อนี้ (สิทธิ์ที่ grant ไว้ยังอยู่)
+    pub fn unpublish_share(&self, name: &str) -> bool {
+        self.receive_options.shares.unpublish(name)
+    }
+
+    pub fn published_shares(&self) -> Vec<SharedFolder> {
+        self.receive_options.shares.published()
+    }
+
+    pub fn grant_share(&self, share: &str, fingerprint: &str) {
+        security::grant_share(DOWNLOAD_DIR, share.to_string(), fingerprint.to_lowercase());
+    }
+
+    pub fn revoke_share(&self, share: &str, fingerprint: &str) {
+        security::revoke_share(DOWNLOAD_DIR, share, &fingerprint.to_lowercase());
+    }
+
+    pub fn share_grants(&self, share: &str) -> Vec<String> {
+        security::share_grants(DOWNLOAD_DIR, share)
+    }
+
+    // --- Whitelist / known_hosts (Guest Mode ไม่มีของตัวเอง และไม่ให้เห็นของเจ้าของเครื่อง) ---
+
+    /// ชื่อผู้ส่งที่รับไฟล์ได้โดยไม่ถาม
+    pub fn trusted_senders(&self) -> Vec<String> {
+        if self.guest { return Vec::new(); }
+        security::trusted_senders(DOWNLOAD_DIR)
+    }
+
+    pub fn add_trusted(&self, sender_name: &str) -> error::Result<()> {
+        if self.guest {
+            return Err(DropTeaError::Config("guest mode does not keep a whitelist".into()));
+        }
+        if sender_name.trim().is_empty() {
+            return Err(DropTeaError::Config("sender name is empty".into()));
+        }
+        security::add_trust(DOWNLOAD_DIR, sender_name.to_string());
+        Ok(())
+    }
+
+    pub fn remove_trusted(&self, sender_name: &str) -> bool {
+        !self.guest && security::remove_trust(DOWNLOAD_DIR, sender_name)
+    }
+
+    /// Host (IP / Peer ID) -> Cert Fingerprint ที่จำไว้ตอนเชื่อมต่อครั้งแรก
+    pub fn known_hosts(&self) -> HashMap<String, String> {
+        if self.guest { return HashMap::new(); }
+        security::known_hosts(&self.storage_path)
+    }
+
+    /// ลืม Fingerprint ของ Host (เช่น Peer ลงแอปใหม่) ครั้งหน้าถามแบบ Peer ใหม่
+    pub fn forget_host(&self, host: &str) -> bool {
+        !self.guest && security::forget_host(&self.storage_path, &host_key(host))
+    }
+
+    /// ข้อมูลที่ส่งให้ Peer ตอน BLE Handshake (Address เดียวกับที่ใส่ใน BLE Advertisement)
+    pub fn handshake_info(&self) -> ConnectionInfo {
+        let endpoint = self.discovery.local_node().as_ref().and_then(BleBackend::advertised_endpoint);
+        ConnectionInfo {
+            name: self.node_name.clone(),
+            ip: endpoint.map(|(ip, _)| ip.to_string()),
+            port: endpoint.map_or(0, |(_, port)| port),
+            ssid: None,
+            fingerprint: self.local_fingerprint.clone(),
+        }
+    }
+
+    // --- Tuning ("Optimize for this machine") ---
+
+    /// วัดเครื่องนี้แล้วคืนค่าแนะนำ (ยังไม่ Apply ให้ Frontend แสดงก่อน)
+    pub fn benchmark(&self) -> error::Result<TuningPreset> {
+        Ok(self.rt.block_on(tuning::benchmark(std::path::Path::new(&self.receive_options.download_dir.get())))?)
+    }
+
+    /// ส่งข้อมูลสังเคราะห์ size Byte หาตัวเองผ่าน Pipeline จริง ทุก Compression (transport = None -> ทุก Transport ที่วัดได้)
+    /// ไม่ใช้ Port / Identity ของ Engine นี้ (ดู loopback_bench.rs)
+    pub fn benchmark_loopback(&self, size: u64, transport: Option<TransportMode>) -> error::Result<Vec<TransferStats>> {
+        #[cfg(feature = "webrtc")]
+        if transport == Some(TransportMode::WebRtc) {
+            return Err(DropTeaError::Config("WebRTC cannot be benchmarked on loopback".into()));
+        }
+        if size == 0 {
+            return Err(DropTeaError::Config("Benchmark size must be greater than zero".into()));
+        }
+        Ok(self.rt.block_on(loopback_bench::benchmark_loopback(size, transport, &self.storage_path))?)
+    }
+
+    pub fn apply_tuning(&self, preset: &TuningPreset) {
+        let msg = Self::apply_preset(&self.incoming_limit, &self.tunables, preset);
+        self.handler.on_event(TransferEvent::Log { level: "INFO".into(), msg });
+    }
+
+    fn apply_preset(incoming_limit: &Limit, tunables: &Tunables, preset: &TuningPreset) -> String {
+        let max_incoming = incoming_limit.resize(preset.max_incoming.max(1));
+        tunables.apply(preset);
+        tracing::info!("⚙️ Tuning applied: {:?}", preset);
+        format!("Tuning applied: incoming={} buffer={}KB compression={}", max_incoming, tunables.io_buffer_size() / 1024, preset.compression)
+    }
+
+    // --- WebRTC Signaling (Copy/Paste ระหว่างสองเครื่อง) ---
+
+    #[cfg(feature = "webrtc")]
+    fn webrtc_transport(&self) -> error::Result<&Arc<WebRtcTransport>> {
+        self.webrtc.as_ref().ok_or_else(|| DropTeaError::Config("Engine is not running in WebRTC mode".into()))
+    }
+
+    /// คืน (session_id, offer): ส่ง offer ให้อีกฝั่ง แล้วใช้ session_id แทน IP ตอน send_file
+    #[cfg(feature = "webrtc")]
+    pub fn webrtc_create_offer(&self) -> error::Result<(String, String)> {
+        let transport = self.webrtc_transport()?;
+        Ok(self.rt.block_on(transport.create_offer())?)
+    }
+
+    #[cfg(feature = "webrtc")]
+    pub fn webrtc_accept_offer(&self, offer: &str) -> error::Result<String> {
+        let transport = self.webrtc_transport()?;
+        Ok(self.rt.block_on(transport.accept_offer(offer))?)
+    }
+
+    #[cfg(feature = "webrtc")]
+    pub fn webrtc_complete_offer(&self, session_id: &str, answer: &str) -> error::Result<()> {
+        let transport = self.webrtc_transport()?;
+        Ok(self.rt.block_on(transport.complete_offer(session_id, answer))?)
+    }
+
+    /// รวม Log/Event/Config/Peer เป็น Zip เดียวสำหรับแนบ Bug Report
+    pub fn export_diagnostics(&self, path: &str, log_dirs: &[std::path::PathBuf]) -> error::Result<()> {
+        let peers: Vec<serde_json::Value> = self.discovery.known_peers.iter().map(|p| serde_json::json!({
+            "id": p.id,
+            "name": p.display_name,
+            "ip": p.host(),
+            "port": p.port,
+            "transport": p.transport.to_string(),
+            "last_seen_secs_ago": p.last_seen.elapsed().as_secs(),
+            "missed_pings": p.missed_pings,
+            "compression": p.compression.as_ref().map(|c| c.iter().map(|a| a.as_str()).collect::<Vec<_>>()),
+            "features": p.features,
+            "external_addr": p.external_addr,
+            "guest": p.guest,
+            "profile": p.profile,
+        })).collect();
+        Ok(diagnostics::export(path, DiagnosticsInput {
+            config: &diagnostics::redacted_config(&self.reloader.current()),
+            peers: serde_json::Value::Array(peers),
+            recorder: &self.recorder,
+            save_path: DOWNLOAD_DIR,
+            log_dirs,
+        })?)
+    }
+
+    /// false = ไม่มีคำขอนี้ค้างอยู่ (ตัดสินไปแล้ว / หมดเวลา)
+    pub fn resolve_request(&self, task_id: String, accept: bool) -> bool {
         if let Ok(mut map) = self.pending_transfers.lock() {
             if let Some(tx) = map.remove(&task_id) {
                 let resp = if accept { crate::core::notification::UserResponse::Accept } else { crate::core::notification::UserResponse::Decline };
                 let _ = tx.send(resp);
+                return true;
             }
         }
+        false
+    }
+}
+// Core ถูกแทน/Free -> Task ทุกตัวที่ยังวิ่งอยู่หยุดตาม
+impl Drop for DropTeaCore {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+use std::io::ErrorKind;
+use crate::core::cancel::REJECT_CANCELLED;
+
+// ==========================================
+// Error ของ Public API (DropTeaCore / Transport / utils)
+// ภายในยังใช้ anyhow ได้ตามเดิม แปลงเป็น DropTeaError ตอนออกจาก API (From<anyhow::Error> จัดหมวดจาก Error Chain)
+// ต้องการหมวดที่แน่นอน -> bail!(DropTeaError::Rejected(..)) แล้ว Chain จะพกหมวดนั้นออกมาเอง
+// Binding ใช้ kind() / code() แทนการเทียบข้อความ
+// ==========================================
+
+#[derive(Debug, Clone, PartialEq)]
+// UniFFI: ชื่อหมวด + ข้อความ (Display) ไม่ส่ง Field แยก
+#[cfg_attr(feature = "mobile", derive(uniffi::Error), uniffi(flat_error))]
+pub enum DropTeaError {
+    // Connect ไม่ได้ / Connection หลุด / Peer ไม่ตอบ
+    Network(String),
+    // Handshake หรือ Certificate ไม่ผ่าน
+    Tls(String),
+    Timeout(String),
+    // ปลายทางปฏิเสธ (ผู้ใช้ / ACL / Webhook)
+    Rejected(String),
+    // อ่าน/เขียนไฟล์ไม่ได้ พื้นที่เต็ม ไม่มีสิทธิ์
+    Storage(String),
+    // ข้อมูลจาก Peer ผิดรูปแบบ / Version คุยกันไม่ได้
+    Protocol(String),
+    Cancelled,
+    // Config หรือ Argument ไม่ถูกต้อง / ใช้ฟีเจอร์ที่โหมดนี้ไม่มี
+    Config(String),
+    Internal(String),
+}
+
+pub type Result<T> = std::result::Result<T, DropTeaError>;
+
+impl DropTeaError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Network(_) => "network",
+            Self::Tls(_) => "tls",
+            Self::Timeout(_) => "timeout",
+            Self::Rejected(_) => "rejected",
+            Self::Storage(_) => "storage",
+            Self::Protocol(_) => "protocol",
+            Self::Cancelled => "cancelled",
+            Self::Config(_) => "config",
+            Self::Internal(_) => "internal",
+        }
+    }
+
+    /// รหัสสำหรับ FFI (ฟังก์ชัน C คืนค่าลบของรหัสนี้ ดู droptea_api.h)
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::Network(_) => 1,
+            Self::Tls(_) => 2,
+            Self::Timeout(_) => 3,
+            Self::Rejected(_) => 4,
+            Self::Storage(_) => 5,
+            Self::Protocol(_) => 6,
+            Self::Cancelled => 7,
+            Self::Config(_) => 8,
+            Self::Internal(_) => 9,
+        }
+    }
+
+    /// ลองใหม่แล้วอาจผ่าน (Connection สะดุด / Peer ตอบช้า)
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Network(_) | Self::Timeout(_))
+    }
+
+    fn from_io(e: &std::io::Error, msg: String) -> Self {
+        match e.kind() {
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => Self::Timeout(msg),
+            ErrorKind::NotFound | ErrorKind::PermissionDenied | ErrorKind::AlreadyExists
+                | ErrorKind::StorageFull | ErrorKind::ReadOnlyFilesystem | ErrorKind::IsADirectory
+                | ErrorKind::NotADirectory | ErrorKind::DirectoryNotEmpty | ErrorKind::FileTooLarge => Self::Storage(msg),
+            ErrorKind::InvalidData => Self::Protocol(msg),
+            ErrorKind::InvalidInput => Self::Config(msg),
+            _ => Self::Network(msg),
+        }
+    }
+}
+
+impl fmt::Display for DropTeaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cancelled => f.write_str(REJECT_CANCELLED),
+            Self::Network(m) | Self::Tls(m) | Self::Timeout(m) | Self::Rejected(m)
+                | Self::Storage(m) | Self::Protocol(m) | Self::Config(m) | Self::Internal(m) => f.write_str(m),
+        }
+    }
+}
+
+impl std::error::Error for DropTeaError {}
+
+impl From<std::io::Error> for DropTeaError {
+    fn from(e: std::io::Error) -> Self {
+        Self::from_io(&e, e.to_string())
+    }
+}
+
+impl From<quinn::ConnectionError> for DropTeaError {
+    fn from(e: quinn::ConnectionError) -> Self { Self::Network(e.to_string()) }
+}
+
+#[cfg(feature = "webrtc")]
+impl From<webrtc::Error> for DropTeaError {
+    fn from(e: webrtc::Error) -> Self { Self::Network(e.to_string()) }
+}
+
+impl From<std::net::AddrParseError> for DropTeaError {
+    fn from(e: std::net::AddrParseError) -> Self { Self::Config(e.to_string()) }
+}
+
+impl From<rustls::client::InvalidDnsNameError> for DropTeaError {
+    fn from(e: rustls::client::InvalidDnsNameError) -> Self { Self::Config(e.to_string()) }
+}
+
+impl From<zip::result::ZipError> for DropTeaError {
+    fn from(e: zip::result::ZipError) -> Self { Self::Storage(e.to_string()) }
+}
+
+impl From<anyhow::Error> for DropTeaError {
+    fn from(e: anyhow::Error) -> Self {
+        // ข้อความเต็มทั้ง Chain (context: cause) เหมือนที่เคยส่งให้ Binding
+        let msg = format!("{:#}", e);
+        if e.to_string() == REJECT_CANCELLED { return Self::Cancelled; }
+        for cause in e.chain() {
+            if let Some(err) = cause.downcast_ref::<DropTeaError>() { return err.clone(); }
+            if cause.is::<rustls::Error>() { return Self::Tls(msg); }
+            if cause.is::<tokio::time::error::Elapsed>() { return Self::Timeout(msg); }
+            if cause.is::<quinn::ConnectionError>() || cause.is::<quinn::ConnectError>() { return Self::Network(msg); }
+            if cause.is::<serde_json::Error>() { return Self::Protocol(msg); }
+            if cause.is::<zip::result::ZipError>() { return Self::Storage(msg); }
+            if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+                // TLS Handshake ที่ล้มใน tokio-rustls มาเป็น io::Error ที่ห่อ rustls::Error ไว้
+                if io.get_ref().is_some_and(|inner| inner.is::<rustls::Error>()) { return Self::Tls(msg); }
+                return Self::from_io(io, msg);
+            }
+        }
+        Self::Internal(msg)
+    }
+}
@@ -1,4 +1,10 @@
+use std::sync::Arc;
 use serde::{Serialize, Deserialize};
+use tokio::sync::broadcast;
+use crate::core::discovery::PeerProfile;
+use crate::core::stats::TransferStats;
+use crate::core::thumbnail::Thumbnail;
+use crate::core::transfer::Metadata;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TransferEvent {
@@ -6,25 +12,138 @@ pub enum TransferEvent {
     ServerStarted { port: u16 },
     Error { task_id: String, error: String },
     
-    Incoming { task_id: String, filename: String },
+    // thumbnail = ภาพตัวอย่างที่ผ่านการตรวจแล้ว (เฉพาะรูป/วิดีโอที่ผู้ส่งแนบมา)
+    // filename = "[[REQUEST]]|ชื่อไฟล์|ขนาด|ผู้ส่ง|เครื่อง[|session][|verdict]" ของ UI เดิม, request = ค่าเดียวกันแยก Field (ชื่อไฟล์มี '|' ได้)
+    Incoming {
+        task_id: String,
+        filename: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        thumbnail: Option<Thumbnail>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        request: Option<IncomingRequest>,
+    },
     Started { task_id: String, msg: String },
-    Progress { task_id: String, current: u64, total: u64 },
-    Completed { task_id: String, info: String },
+    // bytes_per_sec / avg_bytes_per_sec = 0 และ eta_secs = None เมื่อช่องทางนั้นไม่ได้วัด (เช่น BLE)
+    Progress { task_id: String, current: u64, total: u64, bytes_per_sec: u64, avg_bytes_per_sec: u64, eta_secs: Option<u64> },
+    // stats = None เมื่อช่องทางนั้นไม่ได้วัด (เช่น BLE) / metadata = ที่ผู้ส่งแนบมา (ว่าง = ไม่มี)
+    Completed {
+        task_id: String,
+        info: String,
+        stats: Option<TransferStats>,
+        #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+        metadata: Metadata,
+    },
     Rejected { task_id: String, reason: String },
+    // นาฬิกาผู้ส่งเร็ว (+) / ช้า (-) กว่าเครื่องเราเกิน CLOCK_SKEW_WARN_MS
+    ClockSkew { task_id: String, peer: String, skew_ms: i64 },
+    // Connect ไม่ติด กำลังรอ delay_ms ก่อนเริ่มครั้งที่ attempt
+    Retrying { task_id: String, attempt: u32, delay_ms: u64, error: String },
+    // ไม่มี Byte ขยับมา stalled_ms แล้ว (ยังไม่ตัด) ถ้าขยับต่อได้ก็ได้ Progress ตามปกติ
+    Stalled { task_id: String, stalled_ms: u64 },
+    // ข้ามไฟล์เพราะผู้รับมีอยู่แล้ว (existing = Path ที่ตรง มีเฉพาะฝั่งรับ) จบ Task แทน Completed / Rejected
+    Duplicate { task_id: String, filename: String, existing: Option<String> },
+    // Post-receive Hook ล้ม / Timeout (มาหลัง Completed ของ Task เดียวกัน ไฟล์ยังอยู่ที่ path)
+    HookFailed { task_id: String, path: String, error: String },
+    // ภาพรวมของชุดไฟล์ (โฟลเดอร์ / send_batch) แยกจาก Progress รายไฟล์
+    BatchProgress { batch_id: String, files_done: u64, files_total: u64, bytes_done: u64, bytes_total: u64, current: String },
 
     DiscoveryStarted,
     // 🔥 Updated Event
+    // nickname / notes = ที่ User ตั้งไว้ใน Peer Registry (name ยังเป็นชื่อที่ Peer ประกาศ)
     PeerFound { 
         id: String, 
         name: String, 
         ip: String, 
         port: u16, 
         ssid: Option<String>, 
-        transport: String 
+        transport: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        nickname: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        notes: Option<String>,
+        // OS / ชนิดเครื่อง / Protocol / Transport ที่ Peer ประกาศใน mDNS (ว่าง = Peer รุ่นเก่า / BLE)
+        #[serde(default, skip_serializing_if = "PeerProfile::is_empty")]
+        profile: PeerProfile,
+        // Compression ที่ Peer รองรับ (ว่าง = ไม่ได้ประกาศ)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        compression: Vec<String>,
     },
     PeerLost { id: String },
+    // mDNS Resolve ใหม่ได้ IP ไม่ตรงของเดิม (เช่น DHCP Lease เปลี่ยน)
+    PeerUpdated { id: String, old_ip: String, ip: String, port: u16 },
+    // Address ของเครื่องเราเปลี่ยน (สลับ Network) ประกาศ mDNS ใหม่และ Ping Peer ซ้ำแล้ว
+    NetworkChanged { addrs: Vec<String> },
+    // config.toml ถูกแก้แล้ว Apply โดยไม่ Restart (changed = ชื่อค่าที่มีผลแล้ว)
+    ConfigReloaded { changed: Vec<String> },
+    // ผล speed_test กับ Peer (upload = เราส่งไป, download = Peer ส่งมา) ผ่าน Control Channel (TCP ไม่มี TLS)
+    SpeedTest { peer: String, rtt_ms: f64, upload_bytes_per_sec: u64, download_bytes_per_sec: u64 },
+    // Peer List (share ว่าง) / Browse Shared Folder ของเรา (peer = Fingerprint, ไม่มี = IP) allowed = false ถูกปฏิเสธ
+    ShareBrowsed { peer: String, share: String, path: String, allowed: bool },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IncomingRequest {
+    pub filename: String,
+    pub size: u64,
+    pub sender: String,
+    pub device: String,
+    // ข้อเสนอเป็นส่วนหนึ่งของ Session (หลายไฟล์กดรับครั้งเดียว)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    // ผล Reputation: known / unknown / malicious
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verdict: Option<String>,
+    // key-value ที่ผู้ส่งแนบมา (SendOptions::metadata)
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
 }
 
 pub trait TransferEventHandler: Send + Sync {
     fn on_event(&self, event: TransferEvent);
-}
\ No newline at end of file
+}
+
+// Subscriber ที่ตามไม่ทันเกินนี้ recv() ได้ Lagged(n) แล้วข้ามไปตัวที่ยังอยู่ในคิว
+pub const EVENT_STREAM_CAPACITY: usize = 1024;
+
+/// Event ทุกตัวของ Engine (รวมของ Handler ที่ส่งมากับ send_file / send_batch) สำหรับ Rust ที่ใช้ async แทน Trait Object
+/// `while let Ok(evt) = rx.recv().await { ... }` จบเมื่อ Engine ถูกทิ้ง
+pub struct EventStream {
+    tx: broadcast::Sender<TransferEvent>,
+}
+
+impl EventStream {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self { tx: broadcast::channel(EVENT_STREAM_CAPACITY).0 })
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TransferEvent> {
+        self.tx.subscribe()
+    }
+
+    // ไม่มีคนฟัง -> ไม่ต้อง Clone
+    fn publish(&self, event: &TransferEvent) {
+        if self.tx.receiver_count() > 0 {
+            let _ = self.tx.send(event.clone());
+        }
+    }
+}
+
+/// กระจาย Event เข้า EventStream ก่อนส่งต่อให้ Handler เดิม
+pub struct StreamingHandler {
+    pub inner: Box<dyn TransferEventHandler>,
+    pub stream: Arc<EventStream>,
+}
+
+impl TransferEventHandler for StreamingHandler {
+    fn on_event(&self, event: TransferEvent) {
+        self.stream.publish(&event);
+        self.inner.on_event(event);
+    }
+}
+
+/// Handler ว่าง ใช้คู่กับ DropTeaCore::subscribe เมื่อไม่ต้องการ Callback
+pub struct NoopHandler;
+
+impl TransferEventHandler for NoopHandler {
+    fn on_event(&self, _: TransferEvent) {}
+}
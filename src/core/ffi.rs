@@ -1,76 +1,520 @@
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int, c_void};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex as StdMutex, RwLock};
 use tokio::runtime::Runtime;
 
-use crate::core::engine::{DropTeaCore, DropTeaConfig, TransportMode};
+use crate::core::builder::DropTeaBuilder;
+use crate::core::config::AppConfig;
+use crate::core::engine::{DropTeaCore, SendOptions, TransportMode};
+use crate::core::discovery::ActivityState;
 use crate::core::events::{TransferEvent, TransferEventHandler};
+use crate::core::error::{DropTeaError, Result};
+use crate::core::trace::{self, TraceRecord, TraceSink};
+use crate::core::transfer::Metadata;
+use crate::core::utils;
 
-type CppCallback = extern "C" fn(c_int, *const c_char, *const c_char, *const c_char, u64, u64);
+// user_data ที่ Host ให้ไว้ตอน droptea_init ถูกส่งกลับเป็น Argument สุดท้ายทุกครั้ง
+type CppCallback = extern "C" fn(c_int, *const c_char, *const c_char, *const c_char, u64, u64, *mut c_void);
+// Signature เดิม (ไม่มี user_data) ของ droptea_init_legacy
+type LegacyCppCallback = extern "C" fn(c_int, *const c_char, *const c_char, *const c_char, u64, u64);
+type TraceCallback = extern "C" fn(*const c_char);
 
 pub struct DropTeaContext {
     core: RwLock<Arc<DropTeaCore>>,
     _rt: Arc<Runtime>, 
+    // Event ของ droptea_send_file ออกทาง Callback เดียวกับตอน Init
+    callback: HostCallback,
+    // ข้อความของ Error ล่าสุดของ Context นี้ (droptea_last_error_message)
+    last_error: StdMutex<Option<String>>,
+    // Some = สร้างจาก config.toml: Port / dev_mode ในไฟล์ชนะ Argument ของ droptea_start_service
+    configured_port: Option<u16>,
 }
 
-struct CppEventHandlerAdapter { callback: CppCallback }
+/// รหัสที่ทุกฟังก์ชันของ C ABI คืน (ตรงกับ DropTeaErrorCode ใน droptea_api.h ห้ามเปลี่ยนค่าเดิม เพิ่มต่อท้ายได้อย่างเดียว)
+/// 1..=9 = ค่าเดียวกับ DropTeaError::code()
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropTeaErrorCode {
+    Ok = 0,
+    Network = 1,
+    Tls = 2,
+    Timeout = 3,
+    Rejected = 4,
+    Storage = 5,
+    Protocol = 6,
+    Cancelled = 7,
+    Config = 8,
+    Internal = 9,
+    // Pointer เป็น null / ข้อความว่าง
+    InvalidArgument = 10,
+    // เปิด Port ไม่ได้ตอน droptea_init (Port ถูกใช้อยู่ / ไม่มีสิทธิ์)
+    Bind = 11,
+    // ไม่มี Transfer ตาม task_id (จบไปแล้ว)
+    NotFound = 12,
+}
+
+impl From<&DropTeaError> for DropTeaErrorCode {
+    fn from(e: &DropTeaError) -> Self {
+        match e {
+            DropTeaError::Network(_) => Self::Network,
+            DropTeaError::Tls(_) => Self::Tls,
+            DropTeaError::Timeout(_) => Self::Timeout,
+            DropTeaError::Rejected(_) => Self::Rejected,
+            DropTeaError::Storage(_) => Self::Storage,
+            DropTeaError::Protocol(_) => Self::Protocol,
+            DropTeaError::Cancelled => Self::Cancelled,
+            DropTeaError::Config(_) => Self::Config,
+            DropTeaError::Internal(_) => Self::Internal,
+        }
+    }
+}
+
+// Error ที่ไม่มี Context ให้เก็บ (droptea_init ล้ม / ctx เป็น null / Trace Callback)
+static GLOBAL_LAST_ERROR: StdMutex<Option<String>> = StdMutex::new(None);
+
+impl DropTeaContext {
+    fn fail(&self, code: DropTeaErrorCode, msg: impl Into<String>) -> DropTeaErrorCode {
+        *self.last_error.lock().unwrap() = Some(msg.into());
+        code
+    }
+
+    fn check<T>(&self, result: Result<T>) -> DropTeaErrorCode {
+        match result {
+            Ok(_) => DropTeaErrorCode::Ok,
+            Err(e) => self.fail((&e).into(), e.to_string()),
+        }
+    }
+}
+
+fn fail_global(code: DropTeaErrorCode, msg: impl Into<String>) -> DropTeaErrorCode {
+    *GLOBAL_LAST_ERROR.lock().unwrap() = Some(msg.into());
+    code
+}
+
+unsafe fn context<'a>(ctx_ptr: *mut c_void) -> std::result::Result<&'a DropTeaContext, DropTeaErrorCode> {
+    match (ctx_ptr as *const DropTeaContext).as_ref() {
+        Some(context) => Ok(context),
+        None => Err(fail_global(DropTeaErrorCode::InvalidArgument, "context is null")),
+    }
+}
+
+// String ที่ต้องมี (null / ว่าง = InvalidArgument)
+fn required(context: &DropTeaContext, value: Option<String>, what: &str) -> std::result::Result<String, DropTeaErrorCode> {
+    value.filter(|v| !v.is_empty()).ok_or_else(|| context.fail(DropTeaErrorCode::InvalidArgument, format!("{} is missing", what)))
+}
+
+#[derive(Clone, Copy)]
+enum HostCallback {
+    Legacy(LegacyCppCallback),
+    WithData(CppCallback, UserData),
+}
+
+// Pointer ของ Host ไม่ถูกแตะฝั่ง Rust แค่ส่งคืน (Host รับประกันเองว่าใช้ข้าม Thread ได้)
+#[derive(Clone, Copy)]
+struct UserData(*mut c_void);
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+impl HostCallback {
+    fn call(&self, kind: c_int, id: &CStr, d1: &CStr, d2: &CStr, v1: u64, v2: u64) {
+        match *self {
+            HostCallback::Legacy(cb) => cb(kind, id.as_ptr(), d1.as_ptr(), d2.as_ptr(), v1, v2),
+            HostCallback::WithData(cb, user_data) => cb(kind, id.as_ptr(), d1.as_ptr(), d2.as_ptr(), v1, v2, user_data.0),
+        }
+    }
+}
+
+// รหัส Event ตัวแรกของ Callback (ตรงกับ DROPTEA_EVT_* ใน droptea_api.h)
+const EVT_LOG: c_int = 0;
+const EVT_PEER_FOUND: c_int = 1;
+const EVT_PEER_LOST: c_int = 2;
+const EVT_PROGRESS: c_int = 3;
+const EVT_COMPLETED: c_int = 4;
+const EVT_ERROR: c_int = 5;
+const EVT_INCOMING: c_int = 6;
+const EVT_REJECTED: c_int = 7;
+const EVT_STARTED: c_int = 8;
+const EVT_RETRYING: c_int = 9;
+const EVT_SERVER_STARTED: c_int = 10;
+const EVT_STALLED: c_int = 11;
+const EVT_BATCH_PROGRESS: c_int = 12;
+const EVT_PEER_UPDATED: c_int = 13;
+const EVT_DUPLICATE: c_int = 14;
+const EVT_HOOK_FAILED: c_int = 15;
+const EVT_SHARE_BROWSED: c_int = 16;
+
+// data2 ของ INCOMING / COMPLETED: JSON object ของ Metadata ("" = ไม่มี)
+fn metadata_json(metadata: &Metadata) -> String {
+    match metadata.is_empty() {
+        true => String::new(),
+        false => serde_json::to_string(metadata).unwrap_or_default(),
+    }
+}
+
+struct CppEventHandlerAdapter { callback: HostCallback }
 impl TransferEventHandler for CppEventHandlerAdapter {
     fn on_event(&self, event: TransferEvent) {
-        let to_c = |s: &str| CString::new(s).unwrap_or_default();
-        let empty = CString::new("").unwrap();
-        match event {
-            TransferEvent::Log { msg, .. } => (self.callback)(0, empty.as_ptr(), to_c(&msg).as_ptr(), empty.as_ptr(), 0, 0),
-            // ... (Mapping event อื่นๆ ถ้ามี) ...
-            _ => {}
-        }
+        // (type, task_id, data1, data2, val1, val2) Event ที่ไม่มีใน Map ถูกข้าม
+        let (kind, id, d1, d2, v1, v2) = match event {
+            TransferEvent::Log { level, msg } => (EVT_LOG, String::new(), msg, level, 0, 0),
+            TransferEvent::PeerFound { id, name, ip, port, transport, nickname, .. } => (EVT_PEER_FOUND, id, nickname.unwrap_or(name), format!("{}|{}", ip, transport), port as u64, 0),
+            TransferEvent::PeerLost { id } => (EVT_PEER_LOST, id, String::new(), String::new(), 0, 0),
+            TransferEvent::PeerUpdated { id, old_ip, ip, port } => (EVT_PEER_UPDATED, id, ip, old_ip, port as u64, 0),
+            TransferEvent::Progress { task_id, current, total, .. } => (EVT_PROGRESS, task_id, String::new(), String::new(), current, total),
+            TransferEvent::Completed { task_id, info, metadata, .. } => (EVT_COMPLETED, task_id, info, metadata_json(&metadata), 0, 0),
+            TransferEvent::Error { task_id, error } => (EVT_ERROR, task_id, error, String::new(), 0, 0),
+            TransferEvent::Incoming { task_id, filename, request, .. } => (EVT_INCOMING, task_id, filename, request.map(|r| metadata_json(&r.metadata)).unwrap_or_default(), 0, 0),
+            TransferEvent::Rejected { task_id, reason } => (EVT_REJECTED, task_id, reason, String::new(), 0, 0),
+            TransferEvent::Started { task_id, msg } => (EVT_STARTED, task_id, msg, String::new(), 0, 0),
+            TransferEvent::Retrying { task_id, attempt, delay_ms, error } => (EVT_RETRYING, task_id, error, String::new(), attempt as u64, delay_ms),
+            TransferEvent::ServerStarted { port } => (EVT_SERVER_STARTED, String::new(), port.to_string(), String::new(), port as u64, 0),
+            TransferEvent::Stalled { task_id, stalled_ms } => (EVT_STALLED, task_id, String::new(), String::new(), stalled_ms, 0),
+            TransferEvent::Duplicate { task_id, filename, existing } => (EVT_DUPLICATE, task_id, filename, existing.unwrap_or_default(), 0, 0),
+            TransferEvent::HookFailed { task_id, path, error } => (EVT_HOOK_FAILED, task_id, path, error, 0, 0),
+            TransferEvent::ShareBrowsed { peer, share, path, allowed } => (EVT_SHARE_BROWSED, peer, share, path, allowed as u64, 0),
+            TransferEvent::BatchProgress { batch_id, files_done, files_total, bytes_done, bytes_total, current } => (EVT_BATCH_PROGRESS, batch_id, current, format!("{}|{}", files_done, files_total), bytes_done, bytes_total),
+            _ => return,
+        };
+        let to_c = |s: &str| CString::new(s.replace('\0', "")).unwrap_or_default();
+        let (id, d1, d2) = (to_c(&id), to_c(&d1), to_c(&d2));
+        self.callback.call(kind, &id, &d1, &d2, v1, v2);
     }
 }
 
+unsafe fn opt_str(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() { return None; }
+    Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+}
+
+/// user_data ถูกส่งคืนเป็น Argument สุดท้ายของ callback ทุกครั้ง (Host ใช้แทนตัวแปร Global ได้) อาจเป็น null
+/// null = สร้างไม่ได้ ดูเหตุผลจาก droptea_last_error_message(NULL)
+///
+/// # Safety
+/// `storage_path` must be a valid NUL-terminated string; `user_data` must stay valid until `droptea_free`.
 #[no_mangle]
-pub extern "C" fn droptea_init(storage_path: *const c_char, mode: c_int, callback: CppCallback) -> *mut c_void {
-    let c_str = unsafe { CStr::from_ptr(storage_path) };
-    let path_str = c_str.to_string_lossy().into_owned();
-    let rt = Arc::new(Runtime::new().unwrap());
-    let handler = Box::new(CppEventHandlerAdapter { callback });
+pub unsafe extern "C" fn droptea_init(storage_path: *const c_char, mode: c_int, callback: CppCallback, user_data: *mut c_void) -> *mut c_void {
+    init(storage_path, mode, HostCallback::WithData(callback, UserData(user_data)))
+}
+
+/// Signature เดิมก่อนมี user_data (Host รุ่นเก่า)
+///
+/// # Safety
+/// `storage_path` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn droptea_init_legacy(storage_path: *const c_char, mode: c_int, callback: LegacyCppCallback) -> *mut c_void {
+    init(storage_path, mode, HostCallback::Legacy(callback))
+}
 
+unsafe fn init(storage_path: *const c_char, mode: c_int, callback: HostCallback) -> *mut c_void {
+    let Some(path_str) = opt_str(storage_path).filter(|p| !p.is_empty()) else {
+        fail_global(DropTeaErrorCode::InvalidArgument, "storage_path is missing");
+        return std::ptr::null_mut();
+    };
     let transport_mode = if mode == 1 { TransportMode::Quic } else { TransportMode::Tcp };
+    let builder = DropTeaCore::builder()
+        .transport(transport_mode)
+        .storage(path_str)
+        .node_name("ffi_node");
+    create(builder, callback, None)
+}
 
-    let config = DropTeaConfig {
-        mode: transport_mode,
-        port: 0,
-        storage_path: path_str,
-        node_name: "ffi_node".to_string(),
-        dev_mode: false, 
+/// สร้างจาก config.toml แบบเดียวกับ start_server ของ Python (node_name / save_path / mode / port / [peers.*] ...)
+/// config_path = NULL / ว่าง: ไม่มีไฟล์ ใช้ DROPTEA_* อย่างเดียว, มีไฟล์ = ดูไฟล์แล้ว Reload เองเมื่อถูกแก้
+/// null = สร้างไม่ได้ (DROPTEA_ERR_CONFIG = อ่านไฟล์ไม่ได้) ดูเหตุผลจาก droptea_last_error_message(NULL)
+///
+/// # Safety
+/// `config_path` must be null or a valid NUL-terminated string; `user_data` must stay valid until `droptea_free`.
+#[no_mangle]
+pub unsafe extern "C" fn droptea_init_with_config(config_path: *const c_char, callback: CppCallback, user_data: *mut c_void) -> *mut c_void {
+    let config_path = opt_str(config_path).filter(|p| !p.is_empty());
+    let app_config = match &config_path {
+        Some(path) => AppConfig::load_from_file(path),
+        None => AppConfig::from_env(),
     };
+    let engine_config = match app_config {
+        Ok(c) => c.to_engine_config(),
+        Err(e) => {
+            fail_global(DropTeaErrorCode::Config, format!("Config Load Failed: {}", e));
+            return std::ptr::null_mut();
+        }
+    };
+    let port = engine_config.port;
+    let ctx = create(DropTeaBuilder::from_config(engine_config), HostCallback::WithData(callback, UserData(user_data)), Some(port));
+    if let (Some(path), Some(context)) = (config_path, (ctx as *const DropTeaContext).as_ref()) {
+        context.core.read().unwrap().watch_config(&path);
+    }
+    ctx
+}
+
+fn create(builder: DropTeaBuilder, callback: HostCallback, configured_port: Option<u16>) -> *mut c_void {
+    let rt = match Runtime::new() {
+        Ok(rt) => Arc::new(rt),
+        Err(e) => {
+            fail_global(DropTeaErrorCode::Internal, format!("Failed to start runtime: {}", e));
+            return std::ptr::null_mut();
+        }
+    };
+    let built = builder
+        .handler(CppEventHandlerAdapter { callback })
+        .runtime(rt.clone())
+        .build();
 
-    match DropTeaCore::new_with_config(rt.clone(), config, handler) {
+    match built {
         Ok(core) => {
             let context = Box::new(DropTeaContext {
                 core: RwLock::new(Arc::new(core)),
                 _rt: rt,
+                callback,
+                last_error: StdMutex::new(None),
+                configured_port,
             });
             Box::into_raw(context) as *mut c_void
         }
-        Err(_) => std::ptr::null_mut(),
+        Err(e) => {
+            // ตอน Init ยังไม่ได้ Connect ไปไหน -> Network Error มาจากการเปิด Listener เท่านั้น
+            let code = match &e {
+                DropTeaError::Network(_) => DropTeaErrorCode::Bind,
+                e => e.into(),
+            };
+            fail_global(code, e.to_string());
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// ข้อความของ Error ล่าสุดของ ctx (ctx = NULL: ของ droptea_init / ctx ที่เป็น null / Trace Callback)
+/// ผู้เรียกต้องคืนด้วย droptea_free_string, null = ยังไม่มี Error
+///
+/// # Safety
+/// `ctx_ptr` must be null or a pointer returned by `droptea_init`.
+#[no_mangle]
+pub unsafe extern "C" fn droptea_last_error_message(ctx_ptr: *mut c_void) -> *mut c_char {
+    let msg = match (ctx_ptr as *const DropTeaContext).as_ref() {
+        Some(context) => context.last_error.lock().unwrap().clone(),
+        None => GLOBAL_LAST_ERROR.lock().unwrap().clone(),
+    };
+    msg.and_then(|m| CString::new(m.replace('\0', "")).ok()).map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+/// dev_mode มีผลเฉพาะ Context จาก droptea_init (จาก droptea_init_with_config ใช้ Port / dev_mode ในไฟล์)
+/// device_id ไม่ถูกใช้แล้ว: ชื่อเครื่องผูกกับ Cert ตั้งได้ทาง node_name ของ droptea_init_with_config
+///
+/// # Safety
+/// `ctx_ptr` must be null or a pointer returned by `droptea_init`.
+#[no_mangle]
+pub unsafe extern "C" fn droptea_start_service(ctx_ptr: *mut c_void, port: u16, _device_id: *const c_char, dev_mode: bool) -> DropTeaErrorCode {
+    let context = match context(ctx_ptr) { Ok(c) => c, Err(code) => return code };
+    let core = context.core.read().unwrap();
+    let port = match context.configured_port {
+        Some(configured) => configured,
+        None => {
+            let mut config = core.config();
+            if config.dev_mode != dev_mode {
+                config.dev_mode = dev_mode;
+                if let Err(e) = core.reload_config(config) { return context.fail((&e).into(), e.to_string()); }
+            }
+            port
+        }
+    };
+    core.start_service(port);
+    DropTeaErrorCode::Ok
+}
+
+/// NotFound = ไม่มีคำขอนี้ค้างอยู่ (ตัดสินไปแล้ว / หมดเวลา)
+///
+/// # Safety
+/// `ctx_ptr` must be null or a pointer returned by `droptea_init`; `task_id` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn droptea_resolve_request(ctx_ptr: *mut c_void, task_id: *const c_char, accept: bool) -> DropTeaErrorCode {
+    let context = match context(ctx_ptr) { Ok(c) => c, Err(code) => return code };
+    let tid_s = match required(context, opt_str(task_id), "task_id") { Ok(t) => t, Err(code) => return code };
+    match context.core.read().unwrap().resolve_request(tid_s.clone(), accept) {
+        true => DropTeaErrorCode::Ok,
+        false => context.fail(DropTeaErrorCode::NotFound, format!("no pending request '{}'", tid_s)),
+    }
+}
+
+/// NotFound = ไม่มี Transfer นี้ (จบไปแล้ว)
+///
+/// # Safety
+/// `ctx_ptr` must be null or a pointer returned by `droptea_init`; `task_id` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn droptea_cancel_transfer(ctx_ptr: *mut c_void, task_id: *const c_char) -> DropTeaErrorCode {
+    let context = match context(ctx_ptr) { Ok(c) => c, Err(code) => return code };
+    let tid_s = match required(context, opt_str(task_id), "task_id") { Ok(t) => t, Err(code) => return code };
+    match context.core.read().unwrap().cancel_transfer(&tid_s) {
+        true => DropTeaErrorCode::Ok,
+        false => context.fail(DropTeaErrorCode::NotFound, format!("no transfer '{}'", tid_s)),
+    }
+}
+
+/// ส่งไฟล์ (ip = IP หรือ Peer ID จาก Event PEER_FOUND) Event ของ Transfer ออกทาง Callback ที่ให้ไว้ตอน droptea_init
+/// target_os: null = ใช้ OS ที่ Peer ประกาศใน mDNS (ระบุเฉพาะเมื่อต้องการ Override เช่น "ios" = ส่งดิบ)
+/// Ok = เข้าคิวแล้ว (ผลจริงมาทาง COMPLETED / ERROR / REJECTED)
+///
+/// # Safety
+/// `ctx_ptr` must be null or a pointer returned by `droptea_init`; `ip`, `path` and `task_id` must be valid NUL-terminated strings (`target_os` may be null).
+#[no_mangle]
+pub unsafe extern "C" fn droptea_send_file(ctx_ptr: *mut c_void, ip: *const c_char, port: u16, path: *const c_char, task_id: *const c_char, target_os: *const c_char) -> DropTeaErrorCode {
+    droptea_send_file_with_metadata(ctx_ptr, ip, port, path, task_id, target_os, std::ptr::null())
+}
+
+/// เหมือน droptea_send_file แต่แนบ Metadata ของแอป: metadata_json = JSON object ของ string -> string (null / "" = ไม่มี)
+/// ผู้รับได้ใน data2 ของ INCOMING / COMPLETED
+///
+/// # Safety
+/// Same as `droptea_send_file`; `metadata_json` may be null or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn droptea_send_file_with_metadata(ctx_ptr: *mut c_void, ip: *const c_char, port: u16, path: *const c_char, task_id: *const c_char, target_os: *const c_char, metadata_json: *const c_char) -> DropTeaErrorCode {
+    let context = match context(ctx_ptr) { Ok(c) => c, Err(code) => return code };
+    let metadata: Metadata = match opt_str(metadata_json).filter(|m| !m.is_empty()).map(|m| serde_json::from_str(&m)) {
+        None => Metadata::new(),
+        Some(Ok(m)) => m,
+        Some(Err(e)) => return context.fail(DropTeaErrorCode::InvalidArgument, format!("metadata_json: {}", e)),
+    };
+    let args = required(context, opt_str(ip), "ip")
+        .and_then(|ip| Ok((ip, required(context, opt_str(path), "path")?, required(context, opt_str(task_id), "task_id")?)));
+    let (ip_s, path_s, tid_s) = match args { Ok(a) => a, Err(code) => return code };
+    let target_os = opt_str(target_os).filter(|os| !os.is_empty());
+    let handler = CppEventHandlerAdapter { callback: context.callback };
+    context.core.read().unwrap().send_file(ip_s, port, path_s, tid_s, utils::get_system_name(), Box::new(handler), target_os, SendOptions { metadata, ..SendOptions::default() });
+    DropTeaErrorCode::Ok
+}
+
+/// ชื่อสั้นของ droptea_cancel_transfer (คู่กับ droptea_send_file)
+///
+/// # Safety
+/// `ctx_ptr` must be null or a pointer returned by `droptea_init`; `task_id` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn droptea_cancel(ctx_ptr: *mut c_void, task_id: *const c_char) -> DropTeaErrorCode {
+    droptea_cancel_transfer(ctx_ptr, task_id)
+}
+
+/// paused = false คือทำต่อ, NotFound = ไม่มี Transfer นี้
+///
+/// # Safety
+/// `ctx_ptr` must be null or a pointer returned by `droptea_init`; `task_id` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn droptea_pause_transfer(ctx_ptr: *mut c_void, task_id: *const c_char, paused: bool) -> DropTeaErrorCode {
+    let context = match context(ctx_ptr) { Ok(c) => c, Err(code) => return code };
+    let tid_s = match required(context, opt_str(task_id), "task_id") { Ok(t) => t, Err(code) => return code };
+    match context.core.read().unwrap().pause_transfer(&tid_s, paused) {
+        true => DropTeaErrorCode::Ok,
+        false => context.fail(DropTeaErrorCode::NotFound, format!("no transfer '{}'", tid_s)),
+    }
+}
+
+/// # Safety
+/// `ctx_ptr` must be null or a pointer returned by `droptea_init`.
+#[no_mangle]
+pub unsafe extern "C" fn droptea_stop_service(ctx_ptr: *mut c_void) -> DropTeaErrorCode {
+    let context = match context(ctx_ptr) { Ok(c) => c, Err(code) => return code };
+    context.core.read().unwrap().stop_service();
+    DropTeaErrorCode::Ok
+}
+
+/// # Safety
+/// `ctx_ptr` must be null or a pointer returned by `droptea_init`.
+#[no_mangle]
+pub unsafe extern "C" fn droptea_set_activity_state(ctx_ptr: *mut c_void, active: bool) -> DropTeaErrorCode {
+    let context = match context(ctx_ptr) { Ok(c) => c, Err(code) => return code };
+    let state = if active { ActivityState::Active } else { ActivityState::Idle };
+    context.core.read().unwrap().set_activity_state(state);
+    DropTeaErrorCode::Ok
+}
+
+/// # Safety
+/// `ctx_ptr` must be null or a pointer returned by `droptea_init`; `path` and `log_dir` must be valid NUL-terminated strings (`log_dir` may be null).
+#[no_mangle]
+pub unsafe extern "C" fn droptea_export_diagnostics(ctx_ptr: *mut c_void, path: *const c_char, log_dir: *const c_char) -> DropTeaErrorCode {
+    let context = match context(ctx_ptr) { Ok(c) => c, Err(code) => return code };
+    let path_s = match required(context, opt_str(path), "path") { Ok(p) => p, Err(code) => return code };
+    let log_dirs: Vec<std::path::PathBuf> = opt_str(log_dir).into_iter().map(Into::into).collect();
+    context.check(context.core.read().unwrap().export_diagnostics(&path_s, &log_dirs))
+}
+
+/// JSON Array ของ Peer ทั้งที่ออนไลน์และที่เคยเห็น (รูปแบบเดียวกับ get_peers ของ Python)
+/// ผู้เรียกต้องคืน String ด้วย droptea_free_string, null = ไม่สำเร็จ (ดู droptea_last_error_message)
+///
+/// # Safety
+/// `ctx_ptr` must be null or a pointer returned by `droptea_init`.
+#[no_mangle]
+pub unsafe extern "C" fn droptea_get_peers(ctx_ptr: *mut c_void) -> *mut c_char {
+    let Ok(context) = context(ctx_ptr) else { return std::ptr::null_mut() };
+    let peers = context.core.read().unwrap().get_peers();
+    match serde_json::to_string(&peers).ok().and_then(|json| CString::new(json).ok()) {
+        Some(c) => c.into_raw(),
+        None => {
+            context.fail(DropTeaErrorCode::Internal, "failed to serialize peers");
+            std::ptr::null_mut()
+        }
     }
 }
 
+/// JSON Array ของ .part ที่ค้างอยู่ (รูปแบบเดียวกับ list_incomplete ของ Python) คืนด้วย droptea_free_string
+///
+/// # Safety
+/// `ctx_ptr` must be null or a pointer returned by `droptea_init`.
 #[no_mangle]
-pub unsafe extern "C" fn droptea_start_service(ctx_ptr: *mut c_void, port: u16, device_id: *const c_char, dev_mode: bool) {
-    if ctx_ptr.is_null() { return; }
-    let context = &*(ctx_ptr as *mut DropTeaContext);
-    context.core.read().unwrap().start_service(port);
+pub unsafe extern "C" fn droptea_list_incomplete(ctx_ptr: *mut c_void) -> *mut c_char {
+    let Ok(context) = context(ctx_ptr) else { return std::ptr::null_mut() };
+    let files = context.core.read().unwrap().list_incomplete();
+    match serde_json::to_string(&files).ok().and_then(|json| CString::new(json).ok()) {
+        Some(c) => c.into_raw(),
+        None => {
+            context.fail(DropTeaErrorCode::Internal, "failed to serialize incomplete files");
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+/// `s` must be null or a string returned by this library (e.g. `droptea_get_peers`), and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn droptea_free_string(s: *mut c_char) {
+    if !s.is_null() { let _ = CString::from_raw(s); }
 }
 
+/// ประกาศตัวทันทีและ Ping Peer บน LAN (ตัวที่หายไปได้ DROPTEA_EVT_PEER_LOST)
+///
+/// # Safety
+/// `ctx_ptr` must be null or a pointer returned by `droptea_init`.
 #[no_mangle]
-pub unsafe extern "C" fn droptea_resolve_request(ctx_ptr: *mut c_void, task_id: *const c_char, accept: bool) {
-    if ctx_ptr.is_null() { return; }
-    let context = &*(ctx_ptr as *mut DropTeaContext);
-    let tid_s = CStr::from_ptr(task_id).to_string_lossy().into_owned();
-    context.core.read().unwrap().resolve_request(tid_s, accept);
+pub unsafe extern "C" fn droptea_refresh_discovery(ctx_ptr: *mut c_void) -> DropTeaErrorCode {
+    let context = match context(ctx_ptr) { Ok(c) => c, Err(code) => return code };
+    context.core.read().unwrap().refresh_discovery();
+    DropTeaErrorCode::Ok
+}
+
+struct CppTraceSink { callback: TraceCallback }
+impl TraceSink for CppTraceSink {
+    fn record(&self, record: &TraceRecord) {
+        let Ok(json) = serde_json::to_string(record) else { return };
+        let c = CString::new(json).unwrap_or_default();
+        (self.callback)(c.as_ptr());
+    }
 }
 
+/// รับ Trace เป็น JSON ทีละบรรทัด level: 0 = error, 1 = warn, 2 = info, 3 = debug, 4 = trace
+/// callback ถูกเรียกจากหลาย Thread พร้อมกันได้ ตั้งได้ครั้งเดียวต่อโปรเซส (ครั้งถัดไปคืน Config)
+#[no_mangle]
+pub extern "C" fn droptea_set_trace_callback(callback: TraceCallback, level: c_int) -> DropTeaErrorCode {
+    let level = match level {
+        i32::MIN..=0 => tracing::Level::ERROR,
+        1 => tracing::Level::WARN,
+        2 => tracing::Level::INFO,
+        3 => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+    match trace::install(Arc::new(CppTraceSink { callback }), level) {
+        Ok(_) => DropTeaErrorCode::Ok,
+        Err(e) => fail_global((&e).into(), e.to_string()),
+    }
+}
+
+/// # Safety
+/// `ctx_ptr` must be null or a pointer returned by `droptea_init`, and must not be used afterwards.
 #[no_mangle]
 pub unsafe extern "C" fn droptea_free(ctx_ptr: *mut c_void) {
     if !ctx_ptr.is_null() { let _ = Box::from_raw(ctx_ptr as *mut DropTeaContext); }
-}
\ No newline at end of file
+}
@@ -1,31 +1,209 @@
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
 use std::env;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::{Semaphore, mpsc};
 use tokio::time::{timeout};
-use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 use tokio::fs::{self as tokio_fs, File as AsyncFile, OpenOptions};
 use anyhow::{Context, bail};
-use log::info;
+use tracing::info;
 
 use crate::core::transfer::{
-    FileHeader, TransferCallback, DataStream, pack_ack, copy_pipeline,
-    MAX_HEADER_SIZE, IO_TIMEOUT, USER_DECISION_TIMEOUT, ACK_SIZE,
+    FileHeader, Metadata, TransferCallback, DataStream, DynStream, Throughput, pack_ack, unpack_ack, copy_pipeline,
+    MAX_HEADER_SIZE, Timeouts, ACK_SIZE, CLOCK_SKEW_WARN_MS,
+    ACK_EXPIRED, REJECT_EXPIRED, ACK_INCOMPATIBLE, PROTOCOL_VERSION, MIN_PROTOCOL_VERSION, LOCAL_CAPABILITIES,
+    ACK_UNAVAILABLE, CAP_AVAILABILITY, CAP_RESUME, REJECT_BUSY, check_compatibility, incompatible_reason,
+    ACK_DUPLICATE, CAP_DUPLICATE, ACK_DELTA, check_metadata,
 };
-use crate::core::utils::get_unique_path;
-use crate::core::notification::{self, UserResponse};
+use crate::core::utils::{self, has_enough_space};
+use crate::core::history::{self, HistoryEntry};
+use crate::core::io_priority::{FileSink, FileSource, IoPriority};
+use crate::core::notification::UserResponse;
+use crate::core::batch::{BatchCallback, BatchInfo, BatchTracker};
 use crate::core::security;
+use crate::core::archive::{self, ArchiveMode};
+use crate::core::webhook::ApprovalWebhook;
+use crate::core::admin::{self, AdminContext, ControlRequest};
+use crate::core::dedup::{self, ChunkIndex, DedupInfo};
+use crate::core::codes::{self, Claimed, CodeClaim, CodeOffers, CodeRequest};
+use crate::core::delta;
+use crate::core::duplicate;
+use crate::core::hooks::{DynPostReceiveHook, ReceivedFile};
+use crate::core::middleware::{Direction, MiddlewareChain, TransferContext};
+use crate::core::sender_queue::SenderQueues;
+use crate::core::peer_caps::PeerCapsCache;
+use crate::core::stats::{StatsCollector, StatsStore};
+use crate::core::session::{self, Admission, SessionApprovals, SessionDecision, SessionOffer, SessionRequest, MAX_SESSION_OFFER_SIZE, REJECT_MANIFEST};
+use crate::core::shares::{self, ShareCommand, ShareContext, ShareRequest};
+use crate::core::save_rules::{SaveRules, SaveTarget};
+use crate::core::reload::Live;
+use crate::core::peer_prefs::{PeerOverride, PeerOverrides, Throttled};
+use crate::core::thumbnail::{self, Thumbnail};
+use crate::core::discovery::Availability;
+use crate::core::discovery::privacy::{self, IdentifyRequest};
+use crate::core::tuning::Tunables;
+use crate::core::sandbox::SandboxHelper;
+use crate::core::mmap::MappedFile;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use crate::core::transfer::sendfile_pipeline;
+use crate::core::reputation::{self, ReputationCheck};
+use crate::core::cancel::{CancellationToken, RemoteTransfer, TransferSignal, TransferTokens, REJECT_CANCELLED};
+use crate::core::parallel::{self, LaneRegistry, LaneRequest, ParallelSend};
+use crate::core::resume::{self, Parked, ResumeRegistry};
 // 🔥 Import โมดูลใหม่
-use crate::core::compression::{Compressor, Decompressor, CompressionAlgo};
+use crate::core::compression::{self, Compressor, Decompressor, CompressionAlgo};
 
 const IO_BUFFER_SIZE: usize = 1024 * 1024; 
+// เผื่อพื้นที่สำหรับไฟล์ .part และ Metadata ของ Filesystem
+const DISK_SPACE_RESERVE: u64 = 64 * 1024 * 1024;
+const REJECT_NO_SPACE: &str = "Insufficient space";
+// เผื่อเวลาส่ง ACK ก่อนผู้ส่งจะเลิกรอ (Timeouts::user_decision หรืออายุของข้อเสนอ)
+const ACK_DEADLINE_MARGIN: Duration = Duration::from_secs(10);
 
+// เวลาที่ User ตัดสินใจได้จริง: หัก Margin ออก แต่ข้อเสนออายุสั้นหักไม่เกิน 1/4 (ไม่งั้นหมดอายุก่อนได้ถามเลย)
+fn decision_window(limit: Duration) -> Duration {
+    limit.saturating_sub(ACK_DEADLINE_MARGIN.min(limit / 4))
+}
+// ไฟล์ใหญ่กว่านี้ไม่แนบ SHA-256 (อ่านทั้งไฟล์ก่อนส่งข้อเสนอจะช้าเกินไป)
+pub const OFFER_HASH_MAX_SIZE: u64 = 128 * 1024 * 1024;
+
+// Policy ฝั่งรับ (มาจาก DropTeaConfig)
+#[derive(Debug, Clone)]
+pub struct ReceiveOptions {
+    pub archive_mode: ArchiveMode,
+    // Live = เปลี่ยนได้จาก Config Hot-Reload (แต่ละ Transfer อ่านครั้งเดียวตอนเริ่ม)
+    pub approval_webhook: Arc<Live<Option<ApprovalWebhook>>>,
+    pub admin: Option<Arc<AdminContext>>,
+    pub io_priority: IoPriority,
+    pub dedup: Option<Arc<ChunkIndex>>,
+    pub sender_queue: Option<Arc<SenderQueues>>,
+    pub shares: Arc<ShareContext>,
+    // โฟลเดอร์ที่ไฟล์ลง (storage.download_dir) ส่วน Whitelist / History อยู่ที่ state_dir ของ handle_incoming
+    pub download_dir: Arc<Live<String>>,
+    // แยกโฟลเดอร์ปลายทางตามชนิดไฟล์ (None = ลง download_dir ทั้งหมด)
+    pub save_rules: Arc<Live<Option<Arc<SaveRules>>>>,
+    // ค่าจาก Tuning Preset (ปรับได้ตอน Runtime)
+    pub tunables: Arc<Tunables>,
+    // Transfer ที่กำลังรับ (ยกเลิกด้วย Task ID ได้)
+    pub transfers: Arc<TransferTokens>,
+    // แตก Compression ในโปรเซสแยก (None = แตกในโปรเซสนี้)
+    pub sandbox: Option<Arc<SandboxHelper>>,
+    // ถามความน่าเชื่อถือของไฟล์จาก Hash ก่อนขึ้น Prompt (None = ปิด)
+    pub reputation: Option<Arc<dyn ReputationCheck>>,
+    pub timeouts: Timeouts,
+    // รวม Progress ของไฟล์ที่มาเป็นชุด (FileHeader.batch)
+    pub batches: Arc<BatchTracker>,
+    // ชุดที่ User อนุมัติทั้ง Session แล้ว (ดู session.rs)
+    pub sessions: Arc<SessionApprovals>,
+    // จำ Version / Capability ของผู้ส่ง (ผูกกับ Fingerprint) ไว้ใช้ตอนเราส่งกลับ
+    pub peer_caps: Arc<PeerCapsCache>,
+    // สรุปของไฟล์ที่รับจบแล้ว (transport = ชื่อ Mode ของเรา)
+    pub stats: Arc<StatsStore>,
+    pub transport: &'static str,
+    // Guest Mode: ไม่อ่าน/เขียน Whitelist ของเจ้าของเครื่อง (ถามทุกครั้ง)
+    pub guest: bool,
+    // [peers."<id>"]: auto_accept / max_bytes_per_sec ของผู้ส่งแต่ละราย
+    pub peer_overrides: Arc<PeerOverrides>,
+    // จำกัดความเร็วของผู้ส่งที่ [peers.*] ไม่ได้ตั้งไว้ (server.max_bytes_per_sec)
+    pub max_bytes_per_sec: Arc<Live<Option<u64>>>,
+    // สถานะที่ User ตั้ง (set_availability) ไม่ใช่ Available = ปฏิเสธทันทีไม่ถาม
+    pub availability: Arc<Live<Availability>>,
+    // Transfer ที่รอ Stream ย่อยของไฟล์ใหญ่ (FileHeader.parallel)
+    pub parallel: Arc<LaneRegistry>,
+    // .part ของไฟล์ที่ Connection หลุดกลางทาง รอผู้ส่งกลับมาต่อ
+    pub resume: Arc<ResumeRegistry>,
+    // เขียน .part ที่นี่ก่อนย้ายไปปลายทาง (storage.temp_path) None = ข้างไฟล์ปลายทาง
+    pub temp_dir: Option<PathBuf>,
+    // ไฟล์ที่มีอยู่แล้ว (FileHeader.quick_hash ตรง) ตอบ "มีแล้ว" แทนการถาม
+    pub skip_duplicates: bool,
+    // รันตามลำดับหลัง Completed ([hooks] post_receive + DropTeaBuilder::post_receive_hook)
+    pub post_receive: Arc<Vec<DynPostReceiveHook>>,
+    // DropTeaBuilder::middleware (ผู้ส่งของ Engine เดียวกันใช้ชุดนี้ด้วย)
+    pub middleware: Arc<MiddlewareChain>,
+    // Some = Connection ที่เราขอไฟล์เองด้วย request_shared_file: รับได้แค่ไฟล์ชื่อนี้ ไม่ถาม User
+    pub requested: Option<String>,
+    // Code ที่เราเปิดรอผู้รับอยู่ (offer_code) ผู้รับที่พิมพ์ถูกได้ไฟล์ทาง Connection ที่ต่อเข้ามา
+    pub codes: Arc<CodeOffers>,
+}
+
+// 🪝 หลัง Completed: SHA-256 ของไฟล์ที่ลงแล้ว -> Hook ทีละตัว ตัวที่ล้มไม่หยุดตัวถัดไป
+async fn run_post_receive<CB: TransferCallback>(hooks: Arc<Vec<DynPostReceiveHook>>, callback: CB, task_id: String, path: PathBuf, header: FileHeader) {
+    let shown = path.to_string_lossy().to_string();
+    let p = path.clone();
+    let hashed = tokio::task::spawn_blocking(move || utils::sha256_file(&p)).await
+        .map_err(anyhow::Error::from)
+        .and_then(|r| r.map_err(anyhow::Error::from));
+    let sha256 = match hashed {
+        Ok(hash) => hash,
+        Err(e) => return callback.on_hook_failed(&task_id, &shown, &format!("Failed to hash received file: {}", e)),
+    };
+    let file = ReceivedFile {
+        task_id: task_id.clone(),
+        path,
+        filename: header.filename,
+        sender_name: header.sender_name,
+        sha256,
+        metadata: header.metadata,
+    };
+    for hook in hooks.iter() {
+        if let Err(e) = hook.run(&file).await {
+            tracing::warn!("Post-receive hook '{}' failed for '{}': {:#}", hook.name(), shown, e);
+            callback.on_hook_failed(&task_id, &shown, &format!("{}: {:#}", hook.name(), e));
+        }
+    }
+}
+
+// .part ของไฟล์ที่กำลังรับ: ใน temp_dir ต่อท้ายด้วย Task ID (หลายโฟลเดอร์ปลายทางใช้ temp_dir ร่วมกัน ชื่อไฟล์ชนกันได้)
+fn part_path(temp_dir: Option<&Path>, final_path: &Path, task_id: &str) -> PathBuf {
+    match temp_dir {
+        Some(dir) => {
+            let name = final_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            dir.join(format!("{}.{}.part", name, task_id))
+        }
+        None => final_path.with_extension("part"),
+    }
+}
+
+// ผู้ส่งที่ไม่รู้จัก ACK_UNAVAILABLE (จะนับเป็น Accept) -> ตอบ Reject ธรรมดา
+fn unavailable_ack(header: &FileHeader, availability: Availability) -> Vec<u8> {
+    match header.capabilities & CAP_AVAILABILITY != 0 {
+        true => pack_ack(ACK_UNAVAILABLE, availability.code()),
+        false => pack_ack(0, 0),
+    }
+}
+
+// ส่งสด (none) ไม่มี Decoder ให้โจมตี -> ไม่ต้องเสีย Process
+fn decoder<S: DataStream>(sandbox: Option<&SandboxHelper>, stream: S, algo: CompressionAlgo, max_bytes: u64) -> anyhow::Result<Box<dyn AsyncRead + Unpin + Send>> {
+    Ok(match sandbox {
+        Some(helper) if algo != CompressionAlgo::None => Box::new(helper.decode(stream, algo, max_bytes)?),
+        _ => Box::new(Decompressor::new(stream, algo)),
+    })
+}
+
+// Fingerprint ก่อน (ปลอมไม่ได้) แล้วค่อยชื่อที่ผู้ส่งบอก / IP
+fn peer_override(options: &ReceiveOptions, fingerprint: Option<&str>, sender_name: &str, peer_addr: std::net::SocketAddr) -> PeerOverride {
+    let ip = peer_addr.ip().to_canonical().to_string();
+    let mut pref = options.peer_overrides.lookup(fingerprint.into_iter().chain([sender_name, ip.as_str()]));
+    pref.max_bytes_per_sec = pref.max_bytes_per_sec.or(options.max_bytes_per_sec.get());
+    pref
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_incoming<S, CB>(
     mut stream: S,
-    save_path: String,
+    // Whitelist / History (ไม่ย้ายตาม download_dir)
+    state_dir: String,
     callback: CB,
     limiter: Arc<Semaphore>,
     pending_map: Arc<Mutex<HashMap<String, mpsc::UnboundedSender<UserResponse>>>>,
+    options: ReceiveOptions,
+    peer_addr: std::net::SocketAddr,
+    peer_fingerprint: Option<String>,
+    cancel: CancellationToken,
+    // Span "receive" ที่ครอบ Connection นี้ (เติม task_id เมื่อรู้)
+    span: tracing::Span,
 ) -> anyhow::Result<()>
 where 
     S: DataStream, 
@@ -34,10 +212,10 @@ where
     // 1. Read Header Size
     let mut len_buf = [0u8; 4];
     // 1. อ่านขนาด Header และดักจับ Ghost Connection
-    match timeout(IO_TIMEOUT, stream.read_exact(&mut len_buf)).await {
+    match timeout(options.timeouts.io, stream.read_exact(&mut len_buf)).await {
         Ok(Ok(_)) => {}, 
         Ok(Err(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-            log::debug!("Ghost connection detected (Early EOF). Ignoring.");
+            tracing::debug!("Ghost connection detected (Early EOF). Ignoring.");
             return Ok(()); 
         },
         Ok(Err(e)) => return Err(anyhow::Error::new(e)),
@@ -45,7 +223,8 @@ where
     };
 
     let header_len = u32::from_le_bytes(len_buf) as usize;
-    if header_len > MAX_HEADER_SIZE { bail!("Header too large"); }
+    // Manifest ของ Session ใหญ่กว่า Header ปกติได้ (อย่างอื่นยังจำกัดที่ MAX_HEADER_SIZE ด้านล่าง)
+    if header_len > MAX_SESSION_OFFER_SIZE { bail!("Header too large"); }
 
     // 2. อ่าน Header Body (ไปต่อได้เลย ไม่ต้องอ่าน len_buf ซ้ำแล้ว) 
     // timeout(IO_TIMEOUT, stream.read_exact(&mut len_buf)).await.context("Header size timeout")??;
@@ -54,104 +233,796 @@ where
 
     // 2. Read Header Body
     let mut header_buf = vec![0u8; header_len];
-    timeout(IO_TIMEOUT, stream.read_exact(&mut header_buf)).await.context("Header read timeout")??;
+    timeout(options.timeouts.io, stream.read_exact(&mut header_buf)).await.context("Header read timeout")??;
+    // 📥 Connection ที่เราเปิดไปขอไฟล์: อีกฝั่งส่งได้แค่ FileHeader
+    if options.requested.is_some() {
+        if header_len > MAX_HEADER_SIZE { bail!("Header too large"); }
+    } else {
+        // 🎫 Manifest ของชุดไฟล์: ถามครั้งเดียวทั้งชุด
+        if let Ok(request) = serde_json::from_slice::<SessionRequest>(&header_buf) {
+            return handle_session_offer(stream, request.session, &state_dir, &callback, &pending_map, &options, peer_fingerprint.as_deref(), peer_addr).await;
+        }
+        if header_len > MAX_HEADER_SIZE { bail!("Header too large"); }
+        // 🕶️ Peer ขอชื่อจริง (เราเปิด Privacy Mode) -> บอกเฉพาะ Cert ที่เคยรับไฟล์มาแล้ว
+        if serde_json::from_slice::<IdentifyRequest>(&header_buf).is_ok() {
+            let known = peer_fingerprint.as_deref().and_then(|fp| options.peer_caps.get(fp)).is_some_and(|caps| caps.accepted_at.is_some());
+            return privacy::respond(stream, known.then(utils::get_system_name), options.timeouts.io).await;
+        }
+        // 🛠️ Control Channel: Admin Peer ส่งคำสั่งแทน FileHeader
+        if let Ok(request) = serde_json::from_slice::<ControlRequest>(&header_buf) {
+            return admin::handle_control(stream, request, peer_fingerprint.as_deref(), options.admin.as_deref()).await;
+        }
+        // 📂 Browse/Pull จาก Shared Folder (Read-only, ตรวจ ACL ราย Share)
+        if let Ok(request) = serde_json::from_slice::<ShareRequest>(&header_buf) {
+            if let ShareCommand::Request { id, compression } = request.shares {
+                return send_requested(stream, &id, &compression, callback, &options, peer_fingerprint.as_deref(), peer_addr, cancel).await;
+            }
+            let peer = peer_fingerprint.clone().unwrap_or_else(|| peer_addr.ip().to_string());
+            return shares::handle_share(stream, request, &peer, peer_fingerprint.as_deref(), &options.shares, &callback).await;
+        }
+        // 🎟️ ผู้รับพิมพ์ Code ของเรา -> ตรวจแล้วส่งไฟล์ของ Code นั้นกลับไป
+        if let Ok(request) = serde_json::from_slice::<CodeRequest>(&header_buf) {
+            return send_code(stream, request.code, callback, &options, peer_fingerprint.as_deref(), peer_addr, cancel).await;
+        }
+        // 🛤️ Stream ย่อยของไฟล์ใหญ่ที่ส่งแบบ Parallel -> ส่งต่อให้ Transfer ที่ ACK ไปแล้ว
+        if let Ok(request) = serde_json::from_slice::<LaneRequest>(&header_buf) {
+            return options.parallel.deliver(peer_addr.ip(), peer_fingerprint.as_deref(), request.lane, Box::new(stream)).await;
+        }
+    }
     let header: FileHeader = serde_json::from_slice(&header_buf).context("Invalid header JSON")?;
-    let task_id = header.filename.clone();
+    check_metadata(&header.metadata)?;
+    if options.requested.as_ref().is_some_and(|name| *name != header.filename) {
+        bail!("Peer sent '{}' instead of the requested file", header.filename);
+    }
+    if header.parallel.is_some_and(|p| !p.is_valid() || header.dedup.is_some() || header.transfer_id.is_none()) {
+        bail!("Invalid parallel plan");
+    }
+    // 🔁 ผู้ส่งต่อไฟล์ที่หลุดไป -> .part / Task ID เดิม ไม่ถามซ้ำ (ไม่มีให้ต่อ = ปฏิเสธ ไม่ขึ้น Prompt ใหม่)
+    let resumed = match header.resuming {
+        true => peer_fingerprint.as_deref().and_then(|fp| options.resume.claim(fp, &header)),
+        false => None,
+    };
+    if header.resuming && resumed.is_none() {
+        info!("Nothing to resume for '{}' from '{}'", header.filename, header.sender_name);
+        let _ = timeout(options.timeouts.io, stream.write_all(&pack_ack(0, 0))).await;
+        return Ok(());
+    }
+    let peer_pref = peer_override(&options, peer_fingerprint.as_deref(), &header.sender_name, peer_addr);
+    // 👤 ผู้ส่งเป็น Guest -> Cert ใช้ครั้งเดียว ไม่ต้องจำ
+    if let Some(fp) = peer_fingerprint.as_deref().filter(|_| !header.guest) {
+        options.peer_caps.record(fp, Some(&peer_addr.ip().to_canonical().to_string()), |caps| {
+            caps.protocol_version = Some(header.protocol_version);
+            caps.capabilities = Some(header.capabilities);
+        });
+    }
+    // Task ID ฝั่งรับสร้างเองเสมอ (ชื่อไฟล์ซ้ำกันได้ถ้าส่งมาพร้อมกันหลายเครื่อง) ต่อไฟล์ที่หลุด = ใช้ของเดิม
+    // ชื่อไฟล์จะไปกับ Event (on_start / ask_accept_file) แทน
+    let task_id = resumed.as_ref().map_or_else(|| uuid::Uuid::new_v4().to_string(), |r| r.task_id.clone());
+    span.record("task_id", task_id.as_str());
+    let transfer = options.transfers.register(&cancel, &task_id);
+    if let Some(transfer_id) = header.transfer_id.clone() {
+        transfer.set_remote(RemoteTransfer { ip: peer_addr.ip(), control_port: header.control_port, transfer_id });
+    }
+    // 🗂️ ไฟล์ในชุดเดียวกัน -> แจ้ง BatchProgress รวมด้วย (id ของชุดไม่ซ้ำแค่ภายในผู้ส่งคนเดียว)
+    let batch_key = format!("{}|{}", peer_fingerprint.as_deref().unwrap_or(&header.sender_name), header.batch.as_ref().map_or("", |b| b.id.as_str()));
+    let callback = BatchCallback::new(callback, &options.batches, batch_key.clone(), header.batch.as_ref(), &task_id);
+    // 🧬 Wire Format คนละรุ่น -> ปฏิเสธพร้อมเหตุผล ก่อนถาม User
+    if let Some(reason) = check_compatibility(&header) {
+        tracing::warn!("Rejecting '{}' from '{}': {}", header.filename, header.sender_name, reason);
+        // ผู้ส่ง v1 ไม่รู้จัก ACK_INCOMPATIBLE (จะนับเป็น Accept) -> ตอบ Reject ธรรมดา
+        let status = if header.protocol_version >= 2 { ACK_INCOMPATIBLE } else { 0 };
+        let _ = timeout(options.timeouts.io, stream.write_all(&pack_ack(status, PROTOCOL_VERSION as u64))).await;
+        callback.on_reject(&task_id, &reason);
+        return Ok(());
+    }
+    // 🚦 ปิดรับ / ไม่ว่าง -> ปฏิเสธพร้อมเหตุผลก่อนถาม User
+    let availability = options.availability.get();
+    if let Some(reason) = availability.reject_reason().filter(|_| options.requested.is_none()) {
+        info!("Rejecting '{}' from '{}': {}", header.filename, header.sender_name, reason);
+        let _ = timeout(options.timeouts.io, stream.write_all(&unavailable_ack(&header, availability))).await;
+        callback.on_reject(&task_id, reason);
+        return Ok(());
+    }
+    // ⏳ ผู้ส่งกำหนดอายุข้อเสนอมา -> ใช้แทน Timeouts::user_decision
+    let offer_expiry = header.expires_in_ms.map(Duration::from_millis);
+    let decision_limit = decision_window(offer_expiry.unwrap_or(options.timeouts.user_decision));
+    let ack_deadline = tokio::time::Instant::now() + decision_limit;
+
+    // ⏰ เทียบนาฬิกาผู้ส่ง (เฉพาะ Client ที่ส่ง sent_at มา)
+    let received_at = utils::timestamp_millis();
+    let clock_skew_ms = header.sent_at.map(|sent| sent as i64 - received_at as i64);
+    if let Some(skew) = clock_skew_ms.filter(|s| s.abs() > CLOCK_SKEW_WARN_MS) {
+        tracing::warn!("Clock skew with '{}': {} ms", header.sender_name, skew);
+        callback.on_clock_skew(&task_id, &header.sender_name, skew);
+    }
 
     // 3. Rate Limit Check
     let _permit = match limiter.try_acquire() {
         Ok(p) => p,
         Err(_) => {
-            let _ = timeout(IO_TIMEOUT, stream.write_all(&pack_ack(0, 0))).await;
-            callback.on_reject(&task_id, "System Busy");
+            let _ = timeout(options.timeouts.io, stream.write_all(&unavailable_ack(&header, Availability::Busy))).await;
+            callback.on_reject(&task_id, REJECT_BUSY);
             return Ok(());
         }
     };
 
-    // 4. Security Check
-    let is_trusted = security::is_trusted(&save_path, &header.sender_name);
-    let is_accepted = if is_trusted {
-        callback.on_start(&task_id, &header.filename); true 
-    } else {
-        let (tx, mut rx) = mpsc::unbounded_channel();
-        { if let Ok(mut map) = pending_map.lock() { map.insert(task_id.clone(), tx.clone()); } }
-        let _ = callback.ask_accept_file(&task_id, &header.filename, header.filesize, &header.sender_name, &header.sender_device);
-        let decision = timeout(USER_DECISION_TIMEOUT, rx.recv()).await;
-        { if let Ok(mut map) = pending_map.lock() { map.remove(&task_id); } }
-        match decision { Ok(Some(UserResponse::Accept)) => { security::add_trust(&save_path, header.sender_name.clone()); true }, _ => false }
+    // 📁 เลือกโฟลเดอร์ปลายทางตามชนิดไฟล์ (ก่อนเช็คพื้นที่ เพราะอาจอยู่คนละ Disk กับ save_path)
+    // อ่าน download_dir ครั้งเดียว: Reload ระหว่างรับไม่ย้ายไฟล์นี้
+    let save_path = options.download_dir.get();
+    // ไฟล์ของ Mirror ลงตาม Path ในโฟลเดอร์ที่อนุมัติไว้ (ไม่ผ่าน save_rules)
+    let mirror = header.batch.as_ref().and_then(|_| options.sessions.mirror_dir(&batch_key));
+    let target = match (&mirror, options.save_rules.get()) {
+        (Some(root), _) => session::mirror_target(root, &header.filename),
+        (None, Some(rules)) => rules.resolve(&save_path, &header.filename),
+        (None, None) => SaveTarget::unmatched(&save_path),
     };
+    // save_path เองก็สร้างให้ด้วย (Guest Mode ไม่ได้ผ่าน Whitelist ที่เคยสร้างโฟลเดอร์ให้)
+    if let Err(e) = tokio_fs::create_dir_all(&target.dir).await {
+        tracing::warn!("Cannot create destination '{}': {}", target.dir, e);
+        let _ = timeout(options.timeouts.io, stream.write_all(&pack_ack(0, 0))).await;
+        callback.on_reject(&task_id, "Destination unavailable");
+        return Ok(());
+    }
+
+    // 🪞 มีไฟล์นี้อยู่แล้ว -> ข้าม ไม่ถาม User (ผู้ส่งที่ไม่รู้จัก ACK_DUPLICATE ได้ Reject ธรรมดา)
+    if let Some(hash) = header.quick_hash.clone().filter(|_| options.skip_duplicates && resumed.is_none() && mirror.is_none() && header.filesize > 0) {
+        let (dir, size) = (PathBuf::from(&target.dir), header.filesize);
+        let existing = tokio::task::spawn_blocking(move || duplicate::find_existing(&dir, size, &hash)).await.ok().flatten();
+        if let Some(existing) = existing {
+            info!("Skipping '{}' from '{}': already have '{}'", header.filename, header.sender_name, existing.display());
+            let status = if header.capabilities & CAP_DUPLICATE != 0 { ACK_DUPLICATE } else { 0 };
+            let _ = timeout(options.timeouts.io, stream.write_all(&pack_ack(status, 0))).await;
+            callback.on_duplicate(&task_id, &header.filename, Some(&existing.to_string_lossy()));
+            return Ok(());
+        }
+    }
+
+    // 4. Disk Space Preflight (ก่อนถาม User จะได้ไม่ต้องกดรับไฟล์ที่ลงไม่ได้)
+    let required_space = header.filesize.saturating_add(DISK_SPACE_RESERVE);
+    if !has_enough_space(&target.dir, required_space) {
+        let _ = timeout(options.timeouts.io, stream.write_all(&pack_ack(0, 0))).await;
+        callback.on_reject(&task_id, REJECT_NO_SPACE);
+        return Ok(());
+    }
+
+    // 🎫 ชุดที่ User อนุมัติทั้ง Session แล้ว: ไม่ถามซ้ำ แต่ต้องตรงกับ Manifest (Some = ผ่าน, ค่าข้างในคือ SHA-256 ที่ต้องตรวจหลังรับ)
+    // ต่อไฟล์ที่หลุด: Manifest ถูกตัดรายการนี้ไปแล้วตอนรอบแรก -> ใช้ผลเดิม
+    let admission = match &resumed {
+        Some(r) => r.approved.clone().map(|sha256| Admission::Approved { sha256 }),
+        None => header.batch.as_ref().map(|_| options.sessions.admit(&batch_key, &header, &task_id)),
+    };
+    let approved = match admission {
+        None | Some(Admission::Prompt) => None,
+        Some(Admission::Approved { sha256 }) => Some(sha256),
+        Some(Admission::Violation { reason, cancel }) => {
+            tracing::warn!("Aborting session from '{}': {}", header.sender_name, reason);
+            for id in cancel { options.transfers.cancel(&id); }
+            let _ = timeout(options.timeouts.io, stream.write_all(&pack_ack(0, 0))).await;
+            callback.on_reject(&task_id, &reason);
+            return Ok(());
+        }
+    };
+
+    // 📦 หลายไฟล์จากผู้ส่งเดียวกัน = Session เดียว (ถามครั้งเดียว, เขียนทีละไฟล์)
+    let ticket = options.sender_queue.as_ref().filter(|_| resumed.is_none()).map(|q| {
+        q.join(format!("{}|{}", header.sender_name, peer_fingerprint.as_deref().unwrap_or_default()))
+    });
+    let session_id = ticket.as_ref().map(|t| t.session_id().to_string());
+
+    // 5. Security Check
+    let approval_webhook = options.approval_webhook.get();
+    let decide = async {
+        // auto_accept ของ Peer นี้มาก่อน Whitelist
+        let is_trusted = peer_pref.auto_accept.unwrap_or_else(|| !options.guest && security::is_trusted(&state_dir, &header.sender_name));
+        if is_trusted {
+            callback.on_start(&task_id, &header.filename); true 
+        } else if let Some(hook) = &approval_webhook {
+            // Unattended: ให้ระบบอนุมัติภายนอกตัดสิน (ไม่เพิ่มเข้า Whitelist)
+            let accept = hook.ask(&task_id, &header).await;
+            if accept { callback.on_start(&task_id, &header.filename); }
+            accept
+        } else {
+            let verdict = match &options.reputation {
+                Some(check) => Some(reputation::verdict(check.as_ref(), header.sha256.as_deref(), &header.filename).await),
+                None => None,
+            };
+            let (tx, mut rx) = mpsc::unbounded_channel();
+            if let Ok(mut map) = pending_map.lock() { map.insert(task_id.clone(), tx.clone()); }
+            let thumbnail = header.thumbnail.as_ref().and_then(|t| thumbnail::sanitize(t, &header.filename));
+            let _ = callback.ask_accept_file(&task_id, &header.filename, header.filesize, &header.sender_name, &header.sender_device, session_id.as_deref(), verdict.map(|v| v.as_str()), thumbnail.as_ref(), &header.metadata);
+            let decision = match offer_expiry {
+                Some(_) => tokio::time::timeout_at(ack_deadline, rx.recv()).await,
+                None => timeout(options.timeouts.user_decision, rx.recv()).await,
+            };
+            if let Ok(mut map) = pending_map.lock() { map.remove(&task_id); }
+            match decision {
+                Ok(Some(UserResponse::Accept)) => {
+                    if !options.guest && !header.guest { security::add_trust(&state_dir, header.sender_name.clone()); }
+                    true
+                }
+                _ => false,
+            }
+        }
+    };
+    let is_accepted = match &ticket {
+        _ if approved.is_some() || resumed.is_some() || options.requested.is_some() => { callback.on_start(&task_id, &header.filename); true }
+        Some(t) => match t.decide(ack_deadline, decide).await {
+            Some((accepted, reused)) => {
+                // ไฟล์ถัดไปใน Session ที่รับแล้ว ไม่ได้ผ่าน on_start ใน decide
+                if accepted && reused { callback.on_start(&task_id, &header.filename); }
+                accepted
+            }
+            None => false,
+        },
+        None => decide.await,
+    };
+
+    if !is_accepted && offer_expiry.is_some() && tokio::time::Instant::now() >= ack_deadline {
+        info!("Offer '{}' from '{}' expired before a decision", header.filename, header.sender_name);
+        let _ = timeout(options.timeouts.io, stream.write_all(&pack_ack(ACK_EXPIRED, 0))).await;
+        callback.on_reject(&task_id, REJECT_EXPIRED);
+        return Ok(());
+    }
 
     if !is_accepted {
-        let _ = timeout(IO_TIMEOUT, stream.write_all(&pack_ack(0, 0))).await;
-        callback.on_reject(&task_id, "User Rejected");
+        let reason = if approval_webhook.is_some() { "Webhook Rejected" } else { "User Rejected" };
+        let _ = timeout(options.timeouts.io, stream.write_all(&pack_ack(0, 0))).await;
+        callback.on_reject(&task_id, reason);
+        return Ok(());
+    }
+
+    // พื้นที่อาจถูกใช้ไประหว่างรอ User ตัดสินใจ -> เช็คซ้ำก่อนส่ง ACK=1 (.part อยู่อีก Disk ก็ต้องพอทั้งสองที่)
+    let temp_has_space = options.temp_dir.as_deref().is_none_or(|dir| has_enough_space(&dir.to_string_lossy(), required_space));
+    if !has_enough_space(&target.dir, required_space) || !temp_has_space {
+        let _ = timeout(options.timeouts.io, stream.write_all(&pack_ack(0, 0))).await;
+        callback.on_reject(&task_id, REJECT_NO_SPACE);
         return Ok(());
     }
 
-    // 5. Prepare File
-    let final_path = get_unique_path(&save_path, &header.filename);
-    let temp_path = final_path.with_extension("part");
-    let file = OpenOptions::new().write(true).create(true).truncate(true).open(&temp_path).await?;
-    let mut buffered_file = BufWriter::with_capacity(IO_BUFFER_SIZE, file);
+    // 🚦 รอไฟล์ก่อนหน้าของผู้ส่งคนนี้เขียนเสร็จก่อน (Disk ไม่ต้อง Seek ไปมา)
+    let _turn = match &ticket {
+        Some(t) => t.wait_turn(ack_deadline).await,
+        None => None,
+    };
+
+    // 6. Prepare File
+    let (final_path, temp_path, offset) = match &resumed {
+        Some(r) => (r.final_path.clone(), r.temp_path.clone(), r.offset),
+        None => {
+            let final_path = target.final_path(&header.filename);
+            let temp_path = part_path(options.temp_dir.as_deref(), &final_path, &task_id);
+            (final_path, temp_path, 0)
+        }
+    };
+    let file = match offset {
+        0 => {
+            if let Some(dir) = &options.temp_dir { tokio_fs::create_dir_all(dir).await?; }
+            // จองพื้นที่ทั้งไฟล์ไว้ก่อน (ไม่กระจัดกระจาย / Disk เต็มรู้ตั้งแต่ต้น) Filesystem ที่จองไม่ได้ = เขียนต่อท้ายไปเรื่อยๆ แบบเดิม
+            if header.filesize > 0 {
+                if let Err(e) = utils::preallocate_file(temp_path.to_string_lossy().to_string(), header.filesize) {
+                    tracing::debug!("Could not preallocate '{}': {}", temp_path.display(), e);
+                }
+            }
+            OpenOptions::new().write(true).create(true).truncate(header.filesize == 0).open(&temp_path).await?
+        }
+        _ => {
+            // ตัดส่วนที่เกิน offset ทิ้ง (เขียนค้างตอนหลุด) แล้วเขียนต่อจากตรงนั้น
+            let mut file = OpenOptions::new().write(true).open(&temp_path).await?;
+            file.set_len(offset).await?;
+            file.seek(std::io::SeekFrom::Start(offset)).await?;
+            info!("Resuming '{}' at {} of {} bytes", header.filename, offset, header.filesize);
+            file
+        }
+    };
+    let mut sink = FileSink::new(file, options.io_priority, options.tunables.io_buffer_size());
+    // ผู้ส่งเปิด Stream ย่อยทันทีที่ได้ ACK -> ต้องรอรับไว้ก่อน
+    let lanes = header.parallel.zip(header.transfer_id.as_deref())
+        .map(|(plan, id)| options.parallel.expect(peer_addr.ip(), peer_fingerprint.as_deref(), id, plan));
     
-    // 6. Send ACK
-    stream.write_all(&pack_ack(1, 0)).await?;
+    // 🧬 มีไฟล์ชื่อเดียวกันเวอร์ชันเก่าอยู่ -> ขอเฉพาะ Block ที่เปลี่ยน (Signature อ่าน Basis ทั้งไฟล์ ทำก่อนตอบ ACK)
+    let delta = match resumed.is_none().then(|| delta::basis_for(&header, &target.dir)).flatten() {
+        Some(basis) => match delta::Signature::of(&basis).await {
+            Ok(sig) => Some((basis, sig)),
+            Err(e) => {
+                tracing::debug!("No delta for '{}': {}", header.filename, e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    // 7. Send ACK (offset > 0 เฉพาะตอนต่อไฟล์ที่หลุด)
+    match &delta {
+        Some((_, sig)) => {
+            stream.write_all(&pack_ack(ACK_DELTA, sig.block_size)).await?;
+            sig.send(&mut stream).await?;
+            info!("Delta: offered {} blocks of '{}'", sig.block_count(), header.filename);
+        }
+        None => stream.write_all(&pack_ack(1, offset)).await?,
+    }
     
-    // 🔥 7. Auto Detect Compression (ถ้า Header บอกว่า none ก็รับสด, ถ้า zstd ก็แกะ)
+    // 🔥 8. Auto Detect Compression (ไม่บอกก็รับสด, ชื่อที่ไม่รู้จักถูกปฏิเสธไปแล้วใน check_compatibility)
     let algo = header.compression
         .as_deref()
-        .and_then(CompressionAlgo::from_str)
-        .unwrap_or(CompressionAlgo::Zstd);
+        .and_then(CompressionAlgo::from_name)
+        .unwrap_or(CompressionAlgo::None);
 
     info!("Receiving '{}' (Mode: {:?})", header.filename, algo);
 
-    let decoder = Decompressor::new(stream, algo);
+    // 🧩 Dedup: ตอบ Bitmap ก่อน Stream เริ่ม (ไม่มี Index ก็ต้องตอบ = ขอทุก Chunk)
+    let plan = match &header.dedup {
+        Some(info) => {
+            let plan = dedup::negotiate_receive(&mut stream, info, header.filesize, options.dedup.as_ref(), options.timeouts.io).await?;
+            info!("Dedup: reusing {} of {} bytes", plan.reused_bytes(), header.filesize);
+            Some(plan)
+        }
+        None => None,
+    };
+
+    let context = TransferContext {
+        task_id: task_id.clone(),
+        direction: Direction::Receive,
+        filename: header.filename.clone(),
+        filesize: header.filesize,
+        peer: header.sender_name.clone(),
+        metadata: header.metadata.clone(),
+    };
+
+    let collector = StatsCollector::new(options.stats.clone(), options.transport, 0);
+    collector.begin();
     let tid = task_id.clone();
     let cb = callback.clone();
-    
-    match copy_pipeline(decoder, &mut buffered_file, header.filesize, move |c, t| cb.on_progress(&tid, c, t)).await {
+    let observer = collector.clone();
+    let progress = move |c, t, rate: Throughput| {
+        observer.observe(c, &rate);
+        cb.on_progress_ex(&tid, offset + c, offset + t, &rate);
+    };
+    let stalled = |d: Duration| callback.on_stalled(&task_id, d.as_millis() as u64);
+    let remaining = header.filesize - offset;
+
+    let result = match lanes {
+        Some(lanes) => {
+            let streams = header.parallel.map_or(1, |p| p.streams);
+            info!("Receiving '{}' over {} parallel streams", header.filename, streams);
+            let lane_limit = peer_pref.max_bytes_per_sec.map(|b| (b / streams as u64).max(1));
+            let decode = |lane: DynStream, len: u64| decoder(options.sandbox.as_deref(), collector.count_wire(Throttled::new(lane, lane_limit)), algo, len);
+            let received = parallel::receive(lanes, &temp_path, header.filesize, decode, options.io_priority, options.tunables.io_buffer_size(), progress, stalled, options.timeouts, transfer.signal()).await;
+            // ยืนยันบน Stream หลักว่าได้ครบทุกช่วง (ผู้ส่งถือ Stream ย่อยไว้รอ ACK นี้)
+            match received {
+                Ok(()) => timeout(options.timeouts.io, stream.write_all(&pack_ack(1, header.filesize))).await
+                    .map_err(|_| anyhow::anyhow!("Completion ACK timeout")).and_then(|r| Ok(r?)),
+                Err(e) => Err(e),
+            }
+        }
+        None => {
+            let stream = options.middleware.wrap(&context, Box::new(stream));
+            let decoder = decoder(options.sandbox.as_deref(), collector.count_wire(Throttled::new(stream, peer_pref.max_bytes_per_sec)), algo, remaining)?;
+            match (&plan, &delta) {
+                (Some(plan), _) => dedup::assemble(decoder, &mut sink, plan, progress, options.timeouts.io, transfer.signal()).await,
+                (None, Some((basis, sig))) => delta::apply(decoder, &mut sink, basis, sig, header.filesize, progress, options.timeouts.io, transfer.signal()).await,
+                (None, None) => copy_pipeline(decoder, &mut sink, remaining, progress, stalled, options.timeouts, transfer.signal()).await,
+            }
+        }
+    };
+    match result {
         Ok(_) => {
-            buffered_file.flush().await?;
-            let inner = buffered_file.into_inner(); inner.sync_all().await?;
-            tokio_fs::rename(&temp_path, &final_path).await?;
-            callback.on_complete(&task_id, &final_path.to_string_lossy());
+            let mut inner = sink.finish().await?;
+            // ไฟล์ถูกจองไว้เต็มขนาด -> ตัดให้เหลือเท่าที่เขียนจริง (Stream หลัก, Parallel เขียนผ่าน Handle ของตัวเองจนครบ)
+            if header.parallel.is_none() {
+                let written = inner.stream_position().await?;
+                if written != header.filesize { inner.set_len(written).await?; }
+            }
+            // ปิด Handle ก่อนย้าย (Windows ย้ายไฟล์ที่เปิดอยู่ไม่ได้)
+            drop(inner);
+            // 🎫 ผ่านมาด้วย Session -> เนื้อไฟล์ต้องตรง SHA-256 ใน Manifest ไม่งั้นทิ้งทั้ง Session
+            if let Some(Some(expected)) = &approved {
+                let p = temp_path.clone();
+                let actual = tokio::task::spawn_blocking(move || utils::sha256_file(&p)).await??;
+                if !actual.eq_ignore_ascii_case(expected) {
+                    let _ = tokio_fs::remove_file(&temp_path).await;
+                    tracing::warn!("Aborting session from '{}': '{}' does not match its manifest hash", header.sender_name, header.filename);
+                    for id in options.sessions.abort(&batch_key) {
+                        if id != task_id { options.transfers.cancel(&id); }
+                    }
+                    callback.on_reject(&task_id, &format!("{}: '{}' hash mismatch", REJECT_MANIFEST, header.filename));
+                    return Ok(());
+                }
+            }
+            // 🧱 Middleware ไม่ผ่าน -> ไม่ย้ายเข้าที่ ทิ้ง .part
+            if let Err(reason) = options.middleware.after_receive(&context, &temp_path).await {
+                let _ = tokio_fs::remove_file(&temp_path).await;
+                tracing::warn!("Dropping '{}' from '{}': {}", header.filename, header.sender_name, reason);
+                callback.on_reject(&task_id, &reason);
+                return Ok(());
+            }
+            utils::move_file(&temp_path, &final_path).await?;
+            // ⏱️ mtime / Permission ของต้นทาง (ไฟล์ในชุดเดียวกันก็ผ่านตรงนี้ทีละไฟล์)
+            let (mtime, mode) = (local_mtime(header.modified_at, clock_skew_ms, received_at), header.mode);
+            let restored = final_path.clone();
+            if let Ok(Err(e)) = tokio::task::spawn_blocking(move || utils::restore_metadata(&restored, mtime, mode)).await {
+                tracing::debug!("Could not preserve file metadata: {}", e);
+            }
+            // ไฟล์ที่เข้ากฎ Save Rule ไปอยู่โฟลเดอร์ของมันแล้ว ไม่รวมเข้า Archive
+            let archive_mode = if target.matched { ArchiveMode::Off } else { options.archive_mode };
+            let delivered = match archive_mode {
+                ArchiveMode::Off => {
+                    // ไฟล์ที่ถูกรวมเข้า Archive ไม่มี Path ให้อ้างถึง จึง Index เฉพาะไฟล์ปกติ
+                    if let Some(index) = &options.dedup {
+                        if let Err(e) = index.index_file(&final_path).await {
+                            tracing::warn!("Failed to index received file: {}", e);
+                        }
+                    }
+                    final_path
+                }
+                mode => match archive::archive_received(&save_path, &header.sender_name, &final_path, mode).await {
+                    Ok(archive_path) => archive_path,
+                    Err(e) => {
+                        // เก็บไฟล์ไว้ตามปกติ ไม่ให้ Transfer ล้มเพราะ Archive
+                        tracing::warn!("Archive-on-receive failed, keeping loose file: {}", e);
+                        final_path
+                    }
+                },
+            };
+            let entry = HistoryEntry {
+                task_id: task_id.clone(),
+                filename: header.filename.clone(),
+                filesize: header.filesize,
+                sender_name: header.sender_name.clone(),
+                sent_at: header.sent_at,
+                received_at,
+                completed_at: utils::timestamp_millis(),
+                clock_skew_ms,
+            };
+            if let Err(e) = history::append(&state_dir, &entry).await {
+                tracing::warn!("Failed to record history: {}", e);
+            }
+            if let Some(fp) = peer_fingerprint.as_deref().filter(|_| !header.guest) {
+                options.peer_caps.record(fp, None, |caps| { caps.accepted_at.get_or_insert(entry.completed_at); });
+            }
+            let stats = collector.finish(&task_id, algo);
+            callback.on_complete_with_stats(&task_id, &delivered.to_string_lossy(), &stats, &header.metadata);
+            if !options.post_receive.is_empty() {
+                tokio::spawn(run_post_receive(options.post_receive.clone(), callback.clone(), task_id.clone(), delivered, header.clone()));
+            }
             Ok(())
         },
         Err(e) => {
+            // 🔁 หลุดกลางทาง -> เก็บส่วนที่เขียนแล้วไว้ให้ผู้ส่งต่อ (ต้องรู้ว่าเป็นผู้ส่งคนเดิม + ผู้ส่งเข้าใจ offset)
+            let resumable = !transfer.token().is_cancelled() && plan.is_none() && delta.is_none() && header.parallel.is_none()
+                && header.has_capability(CAP_RESUME) && resume::connection_lost(&e);
+            if let (true, Some(fp), Some(transfer_id)) = (resumable, peer_fingerprint.as_deref(), header.transfer_id.as_deref()) {
+                if let Ok(mut file) = sink.finish().await {
+                    // ขนาดไฟล์ = ที่จองไว้ ไม่ใช่ที่เขียนแล้ว -> ใช้ตำแหน่งเขียนล่าสุด
+                    let written = file.stream_position().await.unwrap_or(0);
+                    let parked = Parked {
+                        task_id: task_id.clone(),
+                        filename: header.filename.clone(),
+                        filesize: header.filesize,
+                        final_path,
+                        temp_path,
+                        offset: written.min(header.filesize),
+                        approved,
+                    };
+                    let (cb, error) = (callback.clone(), e.to_string());
+                    options.resume.park(fp, transfer_id, parked, move |id| cb.on_error(id, &error));
+                    return Ok(());
+                }
+            }
             let _ = tokio_fs::remove_file(&temp_path).await;
+            if transfer.token().is_cancelled() {
+                callback.on_reject(&task_id, REJECT_CANCELLED);
+                return Ok(());
+            }
             Err(e)
         }
     }
 }
 
+// 🎫 Manifest ของชุด: ถาม User ครั้งเดียวด้วยสรุป (จำนวนไฟล์ / ขนาดรวม) แล้วจำไว้ให้ไฟล์ในชุดผ่านได้เลย
+#[allow(clippy::too_many_arguments)]
+async fn handle_session_offer<S, CB>(
+    mut stream: S,
+    mut offer: SessionOffer,
+    state_dir: &str,
+    callback: &CB,
+    pending_map: &Mutex<HashMap<String, mpsc::UnboundedSender<UserResponse>>>,
+    options: &ReceiveOptions,
+    peer_fingerprint: Option<&str>,
+    peer_addr: std::net::SocketAddr,
+) -> anyhow::Result<()>
+where S: DataStream, CB: TransferCallback
+{
+    let key = format!("{}|{}", peer_fingerprint.unwrap_or(&offer.sender_name), offer.batch_id);
+    // 🪞 Mirror: เหลือเฉพาะไฟล์ที่ยังไม่มี / เปลี่ยนไป (ตรงกันหมด = ไม่ต้องถาม)
+    let mirror = match offer.mirror.as_deref() {
+        Some(label) => {
+            if !session::is_valid_mirror_label(label) || offer.files.iter().any(|f| session::mirror_path(&f.name).is_none()) {
+                tracing::warn!("Declining mirror '{}' from '{}': invalid path", label, offer.sender_name);
+                session::respond(&mut stream, SessionDecision::Declined, None, options.timeouts.io).await?;
+                let _ = stream.shutdown().await;
+                return Ok(());
+            }
+            let dest = Path::new(&options.download_dir.get()).join(label);
+            let (dir, files) = (dest.clone(), std::mem::take(&mut offer.files));
+            offer.files = tokio::task::spawn_blocking(move || session::mirror_missing(&dir, files)).await?;
+            if offer.files.is_empty() {
+                info!("Mirror '{}' from '{}' is already up to date", label, offer.sender_name);
+                session::respond(&mut stream, SessionDecision::Approved, Some(Vec::new()), options.timeouts.io).await?;
+                let _ = stream.shutdown().await;
+                return Ok(());
+            }
+            Some(dest)
+        }
+        None => None,
+    };
+    let auto_accept = peer_override(options, peer_fingerprint, &offer.sender_name, peer_addr).auto_accept;
+    let decision = if let Some(reason) = options.availability.get().reject_reason() {
+        info!("Declining session of {} files from '{}': {}", offer.files.len(), offer.sender_name, reason);
+        SessionDecision::Declined
+    } else if auto_accept.unwrap_or_else(|| !options.guest && security::is_trusted(state_dir, &offer.sender_name)) {
+        SessionDecision::Approved
+    } else if options.approval_webhook.get().is_some() {
+        // Webhook ตัดสินจาก FileHeader ทีละไฟล์อยู่แล้ว (ไม่มีคนนั่งกด Prompt)
+        SessionDecision::PerFile
+    } else {
+        let task_id = uuid::Uuid::new_v4().to_string();
+        let summary = match offer.mirror.as_deref() {
+            Some(label) => format!("{} files → {}", offer.files.len(), label),
+            None => format!("{} files", offer.files.len()),
+        };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        if let Ok(mut map) = pending_map.lock() { map.insert(task_id.clone(), tx); }
+        let _ = callback.ask_accept_file(&task_id, &summary, offer.total_bytes(), &offer.sender_name, &offer.sender_device, Some(&offer.batch_id), None, None, &Metadata::new());
+        let response = timeout(decision_window(options.timeouts.user_decision), rx.recv()).await;
+        if let Ok(mut map) = pending_map.lock() { map.remove(&task_id); }
+        match response {
+            Ok(Some(UserResponse::Accept)) => SessionDecision::Approved,
+            Err(_) => { callback.on_reject(&task_id, "Timeout"); SessionDecision::Declined }
+            _ => { callback.on_reject(&task_id, "User Rejected"); SessionDecision::Declined }
+        }
+    };
+    if decision == SessionDecision::Approved {
+        info!("Session of {} files from '{}' approved", offer.files.len(), offer.sender_name);
+        options.sessions.approve(key, offer.files.clone(), mirror.clone());
+    }
+    let needed = mirror.map(|_| offer.files.into_iter().map(|f| f.name).collect());
+    session::respond(&mut stream, decision, needed, options.timeouts.io).await?;
+    let _ = stream.shutdown().await;
+    Ok(())
+}
+
+// 📤 Peer ขอไฟล์จาก Share (ShareCommand::Request) -> ส่งกลับทาง Connection เดิมด้วย handle_sending (Event ฝั่งเราเหมือนส่งเอง)
+#[allow(clippy::too_many_arguments)]
+async fn send_requested<S, CB>(
+    mut stream: S,
+    id: &str,
+    peer_algos: &str,
+    callback: CB,
+    options: &ReceiveOptions,
+    peer_fingerprint: Option<&str>,
+    peer_addr: std::net::SocketAddr,
+    cancel: CancellationToken,
+) -> anyhow::Result<()>
+where S: DataStream, CB: TransferCallback + Clone + 'static
+{
+    let Some(fp) = peer_fingerprint else {
+        tracing::warn!("Rejected file request from peer without identity");
+        return shares::answer_request(&mut stream, None).await;
+    };
+    let ctx = &options.shares;
+    let Some(file) = ctx.requested_file(id, fp) else {
+        tracing::warn!("Share request denied: '{}' (fingerprint: {})", id, fp);
+        return shares::answer_request(&mut stream, None).await;
+    };
+    let task_id = uuid::Uuid::new_v4().to_string();
+    let mut context = TransferContext {
+        task_id: task_id.clone(),
+        direction: Direction::Send,
+        filename: file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        filesize: tokio_fs::metadata(&file).await?.len(),
+        peer: fp.to_string(),
+        metadata: Metadata::new(),
+    };
+    if let Err(reason) = options.middleware.before_send(&mut context).await {
+        tracing::warn!("Share request for '{}' blocked: {}", id, reason);
+        return shares::answer_request(&mut stream, None).await;
+    }
+    info!("📤 Share request: '{}' for {} ({})", id, peer_addr, fp);
+    shares::answer_request(&mut stream, Some(&context.filename)).await?;
+    let algo = compression::negotiate(Some(&compression::parse_algo_list(peer_algos)), None, None);
+    let transfer = options.transfers.register(&cancel, &task_id);
+    let stats = StatsCollector::new(options.stats.clone(), options.transport, 0);
+    let params = SendParams { io_priority: options.io_priority, middleware: options.middleware.clone(), guest: options.guest, timeouts: options.timeouts, ..SendParams::default() };
+    handle_sending(stream, file.to_string_lossy().to_string(), task_id, callback, ctx.node_name.clone(), algo, context, params, transfer.signal().clone(), stats).await
+}
+
+// ไม่ต้องมี Identity / Whitelist: รู้ Code = ได้ไฟล์ของ Code นั้น (Task ID ฝั่งเรา = "code:<code>")
+async fn send_code<S, CB>(
+    mut stream: S,
+    claim: CodeClaim,
+    callback: CB,
+    options: &ReceiveOptions,
+    peer_fingerprint: Option<&str>,
+    peer_addr: std::net::SocketAddr,
+    cancel: CancellationToken,
+) -> anyhow::Result<()>
+where S: DataStream, CB: TransferCallback + Clone + 'static
+{
+    let verified = match codes::verify(&mut stream, &claim, &options.codes, options.timeouts.io).await? {
+        Claimed::Verified(verified) => verified,
+        Claimed::Wrong(code) => {
+            tracing::warn!("Wrong code for nameplate {} from {}, code closed", claim.nameplate, peer_addr);
+            callback.on_reject(&format!("{}{}", codes::TASK_PREFIX, code), codes::REJECT_WRONG_CODE);
+            return Ok(());
+        }
+        Claimed::Unknown => return Ok(()),
+    };
+    let task_id = format!("{}{}", codes::TASK_PREFIX, verified.code);
+    let filesize = match tokio_fs::metadata(&verified.path).await {
+        Ok(meta) => meta.len(),
+        Err(e) => {
+            codes::refuse(&mut stream, "File unavailable", options.timeouts.io).await?;
+            callback.on_reject(&task_id, &format!("Cannot read '{}': {}", verified.path.display(), e));
+            return Ok(());
+        }
+    };
+    let mut context = TransferContext {
+        task_id: task_id.clone(),
+        direction: Direction::Send,
+        filename: verified.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        filesize,
+        peer: peer_fingerprint.map_or_else(|| peer_addr.ip().to_string(), str::to_string),
+        metadata: Metadata::new(),
+    };
+    if let Err(reason) = options.middleware.before_send(&mut context).await {
+        tracing::warn!("Code transfer of '{}' blocked: {}", context.filename, reason);
+        codes::refuse(&mut stream, &reason, options.timeouts.io).await?;
+        callback.on_reject(&task_id, &reason);
+        return Ok(());
+    }
+    info!("🎟️ Code {} claimed by {}", claim.nameplate, peer_addr);
+    verified.accept(&mut stream, &context.filename, options.timeouts.io).await?;
+    let algo = compression::negotiate(Some(&compression::parse_algo_list(&claim.compression)), None, None);
+    let transfer = options.transfers.register(&cancel, &task_id);
+    let stats = StatsCollector::new(options.stats.clone(), options.transport, 0);
+    let params = SendParams { io_priority: options.io_priority, middleware: options.middleware.clone(), guest: options.guest, timeouts: options.timeouts, ..SendParams::default() };
+    handle_sending(stream, verified.path.to_string_lossy().to_string(), task_id, callback, options.shares.node_name.clone(), algo, context, params, transfer.signal().clone(), stats).await
+}
+
+// แปลง mtime ของผู้ส่งเป็นเวลาเครื่องเรา: หัก Skew ที่เพี้ยนชัดเจน และไม่ให้อยู่ในอนาคต
+fn local_mtime(modified_at: Option<u64>, clock_skew_ms: Option<i64>, now_ms: u64) -> Option<SystemTime> {
+    let mut mtime = modified_at? as i64;
+    if let Some(skew) = clock_skew_ms.filter(|s| s.abs() > CLOCK_SKEW_WARN_MS) {
+        mtime -= skew;
+    }
+    let mtime = mtime.clamp(0, now_ms as i64) as u64;
+    Some(UNIX_EPOCH + Duration::from_millis(mtime))
+}
+
+// TcpStream จริงของ Plain TCP (ห่อด้วย Throttled ที่ไม่จำกัด Rate ได้) TLS / QUIC / WebRTC = None
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn raw_tcp<S: DataStream>(stream: &mut S) -> Option<&mut tokio::net::TcpStream> {
+    let any = stream.as_any_mut();
+    let inner = match any.is::<Throttled<DynStream>>() {
+        true => any.downcast_mut::<Throttled<DynStream>>()?.unthrottled_mut()?,
+        false => any.downcast_mut::<DynStream>()?,
+    };
+    inner.as_mut().as_any_mut().downcast_mut()
+}
+
+// ไฟล์ถึงเกณฑ์ mmap -> อ่านผ่าน Mapping (Map ไม่ได้ = อ่านแบบเดิม)
+fn file_source(file: AsyncFile, io_priority: IoPriority, mmap_threshold: Option<u64>, total_size: u64, offset: u64) -> FileSource {
+    if io_priority == IoPriority::Normal && mmap_threshold.is_some_and(|t| total_size >= t) {
+        match MappedFile::open(&file, total_size, offset) {
+            Ok(mapped) => return FileSource::Mapped(mapped),
+            Err(e) => tracing::warn!("mmap failed, falling back to buffered reads: {}", e),
+        }
+    }
+    FileSource::new(file, io_priority, IO_BUFFER_SIZE)
+}
+
+/// ค่าที่ไม่บังคับของ handle_sending (Default = ส่งไฟล์เดียวแบบธรรมดา ไม่มี Option พิเศษ)
+#[derive(Default)]
+pub struct SendParams {
+    pub compression_level: Option<i32>,
+    pub io_priority: IoPriority,
+    // ไฟล์ขนาดนี้ขึ้นไปอ่านผ่าน mmap (None = ปิด)
+    pub mmap_threshold: Option<u64>,
+    pub use_dedup: bool,
+    // Some = แบ่งส่งหลาย Stream หลัง ACK (ผู้เรียกเช็คขนาด / Transport / Capability ของ Peer แล้ว)
+    pub parallel: Option<ParallelSend>,
+    // ต่อไฟล์เดิมหลัง Connection หลุด (ผู้รับตอบ offset ที่มีแล้วใน ACK)
+    pub resuming: bool,
+    pub expires_in: Option<Duration>,
+    pub control_port: Option<u16>,
+    pub batch: Option<BatchInfo>,
+    pub thumbnail: Option<Thumbnail>,
+    // แนบ SHA-256 ใน Header (ผู้รับประกาศ reputation::FEATURE) ต้องอ่านทั้งไฟล์ก่อนเสนอ
+    pub offer_sha256: bool,
+    pub middleware: Arc<MiddlewareChain>,
+    pub guest: bool,
+    pub timeouts: Timeouts,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_sending<S>(
     mut stream: S,
     path: String,
     task_id: String,
     callback: impl TransferCallback + Clone + 'static,
     my_device_name: String,
-    target_os: Option<String>,
+    compression_algo: CompressionAlgo,
+    // ผ่าน before_send ของ Middleware แล้ว (metadata = key-value ของแอปผู้เรียก ไม่ใช่ Metadata ของไฟล์)
+    context: TransferContext,
+    params: SendParams,
+    signal: TransferSignal,
+    stats: Arc<StatsCollector>,
 ) -> anyhow::Result<()> 
 where S: DataStream
 {
-    let file = AsyncFile::open(&path).await.context("Failed to open source file")?;
+    let SendParams { compression_level, io_priority, mmap_threshold, use_dedup, parallel, resuming, expires_in, control_port, batch, thumbnail, offer_sha256, middleware, guest, timeouts } = params;
+    check_metadata(&context.metadata)?;
+    if expires_in.is_some_and(|d| d.is_zero()) { bail!("expires_in must be greater than zero"); }
+    let mut file = AsyncFile::open(&path).await.context("Failed to open source file")?;
     let metadata = file.metadata().await?;
     let total_size = metadata.len();
-    let filename = std::path::Path::new(&path).file_name().unwrap().to_string_lossy().to_string();
-    
-    // 🔥 FIXED: เลือกโหมดการส่ง (ถ้าเป็น iOS ให้ส่งสด)
-    let compression_algo = match target_os.as_deref() {
-        Some("ios") => CompressionAlgo::None, // ส่งสด (Raw)
-        _ => CompressionAlgo::Zstd,           // ส่ง Zstd (Default)
+    // ชื่อไฟล์ / ชื่อที่ผู้ส่งตั้งให้ (SendOptions::name)
+    let filename = context.filename.clone();
+
+    // 🔥 ไฟล์ที่บีบมาแล้ว (mp4/zip/jpg/...) ส่งสดดีกว่า
+    let compression_algo = if compression_algo != CompressionAlgo::None && compression::looks_incompressible(std::path::Path::new(&path)).await {
+        CompressionAlgo::None
+    } else {
+        compression_algo
     };
+    
+    info!("Sending '{}' (Mode: {:?})", filename, compression_algo);
 
-    info!("Sending '{}' to {:?} (Mode: {:?})", filename, target_os, compression_algo);
+    let hashes = match use_dedup {
+        true => Some(dedup::hash_chunks(&path, dedup::CHUNK_SIZE).await?),
+        false => None,
+    };
+    let sha256 = match offer_sha256 && total_size <= OFFER_HASH_MAX_SIZE {
+        true => {
+            let p = std::path::PathBuf::from(&path);
+            tokio::task::spawn_blocking(move || utils::sha256_file(&p)).await.ok().and_then(Result::ok)
+        }
+        false => None,
+    };
+    // ต่อไฟล์ที่หลุด = ผู้รับไม่เทียบซ้ำอยู่แล้ว
+    let quick_hash = match total_size > 0 && !resuming {
+        true => {
+            let p = std::path::PathBuf::from(&path);
+            tokio::task::spawn_blocking(move || duplicate::quick_hash(&p)).await.ok().and_then(Result::ok)
+        }
+        false => None,
+    };
 
+    let thumbnail = thumbnail.filter(|_| thumbnail::is_media(&filename));
     let header = FileHeader { 
         filename, 
         filesize: total_size, 
         sender_name: my_device_name, 
         sender_device: env::consts::OS.to_string(),
-        compression: Some(compression_algo.as_str().to_string())
+        compression: Some(compression_algo.as_str().to_string()),
+        sent_at: Some(utils::timestamp_millis()),
+        modified_at: metadata.modified().ok().and_then(utils::system_time_millis),
+        mode: utils::permission_bits(&metadata),
+        dedup: hashes.as_ref().map(|h| DedupInfo { chunk_size: dedup::CHUNK_SIZE, chunk_count: h.len() as u64 }),
+        expires_in_ms: expires_in.map(|d| d.as_millis() as u64),
+        sha256,
+        quick_hash,
+        transfer_id: Some(task_id.clone()),
+        control_port,
+        protocol_version: PROTOCOL_VERSION,
+        min_protocol_version: MIN_PROTOCOL_VERSION,
+        capabilities: LOCAL_CAPABILITIES,
+        batch,
+        thumbnail,
+        guest,
+        parallel: parallel.as_ref().map(|p| p.plan),
+        resuming,
+        metadata: context.metadata.clone(),
     };
     
     let json = serde_json::to_vec(&header).context("Failed to serialize header")?;
@@ -159,27 +1030,137 @@ where S: DataStream
     stream.write_all(&json).await?;
 
     let mut ack = vec![0u8; ACK_SIZE];
-    match timeout(USER_DECISION_TIMEOUT, stream.read_exact(&mut ack)).await {
+    // ผู้รับถอนข้อเสนอเองเมื่อหมดอายุ (ACK_EXPIRED) -> รอเกินอายุไปแค่เผื่อ Network
+    let ack_wait = expires_in.map_or(timeouts.user_decision, |d| d + ACK_DEADLINE_MARGIN);
+    let acked = tokio::select! {
+        r = timeout(ack_wait, stream.read_exact(&mut ack)) => r,
+        _ = signal.cancelled() => { callback.on_reject(&task_id, REJECT_CANCELLED); return Ok(()); }
+    };
+    match acked {
         Ok(Ok(_)) => {},
-        _ => { callback.on_reject(&task_id, "Timeout"); return Ok(()); }
+        _ => {
+            callback.on_reject(&task_id, if expires_in.is_some() { REJECT_EXPIRED } else { "Timeout" });
+            return Ok(());
+        }
     };
+    // ผู้รับไม่มี .part ให้ต่อแล้ว -> จบด้วย Error (ไม่ใช่ผู้รับกดปฏิเสธ)
+    if ack[0] == 0 && resuming { bail!("Connection lost and the receiver could not resume '{}'", header.filename); }
     if ack[0] == 0 { callback.on_reject(&task_id, "Receiver Rejected"); return Ok(()); }
+    if ack[0] == ACK_EXPIRED { callback.on_reject(&task_id, REJECT_EXPIRED); return Ok(()); }
+    if ack[0] == ACK_DUPLICATE { callback.on_duplicate(&task_id, &header.filename, None); return Ok(()); }
+    if ack[0] == ACK_INCOMPATIBLE {
+        let (_, receiver_version) = unpack_ack(&ack)?;
+        callback.on_reject(&task_id, &incompatible_reason(receiver_version, &header));
+        return Ok(());
+    }
+    if ack[0] == ACK_UNAVAILABLE {
+        let (_, code) = unpack_ack(&ack)?;
+        callback.on_reject(&task_id, Availability::from_code(code).reject_reason().unwrap_or(REJECT_BUSY));
+        return Ok(());
+    }
+
+    let offset = match resuming {
+        true => unpack_ack(&ack)?.1,
+        false => 0,
+    };
+    if offset > total_size { bail!("Receiver asked to resume '{}' past its end", header.filename); }
+    if offset > 0 {
+        info!("Resuming '{}' at {} of {} bytes", header.filename, offset, total_size);
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+    }
+    // รอบที่ต่อไฟล์ = Transfer เดิม (on_start ไปแล้วตั้งแต่รอบแรก)
+    if !resuming { callback.on_start(&task_id, &header.filename); }
 
-    callback.on_start(&task_id, &header.filename);
+    let needed = match &hashes {
+        Some(h) => Some(dedup::negotiate_send(&mut stream, h, timeouts.io).await?),
+        None => None,
+    };
+    // 🧬 ผู้รับมีเวอร์ชันเก่า -> หา Block ที่ตรงกันก่อนเริ่ม Stream (อ่านไฟล์ทั้งไฟล์หนึ่งรอบ)
+    let ops = match ack[0] {
+        ACK_DELTA if parallel.is_some() || hashes.is_some() || offset > 0 => bail!("Receiver asked for a delta of '{}' it cannot use", header.filename),
+        ACK_DELTA => {
+            let sig = delta::Signature::receive(&mut stream, unpack_ack(&ack)?.1, timeouts.io).await?;
+            Some(delta::plan(&path, sig).await?)
+        }
+        _ => None,
+    };
 
-    // 🔥 ใช้ Compressor Factory
-    let mut encoder = Compressor::new(stream, compression_algo);
+    stats.begin();
     let tid = task_id.clone();
     let cb = callback.clone();
-    
-    copy_pipeline(
-        BufReader::with_capacity(IO_BUFFER_SIZE, file), 
-        &mut encoder, 
-        total_size, 
-        move |c, t| cb.on_progress(&tid, c, t)
-    ).await?;
+    let observer = stats.clone();
+    let progress = move |c, t, rate: Throughput| {
+        observer.observe(c, &rate);
+        cb.on_progress_ex(&tid, offset + c, offset + t, &rate);
+    };
+    let stalled = |d: Duration| callback.on_stalled(&task_id, d.as_millis() as u64);
+
+    // 🛤️ ข้อมูลไปทาง Stream ย่อยทั้งหมด Stream หลักแค่ปิดท้ายหลังทุกช่วงส่งครบ
+    if let Some(lanes) = parallel {
+        info!("Sending '{}' over {} parallel streams", header.filename, lanes.plan.streams);
+        drop(file);
+        let lanes = match parallel::send(lanes, &path, &task_id, total_size, compression_algo, compression_level, io_priority, IO_BUFFER_SIZE, &stats, progress, stalled, timeouts, &signal).await {
+            Ok(lanes) => lanes,
+            Err(_) if signal.is_cancelled() => { callback.on_reject(&task_id, REJECT_CANCELLED); return Ok(()); }
+            Err(e) => return Err(e),
+        };
+        // ผู้รับเขียนครบทุกช่วงแล้วตอบ ACK บน Stream หลัก -> ค่อยปล่อย Stream ย่อย
+        let mut done = vec![0u8; ACK_SIZE];
+        match signal.timeout(timeouts.io, stream.read_exact(&mut done)).await {
+            Some(Ok(_)) if done[0] == 1 => {}
+            _ if signal.is_cancelled() => { callback.on_reject(&task_id, REJECT_CANCELLED); return Ok(()); }
+            _ => bail!("Receiver did not confirm the parallel transfer"),
+        }
+        drop(lanes);
+        let _ = stream.shutdown().await;
+        callback.on_complete_with_stats(&task_id, "Success", &stats.finish(&task_id, compression_algo), &header.metadata);
+        return Ok(());
+    }
+
+    // 🚀 Plain TCP + ไม่บีบอัด -> Kernel ส่งจาก Page Cache ตรง (ไม่ผ่าน Channel / Buffer ของ copy_pipeline)
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if compression_algo == CompressionAlgo::None && needed.is_none() && ops.is_none() && io_priority == IoPriority::Normal && !middleware.wraps_stream() {
+        if let Some(socket) = raw_tcp(&mut stream) {
+            info!("Zero-copy send for '{}'", header.filename);
+            if let Err(e) = sendfile_pipeline(&file, offset, socket, total_size - offset, progress, stalled, timeouts, &signal).await {
+                if signal.is_cancelled() {
+                    callback.on_reject(&task_id, REJECT_CANCELLED);
+                    return Ok(());
+                }
+                return Err(e);
+            }
+            stats.add_wire(total_size - offset);
+            stream.shutdown().await?;
+            callback.on_complete_with_stats(&task_id, "Success", &stats.finish(&task_id, compression_algo), &header.metadata);
+            return Ok(());
+        }
+    }
+
+    // 🔥 ใช้ Compressor Factory
+    let stream = middleware.wrap(&context, Box::new(stream));
+    let mut encoder = Compressor::with_level(stats.count_wire(stream), compression_algo, compression_level);
+    let sent = match (needed, ops) {
+        (Some(needed), _) => {
+            let (reader, needed_bytes) = dedup::needed_chunks_reader(file, needed, dedup::CHUNK_SIZE, total_size);
+            info!("Dedup: sending {} of {} bytes", needed_bytes, total_size);
+            copy_pipeline(reader, &mut encoder, needed_bytes, progress, stalled, timeouts, &signal).await
+        }
+        (None, Some(ops)) => {
+            info!("Delta: sending {} of {} bytes", delta::literal_bytes(&ops), total_size);
+            let (reader, wire_bytes) = delta::ops_reader(file, ops);
+            copy_pipeline(reader, &mut encoder, wire_bytes, progress, stalled, timeouts, &signal).await
+        }
+        (None, None) => copy_pipeline(file_source(file, io_priority, mmap_threshold, total_size, offset), &mut encoder, total_size - offset, progress, stalled, timeouts, &signal).await,
+    };
+    if let Err(e) = sent {
+        if signal.is_cancelled() {
+            callback.on_reject(&task_id, REJECT_CANCELLED);
+            return Ok(());
+        }
+        return Err(e);
+    }
     
     encoder.shutdown().await?;
-    callback.on_complete(&task_id, "Success");
+    callback.on_complete_with_stats(&task_id, "Success", &stats.finish(&task_id, compression_algo), &header.metadata);
     Ok(())
 }
\ No newline at end of file
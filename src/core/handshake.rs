@@ -1,19 +1,38 @@
-use btleplug::api::{Central, Manager as _, Peripheral, ScanFilter, WriteType};
-use btleplug::platform::Manager;
+use btleplug::api::{Central, Manager as _, Peripheral, ScanFilter, ValueNotification, WriteType};
+use btleplug::platform::{Manager, Peripheral as PlatformPeripheral};
+use futures::stream::{Stream, StreamExt};
+use serde::{Serialize, Deserialize};
 use uuid::Uuid;
 use log::{info, error, warn};
+use std::pin::Pin;
 use std::time::Duration;
 use tokio::time;
 
 // UUID ของ "กล่องจดหมาย" (Characteristic) ที่เราสร้างใน iPad
 const HANDSHAKE_CHAR_UUID: &str = "0000d7eb-0000-1000-8000-00805f9b34fb";
+// อีกฝั่งตอบกลับผ่าน Notify (แอปรุ่นเก่าไม่มี -> ได้แค่ส่งข้อมูลเราไป)
+const REPLY_CHAR_UUID: &str = "0000d7ec-0000-1000-8000-00805f9b34fb";
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
 
-pub async fn connect_and_say_hello(mac_address: String) -> anyhow::Result<()> {
-    info!("🔗 Initiating handshake with: {}", mac_address);
+/// ข้อมูลที่แลกกันตอน Bootstrap ทาง BLE (JSON) พอให้อีกฝั่งต่อ LAN ได้ทันทีโดยไม่ต้องรอ mDNS
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ConnectionInfo {
+    pub name: String,
+    #[serde(default)]
+    pub ip: Option<String>,
+    #[serde(default)]
+    pub port: u16,
+    #[serde(default)]
+    pub ssid: Option<String>,
+    // Fingerprint ของ Cert ที่ใช้บน LAN (ไว้ให้ User เทียบกับตอน TOFU)
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+}
 
+pub(crate) async fn find_and_connect(mac_address: &str) -> anyhow::Result<PlatformPeripheral> {
     let manager = Manager::new().await?;
     let adapters = manager.adapters().await?;
-    let central = adapters.into_iter().nth(0).ok_or(anyhow::anyhow!("No BLE Adapter"))?;
+    let central = adapters.into_iter().next().ok_or(anyhow::anyhow!("No BLE Adapter"))?;
 
     // 1. ลองหาใน Cache ก่อน
     let mut peripherals = central.peripherals().await?;
@@ -75,32 +94,72 @@ pub async fn connect_and_say_hello(mac_address: String) -> anyhow::Result<()> {
     }
 
     info!("✅ Connected! Discovering services...");
+    Ok(device)
+}
+
+// Notification อาจมาเป็นหลายก้อนตาม MTU -> ต่อกันจน Parse JSON ได้
+async fn read_reply(mut notifications: Pin<Box<dyn Stream<Item = ValueNotification> + Send>>, uuid: Uuid) -> Option<ConnectionInfo> {
+    let mut buf = Vec::new();
+    time::timeout(REPLY_TIMEOUT, async {
+        while let Some(n) = notifications.next().await {
+            if n.uuid != uuid { continue; }
+            buf.extend_from_slice(&n.value);
+            if let Ok(info) = serde_json::from_slice::<ConnectionInfo>(&buf) {
+                return Some(info);
+            }
+        }
+        None
+    }).await.ok().flatten()
+}
+
+/// ส่งข้อมูลการเชื่อมต่อของเราให้ Peer ทาง BLE แล้วรอคำตอบ (None = Peer ไม่ตอบ เช่นแอปรุ่นเก่า)
+pub async fn exchange_connection_info(mac_address: &str, ours: &ConnectionInfo) -> anyhow::Result<Option<ConnectionInfo>> {
+    info!("🔗 Initiating handshake with: {}", mac_address);
+    let device = find_and_connect(mac_address).await?;
 
     // 5. Discover Services
     device.discover_services().await?;
 
-    // 6. หา Characteristic เป้าหมาย (d7eb)
+    // 6. หา Characteristic เป้าหมาย (d7eb) และช่องตอบกลับ (d7ec)
     let chars = device.characteristics();
-    let handshake_char = chars.iter().find(|c| c.uuid == Uuid::parse_str(HANDSHAKE_CHAR_UUID).unwrap());
-
-    if let Some(c) = handshake_char {
-        info!("📬 Found Handshake Mailbox! Sending 'Hello'...");
-        
-        let data = "Hello DropTea".as_bytes().to_vec();
-        
-        // เขียนข้อมูล
-        match device.write(c, &data, WriteType::WithoutResponse).await {
-            Ok(_) => info!("🚀 Handshake Sent Successfully!"),
-            Err(e) => error!("❌ Write Failed: {}", e),
-        }
-    } else {
+    let handshake_uuid = Uuid::parse_str(HANDSHAKE_CHAR_UUID)?;
+    let reply_uuid = Uuid::parse_str(REPLY_CHAR_UUID)?;
+    let Some(handshake_char) = chars.iter().find(|c| c.uuid == handshake_uuid) else {
         error!("❌ Error: Handshake Characteristic ({}) not found on device.", HANDSHAKE_CHAR_UUID);
         device.disconnect().await?;
         return Err(anyhow::anyhow!("Characteristic not found"));
+    };
+
+    // Subscribe ก่อนเขียน ไม่งั้นคำตอบที่มาเร็วจะหลุด
+    let notifications = match chars.iter().find(|c| c.uuid == reply_uuid) {
+        Some(c) => match device.subscribe(c).await {
+            Ok(_) => Some(device.notifications().await?),
+            Err(e) => { warn!("⚠️ Subscribe to reply failed: {}", e); None }
+        },
+        None => { warn!("⚠️ Peer has no reply characteristic (older app), sending only"); None }
+    };
+
+    info!("📬 Found Handshake Mailbox! Sending connection info...");
+    let data = serde_json::to_vec(ours)?;
+    // JSON ยาวกว่า MTU -> ต้อง Write แบบ With Response (Long Write)
+    if let Err(e) = device.write(handshake_char, &data, WriteType::WithResponse).await {
+        error!("❌ Write Failed: {}", e);
+        let _ = device.disconnect().await;
+        return Err(e.into());
+    }
+    info!("🚀 Handshake Sent Successfully!");
+
+    let reply = match notifications {
+        Some(n) => read_reply(n, reply_uuid).await,
+        None => None,
+    };
+    match &reply {
+        Some(info) => info!("🤝 Handshake reply from '{}': {:?}:{}", info.name, info.ip, info.port),
+        None => warn!("⚠️ No handshake reply from {}", mac_address),
     }
 
     // Disconnect เมื่อเสร็จงาน (เพื่อไม่ให้บล็อกการเชื่อมต่ออื่น)
     let _ = device.disconnect().await;
-    
-    Ok(())
-}
\ No newline at end of file
+
+    Ok(reply)
+}
@@ -0,0 +1,738 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant, SystemTime};
+use anyhow::{bail, Context};
+use rand::RngCore;
+use tokio::runtime::Runtime;
+use tokio::sync::broadcast;
+
+use crate::core::cancel::REJECT_CANCELLED;
+use crate::core::codes;
+use crate::core::discovery::{DiscoveryInternalEvent, MockDiscovery};
+use crate::core::engine::{DropTeaCore, SendOptions, TransportMode};
+use crate::core::events::{NoopHandler, TransferEvent};
+use crate::core::hooks::{PostReceiveHook, ReceivedFile};
+use crate::core::links::{self, LinkConfig};
+use crate::core::localsend::{self, LocalSendConfig};
+use crate::core::middleware::{TransferContext, TransferMiddleware};
+use crate::core::parallel::ParallelPolicy;
+use crate::core::session::REJECT_MANIFEST;
+use crate::core::transfer::Metadata;
+use crate::core::transports::memory::MemoryNetwork;
+use crate::core::utils;
+
+// ==========================================
+// Integration Harness (feature "harness")
+// Engine สองตัวในโปรเซสเดียว คุยกันผ่าน TLS บน Loopback แล้วเล่น Scenario ทีละตัว
+// ตรวจลำดับ Event ที่แต่ละฝั่งได้ (subscribe) + เนื้อไฟล์ที่ได้จริง
+// Binary: src/bin/droptea-harness.rs -> cargo run --features harness --bin droptea-harness [--memory] [--quic | --plaintcp] [scenario...]
+// --memory = TLS บน MemoryTransport แทน TCP (ไม่ชน Port / Firewall) Discovery เป็น MockDiscovery เสมอ (ไม่ประกาศ mDNS / ไม่แตะ BLE)
+//   + Scenario roam (ตัดทุก Connection กลางไฟล์แล้วผู้ส่งต้องต่อจาก .part เดิมได้)
+// --plaintcp = ไม่มี TLS (ไฟล์ที่ไม่บีบอัดวิ่งทาง sendfile บน Linux)
+// --quic = ทั้งสองฝั่งเป็น mode "quic" / TCP เปิด Parallel ไว้ด้วย: ไฟล์ทดสอบถึงเกณฑ์ -> ส่งหลาย Stream เมื่อผู้ส่งเห็นประกาศของผู้รับ
+// ทั้งสองฝั่งเป็น Guest (ไม่แตะ Identity / Whitelist ของเครื่อง) และรันใน Temp Dir ของตัวเอง (./downloads แยกจากของจริง)
+// ==========================================
+
+const SENDER_NAME: &str = "harness-sender";
+pub const SCENARIOS: &[&str] = &["accept", "reject", "cancel", "resume", "corruption", "discovery", "speedtest", "parallel", "duplicate", "hook", "middleware", "roam", "delta", "code", "localsend", "link"];
+// ต้องตัด Connection กลางทางได้ (MemoryNetwork::drop_links) -> ไม่ระบุ Scenario ก็ข้ามไปถ้าไม่ได้ใช้ --memory
+const MEMORY_ONLY: &[&str] = &["roam"];
+
+const DEFAULT_PORT: u16 = 28181;
+// LocalSend API ของผู้รับ / ผู้ส่งอยู่ที่ port + 2 / port + 3
+const LOCALSEND_OFFSET: u16 = 2;
+// Link ของผู้รับ / ผู้ส่งอยู่ที่ port + 4 / port + 5
+const LINKS_OFFSET: u16 = 4;
+const HOOK_SCENARIO: &str = "hook";
+const MIDDLEWARE_SCENARIO: &str = "middleware";
+// ใหญ่กว่า Socket Buffer ของ Loopback -> ผู้รับ Pause แล้วผู้ส่งส่งจบเองไม่ได้
+const FILE_SIZE: usize = 16 * 1024 * 1024;
+const EVENT_WAIT: Duration = Duration::from_secs(20);
+const PAUSE_HOLD: Duration = Duration::from_secs(1);
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+const SPEED_TEST_DURATION: Duration = Duration::from_millis(500);
+
+// ล้มเฉพาะไฟล์ของ Scenario hook (แนบ SHA-256 ที่ได้มาใน Error ให้ตรวจ) ไฟล์อื่นผ่าน
+#[derive(Debug)]
+struct ScenarioHook;
+
+#[async_trait::async_trait]
+impl PostReceiveHook for ScenarioHook {
+    fn name(&self) -> &str { "harness" }
+
+    async fn run(&self, file: &ReceivedFile) -> anyhow::Result<()> {
+        if file.metadata.get("scenario").map(String::as_str) == Some(HOOK_SCENARIO) { bail!("sha256 {}", file.sha256); }
+        Ok(())
+    }
+}
+
+// ผู้ส่งประทับ Metadata "via" ทุกไฟล์ / ผู้รับทิ้งไฟล์ของ Scenario middleware (บอกค่าที่ประทับมาใน Error ให้ตรวจ)
+#[derive(Debug)]
+struct ScenarioMiddleware;
+
+#[async_trait::async_trait]
+impl TransferMiddleware for ScenarioMiddleware {
+    fn name(&self) -> &str { "harness" }
+
+    async fn before_send(&self, ctx: &mut TransferContext) -> anyhow::Result<()> {
+        ctx.metadata.insert("via".into(), SENDER_NAME.into());
+        Ok(())
+    }
+
+    async fn after_receive(&self, ctx: &TransferContext, _path: &Path) -> anyhow::Result<()> {
+        if ctx.metadata.get("scenario").map(String::as_str) == Some(MIDDLEWARE_SCENARIO) {
+            bail!("filtered {} via {}", ctx.filename, ctx.metadata.get("via").map_or("nothing", String::as_str));
+        }
+        Ok(())
+    }
+}
+
+fn task_of(event: &TransferEvent) -> Option<&str> {
+    match event {
+        TransferEvent::Error { task_id, .. }
+        | TransferEvent::Incoming { task_id, .. }
+        | TransferEvent::Started { task_id, .. }
+        | TransferEvent::Progress { task_id, .. }
+        | TransferEvent::Completed { task_id, .. }
+        | TransferEvent::Rejected { task_id, .. }
+        | TransferEvent::ClockSkew { task_id, .. }
+        | TransferEvent::Retrying { task_id, .. }
+        | TransferEvent::Stalled { task_id, .. }
+        | TransferEvent::Duplicate { task_id, .. }
+        | TransferEvent::HookFailed { task_id, .. } => Some(task_id),
+        _ => None,
+    }
+}
+
+fn is_terminal(event: &TransferEvent) -> bool {
+    matches!(event, TransferEvent::Completed { .. } | TransferEvent::Rejected { .. } | TransferEvent::Error { .. } | TransferEvent::Duplicate { .. })
+}
+
+/// Event ทั้งหมดของ Engine หนึ่งตัว + ตำแหน่งที่ Scenario ตรวจถึงแล้ว
+#[derive(Default)]
+struct EventLog {
+    events: StdMutex<Vec<TransferEvent>>,
+    cursor: StdMutex<usize>,
+}
+
+impl EventLog {
+    fn spawn(rt: &Runtime, mut rx: broadcast::Receiver<TransferEvent>) -> Arc<Self> {
+        let log = Arc::new(Self::default());
+        let sink = log.clone();
+        rt.spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => sink.events.lock().unwrap().push(event),
+                    Err(broadcast::error::RecvError::Lagged(n)) => tracing::warn!("Harness dropped {} events", n),
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        log
+    }
+
+    // ตัวแรกหลัง Cursor ที่ตรง -> เลื่อน Cursor ไปหลังตัวนั้น (ลำดับ expect = ลำดับที่ต้องเกิด)
+    fn take(&self, pred: &impl Fn(&TransferEvent) -> bool) -> Option<TransferEvent> {
+        let events = self.events.lock().unwrap();
+        let mut cursor = self.cursor.lock().unwrap();
+        let at = events.iter().skip(*cursor).position(pred)? + *cursor;
+        *cursor = at + 1;
+        Some(events[at].clone())
+    }
+
+    fn any_pending(&self, pred: &impl Fn(&TransferEvent) -> bool) -> bool {
+        let events = self.events.lock().unwrap();
+        events.iter().skip(*self.cursor.lock().unwrap()).any(pred)
+    }
+
+    // Event ล่าสุดของ Task นี้ (ไว้ใส่ใน Error ตอนรอไม่เจอ)
+    fn trail(&self, task_id: Option<&str>) -> String {
+        let events = self.events.lock().unwrap();
+        let related: Vec<String> = events.iter()
+            .filter(|e| task_id.is_none() || task_of(e) == task_id)
+            .filter(|e| !matches!(e, TransferEvent::Progress { .. } | TransferEvent::Log { .. }))
+            .map(|e| format!("{:?}", e))
+            .collect();
+        related[related.len().saturating_sub(5)..].join(", ")
+    }
+
+    async fn expect(&self, what: &str, task_id: Option<&str>, pred: impl Fn(&TransferEvent) -> bool) -> anyhow::Result<TransferEvent> {
+        let deadline = Instant::now() + EVENT_WAIT;
+        loop {
+            if let Some(event) = self.take(&pred) { return Ok(event); }
+            if Instant::now() >= deadline {
+                bail!("timed out waiting for {} (last events: [{}])", what, self.trail(task_id));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+pub struct Harness {
+    rt: Arc<Runtime>,
+    sender: DropTeaCore,
+    receiver: DropTeaCore,
+    sender_log: Arc<EventLog>,
+    receiver_log: Arc<EventLog>,
+    sender_discovery: Arc<MockDiscovery>,
+    receiver_discovery: Arc<MockDiscovery>,
+    network: Option<Arc<MemoryNetwork>>,
+    port: u16,
+    dir: PathBuf,
+}
+
+impl Harness {
+    /// ผู้รับฟังที่ port / ผู้ส่งที่ port + 1 (ผู้ส่งต้อง start_service ด้วย เพื่อให้มี Control Channel ตอน Cancel / Pause)
+    /// memory = ทั้งสองฝั่งอยู่บน MemoryNetwork เดียวกัน (ไม่ Bind Port ของ Data)
+    pub fn start(port: u16, dir: &Path, memory: bool, mode: TransportMode) -> anyhow::Result<Self> {
+        let rt = Arc::new(Runtime::new()?);
+        let network = memory.then(MemoryNetwork::new);
+        let engine = |name: &str, port: u16, discovery: Arc<MockDiscovery>| {
+            let localsend = LocalSendConfig { port: port + LOCALSEND_OFFSET, https: true };
+            let links = LinkConfig { port: port + LINKS_OFFSET, host: Some("127.0.0.1".into()) };
+            let storage = dir.join(name);
+            std::fs::create_dir_all(&storage)?;
+            let builder = match &network {
+                Some(network) => DropTeaCore::builder().memory_network(network.clone()),
+                None => DropTeaCore::builder(),
+            };
+            let temp = storage.join("temp").to_string_lossy().into_owned();
+            builder
+                .transport(mode)
+                .configure(|c| {
+                    // .part อยู่แยกจาก ./downloads แล้วย้ายตอนรับครบ
+                    c.temp_dir = Some(temp);
+                    c.parallel.threshold = FILE_SIZE as u64;
+                    c.tcp_parallel = ParallelPolicy { threshold: FILE_SIZE as u64, ..ParallelPolicy::default() };
+                    // ไฟล์ขนาด FILE_SIZE อ่านผ่าน mmap / roam (ครึ่งเดียว) อ่านแบบ Buffer
+                    c.mmap_threshold = Some(FILE_SIZE as u64);
+                    c.skip_duplicates = true;
+                    // MockDiscovery แทนทุก Backend -> ไม่ Join Multicast ของ LocalSend
+                    c.localsend = Some(localsend);
+                    c.links = Some(links);
+                })
+                .discovery_backend(discovery)
+                .post_receive_hook(Arc::new(ScenarioHook))
+                .middleware(Arc::new(ScenarioMiddleware))
+                .port(port)
+                .storage(storage.to_string_lossy())
+                .node_name(format!("harness-{}", name))
+                // Plain TCP ไม่มี Cert ให้เป็น Guest -> Identity ลง storage ใน Temp Dir แทน
+                .guest(mode != TransportMode::PlainTcp)
+                .handler(NoopHandler)
+                .runtime(rt.clone())
+                .build()
+                .with_context(|| format!("Failed to start {} engine", name))
+        };
+        let sender_discovery = MockDiscovery::new();
+        let receiver_discovery = MockDiscovery::new();
+        let receiver = engine("receiver", port, receiver_discovery.clone())?;
+        let sender = engine("sender", port + 1, sender_discovery.clone())?;
+        let receiver_log = EventLog::spawn(&rt, receiver.subscribe());
+        let sender_log = EventLog::spawn(&rt, sender.subscribe());
+        receiver.start_service(port);
+        sender.start_service(port + 1);
+        let harness = Self { rt, sender, receiver, sender_log, receiver_log, sender_discovery, receiver_discovery, network, port, dir: dir.to_path_buf() };
+        harness.rt.block_on(harness.receiver_log.expect("receiver ServerStarted", None, |e| matches!(e, TransferEvent::ServerStarted { .. })))?;
+        Ok(harness)
+    }
+
+    pub fn run(&self, scenario: &str) -> anyhow::Result<()> {
+        self.rt.block_on(async {
+            match scenario {
+                "accept" => self.accept().await,
+                "reject" => self.reject().await,
+                "cancel" => self.cancel().await,
+                "resume" => self.resume().await,
+                "corruption" => self.corruption().await,
+                "discovery" => self.discovery().await,
+                "speedtest" => self.speedtest().await,
+                "parallel" => self.parallel().await,
+                "duplicate" => self.duplicate().await,
+                "hook" => self.hook().await,
+                "middleware" => self.middleware().await,
+                "roam" => self.roam().await,
+                "delta" => self.delta().await,
+                "code" => self.code().await,
+                "localsend" => self.localsend().await,
+                "link" => self.link().await,
+                other => bail!("unknown scenario '{}' (available: {})", other, SCENARIOS.join(", ")),
+            }
+        })
+    }
+
+    fn make_file(&self, name: &str) -> anyhow::Result<PathBuf> {
+        self.make_sized(name, FILE_SIZE)
+    }
+
+    fn make_sized(&self, name: &str, size: usize) -> anyhow::Result<PathBuf> {
+        let mut data = vec![0u8; size];
+        rand::thread_rng().fill_bytes(&mut data);
+        let path = self.dir.join(name);
+        std::fs::write(&path, data)?;
+        Ok(path)
+    }
+
+    // ทุกไฟล์แนบ Metadata ชื่อ Scenario ไปด้วย (accept ตรวจว่าถึงผู้รับ)
+    fn send(&self, task_id: &str, path: &Path) {
+        self.forget_sender();
+        let options = SendOptions { metadata: Metadata::from([("scenario".to_string(), task_id.to_string())]), ..SendOptions::default() };
+        self.sender.send_file("127.0.0.1".into(), self.port, path.to_string_lossy().into_owned(), task_id.into(), SENDER_NAME.into(), Box::new(NoopHandler), None, options);
+    }
+
+    // ไม่ใช่ Guest (--plaintcp) กดรับแล้วผู้ส่งเข้า Whitelist -> ล้างก่อนทุก Scenario ให้ผู้รับถามทุกครั้ง
+    fn forget_sender(&self) {
+        self.receiver.remove_trusted(SENDER_NAME);
+    }
+
+    // ข้อเสนอของไฟล์นี้ที่ผู้รับเห็น -> Task ID ฝั่งรับ
+    async fn incoming(&self, filename: &str) -> anyhow::Result<String> {
+        let needle = format!("|{}|", filename);
+        match self.receiver_log.expect(&format!("Incoming '{}'", filename), None, |e| matches!(e, TransferEvent::Incoming { filename, .. } if filename.contains(&needle))).await? {
+            TransferEvent::Incoming { task_id, .. } => Ok(task_id),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn completed(&self, log: &EventLog, side: &str, task_id: &str) -> anyhow::Result<String> {
+        let event = log.expect(&format!("{} terminal event", side), Some(task_id), |e| task_of(e) == Some(task_id) && is_terminal(e)).await?;
+        match event {
+            TransferEvent::Completed { info, .. } => Ok(info),
+            other => bail!("{} expected Completed, got {:?}", side, other),
+        }
+    }
+
+    async fn rejected(&self, log: &EventLog, side: &str, task_id: &str) -> anyhow::Result<String> {
+        let event = log.expect(&format!("{} terminal event", side), Some(task_id), |e| task_of(e) == Some(task_id) && is_terminal(e)).await?;
+        match event {
+            TransferEvent::Rejected { reason, .. } => Ok(reason),
+            other => bail!("{} expected Rejected, got {:?}", side, other),
+        }
+    }
+
+    fn same_content(source: &Path, received: &str) -> anyhow::Result<()> {
+        let (want, got) = (utils::sha256_file(source)?, utils::sha256_file(Path::new(received))?);
+        if want != got { bail!("received file differs from source ({} != {})", got, want); }
+        Ok(())
+    }
+
+    // mtime (ระดับ ms) / Permission ของต้นทางตามไฟล์ไปด้วย
+    fn same_metadata(source: &Path, received: &str) -> anyhow::Result<()> {
+        let (want, got) = (std::fs::metadata(source)?, std::fs::metadata(received)?);
+        let (want_ms, got_ms) = (want.modified().ok().and_then(utils::system_time_millis), got.modified().ok().and_then(utils::system_time_millis));
+        if want_ms != got_ms { bail!("received mtime {:?} != source {:?}", got_ms, want_ms); }
+        if utils::permission_bits(&want) != utils::permission_bits(&got) {
+            bail!("received mode {:?} != source {:?}", utils::permission_bits(&got), utils::permission_bits(&want));
+        }
+        Ok(())
+    }
+
+    // Incoming -> รับ -> ผู้ส่ง Started แล้ว Completed, ผู้รับ Completed และไฟล์ (รวม mtime / Permission) ตรงกัน
+    async fn accept(&self) -> anyhow::Result<()> {
+        let source = self.make_file("accept.bin")?;
+        std::fs::File::options().write(true).open(&source)?.set_modified(SystemTime::now() - Duration::from_secs(86_400))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&source, std::fs::Permissions::from_mode(0o640))?;
+        }
+        self.send("accept", &source);
+        let rx_task = self.incoming("accept.bin").await?;
+        self.receiver.resolve_request(rx_task.clone(), true);
+        self.sender_log.expect("sender Started", Some("accept"), |e| matches!(e, TransferEvent::Started { task_id, .. } if task_id == "accept")).await?;
+        self.completed(&self.sender_log, "sender", "accept").await?;
+        let received = match self.receiver_log.expect("receiver terminal event", Some(&rx_task), |e| task_of(e) == Some(rx_task.as_str()) && is_terminal(e)).await? {
+            TransferEvent::Completed { info, metadata, .. } if metadata.get("scenario").map(String::as_str) == Some("accept") => info,
+            other => bail!("receiver expected Completed with the sender's metadata, got {:?}", other),
+        };
+        Self::same_content(&source, &received)?;
+        Self::same_metadata(&source, &received)
+    }
+
+    // Incoming -> ปฏิเสธ -> สองฝั่ง Rejected ด้วยเหตุผลของตัวเอง
+    async fn reject(&self) -> anyhow::Result<()> {
+        let source = self.make_file("reject.bin")?;
+        self.send("reject", &source);
+        let rx_task = self.incoming("reject.bin").await?;
+        self.receiver.resolve_request(rx_task.clone(), false);
+        let reason = self.rejected(&self.receiver_log, "receiver", &rx_task).await?;
+        if reason != "User Rejected" { bail!("receiver rejected with '{}'", reason); }
+        let reason = self.rejected(&self.sender_log, "sender", "reject").await?;
+        if reason != "Receiver Rejected" { bail!("sender rejected with '{}'", reason); }
+        Ok(())
+    }
+
+    // ผู้รับ Pause ไว้ก่อนกดรับ (ไฟล์ค้างกลางทางแน่นอน) แล้วยกเลิก -> ผู้รับ Cancelled ผู้ส่งจบแบบไม่สำเร็จ
+    async fn cancel(&self) -> anyhow::Result<()> {
+        let source = self.make_file("cancel.bin")?;
+        self.send("cancel", &source);
+        let rx_task = self.incoming("cancel.bin").await?;
+        self.receiver.pause_transfer(&rx_task, true);
+        self.receiver.resolve_request(rx_task.clone(), true);
+        self.sender_log.expect("sender Started", Some("cancel"), |e| matches!(e, TransferEvent::Started { task_id, .. } if task_id == "cancel")).await?;
+        if !self.receiver.cancel_transfer(&rx_task) { bail!("receiver has no transfer {}", rx_task); }
+        let reason = self.rejected(&self.receiver_log, "receiver", &rx_task).await?;
+        if reason != REJECT_CANCELLED { bail!("receiver rejected with '{}'", reason); }
+        // Control Channel กับ Connection ที่ถูกปิดแข่งกันถึงผู้ส่ง -> Rejected หรือ Error ก็ได้ ขอแค่ไม่ Completed
+        let event = self.sender_log.expect("sender terminal event", Some("cancel"), |e| task_of(e) == Some("cancel") && is_terminal(e)).await?;
+        if matches!(event, TransferEvent::Completed { .. }) { bail!("sender completed a cancelled transfer"); }
+        Ok(())
+    }
+
+    // Pause ก่อนกดรับ -> ไม่จบระหว่าง Pause -> Resume แล้วจบครบทั้งไฟล์
+    async fn resume(&self) -> anyhow::Result<()> {
+        let source = self.make_file("resume.bin")?;
+        self.send("resume", &source);
+        let rx_task = self.incoming("resume.bin").await?;
+        self.receiver.pause_transfer(&rx_task, true);
+        self.receiver.resolve_request(rx_task.clone(), true);
+        tokio::time::sleep(PAUSE_HOLD).await;
+        let finished = |e: &TransferEvent| is_terminal(e) && matches!(task_of(e), Some(id) if id == rx_task || id == "resume");
+        if self.receiver_log.any_pending(&finished) || self.sender_log.any_pending(&finished) {
+            bail!("transfer finished while paused (receiver: [{}])", self.receiver_log.trail(Some(&rx_task)));
+        }
+        self.receiver.pause_transfer(&rx_task, false);
+        let received = self.completed(&self.receiver_log, "receiver", &rx_task).await?;
+        self.completed(&self.sender_log, "sender", "resume").await?;
+        Self::same_content(&source, &received)
+    }
+
+    // ชุดที่อนุมัติทั้ง Session แล้วเนื้อไฟล์ไม่ตรง SHA-256 ใน Manifest (ไฟล์ถูกแก้หลังเสนอ) -> ผู้รับทิ้งไฟล์
+    async fn corruption(&self) -> anyhow::Result<()> {
+        let source = self.make_file("corruption.bin")?;
+        let files = vec![("corruption".to_string(), source.to_string_lossy().into_owned())];
+        self.forget_sender();
+        self.sender.send_batch("127.0.0.1".into(), self.port, files, "corruption-batch".into(), SENDER_NAME.into(), Box::new(NoopHandler), None, SendOptions::default());
+        let offer = match self.receiver_log.expect("session offer", None, |e| matches!(e, TransferEvent::Incoming { filename, .. } if filename.contains("corruption-batch"))).await? {
+            TransferEvent::Incoming { task_id, .. } => task_id,
+            _ => unreachable!(),
+        };
+        // ขนาดเท่าเดิม เนื้อใหม่ -> Hash ไม่ตรงกับที่ผู้รับอนุมัติไว้ (จับได้ตอน Header หรือหลังรับครบ แล้วแต่จังหวะ)
+        self.make_file("corruption.bin")?;
+        self.receiver.resolve_request(offer, true);
+        let event = self.receiver_log.expect("receiver hash rejection", None, |e| matches!(e, TransferEvent::Rejected { reason, .. } if reason.contains("corruption.bin"))).await?;
+        if let TransferEvent::Rejected { reason, .. } = &event {
+            if !reason.starts_with(REJECT_MANIFEST) { bail!("receiver rejected with '{}'", reason); }
+        }
+        if std::fs::read_dir(self.dir.join("downloads")).map(|d| d.flatten().any(|e| e.file_name() == "corruption.bin")).unwrap_or(false) {
+            bail!("corrupted file was kept");
+        }
+        Ok(())
+    }
+
+    // Peer ที่ Backend เห็น -> PeerFound (ชื่อ / Address ตามที่ประกาศ) -> หายไป -> PeerLost
+    async fn discovery(&self) -> anyhow::Result<()> {
+        let id = "harness-receiver._droptea._tcp.local.";
+        if !self.sender_discovery.inject(MockDiscovery::peer(id, "harness-receiver", "127.0.0.1", self.port)).await {
+            bail!("sender discovery is not running");
+        }
+        let found = self.sender_log.expect("sender PeerFound", None, |e| matches!(e, TransferEvent::PeerFound { id: found, .. } if found == id)).await?;
+        if let TransferEvent::PeerFound { name, ip, port, .. } = &found {
+            if name != "harness-receiver" || ip != "127.0.0.1" || *port != self.port {
+                bail!("unexpected peer {:?}", found);
+            }
+        }
+        if !self.sender.get_peers().iter().any(|p| p.online && p.id.as_deref() == Some(id)) {
+            bail!("peer missing from get_peers()");
+        }
+        self.sender_discovery.inject(DiscoveryInternalEvent::MdnsLost { id: id.to_string() }).await;
+        self.sender_log.expect("sender PeerLost", None, |e| matches!(e, TransferEvent::PeerLost { id: lost } if lost == id)).await?;
+        Ok(())
+    }
+
+    // ผู้ส่งเห็นประกาศของผู้รับ (Features / Control Port ตรงของจริง) -> Peer ID ที่ผู้ส่งรู้จัก
+    async fn announce_receiver(&self) -> anyhow::Result<String> {
+        let node = self.receiver_discovery.local_node().context("receiver discovery is not running")?;
+        let found = MockDiscovery::announced(&node, "127.0.0.1");
+        let DiscoveryInternalEvent::MdnsFound { id, .. } = &found else { unreachable!() };
+        let id = id.clone();
+        self.sender_discovery.inject(found).await;
+        self.sender_log.expect("sender PeerFound", None, |e| matches!(e, TransferEvent::PeerFound { id: found, .. } if *found == id)).await?;
+        Ok(id)
+    }
+
+    // Peer ที่ประกาศ Control Channel + "speedtest" -> วัดได้ทั้งสองทิศ / Peer ไม่รู้จัก -> Config Error ทันที
+    async fn speedtest(&self) -> anyhow::Result<()> {
+        if self.sender.speed_test("nobody", SPEED_TEST_DURATION).is_ok() { bail!("speed_test accepted an unknown peer"); }
+        let id = self.announce_receiver().await?;
+        self.sender.speed_test(&id, SPEED_TEST_DURATION)?;
+        let error_task = format!("speedtest:{}", id);
+        let event = self.sender_log.expect("sender SpeedTest", Some(&error_task), |e| match e {
+            TransferEvent::SpeedTest { peer, .. } => *peer == id,
+            TransferEvent::Error { task_id, .. } => *task_id == error_task,
+            _ => false,
+        }).await?;
+        match event {
+            TransferEvent::SpeedTest { rtt_ms, upload_bytes_per_sec, download_bytes_per_sec, .. } => {
+                if upload_bytes_per_sec == 0 || download_bytes_per_sec == 0 || rtt_ms <= 0.0 {
+                    bail!("speed test measured nothing: {:?}", (rtt_ms, upload_bytes_per_sec, download_bytes_per_sec));
+                }
+                tracing::info!("Speed test: rtt {:.3} ms, up {} MB/s, down {} MB/s", rtt_ms, upload_bytes_per_sec >> 20, download_bytes_per_sec >> 20);
+                Ok(())
+            }
+            other => bail!("speed test failed: {:?}", other),
+        }
+    }
+
+    // ผู้รับประกาศ "parallel" + ไฟล์ถึงเกณฑ์ -> แบ่งหลาย Stream (QUIC) / หลาย Connection (TCP) แล้วประกอบกลับได้ไฟล์ตรงกัน
+    async fn parallel(&self) -> anyhow::Result<()> {
+        self.announce_receiver().await?;
+        let source = self.make_file("parallel.bin")?;
+        self.send("parallel", &source);
+        let rx_task = self.incoming("parallel.bin").await?;
+        self.receiver.resolve_request(rx_task.clone(), true);
+        self.completed(&self.sender_log, "sender", "parallel").await?;
+        let received = self.completed(&self.receiver_log, "receiver", &rx_task).await?;
+        Self::same_content(&source, &received)
+    }
+
+    // รับไฟล์ไปแล้ว -> ส่งไฟล์เดิมซ้ำ: ผู้รับไม่ถาม ตอบ "มีแล้ว" ทั้งสองฝั่งได้ Duplicate (ผู้รับชี้ไฟล์ที่ตรง)
+    async fn duplicate(&self) -> anyhow::Result<()> {
+        let source = self.make_file("duplicate.bin")?;
+        self.send("duplicate", &source);
+        let rx_task = self.incoming("duplicate.bin").await?;
+        self.receiver.resolve_request(rx_task.clone(), true);
+        self.completed(&self.sender_log, "sender", "duplicate").await?;
+        // ผู้รับย้ายไฟล์เข้าที่เสร็จก่อน ค่อยส่งซ้ำ
+        self.completed(&self.receiver_log, "receiver", &rx_task).await?;
+        self.send("duplicate-again", &source);
+        match self.sender_log.expect("sender terminal event", Some("duplicate-again"), |e| task_of(e) == Some("duplicate-again") && is_terminal(e)).await? {
+            TransferEvent::Duplicate { existing: None, .. } => {}
+            other => bail!("sender expected Duplicate, got {:?}", other),
+        }
+        let event = self.receiver_log.expect("receiver Duplicate", None, |e| matches!(e, TransferEvent::Duplicate { filename, .. } if filename == "duplicate.bin")).await?;
+        match event {
+            TransferEvent::Duplicate { existing: Some(existing), .. } => Self::same_content(&source, &existing),
+            other => bail!("receiver Duplicate without the existing file: {:?}", other),
+        }
+    }
+
+    // รับไฟล์ไปแล้ว -> แก้ไฟล์ต้นทาง (แทรกกลางไฟล์ให้ Block เลื่อน + เขียนทับอีกช่วง) แล้วส่งชื่อเดิม: ผู้รับประกอบจากไฟล์เก่า + ส่วนที่เปลี่ยนได้ตรงต้นทาง
+    async fn delta(&self) -> anyhow::Result<()> {
+        let source = self.make_file("delta.bin")?;
+        self.send("delta", &source);
+        let rx_task = self.incoming("delta.bin").await?;
+        self.receiver.resolve_request(rx_task.clone(), true);
+        self.completed(&self.sender_log, "sender", "delta").await?;
+        self.completed(&self.receiver_log, "receiver", &rx_task).await?;
+
+        let mut data = std::fs::read(&source)?;
+        let mut patch = vec![0u8; 4096];
+        rand::thread_rng().fill_bytes(&mut patch);
+        data[FILE_SIZE / 2..FILE_SIZE / 2 + patch.len()].copy_from_slice(&patch);
+        data.splice(FILE_SIZE / 4..FILE_SIZE / 4, patch[..1000].iter().copied());
+        std::fs::write(&source, data)?;
+        self.send("delta-again", &source);
+        let rx_task = self.incoming("delta.bin").await?;
+        self.receiver.resolve_request(rx_task.clone(), true);
+        self.completed(&self.sender_log, "sender", "delta-again").await?;
+        let received = self.completed(&self.receiver_log, "receiver", &rx_task).await?;
+        Self::same_content(&source, &received)
+    }
+
+    // Post-receive Hook ล้มหลังรับครบ -> ผู้รับ Completed ตามด้วย HookFailed ที่ชี้ไฟล์เดิม (ยังอยู่) และ SHA-256 ตรงต้นทาง
+    async fn hook(&self) -> anyhow::Result<()> {
+        let source = self.make_file("hook.bin")?;
+        self.send(HOOK_SCENARIO, &source);
+        let rx_task = self.incoming("hook.bin").await?;
+        self.receiver.resolve_request(rx_task.clone(), true);
+        let received = self.completed(&self.receiver_log, "receiver", &rx_task).await?;
+        let event = self.receiver_log.expect("receiver HookFailed", Some(&rx_task), |e| matches!(e, TransferEvent::HookFailed { task_id, .. } if *task_id == rx_task)).await?;
+        let TransferEvent::HookFailed { path, error, .. } = event else { unreachable!() };
+        if path != received { bail!("HookFailed for '{}', expected '{}'", path, received); }
+        let want = format!("harness: sha256 {}", utils::sha256_file(&source)?);
+        if error != want { bail!("HookFailed with '{}', expected '{}'", error, want); }
+        Self::same_content(&source, &path)
+    }
+
+    // before_send ของผู้ส่งแก้ Metadata ได้ / after_receive ของผู้รับทิ้งไฟล์ -> Rejected และไม่มีไฟล์ลงที่
+    async fn middleware(&self) -> anyhow::Result<()> {
+        let source = self.make_file("middleware.bin")?;
+        self.send(MIDDLEWARE_SCENARIO, &source);
+        let rx_task = self.incoming("middleware.bin").await?;
+        self.receiver.resolve_request(rx_task.clone(), true);
+        let reason = self.rejected(&self.receiver_log, "receiver", &rx_task).await?;
+        let want = format!("harness: filtered middleware.bin via {}", SENDER_NAME);
+        if reason != want { bail!("receiver rejected with '{}', expected '{}'", reason, want); }
+        if std::fs::read_dir(self.dir.join("downloads")).map(|d| d.flatten().any(|e| e.file_name() == "middleware.bin")).unwrap_or(false) {
+            bail!("filtered file was kept");
+        }
+        Ok(())
+    }
+
+    // ผู้ส่งเปิด Code -> ผู้รับเห็น Nameplate บน LAN: คำผิดครั้งเดียว Code ปิด (ผู้ส่ง Rejected) / Code ใหม่ที่ถูกได้ไฟล์โดยไม่ถาม
+    async fn code(&self) -> anyhow::Result<()> {
+        let source = self.make_file("code.bin")?;
+        let path = source.to_string_lossy().into_owned();
+        let guessed = self.sender.offer_code(&path, None)?;
+        let plate = codes::nameplate(&guessed).context("offer_code returned an invalid code")?;
+        self.announce_sender_code(plate).await?;
+        let wrong = format!("{}-{}", plate, if guessed.ends_with("-acid-acid") { "zoom-zoom" } else { "acid-acid" });
+        if tokio::task::block_in_place(|| self.receiver.receive_code(&wrong)).is_ok() { bail!("receive_code accepted a wrong code"); }
+        let guessed_task = format!("{}{}", codes::TASK_PREFIX, guessed);
+        match self.sender_log.expect("sender Rejected", Some(&guessed_task), |e| task_of(e) == Some(guessed_task.as_str()) && is_terminal(e)).await? {
+            TransferEvent::Rejected { reason, .. } if reason == codes::REJECT_WRONG_CODE => {}
+            other => bail!("sender expected Rejected for the wrong code, got {:?}", other),
+        }
+        if self.sender.cancel_code(&guessed) { bail!("code stayed open after a wrong guess"); }
+
+        let code = self.sender.offer_code(&path, None)?;
+        self.announce_sender_code(codes::nameplate(&code).context("offer_code returned an invalid code")?).await?;
+        tokio::task::block_in_place(|| self.receiver.receive_code(&code.to_uppercase().replace('-', " ")))?;
+        self.completed(&self.sender_log, "sender", &format!("{}{}", codes::TASK_PREFIX, code)).await?;
+        let received = match self.receiver_log.expect("receiver Completed", None, |e| matches!(e, TransferEvent::Completed { info, .. } if info.contains("code"))).await? {
+            TransferEvent::Completed { info, .. } => info,
+            _ => unreachable!(),
+        };
+        Self::same_content(&source, &received)
+    }
+
+    // รอจน Discovery ของผู้ส่งประกาศ Nameplate นี้ แล้วให้ผู้รับเห็นประกาศนั้น
+    async fn announce_sender_code(&self, plate: u16) -> anyhow::Result<()> {
+        let feature = format!("{}{}", codes::FEATURE_PREFIX, plate);
+        let deadline = Instant::now() + EVENT_WAIT;
+        let node = loop {
+            let node = self.sender_discovery.local_node().context("sender discovery is not running")?;
+            if node.features.contains(&feature) { break node; }
+            if Instant::now() >= deadline { bail!("sender never announced {}", feature); }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        };
+        let found = MockDiscovery::announced(&node, "127.0.0.1");
+        self.receiver_discovery.inject(found).await;
+        let deadline = Instant::now() + EVENT_WAIT;
+        while !self.receiver.discovery.known_peers.iter().any(|p| p.features.contains(&feature)) {
+            if Instant::now() >= deadline { bail!("receiver never saw {}", feature); }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        Ok(())
+    }
+
+    // ผู้ส่งเห็น API ของผู้รับเป็นเครื่อง LocalSend -> send_file วิ่งผ่าน prepare-upload / upload แทน Protocol ของเรา
+    async fn localsend(&self) -> anyhow::Result<()> {
+        let id = "localsend:harness-receiver";
+        let found = || {
+            let mut found = MockDiscovery::peer(id, "harness-receiver", "127.0.0.1", self.port + LOCALSEND_OFFSET);
+            if let DiscoveryInternalEvent::MdnsFound { features, .. } = &mut found {
+                features.push(format!("{}https", localsend::FEATURE_PREFIX));
+            }
+            found
+        };
+        // start_service เริ่ม Discovery ของผู้ส่งแบบ Async -> Scenario แรกอาจมาก่อน Backend พร้อมรับ Event
+        let deadline = Instant::now() + EVENT_WAIT;
+        while !self.sender_discovery.inject(found()).await {
+            if Instant::now() >= deadline { bail!("sender discovery is not running"); }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        self.sender_log.expect("sender PeerFound", None, |e| matches!(e, TransferEvent::PeerFound { id: found, .. } if found == id)).await?;
+        let source = self.make_sized("localsend.bin", FILE_SIZE / 4)?;
+        self.forget_sender();
+        self.sender.send_file(id.into(), 0, source.to_string_lossy().into_owned(), "localsend".into(), SENDER_NAME.into(), Box::new(NoopHandler), None, SendOptions::default());
+        let rx_task = self.incoming("localsend.bin").await?;
+        self.receiver.resolve_request(rx_task, true);
+        self.completed(&self.sender_log, "sender", "localsend").await?;
+        let received = match self.receiver_log.expect("receiver Completed", None, |e| matches!(e, TransferEvent::Completed { info, .. } if info.contains("localsend.bin"))).await? {
+            TransferEvent::Completed { info, .. } => info,
+            _ => unreachable!(),
+        };
+        Self::same_content(&source, &received)
+    }
+
+    // Browser โหลดไฟล์ของผู้ส่งทาง Link ได้ไฟล์เดิม แล้ว Link เดิมใช้ซ้ำไม่ได้
+    async fn link(&self) -> anyhow::Result<()> {
+        let source = self.make_sized("link.bin", FILE_SIZE / 4)?;
+        let url = self.sender.create_link(&source.to_string_lossy(), None)?;
+        let (status, body) = Self::http_get(&url).await?;
+        if status != 200 { bail!("download returned HTTP {}", status); }
+        if body != std::fs::read(&source)? { bail!("downloaded file differs from source"); }
+        self.completed(&self.sender_log, "sender", &format!("{}{}", links::TASK_PREFIX, links::token_of(&url))).await?;
+        let (status, _) = Self::http_get(&url).await?;
+        if status != 410 { bail!("used link returned HTTP {} instead of 410", status); }
+        if self.sender.revoke_link(&url) { bail!("link stayed open after the download"); }
+
+        // Link ที่ไม่มีใครโหลดจนครบ ttl -> Error "Link expired" แล้วโหลดไม่ได้
+        let unused = self.sender.create_link(&source.to_string_lossy(), Some(Duration::from_millis(300)))?;
+        let unused_task = format!("{}{}", links::TASK_PREFIX, links::token_of(&unused));
+        match self.sender_log.expect("sender link expiry", Some(&unused_task), |e| task_of(e) == Some(unused_task.as_str()) && is_terminal(e)).await? {
+            TransferEvent::Error { error, .. } if error == "Link expired" => {}
+            other => bail!("expected the unused link to expire, got {:?}", other),
+        }
+        let (status, _) = Self::http_get(&unused).await?;
+        if status != 410 { bail!("expired link returned HTTP {} instead of 410", status); }
+        Ok(())
+    }
+
+    // GET แบบ Browser (Connection: close -> อ่าน Body จนอีกฝั่งปิด)
+    async fn http_get(url: &str) -> anyhow::Result<(u16, Vec<u8>)> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let (authority, path) = url.strip_prefix("http://").and_then(|rest| rest.split_once('/')).context("not an http link")?;
+        let mut stream = tokio::net::TcpStream::connect(authority).await?;
+        stream.write_all(format!("GET /{} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", path, authority).as_bytes()).await?;
+        let mut response = Vec::new();
+        tokio::time::timeout(EVENT_WAIT, stream.read_to_end(&mut response)).await.context("download timed out")??;
+        let head = response.windows(4).position(|w| w == b"\r\n\r\n").context("response without head")?;
+        let status = String::from_utf8_lossy(&response[..head]).split_whitespace().nth(1).and_then(|s| s.parse().ok()).context("malformed status line")?;
+        Ok((status, response[head + 4..].to_vec()))
+    }
+
+    // ผู้รับ Pause ไว้กลางไฟล์ -> ตัดทุก Connection (เปลี่ยน AP) -> ผู้ส่ง Retrying แล้วต่อจาก .part เดิม จบใน Task เดิมของผู้รับ
+    async fn roam(&self) -> anyhow::Result<()> {
+        let network = self.network.as_ref().context("roam needs --memory")?;
+        self.announce_receiver().await?;
+        // ต่ำกว่าเกณฑ์ Parallel (Resume ใช้กับ Stream เดียวเท่านั้น) แต่ยังใหญ่กว่า Buffer ของ Pipe
+        let source = self.make_sized("roam.bin", FILE_SIZE / 2)?;
+        self.send("roam", &source);
+        let rx_task = self.incoming("roam.bin").await?;
+        self.receiver.pause_transfer(&rx_task, true);
+        self.receiver.resolve_request(rx_task.clone(), true);
+        tokio::time::sleep(PAUSE_HOLD).await;
+        network.drop_links();
+        self.receiver.pause_transfer(&rx_task, false);
+        self.sender_log.expect("sender Retrying", Some("roam"), |e| matches!(e, TransferEvent::Retrying { task_id, .. } if task_id == "roam")).await?;
+        self.completed(&self.sender_log, "sender", "roam").await?;
+        let received = self.completed(&self.receiver_log, "receiver", &rx_task).await?;
+        // ต่อจนครบแล้วต้องไม่เหลือ .part / ข้อมูล Resume ค้าง
+        let leftover = self.receiver.list_incomplete();
+        if !leftover.is_empty() { bail!("receiver still has incomplete files: {:?}", leftover); }
+        Self::same_content(&source, &received)
+    }
+}
+
+/// Entry ของ droptea-harness: `droptea-harness [--port N] [--memory] [--quic | --plaintcp] [scenario...]` (ไม่ระบุ = ทุก Scenario)
+pub fn harness_main() -> anyhow::Result<()> {
+    let mut port = DEFAULT_PORT;
+    let mut memory = false;
+    let mut mode = TransportMode::Tcp;
+    let mut selected = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => port = args.next().context("Missing port")?.parse().context("Invalid port")?,
+            "--memory" => memory = true,
+            "--quic" => mode = TransportMode::Quic,
+            "--plaintcp" => mode = TransportMode::PlainTcp,
+            "--list" => { println!("{}", SCENARIOS.join("\n")); return Ok(()); }
+            name => selected.push(name.to_string()),
+        }
+    }
+    if selected.is_empty() {
+        selected = SCENARIOS.iter().filter(|s| memory || !MEMORY_ONLY.contains(s)).map(|s| s.to_string()).collect();
+    }
+
+    let work = std::env::temp_dir().join(format!("droptea-harness-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&work)?;
+    // Engine รับไฟล์ลง ./downloads ของ Working Directory
+    std::env::set_current_dir(&work)?;
+    let harness = Harness::start(port, &work, memory, mode)?;
+    let mut failed = 0;
+    for name in &selected {
+        let started = Instant::now();
+        match harness.run(name) {
+            Ok(()) => println!("✔ {} ({} ms)", name, started.elapsed().as_millis()),
+            Err(e) => { failed += 1; println!("✘ {}: {:#}", name, e); }
+        }
+    }
+    drop(harness);
+    let _ = std::fs::remove_dir_all(&work);
+    if failed > 0 { bail!("{} of {} scenario(s) failed", failed, selected.len()); }
+    Ok(())
+}
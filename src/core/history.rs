@@ -0,0 +1,37 @@
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+use tokio::io::AsyncWriteExt;
+use tokio::fs::OpenOptions;
+
+// ==========================================
+// Transfer History (JSON Lines ต่อท้ายใน save_path)
+// เวลาทั้งหมดเป็น Unix ms จาก utils::timestamp_millis (เรียงลำดับได้เสมอ)
+// ==========================================
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    pub task_id: String,
+    pub filename: String,
+    pub filesize: u64,
+    pub sender_name: String,
+    // นาฬิกาฝั่งส่ง (ตามที่ผู้ส่งบอกมา)
+    pub sent_at: Option<u64>,
+    // นาฬิกาเครื่องเรา
+    pub received_at: u64,
+    pub completed_at: u64,
+    pub clock_skew_ms: Option<i64>,
+}
+
+pub async fn append(save_path: &str, entry: &HistoryEntry) -> anyhow::Result<()> {
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(Path::new(save_path).join(HISTORY_FILE))
+        .await?;
+    file.write_all(&line).await?;
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use tokio::process::Command;
+
+use crate::core::transfer::Metadata;
+
+// ==========================================
+// Post-receive Hook (หลังไฟล์ลงที่ปลายทางแล้ว)
+// เช่น สแกนไวรัส / Import เข้า Photo Library / สั่ง Index ใหม่
+// 1) Command จาก [hooks] post_receive: argv ตรงๆ ไม่ผ่าน Shell (ชื่อไฟล์ / ชื่อผู้ส่งมาจากอีกเครื่อง ห้ามตีความเป็นคำสั่ง)
+//    Placeholder ในแต่ละ Argument: {path} {filename} {sender} {sha256} {task_id}
+// 2) Rust: impl PostReceiveHook แล้วใส่ผ่าน DropTeaBuilder::post_receive_hook
+// รันหลัง Completed แยก Task (ไม่ถือ Slot ของการรับ) ล้ม / Timeout = Event HookFailed (ไฟล์ยังอยู่ตามเดิม)
+// ==========================================
+
+pub const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(60);
+// stderr ของ Command ที่ล้มแนบไปกับ HookFailed ได้ไม่เกินนี้
+const STDERR_TAIL: usize = 512;
+
+/// ไฟล์ที่เพิ่งรับครบ (path = ที่อยู่จริงหลังย้าย / รวมเข้า Archive แล้ว)
+#[derive(Debug, Clone)]
+pub struct ReceivedFile {
+    pub task_id: String,
+    pub path: PathBuf,
+    pub filename: String,
+    pub sender_name: String,
+    // SHA-256 (hex) ของไฟล์ที่รับ (Archive Mode = ของ Archive ทั้งก้อน)
+    pub sha256: String,
+    pub metadata: Metadata,
+}
+
+#[async_trait]
+pub trait PostReceiveHook: Send + Sync + std::fmt::Debug + 'static {
+    fn name(&self) -> &str;
+    async fn run(&self, file: &ReceivedFile) -> anyhow::Result<()>;
+}
+
+pub type DynPostReceiveHook = Arc<dyn PostReceiveHook>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandHook {
+    pub argv: Vec<String>,
+    pub timeout: Duration,
+}
+
+impl CommandHook {
+    fn expand(arg: &str, file: &ReceivedFile) -> String {
+        arg.replace("{path}", &file.path.to_string_lossy())
+            .replace("{filename}", &file.filename)
+            .replace("{sender}", &file.sender_name)
+            .replace("{sha256}", &file.sha256)
+            .replace("{task_id}", &file.task_id)
+    }
+}
+
+#[async_trait]
+impl PostReceiveHook for CommandHook {
+    fn name(&self) -> &str {
+        self.argv.first().map_or("command", String::as_str)
+    }
+
+    async fn run(&self, file: &ReceivedFile) -> anyhow::Result<()> {
+        let Some((program, args)) = self.argv.split_first() else { bail!("Empty post-receive command") };
+        let child = Command::new(program)
+            .args(args.iter().map(|a| Self::expand(a, file)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start '{}'", program))?;
+        let output = tokio::time::timeout(self.timeout, child.wait_with_output()).await
+            .map_err(|_| anyhow::anyhow!("'{}' timed out after {}s", program, self.timeout.as_secs()))??;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let stderr = stderr.trim();
+            let start = stderr.char_indices().rev().nth(STDERR_TAIL - 1).map_or(0, |(i, _)| i);
+            let tail = &stderr[start..];
+            bail!("'{}' exited with {}{}", program, output.status, if tail.is_empty() { String::new() } else { format!(": {}", tail) });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+// ==========================================
+// Hotspot / Internet Connection Sharing
+// เครื่องนี้แชร์เน็ตเป็น Hotspot -> มือถือที่เกาะอยู่เห็นเราผ่าน Address ของ Interface นั้นเท่านั้น
+// แต่ละ OS ใช้ Subnet ต่างกัน และ Adapter เสมือนของ Windows มักรายงานสถานะแปลกๆ
+// จึงหาเองตอน Runtime (หรือกำหนดใน Config ถ้าตั้ง Subnet เอง)
+// ==========================================
+
+// Address ฝั่ง Host ที่ OS ตั้งให้ Default
+const KNOWN_HOST_ADDRS: [Ipv4Addr; 3] = [
+    Ipv4Addr::new(192, 168, 137, 1), // Windows Mobile Hotspot / ICS
+    Ipv4Addr::new(192, 168, 2, 1),   // macOS Internet Sharing (bridge100)
+    Ipv4Addr::new(10, 42, 0, 1),     // Linux NetworkManager (ipv4.method=shared)
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hotspot {
+    pub interface: String,
+    pub addr: IpAddr,
+}
+
+// ชื่อ Interface ที่ OS ใช้ตอนแชร์เน็ต (ใช้กับกรณีเปลี่ยน Subnet ไปจาก Default)
+fn is_sharing_interface(name: &str) -> bool {
+    if cfg!(target_os = "windows") {
+        name.starts_with("Local Area Connection*")
+    } else if cfg!(target_os = "macos") {
+        name.starts_with("bridge")
+    } else {
+        name.starts_with("ap") || name.starts_with("hotspot")
+    }
+}
+
+/// `configured` มาก่อนเสมอ ไม่งั้นหา Interface ที่ดูเป็น Hotspot (None = ไม่ได้เปิด Hotspot)
+pub fn detect(configured: Option<IpAddr>) -> Option<Hotspot> {
+    let ifaces = match if_addrs::get_if_addrs() {
+        Ok(ifaces) => ifaces,
+        Err(e) => {
+            log::warn!("Hotspot detection failed: {}", e);
+            return configured.map(|addr| Hotspot { interface: String::new(), addr });
+        }
+    };
+
+    if let Some(addr) = configured {
+        let interface = ifaces.iter().find(|i| i.ip() == addr).map(|i| i.name.clone());
+        if interface.is_none() {
+            log::warn!("Configured hotspot gateway {} is not assigned to any interface", addr);
+        }
+        return Some(Hotspot { interface: interface.unwrap_or_default(), addr });
+    }
+
+    let v4 = |i: &if_addrs::Interface| match i.ip() {
+        IpAddr::V4(v4) if !v4.is_loopback() => Some(v4),
+        _ => None,
+    };
+    ifaces.iter()
+        .find(|i| v4(i).is_some_and(|ip| KNOWN_HOST_ADDRS.contains(&ip)))
+        .or_else(|| ifaces.iter().find(|i| is_sharing_interface(&i.name) && v4(i).is_some_and(|ip| ip.octets()[3] == 1)))
+        .map(|i| Hotspot { interface: i.name.clone(), addr: i.ip() })
+}
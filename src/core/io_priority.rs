@@ -0,0 +1,221 @@
+use std::future::Future;
+use std::sync::OnceLock;
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter, DuplexStream, ReadBuf};
+use tokio::runtime::{Builder, Runtime};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use anyhow::Context as _;
+
+use crate::core::mmap::MappedFile;
+
+// ==========================================
+// I/O Priority สำหรับ Transfer เบื้องหลัง
+// tokio::fs ใช้ Blocking Pool ร่วมกัน ตั้ง Priority ราย Call ไม่ได้
+// -> งาน Background ทำ Disk I/O บน Runtime แยกที่ทุก Thread ถูกลด Priority ตั้งแต่เกิด
+//    แล้วต่อกับ Pipeline ผ่าน DuplexStream (ฝั่ง Network ยังวิ่งบน Runtime หลัก)
+// ==========================================
+
+const DUPLEX_BUFFER_SIZE: usize = 1024 * 1024;
+const BACKGROUND_BLOCKING_THREADS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IoPriority {
+    #[default]
+    Normal,
+    Background,
+}
+
+impl IoPriority {
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "normal" => Some(IoPriority::Normal),
+            "background" | "low" | "idle" => Some(IoPriority::Background),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IoPriority::Normal => "normal",
+            IoPriority::Background => "background",
+        }
+    }
+}
+
+// --- Platform: ลด Priority ของ Thread ปัจจุบัน ---
+
+#[cfg(target_os = "linux")]
+fn lower_current_thread() {
+    // ioprio เป็นราย Thread บน Linux (who=0 = Thread ที่เรียก)
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+    let ret = unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT) };
+    if ret != 0 { log::debug!("ioprio_set failed: {}", std::io::Error::last_os_error()); }
+}
+
+#[cfg(target_os = "macos")]
+fn lower_current_thread() {
+    extern "C" {
+        fn setiopolicy_np(iotype: libc::c_int, scope: libc::c_int, policy: libc::c_int) -> libc::c_int;
+    }
+    const IOPOL_TYPE_DISK: libc::c_int = 0;
+    const IOPOL_SCOPE_THREAD: libc::c_int = 1;
+    const IOPOL_THROTTLE: libc::c_int = 3;
+    let ret = unsafe { setiopolicy_np(IOPOL_TYPE_DISK, IOPOL_SCOPE_THREAD, IOPOL_THROTTLE) };
+    if ret != 0 { log::debug!("setiopolicy_np failed: {}", std::io::Error::last_os_error()); }
+}
+
+#[cfg(windows)]
+fn lower_current_thread() {
+    // Background Mode = ลดทั้ง CPU, I/O และ Memory Priority ของ Thread
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetCurrentThread() -> *mut std::ffi::c_void;
+        fn SetThreadPriority(thread: *mut std::ffi::c_void, priority: i32) -> i32;
+    }
+    const THREAD_MODE_BACKGROUND_BEGIN: i32 = 0x0001_0000;
+    let ok = unsafe { SetThreadPriority(GetCurrentThread(), THREAD_MODE_BACKGROUND_BEGIN) };
+    if ok == 0 { log::debug!("SetThreadPriority failed: {}", std::io::Error::last_os_error()); }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn lower_current_thread() {}
+
+fn background_runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        Builder::new_multi_thread()
+            .worker_threads(1)
+            .max_blocking_threads(BACKGROUND_BLOCKING_THREADS)
+            .thread_name("droptea-bg-io")
+            .on_thread_start(lower_current_thread)
+            .enable_all()
+            .build()
+            .expect("Failed to build background I/O runtime")
+    })
+}
+
+// --- Source (ฝั่งส่ง: อ่านไฟล์) ---
+
+pub enum FileSource {
+    Direct(BufReader<File>),
+    // ผลของการ copy ฝั่ง Runtime แยก: ตรวจตอน EOF (อ่านไฟล์พังกลางทางต้องไม่กลายเป็นไฟล์สั้นที่ "ส่งสำเร็จ")
+    Background(DuplexStream, Option<oneshot::Receiver<std::io::Result<u64>>>),
+    // ไฟล์ใหญ่ที่ Map ไว้ทั้งก้อน (ดู mmap.rs)
+    Mapped(MappedFile),
+}
+
+impl FileSource {
+    pub fn new(file: File, priority: IoPriority, buffer_size: usize) -> Self {
+        match priority {
+            IoPriority::Normal => FileSource::Direct(BufReader::with_capacity(buffer_size, file)),
+            IoPriority::Background => {
+                let (mut local, remote) = tokio::io::duplex(DUPLEX_BUFFER_SIZE);
+                let (result_tx, result_rx) = oneshot::channel();
+                background_runtime().spawn(async move {
+                    let mut reader = BufReader::with_capacity(buffer_size, file);
+                    // ฝั่ง Pipeline ปิดก่อน (Error/Reject) -> copy จบเอง และไม่มีใครรอผล
+                    let copied = tokio::io::copy(&mut reader, &mut local).await;
+                    let _ = local.shutdown().await;
+                    let _ = result_tx.send(copied);
+                });
+                FileSource::Background(remote, Some(result_rx))
+            }
+        }
+    }
+}
+
+impl AsyncRead for FileSource {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            FileSource::Direct(r) => Pin::new(r).poll_read(cx, buf),
+            FileSource::Background(r, result) => {
+                let before = buf.filled().len();
+                match Pin::new(r).poll_read(cx, buf) {
+                    Poll::Ready(Ok(())) if buf.filled().len() == before => {
+                        // ตรวจผลครั้งเดียว (Receiver ที่ได้ค่าไปแล้ว Poll ซ้ำไม่ได้)
+                        let Some(rx) = result else { return Poll::Ready(Ok(())) };
+                        let copied = std::task::ready!(Pin::new(rx).poll(cx));
+                        *result = None;
+                        match copied {
+                            Ok(Ok(_)) => Poll::Ready(Ok(())),
+                            Ok(Err(e)) => Poll::Ready(Err(e)),
+                            Err(_) => Poll::Ready(Err(std::io::Error::other("Background reader stopped"))),
+                        }
+                    }
+                    other => other,
+                }
+            }
+            FileSource::Mapped(r) => Pin::new(r).poll_read(cx, buf),
+        }
+    }
+}
+
+// --- Sink (ฝั่งรับ: เขียนไฟล์) ---
+
+pub enum FileSink {
+    Direct(BufWriter<File>),
+    Background(DuplexStream, JoinHandle<std::io::Result<File>>),
+}
+
+impl FileSink {
+    pub fn new(file: File, priority: IoPriority, buffer_size: usize) -> Self {
+        match priority {
+            IoPriority::Normal => FileSink::Direct(BufWriter::with_capacity(buffer_size, file)),
+            IoPriority::Background => {
+                let (local, mut remote) = tokio::io::duplex(DUPLEX_BUFFER_SIZE);
+                let handle = background_runtime().spawn(async move {
+                    let mut writer = BufWriter::with_capacity(buffer_size, file);
+                    tokio::io::copy(&mut remote, &mut writer).await?;
+                    writer.flush().await?;
+                    let file = writer.into_inner();
+                    file.sync_all().await?;
+                    Ok(file)
+                });
+                FileSink::Background(local, handle)
+            }
+        }
+    }
+
+    /// Flush + fsync แล้วคืน File (ใช้ตั้ง mtime ต่อ)
+    pub async fn finish(self) -> anyhow::Result<File> {
+        match self {
+            FileSink::Direct(mut w) => {
+                w.flush().await?;
+                let file = w.into_inner();
+                file.sync_all().await?;
+                Ok(file)
+            }
+            FileSink::Background(mut w, handle) => {
+                w.shutdown().await?;
+                drop(w);
+                Ok(handle.await.context("Background writer panicked")??)
+            }
+        }
+    }
+}
+
+impl AsyncWrite for FileSink {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            FileSink::Direct(w) => Pin::new(w).poll_write(cx, buf),
+            FileSink::Background(w, _) => Pin::new(w).poll_write(cx, buf),
+        }
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            FileSink::Direct(w) => Pin::new(w).poll_flush(cx),
+            FileSink::Background(w, _) => Pin::new(w).poll_flush(cx),
+        }
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            FileSink::Direct(w) => Pin::new(w).poll_shutdown(cx),
+            FileSink::Background(w, _) => Pin::new(w).poll_shutdown(cx),
+        }
+    }
+}
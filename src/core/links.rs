@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use anyhow::{bail, Context};
+use rand::Rng;
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::timeout;
+use tracing::{debug, info, warn};
+
+use crate::core::cancel::CancellationToken;
+use crate::core::discovery::MdnsBackend;
+use crate::core::localsend::{percent_encode, read_request, reason};
+use crate::core::transfer::TransferCallback;
+
+// ==========================================
+// Browser Download Links ([links] enabled = true)
+// create_link(path) -> "http://<ip>:<port>/d/<token>" เปิดจาก Browser ของเครื่องที่ไม่มี DropTea (Smart TV / Notebook ที่ลงโปรแกรมไม่ได้)
+// Token ใช้ได้ครั้งเดียว: หยิบออกตอน GET แรก (โหลดไม่จบก็ต้องสร้างใหม่) และหมดอายุตาม ttl
+// HTTP ล้วน (Browser ไม่ยอมรับ Cert Self-signed เงียบๆ) -> ใครได้ Link ไปก่อนก็โหลดได้ ใช้กับไฟล์ที่ส่งต่อให้คนใน LAN เท่านั้น
+// Event ใช้ Task ID "link:<token>": Started / Progress / Completed (IP ผู้โหลด) / Error (หมดอายุ / ขาดกลางทาง)
+// ==========================================
+
+pub const DEFAULT_PORT: u16 = 28191;
+pub const DEFAULT_TTL: Duration = Duration::from_secs(15 * 60);
+pub const TASK_PREFIX: &str = "link:";
+const ROUTE: &str = "/d/";
+const TOKEN_SIZE: usize = 16;
+const BUFFER: usize = 64 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone)]
+pub struct LinkConfig {
+    pub port: u16,
+    // IP / ชื่อที่ใส่ใน Link (None = IP ของ Default Route)
+    pub host: Option<String>,
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self { port: DEFAULT_PORT, host: None }
+    }
+}
+
+struct Link {
+    path: PathBuf,
+    expires_at: Instant,
+}
+
+/// Link ที่ยังไม่ถูกโหลด (Token -> ไฟล์)
+pub struct DownloadLinks {
+    config: LinkConfig,
+    links: StdMutex<HashMap<String, Link>>,
+}
+
+impl std::fmt::Debug for DownloadLinks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DownloadLinks").field("port", &self.config.port).field("open", &self.links.lock().unwrap().len()).finish()
+    }
+}
+
+/// Token จาก Link เต็มหรือ Token เปล่าก็ได้
+pub fn token_of(link: &str) -> &str {
+    link.trim().trim_end_matches('/').rsplit('/').next().unwrap_or_default()
+}
+
+impl DownloadLinks {
+    pub fn new(config: LinkConfig) -> Arc<Self> {
+        Arc::new(Self { config, links: StdMutex::new(HashMap::new()) })
+    }
+
+    pub fn port(&self) -> u16 { self.config.port }
+
+    /// Token ใหม่ของไฟล์นี้ (ผู้เรียกตรวจว่าเป็นไฟล์แล้ว และเรียก expire เมื่อครบ ttl)
+    pub fn create(&self, path: PathBuf, ttl: Duration) -> String {
+        let mut bytes = [0u8; TOKEN_SIZE];
+        rand::thread_rng().fill(&mut bytes);
+        let token = hex::encode(bytes);
+        self.links.lock().unwrap().insert(token.clone(), Link { path, expires_at: Instant::now() + ttl });
+        token
+    }
+
+    pub fn url(&self, token: &str) -> String {
+        let host = self.config.host.clone().unwrap_or_else(MdnsBackend::get_local_ip);
+        let host = if host.contains(':') && !host.starts_with('[') { format!("[{}]", host) } else { host };
+        format!("http://{}:{}{}{}", host, self.config.port, ROUTE, token)
+    }
+
+    /// ปิด Link (false = ไม่มี / โหลดไปแล้ว / หมดอายุแล้ว)
+    pub fn revoke(&self, link: &str) -> bool {
+        self.links.lock().unwrap().remove(token_of(link)).is_some_and(|l| l.expires_at > Instant::now())
+    }
+
+    /// ครบ ttl: ทิ้ง Link (true = ยังไม่ถูกโหลด / ปิด -> แจ้งว่าหมดอายุ)
+    pub fn expire(&self, token: &str) -> bool {
+        self.links.lock().unwrap().remove(token).is_some()
+    }
+
+    /// หยิบ Link ออก (ใช้ได้ครั้งเดียว)
+    fn take(&self, token: &str) -> Option<Link> {
+        self.links.lock().unwrap().remove(token).filter(|l| l.expires_at > Instant::now())
+    }
+
+    pub async fn serve<CB>(self: Arc<Self>, listener: std::net::TcpListener, callback: CB, io: Duration, service: CancellationToken)
+    where CB: TransferCallback + Clone + 'static
+    {
+        // แปลงใน Runtime (start_service ถูกเรียกจาก Thread ที่ไม่มี Reactor)
+        let listener = match TcpListener::from_std(listener) {
+            Ok(l) => l,
+            Err(e) => { warn!("Download links unavailable: {}", e); return; }
+        };
+        info!("🔗 Download links on port {}", self.config.port);
+        loop {
+            let accepted = tokio::select! {
+                _ = service.cancelled() => break,
+                accepted = listener.accept() => accepted,
+            };
+            let Ok((stream, addr)) = accepted else {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                continue;
+            };
+            let (this, callback, service) = (self.clone(), callback.clone(), service.clone());
+            tokio::spawn(async move {
+                if let Err(e) = this.connection(stream, addr, &callback, io, &service).await {
+                    debug!("Download link connection from {} closed: {}", addr, e);
+                }
+            });
+        }
+    }
+
+    // Request เดียวต่อ Connection (Connection: close ทุกคำตอบ)
+    async fn connection<CB: TransferCallback>(&self, stream: TcpStream, addr: SocketAddr, callback: &CB, io: Duration, service: &CancellationToken) -> anyhow::Result<()> {
+        let (read, mut write) = stream.into_split();
+        let mut reader = BufReader::new(read);
+        let Some(request) = timeout(io, read_request(&mut reader)).await.context("Request timed out")?? else { return Ok(()) };
+        let Some(token) = request.path.strip_prefix(ROUTE) else {
+            return respond(&mut write, 404, "Not found\n", io).await;
+        };
+        if request.method != "GET" {
+            return respond(&mut write, 405, "Only GET is supported\n", io).await;
+        }
+        let Some(link) = self.take(token) else {
+            return respond(&mut write, 410, "This link has expired or was already used\n", io).await;
+        };
+        let task_id = format!("{}{}", TASK_PREFIX, token);
+        let ip = addr.ip().to_canonical();
+        match download(&mut write, &link.path, &task_id, callback, io, service).await {
+            Ok(()) => {
+                info!("🔗 {} downloaded by {}", link.path.display(), ip);
+                callback.on_complete(&task_id, &format!("Downloaded by {}", ip));
+                Ok(())
+            }
+            Err(e) => {
+                callback.on_error(&task_id, &e.to_string());
+                Err(e)
+            }
+        }
+    }
+}
+
+// ชื่อไฟล์ใน Header: filename= แบบ ASCII ให้ Browser เก่า + filename* (RFC 5987) ให้ชื่อภาษาไทยถูกต้อง
+fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename.chars().map(|c| if c == ' ' || (c.is_ascii_graphic() && c != '"' && c != '\\') { c } else { '_' }).collect();
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, percent_encode(filename))
+}
+
+async fn respond<W: AsyncWrite + Unpin>(writer: &mut W, status: u16, body: &str, io: Duration) -> anyhow::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, reason(status), body.len(), body,
+    );
+    timeout(io, writer.write_all(response.as_bytes())).await.context("Response timed out")??;
+    let _ = writer.shutdown().await;
+    Ok(())
+}
+
+async fn download<W, CB>(writer: &mut W, path: &Path, task_id: &str, callback: &CB, io: Duration, service: &CancellationToken) -> anyhow::Result<()>
+where W: AsyncWrite + Unpin, CB: TransferCallback
+{
+    let mut file = match tokio::fs::File::open(path).await {
+        Ok(file) => file,
+        Err(e) => {
+            respond(writer, 404, "File is no longer available\n", io).await?;
+            bail!("Failed to open {}: {}", path.display(), e);
+        }
+    };
+    let size = file.metadata().await?.len();
+    let filename = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\nContent-Disposition: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        size, content_disposition(&filename),
+    );
+    timeout(io, writer.write_all(head.as_bytes())).await.context("Response timed out")??;
+    callback.on_start(task_id, &filename);
+    let mut buf = vec![0u8; BUFFER];
+    let (mut sent, mut last_progress) = (0u64, Instant::now());
+    while sent < size {
+        let n = file.read(&mut buf).await?;
+        if n == 0 { bail!("File shrank while downloading"); }
+        let n = n.min((size - sent) as usize);
+        tokio::select! {
+            _ = service.cancelled() => bail!("Service stopped"),
+            written = timeout(io, writer.write_all(&buf[..n])) => written.context("Browser stopped reading")??,
+        }
+        sent += n as u64;
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            callback.on_progress(task_id, sent, size);
+            last_progress = Instant::now();
+        }
+    }
+    callback.on_progress(task_id, sent, size);
+    timeout(io, writer.flush()).await.context("Response timed out")??;
+    let _ = writer.shutdown().await;
+    Ok(())
+}
@@ -0,0 +1,794 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+use rustls::{Certificate, ClientConfig, PrivateKey, ServerConfig, ServerName};
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+use tracing::{debug, info, warn};
+
+use crate::core::cancel::{CancellationToken, TransferSignal, REJECT_CANCELLED};
+use crate::core::discovery::{DeviceType, DiscoveryInternalEvent, PeerProfile};
+use crate::core::handlers::ReceiveOptions;
+use crate::core::notification::UserResponse;
+use crate::core::security;
+use crate::core::transfer::{DataStream, TransferCallback, REJECT_BUSY};
+use crate::core::utils;
+
+// ==========================================
+// LocalSend Interop ([localsend] enabled = true)
+// พูด HTTP API v2 ของ LocalSend (https://github.com/localsend/protocol) คุยกับแอป LocalSend บนมือถือได้โดยไม่ต้องลง DropTea
+// - Discovery: Multicast 224.0.0.167:<port> (ดู discovery/localsend.rs) + POST /register
+// - รับ: prepare-upload (ถาม User ครั้งเดียวทั้งชุด) -> upload ทีละไฟล์ (Body ดิบ) ลง Save Path
+// - ส่ง: Peer ที่มี Feature "localsend=<http|https>" ถูกส่งผ่าน API นี้แทน Protocol ของเรา (ดู spawn_send)
+// Cert ของ LocalSend ไม่ได้ผูกกับตัวตน (ฝั่งนั้นรับ Self-signed ทุกตัว) -> ไม่มี TOFU / Whitelist: ถาม User ทุก Session
+// ==========================================
+
+pub const DEFAULT_PORT: u16 = 53317;
+pub const MULTICAST_ADDR: std::net::Ipv4Addr = std::net::Ipv4Addr::new(224, 0, 0, 167);
+// "localsend=https" / "localsend=http" ตาม protocol ที่ Peer ประกาศ
+pub const FEATURE_PREFIX: &str = "localsend=";
+pub const PROTOCOL_VERSION: &str = "2.1";
+const API: &str = "/api/localsend/v2";
+// Request Line + Header รวมกัน / Body ที่เป็น JSON
+const MAX_HEAD: usize = 16 * 1024;
+const MAX_JSON_BODY: u64 = 1024 * 1024;
+const UPLOAD_BUFFER: usize = 64 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+// Session ที่ผู้ส่งหายไปกลางทาง ไม่ให้ค้างบล็อกคนถัดไปตลอด
+const SESSION_IDLE: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone)]
+pub struct LocalSendConfig {
+    pub port: u16,
+    // false = HTTP เปล่า (LocalSend ตั้ง Encryption ปิดได้)
+    pub https: bool,
+}
+
+impl Default for LocalSendConfig {
+    fn default() -> Self {
+        Self { port: DEFAULT_PORT, https: true }
+    }
+}
+
+// ข้อมูลเครื่องของ LocalSend (Multicast / register / info ใช้ร่วมกัน)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfo {
+    pub alias: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub device_model: Option<String>,
+    #[serde(default)]
+    pub device_type: Option<String>,
+    pub fingerprint: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+    #[serde(default)]
+    pub download: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub announce: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileMeta {
+    pub id: String,
+    pub file_name: String,
+    pub size: u64,
+    #[serde(default)]
+    pub file_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PrepareUpload {
+    info: DeviceInfo,
+    files: HashMap<String, FileMeta>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PrepareResponse {
+    session_id: String,
+    files: HashMap<String, String>,
+}
+
+// ไม่ตรวจ Cert ของปลายทาง: LocalSend ใช้ Self-signed ที่ไม่ได้ผูกกับอะไร (ตัวแอปเองก็รับทุก Cert)
+struct AnyCert;
+
+impl ServerCertVerifier for AnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: std::time::SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+fn local_type(device_type: DeviceType) -> &'static str {
+    match device_type {
+        DeviceType::Phone => "mobile",
+        DeviceType::Laptop | DeviceType::Desktop => "desktop",
+        DeviceType::Server => "server",
+    }
+}
+
+fn remote_type(device_type: &str) -> Option<DeviceType> {
+    match device_type {
+        "mobile" => Some(DeviceType::Phone),
+        "desktop" => Some(DeviceType::Desktop),
+        "server" | "headless" => Some(DeviceType::Server),
+        _ => None,
+    }
+}
+
+/// "https" / "http" จาก Feature ของ Peer (None = ไม่ใช่ Peer ของ LocalSend)
+pub fn peer_protocol(features: &[String]) -> Option<bool> {
+    features.iter().find_map(|f| f.strip_prefix(FEATURE_PREFIX)).map(|p| p != "http")
+}
+
+// --- Session ฝั่งรับ (LocalSend รับได้ทีละ Session) ---
+
+struct InboundFile {
+    token: String,
+    meta: FileMeta,
+    // กำลังรับ / รับแล้ว (upload ซ้ำ = 409)
+    claimed: bool,
+    done: bool,
+}
+
+struct Inbound {
+    id: String,
+    ip: IpAddr,
+    sender: String,
+    // false = ยังรอ User ตัดสินใจ
+    approved: bool,
+    files: HashMap<String, InboundFile>,
+    last_active: Instant,
+}
+
+/// ของที่ Server ต้องใช้รับไฟล์ (ชุดเดียวกับ handle_incoming)
+pub struct ReceiveContext<CB> {
+    pub callback: CB,
+    pub pending_map: Arc<StdMutex<HashMap<String, mpsc::UnboundedSender<UserResponse>>>>,
+    pub options: ReceiveOptions,
+}
+
+pub struct LocalSend {
+    config: LocalSendConfig,
+    alias: String,
+    device_type: DeviceType,
+    fingerprint: String,
+    tls: Option<TlsAcceptor>,
+    connector: TlsConnector,
+    // ตั้งโดย Discovery Backend ตอน start (register ของอีกฝั่งเข้าตาราง Peer ทางนี้)
+    peers: StdMutex<Option<mpsc::Sender<DiscoveryInternalEvent>>>,
+    inbound: StdMutex<Option<Inbound>>,
+}
+
+impl LocalSend {
+    /// identity = Cert ของ Engine (None = สร้างชั่วคราว) ใช้เฉพาะ HTTPS
+    pub fn new(config: LocalSendConfig, alias: &str, device_type: DeviceType, identity: Option<(Vec<Certificate>, PrivateKey)>) -> anyhow::Result<Arc<Self>> {
+        let (tls, fingerprint) = match config.https {
+            true => {
+                let (certs, key) = match identity {
+                    Some(identity) => identity,
+                    None => security::generate_temp_identity()?,
+                };
+                // LocalSend: Fingerprint ในโหมด HTTPS = SHA-256 ของ Cert
+                let fingerprint = certs.first().map(|c| hex::encode(Sha256::digest(&c.0))).context("LocalSend certificate missing")?;
+                let server = ServerConfig::builder().with_safe_defaults().with_no_client_auth().with_single_cert(certs, key)?;
+                (Some(TlsAcceptor::from(Arc::new(server))), fingerprint)
+            }
+            // โหมด HTTP: สุ่มใหม่ทุกครั้ง (ใช้แค่กันเห็นตัวเอง)
+            false => (None, uuid::Uuid::new_v4().simple().to_string()),
+        };
+        let client = ClientConfig::builder().with_safe_defaults().with_custom_certificate_verifier(Arc::new(AnyCert)).with_no_client_auth();
+        Ok(Arc::new(Self {
+            config,
+            alias: alias.to_string(),
+            device_type,
+            fingerprint,
+            tls,
+            connector: TlsConnector::from(Arc::new(client)),
+            peers: StdMutex::new(None),
+            inbound: StdMutex::new(None),
+        }))
+    }
+
+    pub fn port(&self) -> u16 { self.config.port }
+    pub fn fingerprint(&self) -> &str { &self.fingerprint }
+
+    pub fn info(&self, announce: Option<bool>) -> DeviceInfo {
+        DeviceInfo {
+            alias: self.alias.clone(),
+            version: PROTOCOL_VERSION.to_string(),
+            device_model: Some("DropTea".to_string()),
+            device_type: Some(local_type(self.device_type).to_string()),
+            fingerprint: self.fingerprint.clone(),
+            port: Some(self.config.port),
+            protocol: Some(if self.config.https { "https" } else { "http" }.to_string()),
+            download: false,
+            announce,
+        }
+    }
+
+    pub fn attach(&self, tx: mpsc::Sender<DiscoveryInternalEvent>) {
+        *self.peers.lock().unwrap() = Some(tx);
+    }
+
+    pub fn detach(&self) {
+        self.peers.lock().unwrap().take();
+    }
+
+    /// เครื่อง LocalSend ที่ประกาศตัว / register เข้ามา -> Peer ในตารางเดียวกับ mDNS (ของตัวเอง = ข้าม)
+    pub async fn found(&self, info: DeviceInfo, from: IpAddr) {
+        if info.fingerprint == self.fingerprint { return; }
+        let Some(tx) = self.peers.lock().unwrap().clone() else { return };
+        let ip = from.to_canonical().to_string();
+        let protocol = match info.protocol.as_deref() {
+            Some("http") => "http",
+            _ => "https",
+        };
+        debug!("📱 LocalSend device {} ({})", info.alias, ip);
+        let _ = tx.send(DiscoveryInternalEvent::MdnsFound {
+            id: format!("localsend:{}", info.fingerprint),
+            name: info.alias,
+            ip: ip.clone(),
+            addrs: vec![ip],
+            port: info.port.unwrap_or(DEFAULT_PORT),
+            compression: None,
+            features: vec![format!("{}{}", FEATURE_PREFIX, protocol)],
+            external_addr: None,
+            control_port: None,
+            private: false,
+            guest: false,
+            // SHA-256 ไม่ใช่ blake3 ของเรา -> ไม่เอาไปเป็น Key ของ Peer Registry
+            fingerprint: None,
+            profile: PeerProfile {
+                os: None,
+                device_type: info.device_type.as_deref().and_then(remote_type),
+                protocol_version: None,
+                transports: vec!["localsend".to_string()],
+                availability: None,
+            },
+        }).await;
+    }
+
+    // ==========================================
+    // Server (ฝั่งรับ)
+    // ==========================================
+
+    pub async fn serve<CB>(self: Arc<Self>, listener: std::net::TcpListener, ctx: Arc<ReceiveContext<CB>>, service: CancellationToken)
+    where CB: TransferCallback + Clone + 'static
+    {
+        // แปลงใน Runtime (start_service ถูกเรียกจาก Thread ที่ไม่มี Reactor)
+        let listener = match TcpListener::from_std(listener) {
+            Ok(l) => l,
+            Err(e) => { warn!("LocalSend API unavailable: {}", e); return; }
+        };
+        info!("📱 LocalSend API on port {} ({})", self.config.port, if self.config.https { "https" } else { "http" });
+        loop {
+            let accepted = tokio::select! {
+                _ = service.cancelled() => break,
+                accepted = listener.accept() => accepted,
+            };
+            let Ok((stream, addr)) = accepted else {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                continue;
+            };
+            let (this, ctx, service) = (self.clone(), ctx.clone(), service.clone());
+            tokio::spawn(async move {
+                let result = match &this.tls {
+                    Some(tls) => match timeout(ctx.options.timeouts.io, tls.accept(stream)).await {
+                        Ok(Ok(stream)) => this.connection(stream, addr, &ctx, &service).await,
+                        Ok(Err(e)) => Err(e.into()),
+                        Err(_) => Err(anyhow::anyhow!("TLS handshake timed out")),
+                    },
+                    None => this.connection(stream, addr, &ctx, &service).await,
+                };
+                if let Err(e) = result { debug!("LocalSend connection from {} closed: {}", addr, e); }
+            });
+        }
+    }
+
+    // Keep-Alive: หลาย Request ต่อ Connection จนอีกฝั่งปิด / Request ที่ Body ยังค้างอยู่
+    async fn connection<S, CB>(&self, stream: S, addr: SocketAddr, ctx: &ReceiveContext<CB>, service: &CancellationToken) -> anyhow::Result<()>
+    where S: AsyncRead + AsyncWrite + Unpin, CB: TransferCallback + Clone + 'static
+    {
+        let ip = addr.ip().to_canonical();
+        let (read, mut write) = tokio::io::split(stream);
+        let mut reader = BufReader::new(read);
+        loop {
+            let request = tokio::select! {
+                _ = service.cancelled() => return Ok(()),
+                request = read_request(&mut reader) => request?,
+            };
+            let Some(request) = request else { return Ok(()) };
+            let reply = self.route(&request, &mut reader, ip, ctx, service).await;
+            let close = reply.close || !request.keep_alive;
+            write_response(&mut write, reply.status, &reply.body, close).await?;
+            if close { return Ok(()); }
+        }
+    }
+
+    async fn route<R, CB>(&self, request: &Request, reader: &mut R, ip: IpAddr, ctx: &ReceiveContext<CB>, service: &CancellationToken) -> Reply
+    where R: AsyncBufRead + Unpin, CB: TransferCallback + Clone + 'static
+    {
+        let endpoint = request.path.strip_prefix(API).unwrap_or("");
+        match (request.method.as_str(), endpoint) {
+            ("GET", "/info") => Reply::json(&self.info(None)),
+            ("POST", "/register") => match read_json::<_, DeviceInfo>(reader, request).await {
+                Ok(info) => {
+                    self.found(info, ip).await;
+                    Reply::json(&self.info(None))
+                }
+                Err(e) => Reply::error(400, &e.to_string()).closing(),
+            },
+            ("POST", "/prepare-upload") => match read_json::<_, PrepareUpload>(reader, request).await {
+                Ok(prepare) => self.prepare_upload(prepare, ip, ctx).await,
+                Err(e) => Reply::error(400, &e.to_string()).closing(),
+            },
+            ("POST", "/upload") => self.upload(request, reader, ip, ctx, service).await,
+            ("POST", "/cancel") => {
+                let mut inbound = self.inbound.lock().unwrap();
+                if inbound.as_ref().is_some_and(|s| s.ip == ip && request.query.get("sessionId") == Some(&s.id)) {
+                    info!("LocalSend session cancelled by sender");
+                    inbound.take();
+                }
+                Reply::empty(200).closing_if(request.content_length.unwrap_or(0) > 0)
+            }
+            _ => Reply::error(404, "Not found").closing_if(request.content_length.unwrap_or(0) > 0),
+        }
+    }
+
+    // ถามครั้งเดียวทั้งชุดแบบ Session Offer
+    // ถามทุกครั้งแม้ alias ตรงกับ Whitelist: alias เป็นแค่ Field ใน JSON ไม่มี Cert ผูก ใครใน LAN ก็อ้างชื่อนั้นได้
+    async fn prepare_upload<CB>(&self, prepare: PrepareUpload, ip: IpAddr, ctx: &ReceiveContext<CB>) -> Reply
+    where CB: TransferCallback + Clone + 'static
+    {
+        if prepare.files.is_empty() { return Reply::empty(204); }
+        let session_id = uuid::Uuid::new_v4().to_string();
+        {
+            let mut inbound = self.inbound.lock().unwrap();
+            if inbound.as_ref().is_some_and(|s| s.last_active.elapsed() < SESSION_IDLE) {
+                return Reply::error(409, "Blocked by another session");
+            }
+            *inbound = Some(Inbound {
+                id: session_id.clone(),
+                ip,
+                sender: prepare.info.alias.clone(),
+                approved: false,
+                files: prepare.files.iter().map(|(id, meta)| (id.clone(), InboundFile { token: uuid::Uuid::new_v4().to_string(), meta: meta.clone(), claimed: false, done: false })).collect(),
+                last_active: Instant::now(),
+            });
+        }
+        let options = &ctx.options;
+        let total: u64 = prepare.files.values().map(|f| f.size).sum();
+        let sender = &prepare.info.alias;
+        let accepted = if let Some(reason) = options.availability.get().reject_reason() {
+            info!("Declining LocalSend session from '{}': {}", sender, reason);
+            false
+        } else {
+            let task_id = uuid::Uuid::new_v4().to_string();
+            let summary = match prepare.files.values().next() {
+                Some(file) if prepare.files.len() == 1 => file.file_name.clone(),
+                _ => format!("{} files", prepare.files.len()),
+            };
+            let device = prepare.info.device_model.clone().unwrap_or_else(|| "LocalSend".to_string());
+            let (tx, mut rx) = mpsc::unbounded_channel();
+            if let Ok(mut map) = ctx.pending_map.lock() { map.insert(task_id.clone(), tx); }
+            let _ = ctx.callback.ask_accept_file(&task_id, &summary, total, sender, &device, Some(&session_id), None, None, &Default::default());
+            let response = timeout(options.timeouts.user_decision, rx.recv()).await;
+            if let Ok(mut map) = ctx.pending_map.lock() { map.remove(&task_id); }
+            match response {
+                Ok(Some(UserResponse::Accept)) => true,
+                Err(_) => { ctx.callback.on_reject(&task_id, "Timeout"); false }
+                _ => { ctx.callback.on_reject(&task_id, "User Rejected"); false }
+            }
+        };
+        let mut inbound = self.inbound.lock().unwrap();
+        // ผู้ส่งยกเลิกระหว่างรอ
+        let Some(session) = inbound.as_mut().filter(|s| s.id == session_id) else {
+            return Reply::error(403, "Session cancelled");
+        };
+        if !accepted {
+            inbound.take();
+            return Reply::error(403, "Rejected");
+        }
+        info!("LocalSend session of {} files from '{}' approved", session.files.len(), sender);
+        session.approved = true;
+        session.last_active = Instant::now();
+        let files = session.files.iter().map(|(id, f)| (id.clone(), f.token.clone())).collect();
+        Reply::json(&PrepareResponse { session_id, files })
+    }
+
+    async fn upload<R, CB>(&self, request: &Request, reader: &mut R, ip: IpAddr, ctx: &ReceiveContext<CB>, service: &CancellationToken) -> Reply
+    where R: AsyncBufRead + Unpin, CB: TransferCallback + Clone + 'static
+    {
+        let param = |key: &str| request.query.get(key).cloned().unwrap_or_default();
+        let (session_id, file_id, token) = (param("sessionId"), param("fileId"), param("token"));
+        // Body ยังไม่ได้อ่าน -> ตอบ Error แล้วปิด Connection
+        let meta = {
+            let mut inbound = self.inbound.lock().unwrap();
+            let Some(session) = inbound.as_mut().filter(|s| s.id == session_id && s.ip == ip && s.approved) else {
+                return Reply::error(403, "Invalid session").closing();
+            };
+            let Some(file) = session.files.get_mut(&file_id).filter(|f| f.token == token) else {
+                return Reply::error(403, "Invalid token").closing();
+            };
+            if file.claimed { return Reply::error(409, "Already uploaded").closing(); }
+            file.claimed = true;
+            session.last_active = Instant::now();
+            file.meta.clone()
+        };
+        let Some(len) = request.content_length.filter(|len| *len == meta.size) else {
+            self.unclaim(&session_id, &file_id);
+            return Reply::error(if request.chunked { 411 } else { 400 }, "Content-Length must match file size").closing();
+        };
+        let task_id = uuid::Uuid::new_v4().to_string();
+        let transfer = ctx.options.transfers.register(service, &task_id);
+        ctx.callback.on_start(&task_id, &meta.file_name);
+        match receive_file(&mut *reader, len, &meta, &task_id, ctx, transfer.signal()).await {
+            Ok(path) => {
+                info!("📱 Received '{}' from LocalSend", meta.file_name);
+                ctx.callback.on_complete(&task_id, &path.to_string_lossy());
+                let mut inbound = self.inbound.lock().unwrap();
+                if let Some(session) = inbound.as_mut().filter(|s| s.id == session_id) {
+                    if let Some(file) = session.files.get_mut(&file_id) { file.done = true; }
+                    session.last_active = Instant::now();
+                    if session.files.values().all(|f| f.done) {
+                        debug!("LocalSend session from '{}' finished", session.sender);
+                        inbound.take();
+                    }
+                }
+                Reply::empty(200)
+            }
+            Err(e) => {
+                self.unclaim(&session_id, &file_id);
+                match transfer.signal().is_cancelled() {
+                    true => ctx.callback.on_reject(&task_id, REJECT_CANCELLED),
+                    false => ctx.callback.on_error(&task_id, &e.to_string()),
+                }
+                Reply::error(500, &e.to_string()).closing()
+            }
+        }
+    }
+
+    fn unclaim(&self, session_id: &str, file_id: &str) {
+        if let Some(session) = self.inbound.lock().unwrap().as_mut().filter(|s| s.id == session_id) {
+            if let Some(file) = session.files.get_mut(file_id) { file.claimed = false; }
+        }
+    }
+
+    // ==========================================
+    // Client (ฝั่งส่ง)
+    // ==========================================
+
+    async fn connect(&self, host: &str, port: u16, https: bool, io: Duration) -> anyhow::Result<Box<dyn DataStream>> {
+        let (ip, scope_id) = utils::parse_scoped_ip(host).context("Invalid LocalSend address")?;
+        let stream = timeout(io, TcpStream::connect(utils::scoped_socket_addr(ip, port, scope_id))).await.context("LocalSend connect timed out")??;
+        let stream: Box<dyn DataStream> = match https {
+            true => Box::new(timeout(io, self.connector.connect(ServerName::IpAddress(ip), stream)).await.context("LocalSend TLS handshake timed out")??),
+            false => Box::new(stream),
+        };
+        Ok(stream)
+    }
+
+    // Request เดียวต่อ Connection (Connection: close) คืน Status + Body (รอคำตอบไม่จำกัด ผู้เรียกครอบ timeout เอง)
+    async fn call(&self, host: &str, port: u16, https: bool, target: &str, body: &[u8], io: Duration) -> anyhow::Result<(u16, Vec<u8>)> {
+        let mut stream = self.connect(host, port, https, io).await?;
+        write_request_head(&mut stream, host, port, target, "application/json", body.len() as u64).await?;
+        stream.write_all(body).await?;
+        stream.flush().await?;
+        read_response(&mut stream).await
+    }
+
+    /// prepare-upload (อีกฝั่งกดรับ) แล้ว upload ไฟล์เดียว Event ผ่าน callback เหมือน handle_sending
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send<CB>(&self, host: &str, port: u16, https: bool, path: &str, filename: &str, task_id: &str, callback: &CB, signal: &TransferSignal, decision_timeout: Duration, io: Duration) -> anyhow::Result<()>
+    where CB: TransferCallback
+    {
+        let size = tokio::fs::metadata(path).await.context("Failed to read source file")?.len();
+        let meta = FileMeta {
+            id: task_id.to_string(),
+            file_name: filename.to_string(),
+            size,
+            file_type: "application/octet-stream".to_string(),
+            sha256: None,
+        };
+        let prepare = serde_json::to_vec(&PrepareUpload { info: self.info(None), files: HashMap::from([(task_id.to_string(), meta)]) })?;
+        info!("📱 Offering '{}' ({} bytes) to LocalSend at {}", filename, size, host);
+        let target = format!("{}/prepare-upload", API);
+        let answer = tokio::select! {
+            r = timeout(decision_timeout, self.call(host, port, https, &target, &prepare, io)) => r,
+            _ = signal.cancelled() => { callback.on_reject(task_id, REJECT_CANCELLED); return Ok(()); }
+        };
+        let session: PrepareResponse = match answer {
+            Err(_) => { callback.on_reject(task_id, "Timeout"); return Ok(()); }
+            Ok(answer) => match answer? {
+                (200, body) => serde_json::from_slice(&body).context("Invalid prepare-upload response")?,
+                // อีกฝั่งไม่ต้องการไฟล์นี้
+                (204, _) => { callback.on_complete(task_id, "Success"); return Ok(()); }
+                (403, _) => { callback.on_reject(task_id, "Receiver Rejected"); return Ok(()); }
+                (409 | 429, _) => { callback.on_reject(task_id, REJECT_BUSY); return Ok(()); }
+                (status, _) => bail!("LocalSend prepare-upload failed with HTTP {}", status),
+            },
+        };
+        let token = session.files.get(task_id).context("LocalSend receiver did not return an upload token")?;
+
+        callback.on_start(task_id, filename);
+        let target = format!("{}/upload?sessionId={}&fileId={}&token={}", API, percent_encode(&session.session_id), percent_encode(task_id), percent_encode(token));
+        let upload = async {
+            let mut stream = self.connect(host, port, https, io).await?;
+            write_request_head(&mut stream, host, port, &target, "application/octet-stream", size).await?;
+            let mut file = tokio::fs::File::open(path).await.context("Failed to open source file")?;
+            let (mut buf, mut sent, mut last) = (vec![0u8; UPLOAD_BUFFER], 0u64, Instant::now());
+            while sent < size {
+                let n = file.read(&mut buf).await?;
+                if n == 0 { bail!("Source file shrank during upload"); }
+                let n = n.min((size - sent) as usize);
+                timeout(io, stream.write_all(&buf[..n])).await.context("LocalSend upload stalled")??;
+                sent += n as u64;
+                if last.elapsed() >= PROGRESS_INTERVAL || sent == size {
+                    callback.on_progress(task_id, sent, size);
+                    last = Instant::now();
+                }
+            }
+            stream.flush().await?;
+            timeout(io, read_response(&mut stream)).await.context("LocalSend upload response timed out")?
+        };
+        let answer = tokio::select! {
+            r = upload => r?,
+            _ = signal.cancelled() => {
+                let target = format!("{}/cancel?sessionId={}", API, percent_encode(&session.session_id));
+                let _ = timeout(io, self.call(host, port, https, &target, &[], io)).await;
+                callback.on_reject(task_id, REJECT_CANCELLED);
+                return Ok(());
+            }
+        };
+        match answer {
+            (200, _) => { callback.on_complete(task_id, "Success (LocalSend)"); Ok(()) }
+            (status, _) => bail!("LocalSend upload failed with HTTP {}", status),
+        }
+    }
+}
+
+// Body ดิบลง .part แล้ว Rename (ตรวจ SHA-256 ถ้าผู้ส่งแนบมา)
+async fn receive_file<R, CB>(reader: &mut R, len: u64, meta: &FileMeta, task_id: &str, ctx: &ReceiveContext<CB>, signal: &TransferSignal) -> anyhow::Result<std::path::PathBuf>
+where R: AsyncRead + Unpin, CB: TransferCallback
+{
+    let save_path = ctx.options.download_dir.get();
+    tokio::fs::create_dir_all(&save_path).await.context("Failed to create save directory")?;
+    // fileName ของ LocalSend มีโฟลเดอร์ย่อยได้ ("dir/a.txt") -> เก็บแค่ชื่อไฟล์ (get_unique_path ตัดให้)
+    let dest = utils::get_unique_path(&save_path, &meta.file_name);
+    let part = dest.with_file_name(format!("{}.part", dest.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()));
+    let result = async {
+        let mut file = tokio::fs::File::create(&part).await.context("Failed to create file")?;
+        let mut body = reader.take(len);
+        let mut hasher = meta.sha256.as_ref().map(|_| Sha256::new());
+        let (mut buf, mut received, mut last) = (vec![0u8; UPLOAD_BUFFER], 0u64, Instant::now());
+        while received < len {
+            let n = tokio::select! {
+                n = timeout(ctx.options.timeouts.io, body.read(&mut buf)) => n.context("LocalSend upload stalled")??,
+                _ = signal.cancelled() => bail!("Cancelled"),
+            };
+            if n == 0 { bail!("LocalSend upload ended early ({} of {} bytes)", received, len); }
+            file.write_all(&buf[..n]).await?;
+            if let Some(hasher) = hasher.as_mut() { hasher.update(&buf[..n]); }
+            received += n as u64;
+            if last.elapsed() >= PROGRESS_INTERVAL || received == len {
+                ctx.callback.on_progress(task_id, received, len);
+                last = Instant::now();
+            }
+        }
+        file.flush().await?;
+        if let (Some(hasher), Some(expected)) = (hasher, meta.sha256.as_deref()) {
+            if hex::encode(hasher.finalize()) != expected.to_lowercase() { bail!("SHA-256 mismatch"); }
+        }
+        tokio::fs::rename(&part, &dest).await.context("Failed to move received file")?;
+        Ok(dest)
+    }.await;
+    if result.is_err() { let _ = tokio::fs::remove_file(&part).await; }
+    result
+}
+
+// ==========================================
+// HTTP/1.1 แบบพอใช้กับ LocalSend (Content-Length เท่านั้น ไม่รองรับ chunked) / links.rs ใช้ฝั่ง Server ด้วย
+// ==========================================
+
+pub(crate) struct Request {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    query: HashMap<String, String>,
+    content_length: Option<u64>,
+    chunked: bool,
+    keep_alive: bool,
+}
+
+struct Reply {
+    status: u16,
+    body: Vec<u8>,
+    // Body ของ Request ยังค้างใน Stream -> อ่าน Request ถัดไปไม่ได้
+    close: bool,
+}
+
+impl Reply {
+    fn json<T: Serialize>(value: &T) -> Self {
+        Self { status: 200, body: serde_json::to_vec(value).unwrap_or_default(), close: false }
+    }
+
+    fn empty(status: u16) -> Self {
+        Self { status, body: Vec::new(), close: false }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self { status, body: serde_json::to_vec(&serde_json::json!({ "message": message })).unwrap_or_default(), close: false }
+    }
+
+    fn closing(self) -> Self {
+        Self { close: true, ..self }
+    }
+
+    fn closing_if(self, close: bool) -> Self {
+        Self { close: self.close || close, ..self }
+    }
+}
+
+pub(crate) fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        410 => "Gone",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => match std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                Some(b) => { out.push(b); i += 3; continue; }
+                None => out.push(b'%'),
+            },
+            b'+' => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// None = อีกฝั่งปิด Connection ก่อนส่ง Request ใหม่
+pub(crate) async fn read_request<R: AsyncBufRead + Unpin>(reader: &mut R) -> anyhow::Result<Option<Request>> {
+    let mut lines = Vec::new();
+    let mut size = 0usize;
+    loop {
+        let mut line = Vec::new();
+        let n = (&mut *reader).take((MAX_HEAD - size) as u64).read_until(b'\n', &mut line).await?;
+        if n == 0 {
+            if lines.is_empty() { return Ok(None); }
+            bail!("Connection closed inside request head");
+        }
+        size += n;
+        if !line.ends_with(b"\n") { bail!("Request head too large"); }
+        let line = String::from_utf8_lossy(&line).trim_end().to_string();
+        if line.is_empty() {
+            if lines.is_empty() { continue; }
+            break;
+        }
+        lines.push(line);
+    }
+    let mut request_line = lines[0].split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else { bail!("Malformed request line") };
+    let version = request_line.next().unwrap_or("HTTP/1.0");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query.split('&').filter_map(|pair| pair.split_once('=')).map(|(k, v)| (percent_decode(k), percent_decode(v))).collect();
+    let headers: HashMap<String, String> = lines[1..].iter()
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+        .collect();
+    let connection = headers.get("connection").map(|c| c.to_lowercase());
+    Ok(Some(Request {
+        method: method.to_uppercase(),
+        path: path.to_string(),
+        query,
+        content_length: headers.get("content-length").and_then(|l| l.parse().ok()),
+        chunked: headers.get("transfer-encoding").is_some_and(|t| t.to_lowercase().contains("chunked")),
+        keep_alive: match version {
+            "HTTP/1.0" => connection.as_deref() == Some("keep-alive"),
+            _ => connection.as_deref() != Some("close"),
+        },
+    }))
+}
+
+async fn read_json<R, T>(reader: &mut R, request: &Request) -> anyhow::Result<T>
+where R: AsyncBufRead + Unpin, T: serde::de::DeserializeOwned
+{
+    let len = request.content_length.context("Content-Length required")?;
+    if len > MAX_JSON_BODY { bail!("Request body too large"); }
+    let mut body = vec![0u8; len as usize];
+    reader.read_exact(&mut body).await?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+async fn write_response<W: AsyncWrite + Unpin>(writer: &mut W, status: u16, body: &[u8], close: bool) -> anyhow::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: {}\r\n\r\n",
+        status, reason(status), body.len(), if close { "close" } else { "keep-alive" },
+    );
+    writer.write_all(head.as_bytes()).await?;
+    writer.write_all(body).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+async fn write_request_head<W: AsyncWrite + Unpin>(writer: &mut W, host: &str, port: u16, target: &str, content_type: &str, len: u64) -> anyhow::Result<()> {
+    let host = if host.contains(':') && !host.starts_with('[') { format!("[{}]", host) } else { host.to_string() };
+    let head = format!(
+        "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        target, host, port, content_type, len,
+    );
+    writer.write_all(head.as_bytes()).await?;
+    Ok(())
+}
+
+// Status + Body (Content-Length หรืออ่านจนปิด Connection)
+async fn read_response<R: AsyncRead + Unpin>(reader: &mut R) -> anyhow::Result<(u16, Vec<u8>)> {
+    let mut reader = BufReader::new(reader);
+    let mut status_line = Vec::new();
+    (&mut reader).take(MAX_HEAD as u64).read_until(b'\n', &mut status_line).await?;
+    let status = String::from_utf8_lossy(&status_line).split_whitespace().nth(1).and_then(|s| s.parse().ok()).context("Malformed HTTP response")?;
+    let mut content_length = None;
+    loop {
+        let mut line = Vec::new();
+        if (&mut reader).take(MAX_HEAD as u64).read_until(b'\n', &mut line).await? == 0 { break; }
+        let line = String::from_utf8_lossy(&line).trim_end().to_string();
+        if line.is_empty() { break; }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") { content_length = value.trim().parse::<u64>().ok(); }
+        }
+    }
+    let mut body = Vec::new();
+    (&mut reader).take(content_length.unwrap_or(MAX_JSON_BODY).min(MAX_JSON_BODY)).read_to_end(&mut body).await?;
+    Ok((status, body))
+}
+
+pub(crate) fn percent_encode(value: &str) -> String {
+    value.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+        b => format!("%{:02X}", b),
+    }).collect()
+}
+
+/// Bind TCP ของ API (Dual-Stack เหมือน Transport หลัก)
+pub fn bind(port: u16) -> anyhow::Result<std::net::TcpListener> {
+    Ok(utils::bind_dual_stack(port)?)
+}
@@ -0,0 +1,176 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use anyhow::{bail, Context};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, ReadBuf};
+
+use crate::core::cancel::{CancellationToken, TransferSignal};
+use crate::core::compression::{self, CompressionAlgo, Compressor, Decompressor};
+use crate::core::engine::TransportMode;
+use crate::core::security;
+use crate::core::stats::{StatsCollector, StatsStore, TransferStats};
+use crate::core::transfer::{
+    copy_pipeline, pack_ack, unpack_ack, DynTransport, FileHeader, Timeouts,
+    ACK_SIZE, LOCAL_CAPABILITIES, MAX_HEADER_SIZE, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
+use crate::core::transports::{plain_tcp::PlainTcpTransport, quic::QuicTransport, tcp::TcpTransport};
+use crate::core::tuning;
+
+// ==========================================
+// Loopback Benchmark (จูนเครื่องนี้ก่อนใช้จริง)
+// ส่งข้อมูลสังเคราะห์หาตัวเองบน 127.0.0.1 ผ่านของจริงทั้งเส้น: Transport (TLS/QUIC) -> FileHeader + ACK -> Compressor -> copy_pipeline
+// ไม่อ่าน/เขียน Disk -> ตัวเลขคือเพดานของ CPU + Network Stack แยกตาม Transport x Compression
+// Identity แบบ Guest (ไม่แตะ Cert / known_hosts ของเครื่อง) และ Port ว่างที่สุ่มเอง (ไม่ชน Engine ที่รันอยู่)
+// ==========================================
+
+/// Transport ที่วัดบน Loopback ได้ (WebRTC ต้องมี Signaling ก่อน)
+pub const LOOPBACK_TRANSPORTS: &[TransportMode] = &[TransportMode::Tcp, TransportMode::Quic, TransportMode::PlainTcp];
+pub const DEFAULT_BENCH_SIZE: u64 = 64 * 1024 * 1024;
+const BENCH_NODE_NAME: &str = "droptea-bench";
+const BENCH_TASK_ID: &str = "loopback-bench";
+const READ_BUFFER_SIZE: usize = 256 * 1024;
+
+/// tuning::codec_sample ซ้ำไปเรื่อยๆ จนครบ size (บีบได้พอๆ กับเอกสารทั่วไป ไม่ใช่ศูนย์ล้วน)
+struct SyntheticSource {
+    sample: Arc<Vec<u8>>,
+    offset: usize,
+    remaining: u64,
+}
+
+impl AsyncRead for SyntheticSource {
+    fn poll_read(mut self: Pin<&mut Self>, _cx: &mut TaskContext<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let n = (self.sample.len() - self.offset).min(buf.remaining()).min(self.remaining as usize);
+        let start = self.offset;
+        buf.put_slice(&self.sample[start..start + n]);
+        self.offset = (start + n) % self.sample.len();
+        self.remaining -= n as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+// Port ที่ทั้ง TCP และ UDP ว่าง (QUIC ใช้ UDP) ปล่อยคืนก่อนให้ Transport Bind เอง
+fn free_port() -> anyhow::Result<u16> {
+    for _ in 0..16 {
+        let port = std::net::TcpListener::bind(("0.0.0.0", 0))?.local_addr()?.port();
+        if std::net::UdpSocket::bind(("0.0.0.0", port)).is_ok() { return Ok(port); }
+    }
+    bail!("No free port for loopback benchmark")
+}
+
+async fn bench_transport(mode: TransportMode, port: u16, storage_path: &str) -> anyhow::Result<Arc<DynTransport>> {
+    let identity = || security::Identity::load(storage_path, BENCH_NODE_NAME, true);
+    Ok(match mode {
+        TransportMode::Tcp => Arc::new(TcpTransport::new(port, storage_path, &identity()?, None).await?),
+        TransportMode::Quic => Arc::new(QuicTransport::new(port, storage_path, BENCH_NODE_NAME, &identity()?, None).await?),
+        TransportMode::PlainTcp => Arc::new(PlainTcpTransport::new(port).await?),
+        #[cfg(feature = "webrtc")]
+        TransportMode::WebRtc => bail!("WebRTC needs signaling and cannot be benchmarked on loopback"),
+    })
+}
+
+// ฝั่งรับ: อ่าน Header -> ตอบ ACK -> แตกทิ้ง (ไม่เขียน Disk) คืนจำนวน Byte หลังแตก
+async fn receive(transport: Arc<DynTransport>, timeouts: Timeouts) -> anyhow::Result<u64> {
+    let (mut stream, _, _) = transport.accept().await?;
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await?;
+    let header_len = u32::from_le_bytes(len_buf) as usize;
+    if header_len > MAX_HEADER_SIZE { bail!("Header too large"); }
+    let mut header_buf = vec![0u8; header_len];
+    stream.read_exact(&mut header_buf).await?;
+    let header: FileHeader = serde_json::from_slice(&header_buf).context("Invalid header JSON")?;
+    let algo = header.compression.as_deref().and_then(CompressionAlgo::from_name).unwrap_or(CompressionAlgo::None);
+    stream.write_all(&pack_ack(1, 0)).await?;
+    let mut decoder = Decompressor::new(stream, algo);
+    // Stall = อ่านแต่ละครั้งไม่ได้อะไรเลยนานเกิน (ทั้งชุดนานได้ตาม size / ความเร็วของ Codec)
+    let mut buf = vec![0u8; READ_BUFFER_SIZE];
+    let mut received = 0u64;
+    loop {
+        let n = tokio::time::timeout(timeouts.stall, decoder.read(&mut buf)).await.context("Receiver stalled")??;
+        if n == 0 { return Ok(received); }
+        received += n as u64;
+    }
+}
+
+async fn send(transport: &DynTransport, port: u16, size: u64, algo: CompressionAlgo, sample: Arc<Vec<u8>>, stats: &Arc<StatsCollector>, timeouts: Timeouts) -> anyhow::Result<()> {
+    let mut stream = transport.connect("127.0.0.1", port).await?;
+    let header = FileHeader {
+        filename: format!("{}.bin", BENCH_TASK_ID),
+        filesize: size,
+        sender_name: BENCH_NODE_NAME.to_string(),
+        sender_device: std::env::consts::OS.to_string(),
+        compression: Some(algo.as_str().to_string()),
+        sent_at: None,
+        modified_at: None,
+        mode: None,
+        dedup: None,
+        expires_in_ms: None,
+        sha256: None,
+        quick_hash: None,
+        transfer_id: None,
+        control_port: None,
+        protocol_version: PROTOCOL_VERSION,
+        min_protocol_version: MIN_PROTOCOL_VERSION,
+        capabilities: LOCAL_CAPABILITIES,
+        batch: None,
+        thumbnail: None,
+        guest: true,
+        parallel: None,
+        resuming: false,
+        metadata: Default::default(),
+    };
+    let json = serde_json::to_vec(&header)?;
+    stream.write_all(&(json.len() as u32).to_le_bytes()).await?;
+    stream.write_all(&json).await?;
+    let mut ack = vec![0u8; ACK_SIZE];
+    stream.read_exact(&mut ack).await?;
+    if unpack_ack(&ack)?.0 != 1 { bail!("Loopback receiver rejected the benchmark"); }
+
+    stats.begin();
+    let mut encoder = Compressor::new(stats.count_wire(stream), algo);
+    let observer = stats.clone();
+    let source = SyntheticSource { sample, offset: 0, remaining: size };
+    let signal = TransferSignal::detached(CancellationToken::new());
+    copy_pipeline(source, &mut encoder, size, move |c, _, rate| observer.observe(c, &rate), |_| {}, timeouts, &signal).await?;
+    encoder.shutdown().await?;
+    Ok(())
+}
+
+async fn run_one(mode: TransportMode, algo: CompressionAlgo, size: u64, sample: Arc<Vec<u8>>, storage_path: &str) -> anyhow::Result<TransferStats> {
+    let port = free_port()?;
+    let transport = bench_transport(mode, port, storage_path).await?;
+    let timeouts = Timeouts::default();
+    let receiver = tokio::spawn(receive(transport.clone(), timeouts));
+    let stats = StatsCollector::new(StatsStore::new(), mode.as_str(), 0);
+    if let Err(e) = send(transport.as_ref(), port, size, algo, sample, &stats, timeouts).await {
+        receiver.abort();
+        return Err(e);
+    }
+    // นับจนผู้รับแตกครบ (ไม่ใช่แค่ผู้ส่งเขียนเข้า Buffer หมด)
+    let received = receiver.await.context("Loopback receiver panicked")??;
+    if received != size { bail!("Loopback receiver got {} of {} bytes", received, size); }
+    Ok(stats.finish(BENCH_TASK_ID, algo))
+}
+
+/// วัดทุกคู่ Transport x Compression (transport = None -> ทุกตัวใน LOOPBACK_TRANSPORTS) เรียงตามลำดับที่วัด
+/// Gzip / Zlib ช้ากว่าตัวอื่นหลายเท่า -> size ใหญ่ = รอนาน
+pub async fn benchmark_loopback(size: u64, transport: Option<TransportMode>, storage_path: &str) -> anyhow::Result<Vec<TransferStats>> {
+    if size == 0 { bail!("Benchmark size must be greater than zero"); }
+    let transports = match transport {
+        Some(mode) => vec![mode],
+        None => LOOPBACK_TRANSPORTS.to_vec(),
+    };
+    let sample = Arc::new(tuning::codec_sample());
+    let algos: Vec<CompressionAlgo> = std::iter::once(CompressionAlgo::None)
+        .chain(compression::SUPPORTED_ALGOS.iter().copied().filter(|a| *a != CompressionAlgo::None))
+        .collect();
+    let mut results = Vec::new();
+    for mode in transports {
+        for algo in &algos {
+            let stats = run_one(mode, *algo, size, sample.clone(), storage_path).await
+                .with_context(|| format!("Loopback benchmark failed ({} / {})", mode.as_str(), algo.as_str()))?;
+            tracing::info!("Loopback {} / {}: {} MB/s", mode.as_str(), algo.as_str(), stats.avg_bytes_per_sec / (1024 * 1024));
+            results.push(stats);
+        }
+    }
+    Ok(results)
+}
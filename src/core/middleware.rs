@@ -0,0 +1,89 @@
+use std::path::Path;
+use std::sync::Arc;
+use async_trait::async_trait;
+
+use crate::core::transfer::{DynStream, Metadata};
+
+// ==========================================
+// Transfer Middleware (ลงผ่าน DropTeaBuilder::middleware ไม่ต้อง Fork handlers.rs)
+// เช่น เข้ารหัสซ้อนอีกชั้น / Audit Log / กรองไฟล์ตามนโยบายองค์กร
+// 1) before_send: ก่อนเปิด Connection (แก้ Metadata ได้) Err = ไม่ส่ง -> Rejected
+// 2) wrap_stream: ห่อ Stream หลัง Header / ACK / Dedup (เฉพาะเนื้อไฟล์) ทั้งสองฝั่งต้องลงตัวเดียวกัน
+//    wraps_stream() = true -> ผู้ส่งไม่ใช้ sendfile / Parallel / BLE GATT (Byte ต้องผ่าน Wrapper ทุกตัว)
+// 3) after_receive: รับครบแล้ว (ยังเป็น .part ก่อนย้ายเข้าที่) Err = ทิ้งไฟล์ -> Rejected ฝั่งรับ
+// หลายตัว = เรียงตามลำดับที่ลง (wrap ตัวแรกอยู่ชั้นในสุด ติดกับ Transport)
+// ==========================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction { Send, Receive }
+
+/// Transfer ที่ Middleware เห็น (peer = ผู้ส่งตาม Header ฝั่งรับ / Peer ID หรือ IP ฝั่งส่ง)
+#[derive(Debug, Clone)]
+pub struct TransferContext {
+    pub task_id: String,
+    pub direction: Direction,
+    pub filename: String,
+    pub filesize: u64,
+    pub peer: String,
+    pub metadata: Metadata,
+}
+
+#[async_trait]
+pub trait TransferMiddleware: Send + Sync + std::fmt::Debug + 'static {
+    fn name(&self) -> &str;
+
+    async fn before_send(&self, _ctx: &mut TransferContext) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn wraps_stream(&self) -> bool {
+        false
+    }
+
+    fn wrap_stream(&self, _ctx: &TransferContext, stream: DynStream) -> DynStream {
+        stream
+    }
+
+    async fn after_receive(&self, _ctx: &TransferContext, _path: &Path) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+pub type DynTransferMiddleware = Arc<dyn TransferMiddleware>;
+
+/// Middleware ทุกตัวของ Engine (ว่าง = ไม่มีผลกับเส้นทางเดิมเลย)
+#[derive(Debug, Clone, Default)]
+pub struct MiddlewareChain(Vec<DynTransferMiddleware>);
+
+impl MiddlewareChain {
+    pub fn new(middleware: Vec<DynTransferMiddleware>) -> Self {
+        Self(middleware)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn wraps_stream(&self) -> bool {
+        self.0.iter().any(|m| m.wraps_stream())
+    }
+
+    /// Err = เหตุผลที่ใช้ Reject ("<name>: <error>")
+    pub async fn before_send(&self, ctx: &mut TransferContext) -> Result<(), String> {
+        for m in &self.0 {
+            m.before_send(ctx).await.map_err(|e| format!("{}: {:#}", m.name(), e))?;
+        }
+        Ok(())
+    }
+
+    pub fn wrap(&self, ctx: &TransferContext, stream: DynStream) -> DynStream {
+        self.0.iter().fold(stream, |s, m| m.wrap_stream(ctx, s))
+    }
+
+    pub async fn after_receive(&self, ctx: &TransferContext, path: &Path) -> Result<(), String> {
+        for m in &self.0 {
+            m.after_receive(ctx, path).await.map_err(|e| format!("{}: {:#}", m.name(), e))?;
+        }
+        Ok(())
+    }
+}
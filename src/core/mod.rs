@@ -1,13 +1,59 @@
+pub mod admin;
+pub mod archive;
+pub mod batch;
+pub mod builder;
+pub mod ble_transfer;
+pub mod cancel;
+pub mod chunk_store;
+pub mod codes;
 pub mod config;
+pub mod control;
+pub mod dedup;
+pub mod delta;
+pub mod diagnostics;
 pub mod discovery;
+pub mod duplicate;
 pub mod engine;
+pub mod error;
 pub mod events;
 pub mod ffi;
 pub mod handlers;
+#[cfg(feature = "harness")]
+pub mod harness;
 pub mod handshake;
+pub mod history;
+pub mod hooks;
+pub mod hotspot;
+pub mod io_priority;
+pub mod links;
+pub mod localsend;
+pub mod loopback_bench;
+pub mod middleware;
+pub mod mmap;
+pub mod netwatch;
 pub mod notification;
+pub mod outbox;
+pub mod parallel;
+pub mod peer_caps;
+pub mod peer_prefs;
+pub mod peer_registry;
+pub mod port_mapping;
+pub mod reload;
+pub mod rendezvous;
+pub mod reputation;
+pub mod resume;
+pub mod sandbox;
+pub mod save_rules;
 pub mod security;
+pub mod sender_queue;
+pub mod session;
+pub mod shares;
+pub mod stats;
+pub mod thumbnail;
+pub mod trace;
 pub mod transfer;
+pub mod tuning;
 pub mod utils;
 pub mod transports;
-pub mod compression; // 🔥 NEW: ลงทะเบียน Module ใหม่
\ No newline at end of file
+pub mod compression; // 🔥 NEW: ลงทะเบียน Module ใหม่
+pub mod webhook;
\ No newline at end of file
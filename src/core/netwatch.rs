@@ -0,0 +1,28 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use crate::core::discovery::MdnsBackend;
+
+// ==========================================
+// Network Change Watcher
+// สลับ Wi-Fi / เสียบสาย LAN / ต่อ VPN -> Address ของเครื่องเปลี่ยน
+// ไม่มี API แจ้งเตือน Route/Interface ที่ใช้ได้ทุก OS จึง Poll รายการ Address แทน
+// ==========================================
+
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// รอจน Address ของเครื่องต่างจาก `current` แล้วคืนชุดใหม่
+/// ระหว่างสลับ Network Address หายแล้วค่อยมาทีละตัว (รอ DHCP) -> รอให้นิ่งก่อนค่อยคืน
+pub async fn wait_for_change(current: &[IpAddr]) -> Vec<IpAddr> {
+    let mut latest = loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let ips = MdnsBackend::get_local_ips();
+        if ips != current { break ips; }
+    };
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let ips = MdnsBackend::get_local_ips();
+        if ips == latest { return latest; }
+        latest = ips;
+    }
+}
@@ -1,6 +1,4 @@
 use tokio::sync::mpsc;
-use std::sync::Mutex;
-use std::path::Path;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum WinToastError {
@@ -23,6 +21,8 @@ pub enum UserResponse {
 #[cfg(target_os = "windows")]
 mod backend {
     use super::*;
+    use std::sync::Mutex;
+    use std::path::Path;
     use std::ffi::OsStr;
     use std::os::windows::ffi::OsStrExt;
 
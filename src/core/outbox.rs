@@ -0,0 +1,95 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex as StdMutex;
+use log::warn;
+use serde::{Serialize, Deserialize};
+
+use crate::core::batch::BatchInfo;
+use crate::core::thumbnail::Thumbnail;
+use crate::core::transfer::Metadata;
+
+// ==========================================
+// Outbox (คิวส่งที่ค้างอยู่ เก็บลง storage_path)
+// เขียนตอน send_file และลบเมื่อ Transfer จบ (สำเร็จ / ถูกปฏิเสธ / Error / ยกเลิกเอง)
+// แอปปิดไปก่อน -> start_service รอบหน้าส่งต่อให้เอง และรอ Peer ที่ยัง Offline จนกว่าจะกลับมาใน Discovery
+// ==========================================
+
+const OUTBOX_FILE: &str = "outbox.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueuedSend {
+    pub task_id: String,
+    pub ip: String,
+    pub port: u16,
+    pub path: String,
+    pub sender_name: String,
+    #[serde(default)]
+    pub target_os: Option<String>,
+    // Peer ID จาก Discovery (None = ส่งด้วย IP ตรงๆ ไปหาเครื่องที่ไม่รู้จัก)
+    #[serde(default)]
+    pub peer_id: Option<String>,
+    #[serde(default)]
+    pub compression_level: Option<i32>,
+    #[serde(default)]
+    pub io_priority: Option<String>,
+    #[serde(default)]
+    pub expires_in_ms: Option<u64>,
+    #[serde(default)]
+    pub batch: Option<BatchInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<Thumbnail>,
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+    // ชื่อใน Header แทนชื่อไฟล์ (Path ในโฟลเดอร์ของ mirror_folder)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub queued_at: u64,
+}
+
+#[derive(Debug)]
+pub struct Outbox {
+    path: PathBuf,
+    entries: StdMutex<Vec<QueuedSend>>,
+}
+
+impl Outbox {
+    pub fn open(dir: &str) -> Self {
+        let path = Path::new(dir).join(OUTBOX_FILE);
+        let entries = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable outbox {}: {}", path.display(), e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        Self { path, entries: StdMutex::new(entries) }
+    }
+
+    pub fn push(&self, entry: QueuedSend) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|e| e.task_id != entry.task_id);
+        entries.push(entry);
+        self.save(&entries);
+    }
+
+    pub fn remove(&self, task_id: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|e| e.task_id != task_id);
+        if entries.len() != before { self.save(&entries); }
+    }
+
+    pub fn snapshot(&self) -> Vec<QueuedSend> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    // ไฟล์เล็ก เขียนทับทั้งก้อนผ่าน .tmp (ไฟดับกลางทางไม่ได้ไฟล์ครึ่งๆ)
+    fn save(&self, entries: &[QueuedSend]) {
+        let tmp = self.path.with_extension("json.tmp");
+        let result = serde_json::to_vec_pretty(entries).map_err(anyhow::Error::from)
+            .and_then(|data| Ok(std::fs::write(&tmp, data)?))
+            .and_then(|_| Ok(std::fs::rename(&tmp, &self.path)?));
+        if let Err(e) = result {
+            warn!("Failed to persist outbox: {}", e);
+        }
+    }
+}
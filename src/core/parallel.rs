@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use tokio::fs::{File as AsyncFile, OpenOptions};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+use crate::core::cancel::TransferSignal;
+use crate::core::compression::{CompressionAlgo, Compressor};
+use crate::core::io_priority::{FileSink, FileSource, IoPriority};
+use crate::core::peer_prefs::Throttled;
+use crate::core::stats::StatsCollector;
+use crate::core::transfer::{copy_pipeline, DynStream, DynTransport, Throughput, ThroughputMeter, Timeouts, NOTIFY_INTERVAL_MS};
+
+// ==========================================
+// Parallel Streams (ไฟล์ใหญ่บน QUIC / TLS-TCP)
+// Stream เดียวบน Wi-Fi ที่ Packet หาย ช้ากว่า TCP -> แบ่งไฟล์เป็น K ช่วงต่อกัน ส่งพร้อมกัน K Stream บน Connection เดียวกัน (Pool ของ QuicTransport)
+// TCP บน Link ที่ RTT สูง: Window ของ Connection เดียวเป็นเพดาน -> ช่วงละ Connection (TLS แยกกัน) ได้ Throughput รวมหลายเท่า
+// Stream หลักยังคุย FileHeader (parallel = แผน) + ACK ตามปกติ แล้วผู้ส่งเปิด Stream ย่อย: [u32 len][LaneRequest JSON (ช่วงไหน)][ข้อมูลช่วงนั้น บีบแยกกัน]
+// ผู้รับจับคู่ Stream ย่อยกับ Transfer ด้วย (IP + Fingerprint ผู้ส่ง, transfer_id) แล้วเขียนแต่ละช่วงลง Offset ของตัวเองในไฟล์ .part เดียวกัน
+// เขียนครบทุกช่วงแล้วผู้รับตอบ ACK อีกครั้งบน Stream หลัก -> ผู้ส่งถือ Stream ย่อยไว้จนได้ ACK นี้ (ปิดก่อน TLS-TCP อาจ Reset ข้อมูลที่ยังไม่ถึง)
+// ==========================================
+
+/// ประกาศใน mDNS ว่ารับ Stream ย่อยได้
+pub const FEATURE: &str = "parallel";
+pub const MAX_STREAMS: u32 = 16;
+pub const DEFAULT_STREAMS: u32 = 4;
+pub const DEFAULT_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// [quic] / [tcp] ใน config.toml (streams < 2 = ปิด)
+#[derive(Debug, Clone, Copy)]
+pub struct ParallelPolicy {
+    pub streams: u32,
+    // ไฟล์เล็กกว่านี้ส่ง Stream เดียว (เปิด Stream เพิ่มไม่คุ้ม)
+    pub threshold: u64,
+}
+
+impl Default for ParallelPolicy {
+    fn default() -> Self {
+        Self { streams: DEFAULT_STREAMS, threshold: DEFAULT_THRESHOLD }
+    }
+}
+
+impl ParallelPolicy {
+    /// TCP เปิดหลาย Connection ต่อไฟล์ -> ให้ User เปิดเอง
+    pub fn off() -> Self {
+        Self { streams: 1, ..Self::default() }
+    }
+
+    pub fn plan(&self, size: u64) -> Option<ParallelPlan> {
+        let plan = ParallelPlan { streams: self.streams.min(MAX_STREAMS) };
+        (plan.is_valid() && size >= self.threshold.max(1)).then_some(plan)
+    }
+}
+
+/// แนบไปกับ FileHeader.parallel
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParallelPlan {
+    pub streams: u32,
+}
+
+impl ParallelPlan {
+    pub fn is_valid(&self) -> bool {
+        (2..=MAX_STREAMS).contains(&self.streams)
+    }
+
+    /// (offset, len) ของแต่ละ Stream คำนวณได้เองทั้งสองฝั่ง ไม่ต้องส่งไปด้วย
+    pub fn ranges(&self, size: u64) -> Vec<(u64, u64)> {
+        let k = self.streams as u128;
+        (0..k).map(|i| {
+            let start = (i * size as u128 / k) as u64;
+            let end = ((i + 1) * size as u128 / k) as u64;
+            (start, end - start)
+        }).collect()
+    }
+}
+
+// Header ของ Stream ย่อย (แทน FileHeader)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LaneRequest {
+    pub lane: LaneOpen,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LaneOpen {
+    pub transfer_id: String,
+    pub index: u32,
+    // ช่วงที่ผู้ส่งคิดว่าเป็นของ Stream นี้ (ต้องตรงกับ ParallelPlan::ranges ของผู้รับ)
+    pub offset: u64,
+    pub len: u64,
+}
+
+// --- ฝั่งรับ ---
+
+type Lane = (LaneOpen, DynStream);
+
+/// Transfer ที่ ACK แบบ Parallel ไปแล้วและรอ Stream ย่อยอยู่
+#[derive(Debug, Default)]
+pub struct LaneRegistry {
+    waiting: StdMutex<HashMap<String, mpsc::Sender<Lane>>>,
+}
+
+fn lane_key(ip: IpAddr, fingerprint: Option<&str>, transfer_id: &str) -> String {
+    format!("{}|{}|{}", ip.to_canonical(), fingerprint.unwrap_or_default(), transfer_id)
+}
+
+impl LaneRegistry {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// ลงทะเบียนก่อนตอบ ACK (ผู้ส่งเปิด Stream ย่อยทันทีที่ได้ ACK)
+    pub fn expect(self: &Arc<Self>, ip: IpAddr, fingerprint: Option<&str>, transfer_id: &str, plan: ParallelPlan) -> LaneReceiver {
+        let key = lane_key(ip, fingerprint, transfer_id);
+        let (tx, rx) = mpsc::channel(plan.streams as usize);
+        self.waiting.lock().unwrap().insert(key.clone(), tx);
+        LaneReceiver { registry: self.clone(), key, rx, plan }
+    }
+
+    /// Stream ย่อยจาก handle_incoming -> Transfer ที่รออยู่ (ไม่มีใครรอ = Error)
+    pub async fn deliver(&self, ip: IpAddr, fingerprint: Option<&str>, lane: LaneOpen, stream: DynStream) -> anyhow::Result<()> {
+        let tx = self.waiting.lock().unwrap().get(&lane_key(ip, fingerprint, &lane.transfer_id)).cloned();
+        let tx = tx.with_context(|| format!("No transfer waiting for parallel stream of '{}'", lane.transfer_id))?;
+        let transfer_id = lane.transfer_id.clone();
+        tx.send((lane, stream)).await.map_err(|_| anyhow::anyhow!("Transfer '{}' stopped waiting for parallel streams", transfer_id))
+    }
+}
+
+pub struct LaneReceiver {
+    registry: Arc<LaneRegistry>,
+    key: String,
+    rx: mpsc::Receiver<Lane>,
+    plan: ParallelPlan,
+}
+
+impl Drop for LaneReceiver {
+    fn drop(&mut self) {
+        self.registry.waiting.lock().unwrap().remove(&self.key);
+    }
+}
+
+// Progress ของทุก Stream รวมเป็นก้อนเดียว (ส่งต่อไม่ถี่กว่า Stream เดียว)
+struct Aggregate<F> {
+    total: u64,
+    state: StdMutex<(u64, ThroughputMeter, tokio::time::Instant, F)>,
+}
+
+impl<F: FnMut(u64, u64, Throughput) + Send + 'static> Aggregate<F> {
+    fn new(total: u64, on_progress: F) -> Arc<Self> {
+        let now = tokio::time::Instant::now();
+        Arc::new(Self { total, state: StdMutex::new((0, ThroughputMeter::start(), now, on_progress)) })
+    }
+
+    fn lane(self: &Arc<Self>) -> impl FnMut(u64, u64, Throughput) + Send + 'static {
+        let aggregate = self.clone();
+        let mut last = 0u64;
+        move |current, _, _| {
+            let delta = current.saturating_sub(last);
+            last = current;
+            let mut state = aggregate.state.lock().unwrap();
+            let (done, meter, last_notify, on_progress) = &mut *state;
+            *done += delta;
+            let now = tokio::time::Instant::now();
+            if *done == aggregate.total || now.duration_since(*last_notify).as_millis() > NOTIFY_INTERVAL_MS {
+                on_progress(*done, aggregate.total, meter.sample(*done, aggregate.total));
+                *last_notify = now;
+            }
+        }
+    }
+}
+
+/// รอ Stream ย่อยครบทุกช่วงแล้วเขียนลง path (สร้างไว้แล้ว) พร้อมกัน
+/// decode = ห่อ Stream ย่อยเป็นตัวแตก Compression (Sandbox / Throttle / นับ Byte ตามที่ผู้เรียกใช้กับ Stream หลัก)
+#[allow(clippy::too_many_arguments)]
+pub async fn receive<D, F, S>(
+    mut lanes: LaneReceiver,
+    path: &Path,
+    size: u64,
+    decode: D,
+    io_priority: IoPriority,
+    buffer_size: usize,
+    on_progress: F,
+    on_stall: S,
+    timeouts: Timeouts,
+    signal: &TransferSignal,
+) -> anyhow::Result<()>
+where
+    D: Fn(DynStream, u64) -> anyhow::Result<Box<dyn AsyncRead + Unpin + Send>>,
+    F: FnMut(u64, u64, Throughput) + Send + 'static,
+    S: FnMut(Duration) + Clone,
+{
+    OpenOptions::new().write(true).open(path).await?.set_len(size).await?;
+    let ranges = lanes.plan.ranges(size);
+    let mut streams: Vec<Option<DynStream>> = ranges.iter().map(|_| None).collect();
+    for _ in 0..ranges.len() {
+        let (lane, stream) = signal.timeout(timeouts.io, lanes.rx.recv()).await.flatten()
+            .context("Parallel stream did not arrive")?;
+        let index = lane.index as usize;
+        if ranges.get(index) != Some(&(lane.offset, lane.len)) {
+            bail!("Parallel stream {} has range {}+{} outside the plan", lane.index, lane.offset, lane.len);
+        }
+        let slot = streams.get_mut(index).filter(|s| s.is_none())
+            .with_context(|| format!("Duplicate parallel stream {}", lane.index))?;
+        *slot = Some(stream);
+    }
+
+    let aggregate = Aggregate::new(size, on_progress);
+    let writers = streams.into_iter().flatten().zip(ranges).enumerate().map(|(index, (stream, (offset, len)))| {
+        let reader = decode(stream, len);
+        let (progress, on_stall) = (aggregate.lane(), on_stall.clone());
+        async move {
+            let mut file = OpenOptions::new().write(true).open(path).await?;
+            file.seek(SeekFrom::Start(offset)).await?;
+            let mut sink = FileSink::new(file, io_priority, buffer_size);
+            // อ่านจนผู้ส่งปิด Stream (หยุดอ่านก่อน = QUIC ตอบ STOP_SENDING ผู้ส่งจบด้วย Error) เกิน/ขาดช่วงดูจากตำแหน่งไฟล์
+            copy_pipeline(reader?, &mut sink, len, progress, on_stall, timeouts, signal).await?;
+            let end = sink.finish().await?.stream_position().await?;
+            if end != offset + len {
+                bail!("Parallel stream {} sent {} of {} bytes", index, end - offset, len);
+            }
+            Ok(())
+        }
+    });
+    futures::future::try_join_all(writers).await?;
+    Ok(())
+}
+
+// --- ฝั่งส่ง ---
+
+/// Stream ย่อยเปิดผ่าน Transport เดียวกับ Stream หลัก (QUIC = Connection เดียวกันจาก Pool, TCP = Connection ใหม่ต่อช่วง)
+pub struct ParallelSend {
+    pub transport: Arc<DynTransport>,
+    pub host: String,
+    pub port: u16,
+    pub plan: ParallelPlan,
+    // Limit ของ Peer นี้ทั้ง Transfer (แบ่งเท่าๆ กันทุก Stream)
+    pub max_bytes_per_sec: Option<u64>,
+}
+
+/// คืน Stream ย่อยที่ส่งครบแล้ว (ผู้เรียกถือไว้จนผู้รับยืนยันบน Stream หลัก)
+#[allow(clippy::too_many_arguments)]
+pub async fn send<F, S>(
+    lanes: ParallelSend,
+    path: &str,
+    transfer_id: &str,
+    size: u64,
+    algo: CompressionAlgo,
+    level: Option<i32>,
+    io_priority: IoPriority,
+    buffer_size: usize,
+    stats: &Arc<StatsCollector>,
+    on_progress: F,
+    on_stall: S,
+    timeouts: Timeouts,
+    signal: &TransferSignal,
+) -> anyhow::Result<Vec<DynStream>>
+where
+    F: FnMut(u64, u64, Throughput) + Send + 'static,
+    S: FnMut(Duration) + Clone,
+{
+    let limit = lanes.max_bytes_per_sec.map(|b| (b / lanes.plan.streams as u64).max(1));
+    let aggregate = Aggregate::new(size, on_progress);
+    let senders = lanes.plan.ranges(size).into_iter().enumerate().map(|(index, (offset, len))| {
+        let (progress, on_stall, lanes) = (aggregate.lane(), on_stall.clone(), &lanes);
+        async move {
+            let mut stream = Throttled::new(lanes.transport.connect(&lanes.host, lanes.port).await?, limit);
+            let json = serde_json::to_vec(&LaneRequest { lane: LaneOpen { transfer_id: transfer_id.to_string(), index: index as u32, offset, len } })?;
+            stream.write_all(&(json.len() as u32).to_le_bytes()).await?;
+            stream.write_all(&json).await?;
+            let mut file = AsyncFile::open(path).await.context("Failed to open source file")?;
+            file.seek(SeekFrom::Start(offset)).await?;
+            let reader = FileSource::new(file, io_priority, buffer_size).take(len);
+            let mut encoder = Compressor::with_level(stats.count_wire(stream), algo, level);
+            copy_pipeline(reader, &mut encoder, len, progress, on_stall, timeouts, signal).await?;
+            encoder.shutdown().await?;
+            anyhow::Ok(Box::new(encoder.into_inner()) as DynStream)
+        }
+    });
+    futures::future::try_join_all(senders).await
+}
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex as StdMutex;
+use log::warn;
+use serde::{Serialize, Deserialize};
+
+use crate::core::compression::{self, CompressionAlgo};
+use crate::core::utils;
+
+// ==========================================
+// Peer Capability Cache (เก็บลง storage_path ผูกกับ Certificate Fingerprint)
+// สิ่งที่รู้เกี่ยวกับ Peer จาก mDNS (Compression / Feature / Transport) และจาก FileHeader ที่ Peer ส่งมา (Version / Capability)
+// รอบหน้าผู้ส่งเลือก Compression / Dedup ได้ก่อนเปิด Connection แม้ Peer ไม่ได้ประกาศผ่าน mDNS (ส่งด้วย IP / Rendezvous / BLE)
+// Fingerprint เดียวกัน = เครื่องเดียวกัน (IP / Peer ID เปลี่ยนได้ จึงเก็บเป็น Alias ชี้ไปหา Fingerprint)
+// ==========================================
+
+const PEER_CAPS_FILE: &str = "peer_caps.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PeerCaps {
+    // จาก FileHeader ที่ Peer ส่งมาหาเรา
+    #[serde(default)]
+    pub protocol_version: Option<u32>,
+    #[serde(default)]
+    pub capabilities: Option<u64>,
+    // จาก mDNS ตอนเราส่งไปหา Peer
+    #[serde(default)]
+    pub compression: Option<Vec<String>>,
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub transport: Option<String>,
+    // OS ที่ Peer ประกาศ (TXT "os") ใช้เลือก Compression เมื่อส่งด้วย IP ที่ Discovery ไม่เห็น
+    #[serde(default)]
+    pub os: Option<String>,
+    // ครั้งแรกที่เรารับไฟล์จาก Cert นี้สำเร็จ (Privacy Mode บอกชื่อจริงเฉพาะ Peer ที่มีค่านี้)
+    #[serde(default)]
+    pub accepted_at: Option<u64>,
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+impl PeerCaps {
+    pub fn compression_algos(&self) -> Option<Vec<CompressionAlgo>> {
+        self.compression.as_ref().map(|names| compression::parse_algo_list(&names.join(",")))
+    }
+
+    /// Capability Bit ที่รู้แน่ว่า Peer ไม่มี (None = ยังไม่เคยเห็น Header จาก Peer นี้)
+    pub fn lacks(&self, cap: u64) -> bool {
+        self.capabilities.is_some_and(|c| c & cap != cap)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Store {
+    peers: HashMap<String, PeerCaps>,
+    // Peer ID / IP -> Fingerprint
+    aliases: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+pub struct PeerCapsCache {
+    // None = จำใน Memory อย่างเดียว (Guest Mode)
+    path: Option<PathBuf>,
+    store: StdMutex<Store>,
+}
+
+impl PeerCapsCache {
+    pub fn open(dir: &str) -> Self {
+        let path = Path::new(dir).join(PEER_CAPS_FILE);
+        let store = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable peer cache {}: {}", path.display(), e);
+                Store::default()
+            }),
+            Err(_) => Store::default(),
+        };
+        Self { path: Some(path), store: StdMutex::new(store) }
+    }
+
+    pub fn in_memory() -> Self {
+        Self { path: None, store: StdMutex::new(Store::default()) }
+    }
+
+    pub fn get(&self, fingerprint: &str) -> Option<PeerCaps> {
+        self.store.lock().unwrap().peers.get(fingerprint).cloned()
+    }
+
+    /// หาจาก Peer ID / IP ที่เคยผูกไว้ (ไม่เจอ -> คืน None ผู้เรียกลองหา Fingerprint เองจาก known_hosts ได้)
+    pub fn lookup(&self, alias: &str) -> Option<(String, PeerCaps)> {
+        let store = self.store.lock().unwrap();
+        let fingerprint = store.aliases.get(alias)?;
+        store.peers.get(fingerprint).map(|caps| (fingerprint.clone(), caps.clone()))
+    }
+
+    /// แก้ข้อมูลของ Fingerprint นี้ (ผูก alias ด้วยถ้ามี) เขียนลง Disk เฉพาะตอนมีอะไรเปลี่ยน
+    pub fn record<F: FnOnce(&mut PeerCaps)>(&self, fingerprint: &str, alias: Option<&str>, update: F) {
+        let mut store = self.store.lock().unwrap();
+        let mut changed = false;
+        if let Some(alias) = alias {
+            changed |= store.aliases.insert(alias.to_string(), fingerprint.to_string()).as_deref() != Some(fingerprint);
+        }
+        let caps = store.peers.entry(fingerprint.to_string()).or_default();
+        let before = caps.clone();
+        update(caps);
+        if *caps != before {
+            caps.updated_at = utils::timestamp_millis();
+            changed = true;
+        }
+        if changed { self.save(&store); }
+    }
+
+    // เขียนทับทั้งก้อนผ่าน .tmp เหมือน Outbox
+    fn save(&self, store: &Store) {
+        let Some(path) = &self.path else { return };
+        let tmp = path.with_extension("json.tmp");
+        let result = serde_json::to_vec_pretty(store).map_err(anyhow::Error::from)
+            .and_then(|data| Ok(std::fs::write(&tmp, data)?))
+            .and_then(|_| Ok(std::fs::rename(&tmp, path)?));
+        if let Err(e) = result {
+            warn!("Failed to persist peer cache: {}", e);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::{Instant, Sleep};
+
+use crate::core::compression::CompressionAlgo;
+use crate::core::engine::TransportMode;
+use crate::core::error::{DropTeaError, Result};
+
+// ==========================================
+// Per-Peer Overrides ([peers."<id>"] ใน config.toml หรือ set_peer_override ตอน Runtime)
+// <id> = Cert Fingerprint / Peer ID จาก Discovery / ชื่อเครื่องผู้ส่ง / IP (ไม่สนตัวพิมพ์เล็กใหญ่)
+// ตอนส่ง: transport / compression / max_bytes_per_sec, ตอนรับ: auto_accept / max_bytes_per_sec
+// Field ที่ไม่ระบุ = ใช้ค่าของ Engine ตามเดิม
+// ==========================================
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PeerOverride {
+    // บังคับ Transport ตอนส่ง: Mode ของ Engine เอง หรือ plaintcp (ไม่ต้องมี Cert เช่น NAS ในบ้าน)
+    pub transport: Option<TransportMode>,
+    // Some(CompressionAlgo::None) = ไม่บีบเลย, Algo อื่น = ใช้ก่อนถ้า Peer รองรับ
+    pub compression: Option<CompressionAlgo>,
+    // จำกัดความเร็วบน Connection ทั้งตอนส่งและรับ
+    pub max_bytes_per_sec: Option<u64>,
+    // Some(true) = รับเลยไม่ถาม, Some(false) = ถามทุกครั้งแม้อยู่ใน Whitelist
+    pub auto_accept: Option<bool>,
+}
+
+impl PeerOverride {
+    /// transport ที่ Engine โหมดนี้เปิด Connection ให้ได้
+    pub fn validate(&self, mode: TransportMode) -> Result<()> {
+        match self.transport {
+            Some(t) if t != mode && t != TransportMode::PlainTcp => Err(DropTeaError::Config(format!(
+                "peer transport '{}' is not available in {} mode (use '{}' or 'plaintcp')", t.as_str(), mode.as_str(), mode.as_str()))),
+            _ if self.max_bytes_per_sec == Some(0) => Err(DropTeaError::Config("max_bytes_per_sec must be at least 1".into())),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PeerOverrides {
+    map: RwLock<HashMap<String, PeerOverride>>,
+}
+
+impl PeerOverrides {
+    pub fn new(initial: &HashMap<String, PeerOverride>) -> Arc<Self> {
+        let overrides = Arc::new(Self::default());
+        overrides.replace(initial);
+        overrides
+    }
+
+    /// Override ว่างทุก Field = ลบทิ้ง
+    pub fn set(&self, id: &str, value: PeerOverride) {
+        let mut map = self.map.write().unwrap();
+        match value == PeerOverride::default() {
+            true => { map.remove(&id.to_lowercase()); }
+            false => { map.insert(id.to_lowercase(), value); }
+        }
+    }
+
+    pub fn remove(&self, id: &str) -> bool {
+        self.map.write().unwrap().remove(&id.to_lowercase()).is_some()
+    }
+
+    pub fn all(&self) -> HashMap<String, PeerOverride> {
+        self.map.read().unwrap().clone()
+    }
+
+    // Hot-Reload: ชุดจากไฟล์แทนของเดิมทั้งหมด (รวมที่ตั้งผ่าน set ด้วย)
+    pub fn replace(&self, values: &HashMap<String, PeerOverride>) {
+        *self.map.write().unwrap() = values.iter().map(|(k, v)| (k.to_lowercase(), v.clone())).collect();
+    }
+
+    /// Key แรกที่มี Override (ใส่ Key ที่เจาะจงที่สุดก่อน เช่น Fingerprint -> Peer ID -> ชื่อ -> IP)
+    pub fn lookup<'a>(&self, keys: impl IntoIterator<Item = &'a str>) -> PeerOverride {
+        let map = self.map.read().unwrap();
+        if map.is_empty() { return PeerOverride::default(); }
+        keys.into_iter().find_map(|k| map.get(&k.to_lowercase()).cloned()).unwrap_or_default()
+    }
+}
+
+// --- Bandwidth Cap ---
+
+// รอบละไม่เกิน 1/8 วินาทีของ Rate -> IO Timeout ต่อรอบไม่หมดแม้ตั้ง Rate ต่ำมาก
+const THROTTLE_SLICES: u64 = 8;
+const MIN_THROTTLE_CHUNK: usize = 4 * 1024;
+
+/// Stream ที่อ่าน/เขียนได้ไม่เกิน bytes_per_sec (Token Bucket สะสมได้สูงสุด 1 วินาที) None = ผ่านตรง
+pub struct Throttled<S> {
+    inner: S,
+    limit: Option<Bucket>,
+}
+
+struct Bucket {
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+    chunk: usize,
+    sleep: Option<Pin<Box<Sleep>>>,
+    scratch: Vec<u8>,
+}
+
+impl Bucket {
+    // รอจนมี Token (ติดลบได้จากรอบก่อน = ค้างจ่าย)
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            if let Some(sleep) = self.sleep.as_mut() {
+                ready!(sleep.as_mut().poll(cx));
+                self.sleep = None;
+            }
+            let now = Instant::now();
+            self.tokens = (self.tokens + now.duration_since(self.last).as_secs_f64() * self.rate).min(self.rate);
+            self.last = now;
+            if self.tokens > 0.0 { return Poll::Ready(()); }
+            let wait = Duration::from_secs_f64((1.0 - self.tokens) / self.rate);
+            self.sleep = Some(Box::pin(tokio::time::sleep(wait)));
+        }
+    }
+}
+
+impl<S> Throttled<S> {
+    pub fn new(inner: S, bytes_per_sec: Option<u64>) -> Self {
+        let limit = bytes_per_sec.filter(|r| *r > 0).map(|rate| {
+            let chunk = ((rate / THROTTLE_SLICES) as usize).max(MIN_THROTTLE_CHUNK);
+            Bucket { rate: rate as f64, tokens: 0.0, last: Instant::now(), chunk, sleep: None, scratch: Vec::new() }
+        });
+        Self { inner, limit }
+    }
+
+    /// Stream ข้างในเมื่อไม่ได้จำกัด Rate (เขียนตรงเข้าไปได้โดยไม่ข้าม Bucket)
+    pub fn unthrottled_mut(&mut self) -> Option<&mut S> {
+        match self.limit {
+            Some(_) => None,
+            None => Some(&mut self.inner),
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Throttled<S> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let Some(bucket) = this.limit.as_mut() else { return Pin::new(&mut this.inner).poll_read(cx, buf) };
+        ready!(bucket.poll_ready(cx));
+        // อ่านผ่าน Scratch ขนาดหนึ่ง Chunk (ไม่ให้ Socket เทมาทีเดียวทั้ง Buffer)
+        let n = buf.remaining().min(bucket.chunk);
+        bucket.scratch.resize(n, 0);
+        let mut limited = ReadBuf::new(&mut bucket.scratch[..n]);
+        ready!(Pin::new(&mut this.inner).poll_read(cx, &mut limited))?;
+        let got = limited.filled().len();
+        buf.put_slice(&bucket.scratch[..got]);
+        bucket.tokens -= got as f64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Throttled<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let Some(bucket) = this.limit.as_mut() else { return Pin::new(&mut this.inner).poll_write(cx, buf) };
+        ready!(bucket.poll_ready(cx));
+        let n = buf.len().min(bucket.chunk);
+        let written = ready!(Pin::new(&mut this.inner).poll_write(cx, &buf[..n]))?;
+        bucket.tokens -= written as f64;
+        Poll::Ready(Ok(written))
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use log::warn;
+use serde::{Serialize, Deserialize};
+
+use crate::core::discovery::PeerProfile;
+use crate::core::error::{DropTeaError, Result};
+use crate::core::events::{TransferEvent, TransferEventHandler};
+use crate::core::utils;
+
+// ==========================================
+// Peer Registry (เก็บลง storage_path/peers.json)
+// ชื่อเล่น / โน้ต ที่ User ตั้งเอง + ชื่อ / IP / เวลาที่เห็นล่าสุด ของ Peer ที่เคยเจอ
+// Key = Cert Fingerprint ที่ Peer ประกาศใน mDNS (TXT "fp") -> เปลี่ยนชื่อเครื่องแล้วยังเป็น Peer เดิม
+// Peer รุ่นเก่า / BLE ไม่มี fp ใช้ Discovery ID แทน (ตั้งตามชื่อเครื่อง จึงหลุดเมื่อเปลี่ยนชื่อ)
+// fp ใน mDNS ยังไม่ได้ผ่าน TLS -> ใช้แค่แสดงผล ห้ามเอาไปตัดสินเรื่องความน่าเชื่อถือ
+// ==========================================
+
+const PEER_REGISTRY_FILE: &str = "peers.json";
+// last_seen เปลี่ยนทุกรอบ mDNS -> เขียนลง Disk ไม่บ่อยกว่านี้ (ชื่อ / IP / ชื่อเล่นเปลี่ยน = เขียนทันที)
+const SEEN_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+pub const MAX_NICKNAME_LEN: usize = 64;
+pub const MAX_NOTES_LEN: usize = 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PeerRecord {
+    // ชื่อล่าสุดที่ Peer ประกาศ
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub ip: Option<String>,
+    #[serde(default)]
+    pub nickname: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub first_seen: u64,
+    #[serde(default)]
+    pub last_seen: u64,
+}
+
+/// Peer หนึ่งตัวสำหรับ get_peers (รวมที่ออนไลน์อยู่และที่เคยเห็น)
+#[derive(Serialize, Debug, Clone)]
+pub struct PeerEntry {
+    pub key: String,
+    // Discovery ID ตอนนี้ (None = ไม่ได้ออนไลน์) ใช้กับ send_file ได้เลย
+    pub id: Option<String>,
+    pub online: bool,
+    // Profile จาก mDNS (เฉพาะ Peer ที่ออนไลน์อยู่)
+    #[serde(skip_serializing_if = "PeerProfile::is_empty")]
+    pub profile: PeerProfile,
+    #[serde(flatten)]
+    pub record: PeerRecord,
+}
+
+#[derive(Debug)]
+struct State {
+    records: HashMap<String, PeerRecord>,
+    // Discovery ID -> Key (อยู่ใน Memory อย่างเดียว ID เปลี่ยนได้)
+    aliases: HashMap<String, String>,
+    flushed_at: Instant,
+}
+
+#[derive(Debug)]
+pub struct PeerRegistry {
+    // None = จำใน Memory อย่างเดียว (Guest Mode)
+    path: Option<PathBuf>,
+    state: StdMutex<State>,
+}
+
+impl PeerRegistry {
+    pub fn open(dir: &str) -> Self {
+        let path = Path::new(dir).join(PEER_REGISTRY_FILE);
+        let records = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable peer registry {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self { path: Some(path), state: StdMutex::new(State { records, aliases: HashMap::new(), flushed_at: Instant::now() }) }
+    }
+
+    pub fn in_memory() -> Self {
+        Self { path: None, state: StdMutex::new(State { records: HashMap::new(), aliases: HashMap::new(), flushed_at: Instant::now() }) }
+    }
+
+    /// Discovery เห็น Peer (remember = false: Guest / Privacy Token ผูก ID ไว้อย่างเดียว ไม่สร้าง Record ใหม่)
+    pub fn observe(&self, id: &str, fingerprint: Option<&str>, name: &str, ip: Option<&str>, remember: bool) {
+        let key = fingerprint.map_or_else(|| id.to_string(), str::to_lowercase);
+        let mut state = self.state.lock().unwrap();
+        state.aliases.insert(id.to_string(), key.clone());
+        let now = utils::timestamp_millis();
+        let record = match (state.records.contains_key(&key), remember) {
+            (false, false) => return,
+            (false, true) => state.records.entry(key).or_insert(PeerRecord { first_seen: now, ..Default::default() }),
+            (true, _) => state.records.get_mut(&key).unwrap(),
+        };
+        let mut changed = record.name != name;
+        record.name = name.to_string();
+        if let Some(ip) = ip.filter(|ip| !ip.is_empty()) {
+            changed |= record.ip.as_deref() != Some(ip);
+            record.ip = Some(ip.to_string());
+        }
+        record.last_seen = now;
+        if changed || state.flushed_at.elapsed() >= SEEN_FLUSH_INTERVAL { self.save(&mut state); }
+    }
+
+    /// Peer หายไปจาก Discovery -> เวลาที่เห็นล่าสุดคือตอนนี้
+    pub fn touch(&self, id: &str) {
+        let mut state = self.state.lock().unwrap();
+        let Some(key) = state.aliases.get(id).cloned() else { return };
+        let Some(record) = state.records.get_mut(&key) else { return };
+        record.last_seen = utils::timestamp_millis();
+        self.save(&mut state);
+    }
+
+    /// Record ของ Discovery ID (หรือ Key ตรงๆ)
+    pub fn get(&self, peer: &str) -> Option<(String, PeerRecord)> {
+        let state = self.state.lock().unwrap();
+        let key = Self::key_of(&state, peer);
+        state.records.get(&key).map(|r| (key, r.clone()))
+    }
+
+    pub fn all(&self) -> HashMap<String, PeerRecord> {
+        self.state.lock().unwrap().records.clone()
+    }
+
+    pub fn key(&self, peer: &str) -> String {
+        Self::key_of(&self.state.lock().unwrap(), peer)
+    }
+
+    /// None / ข้อความว่าง = ลบ
+    pub fn set_nickname(&self, peer: &str, nickname: Option<&str>) -> Result<()> {
+        let nickname = clean(nickname, MAX_NICKNAME_LEN, "nickname")?;
+        // UI ของ Python แยก Field ของ PEER_FOUND ด้วย '|'
+        if nickname.as_deref().is_some_and(|n| n.contains(['|', '\n', '\r'])) {
+            return Err(DropTeaError::Config("nickname must not contain '|' or line breaks".into()));
+        }
+        self.update(peer, |r| r.nickname = nickname)
+    }
+
+    pub fn set_notes(&self, peer: &str, notes: Option<&str>) -> Result<()> {
+        let notes = clean(notes, MAX_NOTES_LEN, "notes")?;
+        self.update(peer, |r| r.notes = notes)
+    }
+
+    /// ลบทั้ง Record (ถ้ายังออนไลน์จะถูกสร้างใหม่รอบ mDNS ถัดไปโดยไม่มีชื่อเล่น)
+    pub fn forget(&self, peer: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let key = Self::key_of(&state, peer);
+        let removed = state.records.remove(&key).is_some();
+        if removed { self.save(&mut state); }
+        removed
+    }
+
+    // Peer ที่ยังไม่มี Record (เช่น Guest ที่ไม่ได้จำไว้) ก็ตั้งได้ ถ้า User ตั้งใจ
+    fn update(&self, peer: &str, f: impl FnOnce(&mut PeerRecord)) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let key = Self::key_of(&state, peer);
+        if key.trim().is_empty() {
+            return Err(DropTeaError::Config("peer id is empty".into()));
+        }
+        let now = utils::timestamp_millis();
+        f(state.records.entry(key).or_insert(PeerRecord { first_seen: now, last_seen: now, ..Default::default() }));
+        self.save(&mut state);
+        Ok(())
+    }
+
+    fn key_of(state: &State, peer: &str) -> String {
+        match state.aliases.get(peer) {
+            Some(key) => key.clone(),
+            None if state.records.contains_key(peer) => peer.to_string(),
+            // Fingerprint พิมพ์ตัวใหญ่มา
+            None => match state.records.contains_key(&peer.to_lowercase()) {
+                true => peer.to_lowercase(),
+                false => peer.to_string(),
+            },
+        }
+    }
+
+    // เขียนทับทั้งก้อนผ่าน .tmp เหมือน peer_caps
+    fn save(&self, state: &mut State) {
+        state.flushed_at = Instant::now();
+        let Some(path) = &self.path else { return };
+        let tmp = path.with_extension("json.tmp");
+        let result = serde_json::to_vec_pretty(&state.records).map_err(anyhow::Error::from)
+            .and_then(|data| Ok(std::fs::write(&tmp, data)?))
+            .and_then(|_| Ok(std::fs::rename(&tmp, path)?));
+        if let Err(e) = result {
+            warn!("Failed to persist peer registry: {}", e);
+        }
+    }
+}
+
+fn clean(value: Option<&str>, max: usize, what: &str) -> Result<Option<String>> {
+    let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else { return Ok(None) };
+    if value.chars().count() > max {
+        return Err(DropTeaError::Config(format!("{} is longer than {} characters", what, max)));
+    }
+    Ok(Some(value.to_string()))
+}
+
+/// เติมชื่อเล่น / โน้ตลงใน PeerFound และจำเวลาตอน PeerLost (ครอบ Handler หลักของ Engine ชั้นนอกสุด)
+pub struct RegistryHandler {
+    pub inner: Arc<Box<dyn TransferEventHandler>>,
+    pub registry: Arc<PeerRegistry>,
+}
+
+impl TransferEventHandler for RegistryHandler {
+    fn on_event(&self, mut event: TransferEvent) {
+        match &mut event {
+            TransferEvent::PeerFound { id, nickname, notes, .. } => {
+                if let Some((_, record)) = self.registry.get(id) {
+                    *nickname = record.nickname;
+                    *notes = record.notes;
+                }
+            }
+            TransferEvent::PeerLost { id } => self.registry.touch(id),
+            _ => {}
+        }
+        self.inner.on_event(event);
+    }
+}
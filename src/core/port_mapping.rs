@@ -0,0 +1,122 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::time::timeout;
+use anyhow::{bail, Context};
+use igd_next::{PortMappingProtocol, SearchOptions, AddPortError};
+use natpmp::Response;
+
+use crate::core::discovery::MdnsBackend;
+
+// ==========================================
+// Router Port Mapping (Opt-in)
+// ขอ Port Forward จาก Router อัตโนมัติ: NAT-PMP (Apple/miniupnpd) ก่อน แล้วค่อย UPnP IGD
+// ได้ External Address แล้วประกาศต่อใน mDNS TXT "ext"
+// Lease มีอายุ -> ต่ออายุเองทุกครึ่งหนึ่งของ Lifetime (Router ไม่ค้าง Port ถ้าเครื่องดับไป)
+// ==========================================
+
+const LEASE_SECS: u32 = 3600;
+const NATPMP_TIMEOUT: Duration = Duration::from_secs(3);
+const UPNP_SEARCH_TIMEOUT: Duration = Duration::from_secs(5);
+const MIN_RENEW_INTERVAL: Duration = Duration::from_secs(60);
+const DESCRIPTION: &str = "DropTea";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappingProtocol { Tcp, Udp }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappingMethod { NatPmp, Upnp }
+
+#[derive(Debug, Clone)]
+pub struct PortMapping {
+    pub external: SocketAddr,
+    pub method: MappingMethod,
+    pub lifetime: Duration,
+}
+
+/// ขอ Mapping ครั้งแรก แล้ว Spawn Task ต่ออายุไว้เบื้องหลัง (None = Router ไม่รองรับ/ไม่อนุญาต)
+pub async fn start(protocol: MappingProtocol, port: u16) -> Option<PortMapping> {
+    let mapping = match request(protocol, port).await {
+        Ok(m) => m,
+        Err(e) => {
+            log::warn!("Port mapping unavailable: {}", e);
+            return None;
+        }
+    };
+    log::info!("🌐 Port mapped via {:?}: {} -> :{}", mapping.method, mapping.external, port);
+
+    let first = mapping.clone();
+    tokio::spawn(async move {
+        let mut current = mapping;
+        loop {
+            tokio::time::sleep((current.lifetime / 2).max(MIN_RENEW_INTERVAL)).await;
+            match request(protocol, port).await {
+                Ok(renewed) => {
+                    if renewed.external != current.external {
+                        log::warn!("External address changed: {} -> {} (peers keep the old one until re-announce)", current.external, renewed.external);
+                    }
+                    current = renewed;
+                }
+                Err(e) => log::warn!("Port mapping renewal failed: {}", e),
+            }
+        }
+    });
+    Some(first)
+}
+
+pub async fn request(protocol: MappingProtocol, port: u16) -> anyhow::Result<PortMapping> {
+    match natpmp_map(protocol, port).await {
+        Ok(m) => Ok(m),
+        Err(e) => {
+            log::debug!("NAT-PMP failed ({}), trying UPnP", e);
+            upnp_map(protocol, port).await
+        }
+    }
+}
+
+async fn natpmp_map(protocol: MappingProtocol, port: u16) -> anyhow::Result<PortMapping> {
+    let mut client = natpmp::new_tokio_natpmp().await?;
+
+    client.send_public_address_request().await?;
+    // read_response_or_retry รอ recv ไปเรื่อยๆ ถ้า Router เงียบ -> ต้องครอบ Timeout เอง
+    let public_ip = match timeout(NATPMP_TIMEOUT, client.read_response_or_retry()).await.context("NAT-PMP timeout")?? {
+        Response::Gateway(g) => *g.public_address(),
+        other => bail!("Unexpected NAT-PMP response: {:?}", other),
+    };
+
+    let proto = match protocol { MappingProtocol::Tcp => natpmp::Protocol::TCP, MappingProtocol::Udp => natpmp::Protocol::UDP };
+    client.send_port_mapping_request(proto, port, port, LEASE_SECS).await?;
+    let mapped = match timeout(NATPMP_TIMEOUT, client.read_response_or_retry()).await.context("NAT-PMP timeout")?? {
+        Response::TCP(m) | Response::UDP(m) => m,
+        other => bail!("Unexpected NAT-PMP response: {:?}", other),
+    };
+
+    Ok(PortMapping {
+        external: SocketAddr::new(IpAddr::V4(public_ip), mapped.public_port()),
+        method: MappingMethod::NatPmp,
+        lifetime: *mapped.lifetime(),
+    })
+}
+
+async fn upnp_map(protocol: MappingProtocol, port: u16) -> anyhow::Result<PortMapping> {
+    let mut options = SearchOptions::default();
+    options.timeout = Some(UPNP_SEARCH_TIMEOUT);
+    let gateway = igd_next::aio::tokio::search_gateway(options).await.context("No UPnP gateway found")?;
+
+    let local_ip: IpAddr = MdnsBackend::get_local_ip().parse()?;
+    let local_addr = SocketAddr::new(local_ip, port);
+    let proto = match protocol { MappingProtocol::Tcp => PortMappingProtocol::TCP, MappingProtocol::Udp => PortMappingProtocol::UDP };
+
+    // Port เดียวกันก่อน (จำง่าย) ถ้ามีคนจองแล้วให้ Router เลือกให้
+    let external_port = match gateway.add_port(proto, port, local_addr, LEASE_SECS, DESCRIPTION).await {
+        Ok(()) => port,
+        Err(AddPortError::PortInUse) => gateway.add_any_port(proto, local_addr, LEASE_SECS, DESCRIPTION).await?,
+        Err(e) => return Err(e.into()),
+    };
+    let external_ip = gateway.get_external_ip().await?;
+
+    Ok(PortMapping {
+        external: SocketAddr::new(external_ip, external_port),
+        method: MappingMethod::Upnp,
+        lifetime: Duration::from_secs(LEASE_SECS as u64),
+    })
+}
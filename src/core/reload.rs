@@ -0,0 +1,207 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use crate::core::admin::Limit;
+use crate::core::builder::DropTeaBuilder;
+use crate::core::cancel::CancellationToken;
+use crate::core::config::AppConfig;
+use crate::core::diagnostics;
+use crate::core::engine::DropTeaConfig;
+use crate::core::error::{DropTeaError, Result};
+use crate::core::events::{TransferEvent, TransferEventHandler};
+use crate::core::peer_prefs::PeerOverrides;
+use crate::core::save_rules::{SaveRule, SaveRules};
+use crate::core::webhook::ApprovalWebhook;
+
+// ==========================================
+// Config Hot-Reload (แก้ config.toml ขณะ Engine รันอยู่)
+// มีผลทันที: dev_mode, [approval] (รับอัตโนมัติ), storage.download_dir, [[save_rules]], [peers.*], max_incoming / max_outgoing
+// และ server.max_bytes_per_sec (Transfer ถัดไป ที่กำลังวิ่งอยู่ใช้ค่าเดิมจนจบ)
+// ค่าอื่นเปลี่ยน (port / mode / storage / node_name ...) = ต้อง Restart -> ปฏิเสธทั้งไฟล์ ไม่ Apply ครึ่งๆ
+// ไม่มี File Notification ที่ใช้ได้ทุก OS จึง Poll mtime แทน (แบบเดียวกับ netwatch)
+// ==========================================
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// Key ของ diagnostics::redacted_config ที่ Apply ตอน Runtime ได้
+const RELOADABLE: &[&str] = &["dev_mode", "approval_webhook", "download_dir", "save_rules", "peers", "max_incoming", "max_outgoing", "max_bytes_per_sec"];
+
+/// ค่าที่เปลี่ยนได้ระหว่างที่ Transfer อื่นใช้อยู่ (get = Copy ของตอนนั้น Transfer ที่เริ่มไปแล้วใช้ค่าเดิมจนจบ)
+#[derive(Debug, Default)]
+pub struct Live<T>(RwLock<T>);
+
+impl<T: Clone> Live<T> {
+    pub fn new(value: T) -> Arc<Self> {
+        Arc::new(Self(RwLock::new(value)))
+    }
+
+    pub fn get(&self) -> T {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn set(&self, value: T) {
+        *self.0.write().unwrap() = value;
+    }
+}
+
+pub fn build_save_rules(rules: &[SaveRule]) -> Option<Arc<SaveRules>> {
+    (!rules.is_empty()).then(|| Arc::new(SaveRules::new(rules)))
+}
+
+pub struct ConfigReloader {
+    // Config ที่ Apply อยู่ตอนนี้ (ใช้เทียบกับไฟล์ใหม่ และเป็น Config ใน Diagnostics)
+    current: StdMutex<DropTeaConfig>,
+    pub dev_mode: Arc<AtomicBool>,
+    pub approval_webhook: Arc<Live<Option<ApprovalWebhook>>>,
+    pub download_dir: Arc<Live<String>>,
+    pub save_rules: Arc<Live<Option<Arc<SaveRules>>>>,
+    pub peers: Arc<PeerOverrides>,
+    // Default ของ Peer ที่ไม่ได้ตั้ง max_bytes_per_sec ใน [peers.*]
+    pub max_bytes_per_sec: Arc<Live<Option<u64>>>,
+    incoming: Arc<Limit>,
+    outgoing: Arc<Limit>,
+    handler: Arc<Box<dyn TransferEventHandler>>,
+    // Watcher ตัวปัจจุบัน (watch ซ้ำ = หยุดตัวเก่า)
+    watcher: StdMutex<Option<CancellationToken>>,
+}
+
+impl ConfigReloader {
+    pub fn new(config: &DropTeaConfig, incoming: Arc<Limit>, outgoing: Arc<Limit>, handler: Arc<Box<dyn TransferEventHandler>>) -> Arc<Self> {
+        Arc::new(Self {
+            current: StdMutex::new(config.clone()),
+            dev_mode: Arc::new(AtomicBool::new(config.dev_mode)),
+            approval_webhook: Live::new(config.approval_webhook.clone()),
+            download_dir: Live::new(config.download_dir.clone()),
+            save_rules: Live::new(build_save_rules(&config.save_rules)),
+            peers: PeerOverrides::new(&config.peers),
+            max_bytes_per_sec: Live::new(config.max_bytes_per_sec),
+            incoming,
+            outgoing,
+            handler,
+            watcher: StdMutex::new(None),
+        })
+    }
+
+    pub fn current(&self) -> DropTeaConfig {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// Apply ค่าที่ปลอดภัยจาก Config ใหม่ คืนชื่อค่าที่เปลี่ยน (ว่าง = ไม่มีอะไรเปลี่ยน)
+    pub fn apply(&self, config: DropTeaConfig) -> Result<Vec<String>> {
+        let config = DropTeaBuilder::from_config(config).config()?;
+        let mut current = self.current.lock().unwrap();
+        let restart = restart_required(&current, &config);
+        if !restart.is_empty() {
+            return Err(DropTeaError::Config(format!("Restart required to change: {} (nothing was reloaded)", restart.join(", "))));
+        }
+
+        let mut changed = Vec::new();
+        if config.dev_mode != current.dev_mode {
+            self.dev_mode.store(config.dev_mode, Ordering::SeqCst);
+            changed.push("dev_mode");
+        }
+        if config.approval_webhook != current.approval_webhook {
+            self.approval_webhook.set(config.approval_webhook.clone());
+            changed.push("approval_webhook");
+        }
+        if config.download_dir != current.download_dir {
+            tracing::info!("download_dir {} -> {}", current.download_dir, config.download_dir);
+            self.download_dir.set(config.download_dir.clone());
+            changed.push("download_dir");
+        }
+        if config.save_rules != current.save_rules {
+            self.save_rules.set(build_save_rules(&config.save_rules));
+            changed.push("save_rules");
+        }
+        // ทับที่ตั้งผ่าน set_peer_override ด้วย (ไฟล์คือค่าล่าสุดที่ User ตั้งใจ)
+        if config.peers != current.peers {
+            self.peers.replace(&config.peers);
+            changed.push("peers");
+        }
+        // ลดขนาดได้เฉพาะ Permit ที่ว่าง ส่วนที่กำลังรับ/ส่งอยู่คืนตามปกติ
+        if config.max_incoming != current.max_incoming {
+            let applied = self.incoming.resize(config.max_incoming);
+            tracing::info!("max_incoming {} -> {} (applied {})", current.max_incoming, config.max_incoming, applied);
+            changed.push("max_incoming");
+        }
+        if config.max_outgoing != current.max_outgoing {
+            let applied = self.outgoing.resize(config.max_outgoing);
+            tracing::info!("max_outgoing {} -> {} (applied {})", current.max_outgoing, config.max_outgoing, applied);
+            changed.push("max_outgoing");
+        }
+        if config.max_bytes_per_sec != current.max_bytes_per_sec {
+            self.max_bytes_per_sec.set(config.max_bytes_per_sec);
+            changed.push("max_bytes_per_sec");
+        }
+        *current = config;
+        drop(current);
+
+        let changed: Vec<String> = changed.into_iter().map(String::from).collect();
+        if !changed.is_empty() {
+            tracing::info!("🔄 Config reloaded: {}", changed.join(", "));
+            self.handler.on_event(TransferEvent::ConfigReloaded { changed: changed.clone() });
+        }
+        Ok(changed)
+    }
+
+    pub fn reload_file(&self, path: &Path) -> Result<Vec<String>> {
+        let app_config = AppConfig::load_from_file(&path.to_string_lossy())
+            .map_err(|e| DropTeaError::Config(format!("Cannot load {}: {:#}", path.display(), e)))?;
+        self.apply(app_config.to_engine_config())
+    }
+
+    /// Poll ไฟล์จนกว่า cancel (Engine ถูกทิ้ง) หรือมีการ watch ไฟล์อื่นแทน
+    pub fn watch(self: &Arc<Self>, path: PathBuf, cancel: CancellationToken) -> impl std::future::Future<Output = ()> + Send + 'static {
+        if let Some(old) = self.watcher.lock().unwrap().replace(cancel.clone()) { old.cancel(); }
+        let this = self.clone();
+        async move {
+            let mut last = modified(&path);
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                }
+                let now = modified(&path);
+                // ไฟล์หายชั่วคราว (Editor บางตัว Save ด้วยการลบแล้วเขียนใหม่) -> รอรอบถัดไป
+                if now.is_none() || now == last { continue; }
+                last = now;
+                if let Err(e) = this.reload_file(&path) {
+                    tracing::warn!("Config reload rejected: {}", e);
+                    this.handler.on_event(TransferEvent::Error { task_id: "config".into(), error: e.to_string() });
+                }
+            }
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// ชื่อค่าที่ต่างกันแต่ Apply ตอน Runtime ไม่ได้
+fn restart_required(old: &DropTeaConfig, new: &DropTeaConfig) -> Vec<String> {
+    let (old_json, new_json) = (diagnostics::redacted_config(old), diagnostics::redacted_config(new));
+    let mut keys: Vec<String> = match (old_json.as_object(), new_json.as_object()) {
+        (Some(a), Some(b)) => a.keys()
+            .filter(|k| !RELOADABLE.contains(&k.as_str()) && a.get(*k) != b.get(*k))
+            .cloned()
+            .collect(),
+        _ => vec![],
+    };
+    // Diagnostics ตัดบางส่วนทิ้ง (Path ของ Share / Fingerprint / URL เต็ม) -> เทียบทั้งก้อนอีกรอบ
+    if keys.is_empty() {
+        let mut masked = new.clone();
+        masked.dev_mode = old.dev_mode;
+        masked.approval_webhook = old.approval_webhook.clone();
+        masked.download_dir = old.download_dir.clone();
+        masked.save_rules = old.save_rules.clone();
+        masked.peers = old.peers.clone();
+        masked.max_incoming = old.max_incoming;
+        masked.max_outgoing = old.max_outgoing;
+        masked.max_bytes_per_sec = old.max_bytes_per_sec;
+        if format!("{:?}", masked) != format!("{:?}", old) { keys.push("other settings".into()); }
+    }
+    keys
+}
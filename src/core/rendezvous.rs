@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::io::IoSliceMut;
+use std::net::{IpAddr, SocketAddr, UdpSocket as StdUdpSocket};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use dashmap::DashMap;
+use log::{debug, info, warn};
+use quinn::AsyncUdpSocket;
+use quinn::udp::{RecvMeta, Transmit, UdpState};
+use serde::{Serialize, Deserialize};
+use tokio::net::UdpSocket;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::timeout;
+use anyhow::{bail, Context as _};
+
+// ==========================================
+// Rendezvous + UDP Hole Punching (สำหรับ QUIC ข้าม NAT)
+// 1. ทั้งสองฝั่ง Register กับ Server จาก Socket เดียวกับ QUIC (NAT Mapping เดียวกัน)
+// 2. ผู้ส่งขอ Connect -> Server ส่ง Observed Address ให้ทั้งคู่
+// 3. ฝั่งรับยิง Punch ไปหาผู้ส่ง ขณะที่ผู้ส่ง QUIC Connect ไปพร้อมกัน
+// 4. Punch ไม่ผ่านภายในเวลาที่กำหนด -> ผู้ส่งขอ Relay แล้ว Connect ผ่าน Relay ของ Server แทน
+// Server ตอบเฉพาะ Address ที่ Register ไว้แล้ว (ไม่สะท้อน Packet ไปหา Source ที่ปลอมมา)
+// และจำกัดจำนวน Relay + คำขอต่อ IP (Relay คือ Socket + Bandwidth ของ Server)
+// Packet ของ Rendezvous ขึ้นต้นด้วย 0x00 (QUIC ตั้ง Fixed Bit 0x40 เสมอ จึงไม่ชนกัน)
+// ==========================================
+
+const MAGIC: &[u8] = b"\0DTRZ";
+const REGISTER_INTERVAL: Duration = Duration::from_secs(15);
+const PEER_EXPIRY: Duration = Duration::from_secs(60);
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+const PUNCH_INTERVAL: Duration = Duration::from_millis(200);
+const PUNCH_DURATION: Duration = Duration::from_secs(5);
+const RELAY_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+// Relay ที่ไม่มีใครมาจองครบ 2 ฝั่งภายในเวลานี้ -> ปิด
+const RELAY_CLAIM_TIMEOUT: Duration = Duration::from_secs(15);
+const MAX_RELAYS: usize = 64;
+const MAX_RELAYS_PER_SOURCE: usize = 4;
+// Connect / Relay ต่อ IP ต่อช่วงเวลา
+const REQUEST_WINDOW: Duration = Duration::from_secs(10);
+const REQUESTS_PER_WINDOW: u32 = 20;
+pub const PUNCH_CONNECT_TIMEOUT: Duration = Duration::from_secs(6);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum RdvMessage {
+    Register { id: String },
+    Registered { observed: SocketAddr },
+    Connect { id: String, target: String },
+    Peer { id: String, addr: SocketAddr },
+    // Punch ไม่ผ่าน -> ขอ Relay ให้คู่นี้ (Server ตอบ Relayed ไปทั้งสองฝั่ง)
+    Relay { id: String, target: String },
+    Relayed { id: String, port: u16 },
+    Error { reason: String },
+    Punch { id: String },
+}
+
+fn encode(msg: &RdvMessage) -> Vec<u8> {
+    let mut buf = MAGIC.to_vec();
+    buf.extend(serde_json::to_vec(msg).unwrap_or_default());
+    buf
+}
+
+fn decode(data: &[u8]) -> Option<RdvMessage> {
+    data.strip_prefix(MAGIC).and_then(|json| serde_json::from_slice(json).ok())
+}
+
+fn is_rendezvous(data: &[u8]) -> bool { data.starts_with(MAGIC) }
+
+// --- Socket Wrapper: แยก Packet ของ Rendezvous ออกจาก QUIC ---
+
+pub struct FilteredSocket {
+    inner: Box<dyn AsyncUdpSocket>,
+    tx: mpsc::UnboundedSender<(SocketAddr, RdvMessage)>,
+}
+
+impl std::fmt::Debug for FilteredSocket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FilteredSocket").field("inner", &self.inner).finish()
+    }
+}
+
+impl AsyncUdpSocket for FilteredSocket {
+    fn poll_send(&self, state: &UdpState, cx: &mut Context, transmits: &[Transmit]) -> Poll<std::io::Result<usize>> {
+        self.inner.poll_send(state, cx, transmits)
+    }
+
+    fn poll_recv(&self, cx: &mut Context, bufs: &mut [IoSliceMut<'_>], meta: &mut [RecvMeta]) -> Poll<std::io::Result<usize>> {
+        loop {
+            let n = match self.inner.poll_recv(cx, bufs, meta) {
+                Poll::Ready(Ok(n)) => n,
+                other => return other,
+            };
+            // บีบ Datagram ของ QUIC ให้อยู่ต้น Array, ของเราส่งเข้า Channel
+            let mut kept = 0;
+            for i in 0..n {
+                let m = meta[i];
+                // GRO รวมหลาย Segment (stride < len) -> เป็น QUIC แน่นอน
+                if m.len == m.stride && is_rendezvous(&bufs[i][..m.len]) {
+                    if let Some(msg) = decode(&bufs[i][..m.len]) { let _ = self.tx.send((m.addr, msg)); }
+                    continue;
+                }
+                if kept != i {
+                    let (head, tail) = bufs.split_at_mut(i);
+                    head[kept][..m.len].copy_from_slice(&tail[0][..m.len]);
+                    meta[kept] = m;
+                }
+                kept += 1;
+            }
+            if kept > 0 { return Poll::Ready(Ok(kept)); }
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddr> { self.inner.local_addr() }
+
+    fn may_fragment(&self) -> bool { self.inner.may_fragment() }
+}
+
+// --- Client (ฝั่ง QuicTransport) ---
+
+pub struct RendezvousClient {
+    server: SocketAddr,
+    node_id: String,
+    // Clone ของ Socket QUIC (fd เดียวกัน) ไว้ส่ง Packet ดิบ
+    socket: StdUdpSocket,
+    pending: DashMap<String, oneshot::Sender<SocketAddr>>,
+    pending_relays: DashMap<String, oneshot::Sender<SocketAddr>>,
+    observed: StdMutex<Option<SocketAddr>>,
+    // id เพิ่มเติมที่ Register ไว้กับ Socket เดียวกัน (เช่น Nameplate ของ Code ดู codes.rs)
+    aliases: StdMutex<Vec<String>>,
+}
+
+impl RendezvousClient {
+    /// คืน Client + Socket ที่ต้องส่งให้ quinn Endpoint ใช้แทน Socket เดิม
+    pub fn attach(server: SocketAddr, node_id: &str, socket: StdUdpSocket, runtime: &Arc<dyn quinn::Runtime>) -> anyhow::Result<(Arc<Self>, FilteredSocket)> {
+        let raw = socket.try_clone()?;
+        let inner = runtime.wrap_udp_socket(socket)?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let client = Arc::new(Self {
+            server,
+            node_id: node_id.to_string(),
+            socket: raw,
+            pending: DashMap::new(),
+            pending_relays: DashMap::new(),
+            observed: StdMutex::new(None),
+            aliases: StdMutex::new(Vec::new()),
+        });
+        client.clone().spawn_tasks(rx);
+        Ok((client, FilteredSocket { inner, tx }))
+    }
+
+    fn send(&self, to: SocketAddr, msg: &RdvMessage) {
+        if let Err(e) = self.socket.send_to(&encode(msg), to) {
+            debug!("Rendezvous send to {} failed: {}", to, e);
+        }
+    }
+
+    fn relay_addr(&self, port: u16) -> SocketAddr {
+        SocketAddr::new(self.server.ip(), port)
+    }
+
+    fn spawn_tasks(self: Arc<Self>, mut rx: mpsc::UnboundedReceiver<(SocketAddr, RdvMessage)>) {
+        // Keepalive: ให้ Server รู้ว่ายังอยู่ และรักษา NAT Mapping ไม่ให้หมดอายุ
+        let me = self.clone();
+        tokio::spawn(async move {
+            loop {
+                me.send(me.server, &RdvMessage::Register { id: me.node_id.clone() });
+                me.register_aliases();
+                tokio::time::sleep(REGISTER_INTERVAL).await;
+            }
+        });
+
+        tokio::spawn(async move {
+            while let Some((from, msg)) = rx.recv().await {
+                match msg {
+                    RdvMessage::Registered { observed } => {
+                        let mut cur = self.observed.lock().unwrap();
+                        if *cur != Some(observed) { info!("🌐 Rendezvous observed address: {}", observed); }
+                        *cur = Some(observed);
+                    }
+                    RdvMessage::Peer { id, addr } => match self.pending.remove(&id) {
+                        // เราเป็นคนขอ Connect
+                        Some((_, waiter)) => { let _ = waiter.send(addr); }
+                        // มีคนจะ Connect มาหาเรา -> เปิด NAT ให้
+                        None => {
+                            info!("🕳️ Punching towards {} ({})", id, addr);
+                            self.clone().punch(addr);
+                        }
+                    },
+                    RdvMessage::Relayed { id, port } => {
+                        let relay = self.relay_addr(port);
+                        match self.pending_relays.remove(&id) {
+                            Some((_, waiter)) => { let _ = waiter.send(relay); }
+                            // Punch ของอีกฝั่งไม่ผ่าน -> จอง Slot ใน Relay ให้
+                            None => {
+                                info!("🔁 Relaying {} through {}", id, relay);
+                                self.clone().punch(relay);
+                            }
+                        }
+                    }
+                    RdvMessage::Error { reason } => warn!("Rendezvous error from {}: {}", from, reason),
+                    RdvMessage::Punch { id } => debug!("Punch received from {} ({})", id, from),
+                    _ => {}
+                }
+            }
+        });
+    }
+
+    fn register_aliases(&self) {
+        for id in self.aliases.lock().unwrap().clone() {
+            self.send(self.server, &RdvMessage::Register { id });
+        }
+    }
+
+    /// แทนชุด Alias ทั้งหมด (Register ทันที ที่เอาออกหมดอายุเองบน Server ตาม PEER_EXPIRY)
+    pub fn set_aliases(&self, ids: Vec<String>) {
+        *self.aliases.lock().unwrap() = ids;
+        self.register_aliases();
+    }
+
+    pub fn punch(self: Arc<Self>, to: SocketAddr) {
+        tokio::spawn(async move {
+            let started = Instant::now();
+            while started.elapsed() < PUNCH_DURATION {
+                self.send(to, &RdvMessage::Punch { id: self.node_id.clone() });
+                tokio::time::sleep(PUNCH_INTERVAL).await;
+            }
+        });
+    }
+
+    /// ถาม Server ว่า Peer อยู่ที่ไหน (Observed Address)
+    pub async fn resolve(&self, target: &str) -> anyhow::Result<SocketAddr> {
+        let (tx, rx) = oneshot::channel();
+        self.pending.insert(target.to_string(), tx);
+        self.send(self.server, &RdvMessage::Connect { id: self.node_id.clone(), target: target.to_string() });
+        match timeout(RESOLVE_TIMEOUT, rx).await {
+            Ok(Ok(found)) => Ok(found),
+            _ => {
+                self.pending.remove(target);
+                bail!("Peer '{}' not found on rendezvous server", target)
+            }
+        }
+    }
+
+    /// ขอ Relay บน Server (หลัง Punch ไม่ผ่านเท่านั้น)
+    pub async fn relay(&self, target: &str) -> anyhow::Result<SocketAddr> {
+        let (tx, rx) = oneshot::channel();
+        self.pending_relays.insert(target.to_string(), tx);
+        self.send(self.server, &RdvMessage::Relay { id: self.node_id.clone(), target: target.to_string() });
+        match timeout(RESOLVE_TIMEOUT, rx).await {
+            Ok(Ok(relay)) => Ok(relay),
+            _ => {
+                self.pending_relays.remove(target);
+                bail!("Rendezvous server has no relay for '{}'", target)
+            }
+        }
+    }
+}
+
+// --- Server ---
+
+struct Registration { addr: SocketAddr, seen: Instant }
+
+// Relay ที่เปิดอยู่ (รวม / ราย IP ของผู้ขอ) Task ของ Relay คืน Slot เองตอนปิด
+#[derive(Default)]
+struct RelayCount { total: usize, by_source: HashMap<IpAddr, usize> }
+
+type Relays = Arc<StdMutex<RelayCount>>;
+
+/// Rendezvous Server (รันบนเครื่องที่มี Public IP / Port Forward)
+pub async fn serve(bind: SocketAddr) -> anyhow::Result<()> {
+    let socket = UdpSocket::bind(bind).await.context("Failed to bind rendezvous server")?;
+    info!("🌐 Rendezvous server listening on {}", socket.local_addr()?);
+    let mut peers: HashMap<String, Registration> = HashMap::new();
+    let mut requests: HashMap<IpAddr, (Instant, u32)> = HashMap::new();
+    let relays = Relays::default();
+    let mut buf = vec![0u8; 2048];
+
+    loop {
+        let (len, from) = socket.recv_from(&mut buf).await?;
+        let Some(msg) = decode(&buf[..len]) else { continue };
+        peers.retain(|_, r| r.seen.elapsed() < PEER_EXPIRY);
+
+        // คู่ (ผู้ขอ, เป้าหมาย) ของ Connect / Relay: ผู้ขอต้อง Register จาก Address นี้ไว้แล้ว
+        // (Source ที่ปลอมมาจึงไม่ได้คำตอบ) และไม่เกินโควตาต่อ IP
+        let pair = match &msg {
+            RdvMessage::Register { id } => {
+                peers.insert(id.clone(), Registration { addr: from, seen: Instant::now() });
+                let _ = socket.send_to(&encode(&RdvMessage::Registered { observed: from }), from).await;
+                continue;
+            }
+            RdvMessage::Connect { id, target } | RdvMessage::Relay { id, target } => (id, target),
+            _ => continue,
+        };
+        if peers.get(pair.0).map(|r| r.addr) != Some(from) {
+            debug!("Ignoring rendezvous request from unregistered {}", from);
+            continue;
+        }
+        requests.retain(|_, (started, _)| started.elapsed() < REQUEST_WINDOW);
+        let (_, count) = requests.entry(from.ip()).or_insert((Instant::now(), 0));
+        *count += 1;
+        if *count > REQUESTS_PER_WINDOW {
+            debug!("Rate limiting rendezvous requests from {}", from.ip());
+            continue;
+        }
+        if let Some(r) = peers.get_mut(pair.0) { r.seen = Instant::now(); }
+        let Some(target_addr) = peers.get(pair.1).map(|r| r.addr) else {
+            let reply = RdvMessage::Error { reason: format!("Unknown peer '{}'", pair.1) };
+            let _ = socket.send_to(&encode(&reply), from).await;
+            continue;
+        };
+
+        let (to_target, to_requester) = match msg {
+            RdvMessage::Connect { id, target } => (RdvMessage::Peer { id, addr: from }, RdvMessage::Peer { id: target, addr: target_addr }),
+            RdvMessage::Relay { id, target } => {
+                let port = match spawn_relay(bind, from.ip(), &relays).await {
+                    Ok(port) => port,
+                    Err(e) => {
+                        warn!("Relay allocation for {} failed: {}", from, e);
+                        let _ = socket.send_to(&encode(&RdvMessage::Error { reason: "No relay available".into() }), from).await;
+                        continue;
+                    }
+                };
+                (RdvMessage::Relayed { id, port }, RdvMessage::Relayed { id: target, port })
+            }
+            _ => continue,
+        };
+        let _ = socket.send_to(&encode(&to_target), target_addr).await;
+        let _ = socket.send_to(&encode(&to_requester), from).await;
+    }
+}
+
+// Relay แบบใส: จับคู่ 2 Address แรกที่ส่งมา แล้วส่งต่อ Packet QUIC ระหว่างกัน
+async fn spawn_relay(bind: SocketAddr, source: IpAddr, relays: &Relays) -> anyhow::Result<u16> {
+    {
+        let mut count = relays.lock().unwrap();
+        let mine = count.by_source.get(&source).copied().unwrap_or(0);
+        if count.total >= MAX_RELAYS || mine >= MAX_RELAYS_PER_SOURCE {
+            bail!("relay limit reached ({} open, {} from {})", count.total, mine, source);
+        }
+        count.total += 1;
+        *count.by_source.entry(source).or_default() += 1;
+    }
+    let release = {
+        let relays = relays.clone();
+        move || {
+            let mut count = relays.lock().unwrap();
+            count.total -= 1;
+            if let Some(mine) = count.by_source.get_mut(&source) {
+                *mine -= 1;
+                if *mine == 0 { count.by_source.remove(&source); }
+            }
+        }
+    };
+    let socket = match UdpSocket::bind(SocketAddr::new(bind.ip(), 0)).await {
+        Ok(socket) => socket,
+        Err(e) => { release(); return Err(e.into()); }
+    };
+    let port = socket.local_addr()?.port();
+    tokio::spawn(async move {
+        let mut slots: [Option<SocketAddr>; 2] = [None, None];
+        let mut buf = vec![0u8; 65536];
+        loop {
+            let idle = if slots.iter().all(Option::is_some) { RELAY_IDLE_TIMEOUT } else { RELAY_CLAIM_TIMEOUT };
+            let Ok(Ok((len, from))) = timeout(idle, socket.recv_from(&mut buf)).await else { break };
+            if !slots.contains(&Some(from)) {
+                match slots.iter_mut().find(|s| s.is_none()) {
+                    Some(slot) => *slot = Some(from),
+                    None => continue,
+                }
+            }
+            // Punch มีไว้จอง Slot เท่านั้น ไม่ต้องส่งต่อ
+            if is_rendezvous(&buf[..len]) { continue; }
+            let other = if slots[0] == Some(from) { slots[1] } else { slots[0] };
+            if let Some(other) = other {
+                let _ = socket.send_to(&buf[..len], other).await;
+            }
+        }
+        release();
+        debug!("Relay on port {} closed (idle)", port);
+    });
+    Ok(port)
+}
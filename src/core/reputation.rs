@@ -0,0 +1,153 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use log::{info, warn};
+use serde::Deserialize;
+
+// ==========================================
+// Reputation Lookup ตอนถามรับไฟล์
+// ผู้รับที่เปิด Reputation ประกาศ FEATURE ผ่าน mDNS -> ผู้ส่งแนบ SHA-256 มาใน Header -> ถามแหล่งข้อมูล (DB ในเครื่อง / Service ภายนอก) ก่อนขึ้น Prompt
+// ผลลัพธ์แค่ไปแสดงใน Incoming Event ให้ User ตัดสินใจ ไม่ได้ปฏิเสธแทน
+// ==========================================
+
+/// ประกาศใน mDNS ว่าใช้ SHA-256 ใน FileHeader (ไม่ประกาศ = ผู้ส่งไม่ต้องอ่านทั้งไฟล์ก่อนเสนอ)
+pub const FEATURE: &str = "reputation";
+// ไม่ให้ Prompt ค้างรอ Service ช้า
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+    Known,
+    Unknown,
+    Malicious,
+    // ผู้ส่งไม่แนบ Hash หรือถามแหล่งข้อมูลไม่สำเร็จ
+    Unavailable,
+}
+
+impl Verdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Verdict::Known => "known",
+            Verdict::Unknown => "unknown",
+            Verdict::Malicious => "malicious",
+            Verdict::Unavailable => "unavailable",
+        }
+    }
+
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "known" | "clean" => Some(Verdict::Known),
+            "unknown" => Some(Verdict::Unknown),
+            "malicious" => Some(Verdict::Malicious),
+            _ => None,
+        }
+    }
+}
+
+/// แหล่งข้อมูล Reputation (Embedder ใส่ของตัวเองได้ผ่าน `ReceiveOptions::reputation`)
+#[async_trait]
+pub trait ReputationCheck: Send + Sync + std::fmt::Debug + 'static {
+    async fn lookup(&self, sha256: &str) -> anyhow::Result<Verdict>;
+}
+
+/// ไฟล์ข้อความ บรรทัดละ `<sha256> [known|malicious]` (ไม่ระบุ = known) อ่านใหม่ทุกครั้งให้แก้ได้ระหว่างรัน
+#[derive(Debug, Clone)]
+pub struct LocalHashDb {
+    pub path: PathBuf,
+}
+
+#[async_trait]
+impl ReputationCheck for LocalHashDb {
+    async fn lookup(&self, sha256: &str) -> anyhow::Result<Verdict> {
+        let content = tokio::fs::read_to_string(&self.path).await?;
+        let found = content.lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>())
+            .find(|parts| parts.first().is_some_and(|h| h.eq_ignore_ascii_case(sha256)));
+        Ok(match found {
+            Some(parts) => parts.get(1).and_then(|v| Verdict::from_name(&v.to_lowercase())).unwrap_or(Verdict::Known),
+            None => Verdict::Unknown,
+        })
+    }
+}
+
+/// GET `url` ที่แทน `{sha256}` แล้ว: 200 + {"verdict": "..."} / 404 = unknown
+#[derive(Debug, Clone)]
+pub struct HttpReputation {
+    pub url: String,
+    pub timeout: Duration,
+}
+
+#[derive(Deserialize)]
+struct HttpVerdict {
+    verdict: String,
+}
+
+#[async_trait]
+impl ReputationCheck for HttpReputation {
+    async fn lookup(&self, sha256: &str) -> anyhow::Result<Verdict> {
+        let client = reqwest::Client::builder().timeout(self.timeout).build()?;
+        let resp = client.get(self.url.replace("{sha256}", sha256)).send().await?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(Verdict::Unknown);
+        }
+        let body: HttpVerdict = resp.error_for_status()?.json().await?;
+        Verdict::from_name(&body.verdict.to_lowercase()).ok_or_else(|| anyhow::anyhow!("Unknown verdict '{}'", body.verdict))
+    }
+}
+
+/// ถามทุกแหล่ง: malicious ชนะ แล้ว known แล้ว unknown
+#[derive(Debug)]
+pub struct ReputationChain(pub Vec<Arc<dyn ReputationCheck>>);
+
+#[async_trait]
+impl ReputationCheck for ReputationChain {
+    async fn lookup(&self, sha256: &str) -> anyhow::Result<Verdict> {
+        let mut result = Err(anyhow::anyhow!("No reputation source"));
+        for check in &self.0 {
+            match check.lookup(sha256).await {
+                Ok(Verdict::Malicious) => return Ok(Verdict::Malicious),
+                Ok(Verdict::Known) => result = Ok(Verdict::Known),
+                Ok(v) => if !matches!(result, Ok(Verdict::Known)) { result = Ok(v) },
+                Err(e) => warn!("Reputation source {:?} failed: {}", check, e),
+            }
+        }
+        result
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReputationConfig {
+    pub db: Option<PathBuf>,
+    pub url: Option<String>,
+    pub timeout: Option<Duration>,
+}
+
+impl ReputationConfig {
+    pub fn build(&self) -> Option<Arc<dyn ReputationCheck>> {
+        let mut sources: Vec<Arc<dyn ReputationCheck>> = Vec::new();
+        if let Some(path) = &self.db {
+            sources.push(Arc::new(LocalHashDb { path: path.clone() }));
+        }
+        if let Some(url) = &self.url {
+            sources.push(Arc::new(HttpReputation { url: url.clone(), timeout: self.timeout.unwrap_or(LOOKUP_TIMEOUT) }));
+        }
+        match sources.len() {
+            0 => None,
+            1 => sources.pop(),
+            _ => Some(Arc::new(ReputationChain(sources))),
+        }
+    }
+}
+
+pub async fn verdict(check: &dyn ReputationCheck, sha256: Option<&str>, filename: &str) -> Verdict {
+    let Some(hash) = sha256 else { return Verdict::Unavailable; };
+    match tokio::time::timeout(LOOKUP_TIMEOUT, check.lookup(hash)).await {
+        Ok(Ok(v)) => {
+            info!("Reputation of '{}' ({}): {}", filename, hash, v.as_str());
+            v
+        }
+        Ok(Err(e)) => { warn!("Reputation lookup failed: {}", e); Verdict::Unavailable }
+        Err(_) => { warn!("Reputation lookup timed out"); Verdict::Unavailable }
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::core::transfer::FileHeader;
+
+// ==========================================
+// Resume หลัง Connection หลุดกลางไฟล์ (เปลี่ยน AP แล้ว QUIC ย้าย Path ไม่ทัน / TCP โดน Reset)
+// ผู้รับเก็บ .part ที่เขียนไปแล้วไว้ชั่วคราว ผูกกับ (Fingerprint ผู้ส่ง, transfer_id) -> IP ใหม่ก็ต่อได้
+// ผู้ส่งต่อใหม่ด้วย FileHeader เดิม + resuming = true -> ผู้รับไม่ถามซ้ำ ตอบ ACK(1, offset) -> ผู้ส่ง Seek ไป offset แล้วส่งส่วนที่เหลือ
+// ไม่มี .part ให้ต่อ (หมดเวลา) = ปฏิเสธ ผู้ส่งจบด้วย Error เดิม ไม่ขึ้น Prompt ใหม่
+// ข้อมูลที่ Park ไว้เขียนลง <ไฟล์>.part.resume ด้วย -> ผู้รับ Restart แล้ว Janitor อ่านกลับเข้า Registry ได้
+// Janitor (ตอน start_service + ทุก JANITOR_INTERVAL): .part ที่ไม่ถูกแตะเกิน PART_TTL = ลบ ที่เหลือดูได้จาก list_incomplete
+// ==========================================
+
+/// ประกาศใน mDNS ว่าเก็บ .part ไว้ให้ต่อได้
+pub const FEATURE: &str = "resume";
+// รอผู้ส่งกลับมานานเท่านี้แล้วทิ้ง .part (ครอบ Retry ของผู้ส่งหลายรอบ)
+pub const RESUME_WINDOW: Duration = Duration::from_secs(5 * 60);
+// .part ที่ไม่ถูกเขียนนานเท่านี้ = Transfer ที่ตายไปแล้ว (ตัวที่ยังรับอยู่เขียนตลอด)
+pub const PART_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+pub const JANITOR_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const SIDECAR_SUFFIX: &str = ".resume";
+
+/// Connection หลุด / ถูก Reset (ไม่ใช่ Disk เต็ม / ข้อมูลเสีย / ยกเลิก) -> ต่อใหม่แล้วส่งต่อได้
+pub fn connection_lost(e: &anyhow::Error) -> bool {
+    use std::io::ErrorKind;
+    e.chain().any(|cause| {
+        cause.downcast_ref::<quinn::ConnectionError>().is_some()
+            || cause.downcast_ref::<std::io::Error>().is_some_and(|io| matches!(io.kind(),
+                ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::NotConnected
+                    | ErrorKind::BrokenPipe | ErrorKind::UnexpectedEof | ErrorKind::TimedOut))
+    })
+}
+
+/// ไฟล์ที่รับค้างไว้ (task_id เดิม -> UI เห็นเป็น Transfer เดียวกันต่อ)
+#[derive(Serialize, Deserialize)]
+pub struct Parked {
+    pub task_id: String,
+    pub filename: String,
+    pub filesize: u64,
+    pub final_path: PathBuf,
+    pub temp_path: PathBuf,
+    // Byte ที่อยู่บน Disk แล้ว (Flush ก่อน Park)
+    pub offset: u64,
+    // มาในชุดที่อนุมัติทั้ง Session (ค่าข้างในคือ SHA-256 ที่ต้องตรวจหลังรับครบ)
+    pub approved: Option<Option<String>>,
+}
+
+struct Slot {
+    parked: Parked,
+    generation: u64,
+    // หมดเวลาโดยไม่มีใครมาต่อ -> แจ้ง Error ด้วย Callback ของรอบที่หลุด
+    on_expire: Box<dyn FnOnce(&str) + Send>,
+}
+
+#[derive(Default)]
+pub struct ResumeRegistry {
+    slots: StdMutex<HashMap<String, Slot>>,
+    generation: StdMutex<u64>,
+}
+
+impl std::fmt::Debug for ResumeRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResumeRegistry").field("parked", &self.slots.lock().unwrap().len()).finish()
+    }
+}
+
+fn key(fingerprint: &str, transfer_id: &str) -> String {
+    format!("{}|{}", fingerprint, transfer_id)
+}
+
+// สิ่งที่ต้องรู้เพื่อ Park กลับหลัง Restart
+#[derive(Serialize, Deserialize)]
+struct Sidecar {
+    fingerprint: String,
+    transfer_id: String,
+    parked: Parked,
+}
+
+fn sidecar_path(part: &Path) -> PathBuf {
+    let mut path = part.as_os_str().to_owned();
+    path.push(SIDECAR_SUFFIX);
+    PathBuf::from(path)
+}
+
+fn remove_sidecar(part: &Path) {
+    let _ = std::fs::remove_file(sidecar_path(part));
+}
+
+/// .part หนึ่งไฟล์ที่ค้างอยู่บน Disk (list_incomplete)
+#[derive(Debug, Clone, Serialize)]
+pub struct IncompleteFile {
+    pub path: String,
+    pub filename: String,
+    // Byte ที่รับแล้ว / ขนาดเต็ม (รู้เฉพาะไฟล์ที่ Park ไว้ .part ทั่วไปถูกจองเต็มขนาดตั้งแต่ต้น)
+    pub received: Option<u64>,
+    pub filesize: Option<u64>,
+    pub modified_ms: u64,
+    // ผู้ส่งเดิมกลับมาต่อได้ (อยู่ใน Registry)
+    pub resumable: bool,
+}
+
+/// ผลของ Janitor หนึ่งรอบ
+#[derive(Debug, Default)]
+pub struct JanitorReport {
+    pub removed: usize,
+    pub recovered: usize,
+}
+
+impl ResumeRegistry {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// เก็บ .part ไว้ให้ผู้ส่งกลับมาต่อ (ครบ RESUME_WINDOW แล้วยังว่าง -> ลบ .part + on_expire)
+    pub fn park(self: &Arc<Self>, fingerprint: &str, transfer_id: &str, parked: Parked, on_expire: impl FnOnce(&str) + Send + 'static) {
+        let key = key(fingerprint, transfer_id);
+        let generation = {
+            let mut g = self.generation.lock().unwrap();
+            *g += 1;
+            *g
+        };
+        tracing::info!("Parked '{}' at {} of {} bytes, waiting for sender to resume", parked.filename, parked.offset, parked.filesize);
+        let sidecar = Sidecar { fingerprint: fingerprint.to_string(), transfer_id: transfer_id.to_string(), parked };
+        if let Err(e) = serde_json::to_vec(&sidecar).map_err(std::io::Error::from).and_then(|json| std::fs::write(sidecar_path(&sidecar.parked.temp_path), json)) {
+            tracing::debug!("Could not persist resume state: {}", e);
+        }
+        let parked = sidecar.parked;
+        self.slots.lock().unwrap().insert(key.clone(), Slot { parked, generation, on_expire: Box::new(on_expire) });
+        let registry = Arc::downgrade(self);
+        tokio::spawn(async move {
+            tokio::time::sleep(RESUME_WINDOW).await;
+            let Some(registry) = registry.upgrade() else { return };
+            let slot = {
+                let mut slots = registry.slots.lock().unwrap();
+                match slots.get(&key) {
+                    Some(slot) if slot.generation == generation => slots.remove(&key),
+                    _ => None,
+                }
+            };
+            if let Some(slot) = slot {
+                let _ = tokio::fs::remove_file(&slot.parked.temp_path).await;
+                remove_sidecar(&slot.parked.temp_path);
+                (slot.on_expire)(&slot.parked.task_id);
+            }
+        });
+    }
+
+    /// ผู้ส่งกลับมาพร้อม resuming = true: ชื่อ / ขนาดต้องตรงกับที่ Park ไว้ และ .part ยังอยู่ครบ
+    pub fn claim(&self, fingerprint: &str, header: &FileHeader) -> Option<Parked> {
+        let key = key(fingerprint, header.transfer_id.as_deref()?);
+        let slot = self.slots.lock().unwrap().remove(&key)?;
+        let parked = slot.parked;
+        remove_sidecar(&parked.temp_path);
+        let on_disk = std::fs::metadata(&parked.temp_path).map(|m| m.len()).unwrap_or(0);
+        if parked.filename != header.filename || parked.filesize != header.filesize || on_disk < parked.offset {
+            tracing::warn!("Cannot resume '{}': partial file no longer matches", header.filename);
+            let _ = std::fs::remove_file(&parked.temp_path);
+            (slot.on_expire)(&parked.task_id);
+            return None;
+        }
+        Some(parked)
+    }
+
+    fn is_parked(&self, part: &Path) -> bool {
+        self.slots.lock().unwrap().values().any(|slot| slot.parked.temp_path == part)
+    }
+
+    /// กวาด .part ใน dirs: เก่ากว่า ttl = ลบ / มี .resume แต่ไม่อยู่ใน Registry (Restart) = Park กลับให้ผู้ส่งต่อได้
+    pub fn sweep(self: &Arc<Self>, dirs: &[PathBuf], ttl: Duration) -> JanitorReport {
+        let mut report = JanitorReport::default();
+        for (part, modified) in part_files(dirs) {
+            if modified.elapsed().unwrap_or_default() > ttl {
+                if std::fs::remove_file(&part).is_ok() { report.removed += 1; }
+                remove_sidecar(&part);
+                continue;
+            }
+            if self.is_parked(&part) { continue; }
+            let Some(mut sidecar) = std::fs::read(sidecar_path(&part)).ok().and_then(|json| serde_json::from_slice::<Sidecar>(&json).ok()) else { continue };
+            sidecar.parked.temp_path = part;
+            let filename = sidecar.parked.filename.clone();
+            self.park(&sidecar.fingerprint, &sidecar.transfer_id, sidecar.parked, move |_| tracing::info!("Recovered partial file '{}' expired without a resume", filename));
+            report.recovered += 1;
+        }
+        // .resume ที่ไม่มี .part คู่แล้ว
+        for dir in dirs {
+            let Ok(entries) = std::fs::read_dir(dir) else { continue };
+            for path in entries.flatten().map(|e| e.path()) {
+                let Some(name) = path.to_str().and_then(|p| p.strip_suffix(SIDECAR_SUFFIX)) else { continue };
+                if name.ends_with(".part") && !Path::new(name).exists() { let _ = std::fs::remove_file(&path); }
+            }
+        }
+        report
+    }
+
+    /// .part ทั้งหมดใน dirs (ใหม่สุดก่อน)
+    pub fn list_incomplete(&self, dirs: &[PathBuf]) -> Vec<IncompleteFile> {
+        let slots = self.slots.lock().unwrap();
+        let mut files: Vec<IncompleteFile> = part_files(dirs).into_iter().map(|(part, modified)| {
+            let parked = slots.values().map(|slot| &slot.parked).find(|p| p.temp_path == part);
+            IncompleteFile {
+                path: part.to_string_lossy().into_owned(),
+                filename: parked.map_or_else(|| part.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default(), |p| p.filename.clone()),
+                received: parked.map(|p| p.offset),
+                filesize: parked.map(|p| p.filesize),
+                modified_ms: modified.duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64),
+                resumable: parked.is_some(),
+            }
+        }).collect();
+        files.sort_by_key(|f| std::cmp::Reverse(f.modified_ms));
+        files
+    }
+}
+
+// .part ทุกไฟล์ใน dirs (ไม่ลงโฟลเดอร์ย่อย) พร้อมเวลาที่เขียนล่าสุด / โฟลเดอร์ที่ไม่มีอยู่ข้ามไป
+fn part_files(dirs: &[PathBuf]) -> Vec<(PathBuf, std::time::SystemTime)> {
+    let mut seen = std::collections::HashSet::new();
+    dirs.iter()
+        .filter(|dir| seen.insert(dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf())))
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("part"))
+        .filter_map(|path| {
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((path, modified))
+        })
+        .collect()
+}
@@ -0,0 +1,233 @@
+use std::future::Future;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::Stdio;
+use std::task::{Context, Poll};
+use anyhow::Context as _;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, ReadBuf};
+use tokio::process::{ChildStdout, Command};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::core::compression::{CompressionAlgo, Decompressor};
+use crate::core::transfer::DataStream;
+
+// ==========================================
+// Sandboxed Receive (Helper Process)
+// แตก Compression ในโปรเซสแยกสิทธิ์ต่ำ: Stream ที่บีบมา -> stdin ของ Helper -> stdout กลับมาเขียนไฟล์ที่โปรเซสหลัก
+// Helper ไม่ต้องเปิดไฟล์/Socket เอง -> Linux (x86_64/aarch64) เข้า seccomp ก่อนอ่าน Byte แรก เหลือแค่ read/write/จัดการ Memory/exit
+// ถ้า Decoder ถูกเจาะก็ออกไปไหนไม่ได้นอกจาก Pipe ของตัวเอง (OS อื่นได้แค่ rlimit + โปรเซสแยก ยังไม่ใช่ Sandbox เต็มตัว)
+// Binary: src/bin/droptea-sandbox.rs
+// ==========================================
+
+// พอสำหรับ Window ของ zstd/lz4 + Runtime แต่ไม่ให้ Decompression Bomb กิน RAM ทั้งเครื่อง
+const HELPER_MEMORY_LIMIT: u64 = 1024 * 1024 * 1024;
+const HELPER_BUFFER_SIZE: usize = 256 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct SandboxHelper {
+    path: PathBuf,
+}
+
+impl SandboxHelper {
+    pub fn new(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        anyhow::ensure!(path.is_file(), "Sandbox helper not found: {}", path.display());
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// เริ่ม Helper สำหรับ Transfer หนึ่งตัว คืน Reader ของข้อมูลที่แตกแล้ว (ไม่เกิน `max_bytes`)
+    pub fn decode<S: DataStream>(&self, mut stream: S, algo: CompressionAlgo, max_bytes: u64) -> anyhow::Result<SandboxedReader> {
+        let mut child = Command::new(&self.path)
+            .arg(algo.as_str())
+            .arg(max_bytes.to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            // Helper เขียนไฟล์ไม่ได้ (RLIMIT_FSIZE) -> stderr ต้องเป็น Pipe ไม่ใช่ไฟล์ Log ของเรา
+            .stderr(Stdio::piped())
+            .env_clear()
+            .current_dir(std::env::temp_dir())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start sandbox helper {}", self.path.display()))?;
+        let mut stdin = child.stdin.take().context("Sandbox helper has no stdin")?;
+        let stdout = child.stdout.take().context("Sandbox helper has no stdout")?;
+        let mut stderr = child.stderr.take().context("Sandbox helper has no stderr")?;
+
+        // Socket -> Helper (จบเมื่อผู้ส่งปิด Stream หรือ Helper ตาย)
+        let pump = tokio::spawn(async move {
+            let _ = tokio::io::copy(&mut stream, &mut stdin).await;
+            let _ = stdin.shutdown().await;
+        });
+        let (exit_tx, exit_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let ok = match child.wait().await {
+                Ok(status) if status.success() => true,
+                Ok(status) => {
+                    let mut reason = String::new();
+                    let _ = stderr.read_to_string(&mut reason).await;
+                    log::warn!("Sandbox helper exited with {}: {}", status, reason.trim());
+                    false
+                }
+                Err(e) => { log::warn!("Sandbox helper wait failed: {}", e); false }
+            };
+            let _ = exit_tx.send(ok);
+        });
+        Ok(SandboxedReader { stdout, exit: exit_rx, pump })
+    }
+}
+
+/// stdout ของ Helper: EOF นับว่าสำเร็จก็ต่อเมื่อ Helper จบด้วย Exit 0 (ไม่งั้นไฟล์ที่ได้อาจขาดท้าย)
+pub struct SandboxedReader {
+    stdout: ChildStdout,
+    exit: oneshot::Receiver<bool>,
+    pump: JoinHandle<()>,
+}
+
+impl AsyncRead for SandboxedReader {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        match Pin::new(&mut self.stdout).poll_read(cx, buf) {
+            Poll::Ready(Ok(())) if buf.filled().len() == before => match Pin::new(&mut self.exit).poll(cx) {
+                Poll::Ready(Ok(true)) => Poll::Ready(Ok(())),
+                Poll::Ready(_) => Poll::Ready(Err(io::Error::other("Sandbox helper rejected the stream"))),
+                Poll::Pending => Poll::Pending,
+            },
+            other => other,
+        }
+    }
+}
+
+impl Drop for SandboxedReader {
+    fn drop(&mut self) {
+        self.pump.abort();
+    }
+}
+
+// --- ฝั่ง Helper ---
+
+// ลดสิทธิ์ก่อนแตะข้อมูลจาก Network: ห้ามเขียนไฟล์, ห้ามได้สิทธิ์เพิ่ม, root -> nobody
+#[cfg(unix)]
+fn drop_privileges() -> anyhow::Result<()> {
+    unsafe {
+        let limit = |resource, value: u64| {
+            let rlim = libc::rlimit { rlim_cur: value as libc::rlim_t, rlim_max: value as libc::rlim_t };
+            if libc::setrlimit(resource, &rlim) != 0 {
+                anyhow::bail!("setrlimit failed: {}", io::Error::last_os_error());
+            }
+            Ok(())
+        };
+        limit(libc::RLIMIT_FSIZE, 0)?;
+        limit(libc::RLIMIT_CORE, 0)?;
+        limit(libc::RLIMIT_AS, HELPER_MEMORY_LIMIT)?;
+        #[cfg(target_os = "linux")]
+        if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+            anyhow::bail!("PR_SET_NO_NEW_PRIVS failed: {}", io::Error::last_os_error());
+        }
+        if libc::geteuid() == 0 {
+            const NOBODY: u32 = 65534;
+            if libc::setgroups(0, std::ptr::null()) != 0 || libc::setgid(NOBODY) != 0 || libc::setuid(NOBODY) != 0 {
+                anyhow::bail!("Failed to drop root: {}", io::Error::last_os_error());
+            }
+        }
+    }
+    Ok(())
+}
+
+// Windows: โปรเซสแยกอย่างเดียว (ยังไม่มี Restricted Token)
+#[cfg(not(unix))]
+fn drop_privileges() -> anyhow::Result<()> { Ok(()) }
+
+// seccomp-bpf: หลังจากนี้เรียกได้แค่ Syscall ในรายการ นอกนั้น Kernel ฆ่าโปรเซสทันที (SIGSYS)
+// ไม่ใช้ Strict Mode เพราะ Decoder ของ zstd/lz4 จองหน่วยความจำเพิ่มระหว่างแตก (mmap/brk) และ Rust จบด้วย exit_group
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn confine() -> anyhow::Result<()> {
+    use libc::{sock_filter, sock_fprog, BPF_ABS, BPF_JEQ, BPF_JMP, BPF_K, BPF_LD, BPF_RET, BPF_W};
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xC000_003E;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xC000_00B7;
+    // offsetof(struct seccomp_data, nr / arch)
+    const NR: u32 = 0;
+    const ARCH: u32 = 4;
+    const ALLOWED: &[libc::c_long] = &[
+        libc::SYS_read, libc::SYS_write, libc::SYS_writev,
+        libc::SYS_mmap, libc::SYS_munmap, libc::SYS_mremap, libc::SYS_brk, libc::SYS_madvise,
+        libc::SYS_futex, libc::SYS_sched_yield, libc::SYS_clock_gettime,
+        libc::SYS_rt_sigreturn, libc::SYS_rt_sigprocmask, libc::SYS_sigaltstack,
+        libc::SYS_close, libc::SYS_exit, libc::SYS_exit_group,
+    ];
+
+    let stmt = |code: u32, k: u32| sock_filter { code: code as u16, jt: 0, jf: 0, k };
+    let jump = |k: u32, jt: u8, jf: u8| sock_filter { code: (BPF_JMP | BPF_JEQ | BPF_K) as u16, jt, jf, k };
+    let mut filter = vec![
+        // ABI อื่น (เช่น i386 ผ่าน int 0x80) ใช้เลข Syscall คนละชุด -> ไม่ผ่าน
+        stmt(BPF_LD | BPF_W | BPF_ABS, ARCH),
+        jump(AUDIT_ARCH, 1, 0),
+        stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+        stmt(BPF_LD | BPF_W | BPF_ABS, NR),
+    ];
+    for &nr in ALLOWED {
+        filter.push(jump(nr as u32, 0, 1));
+        filter.push(stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_ALLOW));
+    }
+    filter.push(stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_KILL_PROCESS));
+
+    let program = sock_fprog { len: filter.len() as u16, filter: filter.as_mut_ptr() };
+    // ต้องตั้ง PR_SET_NO_NEW_PRIVS ก่อน (drop_privileges) ไม่งั้น User ธรรมดาติดตั้ง Filter ไม่ได้
+    if unsafe { libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &program as *const sock_fprog) } != 0 {
+        anyhow::bail!("Failed to enter seccomp: {}", io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+fn confine() -> anyhow::Result<()> { Ok(()) }
+
+// stdin แบบ Blocking ห่อเป็น AsyncRead ให้ Decompressor: Runtime ไม่ต้องมี epoll / Thread Pool (ซึ่งต้องใช้ Syscall นอก Filter)
+struct BlockingStdin(io::StdinLock<'static>);
+
+impl AsyncRead for BlockingStdin {
+    fn poll_read(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        loop {
+            match self.0.read(buf.initialize_unfilled()) {
+                Ok(n) => { buf.advance(n); return Poll::Ready(Ok(())); }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+    }
+}
+
+/// Entry ของ droptea-sandbox: `droptea-sandbox <algo> <max_bytes>` อ่าน stdin เขียน stdout
+pub fn helper_main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let algo = args.next().as_deref().and_then(CompressionAlgo::from_name).context("Usage: droptea-sandbox <algo> <max_bytes>")?;
+    let max_bytes: u64 = args.next().context("Missing max_bytes")?.parse().context("Invalid max_bytes")?;
+
+    // เตรียมทุกอย่างที่ต้องเปิด/สร้างก่อนเข้า seccomp
+    let rt = tokio::runtime::Builder::new_current_thread().build()?;
+    let mut decoder = Decompressor::new(BlockingStdin(io::stdin().lock()), algo);
+    let mut stdout = io::stdout().lock();
+    let mut buf = vec![0u8; HELPER_BUFFER_SIZE];
+    drop_privileges()?;
+    confine()?;
+
+    rt.block_on(async move {
+        let mut written = 0u64;
+        loop {
+            let n = decoder.read(&mut buf).await?;
+            if n == 0 { break; }
+            written += n as u64;
+            // ข้อมูลแตกออกมาเกินขนาดที่ Header บอก = Stream ผิดปกติ/Bomb
+            anyhow::ensure!(written <= max_bytes, "Decompressed data exceeds declared size ({} bytes)", max_bytes);
+            stdout.write_all(&buf[..n])?;
+        }
+        stdout.flush()?;
+        Ok(())
+    })
+}
@@ -0,0 +1,159 @@
+use std::path::{Path, PathBuf};
+
+use crate::core::utils::get_unique_path;
+
+// ==========================================
+// Save Rules (แยกโฟลเดอร์ปลายทางตามชนิดไฟล์)
+// กฎแรกที่ตรงชนะ ไม่ตรงกฎไหนเลย -> save_path ตามเดิม
+// match รับได้ 3 แบบ: ชื่อกลุ่ม ("images" / "videos" / "audio" / "docs" / "archives"), นามสกุล (".pdf"), MIME ("image/*")
+// MIME เดาจากนามสกุลเท่านั้น (ไม่เปิดอ่านไฟล์ เพราะตัดสินก่อนไฟล์มาถึง)
+// ==========================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    // ชื่อซ้ำ -> เติม _1 / timestamp เหมือน save_path
+    #[default]
+    Unique,
+    // เขียนทับไฟล์เดิม (ตอน rename .part เสร็จ)
+    Overwrite,
+}
+
+impl ConflictPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "unique" | "rename" => Some(Self::Unique),
+            "overwrite" | "replace" => Some(Self::Overwrite),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unique => "unique",
+            Self::Overwrite => "overwrite",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveRule {
+    pub matchers: Vec<String>,
+    // "~/" = Home ของ User
+    pub dest: PathBuf,
+    pub conflict: ConflictPolicy,
+}
+
+// นามสกุล -> MIME (เฉพาะที่พบบ่อย)
+const EXT_MIME: &[(&str, &str)] = &[
+    ("jpg", "image/jpeg"), ("jpeg", "image/jpeg"), ("png", "image/png"), ("gif", "image/gif"),
+    ("webp", "image/webp"), ("heic", "image/heic"), ("heif", "image/heif"), ("bmp", "image/bmp"),
+    ("tif", "image/tiff"), ("tiff", "image/tiff"), ("svg", "image/svg+xml"), ("avif", "image/avif"),
+    ("mp4", "video/mp4"), ("m4v", "video/mp4"), ("mov", "video/quicktime"), ("mkv", "video/x-matroska"),
+    ("avi", "video/x-msvideo"), ("webm", "video/webm"), ("3gp", "video/3gpp"),
+    ("mp3", "audio/mpeg"), ("m4a", "audio/mp4"), ("aac", "audio/aac"), ("wav", "audio/wav"),
+    ("flac", "audio/flac"), ("ogg", "audio/ogg"), ("opus", "audio/opus"),
+    ("pdf", "application/pdf"), ("txt", "text/plain"), ("md", "text/markdown"), ("csv", "text/csv"),
+    ("rtf", "application/rtf"), ("doc", "application/msword"), ("xls", "application/vnd.ms-excel"),
+    ("ppt", "application/vnd.ms-powerpoint"),
+    ("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+    ("xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+    ("pptx", "application/vnd.openxmlformats-officedocument.presentationml.presentation"),
+    ("odt", "application/vnd.oasis.opendocument.text"), ("ods", "application/vnd.oasis.opendocument.spreadsheet"),
+    ("odp", "application/vnd.oasis.opendocument.presentation"), ("epub", "application/epub+zip"),
+    ("zip", "application/zip"), ("tar", "application/x-tar"), ("gz", "application/gzip"),
+    ("zst", "application/zstd"), ("7z", "application/x-7z-compressed"), ("rar", "application/vnd.rar"),
+];
+
+// ชื่อกลุ่ม -> MIME Pattern
+fn group_patterns(name: &str) -> Option<&'static [&'static str]> {
+    match name {
+        "images" => Some(&["image/*"]),
+        "videos" => Some(&["video/*"]),
+        "audio" => Some(&["audio/*"]),
+        "docs" => Some(&["application/pdf", "text/*", "application/rtf", "application/msword", "application/vnd.ms-*",
+            "application/vnd.openxmlformats-officedocument.*", "application/vnd.oasis.opendocument.*", "application/epub+zip"]),
+        "archives" => Some(&["application/zip", "application/x-tar", "application/gzip", "application/zstd",
+            "application/x-7z-compressed", "application/vnd.rar"]),
+        _ => None,
+    }
+}
+
+pub fn guess_mime(filename: &str) -> Option<&'static str> {
+    let ext = Path::new(filename).extension()?.to_str()?.to_lowercase();
+    EXT_MIME.iter().find(|(e, _)| *e == ext).map(|(_, m)| *m)
+}
+
+fn mime_matches(pattern: &str, mime: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => mime.starts_with(prefix),
+        None => pattern == mime,
+    }
+}
+
+impl SaveRule {
+    pub fn matches(&self, filename: &str) -> bool {
+        let ext = Path::new(filename).extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase());
+        let mime = guess_mime(filename);
+        self.matchers.iter().map(|m| m.trim().to_lowercase()).any(|m| {
+            if let Some(want) = m.strip_prefix('.') {
+                ext.as_deref() == Some(want)
+            } else if let Some(patterns) = group_patterns(&m) {
+                mime.is_some_and(|mime| patterns.iter().any(|p| mime_matches(p, mime)))
+            } else {
+                mime.is_some_and(|mime| mime_matches(&m, mime))
+            }
+        })
+    }
+
+    fn dir(&self) -> PathBuf {
+        let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+        match (self.dest.strip_prefix("~"), home) {
+            (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+            _ => self.dest.clone(),
+        }
+    }
+}
+
+/// ที่ลงของไฟล์หนึ่งไฟล์ (ตัดสินครั้งเดียวตอนได้ Header)
+#[derive(Debug, Clone)]
+pub struct SaveTarget {
+    pub dir: String,
+    pub conflict: ConflictPolicy,
+    // false = ไม่เข้ากฎไหน ใช้ save_path
+    pub matched: bool,
+}
+
+impl SaveTarget {
+    pub fn unmatched(save_path: &str) -> Self {
+        Self { dir: save_path.to_string(), conflict: ConflictPolicy::Unique, matched: false }
+    }
+
+    /// Path สุดท้าย (filename ถูกตัดเหลือแค่ชื่อไฟล์เหมือน get_unique_path)
+    pub fn final_path(&self, filename: &str) -> PathBuf {
+        match self.conflict {
+            ConflictPolicy::Unique => get_unique_path(&self.dir, filename),
+            ConflictPolicy::Overwrite => {
+                let safe = Path::new(filename).file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_else(|| "unknown_file".to_string());
+                Path::new(&self.dir).join(safe)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SaveRules {
+    rules: Vec<SaveRule>,
+}
+
+impl SaveRules {
+    pub fn new(rules: &[SaveRule]) -> Self {
+        Self { rules: rules.to_vec() }
+    }
+
+    pub fn resolve(&self, save_path: &str, filename: &str) -> SaveTarget {
+        match self.rules.iter().find(|r| r.matches(filename)) {
+            Some(rule) => SaveTarget { dir: rule.dir().to_string_lossy().to_string(), conflict: rule.conflict, matched: true },
+            None => SaveTarget::unmatched(save_path),
+        }
+    }
+}
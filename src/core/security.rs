@@ -1,10 +1,11 @@
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock, Weak};
 use std::collections::{HashMap, HashSet};
 use std::time::SystemTime;
 use std::fs; 
 use std::path::{Path, PathBuf};
-use rustls::{Certificate, PrivateKey, ServerName, ClientConfig, ServerConfig};
+use rustls::{Certificate, DistinguishedName, PrivateKey, ServerName, ClientConfig, ServerConfig};
 use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::server::{ClientCertVerified, ClientCertVerifier};
 use rcgen::generate_simple_self_signed;
 use blake3;
 use anyhow::{Context, Result as AnyResult};
@@ -17,22 +18,21 @@ use crate::core::transfer::{TransferCallback, CertificateAction};
 // 1. Data Structures for Storage
 // ==========================================
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 struct KnownHostsStore {
     hosts: HashMap<String, String>, // IP/Hostname -> Fingerprint
 }
 
-impl Default for KnownHostsStore {
-    fn default() -> Self {
-        Self { hosts: HashMap::new() }
-    }
-}
-
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 struct WhitelistStore {
     trusted_senders: HashSet<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct ShareAclStore {
+    grants: HashMap<String, HashSet<String>>, // Share Name -> Fingerprints ที่ List/Pull ได้
+}
+
 // ==========================================
 // 2. Security Manager (Thread-Safe State)
 // ==========================================
@@ -41,10 +41,28 @@ pub struct SecurityManager {
     base_path: PathBuf,
     known_hosts: Arc<RwLock<KnownHostsStore>>,
     whitelist: Arc<RwLock<WhitelistStore>>,
+    share_acl: Arc<RwLock<ShareAclStore>>,
+    // false = Guest Mode (จำใน Memory อย่างเดียว ไม่เขียนลง Disk)
+    persist: bool,
 }
 
+// Manager ที่ยังมีคนถืออยู่ (เช่น TofuVerifier ของ Transport) ต่อ base_path
+// Helper ด้านล่างได้ตัวเดียวกัน -> forget_host / remove_trust มีผลกับ Connection ถัดไปทันที และ Cache เก่าไม่เขียนทับของที่ลบไปแล้ว
+static LIVE_MANAGERS: Mutex<Vec<(PathBuf, Weak<SecurityManager>)>> = Mutex::new(Vec::new());
+
 impl SecurityManager {
     pub fn new(base_path: PathBuf) -> Arc<Self> {
+        let mut live = LIVE_MANAGERS.lock().unwrap();
+        live.retain(|(_, weak)| weak.strong_count() > 0);
+        if let Some(manager) = live.iter().find(|(path, _)| *path == base_path).and_then(|(_, weak)| weak.upgrade()) {
+            return manager;
+        }
+        let manager = Self::load(&base_path);
+        live.push((base_path, Arc::downgrade(&manager)));
+        manager
+    }
+
+    fn load(base_path: &Path) -> Arc<Self> {
         // Create directory if not exists
         let sec_path = base_path.join("security");
         if !sec_path.exists() {
@@ -54,11 +72,25 @@ impl SecurityManager {
         // Load caches into memory
         let hosts = Self::load_known_hosts_from_disk(&sec_path);
         let whitelist = Self::load_whitelist_from_disk(&sec_path);
+        let share_acl = Self::load_share_acl_from_disk(&sec_path);
 
         Arc::new(Self {
             base_path: sec_path,
             known_hosts: Arc::new(RwLock::new(hosts)),
             whitelist: Arc::new(RwLock::new(whitelist)),
+            share_acl: Arc::new(RwLock::new(share_acl)),
+            persist: true,
+        })
+    }
+
+    /// เริ่มว่าง ไม่อ่าน/เขียน Disk เลย (หายไปพร้อม Engine)
+    pub fn ephemeral() -> Arc<Self> {
+        Arc::new(Self {
+            base_path: PathBuf::new(),
+            known_hosts: Arc::new(RwLock::new(KnownHostsStore::default())),
+            whitelist: Arc::new(RwLock::new(WhitelistStore::default())),
+            share_acl: Arc::new(RwLock::new(ShareAclStore::default())),
+            persist: false,
         })
     }
 
@@ -78,6 +110,7 @@ impl SecurityManager {
     }
 
     fn save_known_hosts_to_disk(&self, store: &KnownHostsStore) {
+        if !self.persist { return; }
         let path = self.base_path.join("known_hosts.json");
         if let Ok(json) = serde_json::to_string_pretty(store) {
             if let Err(e) = fs::write(&path, json) {
@@ -97,12 +130,36 @@ impl SecurityManager {
     }
 
     fn save_whitelist_to_disk(&self, store: &WhitelistStore) {
+        if !self.persist { return; }
         let path = self.base_path.join("whitelist.json");
         if let Ok(json) = serde_json::to_string_pretty(store) {
             let _ = fs::write(path, json);
         }
     }
 
+    fn load_share_acl_from_disk(sec_path: &Path) -> ShareAclStore {
+        let path = sec_path.join("share_acl.json");
+        if path.exists() {
+            if let Ok(content) = fs::read_to_string(&path) {
+                match serde_json::from_str::<ShareAclStore>(&content) {
+                    Ok(store) => return store,
+                    Err(e) => warn!("Failed to parse share_acl.json: {}", e),
+                }
+            }
+        }
+        ShareAclStore::default()
+    }
+
+    fn save_share_acl_to_disk(&self, store: &ShareAclStore) {
+        if !self.persist { return; }
+        let path = self.base_path.join("share_acl.json");
+        if let Ok(json) = serde_json::to_string_pretty(store) {
+            if let Err(e) = fs::write(&path, json) {
+                error!("Failed to write share_acl.json: {}", e);
+            }
+        }
+    }
+
     // --- Public Logic (Thread-Safe) ---
 
     pub fn get_known_fingerprint(&self, peer_id: &str) -> Option<String> {
@@ -128,6 +185,21 @@ impl SecurityManager {
         info!("Updated known_host for {}", peer_id); 
     }
 
+    pub fn known_hosts(&self) -> HashMap<String, String> {
+        self.known_hosts.read().unwrap().hosts.clone()
+    }
+
+    /// ลืม Fingerprint ที่จำไว้ (ต่อครั้งหน้าจะถาม TOFU ใหม่)
+    pub fn forget_host(&self, peer_id: &str) -> bool {
+        let mut guard = self.known_hosts.write().unwrap();
+        let removed = guard.hosts.remove(peer_id).is_some();
+        if removed {
+            self.save_known_hosts_to_disk(&guard);
+            info!("Forgot known_host {}", peer_id);
+        }
+        removed
+    }
+
     pub fn is_trusted(&self, sender_name: &str) -> bool {
         let guard = self.whitelist.read().unwrap();
         guard.trusted_senders.contains(sender_name)
@@ -140,6 +212,50 @@ impl SecurityManager {
             self.save_whitelist_to_disk(&guard);
         }
     }
+
+    pub fn remove_trust(&self, sender_name: &str) -> bool {
+        let mut guard = self.whitelist.write().unwrap();
+        let removed = guard.trusted_senders.remove(sender_name);
+        if removed {
+            self.save_whitelist_to_disk(&guard);
+        }
+        removed
+    }
+
+    /// รายชื่อใน Whitelist (เรียงตามตัวอักษร)
+    pub fn trusted_senders(&self) -> Vec<String> {
+        let mut senders: Vec<String> = self.whitelist.read().unwrap().trusted_senders.iter().cloned().collect();
+        senders.sort();
+        senders
+    }
+
+    pub fn can_access_share(&self, share: &str, fingerprint: &str) -> bool {
+        let guard = self.share_acl.read().unwrap();
+        guard.grants.get(share).is_some_and(|fps| fps.contains(fingerprint))
+    }
+
+    pub fn grant_share(&self, share: String, fingerprint: String) {
+        let mut guard = self.share_acl.write().unwrap();
+        if guard.grants.entry(share.clone()).or_default().insert(fingerprint) {
+            self.save_share_acl_to_disk(&guard);
+            info!("Granted access to share '{}'", share);
+        }
+    }
+
+    pub fn revoke_share(&self, share: &str, fingerprint: &str) {
+        let mut guard = self.share_acl.write().unwrap();
+        let removed = guard.grants.get_mut(share).is_some_and(|fps| fps.remove(fingerprint));
+        if removed {
+            guard.grants.retain(|_, fps| !fps.is_empty());
+            self.save_share_acl_to_disk(&guard);
+            info!("Revoked access to share '{}'", share);
+        }
+    }
+
+    pub fn share_grants(&self, share: &str) -> Vec<String> {
+        let guard = self.share_acl.read().unwrap();
+        guard.grants.get(share).map(|fps| fps.iter().cloned().collect()).unwrap_or_default()
+    }
 }
 
 // ==========================================
@@ -158,6 +274,42 @@ pub fn add_trust(base_path: &str, sender_name: String) {
     manager.add_trust(sender_name);
 }
 
+pub fn remove_trust(base_path: &str, sender_name: &str) -> bool {
+    SecurityManager::new(PathBuf::from(base_path)).remove_trust(sender_name)
+}
+
+pub fn trusted_senders(base_path: &str) -> Vec<String> {
+    SecurityManager::new(PathBuf::from(base_path)).trusted_senders()
+}
+
+pub fn known_fingerprint(base_path: &str, peer_id: &str) -> Option<String> {
+    SecurityManager::new(PathBuf::from(base_path)).get_known_fingerprint(peer_id)
+}
+
+pub fn known_hosts(base_path: &str) -> HashMap<String, String> {
+    SecurityManager::new(PathBuf::from(base_path)).known_hosts()
+}
+
+pub fn forget_host(base_path: &str, peer_id: &str) -> bool {
+    SecurityManager::new(PathBuf::from(base_path)).forget_host(peer_id)
+}
+
+pub fn can_access_share(base_path: &str, share: &str, fingerprint: &str) -> bool {
+    SecurityManager::new(PathBuf::from(base_path)).can_access_share(share, fingerprint)
+}
+
+pub fn grant_share(base_path: &str, share: String, fingerprint: String) {
+    SecurityManager::new(PathBuf::from(base_path)).grant_share(share, fingerprint);
+}
+
+pub fn revoke_share(base_path: &str, share: &str, fingerprint: &str) {
+    SecurityManager::new(PathBuf::from(base_path)).revoke_share(share, fingerprint);
+}
+
+pub fn share_grants(base_path: &str, share: &str) -> Vec<String> {
+    SecurityManager::new(PathBuf::from(base_path)).share_grants(share)
+}
+
 // ==========================================
 // 4. Identity Management
 // ==========================================
@@ -176,6 +328,8 @@ pub fn load_or_generate_identity(storage_path: &str, node_name: &str) -> AnyResu
         info!("Loading persistent identity: {}", node_name);
         let cert_der = fs::read(&cert_path).context("Failed to read cert")?;
         let key_der = fs::read(&key_path).context("Failed to read key")?;
+        // ใส่ค่านี้ใน [admin] fingerprints ของเครื่องที่ต้องการสั่งงาน
+        info!("Identity fingerprint: {}", fingerprint(&Certificate(cert_der.clone())));
         return Ok((vec![Certificate(cert_der)], PrivateKey(key_der)));
     }
 
@@ -199,16 +353,57 @@ pub fn load_or_generate_identity(storage_path: &str, node_name: &str) -> AnyResu
         f.write_all(&key_der).context("Failed to write key")?;
     }
     fs::write(&cert_path, &cert_der).context("Failed save cert")?;
+    info!("Identity fingerprint: {}", fingerprint(&Certificate(cert_der.clone())));
 
     Ok((vec![Certificate(cert_der)], PrivateKey(key_der)))
 }
 
+// SAN ของ Cert แบบ Guest (ทุกเครื่องใช้ชื่อเดียวกัน -> ไม่ผูกชื่อกับ Fingerprint)
+const TEMP_CERT_NAME: &str = "droptea.temp";
+
 pub fn generate_temp_identity() -> AnyResult<(Vec<Certificate>, PrivateKey)> {
-    let subject_alt_names = vec!["droptea.temp".to_string()];
+    let subject_alt_names = vec![TEMP_CERT_NAME.to_string()];
     let cert = generate_simple_self_signed(subject_alt_names)?;
     Ok((vec![Certificate(cert.serialize_der()?)], PrivateKey(cert.serialize_private_key_der())))
 }
 
+/// ตัวตนที่ Transport ใช้
+/// guest = สร้างใหม่ใน Memory ทุกครั้งที่เปิด (ไม่เขียน Cert/Key ลง Disk ปิด Engine แล้วหายไปเอง)
+#[derive(Clone)]
+pub struct Identity {
+    pub certs: Vec<Certificate>,
+    pub key: PrivateKey,
+    pub guest: bool,
+}
+
+impl Identity {
+    pub fn load(storage_path: &str, node_name: &str, guest: bool) -> AnyResult<Self> {
+        if !guest {
+            let (certs, key) = load_or_generate_identity(storage_path, node_name)?;
+            return Ok(Self { certs, key, guest });
+        }
+        let (certs, key) = generate_temp_identity()?;
+        info!("Guest identity (not persisted): {}", certs.first().map(fingerprint).unwrap_or_default());
+        Ok(Self { certs, key, guest })
+    }
+
+    pub fn fingerprint(&self) -> Option<String> {
+        self.certs.first().map(fingerprint)
+    }
+
+    /// known_hosts / Whitelist ที่คู่กับตัวตนนี้ (Guest ไม่แตะของเจ้าของเครื่อง)
+    pub fn security_manager(&self, storage_path: &str) -> Arc<SecurityManager> {
+        match self.guest {
+            true => SecurityManager::ephemeral(),
+            false => SecurityManager::new(PathBuf::from(storage_path)),
+        }
+    }
+}
+
+pub fn fingerprint(cert: &Certificate) -> String {
+    blake3::hash(&cert.0).to_hex().to_string()
+}
+
 // ==========================================
 // 5. TOFU Verifier (Updated to use Manager)
 // ==========================================
@@ -229,8 +424,7 @@ impl TofuVerifier {
     }
 
     fn check_cert(&self, cert: &Certificate, server_name: &ServerName) -> Result<(), rustls::Error> {
-        let hash = blake3::hash(&cert.0);
-        let fingerprint = hash.to_hex().to_string();
+        let fingerprint = fingerprint(cert);
         
         let peer_id = match server_name {
             ServerName::DnsName(dns) => dns.as_ref().to_string(),
@@ -296,20 +490,94 @@ impl ServerCertVerifier for TofuVerifier {
     }
 }
 
+// ขอ Client Cert แบบไม่บังคับ เพื่อรู้ Fingerprint ของผู้ส่ง (ใช้กับ Admin)
+// ไม่ตรวจ Chain เพราะเป็น Self-signed แต่ rustls ยังตรวจ Signature ของ Handshake ให้ (พิสูจน์ว่าถือ Key จริง)
+pub struct FingerprintClientVerifier;
+
+impl ClientCertVerifier for FingerprintClientVerifier {
+    fn client_auth_mandatory(&self) -> bool { false }
+
+    fn client_auth_root_subjects(&self) -> &[DistinguishedName] { &[] }
+
+    fn verify_client_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _now: SystemTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        Ok(ClientCertVerified::assertion())
+    }
+}
+
+// ผู้ส่งที่ส่ง Cert มา: TOFU ผูกชื่อเครื่องใน Cert (SAN) กับ Fingerprint แบบเดียวกับ TofuVerifier ฝั่ง Client -> ยืนยันตัวตนทั้งสองทาง
+// ชื่อเดิมแต่ Cert เปลี่ยน = ปฏิเสธ Handshake (สวมชื่อที่อยู่ใน Whitelist ไม่ได้) ตั้งใจเปลี่ยน Key -> forget_host("client:<ชื่อ>")
+// ไม่ส่ง Cert มา (ผู้ส่งรุ่นเก่า) / Cert แบบ Guest ยังผ่าน แค่ไม่ถูกจำ
+pub const CLIENT_HOST_PREFIX: &str = "client:";
+
+pub struct TofuClientVerifier {
+    manager: Arc<SecurityManager>,
+}
+
+impl TofuClientVerifier {
+    pub fn new(manager: Arc<SecurityManager>) -> Arc<Self> {
+        Arc::new(Self { manager })
+    }
+}
+
+/// ชื่อเครื่องใน Cert (DNS SAN ตัวแรก ที่ load_or_generate_identity ใส่ node_name ไว้)
+fn cert_name(cert: &Certificate) -> Option<String> {
+    let (_, parsed) = x509_parser::parse_x509_certificate(&cert.0).ok()?;
+    let san = parsed.subject_alternative_name().ok()??;
+    san.value.general_names.iter().find_map(|name| match name {
+        x509_parser::extensions::GeneralName::DNSName(dns) => Some(dns.trim().to_string()),
+        _ => None,
+    })
+}
+
+impl ClientCertVerifier for TofuClientVerifier {
+    fn client_auth_mandatory(&self) -> bool { false }
+
+    fn client_auth_root_subjects(&self) -> &[DistinguishedName] { &[] }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _now: SystemTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        let Some(name) = cert_name(end_entity).filter(|n| !n.is_empty() && n != TEMP_CERT_NAME) else {
+            return Ok(ClientCertVerified::assertion());
+        };
+        let peer_id = format!("{}{}", CLIENT_HOST_PREFIX, name);
+        let fingerprint = fingerprint(end_entity);
+        match self.manager.get_known_fingerprint(&peer_id) {
+            Some(known) if known != fingerprint => {
+                warn!("SECURITY ALERT: Client fingerprint MISMATCH for {}", name);
+                Err(rustls::Error::General(format!("Client fingerprint mismatch for '{}'", name)))
+            }
+            Some(_) => Ok(ClientCertVerified::assertion()),
+            None => {
+                self.manager.save_known_host(peer_id, fingerprint);
+                Ok(ClientCertVerified::assertion())
+            }
+        }
+    }
+}
+
 // ==========================================
 // 6. TLS Config Builders
 // ==========================================
 
-pub fn build_tls_configs(storage_path: &str, node_name: &str) -> AnyResult<(ServerConfig, ClientConfig)> {
-    let (certs, key) = load_or_generate_identity(storage_path, node_name)?; 
+pub fn build_tls_configs(storage_path: &str, identity: &Identity) -> AnyResult<(ServerConfig, ClientConfig)> {
+    let (certs, key) = (identity.certs.clone(), identity.key.clone());
     
-    // ✅ สร้าง Manager ตรงนี้
-    let manager = SecurityManager::new(PathBuf::from(storage_path));
-    let tofu = TofuVerifier::new(manager); 
+    // ✅ สร้าง Manager ตรงนี้ (ใช้ร่วมกันทั้งตรวจ Server และ Client)
+    let manager = identity.security_manager(storage_path);
+    let tofu = TofuVerifier::new(manager.clone());
 
     let server_config = ServerConfig::builder()
         .with_safe_defaults()
-        .with_no_client_auth()
+        .with_client_cert_verifier(TofuClientVerifier::new(manager))
         .with_single_cert(certs.clone(), key.clone())?;
 
     let client_config = ClientConfig::builder()
@@ -322,13 +590,13 @@ pub fn build_tls_configs(storage_path: &str, node_name: &str) -> AnyResult<(Serv
 
 pub fn build_temp_tls_configs() -> AnyResult<(ServerConfig, ClientConfig)> {
     let (certs, key) = generate_temp_identity()?;
-    // ✅ สร้าง Temp Manager
-    let manager = SecurityManager::new(Path::new("./downloads").to_path_buf());
+    // ✅ สร้าง Temp Manager (ไม่เขียน known_hosts ลง Disk)
+    let manager = SecurityManager::ephemeral();
     let tofu = TofuVerifier::new(manager);
 
     let server_config = ServerConfig::builder()
         .with_safe_defaults()
-        .with_no_client_auth()
+        .with_client_cert_verifier(Arc::new(FingerprintClientVerifier))
         .with_single_cert(certs.clone(), key.clone())?;
 
     let client_config = ClientConfig::builder()
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::{Mutex as TokioMutex, OwnedMutexGuard};
+use tokio::time::{timeout_at, Instant};
+
+// ==========================================
+// Per-Sender Queue (ฝั่งรับ)
+// ผู้ส่งคนเดียวยิงหลายไฟล์พร้อมกัน -> รวมเป็น Session เดียว
+//   - ถาม User ทีละไฟล์ ไฟล์แรกตัดสินแล้ว ไฟล์ที่เหลือใน Session ใช้คำตอบเดิม
+//   - เขียนลง Disk ทีละไฟล์ตามลำดับที่เข้ามา (tokio Mutex คิวแบบ FIFO)
+// ผู้ส่งรอ ACK ได้จำกัด -> รอคิวเกิน Deadline ก็ปล่อยวิ่งขนานไปเลย ดีกว่าให้ผู้ส่ง Timeout
+// ==========================================
+
+#[derive(Debug, Default)]
+pub struct SenderQueues {
+    sessions: StdMutex<HashMap<String, (Arc<Session>, usize)>>,
+}
+
+#[derive(Debug)]
+struct Session {
+    id: String,
+    decision: StdMutex<Option<bool>>,
+    ask: Arc<TokioMutex<()>>,
+    turn: Arc<TokioMutex<()>>,
+}
+
+/// ถือไว้ตลอดการรับไฟล์หนึ่งไฟล์ Drop แล้วออกจาก Session (คนสุดท้ายออก = Session จบ)
+pub struct SessionTicket {
+    queues: Arc<SenderQueues>,
+    key: String,
+    session: Arc<Session>,
+}
+
+impl SenderQueues {
+    pub fn new() -> Arc<Self> { Arc::new(Self::default()) }
+
+    pub fn join(self: &Arc<Self>, key: String) -> SessionTicket {
+        let mut sessions = self.sessions.lock().unwrap();
+        let (session, members) = sessions.entry(key.clone()).or_insert_with(|| (Arc::new(Session {
+            id: uuid::Uuid::new_v4().to_string(),
+            decision: StdMutex::new(None),
+            ask: Arc::new(TokioMutex::new(())),
+            turn: Arc::new(TokioMutex::new(())),
+        }), 0));
+        *members += 1;
+        SessionTicket { queues: self.clone(), key, session: session.clone() }
+    }
+}
+
+impl SessionTicket {
+    pub fn session_id(&self) -> &str { &self.session.id }
+
+    /// ตัดสินรับ/ไม่รับ ทีละไฟล์ต่อ Session คืน (accepted, reused)
+    /// reused = ใช้คำตอบของไฟล์ก่อนหน้าใน Session (None = รอคิวถามไม่ทัน Deadline)
+    pub async fn decide<F: Future<Output = bool>>(&self, deadline: Instant, ask: F) -> Option<(bool, bool)> {
+        let _guard = timeout_at(deadline, self.session.ask.clone().lock_owned()).await.ok()?;
+        if let Some(decision) = *self.session.decision.lock().unwrap() {
+            return Some((decision, true));
+        }
+        let decision = ask.await;
+        *self.session.decision.lock().unwrap() = Some(decision);
+        Some((decision, false))
+    }
+
+    /// รอให้ไฟล์ก่อนหน้าของผู้ส่งคนนี้เขียนเสร็จ (None = เลย Deadline, วิ่งขนานแทน)
+    pub async fn wait_turn(&self, deadline: Instant) -> Option<OwnedMutexGuard<()>> {
+        match timeout_at(deadline, self.session.turn.clone().lock_owned()).await {
+            Ok(guard) => Some(guard),
+            Err(_) => {
+                log::debug!("Sender queue wait exceeded ACK budget, receiving in parallel");
+                None
+            }
+        }
+    }
+}
+
+impl Drop for SessionTicket {
+    fn drop(&mut self) {
+        let mut sessions = self.queues.sessions.lock().unwrap();
+        if let Some((_, members)) = sessions.get_mut(&self.key) {
+            *members -= 1;
+            if *members == 0 { sessions.remove(&self.key); }
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use anyhow::{bail, Context};
+use serde::{Serialize, Deserialize};
+use tokio::io::AsyncReadExt;
+use tokio::time::{timeout, Instant};
+
+use crate::core::admin::write_frame;
+use crate::core::duplicate;
+use crate::core::error::DropTeaError;
+use crate::core::save_rules::{ConflictPolicy, SaveTarget};
+use crate::core::transfer::{DataStream, DynTransport, FileHeader};
+use crate::core::utils;
+
+// ==========================================
+// Session Approval (อนุมัติทั้งชุดครั้งเดียว)
+// send_batch ส่ง Manifest (ชื่อ / ขนาด / SHA-256) ไปก่อนไฟล์แรก: [u32 len][JSON] ที่มี key "session"
+// ผู้รับถาม User ครั้งเดียวด้วยสรุปของชุด -> ไฟล์ในชุดนั้น (FileHeader.batch) ไม่ขึ้น Prompt อีก
+// แต่ทุกไฟล์ต้องตรงกับ Manifest ไม่งั้นยกเลิกทั้ง Session (ไฟล์ที่กำลังรับถูกยกเลิก ที่เหลือถูกปฏิเสธ)
+// Peer รุ่นเก่า / Webhook -> PerFile: ผู้ส่งส่งต่อตามปกติ แล้วผู้รับถามทีละไฟล์เหมือนเดิม
+// Mirror (mirror_folder): Offer มี mirror = ชื่อโฟลเดอร์ปลายทาง, name = Path ในโฟลเดอร์ + quick_hash
+// ผู้รับตอบ needed = ไฟล์ที่ยังไม่มี / ต่างจากต้นทาง แล้วรับเฉพาะนั้นลง <save_path>/<mirror>/<name> (ทับของเดิม)
+// ==========================================
+
+// Manifest ของโฟลเดอร์ใหญ่เกิน MAX_HEADER_SIZE ได้ -> รับ Offer ได้ใหญ่กว่า Header ปกติ
+pub const MAX_SESSION_OFFER_SIZE: usize = 1024 * 1024;
+// อนุมัติแล้วแต่ไฟล์มาไม่ครบภายในเวลานี้ -> ทิ้ง Session (ไฟล์ที่มาทีหลังกลับไปถามทีละไฟล์)
+const SESSION_TTL: Duration = Duration::from_secs(60 * 60);
+pub const REJECT_MANIFEST: &str = "Manifest violation";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    pub name: String,
+    pub size: u64,
+    // มีเฉพาะไฟล์ที่ผู้ส่งแนบ SHA-256 ใน FileHeader ด้วย (ไม่ใหญ่เกิน OFFER_HASH_MAX_SIZE)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    // Mirror: ผู้รับเทียบกับไฟล์ที่มีอยู่ (ดู duplicate.rs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quick_hash: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionOffer {
+    pub batch_id: String,
+    pub sender_name: String,
+    pub sender_device: String,
+    pub files: Vec<ManifestEntry>,
+    // Some = mirror_folder ไปที่โฟลเดอร์ชื่อนี้ใน save_path ของผู้รับ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirror: Option<String>,
+}
+
+impl SessionOffer {
+    pub fn total_bytes(&self) -> u64 { self.files.iter().map(|f| f.size).sum() }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionRequest {
+    pub session: SessionOffer,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionDecision {
+    Approved,
+    Declined,
+    // ให้ถามทีละไฟล์ตามปกติ (ผู้รับใช้ Webhook / Peer ไม่รู้จัก Session)
+    PerFile,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionResponse {
+    pub decision: SessionDecision,
+    // ตอบ Offer แบบ Mirror: name ของไฟล์ที่ต้องส่ง (None = ผู้รับไม่รู้จัก Mirror)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub needed: Option<Vec<String>>,
+}
+
+// --- ฝั่งรับ ---
+
+#[derive(Debug)]
+struct ApprovedSession {
+    // ชื่อซ้ำได้ (โฟลเดอร์ย่อยคนละที่) -> เก็บเป็นรายการต่อชื่อ
+    remaining: HashMap<String, Vec<ManifestEntry>>,
+    // Task ID ที่รับเข้ามาแล้ว (ยกเลิกทั้งหมดเมื่อ Session ถูก Abort)
+    admitted: Vec<String>,
+    aborted: bool,
+    approved_at: Instant,
+    // Mirror: โฟลเดอร์ปลายทาง (name ของไฟล์เป็น Path ใต้โฟลเดอร์นี้)
+    mirror: Option<PathBuf>,
+}
+
+pub enum Admission {
+    // ไม่มี Session ที่อนุมัติไว้ -> ถามตามปกติ
+    Prompt,
+    // รับได้เลย ต้องตรวจ SHA-256 ของไฟล์ที่รับมาให้ตรงค่านี้ (ถ้ามี)
+    Approved { sha256: Option<String> },
+    // ไม่ตรง Manifest -> ปฏิเสธไฟล์นี้ และยกเลิก Task ที่คืนมา
+    Violation { reason: String, cancel: Vec<String> },
+}
+
+/// Session ที่ผู้ใช้อนุมัติแล้ว key = Peer + Batch ID (เหมือน key ของ BatchTracker)
+#[derive(Debug, Default)]
+pub struct SessionApprovals {
+    sessions: StdMutex<HashMap<String, ApprovedSession>>,
+}
+
+impl SessionApprovals {
+    pub fn new() -> Arc<Self> { Arc::new(Self::default()) }
+
+    pub fn approve(&self, key: String, files: Vec<ManifestEntry>, mirror: Option<PathBuf>) {
+        let mut remaining: HashMap<String, Vec<ManifestEntry>> = HashMap::new();
+        for entry in files { remaining.entry(entry.name.clone()).or_default().push(entry); }
+        self.sessions.lock().unwrap().insert(key, ApprovedSession { remaining, admitted: Vec::new(), aborted: false, approved_at: Instant::now(), mirror });
+    }
+
+    /// โฟลเดอร์ปลายทางของ Session แบบ Mirror (None = Session ธรรมดา / ไม่มี Session)
+    pub fn mirror_dir(&self, key: &str) -> Option<PathBuf> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions.get(key).filter(|s| s.approved_at.elapsed() < SESSION_TTL).and_then(|s| s.mirror.clone())
+    }
+
+    pub fn admit(&self, key: &str, header: &FileHeader, task_id: &str) -> Admission {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, s| s.approved_at.elapsed() < SESSION_TTL);
+        let Some(session) = sessions.get_mut(key) else { return Admission::Prompt; };
+        if session.aborted {
+            return Admission::Violation { reason: format!("{}: session aborted", REJECT_MANIFEST), cancel: Vec::new() };
+        }
+        let candidates = session.remaining.get_mut(&header.filename);
+        let found = candidates.as_ref().and_then(|c| c.iter().position(|e| {
+            // Header ไม่แนบ Hash (ผู้รับไม่ได้ขอ) -> เทียบเนื้อไฟล์กับ Manifest ตอนรับจบแทน
+            e.size == header.filesize && (e.sha256.is_none() || header.sha256.is_none() || e.sha256 == header.sha256)
+        }));
+        let (Some(candidates), Some(i)) = (candidates, found) else {
+            session.aborted = true;
+            let reason = format!("{}: unexpected '{}' ({} bytes)", REJECT_MANIFEST, header.filename, header.filesize);
+            return Admission::Violation { reason, cancel: std::mem::take(&mut session.admitted) };
+        };
+        let entry = candidates.swap_remove(i);
+        if candidates.is_empty() { session.remaining.remove(&header.filename); }
+        session.admitted.push(task_id.to_string());
+        if session.remaining.is_empty() { sessions.remove(key); }
+        Admission::Approved { sha256: entry.sha256 }
+    }
+
+    /// ไฟล์ที่รับมาแล้วไม่ตรง Manifest (เช่น Hash) -> คืน Task ที่ต้องยกเลิก
+    pub fn abort(&self, key: &str) -> Vec<String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        match sessions.get_mut(key) {
+            Some(session) => {
+                session.aborted = true;
+                std::mem::take(&mut session.admitted)
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+// --- ฝั่งส่ง ---
+
+/// Manifest จากไฟล์ที่จะส่ง (SHA-256 เฉพาะไฟล์ไม่ใหญ่เกิน hash_max_size ให้ตรงกับที่ FileHeader จะแนบ)
+pub async fn build_manifest(paths: &[String], hash_max_size: u64) -> anyhow::Result<Vec<ManifestEntry>> {
+    let mut files = Vec::with_capacity(paths.len());
+    for path in paths {
+        let size = tokio::fs::metadata(path).await.with_context(|| format!("Cannot read '{}'", path))?.len();
+        let name = Path::new(path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let sha256 = match size <= hash_max_size {
+            true => {
+                let p = std::path::PathBuf::from(path);
+                tokio::task::spawn_blocking(move || utils::sha256_file(&p)).await.ok().and_then(Result::ok)
+            }
+            false => None,
+        };
+        files.push(ManifestEntry { name, size, sha256, quick_hash: None });
+    }
+    Ok(files)
+}
+
+// --- Mirror ---
+
+/// Path ในโฟลเดอร์ Mirror ("a/b.txt") ต้องเป็นชื่อล้วน (ไม่มี .. / Root / Drive / Backslash)
+pub fn mirror_path(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+    let plain = !name.is_empty() && !name.contains('\\') && path.components().all(|c| matches!(c, Component::Normal(_)));
+    plain.then(|| path.to_path_buf())
+}
+
+/// ชื่อโฟลเดอร์ปลายทางของ Mirror (ชั้นเดียว)
+pub fn is_valid_mirror_label(label: &str) -> bool {
+    mirror_path(label).is_some_and(|p| p.components().count() == 1)
+}
+
+/// ไฟล์ทั้งหมดใต้ root (ไม่ตาม Symlink) -> (Path จริง, Entry ที่ name เป็น Path ในโฟลเดอร์แบบ '/')
+pub fn scan_mirror(root: &Path) -> anyhow::Result<Vec<(PathBuf, ManifestEntry)>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("Cannot read '{}'", dir.display()))? {
+            let entry = entry?;
+            let kind = entry.file_type()?;
+            let path = entry.path();
+            if kind.is_dir() {
+                dirs.push(path);
+            } else if kind.is_file() {
+                let rel = path.strip_prefix(root)?.components()
+                    .map(|c| c.as_os_str().to_string_lossy().to_string())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let size = entry.metadata()?.len();
+                let quick_hash = Some(duplicate::quick_hash(&path)?);
+                files.push((path, ManifestEntry { name: rel, size, sha256: None, quick_hash }));
+            }
+        }
+    }
+    files.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+    Ok(files)
+}
+
+/// Entry ที่ dest ยังไม่มี / ขนาดหรือ quick_hash ไม่ตรง (ผู้เรียกตรวจ name ด้วย mirror_path แล้ว)
+pub fn mirror_missing(dest: &Path, files: Vec<ManifestEntry>) -> Vec<ManifestEntry> {
+    files.into_iter().filter(|entry| {
+        let path = dest.join(&entry.name);
+        let same = std::fs::metadata(&path).is_ok_and(|m| m.is_file() && m.len() == entry.size)
+            && entry.quick_hash.is_some()
+            && duplicate::quick_hash(&path).ok() == entry.quick_hash;
+        !same
+    }).collect()
+}
+
+/// ที่ลงของไฟล์ Mirror: โฟลเดอร์ย่อยตาม name ทับไฟล์เดิม (name ผิดรูป = ลง root แล้วไปโดนปฏิเสธตอน admit)
+pub fn mirror_target(root: &Path, name: &str) -> SaveTarget {
+    let dir = mirror_path(name).and_then(|p| p.parent().map(|parent| root.join(parent))).unwrap_or_else(|| root.to_path_buf());
+    SaveTarget { dir: dir.to_string_lossy().to_string(), conflict: ConflictPolicy::Overwrite, matched: true }
+}
+
+/// ส่ง Manifest แล้วรอผู้รับตัดสินใจ (wait = เวลาที่ยอมรอ User อีกฝั่ง)
+pub async fn offer(transport: &DynTransport, ip: &str, port: u16, offer: SessionOffer, wait: Duration) -> anyhow::Result<SessionResponse> {
+    let json = serde_json::to_vec(&SessionRequest { session: offer })?;
+    if json.len() > MAX_SESSION_OFFER_SIZE { bail!(DropTeaError::Config("Manifest too large for a session offer".into())); }
+    let mut stream = transport.connect(ip, port).await?;
+    write_frame(&mut stream, &json).await?;
+    let mut len_buf = [0u8; 4];
+    timeout(wait, stream.read_exact(&mut len_buf)).await.context("Session offer timeout")??;
+    let len = u32::from_le_bytes(len_buf) as usize;
+    if len > MAX_SESSION_OFFER_SIZE { bail!(DropTeaError::Protocol("Session response too large".into())); }
+    let mut buf = vec![0u8; len];
+    timeout(wait, stream.read_exact(&mut buf)).await.context("Session offer timeout")??;
+    serde_json::from_slice(&buf).context("Invalid session response")
+}
+
+/// ฝั่งรับตอบ Offer (เรียกจาก handlers หลังตัดสินใจแล้ว)
+pub async fn respond<S: DataStream>(stream: &mut S, decision: SessionDecision, needed: Option<Vec<String>>, io_timeout: Duration) -> anyhow::Result<()> {
+    let json = serde_json::to_vec(&SessionResponse { decision, needed })?;
+    timeout(io_timeout, write_frame(stream, &json)).await.context("Session response timeout")??;
+    Ok(())
+}
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::time::timeout;
+use anyhow::{bail, Context};
+
+use crate::core::admin::{read_frame, write_frame};
+use crate::core::error::DropTeaError;
+use crate::core::security;
+use crate::core::compression;
+use crate::core::duplicate;
+use crate::core::transfer::{DataStream, DynStream, DynTransport, TransferCallback, IO_TIMEOUT};
+
+// ==========================================
+// Read-only Shared Folders (Browse / Pull)
+// Framing เดียวกับ Admin: [u32 len][JSON] แต่ JSON มี key "shares"
+// สิทธิ์เป็นราย Share ผูกกับ Certificate Fingerprint (เก็บใน security/share_acl.json)
+// Share ที่ไม่มีสิทธิ์ตอบเหมือนไม่มีอยู่ ไม่ให้เดาชื่อ Share ได้
+// Request = ขอให้เจ้าของส่งไฟล์ (id จาก Browse) กลับมาทาง Connection เดิมด้วย Pipeline ส่งไฟล์ปกติ
+// (Compression / Progress / Event ครบทั้งสองฝั่ง ต่างจาก Pull ที่ Copy ดิบลงไฟล์)
+// Share เปิดเฉพาะที่ลงไว้ ([[shares]] หรือ publish_share ตอนรัน) List / Browse ทุกครั้งได้ Event ShareBrowsed
+// ==========================================
+
+const FORBIDDEN: &str = "Forbidden";
+// Browse ตอบใน Frame เดียว (ไม่เกิน MAX_HEADER_SIZE) -> แบ่งหน้าด้วย offset
+pub const DEFAULT_MAX_ENTRIES: usize = 200;
+// ชื่อยาว + Hash ยังไม่เกิน 64 KiB
+pub const MAX_SHARE_ENTRIES: usize = 250;
+
+#[derive(Debug, Clone)]
+pub struct SharedFolder {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum ShareCommand {
+    List,
+    // offset = ข้ามกี่รายการ (เรียงตามชื่อ) ได้น้อยกว่าที่ขอ / ว่าง = หมดแล้ว
+    Browse { share: String, #[serde(default)] path: String, #[serde(default)] offset: usize },
+    Pull { share: String, path: String },
+    // compression = Algo ที่ผู้ขอรับได้ (แบบ TXT "comp")
+    Request { id: String, #[serde(default)] compression: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShareRequest {
+    pub shares: ShareCommand,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShareResponse {
+    pub ok: bool,
+    #[serde(default)]
+    pub data: serde_json::Value,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl ShareResponse {
+    fn ok(data: serde_json::Value) -> Self { Self { ok: true, data, error: None } }
+    fn err(msg: &str) -> Self { Self { ok: false, data: serde_json::Value::Null, error: Some(msg.to_string()) } }
+}
+
+// [sharing] ใน Config
+#[derive(Debug, Clone, Copy)]
+pub struct ShareOptions {
+    // แนบ quick_hash ของไฟล์ใน Browse (ผู้ขอเทียบกับไฟล์ที่มีแล้วได้ก่อน Request)
+    pub hashes: bool,
+    // รายการต่อหนึ่ง Browse
+    pub max_entries: usize,
+}
+
+impl Default for ShareOptions {
+    fn default() -> Self {
+        Self { hashes: true, max_entries: DEFAULT_MAX_ENTRIES }
+    }
+}
+
+#[derive(Debug)]
+pub struct ShareContext {
+    // Registry ของ Share ที่เปิดอยู่ (เพิ่ม / ถอดตอนรันได้)
+    folders: RwLock<HashMap<String, PathBuf>>,
+    // sender_name ของไฟล์ที่ส่งตาม Request
+    pub node_name: String,
+    // ที่เก็บ ACL (โฟลเดอร์เดียวกับ Whitelist)
+    pub security_path: String,
+    pub options: ShareOptions,
+}
+
+impl ShareContext {
+    pub fn new(folders: &[SharedFolder], security_path: &str, node_name: &str, options: ShareOptions) -> Self {
+        Self {
+            folders: RwLock::new(folders.iter().map(|f| (f.name.clone(), f.path.clone())).collect()),
+            node_name: node_name.to_string(),
+            security_path: security_path.to_string(),
+            options,
+        }
+    }
+
+    /// เปิด Share ชื่อ name (ชื่อซ้ำ = เปลี่ยน Path) Peer ยังต้องได้ grant_share ก่อนถึงจะเห็น
+    pub fn publish(&self, folder: SharedFolder) -> Result<(), DropTeaError> {
+        if !is_valid_name(&folder.name) {
+            return Err(DropTeaError::Config(format!("share name '{}' must be non-empty without '/'", folder.name)));
+        }
+        if !folder.path.is_dir() {
+            return Err(DropTeaError::Config(format!("share '{}': '{}' is not a directory", folder.name, folder.path.display())));
+        }
+        info!("📂 Published share '{}' -> {}", folder.name, folder.path.display());
+        self.folders.write().unwrap().insert(folder.name, folder.path);
+        Ok(())
+    }
+
+    /// false = ไม่มี Share ชื่อนี้ (สิทธิ์ใน share_acl.json ยังอยู่ เปิดชื่อเดิมอีกครั้งได้สิทธิ์เดิม)
+    pub fn unpublish(&self, name: &str) -> bool {
+        self.folders.write().unwrap().remove(name).is_some()
+    }
+
+    pub fn published(&self) -> Vec<SharedFolder> {
+        let mut folders: Vec<_> = self.folders.read().unwrap().iter()
+            .map(|(name, path)| SharedFolder { name: name.clone(), path: path.clone() })
+            .collect();
+        folders.sort_by(|a, b| a.name.cmp(&b.name));
+        folders
+    }
+
+    fn allowed(&self, share: &str, fingerprint: &str) -> Option<PathBuf> {
+        let root = self.folders.read().unwrap().get(share).cloned()?;
+        security::can_access_share(&self.security_path, share, fingerprint).then_some(root)
+    }
+
+    // Path ต้องอยู่ใต้ Root จริง (กัน ../ และ Symlink ที่ชี้ออกนอก Share)
+    fn resolve(root: &Path, rel: &str) -> Option<PathBuf> {
+        let root = root.canonicalize().ok()?;
+        let target = root.join(rel.trim_start_matches(['/', '\\'])).canonicalize().ok()?;
+        target.starts_with(&root).then_some(target)
+    }
+
+    /// ไฟล์ของ id ที่ Peer นี้ขอได้ (None = ไม่มี / ไม่ใช่ไฟล์ / ไม่มีสิทธิ์)
+    pub fn requested_file(&self, id: &str, fingerprint: &str) -> Option<PathBuf> {
+        let (share, path) = id.split_once('/')?;
+        let file = self.allowed(share, fingerprint).and_then(|root| Self::resolve(&root, path))?;
+        file.is_file().then_some(file)
+    }
+}
+
+// ชื่อ Share เป็นส่วนแรกของ id ของไฟล์ -> ห้ามมี '/'
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['/', '\\'])
+}
+
+/// id ของไฟล์ใน Share (ใช้กับ ShareCommand::Request)
+pub fn file_id(share: &str, path: &str) -> String {
+    format!("{}/{}", share, path.trim_start_matches(['/', '\\']))
+}
+
+// rel = Path ของ dir ใน Share (ไว้สร้าง id ของไฟล์) เรียงตามชื่อเพื่อให้ offset ของแต่ละหน้าคงที่
+fn list_dir(dir: &Path, share: &str, rel: &str, offset: usize, options: ShareOptions) -> anyhow::Result<serde_json::Value> {
+    let mut names: Vec<_> = std::fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    names.sort_by_key(|entry| entry.file_name());
+    let mut entries = Vec::new();
+    for entry in names.into_iter().skip(offset).take(options.max_entries) {
+        let meta = entry.metadata()?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let id = meta.is_file().then(|| file_id(share, &Path::new(rel).join(&name).to_string_lossy()));
+        // อ่านไม่ได้ (เช่นถูกล็อก) ยังแสดงรายการได้ แค่ไม่มี Hash
+        let hash = (options.hashes && meta.is_file()).then(|| duplicate::quick_hash(&entry.path()).ok()).flatten();
+        entries.push(serde_json::json!({
+            "name": name,
+            "is_dir": meta.is_dir(),
+            "size": meta.is_file().then_some(meta.len()),
+            "id": id,
+            "hash": hash,
+        }));
+    }
+    Ok(serde_json::Value::Array(entries))
+}
+
+// (share, path) ของคำสั่งที่นับเป็นการ Browse (List = share ว่าง)
+fn browsed(cmd: &ShareCommand) -> Option<(&str, &str)> {
+    match cmd {
+        ShareCommand::List => Some(("", "")),
+        ShareCommand::Browse { share, path, .. } => Some((share, path)),
+        _ => None,
+    }
+}
+
+/// ฝั่งเจ้าของ Share: ถูกเรียกจาก handle_incoming เมื่อ Header เป็น ShareRequest
+/// peer = Fingerprint (ไม่มี = IP) ใช้ใน Event ShareBrowsed
+pub async fn handle_share<S: DataStream, CB: TransferCallback>(
+    mut stream: S,
+    request: ShareRequest,
+    peer: &str,
+    peer_fingerprint: Option<&str>,
+    ctx: &ShareContext,
+    callback: &CB,
+) -> anyhow::Result<()> {
+    let Some(fp) = peer_fingerprint else {
+        warn!("Rejected share request from peer without identity");
+        if let Some((share, path)) = browsed(&request.shares) { callback.on_share_browsed(peer, share, path, false); }
+        return respond(&mut stream, &ShareResponse::err(FORBIDDEN)).await;
+    };
+
+    match request.shares {
+        ShareCommand::List => {
+            let mut names: Vec<String> = ctx.folders.read().unwrap().keys()
+                .filter(|name| security::can_access_share(&ctx.security_path, name, fp))
+                .cloned()
+                .collect();
+            names.sort();
+            callback.on_share_browsed(peer, "", "", true);
+            respond(&mut stream, &ShareResponse::ok(serde_json::json!(names))).await
+        }
+        ShareCommand::Browse { share, path, offset } => {
+            let dir = ctx.allowed(&share, fp).and_then(|root| ShareContext::resolve(&root, &path)).filter(|dir| dir.is_dir());
+            callback.on_share_browsed(peer, &share, &path, dir.is_some());
+            let response = match dir {
+                Some(dir) => {
+                    let options = ctx.options;
+                    let listed = tokio::task::spawn_blocking(move || list_dir(&dir, &share, &path, offset, options)).await?;
+                    match listed {
+                        Ok(entries) => ShareResponse::ok(entries),
+                        Err(e) => ShareResponse::err(&e.to_string()),
+                    }
+                }
+                None => {
+                    warn!("Share browse denied: '{}/{}' (fingerprint: {})", share, path, fp);
+                    ShareResponse::err(FORBIDDEN)
+                }
+            };
+            respond(&mut stream, &response).await
+        }
+        ShareCommand::Pull { share, path } => {
+            let file = match ctx.allowed(&share, fp).and_then(|root| ShareContext::resolve(&root, &path)) {
+                Some(file) if file.is_file() => file,
+                _ => {
+                    warn!("Share pull denied: '{}/{}' (fingerprint: {})", share, path, fp);
+                    return respond(&mut stream, &ShareResponse::err(FORBIDDEN)).await;
+                }
+            };
+            let mut f = tokio::fs::File::open(&file).await?;
+            let size = f.metadata().await?.len();
+            info!("📤 Share pull: '{}/{}' ({} bytes)", share, path, size);
+            timeout(IO_TIMEOUT, write_frame(&mut stream, &serde_json::to_vec(&ShareResponse::ok(serde_json::json!({ "size": size })))?))
+                .await.context("Share response timeout")??;
+            tokio::io::copy(&mut f, &mut stream).await?;
+            stream.flush().await?;
+            let _ = stream.shutdown().await;
+            Ok(())
+        }
+        // handle_incoming ส่งต่อไป handlers::send_requested เอง (ต้องใช้ Pipeline ส่งไฟล์)
+        ShareCommand::Request { .. } => respond(&mut stream, &ShareResponse::err(FORBIDDEN)).await,
+    }
+}
+
+/// เจ้าของ Share ตอบ Request: Err = ไม่ส่ง (ตอบเหมือนไม่มีไฟล์) / Ok = บอกชื่อไฟล์แล้ว Stream ยังเปิดไว้ส่งต่อ
+pub async fn answer_request<S: DataStream>(stream: &mut S, filename: Option<&str>) -> anyhow::Result<()> {
+    let response = match filename {
+        Some(name) => ShareResponse::ok(serde_json::json!({ "filename": name })),
+        None => return respond(stream, &ShareResponse::err(FORBIDDEN)).await,
+    };
+    timeout(IO_TIMEOUT, write_frame(stream, &serde_json::to_vec(&response)?)).await.context("Share response timeout")??;
+    Ok(())
+}
+
+async fn respond<S: DataStream>(stream: &mut S, response: &ShareResponse) -> anyhow::Result<()> {
+    let json = serde_json::to_vec(response)?;
+    timeout(IO_TIMEOUT, write_frame(stream, &json)).await.context("Share response timeout")??;
+    let _ = stream.shutdown().await;
+    Ok(())
+}
+
+async fn request<S: DataStream>(stream: &mut S, cmd: ShareCommand) -> anyhow::Result<ShareResponse> {
+    let json = serde_json::to_vec(&ShareRequest { shares: cmd })?;
+    write_frame(stream, &json).await?;
+    let buf = read_frame(stream).await?;
+    serde_json::from_slice(&buf).context("Invalid share response")
+}
+
+/// ฝั่งผู้ขอ: List (path ไม่ใช้) หรือ Browse
+pub async fn send_command(transport: &DynTransport, ip: &str, port: u16, cmd: ShareCommand) -> anyhow::Result<ShareResponse> {
+    if matches!(cmd, ShareCommand::Pull { .. } | ShareCommand::Request { .. }) { bail!("Use pull_file / request_file"); }
+    let mut stream = transport.connect(ip, port).await?;
+    request(&mut stream, cmd).await
+}
+
+/// ดึงไฟล์จาก Share ลง dest (เขียน .part ก่อนแล้วค่อย Rename เมื่อครบ)
+pub async fn pull_file(transport: &DynTransport, ip: &str, port: u16, share: &str, path: &str, dest: &Path) -> anyhow::Result<u64> {
+    let mut stream = transport.connect(ip, port).await?;
+    let response = request(&mut stream, ShareCommand::Pull { share: share.to_string(), path: path.to_string() }).await?;
+    if !response.ok {
+        bail!(DropTeaError::Rejected(format!("Pull rejected: {}", response.error.unwrap_or_default())));
+    }
+    let size = response.data.get("size").and_then(|s| s.as_u64()).ok_or_else(|| DropTeaError::Protocol("Missing size in pull response".into()))?;
+
+    let temp_path = dest.with_extension("part");
+    let mut file = tokio::fs::File::create(&temp_path).await?;
+    let copied = tokio::io::copy(&mut (&mut stream).take(size), &mut file).await?;
+    file.flush().await?;
+    if copied != size {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        bail!(DropTeaError::Network(format!("Pull truncated: {} of {} bytes", copied, size)));
+    }
+    tokio::fs::rename(&temp_path, dest).await?;
+    Ok(size)
+}
+
+/// ขอให้เจ้าของส่งไฟล์ id มา: คืน Stream ที่ FileHeader จะตามมา + ชื่อไฟล์ที่ต้องได้
+pub async fn request_file(transport: &DynTransport, ip: &str, port: u16, id: &str) -> anyhow::Result<(DynStream, String)> {
+    let mut stream = transport.connect(ip, port).await?;
+    let cmd = ShareCommand::Request { id: id.to_string(), compression: compression::advertised_algos() };
+    let response = timeout(IO_TIMEOUT, request(&mut stream, cmd)).await.context("Share response timeout")??;
+    if !response.ok {
+        bail!(DropTeaError::Rejected(format!("Request rejected: {}", response.error.unwrap_or_default())));
+    }
+    let filename = response.data.get("filename").and_then(|f| f.as_str()).ok_or_else(|| DropTeaError::Protocol("Missing filename in request response".into()))?;
+    Ok((stream, filename.to_string()))
+}
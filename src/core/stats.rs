@@ -0,0 +1,157 @@
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use serde::{Serialize, Deserialize};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::core::compression::CompressionAlgo;
+use crate::core::transfer::Throughput;
+
+// ==========================================
+// Transfer Stats (สรุปตอน Transfer จบ)
+// แนบไปกับ Completed และเก็บไว้ให้ถามย้อนหลังด้วย get_task_stats(task_id)
+// raw = Byte ของไฟล์ที่ผ่าน Pipeline (Dedup แล้ว ก่อนบีบ), wire = Byte ที่วิ่งบน Connection จริง
+// ==========================================
+
+// เก็บย้อนหลังแค่นี้ (เก่าสุดถูกทิ้งก่อน)
+const MAX_STATS: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TransferStats {
+    pub duration_ms: u64,
+    pub raw_bytes: u64,
+    pub wire_bytes: u64,
+    pub avg_bytes_per_sec: u64,
+    pub peak_bytes_per_sec: u64,
+    // Connect ไม่ติดแล้วลองใหม่กี่ครั้ง (ฝั่งรับเป็น 0 เสมอ)
+    pub retries: u32,
+    pub transport: String,
+    pub compression: String,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    stats: HashMap<String, TransferStats>,
+    order: VecDeque<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct StatsStore {
+    inner: StdMutex<Inner>,
+}
+
+impl StatsStore {
+    pub fn new() -> Arc<Self> { Arc::new(Self::default()) }
+
+    pub fn get(&self, task_id: &str) -> Option<TransferStats> {
+        self.inner.lock().unwrap().stats.get(task_id).cloned()
+    }
+
+    fn insert(&self, task_id: &str, stats: TransferStats) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.stats.insert(task_id.to_string(), stats).is_none() {
+            inner.order.push_back(task_id.to_string());
+        }
+        while inner.order.len() > MAX_STATS {
+            if let Some(old) = inner.order.pop_front() { inner.stats.remove(&old); }
+        }
+    }
+}
+
+/// เก็บตัวเลขระหว่าง Transfer หนึ่งตัว แล้วสรุปลง StatsStore ตอน finish
+#[derive(Debug)]
+pub struct StatsCollector {
+    store: Arc<StatsStore>,
+    transport: String,
+    retries: u32,
+    started: StdMutex<Instant>,
+    raw: AtomicU64,
+    peak: AtomicU64,
+    wire: Arc<AtomicU64>,
+}
+
+impl StatsCollector {
+    pub fn new(store: Arc<StatsStore>, transport: &str, retries: u32) -> Arc<Self> {
+        Arc::new(Self {
+            store,
+            transport: transport.to_string(),
+            retries,
+            started: StdMutex::new(Instant::now()),
+            raw: AtomicU64::new(0),
+            peak: AtomicU64::new(0),
+            wire: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// เริ่มนับเวลาตอนข้อมูลเริ่มวิ่ง (ไม่รวมเวลารอ User / Connect)
+    pub fn begin(&self) {
+        *self.started.lock().unwrap() = Instant::now();
+    }
+
+    /// ห่อ Stream ให้นับ Byte บน Connection
+    pub fn count_wire<S>(&self, inner: S) -> Counted<S> {
+        Counted { inner, count: self.wire.clone() }
+    }
+
+    /// Byte ที่ส่งโดยไม่ผ่าน Stream ที่ห่อไว้ (sendfile)
+    pub fn add_wire(&self, bytes: u64) {
+        self.wire.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn observe(&self, current: u64, rate: &Throughput) {
+        self.raw.fetch_max(current, Ordering::Relaxed);
+        self.peak.fetch_max(rate.bytes_per_sec, Ordering::Relaxed);
+    }
+
+    pub fn finish(&self, task_id: &str, compression: CompressionAlgo) -> TransferStats {
+        let elapsed = self.started.lock().unwrap().elapsed();
+        let raw_bytes = self.raw.load(Ordering::Relaxed);
+        let secs = elapsed.as_secs_f64();
+        let avg_bytes_per_sec = if secs > 0.0 { (raw_bytes as f64 / secs) as u64 } else { 0 };
+        let stats = TransferStats {
+            duration_ms: elapsed.as_millis() as u64,
+            raw_bytes,
+            wire_bytes: self.wire.load(Ordering::Relaxed),
+            avg_bytes_per_sec,
+            // ไฟล์เล็กจบก่อนได้ Sample -> อย่างน้อยเท่าค่าเฉลี่ย
+            peak_bytes_per_sec: self.peak.load(Ordering::Relaxed).max(avg_bytes_per_sec),
+            retries: self.retries,
+            transport: self.transport.clone(),
+            compression: compression.as_str().to_string(),
+        };
+        self.store.insert(task_id, stats.clone());
+        stats
+    }
+}
+
+/// Stream ที่นับ Byte อ่าน/เขียน (ใช้วัด wire_bytes)
+pub struct Counted<S> {
+    inner: S,
+    count: Arc<AtomicU64>,
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Counted<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        self.count.fetch_add((buf.filled().len() - before) as u64, Ordering::Relaxed);
+        result
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Counted<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        let result = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = result { self.count.fetch_add(n as u64, Ordering::Relaxed); }
+        result
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,152 @@
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use serde::{Serialize, Deserialize};
+
+use crate::core::error::{DropTeaError, Result};
+use crate::core::save_rules::guess_mime;
+
+// ==========================================
+// Offer Thumbnail (ภาพตัวอย่างเล็กๆ ของรูป/วิดีโอที่แนบไปกับ FileHeader)
+// ผู้ส่งไม่ได้ทำ Thumbnail เอง: Embedder ย่อด้วย API ของ Platform แล้วส่ง Byte มากับ SendOptions
+// ผู้รับไม่เชื่อ Field ไหนเลย: จำกัดขนาดก่อน Decode, ดู Magic Byte เอง (ไม่ใช้ mime ที่ส่งมา), อ่านขนาดภาพจาก Header แบบเช็คขอบเขตทุกครั้ง
+// ไม่ผ่าน -> ทิ้ง Thumbnail อย่างเดียว (ข้อเสนอยังถามได้ตามปกติ)
+// ==========================================
+
+// หลัง Decode (Base64 ใน Header ใหญ่ขึ้นอีก ~4/3 ยังต่ำกว่า MAX_HEADER_SIZE)
+pub const MAX_THUMBNAIL_BYTES: usize = 32 * 1024;
+pub const MAX_THUMBNAIL_DIMENSION: u32 = 512;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Thumbnail {
+    pub mime: String,
+    // Base64 (Standard) ของไฟล์ภาพทั้งไฟล์
+    pub data: String,
+}
+
+impl Thumbnail {
+    /// ฝั่งส่ง: ตรวจแบบเดียวกับผู้รับ จะได้รู้ตั้งแต่ตอนสั่งส่งว่าภาพใช้ไม่ได้
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (mime, _) = inspect(bytes).map_err(|e| DropTeaError::Config(format!("Invalid thumbnail: {}", e)))?;
+        Ok(Self { mime: mime.to_string(), data: B64.encode(bytes) })
+    }
+
+    pub fn bytes(&self) -> Option<Vec<u8>> {
+        B64.decode(&self.data).ok()
+    }
+
+    /// ใส่ <img src> ได้เลย
+    pub fn data_uri(&self) -> String {
+        format!("data:{};base64,{}", self.mime, self.data)
+    }
+}
+
+/// แนบเฉพาะไฟล์ที่เป็นรูป/วิดีโอ (ไฟล์อื่นมี Thumbnail ก็ไม่ได้ช่วยตัดสินใจ)
+pub fn is_media(filename: &str) -> bool {
+    guess_mime(filename).is_some_and(|m| m.starts_with("image/") || m.starts_with("video/"))
+}
+
+/// ฝั่งรับ: Some = ปลอดภัยพอจะส่งต่อให้ UI (mime ตั้งใหม่จากเนื้อไฟล์)
+pub fn sanitize(thumbnail: &Thumbnail, filename: &str) -> Option<Thumbnail> {
+    if !is_media(filename) {
+        tracing::debug!("Dropping thumbnail for non-media file");
+        return None;
+    }
+    // เช็คความยาวก่อน Decode (ไม่จอง Memory ตามที่ Peer บอก)
+    if thumbnail.data.len() > MAX_THUMBNAIL_BYTES.div_ceil(3) * 4 {
+        tracing::warn!("Dropping oversized thumbnail ({} bytes encoded)", thumbnail.data.len());
+        return None;
+    }
+    let bytes = match B64.decode(&thumbnail.data) {
+        Ok(bytes) => bytes,
+        Err(e) => { tracing::warn!("Dropping thumbnail: bad base64: {}", e); return None; }
+    };
+    match inspect(&bytes) {
+        Ok((mime, _)) => Some(Thumbnail { mime: mime.to_string(), data: thumbnail.data.clone() }),
+        Err(e) => { tracing::warn!("Dropping thumbnail: {}", e); None }
+    }
+}
+
+// คืน (mime, (กว้าง, สูง)) ถ้าเป็นภาพที่รับได้
+fn inspect(bytes: &[u8]) -> std::result::Result<(&'static str, (u32, u32)), String> {
+    if bytes.len() > MAX_THUMBNAIL_BYTES {
+        return Err(format!("{} bytes exceeds {} byte limit", bytes.len(), MAX_THUMBNAIL_BYTES));
+    }
+    let (mime, dims) = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        ("image/png", png_size(bytes))
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        ("image/jpeg", jpeg_size(bytes))
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        ("image/webp", webp_size(bytes))
+    } else {
+        return Err("unsupported format (png / jpeg / webp only)".into());
+    };
+    let (w, h) = dims.ok_or_else(|| format!("malformed {}", mime))?;
+    if w == 0 || h == 0 || w > MAX_THUMBNAIL_DIMENSION || h > MAX_THUMBNAIL_DIMENSION {
+        return Err(format!("{}x{} outside 1..={} px", w, h, MAX_THUMBNAIL_DIMENSION));
+    }
+    Ok((mime, (w, h)))
+}
+
+fn be_u16(b: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_be_bytes(b.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn be_u32(b: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(b.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn le_u16(b: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_le_bytes(b.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn le_u24(b: &[u8], at: usize) -> Option<u32> {
+    let s = b.get(at..at + 3)?;
+    Some(s[0] as u32 | (s[1] as u32) << 8 | (s[2] as u32) << 16)
+}
+
+// Chunk แรกต้องเป็น IHDR (ยาว 13)
+fn png_size(b: &[u8]) -> Option<(u32, u32)> {
+    if be_u32(b, 8)? != 13 || b.get(12..16)? != b"IHDR" { return None; }
+    Some((be_u32(b, 16)?, be_u32(b, 20)?))
+}
+
+// เดินทีละ Segment จนเจอ SOFn (ความยาวทุก Segment ถูกเช็คกับขนาดจริง)
+fn jpeg_size(b: &[u8]) -> Option<(u32, u32)> {
+    let mut i = 2;
+    loop {
+        if *b.get(i)? != 0xFF { return None; }
+        let marker = *b.get(i + 1)?;
+        match marker {
+            // Padding / Marker ที่ไม่มีความยาว
+            0xFF => { i += 1; continue; }
+            0x01 | 0xD0..=0xD7 => { i += 2; continue; }
+            // จบภาพ / เริ่ม Scan ก่อนเจอ SOF = ไฟล์เสีย
+            0xD9 | 0xDA => return None,
+            _ => {}
+        }
+        let len = be_u16(b, i + 2)? as usize;
+        if len < 2 { return None; }
+        if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            return Some((be_u16(b, i + 7)?, be_u16(b, i + 5)?));
+        }
+        i += 2 + len;
+    }
+}
+
+fn webp_size(b: &[u8]) -> Option<(u32, u32)> {
+    match b.get(12..16)? {
+        // Lossy: Frame Tag 3 byte + Start Code 9D 01 2A แล้วตามด้วยขนาด 14 bit
+        b"VP8 " => {
+            if b.get(23..26)? != [0x9D, 0x01, 0x2A] { return None; }
+            Some((le_u16(b, 26)? & 0x3FFF, le_u16(b, 28)? & 0x3FFF))
+        }
+        // Lossless: Signature 0x2F แล้วขนาด 14 bit (ค่าจริง - 1) สองตัวติดกัน
+        b"VP8L" => {
+            if *b.get(20)? != 0x2F { return None; }
+            let bits = u32::from_le_bytes(b.get(21..25)?.try_into().ok()?);
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        // Extended: Canvas 24 bit (ค่าจริง - 1)
+        b"VP8X" => Some((le_u24(b, 24)? + 1, le_u24(b, 27)? + 1)),
+        _ => None,
+    }
+}
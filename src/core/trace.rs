@@ -0,0 +1,210 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+use tracing::level_filters::LevelFilter;
+
+use crate::core::error::{DropTeaError, Result};
+use crate::core::utils;
+
+// ==========================================
+// Structured Trace (tracing) สำหรับ Embedder
+// engine / handlers / transports / discovery ส่ง Event ผ่าน tracing พร้อม Span "send" / "receive" ที่มี task_id / peer
+// ยังไม่ติดตั้ง Subscriber -> Event ไหลไป log เหมือนเดิม (feature "log" ของ tracing)
+// ติดตั้งแล้ว (install / set_subscriber) -> ไปที่ Subscriber อย่างเดียว ได้ครั้งเดียวต่อโปรเซส
+// ==========================================
+
+/// Event หนึ่งตัวพร้อม Span ที่ครอบอยู่ (ตัวนอกสุดก่อน)
+#[derive(Serialize, Debug, Clone)]
+pub struct TraceRecord {
+    pub ts_ms: u64,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    pub fields: Map<String, Value>,
+    pub spans: Vec<SpanRecord>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SpanRecord {
+    pub name: String,
+    pub fields: Map<String, Value>,
+}
+
+pub trait TraceSink: Send + Sync {
+    // ถูกเรียกจาก Thread ที่เกิด Event (ห้าม Block นาน)
+    fn record(&self, record: &TraceRecord);
+}
+
+/// ใช้ Subscriber ของ Embedder เอง (เช่น tracing-subscriber / OpenTelemetry)
+pub fn set_subscriber<S: Subscriber + Send + Sync + 'static>(subscriber: S) -> Result<()> {
+    tracing::subscriber::set_global_default(subscriber)
+        .map_err(|_| DropTeaError::Config("Trace subscriber already installed".into()))
+}
+
+/// ส่ง Event ของ DropTea ตั้งแต่ level นี้ขึ้นไปให้ sink (Event ของ Dependency ไม่ส่ง)
+pub fn install(sink: Arc<dyn TraceSink>, level: Level) -> Result<()> {
+    set_subscriber(SinkSubscriber::new(sink, level))
+}
+
+pub fn level_from_name(name: &str) -> Option<Level> {
+    name.parse().ok()
+}
+
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name().to_string(), Value::String(format!("{:?}", value)));
+    }
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::String(value.to_string()));
+    }
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.0.insert(field.name().to_string(), Value::String(value.to_string()));
+    }
+}
+
+struct SpanData {
+    metadata: &'static Metadata<'static>,
+    fields: Map<String, Value>,
+    parent: Option<u64>,
+    // Handle ที่ยังถืออยู่ + Span ลูกที่ยังไม่ปิด
+    refs: usize,
+}
+
+thread_local! {
+    // Span ที่ Enter อยู่ใน Thread นี้ (Future ที่ .instrument() จะ Enter/Exit ทุกครั้งที่ถูก Poll)
+    static STACK: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+pub struct SinkSubscriber {
+    sink: Arc<dyn TraceSink>,
+    level: Level,
+    next_id: AtomicU64,
+    spans: StdMutex<HashMap<u64, SpanData>>,
+}
+
+impl SinkSubscriber {
+    pub fn new(sink: Arc<dyn TraceSink>, level: Level) -> Self {
+        Self { sink, level, next_id: AtomicU64::new(1), spans: StdMutex::new(HashMap::new()) }
+    }
+
+    fn current() -> Option<u64> {
+        STACK.with(|s| s.borrow().last().copied())
+    }
+
+    // ปิด Span แล้วปล่อย Ref ที่ถือ Parent ไว้ต่อเป็นทอดๆ
+    fn release(spans: &mut HashMap<u64, SpanData>, id: u64) -> bool {
+        let Some(data) = spans.get_mut(&id) else { return false };
+        data.refs -= 1;
+        if data.refs > 0 { return false; }
+        let parent = spans.remove(&id).and_then(|d| d.parent);
+        if let Some(parent) = parent { Self::release(spans, parent); }
+        true
+    }
+}
+
+impl Subscriber for SinkSubscriber {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= &self.level && metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(LevelFilter::from_level(self.level))
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut fields = Map::new();
+        span.record(&mut JsonVisitor(&mut fields));
+        let parent = match span.parent() {
+            Some(p) => Some(p.into_u64()),
+            None if span.is_contextual() => Self::current(),
+            None => None,
+        };
+        let mut spans = self.spans.lock().unwrap();
+        let parent = parent.filter(|p| spans.contains_key(p));
+        if let Some(p) = parent.and_then(|p| spans.get_mut(&p)) { p.refs += 1; }
+        spans.insert(id, SpanData { metadata: span.metadata(), fields, parent, refs: 1 });
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some(data) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            values.record(&mut JsonVisitor(&mut data.fields));
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Map::new();
+        event.record(&mut JsonVisitor(&mut fields));
+        let message = match fields.remove("message") {
+            Some(Value::String(s)) => s,
+            Some(other) => other.to_string(),
+            None => String::new(),
+        };
+        let mut cursor = match event.parent() {
+            Some(p) => Some(p.into_u64()),
+            None if event.is_contextual() => Self::current(),
+            None => None,
+        };
+        let mut chain = Vec::new();
+        {
+            let spans = self.spans.lock().unwrap();
+            while let Some(data) = cursor.and_then(|id| spans.get(&id)) {
+                chain.push(SpanRecord { name: data.metadata.name().to_string(), fields: data.fields.clone() });
+                cursor = data.parent;
+            }
+        }
+        chain.reverse();
+        let metadata = event.metadata();
+        self.sink.record(&TraceRecord {
+            ts_ms: utils::timestamp_millis(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message,
+            fields,
+            spans: chain,
+        });
+    }
+
+    fn enter(&self, span: &Id) {
+        STACK.with(|s| s.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        STACK.with(|s| {
+            let mut stack = s.borrow_mut();
+            if let Some(i) = stack.iter().rposition(|id| *id == span.into_u64()) { stack.remove(i); }
+        });
+    }
+
+    fn clone_span(&self, id: &Id) -> Id {
+        if let Some(data) = self.spans.lock().unwrap().get_mut(&id.into_u64()) { data.refs += 1; }
+        id.clone()
+    }
+
+    fn try_close(&self, id: Id) -> bool {
+        Self::release(&mut self.spans.lock().unwrap(), id.into_u64())
+    }
+}
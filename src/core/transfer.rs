@@ -1,25 +1,108 @@
+use std::collections::BTreeMap;
 use serde::{Serialize, Deserialize};
 use tokio::time::Duration;
 use tokio::io::{AsyncRead, AsyncWrite, AsyncReadExt, AsyncWriteExt};
 use tokio::sync::mpsc;
 use async_trait::async_trait;
+use crate::core::cancel::{TransferSignal, REJECT_CANCELLED};
+use crate::core::batch::{BatchInfo, BatchProgress};
+use crate::core::dedup::DedupInfo;
+use crate::core::parallel::ParallelPlan;
+use crate::core::compression::CompressionAlgo;
+use crate::core::error;
+use crate::core::discovery::PeerInfo;
+use crate::core::stats::TransferStats;
+use crate::core::thumbnail::Thumbnail;
 
 pub const ACK_SIZE: usize = 9;
 pub const MAX_HEADER_SIZE: usize = 64 * 1024;
+// Metadata ของผู้เรียก (key + value รวมกัน) ไม่ให้เบียด Thumbnail ใน Header
+pub const MAX_METADATA_SIZE: usize = 4 * 1024;
+
+/// key-value ที่แอปแนบไปกับ Transfer (เช่น album / conversation id) Engine ไม่ตีความ แค่ส่งต่อให้ Event ของผู้รับ
+pub type Metadata = BTreeMap<String, String>;
 pub const IO_TIMEOUT: Duration = Duration::from_secs(60);
 pub const USER_DECISION_TIMEOUT: Duration = Duration::from_secs(120);
+pub const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+const STALL_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// น้ำหนักของช่วงล่าสุดใน Throughput::bytes_per_sec (ที่เหลือคือค่าเดิม กันตัวเลขกระตุก)
+const THROUGHPUT_SMOOTHING: f64 = 0.3;
+
+/// Timeout ที่ปรับได้จาก Config (Default = ค่าคงที่ด้านบน)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timeouts {
+    // อ่าน/เขียน Stream ไม่ขยับเกินนี้ = Connection ตาย (ไม่นับช่วง Pause)
+    pub io: Duration,
+    // รอผู้ใช้กดรับ/ปฏิเสธ (ส่งแต่ละครั้ง Override ได้ด้วย SendOptions::expires_in)
+    pub user_decision: Duration,
+    // ระหว่าง copy_pipeline ไม่มี Byte ขยับเลยนานเท่านี้ = ค้าง ตัดทิ้งก่อนถึง io (ครึ่งทางแจ้ง on_stall) 0 = ปิด
+    pub stall: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self { io: IO_TIMEOUT, user_decision: USER_DECISION_TIMEOUT, stall: STALL_TIMEOUT }
+    }
+}
+// ACK status: 0 = Reject, 1 = Accept, 2 = ข้อเสนอหมดอายุก่อนผู้รับตัดสินใจ (ส่งเฉพาะเมื่อ Header มี expires_in_ms)
+pub const ACK_EXPIRED: u8 = 2;
+pub const REJECT_EXPIRED: &str = "Expired";
+// 3 = Protocol คุยกันไม่ได้ (offset ของ ACK = Version ของผู้รับ) ส่งเฉพาะผู้ส่งที่รู้จัก (v2 ขึ้นไป)
+pub const ACK_INCOMPATIBLE: u8 = 3;
+pub const REJECT_INCOMPATIBLE: &str = "Incompatible protocol";
+// 4 = ผู้รับไม่ว่าง / ปิดรับอยู่ (offset = Availability::code) ส่งเฉพาะผู้ส่งที่มี CAP_AVAILABILITY
+pub const ACK_UNAVAILABLE: u8 = 4;
+pub const REJECT_BUSY: &str = "Receiver Busy";
+pub const REJECT_RECEIVING_DISABLED: &str = "Receiving Disabled";
+// 5 = ผู้รับมีไฟล์นี้อยู่แล้ว (quick_hash ตรง ดู duplicate.rs) ส่งเฉพาะผู้ส่งที่มี CAP_DUPLICATE
+pub const ACK_DUPLICATE: u8 = 5;
+pub const REJECT_DUPLICATE: &str = "Already Have";
+// 6 = รับได้ แต่มีไฟล์เวอร์ชันเก่าอยู่ ส่งเฉพาะ Block ที่เปลี่ยน (offset = block_size, Signature ตามมา ดู delta.rs) ส่งเฉพาะผู้ส่งที่มี CAP_DELTA
+pub const ACK_DELTA: u8 = 6;
+
+// ==========================================
+// Protocol Version (Wire Format ของ FileHeader / ACK / Stream)
+// เปลี่ยน Wire แบบที่ Peer เก่าอ่านผิด -> เพิ่ม PROTOCOL_VERSION, เลิกรองรับของเก่า -> เพิ่ม MIN_PROTOCOL_VERSION
+// Header ที่ไม่มี Field นี้ (Client รุ่นแรก / Swift) = v1
+// ==========================================
+pub const PROTOCOL_VERSION: u32 = 2;
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+// Capability Bitmask: ความสามารถที่ผู้ส่งรองรับ (ไม่ได้บังคับ ใช้เลือกฟีเจอร์ที่ทั้งสองฝั่งมี)
+pub const CAP_COMPRESSION: u64 = 1 << 0;
+pub const CAP_DEDUP: u64 = 1 << 1;
+pub const CAP_OFFER_EXPIRY: u64 = 1 << 2;
+pub const CAP_SHA256: u64 = 1 << 3;
+pub const CAP_CONTROL_CHANNEL: u64 = 1 << 4;
+pub const CAP_AVAILABILITY: u64 = 1 << 5;
+pub const CAP_PARALLEL_STREAMS: u64 = 1 << 6;
+// ผู้ส่งเข้าใจ offset ใน ACK ตอนต่อไฟล์ที่หลุด (ดู resume.rs) -> ผู้รับเก็บ .part ไว้ให้
+pub const CAP_RESUME: u64 = 1 << 7;
+pub const CAP_DUPLICATE: u64 = 1 << 8;
+pub const CAP_DELTA: u64 = 1 << 9;
+pub const LOCAL_CAPABILITIES: u64 = CAP_COMPRESSION | CAP_DEDUP | CAP_OFFER_EXPIRY | CAP_SHA256 | CAP_CONTROL_CHANNEL | CAP_AVAILABILITY | CAP_PARALLEL_STREAMS | CAP_RESUME | CAP_DUPLICATE | CAP_DELTA;
+
+fn legacy_protocol_version() -> u32 { 1 }
 pub const NOTIFY_INTERVAL_MS: u128 = 100;
 pub const PIPELINE_BUFFER_SIZE: usize = 4 * 1024 * 1024;
 pub const CHANNEL_CAPACITY: usize = 32; 
 
-pub trait DataStream: AsyncRead + AsyncWrite + Unpin + Send + 'static {}
-impl<T: AsyncRead + AsyncWrite + Unpin + Send + 'static> DataStream for T {}
+pub trait DataStream: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+    /// ให้ Fast Path แกะหา Socket จริงข้างใน (เช่น TcpStream ของ Plain TCP สำหรับ sendfile)
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
+}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + 'static> DataStream for T {
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any { self }
+}
 
 #[async_trait]
 pub trait Transport: Send + Sync + 'static {
     type Stream: DataStream;
-    async fn accept(&self) -> anyhow::Result<(Self::Stream, std::net::SocketAddr)>;
-    async fn connect(&self, ip: &str, port: u16) -> anyhow::Result<Self::Stream>;
+    /// คืน Stream, Address และ Fingerprint ของ Cert ผู้ส่ง (None ถ้า Transport ยืนยันตัวตนไม่ได้)
+    async fn accept(&self) -> error::Result<(Self::Stream, std::net::SocketAddr, Option<String>)>;
+    async fn connect(&self, ip: &str, port: u16) -> error::Result<Self::Stream>;
+    /// Address ของเครื่องเปลี่ยน -> ทิ้ง Connection ที่ค้างอยู่กับ Network เดิม (Transport ที่ไม่มี Pool ไม่ต้องทำอะไร)
+    async fn on_network_changed(&self) {}
 }
 
 pub type DynStream = Box<dyn DataStream>;
@@ -39,18 +122,194 @@ pub struct FileHeader {
     // (เพราะ Swift อาจตัด field นี้ออกถ้าเป็น nil)
     #[serde(default)] 
     pub compression: Option<String>, 
+
+    // เวลาฝั่งส่ง (Unix ms) ใช้ตรวจ Clock Skew / mtime ของไฟล์ต้นทาง
+    #[serde(default)]
+    pub sent_at: Option<u64>,
+    #[serde(default)]
+    pub modified_at: Option<u64>,
+    // Permission bits ของไฟล์ต้นทาง (Unix, 0o777) ผู้รับที่เป็น Unix ตั้งให้ตามนี้หลังรับครบ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+
+    // มีค่าเมื่อผู้ส่งจะแลก Chunk Hash หลัง ACK (ดู dedup.rs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedup: Option<DedupInfo>,
+
+    // ข้อเสนอมีอายุเท่านี้นับจากผู้รับได้ Header (นับแบบสัมพัทธ์ ไม่ต้องพึ่งนาฬิกาสองฝั่งตรงกัน)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_in_ms: Option<u64>,
+
+    // SHA-256 ของทั้งไฟล์ (ผู้ส่งแนบมาเฉพาะไฟล์ไม่ใหญ่มาก) ผู้รับใช้ถาม Reputation ก่อนขึ้น Prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+
+    // Hash แบบสุ่มช่วงของไฟล์ (ดู duplicate.rs) ผู้รับใช้หาไฟล์ที่มีอยู่แล้วก่อนถาม
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quick_hash: Option<String>,
+
+    // Task ID ฝั่งส่ง + Control Port ของผู้ส่ง -> ผู้รับสั่ง CANCEL/PAUSE กลับมาได้ (ดู control.rs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transfer_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub control_port: Option<u16>,
+
+    // Version ของผู้ส่ง และ Version ต่ำสุดที่ผู้รับต้องพูดได้ (ดู check_compatibility)
+    #[serde(default = "legacy_protocol_version")]
+    pub protocol_version: u32,
+    #[serde(default = "legacy_protocol_version")]
+    pub min_protocol_version: u32,
+    #[serde(default)]
+    pub capabilities: u64,
+
+    // ไฟล์นี้เป็นส่วนหนึ่งของชุด (โฟลเดอร์ / send_batch) ผู้รับรวม Progress เป็น BatchProgress
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<BatchInfo>,
+
+    // ภาพตัวอย่างของรูป/วิดีโอ ให้ Prompt ของผู้รับแสดงได้ (ผู้รับตรวจเองก่อนใช้ ดู thumbnail.rs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<Thumbnail>,
+
+    // ผู้ส่งเปิด Guest Mode (Cert ชั่วคราว) -> ผู้รับไม่จำชื่อ / Capability ของผู้ส่งนี้
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub guest: bool,
+
+    // ไฟล์ใหญ่แบ่งส่งหลาย Stream พร้อมกันหลัง ACK (ดู parallel.rs) ไม่ใช้ร่วมกับ dedup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallel: Option<ParallelPlan>,
+
+    // ต่อไฟล์เดิมหลัง Connection หลุด (transfer_id เดิม) -> ผู้รับตอบ offset ของ .part หรือปฏิเสธถ้าไม่มีให้ต่อ
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub resuming: bool,
+
+    // key-value ของแอปผู้ส่ง ส่งต่อไปกับ Incoming / Completed ของผู้รับ
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: Metadata,
+}
+
+impl FileHeader {
+    pub fn has_capability(&self, cap: u64) -> bool { self.capabilities & cap == cap }
+}
+
+/// ฝั่งรับ: None = คุยกันได้, Some(เหตุผล) = ต้องปฏิเสธ
+pub fn check_compatibility(header: &FileHeader) -> Option<String> {
+    if header.protocol_version < MIN_PROTOCOL_VERSION {
+        return Some(format!("{}: sender speaks v{}, v{}+ required", REJECT_INCOMPATIBLE, header.protocol_version, MIN_PROTOCOL_VERSION));
+    }
+    if header.min_protocol_version > PROTOCOL_VERSION {
+        return Some(format!("{}: sender requires v{}+, this device speaks v{}", REJECT_INCOMPATIBLE, header.min_protocol_version, PROTOCOL_VERSION));
+    }
+    // ไม่มี Field = ไม่บีบ แต่ชื่อที่ไม่รู้จัก (เช่น Algo ที่ใหม่กว่าเรา) ห้ามเขียน Stream ที่บีบอยู่ลงไฟล์ตรงๆ
+    if let Some(name) = header.compression.as_deref().filter(|n| CompressionAlgo::from_name(n).is_none()) {
+        return Some(format!("{}: unsupported compression '{}'", REJECT_INCOMPATIBLE, name));
+    }
+    None
 }
 
+/// ขนาดรวมของ Metadata ไม่เกิน MAX_METADATA_SIZE (ใช้ทั้งตอนส่งและตอนรับ)
+pub fn check_metadata(metadata: &Metadata) -> anyhow::Result<()> {
+    let size: usize = metadata.iter().map(|(k, v)| k.len() + v.len()).sum();
+    if size > MAX_METADATA_SIZE {
+        anyhow::bail!("Metadata too large ({} bytes, max {})", size, MAX_METADATA_SIZE);
+    }
+    Ok(())
+}
+
+/// ฝั่งส่ง: เหตุผลจาก ACK_INCOMPATIBLE (offset = Version ของผู้รับ) Version พอแล้ว = ผู้รับแตก Compression ที่เราเลือกไม่ได้
+pub fn incompatible_reason(receiver_version: u64, header: &FileHeader) -> String {
+    let required = header.min_protocol_version.max(MIN_PROTOCOL_VERSION) as u64;
+    match header.compression.as_deref() {
+        Some(name) if receiver_version >= required => format!("{}: receiver does not support '{}' compression", REJECT_INCOMPATIBLE, name),
+        _ => format!("{}: receiver speaks v{}, v{}+ required", REJECT_INCOMPATIBLE, receiver_version, required),
+    }
+}
+
+// ต่างกันเกินนี้ถือว่านาฬิกาเพี้ยน (เผื่อ Header ค้างใน Network แล้ว)
+pub const CLOCK_SKEW_WARN_MS: i64 = 5 * 60 * 1000;
+
 pub trait TransferCallback: Send + Sync {
     fn on_start(&self, task_id: &str, filename: &str);
     fn on_progress(&self, task_id: &str, current: u64, total: u64);
+    // Progress พร้อมความเร็ว / ETA จาก copy_pipeline (Default ส่งต่อเป็น on_progress ธรรมดา)
+    fn on_progress_ex(&self, task_id: &str, current: u64, total: u64, _rate: &Throughput) { self.on_progress(task_id, current, total); }
     fn on_complete(&self, task_id: &str, info: &str);
+    // จบพร้อมสรุป + Metadata ของ Transfer (ช่องทางที่ไม่ได้วัด เช่น BLE เรียก on_complete ธรรมดา)
+    fn on_complete_with_stats(&self, task_id: &str, info: &str, _stats: &TransferStats, _metadata: &Metadata) { self.on_complete(task_id, info); }
     fn on_error(&self, task_id: &str, error: &str);
     fn on_reject(&self, task_id: &str, reason: &str);
     fn on_peer_found(&self, id: &str, name: &str, ip: &str, port: u16, ssid: Option<&str>, transport: &str);
+    // Peer พร้อม Profile จาก mDNS (Default ส่งต่อเป็น on_peer_found ธรรมดา)
+    fn on_peer_found_ex(&self, peer: &PeerInfo) {
+        self.on_peer_found(&peer.id, &peer.display_name, &peer.host().unwrap_or_default(), peer.port, peer.ssid.as_deref(), &peer.transport.to_string());
+    }
     fn on_peer_lost(&self, id: &str);
-    fn ask_accept_file(&self, task_id: &str, filename: &str, filesize: u64, sender_name: &str, sender_device: &str) -> anyhow::Result<bool>;
+    fn on_peer_updated(&self, _id: &str, _old_ip: &str, _ip: &str, _port: u16) {}
+    /// session_id: ไฟล์ที่ส่งมาพร้อมกันจากผู้ส่งเดียวกันได้ค่าเดียวกัน (เมื่อเปิด sender_queue)
+    /// verdict: ผล Reputation ("known" / "unknown" / "malicious" / "unavailable") เมื่อเปิด [reputation]
+    #[allow(clippy::too_many_arguments)]
+    fn ask_accept_file(&self, task_id: &str, filename: &str, filesize: u64, sender_name: &str, sender_device: &str, session_id: Option<&str>, verdict: Option<&str>, thumbnail: Option<&Thumbnail>, metadata: &Metadata) -> anyhow::Result<bool>;
     fn ask_verify_certificate(&self, peer_id: &str, fingerprint: &str, filename: Option<&str>) -> anyhow::Result<CertificateAction>;
+    fn on_clock_skew(&self, _task_id: &str, _peer: &str, _skew_ms: i64) {}
+    fn on_batch_progress(&self, _progress: &BatchProgress) {}
+    // Throughput เป็นศูนย์มา stalled_ms แล้ว (ยังไม่ตัด รอ Timeouts::stall)
+    fn on_stalled(&self, _task_id: &str, _stalled_ms: u64) {}
+    // ข้ามไฟล์เพราะผู้รับมีอยู่แล้ว (existing = ไฟล์ที่ตรง รู้เฉพาะฝั่งรับ) Default ส่งต่อเป็น on_reject
+    fn on_duplicate(&self, task_id: &str, _filename: &str, _existing: Option<&str>) { self.on_reject(task_id, REJECT_DUPLICATE); }
+    // Post-receive Hook ล้ม / Timeout หลัง Completed (ไฟล์ยังอยู่)
+    fn on_hook_failed(&self, _task_id: &str, _path: &str, _error: &str) {}
+    // Peer List (share ว่าง) / Browse Share ของเรา allowed = false คือถูกปฏิเสธ (ไม่มีสิทธิ์ / ไม่มี Share)
+    fn on_share_browsed(&self, _peer: &str, _share: &str, _path: &str, _allowed: bool) {}
+}
+
+/// 📈 ความเร็ว / เวลาที่เหลือ แนบไปกับ Progress (Frontend ไม่ต้องคำนวณเองจาก Byte ดิบ)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Throughput {
+    // ช่วงล่าสุด (EWMA)
+    pub bytes_per_sec: u64,
+    // เฉลี่ยตั้งแต่เริ่ม ไม่นับช่วง Pause
+    pub avg_bytes_per_sec: u64,
+    // None = ยังประเมินไม่ได้ (ยังไม่มี Byte ขยับ)
+    pub eta_secs: Option<u64>,
+}
+
+pub struct ThroughputMeter {
+    started: tokio::time::Instant,
+    paused: Duration,
+    last_time: tokio::time::Instant,
+    last_bytes: u64,
+    rate: Option<f64>,
+}
+
+impl ThroughputMeter {
+    pub fn start() -> Self {
+        let now = tokio::time::Instant::now();
+        Self { started: now, paused: Duration::ZERO, last_time: now, last_bytes: 0, rate: None }
+    }
+
+    // ช่วง Pause ไม่นับเป็นเวลาส่ง (ไม่งั้นความเร็วเฉลี่ยตก)
+    pub fn paused_for(&mut self, d: Duration) {
+        self.paused += d;
+        self.last_time += d;
+    }
+
+    pub fn sample(&mut self, done: u64, total: u64) -> Throughput {
+        let now = tokio::time::Instant::now();
+        let window = now.saturating_duration_since(self.last_time).as_secs_f64();
+        if window > 0.0 && done >= self.last_bytes {
+            let current = (done - self.last_bytes) as f64 / window;
+            self.rate = Some(match self.rate {
+                Some(rate) => rate + THROUGHPUT_SMOOTHING * (current - rate),
+                None => current,
+            });
+        }
+        self.last_time = now;
+        self.last_bytes = done;
+        let active = now.duration_since(self.started).saturating_sub(self.paused).as_secs_f64();
+        let avg = if active > 0.0 { done as f64 / active } else { 0.0 };
+        let rate = self.rate.unwrap_or(avg);
+        let eta_secs = (rate >= 1.0).then(|| (total.saturating_sub(done) as f64 / rate).ceil() as u64);
+        Throughput { bytes_per_sec: rate as u64, avg_bytes_per_sec: avg as u64, eta_secs }
+    }
 }
 
 pub fn pack_ack(status: u8, offset: u64) -> Vec<u8> {
@@ -67,22 +326,55 @@ pub fn unpack_ack(data: &[u8]) -> anyhow::Result<(u8, u64)> {
     Ok((data[0], u64::from_le_bytes(offset_buf)))
 }
 
-pub async fn copy_pipeline<R, W, F>(mut reader: R, mut writer: W, total: u64, mut on_progress: F) -> anyhow::Result<()> 
-where R: AsyncReadExt + Unpin + Send + 'static, W: AsyncWriteExt + Unpin, F: FnMut(u64, u64) + Send + 'static
+// 🐢 นับเวลาที่ไม่มี Byte ขยับ (ช่วง Pause ไม่นับ)
+struct StallWatchdog {
+    threshold: Duration,
+    last_moved: tokio::time::Instant,
+    warned: bool,
+}
+
+impl StallWatchdog {
+    fn new(threshold: Duration) -> Self {
+        Self { threshold, last_moved: tokio::time::Instant::now(), warned: false }
+    }
+
+    fn moved(&mut self) {
+        self.last_moved = tokio::time::Instant::now();
+        self.warned = false;
+    }
+
+    fn check<S: FnMut(Duration)>(&mut self, signal: &TransferSignal, on_stall: &mut S) -> anyhow::Result<()> {
+        if self.threshold.is_zero() || signal.is_paused() { self.moved(); return Ok(()); }
+        let idle = self.last_moved.elapsed();
+        if idle >= self.threshold {
+            anyhow::bail!(error::DropTeaError::Timeout(format!("Transfer stalled for {}s", idle.as_secs())));
+        }
+        if !self.warned && idle >= self.threshold / 2 {
+            self.warned = true;
+            on_stall(idle);
+        }
+        Ok(())
+    }
+}
+
+pub async fn copy_pipeline<R, W, F, S>(mut reader: R, mut writer: W, total: u64, mut on_progress: F, mut on_stall: S, timeouts: Timeouts, signal: &TransferSignal) -> anyhow::Result<()> 
+where R: AsyncReadExt + Unpin + Send + 'static, W: AsyncWriteExt + Unpin, F: FnMut(u64, u64, Throughput) + Send + 'static, S: FnMut(Duration)
 {
+    let io_timeout = timeouts.io;
     let (data_tx, mut data_rx) = mpsc::channel::<anyhow::Result<Vec<u8>>>(CHANNEL_CAPACITY);
     let (recycle_tx, mut recycle_rx) = mpsc::channel::<Vec<u8>>(CHANNEL_CAPACITY);
     for _ in 0..CHANNEL_CAPACITY { let _ = recycle_tx.send(vec![0u8; PIPELINE_BUFFER_SIZE]).await; }
     
+    let producer_signal = signal.clone();
     let producer_handle = tokio::spawn(async move {
         loop {
             let mut buf = match recycle_rx.recv().await { Some(b) => b, None => break };
             buf.resize(PIPELINE_BUFFER_SIZE, 0);
-            match tokio::time::timeout(IO_TIMEOUT, reader.read(&mut buf)).await {
-                Ok(Ok(0)) => break, 
-                Ok(Ok(n)) => { buf.truncate(n); if data_tx.send(Ok(buf)).await.is_err() { break; } },
-                Ok(Err(e)) => { let _ = data_tx.send(Err(anyhow::Error::new(e))).await; break; },
-                Err(_) => { let _ = data_tx.send(Err(anyhow::anyhow!("Read Timeout"))).await; break; }
+            match producer_signal.timeout(io_timeout, reader.read(&mut buf)).await {
+                Some(Ok(0)) => break, 
+                Some(Ok(n)) => { buf.truncate(n); if data_tx.send(Ok(buf)).await.is_err() { break; } },
+                Some(Err(e)) => { let _ = data_tx.send(Err(anyhow::Error::new(e))).await; break; },
+                None => { let _ = data_tx.send(Err(anyhow::anyhow!("Read Timeout"))).await; break; }
             }
         }
     });
@@ -90,13 +382,53 @@ where R: AsyncReadExt + Unpin + Send + 'static, W: AsyncWriteExt + Unpin, F: FnM
     let mut uploaded = 0u64;
     let mut last_rep = 0u64;
     let mut last_time = tokio::time::Instant::now();
-    while let Some(result) = data_rx.recv().await {
+    let mut watchdog = StallWatchdog::new(timeouts.stall);
+    let mut meter = ThroughputMeter::start();
+    let mut tick = tokio::time::interval(STALL_CHECK_INTERVAL);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        let result = tokio::select! {
+            biased;
+            _ = signal.cancelled() => {
+                producer_handle.abort();
+                anyhow::bail!(REJECT_CANCELLED);
+            }
+            result = data_rx.recv() => match result { Some(r) => r, None => break },
+            _ = tick.tick() => {
+                if let Err(e) = watchdog.check(signal, &mut on_stall) { producer_handle.abort(); return Err(e); }
+                continue;
+            }
+        };
         let chunk = result?; 
-        tokio::time::timeout(IO_TIMEOUT, writer.write_all(&chunk)).await.map_err(|_| anyhow::anyhow!("Write timeout"))??;
+        watchdog.moved();
+        // ⏸️ Pause: หยุดเขียน -> Producer เติม Buffer จนเต็มแล้วรอเอง
+        if signal.is_paused() {
+            let paused_at = tokio::time::Instant::now();
+            tokio::select! {
+                _ = signal.cancelled() => { producer_handle.abort(); anyhow::bail!(REJECT_CANCELLED); }
+                _ = signal.resumed() => {}
+            }
+            meter.paused_for(paused_at.elapsed());
+            watchdog.moved();
+        }
+        // เขียนทีละส่วนที่ Writer รับได้ (Chunk ละ 4MB บน Link ช้าอาจนานเกิน Stall Threshold ทั้งที่ยังขยับอยู่)
+        let mut written = 0;
+        while written < chunk.len() {
+            let n = tokio::select! {
+                n = signal.timeout(io_timeout, writer.write(&chunk[written..])) => n.ok_or_else(|| anyhow::anyhow!("Write timeout"))??,
+                _ = tick.tick() => {
+                    if let Err(e) = watchdog.check(signal, &mut on_stall) { producer_handle.abort(); return Err(e); }
+                    continue;
+                }
+            };
+            if n == 0 { producer_handle.abort(); return Err(std::io::Error::from(std::io::ErrorKind::WriteZero).into()); }
+            written += n;
+            watchdog.moved();
+        }
         uploaded += chunk.len() as u64;
         let now = tokio::time::Instant::now();
         if (uploaded - last_rep >= (1024*1024) && now.duration_since(last_time).as_millis() > NOTIFY_INTERVAL_MS) || uploaded == total {
-            on_progress(uploaded, total); last_rep = uploaded; last_time = now;
+            on_progress(uploaded, total, meter.sample(uploaded, total)); last_rep = uploaded; last_time = now;
         }
         let _ = recycle_tx.send(chunk).await;
     }
@@ -105,4 +437,59 @@ where R: AsyncReadExt + Unpin + Send + 'static, W: AsyncWriteExt + Unpin, F: FnM
         return Err(anyhow::anyhow!("Producer task panic: {}", e));
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// 🚀 Zero-copy: Kernel ส่งจาก Page Cache เข้า Socket ตรง (sendfile) ไม่ผ่าน Buffer / Channel ใน Userspace
+/// ใช้ได้เฉพาะ Plain TCP ที่ไม่บีบอัด (Byte บน Wire = Byte ในไฟล์) ส่งจาก offset ไป total Byte
+/// Progress / Stall / Pause / Cancel เหมือน copy_pipeline (อ่าน Disk เกิดใน sendfile เอง -> ไม่ใช้กับ IoPriority::Background)
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[allow(clippy::too_many_arguments)]
+pub async fn sendfile_pipeline<F, S>(file: &tokio::fs::File, offset: u64, socket: &tokio::net::TcpStream, total: u64, mut on_progress: F, mut on_stall: S, timeouts: Timeouts, signal: &TransferSignal) -> anyhow::Result<()>
+where F: FnMut(u64, u64, Throughput), S: FnMut(Duration)
+{
+    use std::os::fd::AsRawFd;
+    let (in_fd, out_fd) = (file.as_raw_fd(), socket.as_raw_fd());
+    let mut position = offset as libc::off_t;
+    let mut sent = 0u64;
+    let mut last_rep = 0u64;
+    let mut last_time = tokio::time::Instant::now();
+    let mut watchdog = StallWatchdog::new(timeouts.stall);
+    let mut meter = ThroughputMeter::start();
+    let mut tick = tokio::time::interval(STALL_CHECK_INTERVAL);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    while sent < total {
+        if signal.is_paused() {
+            let paused_at = tokio::time::Instant::now();
+            tokio::select! {
+                _ = signal.cancelled() => anyhow::bail!(REJECT_CANCELLED),
+                _ = signal.resumed() => {}
+            }
+            meter.paused_for(paused_at.elapsed());
+            watchdog.moved();
+        }
+        tokio::select! {
+            biased;
+            _ = signal.cancelled() => anyhow::bail!(REJECT_CANCELLED),
+            ready = signal.timeout(timeouts.io, socket.writable()) => ready.ok_or_else(|| anyhow::anyhow!("Write timeout"))??,
+            _ = tick.tick() => { watchdog.check(signal, &mut on_stall)?; continue; }
+        }
+        // รอบละไม่เกิน PIPELINE_BUFFER_SIZE -> กลับมาเช็ค Cancel / Progress สม่ำเสมอ
+        let chunk = (total - sent).min(PIPELINE_BUFFER_SIZE as u64) as usize;
+        let n = match socket.try_io(tokio::io::Interest::WRITABLE, || {
+            let n = unsafe { libc::sendfile(out_fd, in_fd, &mut position, chunk) };
+            if n < 0 { Err(std::io::Error::last_os_error()) } else { Ok(n as usize) }
+        }) {
+            Ok(0) => anyhow::bail!("Source file ended after {} of {} bytes", sent, total),
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+            Err(e) => return Err(e.into()),
+        };
+        sent += n as u64;
+        watchdog.moved();
+        let now = tokio::time::Instant::now();
+        if (sent - last_rep >= (1024*1024) && now.duration_since(last_time).as_millis() > NOTIFY_INTERVAL_MS) || sent == total {
+            on_progress(sent, total, meter.sample(sent, total)); last_rep = sent; last_time = now;
+        }
+    }
+    Ok(())
+}
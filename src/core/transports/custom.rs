@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use async_trait::async_trait;
+
+use crate::core::engine::{DropTeaConfig, TransportMode};
+use crate::core::error::{DropTeaError, Result};
+use crate::core::transfer::DynTransport;
+
+// ==========================================
+// Custom Transport (Crate อื่นใส่ Transport ของตัวเองได้โดยไม่ต้องแก้ TransportMode)
+// เช่น Serial Link / Unix Socket / Tunnel
+// 1) ตรง: DropTeaCore::with_transport(name, transport) / DropTeaBuilder::custom_transport
+// 2) จาก Config: register_transport(name, factory) ตอนเริ่มโปรแกรม แล้วตั้ง [server] mode = name
+// Engine ไม่โหลด TLS Identity / ไม่ขอ Port Forward / ไม่แบ่ง Parallel Stream ให้ (Transport ดูแลเอง)
+// ==========================================
+
+/// สร้าง Transport ตอน Engine เริ่ม (รันใน Runtime ของ Engine อ่าน port / storage_path จาก Config ได้)
+#[async_trait]
+pub trait TransportFactory: Send + Sync + 'static {
+    async fn create(&self, config: &DropTeaConfig) -> Result<Arc<DynTransport>>;
+}
+
+pub type DynTransportFactory = Arc<dyn TransportFactory>;
+
+fn registry() -> &'static RwLock<HashMap<&'static str, DynTransportFactory>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<&'static str, DynTransportFactory>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// ชื่อ (ตัวเล็ก) ใช้เป็น [server] mode ได้ ลงชื่อเดิมซ้ำ = แทนตัวเก่า / ชื่อของ Transport ในตัว = Error
+pub fn register_transport(name: &'static str, factory: DynTransportFactory) -> Result<()> {
+    if name.is_empty() || name != name.to_lowercase() {
+        return Err(DropTeaError::Config(format!("transport name '{}' must be non-empty lowercase", name)));
+    }
+    if TransportMode::from_name(name).is_some() {
+        return Err(DropTeaError::Config(format!("transport '{}' is built in", name)));
+    }
+    registry().write().unwrap().insert(name, factory);
+    Ok(())
+}
+
+pub fn is_registered(name: &str) -> bool {
+    registry().read().unwrap().contains_key(name)
+}
+
+/// ชื่อที่ลงไว้ทั้งหมด (ไว้พิมพ์ใน Error / --help)
+pub fn registered() -> Vec<&'static str> {
+    let mut names: Vec<_> = registry().read().unwrap().keys().copied().collect();
+    names.sort_unstable();
+    names
+}
+
+/// คืนชื่อแบบ 'static ด้วย (Engine ใช้เป็นชื่อ Transport ใน Stats / mDNS)
+pub(crate) async fn create(name: &str, config: &DropTeaConfig) -> Result<(&'static str, Arc<DynTransport>)> {
+    let found = registry().read().unwrap().get_key_value(name).map(|(n, f)| (*n, f.clone()));
+    let (name, factory) = found.ok_or_else(|| DropTeaError::Config(format!("transport '{}' is not registered", name)))?;
+    Ok((name, factory.create(config).await?))
+}
@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::task::{Context, Poll};
+use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncWrite, DuplexStream, ReadBuf};
+use tokio::sync::{mpsc, Mutex};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+use crate::core::error::{DropTeaError, Result};
+use crate::core::security;
+use crate::core::transfer::{DynStream, Transport};
+
+// ==========================================
+// In-Memory Transport (Engine หลายตัวในโปรเซสเดียว ไม่เปิด Socket)
+// MemoryNetwork = LAN จำลองที่ Transport หลายตัวแชร์กัน ต่อหากันด้วย ip:port ที่ Bind ไว้
+// มี Identity = TLS จริงบน Pipe -> TofuVerifier / known_hosts / Fingerprint ผู้ส่ง ทำงานเหมือน TcpTransport
+// ใช้ผ่าน DropTeaBuilder::memory_network (Control Channel ยังเป็น TCP บน Loopback)
+// drop_links() = จำลองเปลี่ยน AP: ทุก Connection ที่เปิดอยู่หลุด (Connection ใหม่ต่อได้ตามปกติ)
+// ==========================================
+
+// Buffer ของ Pipe แต่ละทิศ (เล็กกว่า Socket Buffer -> Pause / Backpressure เห็นผลเร็ว)
+const PIPE_BUFFER: usize = 256 * 1024;
+const ACCEPT_BACKLOG: usize = 64;
+
+type Pending = (Link, SocketAddr);
+
+/// Pipe ที่รู้ว่าเปิดมาตอน Network รุ่นไหน (drop_links แล้ว = ConnectionReset)
+pub struct Link {
+    pipe: DuplexStream,
+    epoch: Arc<AtomicU64>,
+    born: u64,
+}
+
+impl Link {
+    fn check(&self) -> std::io::Result<()> {
+        match self.epoch.load(Ordering::Relaxed) == self.born {
+            true => Ok(()),
+            false => Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "Memory link dropped")),
+        }
+    }
+}
+
+impl AsyncRead for Link {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        self.check()?;
+        Pin::new(&mut self.pipe).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Link {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        self.check()?;
+        Pin::new(&mut self.pipe).poll_write(cx, buf)
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.check()?;
+        Pin::new(&mut self.pipe).poll_flush(cx)
+    }
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.pipe).poll_shutdown(cx)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryNetwork {
+    listeners: StdMutex<HashMap<SocketAddr, mpsc::Sender<Pending>>>,
+    epoch: Arc<AtomicU64>,
+}
+
+impl MemoryNetwork {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    fn bind(&self, addr: SocketAddr) -> Result<mpsc::Receiver<Pending>> {
+        let mut listeners = self.listeners.lock().unwrap();
+        // Transport เดิมถูกทิ้งแล้ว (Receiver ปิด) = Address ว่าง
+        if listeners.get(&addr).is_some_and(|tx| !tx.is_closed()) {
+            return Err(DropTeaError::Network(format!("Address {} already in use", addr)));
+        }
+        let (tx, rx) = mpsc::channel(ACCEPT_BACKLOG);
+        listeners.insert(addr, tx);
+        Ok(rx)
+    }
+
+    async fn dial(&self, from: SocketAddr, to: SocketAddr) -> Result<Link> {
+        let refused = || DropTeaError::Network(format!("Connection refused by {}", to));
+        let tx = self.listeners.lock().unwrap().get(&to).cloned().ok_or_else(refused)?;
+        let (client, server) = tokio::io::duplex(PIPE_BUFFER);
+        let link = |pipe| Link { pipe, epoch: self.epoch.clone(), born: self.epoch.load(Ordering::Relaxed) };
+        tx.send((link(server), from)).await.map_err(|_| refused())?;
+        Ok(link(client))
+    }
+
+    /// ตัดทุก Connection ที่เปิดอยู่ (เหมือนเปลี่ยน AP กลางทาง) ฝั่งที่กำลังอ่าน/เขียนได้ ConnectionReset
+    pub fn drop_links(&self) {
+        self.epoch.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+pub struct MemoryTransport {
+    network: Arc<MemoryNetwork>,
+    addr: SocketAddr,
+    incoming: Mutex<mpsc::Receiver<Pending>>,
+    tls: Option<(TlsAcceptor, TlsConnector)>,
+}
+
+impl MemoryTransport {
+    /// ไม่มี TLS (เหมือน plaintcp: ไม่มี Fingerprint ผู้ส่ง)
+    pub fn new(network: Arc<MemoryNetwork>, addr: SocketAddr) -> Result<Self> {
+        let incoming = Mutex::new(network.bind(addr)?);
+        Ok(Self { network, addr, incoming, tls: None })
+    }
+
+    /// TLS ด้วย Identity และ known_hosts ของ storage_path เดียวกับ TcpTransport
+    pub fn with_identity(network: Arc<MemoryNetwork>, addr: SocketAddr, storage_path: &str, identity: &security::Identity) -> anyhow::Result<Self> {
+        let (server_cfg, client_cfg) = security::build_tls_configs(storage_path, identity)?;
+        let mut transport = Self::new(network, addr)?;
+        transport.tls = Some((TlsAcceptor::from(Arc::new(server_cfg)), TlsConnector::from(Arc::new(client_cfg))));
+        Ok(transport)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+#[async_trait]
+impl Transport for MemoryTransport {
+    type Stream = DynStream;
+
+    async fn accept(&self) -> Result<(Self::Stream, SocketAddr, Option<String>)> {
+        let (stream, addr) = self.incoming.lock().await.recv().await
+            .ok_or_else(|| DropTeaError::Network("Memory network closed".into()))?;
+        let Some((acceptor, _)) = &self.tls else { return Ok((Box::new(stream), addr, None)) };
+        let tls_stream = acceptor.accept(stream).await?;
+        let fingerprint = tls_stream.get_ref().1.peer_certificates()
+            .and_then(|certs| certs.first())
+            .map(security::fingerprint);
+        Ok((Box::new(tls_stream), addr, fingerprint))
+    }
+
+    #[tracing::instrument(level = "debug", skip(self), fields(transport = "memory"))]
+    async fn connect(&self, ip: &str, port: u16) -> Result<Self::Stream> {
+        let (addr, _) = crate::core::utils::parse_scoped_ip(ip)
+            .ok_or_else(|| DropTeaError::Config(format!("Memory transport needs an IP address, got '{}'", ip)))?;
+        let stream = self.network.dial(self.addr, SocketAddr::new(addr, port)).await?;
+        let Some((_, connector)) = &self.tls else { return Ok(Box::new(stream)) };
+        let domain = tokio_rustls::rustls::ServerName::try_from(ip)
+            .or_else(|_| tokio_rustls::rustls::ServerName::try_from("droptea.p2p"))?;
+        let tls_stream = connector.connect(domain, stream).await?;
+        Ok(Box::new(tls_stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+    use std::sync::Once;
+    use std::time::Duration;
+    use rand::RngCore;
+    use tokio::runtime::Runtime;
+    use tokio::sync::broadcast::{self, error::RecvError};
+
+    use crate::core::discovery::MockDiscovery;
+    use crate::core::engine::{DropTeaCore, SendOptions};
+    use crate::core::events::{NoopHandler, TransferEvent};
+
+    const RECEIVER_PORT: u16 = 28300;
+    const SENDER_PORT: u16 = 28301;
+    const OTHER_PORT: u16 = 28302;
+    const WAIT: Duration = Duration::from_secs(20);
+    static WORKDIR: Once = Once::new();
+
+    // Engine หลายตัวบน MemoryNetwork เดียวกัน ทุกตัวเป็น Guest (Cert สุ่ม known_hosts อยู่ใน Memory ของแต่ละตัว)
+    struct Lan {
+        rt: Arc<Runtime>,
+        network: Arc<MemoryNetwork>,
+        dir: PathBuf,
+    }
+
+    impl Lan {
+        fn new() -> Self {
+            // History ของไฟล์ที่รับลง ./downloads ของ Working Directory -> ย้ายออกจาก Source Tree ครั้งเดียวทั้งโปรเซส
+            WORKDIR.call_once(|| {
+                let work = std::env::temp_dir().join(format!("droptea-memory-tests-{}", std::process::id()));
+                std::fs::create_dir_all(&work).unwrap();
+                std::env::set_current_dir(&work).unwrap();
+            });
+            let dir = std::env::temp_dir().join(format!("droptea-memory-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self { rt: Arc::new(Runtime::new().unwrap()), network: MemoryNetwork::new(), dir }
+        }
+
+        // start_service แล้วรอจน Accept ได้ (ไฟล์ที่รับลง <dir>/<name>/downloads)
+        fn engine(&self, name: &str, port: u16) -> (DropTeaCore, broadcast::Receiver<TransferEvent>) {
+            let storage = self.dir.join(name);
+            std::fs::create_dir_all(&storage).unwrap();
+            let engine = DropTeaCore::builder()
+                .memory_network(self.network.clone())
+                .discovery_backend(MockDiscovery::new())
+                // Connect ไม่ผ่าน (TOFU) = จบเลย ไม่ต้องรอ Backoff
+                .configure(|c| c.retry.attempts = 1)
+                .port(port)
+                .storage(storage.to_string_lossy())
+                .download_dir(storage.join("downloads").to_string_lossy())
+                .node_name(name)
+                .guest(true)
+                .handler(NoopHandler)
+                .runtime(self.rt.clone())
+                .build()
+                .unwrap();
+            let mut events = engine.subscribe();
+            engine.start_service(port);
+            self.wait_for(&mut events, "ServerStarted", |e| matches!(e, TransferEvent::ServerStarted { .. }));
+            (engine, events)
+        }
+
+        fn file(&self, name: &str, data: &[u8]) -> PathBuf {
+            let path = self.dir.join(name);
+            std::fs::write(&path, data).unwrap();
+            path
+        }
+
+        fn send(&self, sender: &DropTeaCore, port: u16, path: &Path, task_id: &str) {
+            sender.send_file("127.0.0.1".into(), port, path.to_string_lossy().into_owned(), task_id.into(), "memory-sender".into(), Box::new(NoopHandler), None, SendOptions::default());
+        }
+
+        // ข้าม Event อื่นระหว่างทาง (Progress / Log ...) จนเจอตัวที่ตรง
+        fn wait_for(&self, events: &mut broadcast::Receiver<TransferEvent>, what: &str, pred: impl Fn(&TransferEvent) -> bool) -> TransferEvent {
+            self.rt.block_on(async {
+                tokio::time::timeout(WAIT, async {
+                    loop {
+                        match events.recv().await {
+                            Ok(event) if pred(&event) => return event,
+                            Ok(_) | Err(RecvError::Lagged(_)) => {}
+                            Err(RecvError::Closed) => panic!("event stream closed while waiting for {}", what),
+                        }
+                    }
+                }).await.unwrap_or_else(|_| panic!("timed out waiting for {}", what))
+            })
+        }
+
+        // ข้อเสนอที่ผู้รับเห็น -> Task ID ฝั่งรับ
+        fn incoming(&self, events: &mut broadcast::Receiver<TransferEvent>, filename: &str) -> String {
+            let needle = format!("|{}|", filename);
+            match self.wait_for(events, "Incoming", |e| matches!(e, TransferEvent::Incoming { filename, .. } if filename.contains(&needle))) {
+                TransferEvent::Incoming { task_id, .. } => task_id,
+                _ => unreachable!(),
+            }
+        }
+
+        fn finished(&self, events: &mut broadcast::Receiver<TransferEvent>, task: &str) -> TransferEvent {
+            self.wait_for(events, task, |e| match e {
+                TransferEvent::Completed { task_id, .. } | TransferEvent::Rejected { task_id, .. } | TransferEvent::Error { task_id, .. } => task_id == task,
+                _ => false,
+            })
+        }
+
+        // Incoming -> กดรับ -> สองฝั่ง Completed -> Event ของผู้ส่ง + Path ที่ผู้รับเก็บไว้
+        fn deliver(&self, sender_events: &mut broadcast::Receiver<TransferEvent>, receiver: &DropTeaCore, receiver_events: &mut broadcast::Receiver<TransferEvent>, filename: &str, task_id: &str) -> (TransferEvent, PathBuf) {
+            let rx_task = self.incoming(receiver_events, filename);
+            assert!(receiver.resolve_request(rx_task.clone(), true));
+            let received = match self.finished(receiver_events, &rx_task) {
+                TransferEvent::Completed { info, .. } => PathBuf::from(info),
+                other => panic!("receiver expected Completed, got {:?}", other),
+            };
+            match self.finished(sender_events, task_id) {
+                sent @ TransferEvent::Completed { .. } => (sent, received),
+                other => panic!("sender expected Completed, got {:?}", other),
+            }
+        }
+    }
+
+    impl Drop for Lan {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn random_bytes(len: usize) -> Vec<u8> {
+        let mut data = vec![0u8; len];
+        rand::thread_rng().fill_bytes(&mut data);
+        data
+    }
+
+    #[test]
+    fn accepted_file_arrives_and_rejected_file_does_not() {
+        let lan = Lan::new();
+        let (receiver, mut rx) = lan.engine("receiver", RECEIVER_PORT);
+        let (sender, mut tx) = lan.engine("sender", SENDER_PORT);
+
+        let data = random_bytes(256 * 1024);
+        lan.send(&sender, RECEIVER_PORT, &lan.file("accepted.bin", &data), "accept");
+        let (_, received) = lan.deliver(&mut tx, &receiver, &mut rx, "accepted.bin", "accept");
+        assert_eq!(received.parent(), Some(lan.dir.join("receiver").join("downloads").as_path()));
+        assert_eq!(std::fs::read(&received).unwrap(), data);
+
+        lan.send(&sender, RECEIVER_PORT, &lan.file("rejected.bin", &data), "reject");
+        let rx_task = lan.incoming(&mut rx, "rejected.bin");
+        assert!(receiver.resolve_request(rx_task.clone(), false));
+        match lan.finished(&mut rx, &rx_task) {
+            TransferEvent::Rejected { reason, .. } => assert_eq!(reason, "User Rejected"),
+            other => panic!("receiver expected Rejected, got {:?}", other),
+        }
+        match lan.finished(&mut tx, "reject") {
+            TransferEvent::Rejected { reason, .. } => assert_eq!(reason, "Receiver Rejected"),
+            other => panic!("sender expected Rejected, got {:?}", other),
+        }
+        assert!(!lan.dir.join("receiver").join("downloads").join("rejected.bin").exists());
+    }
+
+    #[test]
+    fn compressible_file_is_sent_compressed() {
+        let lan = Lan::new();
+        let (receiver, mut rx) = lan.engine("receiver", RECEIVER_PORT);
+        let (sender, mut tx) = lan.engine("sender", SENDER_PORT);
+
+        // ส่งด้วย IP ไม่มี Capability ของ Peer -> zstd
+        let data = "DropTea memory transport compression test line\n".repeat(64 * 1024).into_bytes();
+        lan.send(&sender, RECEIVER_PORT, &lan.file("notes.txt", &data), "compressed");
+        let (sent, received) = lan.deliver(&mut tx, &receiver, &mut rx, "notes.txt", "compressed");
+        assert_eq!(std::fs::read(&received).unwrap(), data);
+        let TransferEvent::Completed { stats: Some(stats), .. } = sent else { panic!("sender Completed without stats: {:?}", sent) };
+        assert_eq!(stats.compression, "zstd");
+        assert_eq!(stats.raw_bytes, data.len() as u64);
+        assert!(stats.wire_bytes < stats.raw_bytes / 10, "wire {} of raw {}", stats.wire_bytes, stats.raw_bytes);
+    }
+
+    #[test]
+    fn changed_fingerprint_at_known_host_is_refused() {
+        let lan = Lan::new();
+        let (first, mut first_rx) = lan.engine("first", RECEIVER_PORT);
+        let (sender, mut tx) = lan.engine("sender", SENDER_PORT);
+
+        // ครั้งแรกที่ 127.0.0.1 -> ผู้ส่งจำ Fingerprint ของ first
+        let data = random_bytes(64 * 1024);
+        let source = lan.file("tofu.bin", &data);
+        lan.send(&sender, RECEIVER_PORT, &source, "trusted");
+        lan.deliver(&mut tx, &first, &mut first_rx, "tofu.bin", "trusted");
+
+        // Engine อื่น (Cert คนละใบ) ที่ Host เดียวกัน -> TLS ไม่ผ่าน ไม่ถึงขั้นเสนอไฟล์
+        let (_second, mut second_rx) = lan.engine("second", OTHER_PORT);
+        lan.send(&sender, OTHER_PORT, &source, "mismatch");
+        match lan.finished(&mut tx, "mismatch") {
+            TransferEvent::Error { error, .. } => assert!(error.contains("Fingerprint mismatch"), "unexpected error: {}", error),
+            other => panic!("sender expected Error, got {:?}", other),
+        }
+        while let Ok(event) = second_rx.try_recv() {
+            assert!(!matches!(event, TransferEvent::Incoming { .. }), "second engine was offered the file: {:?}", event);
+        }
+    }
+}
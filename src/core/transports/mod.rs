@@ -1,3 +1,7 @@
 pub mod tcp;
 pub mod quic;
-pub mod plain_tcp;
\ No newline at end of file
+pub mod plain_tcp;
+#[cfg(feature = "webrtc")]
+pub mod webrtc;
+pub mod memory;
+pub mod custom;
@@ -1,19 +1,24 @@
 use async_trait::async_trait;
 use tokio::net::{TcpListener, TcpStream};
-use anyhow::Result;
+use crate::core::error::{DropTeaError, Result};
 use std::net::SocketAddr;
 
 use crate::core::transfer::{Transport, DynStream};
 
 pub struct PlainTcpTransport {
-    listener: TcpListener,
+    // None = ใช้ Connect อย่างเดียว (ส่งหา Peer ที่บังคับ plaintcp ไว้ ขณะที่ Engine รันโหมดอื่น)
+    listener: Option<TcpListener>,
 }
 
 impl PlainTcpTransport {
     pub async fn new(port: u16) -> Result<Self> {
         // Bind Port แบบ TCP ปกติ
         let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
-        Ok(Self { listener })
+        Ok(Self { listener: Some(listener) })
+    }
+
+    pub fn client() -> Self {
+        Self { listener: None }
     }
 }
 
@@ -21,12 +26,14 @@ impl PlainTcpTransport {
 impl Transport for PlainTcpTransport {
     type Stream = DynStream;
 
-    async fn accept(&self) -> Result<(Self::Stream, SocketAddr)> {
-        // รับ Connection เข้ามาแล้วส่งคืน Stream เลย (ไม่ต้อง Handshake TLS)
-        let (stream, addr) = self.listener.accept().await?;
-        Ok((Box::new(stream), addr))
+    async fn accept(&self) -> Result<(Self::Stream, SocketAddr, Option<String>)> {
+        // รับ Connection เข้ามาแล้วส่งคืน Stream เลย (ไม่ต้อง Handshake TLS -> ไม่มี Fingerprint)
+        let listener = self.listener.as_ref().ok_or_else(|| DropTeaError::Config("Plain TCP transport is connect-only".into()))?;
+        let (stream, addr) = listener.accept().await?;
+        Ok((Box::new(stream), addr, None))
     }
 
+    #[tracing::instrument(level = "debug", skip(self), fields(transport = "plaintcp"))]
     async fn connect(&self, ip: &str, port: u16) -> Result<Self::Stream> {
         // เชื่อมต่อไปหาปลายทางแบบ TCP ปกติ
         let stream = TcpStream::connect(format!("{}:{}", ip, port)).await?;
@@ -1,6 +1,8 @@
 use crate::core::transfer::{Transport, DataStream};
 use crate::core::security;
-use quinn::{Endpoint, RecvStream, SendStream, Connection, TransportConfig, VarInt};
+use crate::core::error;
+use crate::core::rendezvous::{RendezvousClient, PUNCH_CONNECT_TIMEOUT};
+use quinn::{Endpoint, EndpointConfig, RecvStream, SendStream, Connection, TransportConfig, VarInt};
 use async_trait::async_trait;
 use std::sync::Arc;
 use std::net::SocketAddr;
@@ -9,12 +11,45 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::collections::HashMap;
 use tokio::sync::RwLock; // ✅ เปลี่ยนใช้ RwLock เพื่อ High Concurrency
+use tokio::sync::{mpsc, Mutex};
 use std::time::Duration;
 
 // --- Constants & Configuration ---
 
 pub const PROTOCOL_SERVER_NAME: &str = "droptea.p2p";
 pub const PROTOCOL_ALPN: &[&[u8]] = &[b"droptea-p2p"];
+// Stream ที่ Accept แล้วแต่ Engine ยังไม่ได้หยิบ (รวมทุก Connection)
+const STREAM_BACKLOG: usize = 64;
+
+type Accepted = (QuicDataStream, SocketAddr, Option<String>);
+
+/// Congestion Control ของ Connection (BBR ไม่หั่น Window ทุกครั้งที่ Packet หาย -> Wi-Fi ไกลๆ / RTT สูงเร็วกว่า Cubic)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CongestionControl {
+    #[default]
+    Cubic,
+    NewReno,
+    Bbr,
+}
+
+impl CongestionControl {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cubic => "cubic",
+            Self::NewReno => "newreno",
+            Self::Bbr => "bbr",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "cubic" => Some(Self::Cubic),
+            "newreno" | "new_reno" | "reno" => Some(Self::NewReno),
+            "bbr" => Some(Self::Bbr),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct QuicConfig {
@@ -23,6 +58,15 @@ pub struct QuicConfig {
     pub max_concurrent_streams: u32, // ✅ เพิ่ม Config สำหรับ Parallelism
     pub keep_alive_interval: Duration,
     pub max_idle_timeout: Duration,
+    pub congestion: CongestionControl,
+    // None = ค่าของ quinn (~14 KB) Link ที่ RTT สูงขยายให้ Slow Start ไม่ต้องไต่จากศูนย์
+    pub initial_window: Option<u64>,
+    // quinn Pace ทุก Packet ตาม Window / RTT เสมอ -> RTT ตั้งต้นคือจังหวะ Pace ก่อนวัด RTT จริงได้ (None = 333ms ของ quinn)
+    pub initial_rtt: Option<Duration>,
+    // GSO ส่งหลาย Packet ในครั้งเดียว (ประหยัด CPU แต่เป็น Burst) ปิด = Pace ละเอียดกว่าบน Access Point ที่ Buffer เล็ก
+    pub segmentation_offload: bool,
+    // ตั้งไว้ = Connect ด้วย Peer ID (ไม่ใช่ IP) ได้ผ่าน Hole Punching
+    pub rendezvous_server: Option<SocketAddr>,
 }
 
 impl Default for QuicConfig {
@@ -34,6 +78,11 @@ impl Default for QuicConfig {
             max_concurrent_streams: 1000,              // ✅ รองรับ 1000 streams พร้อมกัน
             keep_alive_interval: Duration::from_secs(5),
             max_idle_timeout: Duration::from_secs(60),
+            congestion: CongestionControl::default(),
+            initial_window: None,
+            initial_rtt: None,
+            segmentation_offload: true,
+            rendezvous_server: None,
         }
     }
 }
@@ -69,6 +118,10 @@ pub struct QuicTransport {
     endpoint: Endpoint,
     // ✅ ใช้ RwLock: อ่านได้หลาย thread พร้อมกัน, เขียนทีละ thread
     connections: Arc<RwLock<HashMap<SocketAddr, Connection>>>,
+    rendezvous: Option<Arc<RendezvousClient>>,
+    // ผู้ส่ง Pool Connection แล้วเปิด Stream ใหม่ทุกครั้ง -> ต้อง Accept ทุก Stream ของทุก Connection ไม่ใช่แค่ Stream แรก
+    streams_tx: mpsc::Sender<Accepted>,
+    streams: Mutex<mpsc::Receiver<Accepted>>,
 }
 
 impl QuicTransport {
@@ -76,12 +129,12 @@ impl QuicTransport {
         port: u16, 
         storage_path: &str, 
         node_name: &str, 
+        identity: &security::Identity,
         config: Option<QuicConfig>
     ) -> anyhow::Result<Self> {
         
         let config = config.unwrap_or_default();
-        let (certs, key) = security::load_or_generate_identity(storage_path, node_name)?;
-        let sec_path = std::path::Path::new(storage_path).join("security");
+        let (certs, key) = (identity.certs.clone(), identity.key.clone());
 
         // 1. Setup Transport Config (Performance Tuning)
         let mut transport_config = TransportConfig::default();
@@ -91,35 +144,60 @@ impl QuicTransport {
         transport_config.receive_window(VarInt::try_from(config.connection_window_size).unwrap_or(VarInt::MAX));
         
         // Parallelism Limits
-        transport_config.max_concurrent_uni_streams(VarInt::try_from(config.max_concurrent_streams).unwrap_or(VarInt::from_u32(100)));
-        transport_config.max_concurrent_bidi_streams(VarInt::try_from(config.max_concurrent_streams).unwrap_or(VarInt::from_u32(100)));
+        transport_config.max_concurrent_uni_streams(VarInt::from_u32(config.max_concurrent_streams));
+        transport_config.max_concurrent_bidi_streams(VarInt::from_u32(config.max_concurrent_streams));
 
         transport_config.keep_alive_interval(Some(config.keep_alive_interval));
         transport_config.max_idle_timeout(Some(config.max_idle_timeout.try_into()?));
+
+        // Congestion Control & Pacing
+        match config.congestion {
+            CongestionControl::Cubic => {
+                let mut cc = quinn::congestion::CubicConfig::default();
+                if let Some(window) = config.initial_window { cc.initial_window(window); }
+                transport_config.congestion_controller_factory(Arc::new(cc));
+            }
+            CongestionControl::NewReno => {
+                let mut cc = quinn::congestion::NewRenoConfig::default();
+                if let Some(window) = config.initial_window { cc.initial_window(window); }
+                transport_config.congestion_controller_factory(Arc::new(cc));
+            }
+            CongestionControl::Bbr => {
+                let mut cc = quinn::congestion::BbrConfig::default();
+                if let Some(window) = config.initial_window { cc.initial_window(window); }
+                transport_config.congestion_controller_factory(Arc::new(cc));
+            }
+        }
+        if let Some(rtt) = config.initial_rtt { transport_config.initial_rtt(rtt); }
+        transport_config.enable_segmentation_offload(config.segmentation_offload);
         
         // Optimization: Disable Datagram buffer if not used (Save Memory/CPU)
         transport_config.datagram_receive_buffer_size(None);
 
         let transport_config_arc = Arc::new(transport_config);
 
+        // known_hosts ชุดเดียวกันทั้งตรวจผู้รับ (TofuVerifier) และตรวจผู้ส่ง (TofuClientVerifier)
+        let manager = identity.security_manager(storage_path);
+
         // 2. Setup Server Config
         let mut server_crypto = rustls::ServerConfig::builder()
             .with_safe_defaults()
-            .with_no_client_auth()
-            .with_single_cert(certs, key)?;
+            .with_client_cert_verifier(security::TofuClientVerifier::new(manager.clone()))
+            .with_single_cert(certs.clone(), key.clone())?;
         
         server_crypto.alpn_protocols = PROTOCOL_ALPN.iter().map(|&x| x.to_vec()).collect();
         
         let mut server_config = quinn::ServerConfig::with_crypto(Arc::new(server_crypto));
         server_config.transport_config(transport_config_arc.clone());
+        // 📶 ผู้ส่งเปลี่ยน AP / IP -> Connection เดิมย้ายไป Address ใหม่ได้ (Socket ผูก Wildcard -> ขาออกใช้ Address ใหม่เอง)
+        // ย้ายไม่สำเร็จ (ฝั่งรับเป็นคนย้าย / NAT ใหม่) -> Connection หลุด แล้วผู้ส่งต่อใหม่ด้วย resume.rs
+        server_config.migration(true);
         
         // 3. Setup Client Config
         let mut client_crypto = rustls::ClientConfig::builder()
             .with_safe_defaults()
-            .with_custom_certificate_verifier(security::TofuVerifier::new(
-            security::SecurityManager::new(sec_path) 
-            ))
-            .with_no_client_auth();
+            .with_custom_certificate_verifier(security::TofuVerifier::new(manager))
+            .with_client_auth_cert(certs, key)?;
             
         client_crypto.alpn_protocols = PROTOCOL_ALPN.iter().map(|&x| x.to_vec()).collect();
         
@@ -128,15 +206,49 @@ impl QuicTransport {
 
         // 4. Create Endpoint
         let addr = SocketAddr::from(([0, 0, 0, 0], port));
-        let mut endpoint = Endpoint::server(server_config, addr)?;
+        let socket = std::net::UdpSocket::bind(addr)?;
+        let runtime = quinn::default_runtime().ok_or_else(|| anyhow::anyhow!("No async runtime found"))?;
+        let (mut endpoint, rendezvous) = match config.rendezvous_server {
+            // Rendezvous ต้องใช้ Socket เดียวกับ QUIC เพื่อให้ NAT Mapping ตรงกัน
+            Some(server) => {
+                let (client, filtered) = RendezvousClient::attach(server, node_name, socket, &runtime)?;
+                (Endpoint::new_with_abstract_socket(EndpointConfig::default(), Some(server_config), filtered, runtime)?, Some(client))
+            }
+            None => (Endpoint::new(EndpointConfig::default(), Some(server_config), socket, runtime)?, None),
+        };
         endpoint.set_default_client_config(client_config);
 
+        let (streams_tx, streams) = mpsc::channel(STREAM_BACKLOG);
         Ok(Self { 
             endpoint,
             connections: Arc::new(RwLock::new(HashMap::new())), // ✅ Init RwLock
+            rendezvous,
+            streams_tx,
+            streams: Mutex::new(streams),
         })
     }
 
+    /// Client ของ Rendezvous (None = ไม่ได้ตั้ง rendezvous_server)
+    pub fn rendezvous(&self) -> Option<Arc<RendezvousClient>> { self.rendezvous.clone() }
+
+    // Handshake + รับ Stream ของ Connection นี้ไปเรื่อยๆ จนอีกฝั่งปิด (แยก Task ไม่ให้ Handshake ช้าบล็อก Connection อื่น)
+    fn serve_connection(&self, connecting: quinn::Connecting) {
+        let tx = self.streams_tx.clone();
+        tokio::spawn(async move {
+            let connection = match connecting.await {
+                Ok(connection) => connection,
+                Err(e) => { tracing::debug!("QUIC handshake failed: {}", e); return; }
+            };
+            let addr = connection.remote_address();
+            let fingerprint = connection.peer_identity()
+                .and_then(|id| id.downcast::<Vec<rustls::Certificate>>().ok())
+                .and_then(|certs| certs.first().map(security::fingerprint));
+            while let Ok((send, recv)) = connection.accept_bi().await {
+                if tx.send((QuicDataStream { send, recv }, addr, fingerprint.clone())).await.is_err() { break; }
+            }
+        });
+    }
+
     // ✅ Logic ใหม่: Double-Checked Locking เพื่อลด Blocking I/O
     async fn get_or_connect(&self, addr: SocketAddr) -> anyhow::Result<Connection> {
         // STEP 1: Fast Path (Read Lock) - เช็คเร็วๆ ว่ามีของไหม
@@ -172,31 +284,71 @@ impl QuicTransport {
 
         Ok(connection)
     }
+
+    // Hole Punching: ขอ Address จาก Rendezvous แล้ว Connect พร้อมกับที่อีกฝั่งยิง Punch มา
+    // ไม่ผ่านภายใน PUNCH_CONNECT_TIMEOUT -> ขอ Relay จาก Server
+    async fn connect_via_rendezvous(&self, rendezvous: &Arc<RendezvousClient>, peer_id: &str) -> anyhow::Result<Connection> {
+        let addr = rendezvous.resolve(peer_id).await?;
+        rendezvous.clone().punch(addr);
+        match tokio::time::timeout(PUNCH_CONNECT_TIMEOUT, self.get_or_connect(addr)).await {
+            Ok(Ok(conn)) => {
+                tracing::info!("🕳️ Hole punch to {} ({}) succeeded", peer_id, addr);
+                Ok(conn)
+            }
+            result => {
+                let relay = rendezvous.relay(peer_id).await.map_err(|e| match result {
+                    Ok(Err(punch)) => punch.context(e),
+                    _ => e.context(format!("Hole punch to {} timed out", peer_id)),
+                })?;
+                tracing::warn!("Hole punch to {} failed, falling back to relay {}", peer_id, relay);
+                self.get_or_connect(relay).await
+            }
+        }
+    }
 }
 
 #[async_trait]
 impl Transport for QuicTransport {
     type Stream = Box<dyn DataStream>;
 
-    async fn accept(&self) -> anyhow::Result<(Self::Stream, SocketAddr)> {
-        let connecting = self.endpoint.accept().await.ok_or(anyhow::anyhow!("Endpoint closed"))?;
-        let connection = connecting.await?;
-        let addr = connection.remote_address();
-        
-        let (send, recv) = connection.accept_bi().await?;
-        
-        Ok((Box::new(QuicDataStream { send, recv }), addr))
+    async fn accept(&self) -> error::Result<(Self::Stream, SocketAddr, Option<String>)> {
+        let mut streams = self.streams.lock().await;
+        loop {
+            tokio::select! {
+                // streams_tx อยู่ใน self -> recv ไม่มีทางได้ None
+                Some((stream, addr, fingerprint)) = streams.recv() => return Ok((Box::new(stream), addr, fingerprint)),
+                connecting = self.endpoint.accept() => {
+                    let connecting = connecting.ok_or(anyhow::anyhow!("Endpoint closed"))?;
+                    self.serve_connection(connecting);
+                }
+            }
+        }
     }
 
-    async fn connect(&self, ip: &str, port: u16) -> anyhow::Result<Self::Stream> {
-        let addr: SocketAddr = format!("{}:{}", ip, port).parse()?;
-        
-        // เรียกใช้ Logic ใหม่ (Connection Pooling + Non-blocking)
-        let connection = self.get_or_connect(addr).await?;
+    #[tracing::instrument(level = "debug", skip(self), fields(transport = "quic"))]
+    async fn connect(&self, ip: &str, port: u16) -> error::Result<Self::Stream> {
+        // ไม่ใช่ IP + มี Rendezvous -> ถือว่าเป็น Peer ID
+        let connection = match (format!("{}:{}", ip, port).parse::<SocketAddr>(), &self.rendezvous) {
+            // เรียกใช้ Logic ใหม่ (Connection Pooling + Non-blocking)
+            (Ok(addr), _) => self.get_or_connect(addr).await?,
+            (Err(_), Some(rendezvous)) => self.connect_via_rendezvous(rendezvous, ip).await?,
+            (Err(e), None) => return Err(e.into()),
+        };
         
         // เปิด Stream ใหม่บน Connection เดิม (Multiplexing)
         let (send, recv) = connection.open_bi().await?;
         
         Ok(Box::new(QuicDataStream { send, recv }))
     }
+
+    async fn on_network_changed(&self) {
+        // Path เดิมอาจตายเงียบ (รอ Idle Timeout นาน) -> ถอดออกจาก Pool ให้ connect รอบหน้าต่อใหม่
+        // Stream ที่กำลังส่งอยู่ใช้ Connection เดิมต่อไปได้ถ้าย้าย Path สำเร็จ ไม่งั้นหลุดแล้ว Resume ที่ Connection ใหม่
+        let mut conns = self.connections.write().await;
+        let dropped = conns.len();
+        conns.clear();
+        if dropped > 0 {
+            tracing::info!("🔌 Dropped {} pooled QUIC connection(s) after network change", dropped);
+        }
+    }
 }
\ No newline at end of file
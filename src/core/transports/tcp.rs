@@ -1,5 +1,6 @@
 use crate::core::transfer::{Transport, DataStream};
 use crate::core::security;
+use crate::core::error;
 use tokio::net::{TcpListener, TcpStream};
 use tokio_rustls::{TlsAcceptor, TlsConnector};
 use async_trait::async_trait;
@@ -30,6 +31,7 @@ pub struct TcpTransport {
     listener: TcpListener,
     acceptor: TlsAcceptor,
     connector: TlsConnector,
+    #[allow(dead_code)]
     config: TcpConfig, 
 }
 
@@ -37,15 +39,15 @@ impl TcpTransport {
     pub async fn new(
         port: u16, 
         storage_path: &str, 
-        node_name: &str,
+        identity: &security::Identity,
         config: Option<TcpConfig> // รับ Config
     ) -> anyhow::Result<Self> {
         
         let config = config.unwrap_or_default();
-        let addr = format!("0.0.0.0:{}", port);
-        let listener = TcpListener::bind(&addr).await?;
+        // Dual-Stack: Peer ที่ประกาศแค่ IPv6 ก็ต่อเข้ามาได้
+        let listener = TcpListener::from_std(crate::core::utils::bind_dual_stack(port)?)?;
         
-        let (server_cfg, client_cfg) = security::build_tls_configs(storage_path, node_name)?;
+        let (server_cfg, client_cfg) = security::build_tls_configs(storage_path, identity)?;
         
         Ok(Self {
             listener,
@@ -72,20 +74,30 @@ impl TcpTransport {
 impl Transport for TcpTransport {
     type Stream = Box<dyn DataStream>;
 
-    async fn accept(&self) -> anyhow::Result<(Self::Stream, std::net::SocketAddr)> {
+    async fn accept(&self) -> error::Result<(Self::Stream, std::net::SocketAddr, Option<String>)> {
         let (stream, addr) = self.listener.accept().await?;
+        // Dual-Stack ให้ v4 มาเป็น ::ffff:a.b.c.d -> แปลงกลับให้ตรงกับที่ Discovery เก็บ
+        let addr = std::net::SocketAddr::new(addr.ip().to_canonical(), addr.port());
         
         // 🔥 Apply Tuning ทันทีที่รับ Connection
         if let Err(e) = self.apply_socket_tuning(&stream) {
-            log::warn!("Failed to tune accepted TCP socket: {}", e);
+            tracing::warn!("Failed to tune accepted TCP socket: {}", e);
         }
 
         let tls_stream = self.acceptor.accept(stream).await?;
-        Ok((Box::new(tls_stream), addr))
+        let fingerprint = tls_stream.get_ref().1.peer_certificates()
+            .and_then(|certs| certs.first())
+            .map(security::fingerprint);
+        Ok((Box::new(tls_stream), addr, fingerprint))
     }
 
-    async fn connect(&self, ip: &str, port: u16) -> anyhow::Result<Self::Stream> {
-        let stream = TcpStream::connect((ip, port)).await?;
+    #[tracing::instrument(level = "debug", skip(self), fields(transport = "tcp"))]
+    async fn connect(&self, ip: &str, port: u16) -> error::Result<Self::Stream> {
+        // IP Literal (รวม [v6] และ Link-local แบบ fe80::1%eth0) ต่อตรงพร้อม Scope, นอกนั้นให้ DNS Resolve
+        let stream = match crate::core::utils::parse_scoped_ip(ip) {
+            Some((addr, scope_id)) => TcpStream::connect(crate::core::utils::scoped_socket_addr(addr, port, scope_id)).await?,
+            None => TcpStream::connect((ip, port)).await?,
+        };
         
         // 🔥 Apply Tuning ทันทีที่ Connect ติด
         self.apply_socket_tuning(&stream)?;
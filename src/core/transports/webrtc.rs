@@ -0,0 +1,232 @@
+use crate::core::transfer::{Transport, DynStream, IO_TIMEOUT};
+use crate::core::error::{self, DropTeaError};
+use async_trait::async_trait;
+use anyhow::Context as _;
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use dashmap::DashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::{mpsc, oneshot, Mutex as TokioMutex};
+use tokio::time::{timeout, Sleep};
+use webrtc::api::{APIBuilder, API};
+use webrtc::api::setting_engine::SettingEngine;
+use webrtc::data::data_channel::PollDataChannel;
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::ice_transport::ice_server::RTCIceServer;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+
+// ==========================================
+// WebRTC Data-Channel Transport (ข้าม NAT)
+// Signaling แบบ Copy/Paste: ฝั่งส่ง create_offer -> ฝั่งรับ accept_offer -> ฝั่งส่ง complete_offer
+// จากนั้น connect(session_id, _) จะเปิด Data Channel ใหม่ต่อไฟล์ (เหมือน Stream ของ QUIC)
+// ==========================================
+
+pub const DEFAULT_STUN_SERVERS: &[&str] = &["stun:stun.l.google.com:19302"];
+// Channel แรกมีไว้ให้ SDP มี SCTP เท่านั้น ฝั่งรับไม่นับเป็น Transfer
+const BOOTSTRAP_LABEL: &str = "droptea-bootstrap";
+// SCTP ส่งเป็น Message -> ตัดไม่ให้เกินขนาดที่ Browser/libwebrtc รับได้
+const MAX_MESSAGE_SIZE: usize = 16 * 1024;
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+// Backpressure: PollDataChannel buffer ไม่จำกัด ต้องรอเองเมื่อคิวค้างเยอะ
+const MAX_BUFFERED_AMOUNT: usize = 4 * 1024 * 1024;
+const BACKPRESSURE_POLL: Duration = Duration::from_millis(5);
+
+// --- Data Stream Wrapper ---
+
+pub struct WebRtcDataStream {
+    inner: PollDataChannel,
+    backoff: Option<Pin<Box<Sleep>>>,
+    close_fut: Option<Pin<Box<dyn Future<Output = std::io::Result<()>> + Send>>>,
+    // ถือ PeerConnection ไว้ไม่ให้ถูก Drop ระหว่าง Transfer
+    _pc: Arc<RTCPeerConnection>,
+}
+
+impl WebRtcDataStream {
+    fn new(channel: Arc<webrtc::data::data_channel::DataChannel>, pc: Arc<RTCPeerConnection>) -> Self {
+        let mut inner = PollDataChannel::new(channel);
+        inner.set_read_buf_capacity(READ_BUFFER_SIZE);
+        Self { inner, backoff: None, close_fut: None, _pc: pc }
+    }
+}
+
+impl AsyncRead for WebRtcDataStream {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for WebRtcDataStream {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        loop {
+            if let Some(sleep) = self.backoff.as_mut() {
+                if sleep.as_mut().poll(cx).is_pending() { return Poll::Pending; }
+                self.backoff = None;
+            }
+            if self.inner.buffered_amount() <= MAX_BUFFERED_AMOUNT { break; }
+            self.backoff = Some(Box::pin(tokio::time::sleep(BACKPRESSURE_POLL)));
+        }
+        let len = buf.len().min(MAX_MESSAGE_SIZE);
+        Pin::new(&mut self.inner).poll_write(cx, &buf[..len])
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+    // SCTP ไม่มี Half-close (Shutdown::Write ไม่ถึงอีกฝั่ง) -> รอส่งค้างให้หมดแล้วปิด Channel ทั้งเส้น
+    // อีกฝั่งจะอ่านได้ EOF เหมือน TCP FIN
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        if self.close_fut.is_none() {
+            if Pin::new(&mut self.inner).poll_flush(cx)?.is_pending() { return Poll::Pending; }
+            let channel = self.inner.clone_inner();
+            self.close_fut = Some(Box::pin(async move {
+                while channel.buffered_amount() > 0 {
+                    tokio::time::sleep(BACKPRESSURE_POLL).await;
+                }
+                channel.close().await.map_err(std::io::Error::other)
+            }));
+        }
+        self.close_fut.as_mut().map(|f| f.as_mut().poll(cx)).unwrap_or(Poll::Ready(Ok(())))
+    }
+}
+
+// --- Transport Implementation ---
+
+pub struct WebRtcTransport {
+    api: API,
+    ice_servers: Vec<String>,
+    // session_id -> PeerConnection (ทั้งฝั่งส่งและฝั่งรับ)
+    sessions: Arc<DashMap<String, Arc<RTCPeerConnection>>>,
+    incoming_tx: mpsc::Sender<(DynStream, SocketAddr, Option<String>)>,
+    incoming_rx: TokioMutex<mpsc::Receiver<(DynStream, SocketAddr, Option<String>)>>,
+}
+
+impl WebRtcTransport {
+    pub fn new(ice_servers: Option<Vec<String>>) -> anyhow::Result<Self> {
+        let mut settings = SettingEngine::default();
+        // ใช้ Data Channel แบบ Raw Stream (AsyncRead/AsyncWrite) แทน Callback on_message
+        settings.detach_data_channels();
+        let api = APIBuilder::new().with_setting_engine(settings).build();
+        let (incoming_tx, incoming_rx) = mpsc::channel(32);
+        Ok(Self {
+            api,
+            ice_servers: ice_servers.unwrap_or_else(|| DEFAULT_STUN_SERVERS.iter().map(|s| s.to_string()).collect()),
+            sessions: Arc::new(DashMap::new()),
+            incoming_tx,
+            incoming_rx: TokioMutex::new(incoming_rx),
+        })
+    }
+
+    async fn new_peer_connection(&self, session_id: &str) -> anyhow::Result<Arc<RTCPeerConnection>> {
+        let config = RTCConfiguration {
+            ice_servers: vec![RTCIceServer { urls: self.ice_servers.clone(), ..Default::default() }],
+            ..Default::default()
+        };
+        let pc = Arc::new(self.api.new_peer_connection(config).await?);
+
+        // ลบ Session ทิ้งเมื่อหลุด
+        let sessions = self.sessions.clone();
+        let sid = session_id.to_string();
+        pc.on_peer_connection_state_change(Box::new(move |state| {
+            if matches!(state, RTCPeerConnectionState::Failed | RTCPeerConnectionState::Closed) {
+                tracing::info!("WebRTC session {} {}", sid, state);
+                sessions.remove(&sid);
+            }
+            Box::pin(async {})
+        }));
+        self.sessions.insert(session_id.to_string(), pc.clone());
+        Ok(pc)
+    }
+
+    // ไม่ใช้ Trickle ICE: รอ Gather ครบแล้วค่อยส่ง SDP ก้อนเดียว (Copy/Paste ได้)
+    async fn finish_local_description(pc: &RTCPeerConnection, desc: RTCSessionDescription) -> anyhow::Result<String> {
+        let mut gathered = pc.gathering_complete_promise().await;
+        pc.set_local_description(desc).await?;
+        let _ = timeout(IO_TIMEOUT, gathered.recv()).await;
+        let local = pc.local_description().await.context("No local description")?;
+        Ok(B64.encode(serde_json::to_vec(&local)?))
+    }
+
+    fn decode_description(signal: &str) -> anyhow::Result<RTCSessionDescription> {
+        let json = B64.decode(signal.trim()).context("Invalid signaling string")?;
+        serde_json::from_slice(&json).context("Invalid session description")
+    }
+
+    /// ฝั่งส่ง: สร้าง Offer คืน (session_id, offer) ให้ผู้ใช้ส่งไปอีกฝั่ง
+    pub async fn create_offer(&self) -> anyhow::Result<(String, String)> {
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let pc = self.new_peer_connection(&session_id).await?;
+        pc.create_data_channel(BOOTSTRAP_LABEL, None).await?;
+        let offer = pc.create_offer(None).await?;
+        let signal = Self::finish_local_description(&pc, offer).await?;
+        Ok((session_id, signal))
+    }
+
+    /// ฝั่งรับ: รับ Offer แล้วคืน Answer (Data Channel ที่เปิดเข้ามาจะออกทาง accept())
+    pub async fn accept_offer(&self, offer: &str) -> anyhow::Result<String> {
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let pc = self.new_peer_connection(&session_id).await?;
+
+        let tx = self.incoming_tx.clone();
+        let pc_weak = Arc::downgrade(&pc);
+        pc.on_data_channel(Box::new(move |dc: Arc<RTCDataChannel>| {
+            let tx = tx.clone();
+            let pc_weak = pc_weak.clone();
+            Box::pin(async move {
+                if dc.label() == BOOTSTRAP_LABEL { return; }
+                let dc_open = dc.clone();
+                dc.on_open(Box::new(move || Box::pin(async move {
+                    let (Some(pc), Ok(raw)) = (pc_weak.upgrade(), dc_open.detach().await) else { return };
+                    let stream: DynStream = Box::new(WebRtcDataStream::new(raw, pc));
+                    // WebRTC ไม่มี Address ที่แท้จริงให้ (ผ่าน TURN/NAT) และยังไม่ผูก Cert กับ Identity
+                    let _ = tx.send((stream, SocketAddr::from(([0, 0, 0, 0], 0)), None)).await;
+                })));
+            })
+        }));
+
+        pc.set_remote_description(Self::decode_description(offer)?).await?;
+        let answer = pc.create_answer(None).await?;
+        Self::finish_local_description(&pc, answer).await
+    }
+
+    /// ฝั่งส่ง: ใส่ Answer ที่ได้กลับมา หลังจากนี้ send_file ไปที่ session_id ได้
+    pub async fn complete_offer(&self, session_id: &str, answer: &str) -> anyhow::Result<()> {
+        let pc = self.sessions.get(session_id).map(|p| p.clone())
+            .ok_or_else(|| DropTeaError::Config(format!("Unknown WebRTC session {}", session_id)))?;
+        pc.set_remote_description(Self::decode_description(answer)?).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Transport for WebRtcTransport {
+    type Stream = DynStream;
+
+    async fn accept(&self) -> error::Result<(Self::Stream, SocketAddr, Option<String>)> {
+        match self.incoming_rx.lock().await.recv().await {
+            Some(conn) => Ok(conn),
+            None => Err(DropTeaError::Network("WebRTC transport closed".into())),
+        }
+    }
+
+    // ip = session_id จาก create_offer (port ไม่ใช้)
+    #[tracing::instrument(level = "debug", skip(self, _port), fields(transport = "webrtc"))]
+    async fn connect(&self, session_id: &str, _port: u16) -> error::Result<Self::Stream> {
+        let pc = self.sessions.get(session_id).map(|p| p.clone()).context("Unknown WebRTC session")?;
+        let dc = pc.create_data_channel(&format!("droptea-{}", uuid::Uuid::new_v4()), None).await?;
+
+        let (open_tx, open_rx) = oneshot::channel();
+        dc.on_open(Box::new(move || Box::pin(async move { let _ = open_tx.send(()); })));
+        timeout(IO_TIMEOUT, open_rx).await.context("Data channel open timeout")?
+            .map_err(|_| DropTeaError::Network("Data channel closed before open".into()))?;
+
+        let raw = dc.detach().await?;
+        Ok(Box::new(WebRtcDataStream::new(raw, pc)))
+    }
+}
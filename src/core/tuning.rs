@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex as StdMutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+use serde::{Serialize, Deserialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use anyhow::Context;
+
+use crate::core::compression::{self, CompressionAlgo, Compressor, Decompressor};
+
+// ==========================================
+// Tuning Preset ("Optimize for this machine")
+// วัดความเร็วเขียน Disk และแตก Compression ของเครื่องนี้ แล้วแปลงเป็นค่าแนะนำ
+// สำหรับกรณีหลาย Peer ส่งเข้ามาพร้อมกัน (Fan-in) Frontend เอาไปแสดง/กด Apply ได้
+// ==========================================
+
+const DISK_SAMPLE_SIZE: usize = 64 * 1024 * 1024;
+const DISK_CHUNK_SIZE: usize = 1024 * 1024;
+const CODEC_SAMPLE_SIZE: usize = 8 * 1024 * 1024;
+const BENCH_FILE_NAME: &str = ".droptea-bench.tmp";
+// Transfer หนึ่งตัวบน Wi-Fi ทั่วไปได้ประมาณนี้ -> Disk รับได้กี่ตัวพร้อมกันก่อนจะกลายเป็นคอขวด
+const TYPICAL_LINK_MBPS: f64 = 40.0;
+const MAX_RECOMMENDED_INCOMING: usize = 16;
+pub const DEFAULT_IO_BUFFER_SIZE: usize = 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TuningPreset {
+    pub disk_write_mbps: f64,
+    // MB/s ของข้อมูลหลังแตก แยกตาม Algo
+    pub decompress_mbps: BTreeMap<String, f64>,
+    pub max_incoming: usize,
+    pub io_buffer_size: usize,
+    pub compression: String,
+}
+
+fn mbps(bytes: usize, started: Instant) -> f64 {
+    let secs = started.elapsed().as_secs_f64().max(1e-6);
+    (bytes as f64 / (1024.0 * 1024.0)) / secs
+}
+
+// ครึ่งข้อความซ้ำ ครึ่งสุ่ม ใกล้เคียงไฟล์เอกสาร/โค้ดทั่วไป (ไม่ใช่กรณีดีสุดหรือแย่สุดของ Codec)
+pub fn codec_sample() -> Vec<u8> {
+    use rand::RngCore;
+    let text = b"DropTea benchmark sample: the quick brown fox jumps over the lazy dog. ";
+    let mut sample = Vec::with_capacity(CODEC_SAMPLE_SIZE);
+    let mut noise = [0u8; 4096];
+    let mut rng = rand::thread_rng();
+    while sample.len() < CODEC_SAMPLE_SIZE {
+        for _ in 0..(4096 / text.len()) { sample.extend_from_slice(text); }
+        rng.fill_bytes(&mut noise);
+        sample.extend_from_slice(&noise);
+    }
+    sample.truncate(CODEC_SAMPLE_SIZE);
+    sample
+}
+
+async fn measure_disk(dir: &Path) -> anyhow::Result<f64> {
+    let path = dir.join(BENCH_FILE_NAME);
+    let chunk = vec![0xA5u8; DISK_CHUNK_SIZE];
+    let started = Instant::now();
+    let result = async {
+        let mut file = tokio::fs::File::create(&path).await?;
+        for _ in 0..(DISK_SAMPLE_SIZE / DISK_CHUNK_SIZE) {
+            file.write_all(&chunk).await?;
+        }
+        // ต้องถึง Disk จริง ไม่ใช่แค่ Page Cache
+        file.sync_all().await?;
+        anyhow::Ok(mbps(DISK_SAMPLE_SIZE, started))
+    }.await;
+    let _ = tokio::fs::remove_file(&path).await;
+    result.context("Disk benchmark failed")
+}
+
+async fn measure_decompress(algo: CompressionAlgo, sample: &[u8]) -> anyhow::Result<f64> {
+    let mut encoder = Compressor::new(Vec::new(), algo);
+    encoder.write_all(sample).await?;
+    encoder.shutdown().await?;
+    let compressed = match encoder {
+        Compressor::Zstd(e) => e.into_inner(),
+        Compressor::Lz4(e) => e.into_inner(),
+        Compressor::Gzip(e) => e.into_inner(),
+        Compressor::Zlib(e) => e.into_inner(),
+        Compressor::None(w) => w,
+    };
+
+    let started = Instant::now();
+    let mut decoder = Decompressor::new(compressed.as_slice(), algo);
+    let mut out = Vec::with_capacity(sample.len());
+    decoder.read_to_end(&mut out).await?;
+    Ok(mbps(out.len(), started))
+}
+
+/// วัดจริงบน `save_path` (ใช้เวลาราว 1-3 วินาที เขียนไฟล์ชั่วคราว 64 MB)
+pub async fn benchmark(save_path: &Path) -> anyhow::Result<TuningPreset> {
+    tokio::fs::create_dir_all(save_path).await?;
+    let disk_write_mbps = measure_disk(save_path).await?;
+
+    let sample = codec_sample();
+    let mut decompress_mbps = BTreeMap::new();
+    for algo in compression::SUPPORTED_ALGOS.iter().copied().filter(|a| *a != CompressionAlgo::None) {
+        decompress_mbps.insert(algo.as_str().to_string(), measure_decompress(algo, &sample).await?);
+    }
+
+    Ok(recommend(disk_write_mbps, decompress_mbps))
+}
+
+fn recommend(disk_write_mbps: f64, decompress_mbps: BTreeMap<String, f64>) -> TuningPreset {
+    let max_incoming = ((disk_write_mbps / TYPICAL_LINK_MBPS) as usize).clamp(1, MAX_RECOMMENDED_INCOMING);
+    // Disk เร็ว -> Buffer ใหญ่ลดจำนวน Syscall / Disk ช้า (HDD, SD Card) -> Buffer เล็กกัน RAM บวมตอนหลายไฟล์
+    let io_buffer_size = match disk_write_mbps {
+        s if s >= 500.0 => 4 * 1024 * 1024,
+        s if s >= 100.0 => DEFAULT_IO_BUFFER_SIZE,
+        _ => 256 * 1024,
+    };
+    // Algo ตัวแรกตามลำดับความชอบที่แตกทันทุก Transfer ที่รับพร้อมกัน ไม่งั้นส่งสด
+    let needed = TYPICAL_LINK_MBPS * max_incoming as f64;
+    let compression = compression::SUPPORTED_ALGOS.iter()
+        .find(|a| decompress_mbps.get(a.as_str()).is_some_and(|s| *s >= needed))
+        .unwrap_or(&CompressionAlgo::None)
+        .as_str()
+        .to_string();
+    TuningPreset { disk_write_mbps, decompress_mbps, max_incoming, io_buffer_size, compression }
+}
+
+/// ค่าที่ Apply ได้ตอน Runtime (Handler อ่านทุก Transfer ใหม่)
+#[derive(Debug)]
+pub struct Tunables {
+    io_buffer_size: AtomicUsize,
+    compression: StdMutex<Option<CompressionAlgo>>,
+}
+
+impl Default for Tunables {
+    fn default() -> Self {
+        Self { io_buffer_size: AtomicUsize::new(DEFAULT_IO_BUFFER_SIZE), compression: StdMutex::new(None) }
+    }
+}
+
+impl Tunables {
+    pub fn io_buffer_size(&self) -> usize { self.io_buffer_size.load(Ordering::Relaxed) }
+
+    pub fn compression(&self) -> Option<CompressionAlgo> { *self.compression.lock().unwrap() }
+
+    pub fn apply(&self, preset: &TuningPreset) {
+        self.io_buffer_size.store(preset.io_buffer_size.max(64 * 1024), Ordering::Relaxed);
+        *self.compression.lock().unwrap() = CompressionAlgo::from_name(&preset.compression);
+    }
+}
@@ -2,19 +2,71 @@ use anyhow::Context;
 use fs2::FileExt;
 use std::fs::{self as std_fs, File as StdFile};
 use std::io::{self, BufReader, Read};
+use std::net::{IpAddr, SocketAddr, SocketAddrV6};
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use walkdir::WalkDir;
 use whoami;
 use zip::write::FileOptions;
 use socket2::SockRef; // 🔥 Import socket2
 
+use crate::core::error::{self, DropTeaError};
+
 // --- Constants ---
 pub const ACK_SIZE: usize = 9;
 // ปรับ Buffer Size เป็น 128KB สำหรับการอ่านไฟล์เพื่อ Hash/Compress
 // (ส่วน App buffer สำหรับ Pipeline จะแยกไปแก้ใน handlers.rs)
 const BUFFER_SIZE: usize = 128 * 1024;
 
+// --- Clock ---
+// Monotonic-then-Wallclock: จับคู่ Instant กับเวลาจริงครั้งเดียวตอนเริ่ม แล้วนับต่อด้วย Instant
+// เวลาใน History จึงไม่ย้อนกลับแม้ NTP/ผู้ใช้จะปรับนาฬิการะหว่างทำงาน
+static CLOCK_ANCHOR: OnceLock<(Instant, u64)> = OnceLock::new();
+
+pub fn timestamp_millis() -> u64 {
+    let (instant, wall_ms) = CLOCK_ANCHOR.get_or_init(|| {
+        let wall = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        (Instant::now(), wall.as_millis() as u64)
+    });
+    wall_ms + instant.elapsed().as_millis() as u64
+}
+
+pub fn system_time_millis(t: SystemTime) -> Option<u64> {
+    t.duration_since(UNIX_EPOCH).ok().map(|d| d.as_millis() as u64)
+}
+
+/// Permission bits (rwx ของ owner / group / other) ที่แนบไปกับ FileHeader (ไม่ใช่ Unix = None)
+pub fn permission_bits(metadata: &std::fs::Metadata) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Some(metadata.permissions().mode() & 0o777)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// คืน mtime / Permission ให้ไฟล์ที่รับ (หลังย้ายเข้าที่แล้ว: Copy ข้าม Disk ไม่พา Metadata ไปด้วย)
+pub fn restore_metadata(path: &Path, mtime: Option<SystemTime>, mode: Option<u32>) -> io::Result<()> {
+    // mtime ก่อน เพราะ mode อาจปิดสิทธิ์เขียนไปแล้ว
+    if let Some(mtime) = mtime {
+        StdFile::options().write(true).open(path)?.set_modified(mtime)?;
+    }
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        // ไม่รับ setuid / setgid / sticky และเจ้าของต้องอ่านไฟล์ตัวเองได้เสมอ
+        std_fs::set_permissions(path, std_fs::Permissions::from_mode((mode & 0o777) | 0o400))?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    Ok(())
+}
+
 // --- System Info ---
 pub fn get_system_name() -> String {
     let username = whoami::username();
@@ -29,7 +81,7 @@ pub fn get_system_name() -> String {
 
 // --- 🔧 Network Tuning (ใหม่) ---
 // ฟังก์ชันสำหรับจูน Socket ให้เหมาะกับ Wi-Fi (High Bandwidth, High Jitter)
-pub fn apply_wifi_tuning(stream: &tokio::net::TcpStream) -> anyhow::Result<()> {
+pub fn apply_wifi_tuning(stream: &tokio::net::TcpStream) -> error::Result<()> {
     let socket = SockRef::from(stream);
     
     // 1. ขยาย TCP Buffer (Kernel Level) เป็น 2MB
@@ -44,9 +96,74 @@ pub fn apply_wifi_tuning(stream: &tokio::net::TcpStream) -> anyhow::Result<()> {
     Ok(())
 }
 
+// --- 🌐 IPv6 Addressing ---
+// รับได้ทั้ง "192.168.1.5", "[fe80::1%eth0]", "fe80::1%3" -> (IP, Scope ID)
+// Zone เป็นชื่อ Interface ได้บน Unix (แปลงเป็น Index ด้วย if_nametoindex), Windows ใช้ตัวเลข
+pub fn parse_scoped_ip(host: &str) -> Option<(IpAddr, u32)> {
+    let host = host.trim_matches(|c| c == '[' || c == ']');
+    let (addr, zone) = match host.split_once('%') {
+        Some((addr, zone)) => (addr, Some(zone)),
+        None => (host, None),
+    };
+    let ip = addr.parse::<IpAddr>().ok()?;
+    let scope_id = match zone {
+        Some(zone) if ip.is_ipv6() => zone_to_index(zone)?,
+        Some(_) => return None,
+        None => 0,
+    };
+    Some((ip, scope_id))
+}
+
+fn zone_to_index(zone: &str) -> Option<u32> {
+    if let Ok(index) = zone.parse::<u32>() { return Some(index); }
+    #[cfg(unix)]
+    {
+        let name = std::ffi::CString::new(zone).ok()?;
+        let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+        if index != 0 { return Some(index); }
+    }
+    None
+}
+
+pub fn scoped_socket_addr(ip: IpAddr, port: u16, scope_id: u32) -> SocketAddr {
+    match ip {
+        IpAddr::V6(v6) => SocketAddr::V6(SocketAddrV6::new(v6, port, 0, scope_id)),
+        IpAddr::V4(_) => SocketAddr::new(ip, port),
+    }
+}
+
+// กลับเป็น String สำหรับส่งให้ UI / send_file (Link-local ต้องพก Zone ไปด้วย)
+pub fn format_scoped_ip(ip: IpAddr, scope_id: u32) -> String {
+    if ip.is_ipv6() && scope_id != 0 { format!("{}%{}", ip, scope_id) } else { ip.to_string() }
+}
+
+pub fn is_link_local_v6(ip: &IpAddr) -> bool {
+    matches!(ip, IpAddr::V6(v6) if (v6.segments()[0] & 0xffc0) == 0xfe80)
+}
+
+// Listen แบบ Dual-Stack ([::] รับทั้ง v4/v6) ถ้าเครื่องปิด IPv6 ค่อยถอยไป 0.0.0.0
+pub fn bind_dual_stack(port: u16) -> io::Result<std::net::TcpListener> {
+    use socket2::{Domain, Socket, Type};
+    let bind = |domain: Domain, addr: SocketAddr| -> io::Result<std::net::TcpListener> {
+        let socket = Socket::new(domain, Type::STREAM, None)?;
+        if domain == Domain::IPV6 { socket.set_only_v6(false)?; }
+        #[cfg(unix)]
+        socket.set_reuse_address(true)?;
+        socket.bind(&addr.into())?;
+        socket.listen(1024)?;
+        socket.set_nonblocking(true)?;
+        Ok(socket.into())
+    };
+    bind(Domain::IPV6, SocketAddr::new(IpAddr::from([0u16; 8]), port))
+        .or_else(|e| {
+            log::warn!("IPv6 listen unavailable ({}), falling back to IPv4", e);
+            bind(Domain::IPV4, SocketAddr::new(IpAddr::from([0u8; 4]), port))
+        })
+}
+
 // --- 📦 File Operations ---
 
-pub fn calculate_quick_hash(path: String, limit: Option<u64>) -> anyhow::Result<Vec<u8>> {
+pub fn calculate_quick_hash(path: String, limit: Option<u64>) -> error::Result<Vec<u8>> {
     let f = StdFile::open(&path).context("Failed to open file for hashing")?;
     let mut reader = BufReader::with_capacity(BUFFER_SIZE, f);
     
@@ -71,7 +188,27 @@ pub fn calculate_quick_hash(path: String, limit: Option<u64>) -> anyhow::Result<
     Ok(h.finalize().as_bytes().to_vec())
 }
 
-pub fn compress_folder(folder: String, zip_out: String) -> anyhow::Result<bool> {
+pub fn sha256_bytes(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(Sha256::digest(data))
+}
+
+/// SHA-256 ทั้งไฟล์ (hex) ใช้เทียบกับฐานข้อมูล Reputation ภายนอกที่ไม่รู้จัก blake3
+pub fn sha256_file(path: &Path) -> error::Result<String> {
+    use sha2::{Digest, Sha256};
+    let f = StdFile::open(path).context("Failed to open file for hashing")?;
+    let mut reader = BufReader::with_capacity(BUFFER_SIZE, f);
+    let mut h = Sha256::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 { break; }
+        h.update(&buffer[..n]);
+    }
+    Ok(hex::encode(h.finalize()))
+}
+
+pub fn compress_folder(folder: String, zip_out: String) -> error::Result<bool> {
     let f = StdFile::create(&zip_out).context("Failed to create zip file")?;
     let mut z = zip::ZipWriter::new(f);
     let folder_path = Path::new(&folder);
@@ -79,12 +216,12 @@ pub fn compress_folder(folder: String, zip_out: String) -> anyhow::Result<bool>
     let walk = WalkDir::new(&folder);
 
     for entry in walk {
-        let entry = entry.map_err(|e| anyhow::anyhow!("WalkDir error: {}", e))?;
+        let entry = entry.map_err(|e| DropTeaError::Storage(format!("WalkDir error: {}", e)))?;
         let path = entry.path();
 
         if path.is_dir() { continue; }
 
-        let name = path.strip_prefix(folder_path)?.to_str().unwrap_or("unknown");
+        let name = path.strip_prefix(folder_path).map_err(|e| DropTeaError::Internal(e.to_string()))?.to_str().unwrap_or("unknown");
 
         #[cfg(unix)]
         let options = {
@@ -107,7 +244,7 @@ pub fn compress_folder(folder: String, zip_out: String) -> anyhow::Result<bool>
     Ok(true)
 }
 
-pub fn extract_zip(zip_path: String, extract_to: String) -> anyhow::Result<bool> {
+pub fn extract_zip(zip_path: String, extract_to: String) -> error::Result<bool> {
     let f = StdFile::open(&zip_path)?;
     let mut z = zip::ZipArchive::new(f)?;
 
@@ -141,12 +278,59 @@ pub fn extract_zip(zip_path: String, extract_to: String) -> anyhow::Result<bool>
     Ok(true)
 }
 
-pub fn preallocate_file(path: String, size: u64) -> anyhow::Result<bool> {
+pub fn preallocate_file(path: String, size: u64) -> error::Result<bool> {
     let f = StdFile::create(&path)?;
     f.allocate(size)?;
     Ok(true)
 }
 
+// เช็คพื้นที่ว่างก่อนรับไฟล์ (ถ้าเช็คไม่ได้ให้ผ่านไปก่อน ไม่บล็อกการรับ)
+pub fn has_enough_space(dir: &str, required: u64) -> bool {
+    match fs2::available_space(dir) {
+        Ok(available) => available >= required,
+        Err(e) => {
+            log::warn!("Failed to query free space for '{}': {}", dir, e);
+            true
+        }
+    }
+}
+
+/// ย้ายไฟล์ที่รับครบแล้วไปปลายทาง (ต่าง Filesystem ได้: Copy ไปไฟล์ชั่วคราวข้างปลายทางก่อนแล้ว Rename -> ปลายทางไม่เห็นไฟล์ครึ่งๆ)
+pub async fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    match tokio::fs::rename(from, to).await {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            let mut staging = to.as_os_str().to_owned();
+            staging.push(".moving");
+            let staging = PathBuf::from(staging);
+            let copied = async {
+                tokio::fs::copy(from, &staging).await?;
+                tokio::fs::File::open(&staging).await?.sync_all().await?;
+                tokio::fs::rename(&staging, to).await
+            }.await;
+            if let Err(e) = copied {
+                let _ = tokio::fs::remove_file(&staging).await;
+                return Err(e);
+            }
+            tokio::fs::remove_file(from).await
+        }
+        result => result,
+    }
+}
+
+// ลบไฟล์ .part ที่ไม่ได้ถูกเขียนนานเกิน max_age (Transfer ที่ตายกลางทาง)
+pub fn cleanup_stale_parts(dir: &str, max_age: Duration) -> error::Result<usize> {
+    let mut removed = 0;
+    for entry in std_fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("part") { continue; }
+        let age = std_fs::metadata(&path)?.modified()?.elapsed().unwrap_or_default();
+        if age > max_age && std_fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
 pub fn get_unique_path(dir: &str, raw_filename: &str) -> PathBuf {
     let safe_filename = Path::new(raw_filename)
         .file_name()
@@ -176,9 +360,9 @@ pub fn pack_ack(status: u8, offset: u64) -> Vec<u8> {
     b
 }
 
-pub fn unpack_ack(data: &[u8]) -> anyhow::Result<(u8, u64)> {
+pub fn unpack_ack(data: &[u8]) -> error::Result<(u8, u64)> {
     if data.len() < ACK_SIZE {
-        return Err(anyhow::anyhow!("ACK data too short: expected {}, got {}", ACK_SIZE, data.len()));
+        return Err(DropTeaError::Protocol(format!("ACK data too short: expected {}, got {}", ACK_SIZE, data.len())));
     }
     let mut off_bytes = [0u8; 8];
     off_bytes.copy_from_slice(&data[1..9]);
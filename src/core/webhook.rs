@@ -0,0 +1,66 @@
+use std::time::Duration;
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+
+use crate::core::transfer::FileHeader;
+
+// ==========================================
+// Approval Webhook สำหรับเครื่องรับแบบไม่มีคนเฝ้า (Unattended)
+// POST ข้อมูลคำขอ -> 2xx = รับ (เว้นแต่ Body บอก {"accept": false}), อื่นๆ/Timeout = ปฏิเสธ
+// ==========================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApprovalWebhook {
+    pub url: String,
+    pub timeout: Duration,
+}
+
+#[derive(Serialize)]
+struct ApprovalRequest<'a> {
+    task_id: &'a str,
+    filename: &'a str,
+    filesize: u64,
+    sender_name: &'a str,
+    sender_device: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ApprovalResponse {
+    #[serde(default = "default_accept")]
+    accept: bool,
+}
+
+fn default_accept() -> bool { true }
+
+impl ApprovalWebhook {
+    pub async fn ask(&self, task_id: &str, header: &FileHeader) -> bool {
+        match self.call(task_id, header).await {
+            Ok(accept) => {
+                info!("Webhook decision for '{}': {}", header.filename, if accept { "accept" } else { "reject" });
+                accept
+            }
+            Err(e) => {
+                warn!("Approval webhook failed (rejecting): {}", e);
+                false
+            }
+        }
+    }
+
+    async fn call(&self, task_id: &str, header: &FileHeader) -> anyhow::Result<bool> {
+        let client = reqwest::Client::builder().timeout(self.timeout).build()?;
+        let body = ApprovalRequest {
+            task_id,
+            filename: &header.filename,
+            filesize: header.filesize,
+            sender_name: &header.sender_name,
+            sender_device: &header.sender_device,
+        };
+        let resp = client.post(&self.url).json(&body).send().await?;
+        if !resp.status().is_success() {
+            return Ok(false);
+        }
+        // Body ว่างหรือไม่ใช่ JSON = รับ (ถือตาม Status)
+        let bytes = resp.bytes().await?;
+        Ok(serde_json::from_slice::<ApprovalResponse>(&bytes).map(|r| r.accept).unwrap_or(true))
+    }
+}
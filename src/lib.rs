@@ -1,45 +1,141 @@
+// json! ของ diagnostics::redacted_config ยาวเกิน Default (128)
+#![recursion_limit = "256"]
+
 pub mod core;
 
+#[cfg(feature = "mobile")]
+uniffi::setup_scaffolding!();
+
 #[cfg(feature = "python")]
+#[allow(non_local_definitions)] // pyo3 0.20 macro expansion
 pub mod python_api {
-    use super::*;
     use pyo3::prelude::*;
+    use std::collections::HashMap;
     use std::sync::{Arc, RwLock};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
     use tokio::runtime::Runtime;
     
-    use crate::core::engine::{DropTeaCore, DropTeaConfig, TransportMode};
-    use crate::core::events::TransferEvent; 
+    use crate::core::engine::{DropTeaCore, SendOptions, TransportMode};
+    use crate::core::compression::CompressionAlgo;
+    use crate::core::peer_prefs::PeerOverride;
+    use crate::core::discovery::{ActivityState, Availability};
+    use crate::core::io_priority::IoPriority;
+    use crate::core::admin::AdminCommand;
+    use crate::core::shares::ShareCommand;
+    use crate::core::events::{IncomingRequest, TransferEvent};
     use crate::core::events::TransferEventHandler;
     use crate::core::utils;
     use crate::core::handshake;
     use crate::core::config::AppConfig; 
+    use crate::core::trace::{self, TraceRecord, TraceSink};
+    use crate::core::thumbnail::Thumbnail;
+    use crate::core::transfer::Metadata;
+    use crate::core::error;
+    use crate::core::cancel::REJECT_CANCELLED;
+    use crate::core::loopback_bench;
+    use crate::core::control;
+
+    // Exception ตามหมวดของ DropTeaError (ทุกตัวสืบจาก DropTeaError ซึ่งเป็น RuntimeError -> โค้ดเดิมที่ดัก RuntimeError ยังใช้ได้)
+    pyo3::create_exception!(droptea_core, DropTeaError, pyo3::exceptions::PyRuntimeError);
+    pyo3::create_exception!(droptea_core, NetworkError, DropTeaError);
+    pyo3::create_exception!(droptea_core, TlsError, DropTeaError);
+    pyo3::create_exception!(droptea_core, TimedOutError, DropTeaError);
+    pyo3::create_exception!(droptea_core, RejectedError, DropTeaError);
+    pyo3::create_exception!(droptea_core, StorageError, DropTeaError);
+    pyo3::create_exception!(droptea_core, ProtocolError, DropTeaError);
+    pyo3::create_exception!(droptea_core, CancelledError, DropTeaError);
+    pyo3::create_exception!(droptea_core, ConfigError, DropTeaError);
+
+    fn to_py_err(e: error::DropTeaError) -> PyErr {
+        let msg = e.to_string();
+        match e {
+            error::DropTeaError::Network(_) => NetworkError::new_err(msg),
+            error::DropTeaError::Tls(_) => TlsError::new_err(msg),
+            error::DropTeaError::Timeout(_) => TimedOutError::new_err(msg),
+            error::DropTeaError::Rejected(_) => RejectedError::new_err(msg),
+            error::DropTeaError::Storage(_) => StorageError::new_err(msg),
+            error::DropTeaError::Protocol(_) => ProtocolError::new_err(msg),
+            error::DropTeaError::Cancelled => CancelledError::new_err(msg),
+            error::DropTeaError::Config(_) => ConfigError::new_err(msg),
+            error::DropTeaError::Internal(_) => DropTeaError::new_err(msg),
+        }
+    }
 
     struct PyEventHandler {
         callback: PyObject,
         rt: tokio::runtime::Handle,
+        // true = callback(evt) ได้ Object ของ Event (PeerFoundEvent / ProgressEvent ...) แทน (type, arg1, arg2)
+        typed: bool,
     }
     
     impl TransferEventHandler for PyEventHandler {
         fn on_event(&self, event: TransferEvent) {
             let callback = self.callback.clone();
+            if self.typed {
+                self.rt.spawn(async move {
+                    Python::with_gil(|py| {
+                        if let Err(e) = event_to_py(py, event).and_then(|evt| callback.call1(py, (evt,))) { e.print(py); }
+                    });
+                });
+                return;
+            }
+            // ข้อมูลเสริมไปเป็น Event แยกก่อน Event หลักของ ID เดียวกัน (UI เดิมที่ไม่รู้จักก็ข้ามไป)
+            // INCOMING_THUMBNAIL = ภาพตัวอย่าง (data: URI), PEER_PROFILE = JSON ของ os / device_type / protocol_version / transports / compression
+            let extra = match &event {
+                TransferEvent::Incoming { task_id, thumbnail: Some(t), .. } => Some(("INCOMING_THUMBNAIL", task_id.clone(), t.data_uri())),
+                TransferEvent::PeerFound { id, profile, compression, .. } if !profile.is_empty() || !compression.is_empty() => {
+                    let mut data = serde_json::to_value(profile).unwrap_or_default();
+                    data["compression"] = compression.clone().into();
+                    Some(("PEER_PROFILE", id.clone(), data.to_string()))
+                },
+                _ => None,
+            };
             let (evt_type, arg1, arg2) = match event {
                 TransferEvent::Log { msg, .. } => ("LOG".to_string(), msg, "".to_string()),
                 TransferEvent::ServerStarted { port } => ("SERVER_STARTED".to_string(), port.to_string(), "".to_string()),
                 TransferEvent::Error { task_id, error } => ("ERROR".to_string(), task_id, error),
-                TransferEvent::Incoming { task_id, filename } => ("Incoming".to_string(), task_id, filename),
+                TransferEvent::Incoming { task_id, filename, .. } => ("Incoming".to_string(), task_id, filename),
                 TransferEvent::Started { task_id, msg } => ("START".to_string(), task_id, msg),
-                TransferEvent::Progress { task_id, current, total } => ("PROGRESS".to_string(), task_id, format!("{}|{}", current, total)),
-                TransferEvent::Completed { task_id, info } => ("COMPLETED".to_string(), task_id, info),
+                // current|total|bytes_per_sec|avg_bytes_per_sec|eta_secs (eta ว่าง = ยังประเมินไม่ได้)
+                TransferEvent::Progress { task_id, current, total, bytes_per_sec, avg_bytes_per_sec, eta_secs } => {
+                    let eta = eta_secs.map(|s| s.to_string()).unwrap_or_default();
+                    ("PROGRESS".to_string(), task_id, format!("{}|{}|{}|{}|{}", current, total, bytes_per_sec, avg_bytes_per_sec, eta))
+                },
+                TransferEvent::Completed { task_id, info, .. } => ("COMPLETED".to_string(), task_id, info),
                 TransferEvent::Rejected { task_id, reason } => ("REJECTED".to_string(), task_id, reason),
+                TransferEvent::ClockSkew { task_id, peer, skew_ms } => ("CLOCK_SKEW".to_string(), task_id, format!("{}|{}", peer, skew_ms)),
+                TransferEvent::Stalled { task_id, stalled_ms } => ("STALLED".to_string(), task_id, stalled_ms.to_string()),
+                // filename|existing (existing ว่าง = ฝั่งส่ง)
+                TransferEvent::Duplicate { task_id, filename, existing } => ("DUPLICATE".to_string(), task_id, format!("{}|{}", filename, existing.unwrap_or_default())),
+                TransferEvent::HookFailed { task_id, path, error } => ("HOOK_FAILED".to_string(), task_id, format!("{}|{}", path, error)),
+                TransferEvent::Retrying { task_id, attempt, delay_ms, error } => ("RETRYING".to_string(), task_id, format!("{}|{}|{}", attempt, delay_ms, error)),
+                TransferEvent::BatchProgress { batch_id, files_done, files_total, bytes_done, bytes_total, current } => {
+                    ("BATCH_PROGRESS".to_string(), batch_id, format!("{}|{}|{}|{}|{}", files_done, files_total, bytes_done, bytes_total, current))
+                },
                 TransferEvent::DiscoveryStarted => ("DISCOVERY_STARTED".to_string(), "".to_string(), "".to_string()),
-                TransferEvent::PeerFound { id, name, ip, port, ssid, transport } => {
-                    let data = format!("{}|{}|{}|{}|{}", name, ip, port, ssid.unwrap_or_default(), transport);
+                TransferEvent::PeerFound { id, name, ip, port, ssid, transport, nickname, .. } => {
+                    let mut data = format!("{}|{}|{}|{}|{}", name, ip, port, ssid.unwrap_or_default(), transport);
+                    // Field ที่ 6 (เฉพาะ Peer ที่ตั้งชื่อเล่นไว้) -> UI เดิมที่อ่าน 5 ช่องยังใช้ได้ โน้ตดูจาก get_peers
+                    if let Some(nickname) = nickname { data.push('|'); data.push_str(&nickname); }
                     ("PEER_FOUND".to_string(), id, data)
                 },
                 TransferEvent::PeerLost { id } => ("PEER_LOST".to_string(), id, "".to_string()),
+                TransferEvent::PeerUpdated { id, old_ip, ip, port } => ("PEER_UPDATED".to_string(), id, format!("{}|{}|{}", old_ip, ip, port)),
+                TransferEvent::NetworkChanged { addrs } => ("NETWORK_CHANGED".to_string(), "".to_string(), addrs.join("|")),
+                TransferEvent::ConfigReloaded { changed } => ("CONFIG_RELOADED".to_string(), "".to_string(), changed.join("|")),
+                // rtt_ms|upload_bytes_per_sec|download_bytes_per_sec
+                TransferEvent::SpeedTest { peer, rtt_ms, upload_bytes_per_sec, download_bytes_per_sec } => {
+                    ("SPEED_TEST".to_string(), peer, format!("{:.3}|{}|{}", rtt_ms, upload_bytes_per_sec, download_bytes_per_sec))
+                },
+                // share|path|allowed (share ว่าง = List)
+                TransferEvent::ShareBrowsed { peer, share, path, allowed } => ("SHARE_BROWSED".to_string(), peer, format!("{}|{}|{}", share, path, allowed)),
             };
             self.rt.spawn(async move {
                 Python::with_gil(|py| { 
+                    if let Some(extra) = extra {
+                        if let Err(e) = callback.call1(py, extra) { e.print(py); }
+                    }
                     if let Err(e) = callback.call1(py, (evt_type, arg1, arg2)) { 
                         e.print(py); 
                     } 
@@ -48,10 +144,172 @@ pub mod python_api {
         }
     }
 
+    fn json_to_py<T: serde::Serialize>(py: Python, value: &T) -> PyResult<PyObject> {
+        let json = serde_json::to_string(value).map_err(|e| DropTeaError::new_err(e.to_string()))?;
+        Ok(py.import("json")?.call_method1("loads", (json,))?.into())
+    }
+
+    // Event แบบมี Type: ชื่อ Class = ชื่อ Event ฝั่ง Rust + "Event", Field อ่านเป็น Attribute (evt.task_id, evt.current)
+    // kind = ชื่อ Event ฝั่ง Rust ("Progress", "PeerFound", ...) ใช้แยกชนิดแทน isinstance ได้
+    macro_rules! py_events {
+        ($($name:ident { $($field:ident: $ty:ty),* $(,)? })*) => {$(
+            #[pyclass(get_all, frozen)]
+            struct $name { $($field: $ty),* }
+
+            #[pymethods]
+            impl $name {
+                #[getter]
+                fn kind(&self) -> &'static str {
+                    stringify!($name).trim_end_matches("Event")
+                }
+
+                fn __repr__(&self, py: Python) -> PyResult<String> {
+                    let _ = py;
+                    let fields: Vec<String> = vec![$(format!("{}={}", stringify!($field), self.$field.clone().into_py(py).as_ref(py).repr()?)),*];
+                    Ok(format!("{}({})", stringify!($name), fields.join(", ")))
+                }
+            }
+        )*};
+    }
+
+    py_events! {
+        LogEvent { level: String, msg: String }
+        ServerStartedEvent { port: u16 }
+        ErrorEvent { task_id: String, error: String }
+        // raw = ข้อความ "[[REQUEST]]|..." แบบเดิม, thumbnail = data: URI, metadata = dict ที่ผู้ส่งแนบมา
+        IncomingEvent { task_id: String, filename: String, size: u64, sender: String, device: String, session_id: Option<String>, verdict: Option<String>, thumbnail: Option<String>, metadata: Metadata, raw: String }
+        StartedEvent { task_id: String, msg: String }
+        ProgressEvent { task_id: String, current: u64, total: u64, bytes_per_sec: u64, avg_bytes_per_sec: u64, eta_secs: Option<u64> }
+        // stats = dict (None = ช่องทางนั้นไม่ได้วัด) / metadata = dict ที่แนบกับ Transfer นี้
+        CompletedEvent { task_id: String, info: String, stats: Option<PyObject>, metadata: Metadata }
+        RejectedEvent { task_id: String, reason: String }
+        ClockSkewEvent { task_id: String, peer: String, skew_ms: i64 }
+        RetryingEvent { task_id: String, attempt: u32, delay_ms: u64, error: String }
+        StalledEvent { task_id: String, stalled_ms: u64 }
+        DuplicateEvent { task_id: String, filename: String, existing: Option<String> }
+        HookFailedEvent { task_id: String, path: String, error: String }
+        BatchProgressEvent { batch_id: String, files_done: u64, files_total: u64, bytes_done: u64, bytes_total: u64, current: String }
+        DiscoveryStartedEvent {}
+        // profile = dict ของ os / device_type / protocol_version / transports / availability
+        PeerFoundEvent { id: String, name: String, ip: String, port: u16, ssid: Option<String>, transport: String, nickname: Option<String>, notes: Option<String>, profile: PyObject, compression: Vec<String> }
+        PeerLostEvent { id: String }
+        PeerUpdatedEvent { id: String, old_ip: String, ip: String, port: u16 }
+        NetworkChangedEvent { addrs: Vec<String> }
+        ConfigReloadedEvent { changed: Vec<String> }
+        SpeedTestEvent { peer: String, rtt_ms: f64, upload_bytes_per_sec: u64, download_bytes_per_sec: u64 }
+        ShareBrowsedEvent { peer: String, share: String, path: String, allowed: bool }
+    }
+
+    fn event_to_py(py: Python, event: TransferEvent) -> PyResult<PyObject> {
+        Ok(match event {
+            TransferEvent::Log { level, msg } => LogEvent { level, msg }.into_py(py),
+            TransferEvent::ServerStarted { port } => ServerStartedEvent { port }.into_py(py),
+            TransferEvent::Error { task_id, error } => ErrorEvent { task_id, error }.into_py(py),
+            TransferEvent::Incoming { task_id, filename, thumbnail, request } => {
+                let request = request.unwrap_or_else(|| IncomingRequest { filename: filename.clone(), size: 0, sender: String::new(), device: String::new(), session_id: None, verdict: None, metadata: Metadata::new() });
+                IncomingEvent {
+                    task_id, filename: request.filename, size: request.size, sender: request.sender, device: request.device,
+                    session_id: request.session_id, verdict: request.verdict, thumbnail: thumbnail.map(|t| t.data_uri()), metadata: request.metadata, raw: filename,
+                }.into_py(py)
+            },
+            TransferEvent::Started { task_id, msg } => StartedEvent { task_id, msg }.into_py(py),
+            TransferEvent::Progress { task_id, current, total, bytes_per_sec, avg_bytes_per_sec, eta_secs } => ProgressEvent { task_id, current, total, bytes_per_sec, avg_bytes_per_sec, eta_secs }.into_py(py),
+            TransferEvent::Completed { task_id, info, stats, metadata } => CompletedEvent { task_id, info, stats: stats.map(|s| json_to_py(py, &s)).transpose()?, metadata }.into_py(py),
+            TransferEvent::Rejected { task_id, reason } => RejectedEvent { task_id, reason }.into_py(py),
+            TransferEvent::ClockSkew { task_id, peer, skew_ms } => ClockSkewEvent { task_id, peer, skew_ms }.into_py(py),
+            TransferEvent::Retrying { task_id, attempt, delay_ms, error } => RetryingEvent { task_id, attempt, delay_ms, error }.into_py(py),
+            TransferEvent::Stalled { task_id, stalled_ms } => StalledEvent { task_id, stalled_ms }.into_py(py),
+            TransferEvent::Duplicate { task_id, filename, existing } => DuplicateEvent { task_id, filename, existing }.into_py(py),
+            TransferEvent::HookFailed { task_id, path, error } => HookFailedEvent { task_id, path, error }.into_py(py),
+            TransferEvent::BatchProgress { batch_id, files_done, files_total, bytes_done, bytes_total, current } => BatchProgressEvent { batch_id, files_done, files_total, bytes_done, bytes_total, current }.into_py(py),
+            TransferEvent::DiscoveryStarted => DiscoveryStartedEvent {}.into_py(py),
+            TransferEvent::PeerFound { id, name, ip, port, ssid, transport, nickname, notes, profile, compression } => {
+                PeerFoundEvent { id, name, ip, port, ssid, transport, nickname, notes, profile: json_to_py(py, &profile)?, compression }.into_py(py)
+            },
+            TransferEvent::PeerLost { id } => PeerLostEvent { id }.into_py(py),
+            TransferEvent::PeerUpdated { id, old_ip, ip, port } => PeerUpdatedEvent { id, old_ip, ip, port }.into_py(py),
+            TransferEvent::NetworkChanged { addrs } => NetworkChangedEvent { addrs }.into_py(py),
+            TransferEvent::ConfigReloaded { changed } => ConfigReloadedEvent { changed }.into_py(py),
+            TransferEvent::SpeedTest { peer, rtt_ms, upload_bytes_per_sec, download_bytes_per_sec } => SpeedTestEvent { peer, rtt_ms, upload_bytes_per_sec, download_bytes_per_sec }.into_py(py),
+            TransferEvent::ShareBrowsed { peer, share, path, allowed } => ShareBrowsedEvent { peer, share, path, allowed }.into_py(py),
+        })
+    }
+
+    // async for evt in engine.events(): ... (จบเมื่อ Engine ถูกแทนด้วย start_server ใหม่ / ถูกทิ้ง)
+    #[pyclass]
+    struct EventIterator {
+        rx: Arc<tokio::sync::Mutex<tokio::sync::broadcast::Receiver<TransferEvent>>>,
+    }
+
+    #[pymethods]
+    impl EventIterator {
+        fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+            slf
+        }
+
+        fn __anext__(&self, py: Python) -> PyResult<Option<PyObject>> {
+            let rx = self.rx.clone();
+            let next = pyo3_asyncio::tokio::future_into_py(py, async move {
+                let mut rx = rx.lock().await;
+                loop {
+                    match rx.recv().await {
+                        Ok(event) => return Python::with_gil(|py| event_to_py(py, event)),
+                        // ตามไม่ทัน -> ข้ามไปตัวที่ยังอยู่ในคิว
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => log::warn!("Python event iterator skipped {} events", n),
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => return Err(pyo3::exceptions::PyStopAsyncIteration::new_err(())),
+                    }
+                }
+            })?;
+            Ok(Some(next.into()))
+        }
+    }
+
+    // ส่งต่อ Event ให้ Callback (ถ้ามี) และจับ Event สุดท้ายของ task_id ให้ send_file_async
+    struct ResultHandler {
+        task_id: String,
+        tx: std::sync::Mutex<Option<tokio::sync::oneshot::Sender<TransferEvent>>>,
+        inner: Option<PyEventHandler>,
+    }
+
+    impl TransferEventHandler for ResultHandler {
+        fn on_event(&self, event: TransferEvent) {
+            let done = match &event {
+                TransferEvent::Completed { task_id, .. } | TransferEvent::Error { task_id, .. } | TransferEvent::Rejected { task_id, .. } => *task_id == self.task_id,
+                _ => false,
+            };
+            if done {
+                if let Some(tx) = self.tx.lock().unwrap().take() { let _ = tx.send(event.clone()); }
+            }
+            if let Some(inner) = &self.inner { inner.on_event(event); }
+        }
+    }
+
+    // stop_server / restart รอ Task ของ Engine เดิมหยุดได้นานสุดเท่านี้
+    const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
     #[pyclass]
     struct DropTeaEngine {
         core: Arc<RwLock<Arc<DropTeaCore>>>,
         rt: Arc<Runtime>,
+        typed_events: AtomicBool,
+        // config_path / callback ของ start_server ล่าสุด (restart ใช้ซ้ำ)
+        last_start: std::sync::Mutex<Option<(String, PyObject)>>,
+    }
+
+    impl DropTeaEngine {
+        fn handler(&self, callback: PyObject) -> PyEventHandler {
+            PyEventHandler { callback, rt: self.rt.handle().clone(), typed: self.typed_events.load(Ordering::Relaxed) }
+        }
+
+        // Core เปล่าที่ไม่ได้ Start (ก่อน start_server / หลัง stop_server) Port 0 ไม่ชน Instance อื่น
+        fn idle_core(rt: &Arc<Runtime>) -> PyResult<DropTeaCore> {
+            DropTeaCore::builder()
+                .storage(".")
+                .node_name("init")
+                .runtime(rt.clone())
+                .build()
+                .map_err(to_py_err)
+        }
     }
 
     #[pymethods]
@@ -59,97 +317,555 @@ pub mod python_api {
         #[new]
         fn new() -> PyResult<Self> {
             let rt = Arc::new(Runtime::new().unwrap());
-            struct NoOp; impl TransferEventHandler for NoOp { fn on_event(&self, _: TransferEvent) {} }
-            let config = DropTeaConfig {
-                mode: TransportMode::Tcp,
-                port: 0,
-                storage_path: ".".to_string(),
-                node_name: "init".to_string(),
-                dev_mode: false,
+            let core = Self::idle_core(&rt)?;
+            Ok(DropTeaEngine { core: Arc::new(RwLock::new(Arc::new(core))), rt, typed_events: AtomicBool::new(false), last_start: Default::default() })
+        }
+
+        // with DropTeaEngine() as engine: ... -> stop_server ตอนออกจาก Block (Exception ไม่ถูกกลืน)
+        fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+            slf
+        }
+
+        fn __exit__(&self, py: Python, _exc_type: Option<&PyAny>, _exc: Option<&PyAny>, _tb: Option<&PyAny>) -> PyResult<bool> {
+            self.stop_server(py)?;
+            Ok(false)
+        }
+
+        // หยุด Service และปล่อย Port / Task ทั้งหมด (start_server ใหม่ได้) ไม่ได้ Start อยู่ = ไม่ทำอะไร
+        fn stop_server(&self, py: Python) -> PyResult<()> {
+            let idle = Arc::new(Self::idle_core(&self.rt)?);
+            let old = std::mem::replace(&mut *self.core.write().unwrap(), idle);
+            // Task ที่ถือ Transport อยู่ต้องเห็น Cancel ก่อน Listener ถึงคืน Port (ไม่ถือ GIL ระหว่างรอ)
+            if !py.allow_threads(|| self.rt.block_on(DropTeaCore::close(old, STOP_TIMEOUT))) {
+                log::warn!("Engine still in use after stop_server, port may not be released yet");
+            }
+            Ok(())
+        }
+
+        // stop_server แล้ว start_server ด้วย Callback เดิม (config_path = None ใช้ไฟล์เดิม)
+        #[pyo3(signature = (config_path=None))]
+        fn restart(&self, py: Python, config_path: Option<String>) -> PyResult<()> {
+            let (last_path, callback) = match &*self.last_start.lock().unwrap() {
+                Some((path, callback)) => (path.clone(), callback.clone_ref(py)),
+                None => return Err(ConfigError::new_err("restart() needs a previous start_server()")),
             };
-            let core = DropTeaCore::new_with_config(rt.clone(), config, Box::new(NoOp))
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
-            Ok(DropTeaEngine { core: Arc::new(RwLock::new(Arc::new(core))), rt })
+            self.stop_server(py)?;
+            let typed = self.typed_events.load(Ordering::Relaxed);
+            self.start_server(py, config_path.unwrap_or(last_path), callback, typed)
         }
 
         fn get_my_name(&self) -> String { utils::get_system_name() }
 
-        fn start_server(&self, config_path: String, callback: PyObject) -> PyResult<()> {
-            let py_handler = PyEventHandler { callback, rt: self.rt.handle().clone() };
-            let app_config = AppConfig::load_from_file(&config_path)
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Config Load Failed: {}", e)))?;
+        // config_path ว่าง = ไม่มีไฟล์ ใช้ DROPTEA_* อย่างเดียว (Container / Headless)
+        // typed = True: callback(evt) ได้ Object ของ Event (PeerFoundEvent, ProgressEvent, ...) รวมถึง Callback ของ send_file / send_batch
+        #[pyo3(signature = (config_path, callback, typed=false))]
+        fn start_server(&self, py: Python, config_path: String, callback: PyObject, typed: bool) -> PyResult<()> {
+            self.typed_events.store(typed, Ordering::Relaxed);
+            *self.last_start.lock().unwrap() = Some((config_path.clone(), callback.clone_ref(py)));
+            let py_handler = self.handler(callback);
+            let app_config = match config_path.is_empty() {
+                true => AppConfig::from_env(),
+                false => AppConfig::load_from_file(&config_path),
+            }.map_err(|e| ConfigError::new_err(format!("Config Load Failed: {}", e)))?;
             let engine_config = app_config.to_engine_config();
             let port = engine_config.port;
             let real_core = DropTeaCore::new_with_config(self.rt.clone(), engine_config, Box::new(py_handler))
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+                .map_err(to_py_err)?;
             real_core.start_service(port);
+            // แก้ไฟล์ระหว่างรัน -> CONFIG_RELOADED หรือ ERROR (task_id = "config") ถ้าต้อง Restart
+            if !config_path.is_empty() { real_core.watch_config(&config_path); }
             *self.core.write().unwrap() = Arc::new(real_core);
             Ok(())
         }
         
-        #[pyo3(signature = (ip, port, file_path, task_id, callback, my_device_name=None, target_os=None))]
-        fn send_file(&self, ip: String, port: u16, file_path: String, task_id: String, callback: PyObject, my_device_name: Option<String>, target_os: Option<String>) -> PyResult<()> {
+        // Reload ทันทีไม่ต้องรอ Watcher คืนชื่อค่าที่เปลี่ยน
+        fn reload_config(&self, config_path: String) -> PyResult<Vec<String>> {
+            let app_config = AppConfig::load_from_file(&config_path)
+                .map_err(|e| ConfigError::new_err(format!("Config Load Failed: {}", e)))?;
+            self.core.read().unwrap().reload_config(app_config.to_engine_config()).map_err(to_py_err)
+        }
+
+        // peer = Fingerprint / Peer ID / ชื่อเครื่อง / IP, compression="none" = ไม่บีบ (ไม่ใส่สักค่า = ลบ)
+        #[pyo3(signature = (peer, transport=None, compression=None, max_bytes_per_sec=None, auto_accept=None))]
+        fn set_peer_override(&self, peer: String, transport: Option<String>, compression: Option<String>, max_bytes_per_sec: Option<u64>, auto_accept: Option<bool>) -> PyResult<()> {
+            let unknown = |kind: &str, name: &str| to_py_err(error::DropTeaError::Config(format!("Unknown {} '{}'", kind, name)));
+            let prefs = PeerOverride {
+                transport: transport.map(|t| TransportMode::from_name(&t.to_lowercase()).ok_or_else(|| unknown("transport", &t))).transpose()?,
+                compression: compression.map(|c| CompressionAlgo::from_name(&c.to_lowercase()).ok_or_else(|| unknown("compression", &c))).transpose()?,
+                max_bytes_per_sec,
+                auto_accept,
+            };
+            self.core.read().unwrap().set_peer_override(&peer, prefs).map_err(to_py_err)
+        }
+
+        fn clear_peer_override(&self, peer: String) -> bool {
+            self.core.read().unwrap().clear_peer_override(&peer)
+        }
+
+        // io_priority: "background" = ไม่แย่ง Disk กับผู้ใช้ (None = ใช้ค่าจาก Config)
+        // thumbnail = bytes ของ PNG / JPEG / WebP ที่ย่อไว้แล้ว (แนบเฉพาะไฟล์รูป/วิดีโอ)
+        // metadata = dict[str, str] ของแอป (รวมไม่เกิน 4 KB) ผู้รับได้ใน IncomingEvent / CompletedEvent
+        // target_os: Deprecated ไม่ต้องส่งแล้ว (Engine ใช้ OS ที่ Peer ประกาศใน mDNS) ระบุเฉพาะเมื่อต้องการ Override เช่น "ios" = ส่งดิบ
+        #[pyo3(signature = (ip, port, file_path, task_id, callback, my_device_name=None, target_os=None, compression_level=None, io_priority=None, expires_in_secs=None, thumbnail=None, metadata=None))]
+        #[allow(clippy::too_many_arguments)]
+        fn send_file(&self, ip: String, port: u16, file_path: String, task_id: String, callback: PyObject, my_device_name: Option<String>, target_os: Option<String>, compression_level: Option<i32>, io_priority: Option<String>, expires_in_secs: Option<u64>, thumbnail: Option<Vec<u8>>, metadata: Option<Metadata>) -> PyResult<()> {
+            let thumbnail = thumbnail.as_deref().map(Thumbnail::from_bytes).transpose().map_err(to_py_err)?;
             let core_guard = self.core.read().unwrap();
-            let task_handler = PyEventHandler { callback, rt: self.rt.handle().clone() };
+            let task_handler = self.handler(callback);
             core_guard.send_file(
                 ip, port, file_path, task_id, 
-                my_device_name.unwrap_or_else(|| utils::get_system_name()), 
+                my_device_name.unwrap_or_else(utils::get_system_name), 
                 Box::new(task_handler),
-                target_os
+                target_os,
+                SendOptions {
+                    compression_level,
+                    io_priority: io_priority.as_deref().and_then(IoPriority::from_name),
+                    expires_in: expires_in_secs.map(Duration::from_secs),
+                    batch: None,
+                    thumbnail,
+                    metadata: metadata.unwrap_or_default(),
+                    name: None,
+                },
             );
             Ok(())
         }
 
+        // await ได้: คืน CompletedEvent (info / stats) หรือ Raise RejectedError / CancelledError / DropTeaError
+        // callback (ถ้ามี) ได้ Event ระหว่างทางเหมือน send_file
+        #[pyo3(signature = (ip, port, file_path, task_id, callback=None, my_device_name=None, target_os=None, compression_level=None, io_priority=None, expires_in_secs=None, thumbnail=None, metadata=None))]
+        #[allow(clippy::too_many_arguments)]
+        fn send_file_async<'py>(&self, py: Python<'py>, ip: String, port: u16, file_path: String, task_id: String, callback: Option<PyObject>, my_device_name: Option<String>, target_os: Option<String>, compression_level: Option<i32>, io_priority: Option<String>, expires_in_secs: Option<u64>, thumbnail: Option<Vec<u8>>, metadata: Option<Metadata>) -> PyResult<&'py PyAny> {
+            let thumbnail = thumbnail.as_deref().map(Thumbnail::from_bytes).transpose().map_err(to_py_err)?;
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            let handler = ResultHandler {
+                task_id: task_id.clone(),
+                tx: std::sync::Mutex::new(Some(tx)),
+                inner: callback.map(|callback| self.handler(callback)),
+            };
+            self.core.read().unwrap().send_file(
+                ip, port, file_path, task_id,
+                my_device_name.unwrap_or_else(utils::get_system_name),
+                Box::new(handler),
+                target_os,
+                SendOptions {
+                    compression_level,
+                    io_priority: io_priority.as_deref().and_then(IoPriority::from_name),
+                    expires_in: expires_in_secs.map(Duration::from_secs),
+                    batch: None,
+                    thumbnail,
+                    metadata: metadata.unwrap_or_default(),
+                    name: None,
+                },
+            );
+            pyo3_asyncio::tokio::future_into_py(py, async move {
+                let event = rx.await.map_err(|_| CancelledError::new_err("Transfer ended without a result"))?;
+                match event {
+                    TransferEvent::Rejected { reason, .. } if reason == REJECT_CANCELLED => Err(CancelledError::new_err(reason)),
+                    TransferEvent::Rejected { reason, .. } => Err(RejectedError::new_err(reason)),
+                    TransferEvent::Error { error, .. } => Err(DropTeaError::new_err(error)),
+                    event => Python::with_gil(|py| event_to_py(py, event)),
+                }
+            })
+        }
+
+        // async for evt in engine.events() (เรียกหลัง start_server เห็นเฉพาะ Event หลังจากนี้)
+        fn events(&self) -> EventIterator {
+            EventIterator { rx: Arc::new(tokio::sync::Mutex::new(self.core.read().unwrap().subscribe())) }
+        }
+
+        // files = [(task_id, path), ...] ส่งเป็นชุดเดียว ได้ BATCH_PROGRESS รวมนอกจาก Event รายไฟล์
+        #[pyo3(signature = (ip, port, files, batch_id, callback, my_device_name=None, target_os=None, compression_level=None, io_priority=None, expires_in_secs=None, metadata=None))]
+        #[allow(clippy::too_many_arguments)]
+        fn send_batch(&self, ip: String, port: u16, files: Vec<(String, String)>, batch_id: String, callback: PyObject, my_device_name: Option<String>, target_os: Option<String>, compression_level: Option<i32>, io_priority: Option<String>, expires_in_secs: Option<u64>, metadata: Option<Metadata>) -> PyResult<()> {
+            let core_guard = self.core.read().unwrap();
+            let task_handler = self.handler(callback);
+            core_guard.send_batch(
+                ip, port, files, batch_id,
+                my_device_name.unwrap_or_else(utils::get_system_name),
+                Box::new(task_handler),
+                target_os,
+                SendOptions {
+                    compression_level,
+                    io_priority: io_priority.as_deref().and_then(IoPriority::from_name),
+                    expires_in: expires_in_secs.map(Duration::from_secs),
+                    batch: None,
+                    thumbnail: None,
+                    metadata: metadata.unwrap_or_default(),
+                    name: None,
+                },
+            );
+            Ok(())
+        }
+
+        // ส่งเฉพาะไฟล์ที่ปลายทางยังไม่มี / เปลี่ยนไป ลง <save_path>/<remote_label> ของปลายทาง
+        // รอผู้รับตัดสินใจก่อนคืน [(task_id, path ในโฟลเดอร์)] ที่เข้าคิวส่ง (ว่าง = ตรงกันอยู่แล้ว)
+        #[pyo3(signature = (ip, port, local_dir, remote_label, callback, my_device_name=None, metadata=None))]
+        #[allow(clippy::too_many_arguments)]
+        fn mirror_folder(&self, py: Python, ip: String, port: u16, local_dir: String, remote_label: String, callback: PyObject, my_device_name: Option<String>, metadata: Option<Metadata>) -> PyResult<Vec<(String, String)>> {
+            let task_handler = self.handler(callback);
+            let options = SendOptions { metadata: metadata.unwrap_or_default(), ..SendOptions::default() };
+            py.allow_threads(|| self.core.read().unwrap().mirror_folder(
+                ip, port, &local_dir, &remote_label,
+                my_device_name.unwrap_or_else(utils::get_system_name),
+                Box::new(task_handler),
+                options,
+            )).map_err(to_py_err)
+        }
+
         fn resolve_request(&self, task_id: String, accept: bool) -> PyResult<()> {
             self.core.read().unwrap().resolve_request(task_id, accept);
             Ok(())
         }
-    } 
 
-    #[pyfunction]
-    fn send_handshake(py: Python, mac: String) -> PyResult<&PyAny> {
-        pyo3_asyncio::tokio::future_into_py(py, async move {
-            match handshake::connect_and_say_hello(mac).await { 
-                Ok(_) => Ok(()), 
-                Err(e) => Err(pyo3::exceptions::PyRuntimeError::new_err(format!("{}", e))), 
-            }
-        })
-    }
+        // True = ยกเลิกแล้ว (Transfer จะจบด้วย REJECTED "Cancelled")
+        fn cancel_transfer(&self, task_id: String) -> bool {
+            self.core.read().unwrap().cancel_transfer(&task_id)
+        }
+
+        // หยุดชั่วคราว/ทำต่อ (แจ้ง Peer ผ่าน Control Channel ด้วย) False = ไม่มี Transfer นี้
+        fn pause_transfer(&self, task_id: String) -> bool {
+            self.core.read().unwrap().pause_transfer(&task_id, true)
+        }
+
+        fn resume_transfer(&self, task_id: String) -> bool {
+            self.core.read().unwrap().pause_transfer(&task_id, false)
+        }
+
+        // JSON ของ TransferStats (duration_ms, raw_bytes, wire_bytes, avg/peak_bytes_per_sec, retries, transport, compression)
+        // None = ยังไม่จบ / ไม่สำเร็จ
+        fn get_task_stats(&self, task_id: String) -> PyResult<Option<String>> {
+            let stats = self.core.read().unwrap().task_stats(&task_id);
+            stats.map(|s| serde_json::to_string(&s)).transpose().map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+        }
+
+        // JSON Array: [{key, id, online, name, ip, nickname, notes, first_seen, last_seen}] (id = ใช้กับ send_file ได้, None = ออฟไลน์)
+        fn get_peers(&self) -> PyResult<String> {
+            let peers = self.core.read().unwrap().get_peers();
+            serde_json::to_string(&peers).map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+        }
+
+        // JSON Array: [{path, filename, received, filesize, modified_ms, resumable}] ของ .part ที่ค้างอยู่
+        // resumable = ผู้ส่งเดิมส่งซ้ำแล้วต่อจาก received ได้ (received / filesize = None ถ้าไม่รู้)
+        fn list_incomplete(&self) -> PyResult<String> {
+            let files = self.core.read().unwrap().list_incomplete();
+            serde_json::to_string(&files).map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+        }
+
+        // peer = id จาก PEER_FOUND หรือ key จาก get_peers (None = ลบ)
+        #[pyo3(signature = (peer, nickname=None))]
+        fn set_peer_nickname(&self, peer: String, nickname: Option<String>) -> PyResult<()> {
+            self.core.read().unwrap().set_peer_nickname(&peer, nickname.as_deref()).map_err(to_py_err)
+        }
+
+        #[pyo3(signature = (peer, notes=None))]
+        fn set_peer_notes(&self, peer: String, notes: Option<String>) -> PyResult<()> {
+            self.core.read().unwrap().set_peer_notes(&peer, notes.as_deref()).map_err(to_py_err)
+        }
+
+        fn forget_peer(&self, peer: String) -> bool {
+            self.core.read().unwrap().forget_peer(&peer)
+        }
+
+        fn stop_service(&self) -> PyResult<()> {
+            self.core.read().unwrap().stop_service();
+            Ok(())
+        }
+
+        // command_json เช่น {"cmd": "status"} / {"cmd": "set_limits", "max_incoming": 2} / {"cmd": "cleanup"}
+        fn admin_command(&self, ip: String, port: u16, command_json: String) -> PyResult<String> {
+            let cmd: AdminCommand = serde_json::from_str(&command_json)
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+            let resp = self.core.read().unwrap().send_admin_command(&ip, port, cmd)
+                .map_err(to_py_err)?;
+            serde_json::to_string(&resp).map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+        }
+
+        // command_json เช่น {"cmd": "list"} / {"cmd": "browse", "share": "photos", "path": "2024", "offset": 200}
+        fn share_command(&self, ip: String, port: u16, command_json: String) -> PyResult<String> {
+            let cmd: ShareCommand = serde_json::from_str(&command_json)
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+            let resp = self.core.read().unwrap().send_share_command(&ip, port, cmd)
+                .map_err(to_py_err)?;
+            serde_json::to_string(&resp).map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+        }
+
+        fn pull_shared_file(&self, ip: String, port: u16, share: String, path: String, dest: String) -> PyResult<u64> {
+            self.core.read().unwrap().pull_shared_file(&ip, port, &share, &path, &dest)
+                .map_err(to_py_err)
+        }
+
+        // id จากผล browse (เช่น "slides/2024/deck.pdf") ไฟล์มาเป็น Event ปกติโดยไม่ถาม
+        fn request_shared_file(&self, ip: String, port: u16, id: String) -> PyResult<()> {
+            self.core.read().unwrap().request_shared_file(&ip, port, &id)
+                .map_err(to_py_err)
+        }
+
+        // คืน Code เช่น "42-tiger-lemon" ให้ผู้รับพิมพ์ Event ของการส่งใช้ Task ID "code:<code>"
+        fn offer_code(&self, path: String, ttl_secs: Option<u64>) -> PyResult<String> {
+            self.core.read().unwrap().offer_code(&path, ttl_secs.map(Duration::from_secs))
+                .map_err(to_py_err)
+        }
+
+        fn cancel_code(&self, code: String) -> PyResult<bool> {
+            Ok(self.core.read().unwrap().cancel_code(&code))
+        }
+
+        // คืน Link "http://<ip>:<port>/d/<token>" ให้ Browser โหลดได้ครั้งเดียว (ต้องเปิด [links]) Event ใช้ Task ID "link:<token>"
+        fn create_link(&self, path: String, ttl_secs: Option<u64>) -> PyResult<String> {
+            self.core.read().unwrap().create_link(&path, ttl_secs.map(Duration::from_secs))
+                .map_err(to_py_err)
+        }
+
+        fn revoke_link(&self, link: String) -> PyResult<bool> {
+            Ok(self.core.read().unwrap().revoke_link(&link))
+        }
+
+        // หาผู้ส่งของ Code (LAN ก่อน แล้ว Rendezvous) ไฟล์มาเป็น Event ปกติโดยไม่ถาม
+        fn receive_code(&self, py: Python, code: String) -> PyResult<()> {
+            py.allow_threads(|| self.core.read().unwrap().receive_code(&code))
+                .map_err(to_py_err)
+        }
+
+        // เปิดโฟลเดอร์เป็น Share ตอนรัน (หายเมื่อ Restart ถาวร = [[shares]] ใน Config)
+        fn publish_share(&self, name: String, path: String) -> PyResult<()> {
+            self.core.read().unwrap().publish_share(&name, &path)
+                .map_err(to_py_err)
+        }
+
+        fn unpublish_share(&self, name: String) -> PyResult<bool> {
+            Ok(self.core.read().unwrap().unpublish_share(&name))
+        }
+
+        // [(name, path), ...] เรียงตามชื่อ
+        fn published_shares(&self) -> PyResult<Vec<(String, String)>> {
+            Ok(self.core.read().unwrap().published_shares().into_iter()
+                .map(|s| (s.name, s.path.to_string_lossy().to_string()))
+                .collect())
+        }
+
+        fn grant_share(&self, share: String, fingerprint: String) -> PyResult<()> {
+            self.core.read().unwrap().grant_share(&share, &fingerprint);
+            Ok(())
+        }
+
+        fn revoke_share(&self, share: String, fingerprint: String) -> PyResult<()> {
+            self.core.read().unwrap().revoke_share(&share, &fingerprint);
+            Ok(())
+        }
+
+        fn share_grants(&self, share: String) -> PyResult<Vec<String>> {
+            Ok(self.core.read().unwrap().share_grants(&share))
+        }
+
+        // Whitelist = ชื่อผู้ส่งที่รับไฟล์ได้โดยไม่ถาม
+        fn list_trusted(&self) -> Vec<String> {
+            self.core.read().unwrap().trusted_senders()
+        }
+
+        fn add_trusted(&self, name: String) -> PyResult<()> {
+            self.core.read().unwrap().add_trusted(&name).map_err(to_py_err)
+        }
+
+        fn remove_trusted(&self, name: String) -> bool {
+            self.core.read().unwrap().remove_trusted(&name)
+        }
+
+        // {host: fingerprint} ที่จำไว้ (TOFU)
+        fn list_known_hosts(&self) -> HashMap<String, String> {
+            self.core.read().unwrap().known_hosts()
+        }
+
+        fn forget_host(&self, id: String) -> bool {
+            self.core.read().unwrap().forget_host(&id)
+        }
+
+        // วัดเครื่องนี้ คืน Preset เป็น JSON (ยังไม่ Apply) ให้ Frontend แสดงปุ่ม "Optimize for this machine"
+        fn benchmark(&self) -> PyResult<String> {
+            let preset = self.core.read().unwrap().benchmark()
+                .map_err(to_py_err)?;
+            serde_json::to_string(&preset).map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+        }
+
+        // ส่งข้อมูลสังเคราะห์หาตัวเองทุก Transport x Compression คืน JSON list ของ TransferStats (ไม่ Apply อะไร)
+        #[pyo3(signature = (size=loopback_bench::DEFAULT_BENCH_SIZE, transport=None))]
+        fn benchmark_loopback(&self, py: Python, size: u64, transport: Option<String>) -> PyResult<String> {
+            let transport = transport.map(|t| TransportMode::from_name(&t.to_lowercase())
+                .ok_or_else(|| to_py_err(error::DropTeaError::Config(format!("Unknown transport '{}'", t)))))
+                .transpose()?;
+            let results = py.allow_threads(|| self.core.read().unwrap().benchmark_loopback(size, transport))
+                .map_err(to_py_err)?;
+            serde_json::to_string(&results).map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+        }
+
+        fn apply_tuning(&self, preset_json: String) -> PyResult<()> {
+            let preset = serde_json::from_str(&preset_json)
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+            self.core.read().unwrap().apply_tuning(&preset);
+            Ok(())
+        }
+
+        // WebRTC Signaling: คืน (session_id, offer) (Build ด้วย --features webrtc)
+        #[cfg(feature = "webrtc")]
+        fn webrtc_create_offer(&self) -> PyResult<(String, String)> {
+            self.core.read().unwrap().webrtc_create_offer()
+                .map_err(to_py_err)
+        }
+
+        #[cfg(feature = "webrtc")]
+        fn webrtc_accept_offer(&self, offer: String) -> PyResult<String> {
+            self.core.read().unwrap().webrtc_accept_offer(&offer)
+                .map_err(to_py_err)
+        }
+
+        #[cfg(feature = "webrtc")]
+        fn webrtc_complete_offer(&self, session_id: String, answer: String) -> PyResult<()> {
+            self.core.read().unwrap().webrtc_complete_offer(&session_id, &answer)
+                .map_err(to_py_err)
+        }
+
+        // log_dir: โฟลเดอร์ Log ของฝั่ง Python (logger_config.py เขียนไว้ที่ logs/)
+        #[pyo3(signature = (path, log_dir=None))]
+        fn export_diagnostics(&self, path: String, log_dir: Option<String>) -> PyResult<()> {
+            let log_dirs = vec![std::path::PathBuf::from(log_dir.unwrap_or_else(|| "logs".to_string()))];
+            self.core.read().unwrap().export_diagnostics(&path, &log_dirs)
+                .map_err(to_py_err)
+        }
+
+        // BLE Bootstrap: ส่งข้อมูลการเชื่อมต่อของเรา คืนคำตอบของ Peer เป็น JSON (None = Peer ไม่ตอบ)
+        fn ble_handshake<'py>(&self, py: Python<'py>, mac: String) -> PyResult<&'py PyAny> {
+            let (ours, discovery) = {
+                let core = self.core.read().unwrap();
+                (core.handshake_info(), core.discovery.clone())
+            };
+            pyo3_asyncio::tokio::future_into_py(py, async move {
+                let reply = handshake::exchange_connection_info(&mac, &ours).await
+                    .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+                if let Some(info) = &reply {
+                    discovery.report_handshake(&mac, info).await;
+                }
+                Ok(reply.map(|info| serde_json::to_string(&info).unwrap_or_default()))
+            })
+        }
+
+        fn set_activity_state(&self, active: bool) -> PyResult<()> {
+            let state = if active { ActivityState::Active } else { ActivityState::Idle };
+            self.core.read().unwrap().set_activity_state(state);
+            Ok(())
+        }
+
+        // ประกาศตัวทันทีและ Ping Peer บน LAN (Peer ที่หายไปได้ PEER_LOST)
+        fn refresh_discovery(&self) -> PyResult<()> {
+            self.core.read().unwrap().refresh_discovery();
+            Ok(())
+        }
+
+        // วัด RTT / ความเร็วกับ Peer (ทิศละ seconds) ผลมาทาง Callback: SPEED_TEST (peer_id, "rtt_ms|upload|download")
+        #[pyo3(signature = (peer_id, seconds=control::DEFAULT_SPEED_TEST.as_secs_f64()))]
+        fn speed_test(&self, peer_id: String, seconds: f64) -> PyResult<()> {
+            let duration = Duration::try_from_secs_f64(seconds)
+                .map_err(|e| ConfigError::new_err(format!("invalid duration {}: {}", seconds, e)))?;
+            self.core.read().unwrap().speed_test(&peer_id, duration).map_err(to_py_err)
+        }
+
+        // "available", "busy", "receiving_disabled" (Peer เห็นใน PEER_PROFILE, ส่งมาก็ถูกปฏิเสธด้วยเหตุผลนั้น)
+        fn set_availability(&self, status: String) -> PyResult<()> {
+            let availability = Availability::from_name(&status.to_lowercase())
+                .ok_or_else(|| ConfigError::new_err(format!("unknown availability '{}'", status)))?;
+            self.core.read().unwrap().set_availability(availability);
+            Ok(())
+        }
+
+        fn get_availability(&self) -> String {
+            self.core.read().unwrap().availability().as_str().to_string()
+        }
+    } 
 
     #[pyfunction]
     fn calculate_quick_hash(_py: Python, f: String, l: Option<u64>) -> PyResult<String> {
         utils::calculate_quick_hash(f, l)
-            .map(|v| hex::encode(v))
-            .map_err(|e| pyo3::exceptions::PyIOError::new_err(e.to_string()))
+            .map(hex::encode)
+            .map_err(to_py_err)
     }
 
     #[pyfunction] 
     fn compress_folder(_py: Python, f: String, z: String) -> PyResult<bool> { 
-        utils::compress_folder(f, z).map_err(|e| pyo3::exceptions::PyIOError::new_err(e.to_string())) 
+        utils::compress_folder(f, z).map_err(to_py_err) 
     }
 
     #[pyfunction] 
     fn extract_zip(_py: Python, z: String, e: String) -> PyResult<bool> { 
-        utils::extract_zip(z, e).map_err(|e| pyo3::exceptions::PyIOError::new_err(e.to_string())) 
+        utils::extract_zip(z, e).map_err(to_py_err) 
     }
 
     #[pyfunction] 
     fn preallocate_file(_py: Python, p: String, s: u64) -> PyResult<bool> { 
-        utils::preallocate_file(p, s).map_err(|e| pyo3::exceptions::PyIOError::new_err(e.to_string())) 
+        utils::preallocate_file(p, s).map_err(to_py_err) 
+    }
+
+    struct PyTraceSink { tx: std::sync::mpsc::Sender<String> }
+    impl TraceSink for PyTraceSink {
+        fn record(&self, record: &TraceRecord) {
+            if let Ok(json) = serde_json::to_string(record) { let _ = self.tx.send(json); }
+        }
+    }
+
+    // callback(json) ได้ Trace ของ DropTea พร้อม Span (task_id / peer) ตั้งได้ครั้งเดียว หลังตั้งแล้ว Log ส่วนนี้ไม่ผ่าน logging ของ Python อีก
+    #[pyfunction]
+    #[pyo3(signature = (callback, level=None))]
+    fn set_trace_callback(callback: PyObject, level: Option<String>) -> PyResult<()> {
+        let level = match level.as_deref() {
+            Some(name) => trace::level_from_name(name).ok_or_else(|| to_py_err(error::DropTeaError::Config(format!("Unknown trace level '{}'", name))))?,
+            None => tracing::Level::DEBUG,
+        };
+        let (tx, rx) = std::sync::mpsc::channel::<String>();
+        trace::install(Arc::new(PyTraceSink { tx }), level).map_err(to_py_err)?;
+        // เรียก Python จาก Thread แยก -> Worker ที่เกิด Event ไม่ต้องรอ GIL
+        std::thread::spawn(move || {
+            for json in rx {
+                Python::with_gil(|py| {
+                    if let Err(e) = callback.call1(py, (json,)) { e.print(py); }
+                });
+            }
+        });
+        Ok(())
     }
 
     #[pymodule]
-    fn droptea_core(_py: Python, m: &PyModule) -> PyResult<()> {
+    fn droptea_core(py: Python, m: &PyModule) -> PyResult<()> {
         pyo3_log::init();
         m.add_class::<DropTeaEngine>()?;
+        m.add_class::<EventIterator>()?;
+        for class in [
+            py.get_type::<LogEvent>(), py.get_type::<ServerStartedEvent>(), py.get_type::<ErrorEvent>(), py.get_type::<IncomingEvent>(),
+            py.get_type::<StartedEvent>(), py.get_type::<ProgressEvent>(), py.get_type::<CompletedEvent>(), py.get_type::<RejectedEvent>(),
+            py.get_type::<ClockSkewEvent>(), py.get_type::<RetryingEvent>(), py.get_type::<StalledEvent>(), py.get_type::<DuplicateEvent>(), py.get_type::<HookFailedEvent>(), py.get_type::<BatchProgressEvent>(),
+            py.get_type::<DiscoveryStartedEvent>(), py.get_type::<PeerFoundEvent>(), py.get_type::<PeerLostEvent>(), py.get_type::<PeerUpdatedEvent>(),
+            py.get_type::<NetworkChangedEvent>(), py.get_type::<ConfigReloadedEvent>(), py.get_type::<SpeedTestEvent>(), py.get_type::<ShareBrowsedEvent>(),
+        ] {
+            m.add(class.name()?, class)?;
+        }
+        m.add("DropTeaError", py.get_type::<DropTeaError>())?;
+        m.add("NetworkError", py.get_type::<NetworkError>())?;
+        m.add("TlsError", py.get_type::<TlsError>())?;
+        m.add("TimedOutError", py.get_type::<TimedOutError>())?;
+        m.add("RejectedError", py.get_type::<RejectedError>())?;
+        m.add("StorageError", py.get_type::<StorageError>())?;
+        m.add("ProtocolError", py.get_type::<ProtocolError>())?;
+        m.add("CancelledError", py.get_type::<CancelledError>())?;
+        m.add("ConfigError", py.get_type::<ConfigError>())?;
         m.add_function(wrap_pyfunction!(calculate_quick_hash, m)?)?;
         m.add_function(wrap_pyfunction!(compress_folder, m)?)?;
         m.add_function(wrap_pyfunction!(extract_zip, m)?)?;
         m.add_function(wrap_pyfunction!(preallocate_file, m)?)?;
-        m.add_function(wrap_pyfunction!(send_handshake, m)?)?;
+        m.add_function(wrap_pyfunction!(set_trace_callback, m)?)?;
         Ok(())
     }
 }
 
 #[cfg(feature = "ffi")]
-pub use core::ffi::*;
\ No newline at end of file
+pub use core::ffi::*;
+#[cfg(feature = "node")]
+pub mod node;
+
+#[cfg(feature = "mobile")]
+pub mod mobile;
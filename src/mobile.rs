@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+use crate::core::builder::DropTeaBuilder;
+use crate::core::discovery::PeerProfile;
+use crate::core::engine::{DropTeaCore, SendOptions, TransportMode};
+use crate::core::error::{DropTeaError, Result};
+use crate::core::events::{TransferEvent, TransferEventHandler};
+use crate::core::peer_registry::PeerEntry;
+use crate::core::utils;
+
+// ==========================================
+// Swift / Kotlin Binding (UniFFI) สำหรับแอป iOS / Android
+// let engine = MobileEngine(); try engine.start(config: MobileConfig(...), listener: self)
+// Event ทุกตัว (รวมของ sendFile) มาที่ EventListener.onEvent บน Thread ของ Engine (Dispatch ไป UI Thread เอง)
+// Error = DropTeaError (Swift: enum ที่ throw ได้, Kotlin: DropTeaException.Network / .Config / ...)
+// ==========================================
+
+// เท่ากับ stop_server ของ Python
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// ค่าที่แอปมือถือต้องระบุเอง (ไม่มี config.toml / DROPTEA_* บนมือถือ)
+#[derive(uniffi::Record)]
+pub struct MobileConfig {
+    // โฟลเดอร์ของแอป (iOS: Application Support, Android: filesDir) เก็บ Cert / known_hosts / peers.json
+    // ไฟล์ที่รับยังลง ./downloads ของ Working Directory (DOWNLOAD_DIR ของ Engine) ตั้ง cwd ของโปรเซสให้เขียนได้ก่อน start
+    pub storage_path: String,
+    pub port: u16,
+    // tcp, quic, plaintcp
+    pub mode: String,
+    // None = ชื่อเครื่องจากระบบ
+    pub node_name: Option<String>,
+}
+
+/// TransferEvent แบบ Field ธรรมดา (Thumbnail = bytes ของภาพ, ไม่มี Stats / Profile ทั้งก้อน)
+#[derive(uniffi::Enum)]
+pub enum MobileEvent {
+    Log { level: String, msg: String },
+    ServerStarted { port: u16 },
+    Error { task_id: String, error: String },
+    // request = None เฉพาะ Event ที่มาจาก Engine รุ่นเก่า (ใช้ filename แบบ '|' แทน)
+    Incoming { task_id: String, filename: String, size: Option<u64>, sender: Option<String>, device: Option<String>, session_id: Option<String>, verdict: Option<String>, thumbnail: Option<Vec<u8>>, metadata: HashMap<String, String> },
+    Started { task_id: String, msg: String },
+    Progress { task_id: String, current: u64, total: u64, bytes_per_sec: u64, eta_secs: Option<u64> },
+    Completed { task_id: String, info: String, duration_ms: Option<u64>, avg_bytes_per_sec: Option<u64>, metadata: HashMap<String, String> },
+    Rejected { task_id: String, reason: String },
+    ClockSkew { task_id: String, peer: String, skew_ms: i64 },
+    Retrying { task_id: String, attempt: u32, delay_ms: u64, error: String },
+    Stalled { task_id: String, stalled_ms: u64 },
+    Duplicate { task_id: String, filename: String, existing: Option<String> },
+    HookFailed { task_id: String, path: String, error: String },
+    BatchProgress { batch_id: String, files_done: u64, files_total: u64, bytes_done: u64, bytes_total: u64, current: String },
+    DiscoveryStarted,
+    PeerFound { id: String, name: String, ip: String, port: u16, transport: String, nickname: Option<String>, os: Option<String>, device_type: Option<String> },
+    PeerLost { id: String },
+    PeerUpdated { id: String, old_ip: String, ip: String, port: u16 },
+    NetworkChanged { addrs: Vec<String> },
+    ConfigReloaded { changed: Vec<String> },
+    SpeedTest { peer: String, rtt_ms: f64, upload_bytes_per_sec: u64, download_bytes_per_sec: u64 },
+    ShareBrowsed { peer: String, share: String, path: String, allowed: bool },
+}
+
+impl From<TransferEvent> for MobileEvent {
+    fn from(event: TransferEvent) -> Self {
+        match event {
+            TransferEvent::Log { level, msg } => Self::Log { level, msg },
+            TransferEvent::ServerStarted { port } => Self::ServerStarted { port },
+            TransferEvent::Error { task_id, error } => Self::Error { task_id, error },
+            TransferEvent::Incoming { task_id, filename, thumbnail, request } => Self::Incoming {
+                task_id,
+                filename: request.as_ref().map_or(filename, |r| r.filename.clone()),
+                size: request.as_ref().map(|r| r.size),
+                sender: request.as_ref().map(|r| r.sender.clone()),
+                device: request.as_ref().map(|r| r.device.clone()),
+                session_id: request.as_ref().and_then(|r| r.session_id.clone()),
+                verdict: request.as_ref().and_then(|r| r.verdict.clone()),
+                thumbnail: thumbnail.and_then(|t| t.bytes()),
+                metadata: request.map(|r| r.metadata.into_iter().collect()).unwrap_or_default(),
+            },
+            TransferEvent::Started { task_id, msg } => Self::Started { task_id, msg },
+            TransferEvent::Progress { task_id, current, total, bytes_per_sec, eta_secs, .. } => Self::Progress { task_id, current, total, bytes_per_sec, eta_secs },
+            TransferEvent::Completed { task_id, info, stats, metadata } => Self::Completed {
+                task_id,
+                info,
+                duration_ms: stats.as_ref().map(|s| s.duration_ms),
+                avg_bytes_per_sec: stats.map(|s| s.avg_bytes_per_sec),
+                metadata: metadata.into_iter().collect(),
+            },
+            TransferEvent::Rejected { task_id, reason } => Self::Rejected { task_id, reason },
+            TransferEvent::ClockSkew { task_id, peer, skew_ms } => Self::ClockSkew { task_id, peer, skew_ms },
+            TransferEvent::Retrying { task_id, attempt, delay_ms, error } => Self::Retrying { task_id, attempt, delay_ms, error },
+            TransferEvent::Stalled { task_id, stalled_ms } => Self::Stalled { task_id, stalled_ms },
+            TransferEvent::Duplicate { task_id, filename, existing } => Self::Duplicate { task_id, filename, existing },
+            TransferEvent::HookFailed { task_id, path, error } => Self::HookFailed { task_id, path, error },
+            TransferEvent::BatchProgress { batch_id, files_done, files_total, bytes_done, bytes_total, current } => Self::BatchProgress { batch_id, files_done, files_total, bytes_done, bytes_total, current },
+            TransferEvent::DiscoveryStarted => Self::DiscoveryStarted,
+            TransferEvent::PeerFound { id, name, ip, port, transport, nickname, profile, .. } => {
+                let (os, device_type) = profile_fields(profile);
+                Self::PeerFound { id, name, ip, port, transport, nickname, os, device_type }
+            }
+            TransferEvent::PeerLost { id } => Self::PeerLost { id },
+            TransferEvent::PeerUpdated { id, old_ip, ip, port } => Self::PeerUpdated { id, old_ip, ip, port },
+            TransferEvent::NetworkChanged { addrs } => Self::NetworkChanged { addrs },
+            TransferEvent::ConfigReloaded { changed } => Self::ConfigReloaded { changed },
+            TransferEvent::SpeedTest { peer, rtt_ms, upload_bytes_per_sec, download_bytes_per_sec } => Self::SpeedTest { peer, rtt_ms, upload_bytes_per_sec, download_bytes_per_sec },
+            TransferEvent::ShareBrowsed { peer, share, path, allowed } => Self::ShareBrowsed { peer, share, path, allowed },
+        }
+    }
+}
+
+fn profile_fields(profile: PeerProfile) -> (Option<String>, Option<String>) {
+    (profile.os, profile.device_type.map(|d| d.as_str().to_string()))
+}
+
+/// Peer หนึ่งตัวจาก get_peers (ออนไลน์อยู่ก่อน แล้วเรียงตามเวลาที่เห็นล่าสุด)
+#[derive(uniffi::Record)]
+pub struct MobilePeer {
+    pub key: String,
+    // Discovery ID ตอนนี้ (None = ไม่ได้ออนไลน์)
+    pub id: Option<String>,
+    pub online: bool,
+    pub name: String,
+    pub ip: Option<String>,
+    pub nickname: Option<String>,
+    pub notes: Option<String>,
+    pub os: Option<String>,
+    pub device_type: Option<String>,
+    pub last_seen: u64,
+}
+
+impl From<PeerEntry> for MobilePeer {
+    fn from(entry: PeerEntry) -> Self {
+        let (os, device_type) = profile_fields(entry.profile);
+        Self {
+            key: entry.key,
+            id: entry.id,
+            online: entry.online,
+            name: entry.record.name,
+            ip: entry.record.ip,
+            nickname: entry.record.nickname,
+            notes: entry.record.notes,
+            os,
+            device_type,
+            last_seen: entry.record.last_seen,
+        }
+    }
+}
+
+#[uniffi::export(callback_interface)]
+pub trait EventListener: Send + Sync {
+    fn on_event(&self, event: MobileEvent);
+}
+
+struct ListenerHandler(Arc<dyn EventListener>);
+
+impl TransferEventHandler for ListenerHandler {
+    fn on_event(&self, event: TransferEvent) {
+        self.0.on_event(event.into());
+    }
+}
+
+struct Running {
+    core: Arc<DropTeaCore>,
+    listener: Arc<dyn EventListener>,
+}
+
+#[derive(uniffi::Object)]
+pub struct MobileEngine {
+    rt: Arc<Runtime>,
+    running: RwLock<Option<Running>>,
+}
+
+#[uniffi::export]
+impl MobileEngine {
+    #[uniffi::constructor]
+    pub fn new() -> Result<Arc<Self>> {
+        let rt = Runtime::new().map_err(|e| DropTeaError::Internal(format!("Failed to start runtime: {}", e)))?;
+        Ok(Arc::new(Self { rt: Arc::new(rt), running: RwLock::new(None) }))
+    }
+
+    /// Start ซ้ำ = Stop ตัวเดิมก่อน
+    pub fn start(&self, config: MobileConfig, listener: Box<dyn EventListener>) -> Result<()> {
+        self.stop();
+        let mode = TransportMode::from_name(&config.mode.to_lowercase())
+            .ok_or_else(|| DropTeaError::Config(format!("Unknown transport '{}'", config.mode)))?;
+        let listener: Arc<dyn EventListener> = Arc::from(listener);
+        let mut builder = DropTeaBuilder::new()
+            .storage(config.storage_path)
+            .port(config.port)
+            .transport(mode)
+            .runtime(self.rt.clone())
+            .handler(ListenerHandler(listener.clone()));
+        if let Some(name) = config.node_name { builder = builder.node_name(name); }
+        let core = builder.build()?;
+        core.start_service(config.port);
+        *self.running.write().unwrap() = Some(Running { core: Arc::new(core), listener });
+        Ok(())
+    }
+
+    /// ปล่อย Port / Task ทั้งหมด (เรียกตอนแอปเข้า Background นานๆ ได้) ไม่ได้ Start อยู่ = ไม่ทำอะไร
+    pub fn stop(&self) {
+        let Some(running) = self.running.write().unwrap().take() else { return };
+        if !self.rt.block_on(DropTeaCore::close(running.core, STOP_TIMEOUT)) {
+            log::warn!("Engine still in use after stop, port may not be released yet");
+        }
+    }
+
+    /// Event ของ Transfer นี้ไปที่ Listener ของ start (task_id เดียวกัน)
+    pub fn send_file(&self, ip: String, port: u16, path: String, task_id: String) -> Result<()> {
+        self.send_file_with_metadata(ip, port, path, task_id, HashMap::new())
+    }
+
+    /// เหมือน send_file แต่แนบ key-value ของแอป (ผู้รับได้ใน Incoming / Completed)
+    pub fn send_file_with_metadata(&self, ip: String, port: u16, path: String, task_id: String, metadata: HashMap<String, String>) -> Result<()> {
+        let running = self.running.read().unwrap();
+        let running = running.as_ref().ok_or_else(not_started)?;
+        running.core.send_file(
+            ip, port, path, task_id,
+            utils::get_system_name(),
+            Box::new(ListenerHandler(running.listener.clone())),
+            None,
+            SendOptions { metadata: metadata.into_iter().collect(), ..SendOptions::default() },
+        );
+        Ok(())
+    }
+
+    /// ตอบ Incoming (false = ไม่มีคำขอนี้รออยู่แล้ว)
+    pub fn resolve(&self, task_id: String, accept: bool) -> Result<bool> {
+        let running = self.running.read().unwrap();
+        Ok(running.as_ref().ok_or_else(not_started)?.core.resolve_request(task_id, accept))
+    }
+
+    pub fn cancel(&self, task_id: String) -> Result<bool> {
+        let running = self.running.read().unwrap();
+        Ok(running.as_ref().ok_or_else(not_started)?.core.cancel_transfer(&task_id))
+    }
+
+    pub fn get_peers(&self) -> Result<Vec<MobilePeer>> {
+        let running = self.running.read().unwrap();
+        Ok(running.as_ref().ok_or_else(not_started)?.core.get_peers().into_iter().map(MobilePeer::from).collect())
+    }
+}
+
+fn not_started() -> DropTeaError {
+    DropTeaError::Config("Engine is not started (call start() first)".into())
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi_derive::napi;
+use serde_json::{Map, Value};
+use tokio::runtime::Runtime;
+
+use crate::core::config::AppConfig;
+use crate::core::engine::{DropTeaCore, SendOptions};
+use crate::core::error;
+use crate::core::events::{TransferEvent, TransferEventHandler};
+use crate::core::utils;
+
+// ==========================================
+// Node.js / Electron Binding (napi-rs)
+// const { DropTeaEngine } = require('./droptea.node')
+// engine.start('config.toml', (evt) => ...) -> evt = { kind: 'Progress', task_id, current, total, ... }
+// Event ทุกตัว (รวมของ sendFile) มาที่ Callback เดียวจาก start บน Main Thread ของ Node
+// ==========================================
+
+// เท่ากับ stop_server ของ Python
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+type EventCallback = ThreadsafeFunction<Value, ErrorStrategy::Fatal>;
+
+fn to_js_err(e: error::DropTeaError) -> Error {
+    Error::from_reason(e.to_string())
+}
+
+// { "Progress": { ... } } ของ serde -> { kind: "Progress", ... } (Variant ที่ไม่มี Field = { kind })
+fn event_to_js(event: &TransferEvent) -> Value {
+    let mut object = Map::new();
+    match serde_json::to_value(event) {
+        Ok(Value::String(kind)) => { object.insert("kind".into(), Value::String(kind)); }
+        Ok(Value::Object(tagged)) => {
+            if let Some((kind, fields)) = tagged.into_iter().next() {
+                object.insert("kind".into(), Value::String(kind));
+                if let Value::Object(fields) = fields { object.extend(fields); }
+            }
+        }
+        _ => {}
+    }
+    Value::Object(object)
+}
+
+struct NodeEventHandler {
+    callback: EventCallback,
+}
+
+impl TransferEventHandler for NodeEventHandler {
+    fn on_event(&self, event: TransferEvent) {
+        // NonBlocking: Thread ของ Engine ไม่ต้องรอ JS (คิวไม่จำกัด)
+        self.callback.call(event_to_js(&event), ThreadsafeFunctionCallMode::NonBlocking);
+    }
+}
+
+struct Running {
+    core: Arc<DropTeaCore>,
+    callback: EventCallback,
+}
+
+#[napi]
+pub struct DropTeaEngine {
+    rt: Arc<Runtime>,
+    running: RwLock<Option<Running>>,
+}
+
+#[napi]
+impl DropTeaEngine {
+    #[napi(constructor)]
+    pub fn new() -> Result<Self> {
+        let rt = Runtime::new().map_err(|e| Error::from_reason(format!("Failed to start runtime: {}", e)))?;
+        Ok(Self { rt: Arc::new(rt), running: RwLock::new(None) })
+    }
+
+    /// config_path = null / "" ใช้ DROPTEA_* อย่างเดียว, Start ซ้ำ = Stop ตัวเดิมก่อน
+    #[napi(ts_args_type = "configPath: string | null, callback: (event: { kind: string, [field: string]: any }) => void")]
+    pub fn start(&self, config_path: Option<String>, callback: JsFunction) -> Result<()> {
+        self.stop();
+        let callback: EventCallback = callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<Value>| {
+            ctx.env.to_js_value(&ctx.value).map(|value| vec![value])
+        })?;
+        let config_path = config_path.unwrap_or_default();
+        let app_config = match config_path.is_empty() {
+            true => AppConfig::from_env(),
+            false => AppConfig::load_from_file(&config_path),
+        }.map_err(|e| Error::from_reason(format!("Config Load Failed: {}", e)))?;
+        let engine_config = app_config.to_engine_config();
+        let port = engine_config.port;
+        let handler = NodeEventHandler { callback: callback.clone() };
+        let core = DropTeaCore::new_with_config(self.rt.clone(), engine_config, Box::new(handler)).map_err(to_js_err)?;
+        core.start_service(port);
+        if !config_path.is_empty() { core.watch_config(&config_path); }
+        *self.running.write().unwrap() = Some(Running { core: Arc::new(core), callback });
+        Ok(())
+    }
+
+    /// ปล่อย Port / Task ทั้งหมด (start ใหม่ได้) ไม่ได้ Start อยู่ = ไม่ทำอะไร
+    #[napi]
+    pub fn stop(&self) {
+        let Some(running) = self.running.write().unwrap().take() else { return };
+        if !self.rt.block_on(DropTeaCore::close(running.core, STOP_TIMEOUT)) {
+            log::warn!("Engine still in use after stop, port may not be released yet");
+        }
+    }
+
+    /// Event ของ Transfer นี้ไปที่ Callback ของ start (task_id เดียวกัน)
+    #[napi]
+    /// metadata = { key: value } ของแอป (ผู้รับได้ใน Event incoming / completed)
+    pub fn send_file(&self, ip: String, port: u32, path: String, task_id: String, my_device_name: Option<String>, metadata: Option<HashMap<String, String>>) -> Result<()> {
+        let port = u16::try_from(port).map_err(|_| Error::from_reason(format!("Invalid port {}", port)))?;
+        let running = self.running.read().unwrap();
+        let running = running.as_ref().ok_or_else(not_started)?;
+        running.core.send_file(
+            ip, port, path, task_id,
+            my_device_name.unwrap_or_else(utils::get_system_name),
+            Box::new(NodeEventHandler { callback: running.callback.clone() }),
+            None,
+            SendOptions { metadata: metadata.unwrap_or_default().into_iter().collect(), ..SendOptions::default() },
+        );
+        Ok(())
+    }
+
+    /// ตอบ Event Incoming (false = ไม่มีคำขอนี้รออยู่แล้ว)
+    #[napi]
+    pub fn resolve(&self, task_id: String, accept: bool) -> Result<bool> {
+        let running = self.running.read().unwrap();
+        Ok(running.as_ref().ok_or_else(not_started)?.core.resolve_request(task_id, accept))
+    }
+
+    /// Peer ที่ออนไลน์อยู่และที่เคยเห็น (รูปแบบเดียวกับ get_peers ของ Python)
+    #[napi(ts_return_type = "Array<{ key: string, id?: string, online: boolean, [field: string]: any }>")]
+    pub fn get_peers(&self) -> Result<Value> {
+        let running = self.running.read().unwrap();
+        let peers = running.as_ref().ok_or_else(not_started)?.core.get_peers();
+        serde_json::to_value(peers).map_err(|e| Error::from_reason(e.to_string()))
+    }
+}
+
+fn not_started() -> Error {
+    Error::from_reason("Engine is not started (call start() first)")
+}
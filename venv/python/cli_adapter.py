@@ -114,7 +114,7 @@ class CLITransferUI(TransferEvents):
             'last_bytes': 0
         }
 
-    def on_progress(self, task_id, current, total):
+    def on_progress(self, task_id, current, total, rate=None):
         meta = self.task_meta.get(task_id)
         if meta:
             now = time.time()
@@ -133,7 +133,7 @@ class CLITransferUI(TransferEvents):
             meta['total'] = total
             elapsed = now - meta['start_time']
             # Average for progress bar
-            avg_speed = current / elapsed if elapsed > 0 else 0
+            avg_speed = rate["avg_bps"] if rate else (current / elapsed if elapsed > 0 else 0)
             
             draw_ascii_bar(current, total, meta['filename'], speed_bps=avg_speed, elapsed=elapsed)
 
@@ -4,7 +4,7 @@ class TransferEvents(ABC):
     @abstractmethod
     def on_task_added(self, task_id, filename, side="SEND"): pass
     @abstractmethod
-    def on_progress(self, task_id, current, total): pass
+    def on_progress(self, task_id, current, total, rate=None): pass
     @abstractmethod
     def on_status_change(self, task_id, status, message=""): pass
     @abstractmethod
@@ -4,7 +4,9 @@ import argparse
 import logging
 import os
 import threading
+import time
 import ctypes
+import json
 
 from rich.console import Console
 from rich.panel import Panel
@@ -16,7 +18,7 @@ from prompt_toolkit.history import InMemoryHistory
 from prompt_toolkit.formatted_text import HTML
 from prompt_toolkit.patch_stdout import patch_stdout
 
-from droptea_core import DropTeaEngine, send_handshake
+from droptea_core import DropTeaEngine
 from logger_config import setup_logging
 from cli_adapter import CLITransferUI
 from transfer_manager import AsyncTransferManager
@@ -67,7 +69,14 @@ def get_os_icon(os_name):
     if "linux" in os_name: return "🐧 Linux"
     return "💻 Device"
 
-def print_file_request(console, filename, filesize, sender_name, sender_device):
+VERDICT_LABELS = {
+    'known': "[green]✅ Known file[/]",
+    'unknown': "[yellow]❔ Unknown file[/]",
+    'malicious': "[bold red]☠️ Flagged as malicious[/]",
+    'unavailable': "[dim]Not checked[/]",
+}
+
+def print_file_request(console, filename, filesize, sender_name, sender_device, verdict=None):
     size_str = ""
     if filesize < 1024: size_str = f"{filesize} B"
     elif filesize < 1024**2: size_str = f"{filesize/1024:.1f} KB"
@@ -84,6 +93,8 @@ def print_file_request(console, filename, filesize, sender_name, sender_device):
     grid.add_row("Device:", f"[magenta]{os_icon}[/]")
     grid.add_row("File:", f"{file_icon}  [bold yellow]{filename}[/]")
     grid.add_row("Size:", f"[green]{size_str}[/]")
+    if verdict:
+        grid.add_row("Check:", VERDICT_LABELS.get(verdict, verdict))
 
     console.print(Panel(
         Align.center(grid), 
@@ -101,7 +112,7 @@ class RustEventHandler:
     def __init__(self, receiver, ui, loop):
         self.receiver = receiver; self.ui = ui; self.loop = loop
 
-    def handle_incoming_request(self, task_id, filename, filesize, sender_name, sender_device):
+    def handle_incoming_request(self, task_id, filename, filesize, sender_name, sender_device, verdict=None):
         global user_decision
         
         try: fsize_int = int(filesize)
@@ -112,7 +123,8 @@ class RustEventHandler:
 
         pending_request.update({
             'type': 'file', 'task_id': task_id, 'filename': filename, 
-            'filesize': fsize_int, 'sender_name': sender_name, 'sender_device': sender_device
+            'filesize': fsize_int, 'sender_name': sender_name, 'sender_device': sender_device,
+            'verdict': verdict
         })
         request_event.clear()
         user_decision = False
@@ -155,7 +167,9 @@ class RustEventHandler:
                     content = data.replace("[[REQUEST]]|", "")
                     parts = content.split('|')
                     if len(parts) >= 4:
-                        self.handle_incoming_request(task_id, parts[0], parts[1], parts[2], parts[3])
+                        # parts[4] = session, parts[5] = ผล Reputation (มีเมื่อเปิด [reputation])
+                        verdict = parts[5] if len(parts) > 5 else None
+                        self.handle_incoming_request(task_id, parts[0], parts[1], parts[2], parts[3], verdict)
                         return 
                 except Exception as e:
                     logger.error(f"Failed to parse incoming request: {e}")
@@ -165,6 +179,14 @@ class RustEventHandler:
                      request_event.set()
                      pending_request.clear()
 
+            # ⏳ ข้อเสนอหมดอายุระหว่างรอเรากด -> ปิด Prompt ทิ้ง
+            if event_type == "REJECTED" and data == "Expired":
+                 if pending_request and pending_request.get('task_id') == task_id:
+                     user_decision = False
+                     pending_request.clear()
+                     self.loop.call_soon_threadsafe(ui_cancel_event.set)
+                     request_event.set()
+
             if event_type == "PEER_FOUND":
                 try: 
                     parts = data.split('|')
@@ -177,6 +199,12 @@ class RustEventHandler:
 
             elif event_type == "PEER_LOST":
                 if task_id in active_peers: del active_peers[task_id]
+
+            elif event_type == "PEER_UPDATED":
+                try:
+                    _old_ip, ip, port = data.split('|')
+                    if task_id in active_peers: active_peers[task_id].update({'ip': ip, 'port': int(port)})
+                except: pass
             
             self.receiver._rust_callback(event_type, task_id, data)
 
@@ -207,7 +235,8 @@ async def input_loop(transfer_mgr, ui, engine, config_name):
                         pending_request.get('filename'), 
                         pending_request.get('filesize', 0), 
                         pending_request.get('sender_name', 'Unknown'),
-                        pending_request.get('sender_device', 'Unknown')
+                        pending_request.get('sender_device', 'Unknown'),
+                        pending_request.get('verdict')
                     )
                     msg = "👉 Accept File? (y/n): "
                 
@@ -243,7 +272,12 @@ async def input_loop(transfer_mgr, ui, engine, config_name):
                 target_mac = parts[1]
                 ui.console.print(f"[dim]👉 Connecting to {target_mac}...[/]")
                 try:
-                    await send_handshake(target_mac)
+                    reply = await engine.ble_handshake(target_mac)
+                    if reply:
+                        peer = json.loads(reply)
+                        ui.console.print(f"[green]🤝 {peer.get('name')} @ {peer.get('ip')}:{peer.get('port')}[/]")
+                    else:
+                        ui.console.print("[yellow]⚠️ Peer did not reply (older app?)[/]")
                 except Exception as e:
                     ui.console.print(f"[red]❌ Connection Failed: {e}[/]")
 
@@ -271,6 +305,50 @@ async def input_loop(transfer_mgr, ui, engine, config_name):
                 except Exception as e:
                     ui.console.print(f"[red]❌ Error: {e}[/]")
             
+            elif parts[0] == "diag":
+                out = parts[1] if len(parts) >= 2 else f"droptea_diag_{int(time.time())}.zip"
+                try:
+                    engine.export_diagnostics(out, "logs")
+                    ui.console.print(f"[green]🩺 Diagnostics saved to {out}[/]")
+                except Exception as e:
+                    ui.console.print(f"[red]❌ Export failed: {e}[/]")
+
+            elif parts[0] == "speedtest":
+                # speedtest <index> [seconds] -> ผลมาเป็น Event SPEED_TEST
+                try:
+                    peer_id = list(active_peers.keys())[int(parts[1])]
+                    seconds = float(parts[2]) if len(parts) >= 3 else 3.0
+                    engine.speed_test(peer_id, seconds)
+                    ui.console.print(f"[dim]📶 Testing {active_peers[peer_id]['name']} for {seconds:g}s each way...[/]")
+                except (IndexError, ValueError):
+                    ui.console.print("[yellow]Usage: speedtest <index> [seconds] (check 'list')[/]")
+                except Exception as e:
+                    ui.console.print(f"[red]❌ Speed test failed: {e}[/]")
+
+            elif parts[0] == "bench":
+                # bench [size_mb] [tcp|quic|plaintcp] -> ส่งหาตัวเองทุก Compression แล้วเทียบความเร็ว
+                try:
+                    size_mb = int(parts[1]) if len(parts) >= 2 else 64
+                    transport = parts[2] if len(parts) >= 3 else None
+                    ui.console.print(f"[dim]⏱️ Benchmarking {size_mb} MB over loopback...[/]")
+                    raw = await asyncio.to_thread(engine.benchmark_loopback, size_mb * 1024 * 1024, transport)
+                    for r in sorted(json.loads(raw), key=lambda r: -r['avg_bytes_per_sec']):
+                        ratio = r['wire_bytes'] / max(r['raw_bytes'], 1)
+                        ui.console.print(f"  [bold cyan]{r['transport']:<9}[/] {r['compression']:<5} {r['avg_bytes_per_sec'] / 1048576:8.1f} MB/s  [dim]wire {ratio:.0%}[/]")
+                except ValueError:
+                    ui.console.print("[yellow]Usage: bench [size_mb] [tcp|quic|plaintcp][/]")
+                except Exception as e:
+                    ui.console.print(f"[red]❌ Benchmark failed: {e}[/]")
+
+            elif parts[0] == "incomplete":
+                # .part ที่ค้างอยู่ (resumable = ผู้ส่งเดิมส่งซ้ำแล้วต่อได้)
+                files = json.loads(engine.list_incomplete())
+                if not files: ui.console.print("[dim]No incomplete files[/]")
+                for f in files:
+                    progress = f"{f['received'] / max(f['filesize'], 1):.0%}" if f.get('received') is not None else "?"
+                    tag = "[green]resumable[/]" if f['resumable'] else "[dim]stale[/]"
+                    ui.console.print(f"  {get_file_icon(f['filename'])} {f['filename']} [dim]{progress}[/] {tag}")
+
             elif parts[0] == "exit": break
         except (EOFError, KeyboardInterrupt): break
 
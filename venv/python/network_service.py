@@ -39,9 +39,15 @@ class AsyncReceiver:
                 
                 elif event == "PROGRESS":
                     try:
-                        if isinstance(data, str) and "|" in data: c, t = map(int, data.split("|"))
+                        # current|total|bps|avg_bps|eta (Core คำนวณความเร็ว / ETA มาให้แล้ว)
+                        rate = None
+                        if isinstance(data, str) and "|" in data:
+                            parts = data.split("|")
+                            c, t = int(parts[0]), int(parts[1])
+                            if len(parts) >= 5:
+                                rate = {"bps": int(parts[2]), "avg_bps": int(parts[3]), "eta": int(parts[4]) if parts[4] else None}
                         else: c, t = data
-                        self.events.on_progress(task_id, c, t)
+                        self.events.on_progress(task_id, c, t, rate)
                     except: pass
                 
                 elif event == "COMPLETED": 
@@ -56,10 +62,42 @@ class AsyncReceiver:
                 elif event == "REJECTED":
                     self.events.on_reject(task_id, str(data))
 
+                elif event == "DUPLICATE":
+                    # filename|existing (ฝั่งส่งไม่รู้ Path ของผู้รับ)
+                    filename, _, existing = str(data).partition("|")
+                    self.events.on_reject(task_id, f"Already have {existing or filename}")
+
+                elif event == "HOOK_FAILED":
+                    path, _, error = str(data).partition("|")
+                    logger.warning(f"🪝 Post-receive hook failed for {path}: {error}")
+
+                elif event == "STALLED":
+                    logger.warning(f"🐢 {task_id}: no data for {int(data) / 1000:.0f}s")
+
+                elif event == "RETRYING":
+                    attempt, delay_ms, err_msg = str(data).split("|", 2)
+                    logger.warning(f"🔁 {task_id}: {err_msg}, retry #{attempt} in {int(delay_ms) / 1000:.1f}s")
+
+                elif event == "BATCH_PROGRESS":
+                    files_done, files_total, bytes_done, bytes_total, current = str(data).split("|", 4)
+                    logger.debug(f"📦 Batch {task_id}: {files_done}/{files_total} files, {bytes_done}/{bytes_total} bytes ({current})")
+
+                elif event == "CLOCK_SKEW":
+                    peer, skew_ms = str(data).rsplit("|", 1)
+                    logger.warning(f"⏰ Clock of '{peer}' is off by {int(skew_ms) / 1000:+.0f}s (file times adjusted)")
+
                 elif event == "SERVER_STARTED": 
                     logger.info(data)
                 elif event == "PEER_FOUND": 
                     logger.debug(f"Peer: {data}")
+                elif event == "PEER_UPDATED":
+                    old_ip, ip, port = str(data).split("|")
+                    logger.info(f"🔄 Peer moved: {old_ip} -> {ip}:{port}")
+                elif event == "NETWORK_CHANGED":
+                    logger.info(f"📶 Network changed, now on: {str(data).replace('|', ', ')}")
+                elif event == "SPEED_TEST":
+                    rtt_ms, up, down = str(data).split("|")
+                    logger.info(f"📶 Speed test {task_id}: RTT {float(rtt_ms):.1f} ms, ⬆ {int(up) / 1048576:.1f} MB/s, ⬇ {int(down) / 1048576:.1f} MB/s")
 
             except Exception as e: logger.error(f"Callback error: {e}")
 
@@ -14,7 +14,7 @@ class TransferTask:
     file_path: str = field(compare=False)
     peer_ip: str = field(compare=False); peer_port: int = field(compare=False)
     task_id: str = field(compare=False)
-    # 🔥 1. เพิ่ม field นี้เพื่อระบุ OS ปลายทาง
+    # Override OS ปลายทาง (None = Rust ใช้ที่ Peer ประกาศใน mDNS)
     target_os: Optional[str] = field(default=None, compare=False)
 
 class AsyncTransferManager:
@@ -47,12 +47,16 @@ class AsyncTransferManager:
             
             elif event == "PROGRESS":
                 try:
+                    rate = None
                     if isinstance(data, str) and "|" in data:
-                        c, t = map(int, data.split("|"))
+                        parts = data.split("|")
+                        c, t = int(parts[0]), int(parts[1])
+                        if len(parts) >= 5:
+                            rate = {"bps": int(parts[2]), "avg_bps": int(parts[3]), "eta": int(parts[4]) if parts[4] else None}
                     elif isinstance(data, (list, tuple)):
-                        c, t = data
+                        c, t = data[0], data[1]
                     else: return
-                    self.events.on_progress(task_id, c, t)
+                    self.events.on_progress(task_id, c, t, rate)
                 except: pass
             
             elif event == "COMPLETED":
@@ -76,22 +80,17 @@ class AsyncTransferManager:
 
         task_id = os.path.basename(file_path)
         
-        # 🔥 2. เพิ่ม Logic ตรวจสอบ OS จากชื่อเครื่อง (Heuristic)
-        detected_os = None
+        # OS ของปลายทาง Rust รู้เองจาก mDNS (TXT "os") ไม่ต้องเดาจากชื่อเครื่องแล้ว
         if isinstance(peer_info, dict): 
             ip, port = peer_info['ip'], peer_info['port']
-            p_name = peer_info.get('name', '').lower()
-            
-            # ถ้าชื่อเครื่องมีคำว่า iphone หรือ ipad ให้ถือว่าเป็น iOS
-            if "iphone" in p_name or "ipad" in p_name:
-                detected_os = "ios"
-            elif "mac" in p_name:
-                detected_os = "macos"
         else: 
             ip, port = peer_info
 
-        # สร้าง Task โดยระบุ target_os ไปด้วย
-        task = TransferTask(10, file_path, ip, port, task_id, target_os=detected_os)
+        # Peer ที่เห็นแค่ทาง BLE ยังไม่มี IP -> ส่ง Peer ID ไปแทน (Rust จะรอจนเจอบน LAN)
+        if not ip:
+            ip = peer_name
+
+        task = TransferTask(10, file_path, ip, port, task_id)
         await self.queue.put(task)
         self.active_tasks[task_id] = task
         
@@ -104,8 +103,7 @@ class AsyncTransferManager:
         while self._running:
             task = await self.queue.get()
             
-            # 🔥 3. ส่ง task.target_os ไปให้ Rust
-            # (ถ้าเป็น "ios" Rust จะรู้ทันทีว่าต้องส่งแบบ Raw)
+            # task.target_os = Override (None = ให้ Rust ใช้ OS ที่ Peer ประกาศ)
             self.engine.send_file(
                 task.peer_ip, 
                 task.peer_port, 